@@ -0,0 +1,43 @@
+//! Throughput of the TCP framings for large tool results.
+//!
+//! Run with `cargo bench --bench framing`.
+
+use std::io::Cursor;
+
+use mcp_common::framing::{read_frame_sync, write_frame_sync, Framing};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use serde_json::json;
+
+// A tools/call response carrying `size` bytes of text, like a large log or file read.
+fn large_result(size: usize) -> Vec<u8> {
+    let text: String = "line of tool output\n".chars().cycle().take(size).collect();
+    serde_json::to_vec(&json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "result": { "content": [{ "type": "text", "text": text }] }
+    }))
+    .unwrap()
+}
+
+fn bench_framing(c: &mut Criterion) {
+    let mut group = c.benchmark_group("framing_roundtrip");
+    for size in [64 * 1024, 1024 * 1024, 16 * 1024 * 1024] {
+        let body = large_result(size);
+        group.throughput(Throughput::Bytes(body.len() as u64));
+        for framing in [Framing::Newline, Framing::ContentLength] {
+            group.bench_with_input(BenchmarkId::new(format!("{:?}", framing), size), &body, |b, body| {
+                let mut wire = Vec::with_capacity(body.len() + 64);
+                b.iter(|| {
+                    wire.clear();
+                    write_frame_sync(&mut wire, framing, body).unwrap();
+                    let mut reader = Cursor::new(&wire);
+                    read_frame_sync(&mut reader, framing).unwrap().unwrap()
+                });
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_framing);
+criterion_main!(benches);
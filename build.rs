@@ -0,0 +1,5 @@
+fn main() {
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/mcp_transport.proto")
+        .expect("Failed to compile proto/mcp_transport.proto");
+}
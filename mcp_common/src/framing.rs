@@ -0,0 +1,552 @@
+//! Wire framing for the TCP transport.
+//!
+//! The default framing is one JSON message per line. Clients may instead send
+//! LSP-style `Content-Length` headers, which survive messages with embedded
+//! newlines and let the reader allocate large payloads up front. The server
+//! picks the framing from the first byte a client sends, so there is no extra
+//! round trip and existing newline clients keep working unchanged.
+//!
+//! Either way, clients may send JSON-RPC batches: [`bridge`] takes them
+//! apart for the MCP service, which only handles single messages.
+//! [`bridge_with`] can also answer methods outside MCP, such as `health`,
+//! before they reach the service.
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Write};
+use std::sync::{Arc, Mutex};
+
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream};
+use tokio::sync::mpsc;
+
+/// Largest frame accepted, guarding against bogus `Content-Length` headers.
+pub const MAX_FRAME_LEN: usize = 64 * 1024 * 1024;
+
+const BRIDGE_BUFFER: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// One compact JSON message per line.
+    #[default]
+    Newline,
+    /// `Content-Length: N\r\n\r\n` followed by N bytes of JSON.
+    ContentLength,
+}
+
+impl std::str::FromStr for Framing {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "newline" | "ndjson" => Ok(Framing::Newline),
+            "content-length" | "length" => Ok(Framing::ContentLength),
+            other => Err(anyhow::anyhow!(
+                "Unknown framing '{}'. Expected newline or content-length",
+                other
+            )),
+        }
+    }
+}
+
+/// Guesses the client's framing from the first byte it sent, without consuming it.
+pub async fn detect(stream: &tokio::net::TcpStream) -> io::Result<Framing> {
+    let mut first = [0u8; 1];
+    let read = stream.peek(&mut first).await?;
+    Ok(classify(&first[..read]))
+}
+
+/// Like [`detect`], for streams that can't peek (such as TLS): the first
+/// bytes are buffered in `reader` and left there for the session to read.
+pub async fn detect_buffered<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Framing> {
+    Ok(classify(reader.fill_buf().await?))
+}
+
+fn classify(first: &[u8]) -> Framing {
+    match first.first() {
+        Some(b'C' | b'c') => Framing::ContentLength,
+        _ => Framing::Newline,
+    }
+}
+
+fn parse_content_length(header: &str, content_length: &mut Option<usize>) -> io::Result<()> {
+    if let Some((name, value)) = header.split_once(':') {
+        if name.trim().eq_ignore_ascii_case("content-length") {
+            let len: usize = value
+                .trim()
+                .parse()
+                .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Invalid Content-Length header"))?;
+            if len > MAX_FRAME_LEN {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Frame of {} bytes exceeds the {} byte limit", len, MAX_FRAME_LEN),
+                ));
+            }
+            *content_length = Some(len);
+        }
+    }
+    Ok(())
+}
+
+/// Writes one message. `body` must be compact JSON for newline framing.
+pub fn write_frame_sync(writer: &mut impl Write, framing: Framing, body: &[u8]) -> io::Result<()> {
+    match framing {
+        Framing::Newline => {
+            writer.write_all(body)?;
+            writer.write_all(b"\n")?;
+        }
+        Framing::ContentLength => {
+            write!(writer, "Content-Length: {}\r\n\r\n", body.len())?;
+            writer.write_all(body)?;
+        }
+    }
+    writer.flush()
+}
+
+/// Reads one message body, or `None` at end of stream.
+pub fn read_frame_sync(reader: &mut impl BufRead, framing: Framing) -> io::Result<Option<Vec<u8>>> {
+    let mut line = String::new();
+    match framing {
+        Framing::Newline => loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 {
+                return Ok(None);
+            }
+            if !line.trim().is_empty() {
+                return Ok(Some(line.trim_end().as_bytes().to_vec()));
+            }
+        },
+        Framing::ContentLength => {
+            let mut content_length = None;
+            loop {
+                line.clear();
+                if reader.read_line(&mut line)? == 0 {
+                    return Ok(None);
+                }
+                let header = line.trim_end();
+                if header.is_empty() {
+                    if content_length.is_some() {
+                        break;
+                    }
+                    continue;
+                }
+                parse_content_length(header, &mut content_length)?;
+            }
+            let mut body = vec![0; content_length.unwrap_or_default()];
+            reader.read_exact(&mut body)?;
+            Ok(Some(body))
+        }
+    }
+}
+
+/// Async counterpart of `write_frame_sync`.
+pub async fn write_frame<W: AsyncWrite + Unpin>(writer: &mut W, framing: Framing, body: &[u8]) -> io::Result<()> {
+    match framing {
+        Framing::Newline => {
+            writer.write_all(body).await?;
+            writer.write_all(b"\n").await?;
+        }
+        Framing::ContentLength => {
+            writer.write_all(format!("Content-Length: {}\r\n\r\n", body.len()).as_bytes()).await?;
+            writer.write_all(body).await?;
+        }
+    }
+    writer.flush().await
+}
+
+/// Async counterpart of `read_frame_sync`.
+pub async fn read_frame<R: AsyncBufRead + Unpin>(reader: &mut R, framing: Framing) -> io::Result<Option<Vec<u8>>> {
+    match framing {
+        Framing::Newline => {
+            let mut line = String::new();
+            loop {
+                line.clear();
+                if reader.read_line(&mut line).await? == 0 {
+                    return Ok(None);
+                }
+                if !line.trim().is_empty() {
+                    return Ok(Some(line.trim_end().as_bytes().to_vec()));
+                }
+            }
+        }
+        Framing::ContentLength => read_content_length_frame(reader).await,
+    }
+}
+
+async fn read_content_length_frame<R: AsyncBufRead + Unpin>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut line = String::new();
+    let mut content_length = None;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).await? == 0 {
+            return Ok(None);
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            continue;
+        }
+        parse_content_length(header, &mut content_length)?;
+    }
+    let mut body = vec![0; content_length.unwrap_or_default()];
+    reader.read_exact(&mut body).await?;
+    Ok(Some(body))
+}
+
+/// Answers requests in the bridge itself: given a method and its params,
+/// returns the result, or `None` to pass the request on to the service.
+pub type LocalMethods = Arc<dyn Fn(&str, &Value) -> Option<Value> + Send + Sync>;
+
+/// Adapts a connection in `framing` to the newline-delimited stream the MCP
+/// service expects. JSON-RPC batches are split into single messages on the
+/// way in, and the responses to their requests go back as one array once
+/// all of them have arrived.
+pub fn bridge<T>(io: T, framing: Framing) -> DuplexStream
+where
+    T: AsyncRead + AsyncWrite + Send + 'static,
+{
+    bridge_with(io, framing, None)
+}
+
+/// Like [`bridge`], with requests that `local` answers kept from the service.
+/// Their responses join batches like the service's do.
+pub fn bridge_with<T>(io: T, framing: Framing, local: Option<LocalMethods>) -> DuplexStream
+where
+    T: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (service_side, bridge_side) = tokio::io::duplex(BRIDGE_BUFFER);
+    let (socket_read, mut socket_write) = tokio::io::split(io);
+    let (bridge_read, mut bridge_write) = tokio::io::split(bridge_side);
+    let batches = Arc::new(Mutex::new(Batches::default()));
+    // Errors the bridge answers itself, without asking the service.
+    let (replies, mut bridge_replies) = mpsc::unbounded_channel::<Value>();
+    // Responses from `local`, which may belong to a batch.
+    let (answers, mut local_answers) = mpsc::unbounded_channel::<Value>();
+
+    let reader_batches = batches.clone();
+    let reader_task = tokio::spawn(async move {
+        let mut reader = tokio::io::BufReader::new(socket_read);
+        loop {
+            let body = match read_frame(&mut reader, framing).await {
+                Ok(Some(body)) => body,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::warn!("Dropping connection with bad frame: {}", e);
+                    break;
+                }
+            };
+            let messages = match serde_json::from_slice::<Value>(&body) {
+                Ok(Value::Array(entries)) if entries.is_empty() => {
+                    let _ = replies.send(error_response(Value::Null, INVALID_REQUEST, "Empty batch"));
+                    continue;
+                }
+                Ok(Value::Array(entries)) => {
+                    let (messages, errors) = reader_batches.lock().unwrap().split(entries);
+                    if let Some(errors) = errors {
+                        let _ = replies.send(Value::Array(errors));
+                    }
+                    messages
+                }
+                Ok(message) => vec![message],
+                Err(e) => {
+                    let _ = replies.send(error_response(Value::Null, PARSE_ERROR, &format!("Parse error: {}", e)));
+                    continue;
+                }
+            };
+            for message in messages {
+                if let Some(answer) = local.as_ref().and_then(|local| answer_locally(local, &message)) {
+                    let _ = answers.send(answer);
+                    continue;
+                }
+                // Re-encoded compactly so newlines inside the message can't split it.
+                let mut line = serde_json::to_vec(&message).expect("JSON values serialize");
+                line.push(b'\n');
+                if bridge_write.write_all(&line).await.is_err() {
+                    return;
+                }
+            }
+        }
+        let _ = bridge_write.shutdown().await;
+    });
+
+    tokio::spawn(async move {
+        let mut lines = tokio::io::BufReader::new(bridge_read).lines();
+        loop {
+            let body = tokio::select! {
+                line = lines.next_line() => match line {
+                    Ok(Some(line)) if line.trim().is_empty() => continue,
+                    Ok(Some(line)) => match batches.lock().unwrap().collect(line) {
+                        Some(body) => body,
+                        None => continue,
+                    },
+                    _ => break,
+                },
+                Some(reply) = bridge_replies.recv() => serde_json::to_vec(&reply).expect("JSON values serialize"),
+                Some(answer) = local_answers.recv() => {
+                    let line = serde_json::to_string(&answer).expect("JSON values serialize");
+                    match batches.lock().unwrap().collect(line) {
+                        Some(body) => body,
+                        None => continue,
+                    }
+                }
+            };
+            if write_frame(&mut socket_write, framing, &body).await.is_err() {
+                break;
+            }
+        }
+        let _ = socket_write.shutdown().await;
+        // A half-open socket never reaches EOF, so stop reading once the
+        // service side has closed.
+        reader_task.abort();
+    });
+
+    service_side
+}
+
+// The response to `message` when it is a request `local` answers.
+fn answer_locally(local: &LocalMethods, message: &Value) -> Option<Value> {
+    let (id, method) = (message.get("id")?, message.get("method")?.as_str()?);
+    let result = local(method, message.get("params").unwrap_or(&Value::Null))?;
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+// Batched requests reach the service under an id of this form, so their
+// responses can't be confused with those of other batches or of single
+// requests that reuse the same id: the prefix, the batch number, a colon and
+// the client's id as JSON.
+const BATCH_ID_PREFIX: &str = "batch:";
+
+// Batches whose requests the service hasn't answered yet.
+#[derive(Default)]
+struct Batches {
+    open: HashMap<u64, OpenBatch>,
+    next_batch: u64,
+}
+
+struct OpenBatch {
+    /// Ids, in their JSON form, of the requests still unanswered.
+    waiting: HashSet<String>,
+    responses: Vec<Value>,
+}
+
+impl Batches {
+    // Returns the batch's messages to forward, registering its requests
+    // under (batch, id). Entries that aren't messages, and requests whose id
+    // an earlier entry already used, get errors; these are returned here
+    // when the batch has no requests to wait for.
+    fn split(&mut self, entries: Vec<Value>) -> (Vec<Value>, Option<Vec<Value>>) {
+        let batch = self.next_batch;
+        let mut messages = Vec::new();
+        let mut errors = Vec::new();
+        let mut waiting = HashSet::new();
+        for mut entry in entries {
+            if !entry.is_object() {
+                errors.push(error_response(Value::Null, INVALID_REQUEST, "Batch entries must be objects"));
+                continue;
+            }
+            if let (Some(id), Some(_)) = (entry.get("id"), entry.get("method")) {
+                let id = id.to_string();
+                if !waiting.insert(id.clone()) {
+                    errors.push(error_response(entry["id"].clone(), INVALID_REQUEST, "Duplicate request id in batch"));
+                    continue;
+                }
+                entry["id"] = Value::String(format!("{}{}:{}", BATCH_ID_PREFIX, batch, id));
+            }
+            messages.push(entry);
+        }
+        if waiting.is_empty() {
+            return (messages, (!errors.is_empty()).then_some(errors));
+        }
+        self.next_batch += 1;
+        self.open.insert(batch, OpenBatch { waiting, responses: errors });
+        (messages, None)
+    }
+
+    // Takes a line from the service: the frame body to send now, or `None`
+    // when it answers a batch that is still waiting for other responses.
+    fn collect(&mut self, line: String) -> Option<Vec<u8>> {
+        if self.open.is_empty() {
+            return Some(line.into_bytes());
+        }
+        let Ok(mut message) = serde_json::from_str::<Value>(&line) else {
+            return Some(line.into_bytes());
+        };
+        let key = match (message.get("id").and_then(Value::as_str), message.get("method")) {
+            (Some(id), None) => batch_key(id),
+            _ => None,
+        };
+        let Some((batch, id)) = key else {
+            return Some(line.into_bytes());
+        };
+        let Some(open) = self.open.get_mut(&batch) else {
+            return Some(line.into_bytes());
+        };
+        if !open.waiting.remove(&id) {
+            return Some(line.into_bytes());
+        }
+        message["id"] = serde_json::from_str(&id).expect("ids are stored as JSON");
+        open.responses.push(message);
+        if !open.waiting.is_empty() {
+            return None;
+        }
+        let open = self.open.remove(&batch).expect("batch is open");
+        Some(serde_json::to_vec(&open.responses).expect("JSON values serialize"))
+    }
+}
+
+// The (batch, client id) behind an id that `Batches::split` assigned.
+fn batch_key(id: &str) -> Option<(u64, String)> {
+    let (batch, id) = id.strip_prefix(BATCH_ID_PREFIX)?.split_once(':')?;
+    Some((batch.parse().ok()?, id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(id: Value) -> Value {
+        json!({ "jsonrpc": "2.0", "id": id, "method": "ping" })
+    }
+
+    // What the service sends back for a forwarded request.
+    fn respond(message: &Value) -> String {
+        json!({ "jsonrpc": "2.0", "id": message["id"], "result": {} }).to_string()
+    }
+
+    fn ids(body: &[u8]) -> Vec<Value> {
+        let responses: Vec<Value> = serde_json::from_slice(body).unwrap();
+        responses.into_iter().map(|response| response["id"].clone()).collect()
+    }
+
+    #[test]
+    fn test_content_length_frames() {
+        let mut wire = Vec::new();
+        write_frame_sync(&mut wire, Framing::ContentLength, b"{\"a\":\n1}").unwrap();
+        assert_eq!(wire, b"Content-Length: 8\r\n\r\n{\"a\":\n1}");
+        assert_eq!(classify(&wire), Framing::ContentLength);
+        assert_eq!(classify(b"{}"), Framing::Newline);
+
+        // Header names are case-insensitive, other headers and stray blank lines are skipped.
+        let mut reader = io::Cursor::new(b"\r\ncontent-length: 2\r\nContent-Type: application/json\r\n\r\n{}".to_vec());
+        assert_eq!(read_frame_sync(&mut reader, Framing::ContentLength).unwrap().unwrap(), b"{}");
+        assert!(read_frame_sync(&mut reader, Framing::ContentLength).unwrap().is_none());
+
+        let mut reader = io::Cursor::new(b"Content-Length: two\r\n\r\n{}".to_vec());
+        let e = read_frame_sync(&mut reader, Framing::ContentLength).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+
+        let oversized = format!("Content-Length: {}\r\n\r\n", MAX_FRAME_LEN + 1);
+        let e = read_frame_sync(&mut io::Cursor::new(oversized.into_bytes()), Framing::ContentLength).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[tokio::test]
+    async fn test_read_frame() {
+        let mut wire: &[u8] = b"Content-Length: 6\r\n\r\n[1,\n2]Content-Length: 2\r\n\r\n{}";
+        assert_eq!(read_frame(&mut wire, Framing::ContentLength).await.unwrap().unwrap(), b"[1,\n2]");
+        assert_eq!(read_frame(&mut wire, Framing::ContentLength).await.unwrap().unwrap(), b"{}");
+        assert!(read_frame(&mut wire, Framing::ContentLength).await.unwrap().is_none());
+
+        let mut wire: &[u8] = b"\n{\"a\":1}\n";
+        assert_eq!(read_frame(&mut wire, Framing::Newline).await.unwrap().unwrap(), b"{\"a\":1}");
+        assert!(read_frame(&mut wire, Framing::Newline).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_bridge_answers_batches_as_one_frame() {
+        let (client, server) = tokio::io::duplex(BRIDGE_BUFFER);
+        let local: LocalMethods = Arc::new(|method, _| (method == "health").then(|| json!({ "ok": true })));
+        let service = bridge_with(server, Framing::ContentLength, Some(local));
+        let (client_read, mut client_write) = tokio::io::split(client);
+        let mut client_read = tokio::io::BufReader::new(client_read);
+        let (service_read, mut service_write) = tokio::io::split(service);
+        let mut service_lines = tokio::io::BufReader::new(service_read).lines();
+
+        let health = json!({ "jsonrpc": "2.0", "id": 3, "method": "health" });
+        let batch = json!([request(json!(1)), health, request(json!(2))]);
+        write_frame(&mut client_write, Framing::ContentLength, batch.to_string().as_bytes())
+            .await
+            .unwrap();
+
+        // The service sees single newline-delimited requests, without the local one.
+        for _ in 0..2 {
+            let line = service_lines.next_line().await.unwrap().unwrap();
+            let message: Value = serde_json::from_str(&line).unwrap();
+            assert_eq!(message["method"], json!("ping"));
+            service_write.write_all(format!("{}\n", respond(&message)).as_bytes()).await.unwrap();
+        }
+
+        let body = read_frame(&mut client_read, Framing::ContentLength).await.unwrap().unwrap();
+        let mut ids = ids(&body);
+        ids.sort_by_key(|id| id.as_i64());
+        assert_eq!(ids, vec![json!(1), json!(2), json!(3)]);
+    }
+
+    #[test]
+    fn test_batch_responses_are_collected() {
+        let mut batches = Batches::default();
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        let (messages, errors) = batches.split(vec![request(json!(1)), notification, request(json!("a"))]);
+        assert!(errors.is_none());
+        assert_eq!(messages.len(), 3);
+
+        assert!(batches.collect(respond(&messages[2])).is_none());
+        // Lines that belong to no batch pass straight through.
+        let other = json!({ "jsonrpc": "2.0", "method": "notifications/progress" }).to_string();
+        assert_eq!(batches.collect(other.clone()).unwrap(), other.into_bytes());
+        let body = batches.collect(respond(&messages[0])).unwrap();
+        assert_eq!(ids(&body), vec![json!("a"), json!(1)]);
+        assert!(batches.open.is_empty());
+    }
+
+    #[test]
+    fn test_batch_with_duplicate_ids() {
+        let mut batches = Batches::default();
+        let (messages, errors) = batches.split(vec![request(json!(1)), request(json!(1)), request(json!(2))]);
+        assert!(errors.is_none());
+        assert_eq!(messages.len(), 2);
+
+        assert!(batches.collect(respond(&messages[0])).is_none());
+        let body = batches.collect(respond(&messages[1])).unwrap();
+        let responses: Vec<Value> = serde_json::from_slice(&body).unwrap();
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["id"], json!(1));
+        assert_eq!(responses[0]["error"]["code"], json!(INVALID_REQUEST));
+        assert_eq!(ids(&body)[1..], [json!(1), json!(2)]);
+    }
+
+    #[test]
+    fn test_batch_without_requests() {
+        let mut batches = Batches::default();
+        let (messages, errors) = batches.split(vec![json!(7)]);
+        assert!(messages.is_empty());
+        assert_eq!(errors.unwrap().len(), 1);
+        assert!(batches.open.is_empty());
+    }
+
+    #[test]
+    fn test_batches_with_overlapping_ids() {
+        let mut batches = Batches::default();
+        let (first, _) = batches.split(vec![request(json!(1)), request(json!(2))]);
+        let (second, _) = batches.split(vec![request(json!(1)), request(json!(3))]);
+        assert_ne!(first[0]["id"], second[0]["id"]);
+
+        // A single request reusing an open batch's id is answered on its own.
+        let single = respond(&request(json!(1)));
+        assert_eq!(batches.collect(single.clone()).unwrap(), single.into_bytes());
+
+        assert!(batches.collect(respond(&second[0])).is_none());
+        assert!(batches.collect(respond(&first[1])).is_none());
+        assert_eq!(ids(&batches.collect(respond(&second[1])).unwrap()), vec![json!(1), json!(3)]);
+        assert_eq!(ids(&batches.collect(respond(&first[0])).unwrap()), vec![json!(2), json!(1)]);
+        assert!(batches.open.is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+//! JSON-RPC messages over a framed stream, for clients talking to a server.
+
+use std::io;
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufRead, AsyncWrite};
+
+use crate::framing::{self, Framing};
+
+/// Writes one message in `framing`.
+pub async fn write_message<W: AsyncWrite + Unpin>(writer: &mut W, framing: Framing, message: &impl Serialize) -> io::Result<()> {
+    let body = serde_json::to_vec(message)?;
+    framing::write_frame(writer, framing, &body).await
+}
+
+/// Reads one message, or `None` at end of stream.
+pub async fn read_message<R: AsyncBufRead + Unpin>(reader: &mut R, framing: Framing) -> io::Result<Option<Value>> {
+    match framing::read_frame(reader, framing).await? {
+        Some(body) => Ok(Some(serde_json::from_slice(&body)?)),
+        None => Ok(None),
+    }
+}
+
+/// Answers a request the server sent the client; only `ping` is supported.
+pub fn server_request_reply(id: Value, method: &str) -> Value {
+    if method == "ping" {
+        json!({ "jsonrpc": "2.0", "id": id, "result": {} })
+    } else {
+        json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": { "code": -32601, "message": format!("Method not found: {}", method) },
+        })
+    }
+}
+
+/// Reads until the response to `id` arrives, skipping notifications and
+/// answering the server's own requests (keepalive pings). For one-shot
+/// clients with a single request in flight.
+pub async fn read_response<R, W>(reader: &mut R, writer: &mut W, framing: Framing, id: &Value) -> anyhow::Result<Value>
+where
+    R: AsyncBufRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    loop {
+        let message = read_message(reader, framing)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("MCP server closed the connection"))?;
+        match (message.get("id"), message.get("method").and_then(Value::as_str)) {
+            (Some(request_id), Some(method)) => {
+                write_message(writer, framing, &server_request_reply(request_id.clone(), method)).await?;
+            }
+            (Some(response_id), None) if response_id == id => return Ok(message),
+            _ => {}
+        }
+    }
+}
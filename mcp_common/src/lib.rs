@@ -0,0 +1,9 @@
+//! Pieces every server and client binary shares: where runtime files live,
+//! the lock file that advertises a running server and its secret, wire
+//! framing, and the JSON-RPC plumbing clients need on top of it.
+
+pub mod framing;
+pub mod jsonrpc;
+pub mod lock;
+pub mod runtime_paths;
+pub mod secret;
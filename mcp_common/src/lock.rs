@@ -0,0 +1,79 @@
+//! The lock file a running server writes so local clients can find it.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use tokio::net::TcpStream;
+
+use crate::runtime_paths;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct LockData {
+    pub pid: u32,
+    pub port: u16,
+    /// Shared secret clients must present in `initialize`. Missing in lock
+    /// files written by older servers, which accept any client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// Address to dial when the server is bound to a specific non-loopback
+    /// address; otherwise clients use localhost.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// Certificate the server presents when it speaks TLS.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tls_cert: Option<PathBuf>,
+    /// Port of the server's WebSocket listener, if it has one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_port: Option<u16>,
+}
+
+impl LockData {
+    /// The host clients dial.
+    pub fn host(&self) -> &str {
+        self.host.as_deref().unwrap_or("localhost")
+    }
+
+    /// Opens a plain TCP connection to the server. Callers must check
+    /// `tls_cert` first: a TLS server won't answer plain JSON-RPC.
+    pub async fn connect(&self) -> io::Result<TcpStream> {
+        TcpStream::connect((self.host(), self.port)).await
+    }
+}
+
+/// Prefix of the line a TCP server prints on stderr once it is listening, so
+/// a process that spawned it learns the port without reading the lock file.
+pub const PORT_ANNOUNCEMENT: &str = "SERVER_PORT:";
+
+/// The port in a `SERVER_PORT: <port>` line, if that is what `line` is.
+pub fn parse_port_announcement(line: &str) -> Option<u16> {
+    line.trim().strip_prefix(PORT_ANNOUNCEMENT)?.trim().parse().ok()
+}
+
+pub fn read(path: &Path) -> anyhow::Result<LockData> {
+    let content = fs::read_to_string(path)?;
+    Ok(serde_json::from_str(&content)?)
+}
+
+/// Writes the lock file readable only by the current user: it holds the token.
+pub fn write(path: &Path, data: &LockData) -> anyhow::Result<()> {
+    runtime_paths::write_private_file(path, serde_json::to_string(data)?.as_bytes())?;
+    Ok(())
+}
+
+pub fn remove(path: &Path) -> io::Result<()> {
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+    Ok(())
+}
+
+/// Removes the lock file only if it belongs to this process, since another
+/// server may already have replaced a stale one.
+pub fn remove_own(path: &Path) -> anyhow::Result<()> {
+    if read(path).is_ok_and(|lock| lock.pid == std::process::id()) {
+        remove(path)?;
+    }
+    Ok(())
+}
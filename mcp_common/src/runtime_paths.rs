@@ -0,0 +1,294 @@
+//! Per-user locations for the server's lock file and logs.
+//!
+//! Each named server instance (`--instance <name>`) has its own lock file,
+//! logs and job store. The `default` instance keeps the original file names.
+//! Logs go to the runtime dir unless `--log-dir` or `MCP_LOG_DIR` names
+//! another directory.
+//!
+//! Files live in `$XDG_RUNTIME_DIR/copilot_mcp_tool` when available and in the
+//! local data dir (`%LOCALAPPDATA%` on Windows) otherwise. On Unix the
+//! directory is 0700 and files are 0600. On Windows the per-user profile
+//! directory already carries owner-only ACLs, which new files inherit.
+
+use std::env;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const APP_DIR_NAME: &str = "copilot_mcp_tool";
+const LOCK_FILE_NAME: &str = "copilot_mcp_tool.lock";
+// Raw stdout and stderr captures written by older versions.
+const STDOUT_LOG_NAME: &str = "copilot_mcp_server.stdout.log";
+const STDERR_LOG_NAME: &str = "copilot_mcp_server.stderr.log";
+const SERVER_LOG_NAME: &str = "copilot_mcp_server.log";
+const REPL_HISTORY_NAME: &str = "repl_history";
+const JOBS_DIR_NAME: &str = "jobs";
+const AUDIT_LOG_NAME: &str = "audit.jsonl";
+const VECTOR_STORE_NAME: &str = "vector_store.json";
+const CONVERSATIONS_NAME: &str = "conversations.sqlite3";
+const LLM_USAGE_NAME: &str = "llm_usage.json";
+const PLUGIN_COPIES_DIR_NAME: &str = "plugin_copies";
+
+pub const DEFAULT_INSTANCE: &str = "default";
+
+static INSTANCE: OnceLock<String> = OnceLock::new();
+static LOG_DIR: OnceLock<Option<PathBuf>> = OnceLock::new();
+
+fn is_valid_instance(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= 64
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Selects the server instance the other paths belong to. Must be called
+/// before any of them; without it the instance is `MCP_INSTANCE` or `default`.
+pub fn set_instance(name: &str) -> io::Result<()> {
+    if !is_valid_instance(name) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Invalid instance name '{}': use letters, digits, '-' and '_'", name),
+        ));
+    }
+    INSTANCE
+        .set(name.to_string())
+        .map_err(|_| io::Error::other("Instance already selected"))
+}
+
+/// Name of the selected server instance.
+pub fn instance() -> &'static str {
+    INSTANCE.get_or_init(|| {
+        env::var("MCP_INSTANCE")
+            .ok()
+            .filter(|name| is_valid_instance(name))
+            .unwrap_or_else(|| DEFAULT_INSTANCE.to_string())
+    })
+}
+
+// `copilot_mcp_server.log` for the default instance, `copilot_mcp_server.<name>.log` otherwise.
+fn instance_file_name(file_name: &str) -> String {
+    let name = instance();
+    if name == DEFAULT_INSTANCE {
+        return file_name.to_string();
+    }
+    match file_name.split_once('.') {
+        Some((stem, rest)) => format!("{}.{}.{}", stem, name, rest),
+        None => format!("{}.{}", file_name, name),
+    }
+}
+
+/// Returns the per-user runtime directory, creating it if needed.
+pub fn runtime_dir() -> io::Result<PathBuf> {
+    let base = dirs::runtime_dir()
+        .or_else(dirs::data_local_dir)
+        .unwrap_or_else(env::temp_dir);
+    let dir = base.join(APP_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    restrict_to_owner(&dir, true)?;
+    Ok(dir)
+}
+
+/// Returns the per-user directory for state that must survive reboots
+/// (the runtime dir may be a tmpfs), creating it if needed.
+pub fn data_dir() -> io::Result<PathBuf> {
+    let base = dirs::data_local_dir().unwrap_or_else(env::temp_dir);
+    let dir = base.join(APP_DIR_NAME);
+    fs::create_dir_all(&dir)?;
+    restrict_to_owner(&dir, true)?;
+    Ok(dir)
+}
+
+pub fn lock_file_path() -> io::Result<PathBuf> {
+    Ok(runtime_dir()?.join(instance_file_name(LOCK_FILE_NAME)))
+}
+
+/// Lock files of every instance, as `(instance name, path)`.
+pub fn instance_lock_files() -> io::Result<Vec<(String, PathBuf)>> {
+    let (stem, extension) = LOCK_FILE_NAME.split_once('.').unwrap_or((LOCK_FILE_NAME, ""));
+    let mut locks = Vec::new();
+    for entry in fs::read_dir(runtime_dir()?)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().into_owned();
+        let name = if file_name == LOCK_FILE_NAME {
+            DEFAULT_INSTANCE
+        } else {
+            match file_name
+                .strip_prefix(stem)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .and_then(|rest| rest.strip_suffix(extension))
+                .and_then(|rest| rest.strip_suffix('.'))
+            {
+                Some(name) if is_valid_instance(name) => name,
+                _ => continue,
+            }
+        };
+        locks.push((name.to_string(), entry.path()));
+    }
+    locks.sort();
+    Ok(locks)
+}
+
+/// Selects the directory logs are written to and read from. Like
+/// `set_instance`, must be called before any log path is used; without it
+/// the directory is `MCP_LOG_DIR`, or the runtime dir.
+pub fn set_log_dir(dir: &Path) -> io::Result<()> {
+    let dir = std::path::absolute(dir)?;
+    LOG_DIR
+        .set(Some(dir))
+        .map_err(|_| io::Error::other("Log directory already selected"))
+}
+
+/// The log directory chosen with `set_log_dir` or `MCP_LOG_DIR`, if any.
+pub fn configured_log_dir() -> Option<&'static Path> {
+    LOG_DIR
+        .get_or_init(|| {
+            env::var_os("MCP_LOG_DIR")
+                .filter(|dir| !dir.is_empty())
+                .and_then(|dir| std::path::absolute(dir).ok())
+        })
+        .as_deref()
+}
+
+/// Returns the directory of the server's logs, creating it if needed.
+pub fn log_dir() -> io::Result<PathBuf> {
+    match configured_log_dir() {
+        Some(dir) => {
+            fs::create_dir_all(dir)?;
+            Ok(dir.to_path_buf())
+        }
+        None => runtime_dir(),
+    }
+}
+
+/// Path of the server's own (rotated) tracing log.
+pub fn server_log_path() -> io::Result<PathBuf> {
+    Ok(log_dir()?.join(instance_file_name(SERVER_LOG_NAME)))
+}
+
+/// Directory of the TCP server's persisted background jobs.
+pub fn jobs_dir() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(instance_file_name(JOBS_DIR_NAME)))
+}
+
+/// The tool-call audit log; kept across reboots.
+pub fn audit_log_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(instance_file_name(AUDIT_LOG_NAME)))
+}
+
+/// Documents and embeddings indexed for `semantic_search`; kept across reboots.
+pub fn vector_store_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(instance_file_name(VECTOR_STORE_NAME)))
+}
+
+/// Chat history saved by `chat_completion`; kept across reboots.
+pub fn conversations_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(instance_file_name(CONVERSATIONS_NAME)))
+}
+
+/// Daily LLM token and cost totals; kept across reboots.
+pub fn llm_usage_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(instance_file_name(LLM_USAGE_NAME)))
+}
+
+/// Private copies of the native plugins this process has loaded, so a plugin
+/// can be replaced on disk while its old version is still in use.
+pub fn plugin_copies_dir() -> io::Result<PathBuf> {
+    let dir = runtime_dir()?.join(instance_file_name(PLUGIN_COPIES_DIR_NAME));
+    fs::create_dir_all(&dir)?;
+    restrict_to_owner(&dir, true)?;
+    Ok(dir)
+}
+
+/// Command history of the interactive `repl`; kept across reboots.
+pub fn repl_history_path() -> io::Result<PathBuf> {
+    Ok(data_dir()?.join(REPL_HISTORY_NAME))
+}
+
+/// Creates (or truncates) a file only the current user can read.
+pub fn create_private_file(path: &Path) -> io::Result<File> {
+    open_private_file(path, false)
+}
+
+/// Opens a file only the current user can read for appending, creating it if needed.
+pub fn append_private_file(path: &Path) -> io::Result<File> {
+    open_private_file(path, true)
+}
+
+fn open_private_file(path: &Path, append: bool) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    if append {
+        options.append(true).create(true);
+    } else {
+        options.write(true).create(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let file = options.open(path)?;
+    // `mode` only applies to new files; tighten files left by older versions too.
+    restrict_to_owner(path, false)?;
+    Ok(file)
+}
+
+pub fn write_private_file(path: &Path, contents: &[u8]) -> io::Result<()> {
+    create_private_file(path)?.write_all(contents)
+}
+
+#[cfg(unix)]
+fn restrict_to_owner(path: &Path, is_dir: bool) -> io::Result<()> {
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    let metadata = fs::metadata(path)?;
+    // Refuse to use a path someone else created for us, e.g. in a shared temp dir.
+    if metadata.uid() != unsafe { libc::geteuid() } {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} is owned by another user", path.display()),
+        ));
+    }
+    let mode = if is_dir { 0o700 } else { 0o600 };
+    if metadata.permissions().mode() & 0o777 != mode {
+        fs::set_permissions(path, fs::Permissions::from_mode(mode))?;
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn restrict_to_owner(_path: &Path, _is_dir: bool) -> io::Result<()> {
+    Ok(())
+}
+
+/// Moves the lock file and logs out of the shared temp dir used by older
+/// versions. Best effort: anything that cannot be moved is left alone.
+pub fn migrate_legacy_files() -> io::Result<()> {
+    let legacy_dir = env::temp_dir();
+    let dir = runtime_dir()?;
+    if legacy_dir == dir {
+        return Ok(());
+    }
+
+    for name in [LOCK_FILE_NAME, STDOUT_LOG_NAME, STDERR_LOG_NAME] {
+        let legacy = legacy_dir.join(name);
+        let target = dir.join(name);
+        if !legacy.is_file() || target.exists() {
+            continue;
+        }
+        // Only migrate files we own, since anyone can plant a lock file in /tmp.
+        // Copy rather than rename: the temp dir is often on another filesystem,
+        // and the copy gets owner-only permissions.
+        let migrated = restrict_to_owner(&legacy, false)
+            .and_then(|()| fs::read(&legacy))
+            .and_then(|contents| write_private_file(&target, &contents));
+        match migrated {
+            Ok(()) => {
+                if let Err(e) = fs::remove_file(&legacy) {
+                    tracing::warn!("Migrated {} but could not remove it: {}", legacy.display(), e);
+                }
+            }
+            Err(e) => tracing::warn!("Could not migrate {}: {}", legacy.display(), e),
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,46 @@
+//! Random secrets and their comparison.
+
+/// Returns a fresh token: two random v4 UUIDs as 64 hex characters.
+pub fn generate_token() -> String {
+    format!(
+        "{}{}",
+        uuid::Uuid::new_v4().simple(),
+        uuid::Uuid::new_v4().simple()
+    )
+}
+
+/// Compares in constant time so a client can't recover the token byte by byte.
+pub fn token_matches(expected: &str, offered: &str) -> bool {
+    let (expected, offered) = (expected.as_bytes(), offered.as_bytes());
+    expected.len() == offered.len()
+        && expected
+            .iter()
+            .zip(offered)
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_token_matches() {
+        let token = generate_token();
+        assert!(token_matches(&token, &token.clone()));
+        assert!(!token_matches(&token, &token[..63]));
+        assert!(!token_matches(&token, &format!("{}0", token)));
+        assert!(!token_matches(&token, ""));
+        let mut flipped = token.clone().into_bytes();
+        flipped[0] = if flipped[0] == b'a' { b'b' } else { b'a' };
+        assert!(!token_matches(&token, &String::from_utf8(flipped).unwrap()));
+    }
+
+    #[test]
+    fn test_generate_token() {
+        let token = generate_token();
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_ne!(token, generate_token());
+    }
+}
@@ -0,0 +1,242 @@
+//! Stable error codes shared by the copilot_mcp_tool workspace.
+//!
+//! Every error carries an [`ErrorCode`] whose string form (e.g.
+//! `system.process_not_found`) never changes, so clients can branch on it
+//! instead of matching human-readable messages.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+/// Broad class of an error, used to pick JSON-RPC and plugin return codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    InvalidInput,
+    NotFound,
+    PermissionDenied,
+    Unavailable,
+    Timeout,
+    Unsupported,
+    Upstream,
+    Internal,
+}
+
+impl ErrorCategory {
+    /// JSON-RPC error code used when the error is returned as a protocol error.
+    pub fn json_rpc_code(&self) -> i32 {
+        match self {
+            ErrorCategory::InvalidInput => -32602,
+            ErrorCategory::NotFound => -32002,
+            _ => -32603,
+        }
+    }
+}
+
+macro_rules! error_codes {
+    ($($variant:ident => ($code:literal, $num:literal, $category:ident),)*) => {
+        /// Stable, machine-readable error codes.
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+        pub enum ErrorCode {
+            $(
+                #[serde(rename = $code)]
+                $variant,
+            )*
+        }
+
+        impl ErrorCode {
+            /// Stable string form, e.g. `system.process_not_found`.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $(ErrorCode::$variant => $code,)*
+                }
+            }
+
+            /// Stable numeric form, used where only an integer fits (plugin ABI).
+            pub fn as_i32(&self) -> i32 {
+                match self {
+                    $(ErrorCode::$variant => $num,)*
+                }
+            }
+
+            pub fn category(&self) -> ErrorCategory {
+                match self {
+                    $(ErrorCode::$variant => ErrorCategory::$category,)*
+                }
+            }
+
+            pub fn from_str_code(code: &str) -> Option<Self> {
+                match code {
+                    $($code => Some(ErrorCode::$variant),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+error_codes! {
+    // Generic
+    InvalidArgument => ("common.invalid_argument", 1000, InvalidInput),
+    NotImplemented => ("common.not_implemented", 1001, Unsupported),
+    UnsupportedPlatform => ("common.unsupported_platform", 1002, Unsupported),
+    Internal => ("common.internal", 1003, Internal),
+    Timeout => ("common.timeout", 1004, Timeout),
+    Io => ("common.io", 1005, Internal),
+    Busy => ("common.busy", 1006, Unavailable),
+    ContentTooLarge => ("common.content_too_large", 1007, InvalidInput),
+    ShuttingDown => ("common.shutting_down", 1008, Unavailable),
+    ToolDenied => ("common.tool_denied", 1009, PermissionDenied),
+    ConfirmationRequired => ("common.confirmation_required", 1010, PermissionDenied),
+    ResourceNotFound => ("common.resource_not_found", 1011, NotFound),
+    Cancelled => ("common.cancelled", 1012, Unavailable),
+    RateLimited => ("common.rate_limited", 1013, Unavailable),
+    // System commands
+    ProcessNotFound => ("system.process_not_found", 2000, NotFound),
+    CommandFailed => ("system.command_failed", 2001, Upstream),
+    PermissionDenied => ("system.permission_denied", 2002, PermissionDenied),
+    SandboxViolation => ("system.sandbox_violation", 2003, PermissionDenied),
+    FileNotFound => ("system.file_not_found", 2004, NotFound),
+    // LLM drivers
+    LlmRequestFailed => ("llm.request_failed", 3000, Upstream),
+    LlmAuthFailed => ("llm.auth_failed", 3001, PermissionDenied),
+    LlmInvalidResponse => ("llm.invalid_response", 3002, Upstream),
+    ConversationNotFound => ("llm.conversation_not_found", 3003, NotFound),
+    LlmBudgetExceeded => ("llm.budget_exceeded", 3004, Unavailable),
+    // OAuth
+    OAuthProviderNotFound => ("oauth.provider_not_found", 4000, NotFound),
+    OAuthTokenNotFound => ("oauth.token_not_found", 4001, NotFound),
+    OAuthInvalidState => ("oauth.invalid_state", 4002, InvalidInput),
+    OAuthExchangeFailed => ("oauth.exchange_failed", 4003, Upstream),
+    OAuthRefreshFailed => ("oauth.refresh_failed", 4004, Upstream),
+    // Remote desktop
+    PeerOffline => ("rustdesk.peer_offline", 5000, Unavailable),
+    PeerAuthFailed => ("rustdesk.auth_failed", 5001, PermissionDenied),
+    SessionNotFound => ("rustdesk.session_not_found", 5002, NotFound),
+    // OBS
+    ObsNotConnected => ("obs.not_connected", 6000, Unavailable),
+    ObsRequestFailed => ("obs.request_failed", 6001, Upstream),
+    // Background jobs
+    JobNotFound => ("jobs.not_found", 7000, NotFound),
+    JobNotFinished => ("jobs.not_finished", 7001, Unavailable),
+    // Network diagnostics
+    DnsResolutionFailed => ("network.dns_failed", 8000, NotFound),
+    HostUnreachable => ("network.unreachable", 8001, Unavailable),
+}
+
+impl std::fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// An error with a stable code, a message for humans, and optional details.
+#[derive(Debug, Clone, thiserror::Error, Serialize, Deserialize)]
+#[error("{code}: {message}")]
+pub struct McpToolError {
+    pub code: ErrorCode,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+}
+
+impl McpToolError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    pub fn category(&self) -> ErrorCategory {
+        self.code.category()
+    }
+
+    /// JSON payload used for structured tool errors and plugin messages.
+    /// `error` keeps the message under the key existing clients already read.
+    pub fn to_json(&self) -> Value {
+        let mut value = json!({
+            "error": self.message,
+            "code": self.code.as_str(),
+            "category": self.code.category(),
+        });
+        if let Some(details) = &self.details {
+            value["details"] = details.clone();
+        }
+        value
+    }
+
+    /// Return code for the C plugin ABI (always negative).
+    pub fn plugin_code(&self) -> i32 {
+        -self.code.as_i32()
+    }
+
+    /// Tool-level error result, so the model sees the failure in-band.
+    #[cfg(feature = "rmcp")]
+    pub fn into_call_tool_result(self) -> rmcp::model::CallToolResult {
+        rmcp::model::CallToolResult::structured_error(self.to_json())
+    }
+}
+
+impl From<std::io::Error> for McpToolError {
+    fn from(e: std::io::Error) -> Self {
+        let code = match e.kind() {
+            std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+            std::io::ErrorKind::TimedOut => ErrorCode::Timeout,
+            _ => ErrorCode::Io,
+        };
+        McpToolError::new(code, e.to_string())
+    }
+}
+
+#[cfg(feature = "rmcp")]
+impl From<McpToolError> for rmcp::ErrorData {
+    fn from(e: McpToolError) -> Self {
+        let data = Some(e.to_json());
+        rmcp::ErrorData::new(
+            rmcp::model::ErrorCode(e.category().json_rpc_code()),
+            e.message,
+            data,
+        )
+    }
+}
+
+/// Extracts the stable code from a JSON-RPC error or structured tool error payload.
+pub fn code_of(value: &Value) -> Option<ErrorCode> {
+    value
+        .get("code")
+        .and_then(Value::as_str)
+        .or_else(|| value.get("data").and_then(|d| d.get("code")).and_then(Value::as_str))
+        .and_then(ErrorCode::from_str_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_code_round_trip() {
+        let code = ErrorCode::ProcessNotFound;
+        assert_eq!(code.as_str(), "system.process_not_found");
+        assert_eq!(ErrorCode::from_str_code(code.as_str()), Some(code));
+        assert_eq!(serde_json::to_value(code).unwrap(), json!("system.process_not_found"));
+    }
+
+    #[test]
+    fn test_error_json_and_lookup() {
+        let err = McpToolError::new(ErrorCode::OAuthInvalidState, "CSRF token mismatch")
+            .with_details(json!({ "provider": "github" }));
+        let value = err.to_json();
+
+        assert_eq!(value["error"], "CSRF token mismatch");
+        assert_eq!(value["category"], "invalid_input");
+        assert_eq!(code_of(&value), Some(ErrorCode::OAuthInvalidState));
+        assert_eq!(code_of(&json!({ "data": value })), Some(ErrorCode::OAuthInvalidState));
+        assert!(err.plugin_code() < 0);
+    }
+}
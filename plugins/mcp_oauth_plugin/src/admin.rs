@@ -0,0 +1,153 @@
+//! The `/admin` page: configured providers with their token status, and
+//! buttons to log in, refresh and revoke.
+//!
+//! Templates are compiled into the plugin, so the page works whatever the
+//! host's working directory. Log-in buttons link to the OAuth routes, which
+//! the host mounts at `/oauth/start/:provider` and
+//! `/oauth/device/:provider/poll`.
+
+use std::sync::{Arc, LazyLock};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tera::{Context, Tera};
+use url::form_urlencoded;
+
+use crate::handlers::{self, PluginAppState};
+use crate::oauth::OAuthClient;
+use crate::providers;
+
+static TEMPLATES: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    tera.add_raw_templates(vec![
+        ("base.html", include_str!("../templates/base.html")),
+        ("admin.html", include_str!("../templates/admin.html")),
+        ("device.html", include_str!("../templates/device.html")),
+        ("logged_in.html", include_str!("../templates/logged_in.html")),
+    ])
+    .expect("OAuth templates are valid");
+    tera.autoescape_on(vec![".html"]);
+    tera
+});
+
+/// Renders one of the plugin's templates.
+pub fn render(template: &str, context: &Context) -> anyhow::Result<Html<String>> {
+    Ok(Html(TEMPLATES.render(template, context)?))
+}
+
+/// The admin routes. Mount them next to the OAuth handlers.
+pub fn router(state: Arc<PluginAppState>) -> Router {
+    Router::new()
+        .route("/admin", get(admin_page))
+        .route("/admin/device/:provider", post(admin_device_login))
+        .route("/admin/refresh/:provider", post(admin_refresh))
+        .route("/admin/revoke/:provider", post(admin_revoke))
+        .with_state(state)
+}
+
+// One row of the providers table.
+#[derive(Debug, Serialize)]
+struct ProviderRow {
+    id: String,
+    name: String,
+    authorization_code: bool,
+    device_code: bool,
+    signed_in: bool,
+    expired: bool,
+    expires_at: Option<DateTime<Utc>>,
+    has_refresh_token: bool,
+}
+
+// Outcome of the last button press, carried through the redirect.
+#[derive(Debug, Default, Deserialize)]
+struct Flash {
+    notice: Option<String>,
+    error: Option<String>,
+}
+
+fn back_to_admin(key: &str, message: &str) -> Redirect {
+    let query: String = form_urlencoded::Serializer::new(String::new()).append_pair(key, message).finish();
+    Redirect::to(&format!("/admin?{}", query))
+}
+
+fn failure(e: anyhow::Error) -> Response {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)).into_response()
+}
+
+// The providers table
+async fn admin_page(Query(flash): Query<Flash>, State(plugin_app_state): State<Arc<PluginAppState>>) -> Response {
+    let configs = plugin_app_state.oauth_configs.read().await;
+    let tokens = plugin_app_state.token_store.all().await;
+    let providers: Vec<ProviderRow> = providers::describe(&configs, |id| tokens.contains_key(id))
+        .into_iter()
+        .map(|provider| {
+            let token = tokens.get(&provider.id);
+            ProviderRow {
+                expired: token.is_some_and(|token| token.is_expired()),
+                expires_at: token.map(|token| token.expires_at),
+                has_refresh_token: token.is_some_and(|token| !token.refresh_token.is_empty()),
+                id: provider.id,
+                name: provider.name,
+                authorization_code: provider.authorization_code,
+                device_code: provider.device_code,
+                signed_in: provider.signed_in,
+            }
+        })
+        .collect();
+
+    let mut context = Context::new();
+    context.insert("providers", &providers);
+    context.insert("metrics", &plugin_app_state.token_store.auth_flow_metrics().await);
+    context.insert("notice", &flash.notice);
+    context.insert("error", &flash.error);
+    render("admin.html", &context).map_or_else(failure, IntoResponse::into_response)
+}
+
+// Starts a device-code sign-in and shows the code; the page polls until done
+async fn admin_device_login(
+    Path(provider): Path<String>,
+    State(plugin_app_state): State<Arc<PluginAppState>>,
+) -> Response {
+    let name = providers::preset(&provider).map_or_else(|| provider.clone(), |preset| preset.name.to_string());
+    let started = handlers::start_device_auth(Path(provider.clone()), State(plugin_app_state)).await;
+    let code = match started {
+        Ok(code) => code.0,
+        Err(e) => return back_to_admin("error", &format!("Sign-in to {} failed: {:#}", name, e)).into_response(),
+    };
+    let mut context = Context::new();
+    context.insert("provider", &provider);
+    context.insert("name", &name);
+    context.insert("user_code", &code["user_code"]);
+    context.insert("verification_uri", &code["verification_uri"]);
+    context.insert("verification_uri_complete", &code["verification_uri_complete"]);
+    context.insert("interval", &code["interval"]);
+    render("device.html", &context).map_or_else(failure, IntoResponse::into_response)
+}
+
+// Exchanges the provider's refresh token now
+async fn admin_refresh(Path(provider): Path<String>, State(plugin_app_state): State<Arc<PluginAppState>>) -> Redirect {
+    let config = match plugin_app_state.oauth_configs.read().await.get(&provider).cloned() {
+        Some(config) => config,
+        None => return back_to_admin("error", &format!("OAuth provider {} not found", provider)),
+    };
+    let client = OAuthClient::new(config, plugin_app_state.token_store.clone());
+    match client.refresh_token(&provider).await {
+        Ok(token) => back_to_admin("notice", &format!("Refreshed {}; valid until {}", provider, token.expires_at)),
+        Err(e) => back_to_admin("error", &format!("Refreshing {} failed: {:#}", provider, e)),
+    }
+}
+
+// Deletes the provider's stored token
+async fn admin_revoke(Path(provider): Path<String>, State(plugin_app_state): State<Arc<PluginAppState>>) -> Redirect {
+    match plugin_app_state.token_store.remove(&provider).await {
+        Ok(()) => back_to_admin("notice", &format!("Removed the token for {}", provider)),
+        Err(e) => back_to_admin("error", &format!("Removing the token for {} failed: {:#}", provider, e)),
+    }
+}
@@ -0,0 +1,220 @@
+//! GitHub Copilot sign-in.
+//!
+//! Copilot needs two tokens: a long-lived GitHub OAuth token for the Copilot
+//! OAuth app, and a short-lived (~30 minute) Copilot session token exchanged
+//! from it. The GitHub token is found in the token store, `GITHUB_TOKEN`, the
+//! `hosts.json`/`apps.json` written by Copilot editor plugins, or `gh auth
+//! token`; failing that, `start_device_flow` signs the user in. Both tokens
+//! are cached in the [`TokenStore`].
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use serde::Deserialize;
+
+use crate::token_store::{OAuthToken, TokenStore};
+
+pub use crate::oauth::DeviceCode;
+
+/// Token store entry holding the GitHub OAuth token.
+pub const GITHUB_PROVIDER_ID: &str = "github-copilot";
+/// Token store entry holding the current Copilot session token.
+pub const COPILOT_SESSION_PROVIDER_ID: &str = "github-copilot-session";
+
+/// OAuth app used by the official Copilot editor plugins.
+pub const CLIENT_ID: &str = "Iv1.b507a08c87ecfe98";
+const DEVICE_CODE_URL: &str = "https://github.com/login/device/code";
+const ACCESS_TOKEN_URL: &str = "https://github.com/login/oauth/access_token";
+const COPILOT_TOKEN_URL: &str = "https://api.github.com/copilot_internal/v2/token";
+
+pub struct GitHubCopilotAuth {
+    http_client: reqwest::Client,
+    token_store: TokenStore,
+}
+
+impl GitHubCopilotAuth {
+    pub fn new(token_store: TokenStore) -> Self {
+        Self {
+            http_client: reqwest::Client::new(),
+            token_store,
+        }
+    }
+
+    /// Starts a device-code sign-in for the Copilot OAuth app.
+    pub async fn start_device_flow(&self) -> Result<DeviceCode> {
+        let response = self
+            .http_client
+            .post(DEVICE_CODE_URL)
+            .header("Accept", "application/json")
+            .form(&[("client_id", CLIENT_ID), ("scope", "read:user")])
+            .send()
+            .await
+            .context("Failed to start GitHub device sign-in")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(anyhow!("GitHub device sign-in failed: {} - {}", status, body));
+        }
+        response.json().await.context("Failed to parse GitHub device code response")
+    }
+
+    /// Waits for the user to approve the device code, then stores and returns
+    /// the GitHub OAuth token.
+    pub async fn poll_device_flow(&self, code: &DeviceCode) -> Result<String> {
+        #[derive(Deserialize)]
+        struct PollResponse {
+            access_token: Option<String>,
+            error: Option<String>,
+            interval: Option<u64>,
+        }
+
+        let deadline = tokio::time::Instant::now() + Duration::from_secs(code.expires_in);
+        let mut interval = Duration::from_secs(code.interval.max(1));
+        while tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(interval).await;
+            let response: PollResponse = self
+                .http_client
+                .post(ACCESS_TOKEN_URL)
+                .header("Accept", "application/json")
+                .form(&[
+                    ("client_id", CLIENT_ID),
+                    ("device_code", code.device_code.as_str()),
+                    ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+                ])
+                .send()
+                .await
+                .context("Failed to poll GitHub device sign-in")?
+                .json()
+                .await
+                .context("Failed to parse GitHub access token response")?;
+
+            if let Some(access_token) = response.access_token {
+                self.token_store.save(OAuthToken {
+                    provider_id: GITHUB_PROVIDER_ID.to_string(),
+                    access_token: access_token.clone(),
+                    // GitHub OAuth app tokens don't expire and have no refresh token.
+                    refresh_token: String::new(),
+                    expires_at: Utc::now() + chrono::Duration::days(365 * 100),
+                    enterprise_url: None,
+                    project_id: None,
+                }).await?;
+                return Ok(access_token);
+            }
+            match response.error.as_deref() {
+                Some("authorization_pending") => {}
+                Some("slow_down") => {
+                    interval = Duration::from_secs(response.interval.unwrap_or(interval.as_secs() + 5));
+                }
+                Some(error) => {
+                    return Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, format!("GitHub sign-in failed: {}", error)).into());
+                }
+                None => return Err(anyhow!("GitHub sign-in returned neither a token nor an error")),
+            }
+        }
+        Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, "GitHub sign-in timed out before the code was approved").into())
+    }
+
+    /// Finds a GitHub token without user interaction, or `None` if the user
+    /// has to sign in first.
+    pub async fn github_token(&self) -> Option<String> {
+        if let Some(token) = self.token_store.get(GITHUB_PROVIDER_ID).await {
+            return Some(token.access_token);
+        }
+        if let Some(token) = std::env::var("GITHUB_TOKEN").ok().filter(|token| !token.is_empty()) {
+            return Some(token);
+        }
+        if let Some(token) = editor_plugin_token() {
+            return Some(token);
+        }
+        gh_cli_token()
+    }
+
+    /// Returns a Copilot session token, exchanging the GitHub token for a new
+    /// one when the cached token is about to expire.
+    pub async fn copilot_token(&self) -> Result<OAuthToken> {
+        if let Some(token) = self.token_store.get(COPILOT_SESSION_PROVIDER_ID).await.filter(|token| !token.needs_refresh()) {
+            return Ok(token);
+        }
+        let github_token = self.github_token().await.ok_or_else(|| {
+            McpToolError::new(
+                ErrorCode::OAuthTokenNotFound,
+                "Not signed in to GitHub Copilot. Run `copilot_mcp_tool copilot login`",
+            )
+        })?;
+
+        #[derive(Deserialize)]
+        struct CopilotTokenResponse {
+            token: String,
+            expires_at: i64,
+        }
+
+        let response = self
+            .http_client
+            .get(COPILOT_TOKEN_URL)
+            .header("Authorization", format!("token {}", github_token))
+            .header("Accept", "application/json")
+            .header("User-Agent", "copilot_mcp_tool")
+            .send()
+            .await
+            .context("Failed to request a Copilot token")?;
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            let code = if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+                ErrorCode::LlmAuthFailed
+            } else {
+                ErrorCode::OAuthRefreshFailed
+            };
+            return Err(McpToolError::new(code, format!("Copilot token request failed: {} - {}", status, body)).into());
+        }
+        let response: CopilotTokenResponse = response.json().await.context("Failed to parse Copilot token response")?;
+
+        let token = OAuthToken {
+            provider_id: COPILOT_SESSION_PROVIDER_ID.to_string(),
+            access_token: response.token,
+            refresh_token: String::new(),
+            expires_at: DateTime::from_timestamp(response.expires_at, 0).unwrap_or_else(Utc::now),
+            enterprise_url: None,
+            project_id: None,
+        };
+        self.token_store.save(token.clone()).await?;
+        Ok(token)
+    }
+}
+
+// Copilot editor plugins keep their config in ~/.config/github-copilot, or
+// %LOCALAPPDATA%\github-copilot on Windows.
+fn editor_plugin_dir() -> Option<PathBuf> {
+    if cfg!(windows) {
+        return dirs::data_local_dir().map(|dir| dir.join("github-copilot"));
+    }
+    std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))
+        .map(|dir| dir.join("github-copilot"))
+}
+
+// Both files map "github.com[:<client id>]" to `{ "oauth_token": ... }`.
+fn editor_plugin_token() -> Option<String> {
+    let dir = editor_plugin_dir()?;
+    ["apps.json", "hosts.json"].iter().find_map(|file| {
+        let content = std::fs::read_to_string(dir.join(file)).ok()?;
+        let hosts: serde_json::Map<String, serde_json::Value> = serde_json::from_str(&content).ok()?;
+        hosts
+            .iter()
+            .filter(|(host, _)| host.starts_with("github.com"))
+            .find_map(|(_, entry)| entry.get("oauth_token")?.as_str().map(str::to_string))
+    })
+}
+
+fn gh_cli_token() -> Option<String> {
+    let output = std::process::Command::new("gh").args(["auth", "token"]).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let token = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (!token.is_empty()).then_some(token)
+}
@@ -1,22 +1,29 @@
 use axum::{
     extract::{Path, Query, State},
-    response::{Html, IntoResponse, Redirect, Response},
+    response::{Html, Redirect},
+    Json,
 };
 use oauth2::{
     basic::BasicClient,
-    reqwest::async_http_client, // Reverted import
-    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, RedirectUrl, Scope,
-    TokenResponse, TokenUrl,
+    AuthUrl, AuthorizationCode, ClientId, ClientSecret, CsrfToken, EndpointNotSet, EndpointSet, PkceCodeChallenge,
+    PkceCodeVerifier, RedirectUrl, Scope, TokenResponse, TokenUrl,
 };
 use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::Instant;
 use tracing::{error, info};
 use url::Url;
 use chrono::Utc;
 use anyhow::Result; // Use anyhow::Result for error handling in the plugin handlers
+use mcp_errors::{ErrorCode, McpToolError};
 
-use crate::oauth::{OAuthClient, OAuthConfig};
-use crate::token_store::{OAuthToken, TokenStore};
+use crate::admin;
+use crate::oauth::{DeviceCode, DevicePoll, OAuthClient, OAuthConfig};
+use crate::providers::{self, ProviderInfo};
+use crate::token_store::{AuthFlowMetrics, OAuthToken, TokenStore};
 
 // Placeholder for AppState relevant parts for the plugin
 // This will be replaced by an actual struct passed from the main app
@@ -26,6 +33,17 @@ pub struct PluginAppState {
     pub token_store: TokenStore,
     pub oauth_configs: Arc<tokio::sync::RwLock<std::collections::HashMap<String, OAuthConfig>>>,
     pub public_url: Url,
+    /// Device-code sign-ins in progress, by provider
+    pub device_auths: Arc<tokio::sync::RwLock<HashMap<String, PendingDeviceAuth>>>,
+}
+
+/// A device-code sign-in waiting for the user. The device code stays on the
+/// server; clients only see the user code.
+#[derive(Debug, Clone)]
+pub struct PendingDeviceAuth {
+    code: DeviceCode,
+    expires_at: Instant,
+    next_poll_at: Instant,
 }
 
 
@@ -54,24 +72,27 @@ pub async fn oauth_start(
         .await
         .get(&provider)
         .cloned()
-        .ok_or_else(|| anyhow::anyhow!("OAuth provider {} not found", provider))?; // Use anyhow
+        .ok_or_else(|| McpToolError::new(ErrorCode::OAuthProviderNotFound, format!("OAuth provider {} not found", provider)))?;
 
-    let client = create_oauth_client(config, plugin_app_state.public_url.clone(), plugin_app_state.token_store.clone()).await?;
+    let client = create_oauth_client(config, plugin_app_state.public_url.clone())?;
 
+    // S256 PKCE: the verifier stays with the CSRF state until the callback.
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
     let (authorize_url, csrf_state) = client
         .authorize_url(CsrfToken::new_random)
+        .set_pkce_challenge(pkce_challenge)
         .add_scope(Scope::new("openid".to_string()))
         .add_scope(Scope::new("email".to_string()))
         .add_scope(Scope::new("profile".to_string()))
         .add_extra_param("access_type", "offline")
         .add_extra_param("prompt", "consent")
         .add_extra_param("provider", &provider)
-        .build() // Added .build()
         .url();
 
     plugin_app_state
         .token_store
-        .save_csrf_token(provider, csrf_state.secret().to_string());
+        .save_csrf_token(provider, csrf_state.secret().to_string(), pkce_verifier.secret().to_string())
+        .await?;
 
     Ok(Redirect::to(authorize_url.as_str()))
 }
@@ -84,10 +105,13 @@ pub async fn oauth_callback(
 ) -> Result<Html<String>, anyhow::Error> { // Use anyhow::Error
     info!("OAuth callback received");
 
-    let provider = plugin_app_state
+    // Taking the state makes it single-use, even if the exchange fails.
+    let pending = plugin_app_state
         .token_store
-        .get_csrf_token_provider(&state)
-        .ok_or_else(|| anyhow::anyhow!("Invalid or expired CSRF token".to_string()))?; // Use anyhow
+        .retrieve_csrf_token(&state)
+        .await
+        .ok_or_else(|| McpToolError::new(ErrorCode::OAuthInvalidState, "Invalid or expired CSRF token"))?;
+    let provider = pending.provider_id;
 
     let config = plugin_app_state
         .oauth_configs
@@ -95,26 +119,20 @@ pub async fn oauth_callback(
         .await
         .get(&provider)
         .cloned()
-        .ok_or_else(|| anyhow::anyhow!("OAuth provider {} not found", provider))?; // Use anyhow
+        .ok_or_else(|| McpToolError::new(ErrorCode::OAuthProviderNotFound, format!("OAuth provider {} not found", provider)))?;
 
-    let csrf_token = plugin_app_state
-        .token_store
-        .retrieve_csrf_token(&state)
-        .ok_or_else(|| anyhow::anyhow!("CSRF token not found or expired".to_string()))?; // Use anyhow
+    let client = create_oauth_client(config, plugin_app_state.public_url.clone())?;
 
-    if csrf_token != state {
-        return Err(anyhow::anyhow!("CSRF token mismatch".to_string())); // Use anyhow
+    let mut exchange = client.exchange_code(AuthorizationCode::new(code));
+    if !pending.pkce_verifier.is_empty() {
+        exchange = exchange.set_pkce_verifier(PkceCodeVerifier::new(pending.pkce_verifier));
     }
-
-    let client = create_oauth_client(config, plugin_app_state.public_url.clone(), plugin_app_state.token_store.clone()).await?;
-
-    let token_result = client
-        .exchange_code(AuthorizationCode::new(code))
-        .request_async(async_http_client)
+    let token_result = exchange
+        .request_async(&http_client()?)
         .await
-        .map_err(|e| anyhow::anyhow!("Failed to exchange code for token: {}", e))?; // Use anyhow
+        .map_err(|e| McpToolError::new(ErrorCode::OAuthExchangeFailed, format!("Failed to exchange code for token: {}", e)))?;
 
-    info!("Successfully authenticated, access token: {}", token_result.access_token().secret());
+    info!("Successfully authenticated with provider: {}", provider);
 
     let oauth_token = OAuthToken {
         provider_id: provider.clone(),
@@ -124,9 +142,129 @@ pub async fn oauth_callback(
         enterprise_url: None,
         project_id: None,
     };
-    plugin_app_state.token_store.save(oauth_token)?;
+    let expires_at = oauth_token.expires_at;
+    plugin_app_state.token_store.save(oauth_token).await?;
+
+    let mut context = tera::Context::new();
+    context.insert("name", &providers::preset(&provider).map_or(provider.as_str(), |preset| preset.name));
+    context.insert("expires_at", &expires_at);
+    admin::render("logged_in.html", &context)
+}
+
+// Device-code start handler. Needs no redirect URL, so it works on headless
+// servers: the user opens the verification URL on any device and enters the code.
+pub async fn start_device_auth(
+    Path(provider): Path<String>,
+    State(plugin_app_state): State<Arc<PluginAppState>>,
+) -> Result<Json<Value>, anyhow::Error> {
+    info!("Device sign-in started for provider: {}", provider);
+
+    let config = provider_config(&plugin_app_state, &provider).await?;
+    let client = OAuthClient::new(config, plugin_app_state.token_store.clone());
+    let code = client.start_device_authorization().await?;
+
+    let response = json!({
+        "user_code": code.user_code,
+        "verification_uri": code.verification_uri,
+        "verification_uri_complete": code.verification_uri_complete,
+        "expires_in": code.expires_in,
+        "interval": code.interval,
+    });
+    let now = Instant::now();
+    plugin_app_state.device_auths.write().await.insert(
+        provider,
+        PendingDeviceAuth {
+            expires_at: now + Duration::from_secs(code.expires_in),
+            next_poll_at: now,
+            code,
+        },
+    );
+    Ok(Json(response))
+}
+
+// Device-code poll handler. Returns `pending` until the user approves the code,
+// then `complete` once the token is stored. Polls faster than the provider's
+// interval are answered from here without asking the provider.
+pub async fn poll_device_auth(
+    Path(provider): Path<String>,
+    State(plugin_app_state): State<Arc<PluginAppState>>,
+) -> Result<Json<Value>, anyhow::Error> {
+    let (device_code, interval) = {
+        let mut device_auths = plugin_app_state.device_auths.write().await;
+        let pending = device_auths.get_mut(&provider).ok_or_else(|| {
+            McpToolError::new(ErrorCode::OAuthInvalidState, format!("No device sign-in in progress for {}", provider))
+        })?;
+
+        let now = Instant::now();
+        if now >= pending.expires_at {
+            device_auths.remove(&provider);
+            return Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, "The device code expired; start the sign-in again").into());
+        }
+        if now < pending.next_poll_at {
+            return Ok(Json(json!({ "status": "pending", "interval": pending.code.interval })));
+        }
+        // Claims this poll slot, so concurrent callers wait instead of polling too.
+        pending.next_poll_at = now + Duration::from_secs(pending.code.interval);
+        (pending.code.device_code.clone(), pending.code.interval)
+    };
+
+    // The lock is not held across the round trip to the provider.
+    let config = provider_config(&plugin_app_state, &provider).await?;
+    let client = OAuthClient::new(config, plugin_app_state.token_store.clone());
+    let outcome = client.poll_device_authorization(&provider, &device_code).await;
 
-    Ok(Html("<h1>Successfully logged in!</h1>".to_string()))
+    let mut device_auths = plugin_app_state.device_auths.write().await;
+    // The sign-in may have been restarted with a new code while polling.
+    let is_current = device_auths
+        .get(&provider)
+        .is_some_and(|pending| pending.code.device_code == device_code);
+    match outcome {
+        Ok(DevicePoll::Pending) => Ok(Json(json!({ "status": "pending", "interval": interval }))),
+        Ok(DevicePoll::SlowDown { interval }) => {
+            if let Some(pending) = device_auths.get_mut(&provider).filter(|_| is_current) {
+                pending.code.interval = interval;
+                pending.next_poll_at = Instant::now() + Duration::from_secs(interval);
+            }
+            Ok(Json(json!({ "status": "pending", "interval": interval })))
+        }
+        Ok(DevicePoll::Complete(token)) => {
+            if is_current {
+                device_auths.remove(&provider);
+            }
+            info!("Device sign-in completed for provider: {}", provider);
+            Ok(Json(json!({ "status": "complete", "expires_at": token.expires_at })))
+        }
+        Err(e) => {
+            if is_current {
+                device_auths.remove(&provider);
+            }
+            error!("Device sign-in failed for provider {}: {}", provider, e);
+            Err(e)
+        }
+    }
+}
+
+async fn provider_config(plugin_app_state: &PluginAppState, provider: &str) -> Result<OAuthConfig, anyhow::Error> {
+    plugin_app_state
+        .oauth_configs
+        .read()
+        .await
+        .get(provider)
+        .cloned()
+        .ok_or_else(|| McpToolError::new(ErrorCode::OAuthProviderNotFound, format!("OAuth provider {} not found", provider)).into())
+}
+
+// Lists the configured providers, which flows each supports, and whether a
+// token is stored for it
+pub async fn list_providers(State(plugin_app_state): State<Arc<PluginAppState>>) -> Json<Vec<ProviderInfo>> {
+    let configs = plugin_app_state.oauth_configs.read().await;
+    let tokens = plugin_app_state.token_store.all().await;
+    Json(providers::describe(&configs, |id| tokens.contains_key(id)))
+}
+
+// Reports outstanding and finished authorization-code logins
+pub async fn auth_flow_metrics(State(plugin_app_state): State<Arc<PluginAppState>>) -> Json<AuthFlowMetrics> {
+    Json(plugin_app_state.token_store.auth_flow_metrics().await)
 }
 
 // Generic login page (if needed)
@@ -136,12 +274,24 @@ pub async fn oauth_login() -> Html<String> {
 
 // Generic logout handler
 pub async fn oauth_logout(State(plugin_app_state): State<Arc<PluginAppState>>) -> Result<Redirect, anyhow::Error> { // Use anyhow::Error
-    plugin_app_state.token_store.remove_all_tokens()?;
+    plugin_app_state.token_store.remove_all_tokens().await?;
     Ok(Redirect::to("/admin"))
 }
 
-// Helper to create OAuth client - now takes public_url and token_store
-async fn create_oauth_client(config: OAuthConfig, public_url: Url, token_store: TokenStore) -> Result<BasicClient, anyhow::Error> {
+// An authorization-code client: auth and token endpoints set, the rest not.
+type CodeClient = BasicClient<EndpointSet, EndpointNotSet, EndpointNotSet, EndpointNotSet, EndpointSet>;
+
+// The token request must not follow redirects, or the code could leak to
+// wherever the token endpoint points.
+fn http_client() -> Result<oauth2::reqwest::Client, anyhow::Error> {
+    oauth2::reqwest::ClientBuilder::new()
+        .redirect(oauth2::reqwest::redirect::Policy::none())
+        .build()
+        .map_err(|e| anyhow::anyhow!("Failed to build HTTP client: {}", e))
+}
+
+// Helper to create OAuth client, redirecting back to `public_url`
+fn create_oauth_client(config: OAuthConfig, public_url: Url) -> Result<CodeClient, anyhow::Error> {
     let client_id = ClientId::new(config.client_id);
     let client_secret = config.client_secret.map(ClientSecret::new);
     let auth_url = AuthUrl::new(config.auth_url)
@@ -155,11 +305,99 @@ async fn create_oauth_client(config: OAuthConfig, public_url: Url, token_store:
     let redirect_url = RedirectUrl::new(redirect_url.to_string())
         .map_err(|e| anyhow::anyhow!("Invalid RedirectUrl: {}", e))?; // Use anyhow
 
-    let client = BasicClient::new(client_id) // Updated BasicClient::new
-        .set_client_secret(client_secret)
-        .set_auth_url(auth_url)
-        .set_token_url(Some(token_url)) // token_url is not optional in oauth2 v5
+    let client = BasicClient::new(client_id)
+        .set_auth_uri(auth_url)
+        .set_token_uri(token_url)
         .set_redirect_uri(redirect_url);
 
-    Ok(client)
-}
\ No newline at end of file
+    Ok(match client_secret {
+        Some(secret) => client.set_client_secret(secret),
+        None => client,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, response::IntoResponse, routing::post, Form, Router};
+    use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+    use sha2::{Digest, Sha256};
+    use tempfile::TempDir;
+    use tokio::sync::{Mutex, RwLock};
+
+    type Received = Arc<Mutex<Option<HashMap<String, String>>>>;
+
+    // A token endpoint that remembers the form it was sent.
+    async fn mock_token_endpoint() -> (String, Received) {
+        let received: Received = Arc::new(Mutex::new(None));
+        let recorded = received.clone();
+        let app = Router::new().route(
+            "/token",
+            post(move |Form(form): Form<HashMap<String, String>>| async move {
+                *recorded.lock().await = Some(form);
+                Json(json!({
+                    "access_token": "access-123",
+                    "token_type": "bearer",
+                    "expires_in": 3600,
+                    "refresh_token": "refresh-456",
+                }))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (format!("http://{}/token", addr), received)
+    }
+
+    #[tokio::test]
+    async fn test_code_flow_uses_pkce() {
+        let (token_url, received) = mock_token_endpoint().await;
+        let temp_dir = TempDir::new().unwrap();
+        let token_store = TokenStore::new(temp_dir.path().join("tokens.json")).await.unwrap();
+        let config = OAuthConfig {
+            client_id: "client".to_string(),
+            client_secret: None,
+            auth_url: "https://auth.example.com/authorize".to_string(),
+            token_url,
+            redirect_uri: String::new(),
+            scopes: Vec::new(),
+            device_auth_url: None,
+        };
+        let state = Arc::new(PluginAppState {
+            token_store: token_store.clone(),
+            oauth_configs: Arc::new(RwLock::new(HashMap::from([("test".to_string(), config)]))),
+            public_url: Url::parse("http://127.0.0.1:8080").unwrap(),
+            device_auths: Arc::new(RwLock::new(HashMap::new())),
+        });
+
+        let redirect = oauth_start(Path("test".to_string()), State(state.clone())).await.unwrap().into_response();
+        let location = Url::parse(redirect.headers()[header::LOCATION].to_str().unwrap()).unwrap();
+        let params: HashMap<String, String> = location.query_pairs().into_owned().collect();
+        assert_eq!(params["code_challenge_method"], "S256");
+
+        let callback = |state: Arc<PluginAppState>| {
+            oauth_callback(
+                Query(AuthCode {
+                    code: "code-789".to_string(),
+                    state: params["state"].clone(),
+                }),
+                Query(AuthState { state: params["state"].clone() }),
+                State(state),
+            )
+        };
+        let Html(page) = callback(state.clone()).await.unwrap();
+        assert!(page.contains("Signed in to test"));
+
+        // The exchange proves it holds the verifier behind the challenge.
+        let form = received.lock().await.take().unwrap();
+        assert_eq!(form["grant_type"], "authorization_code");
+        assert_eq!(form["code"], "code-789");
+        let challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(form["code_verifier"].as_bytes()));
+        assert_eq!(challenge, params["code_challenge"]);
+        assert_eq!(token_store.get("test").await.unwrap().access_token, "access-123");
+
+        // The state is single-use.
+        assert!(callback(state).await.is_err());
+        assert!(received.lock().await.is_none());
+    }
+}
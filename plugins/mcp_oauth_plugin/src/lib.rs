@@ -1,3 +1,7 @@
 pub mod oauth;
 pub mod token_store;
-pub mod handlers;
\ No newline at end of file
+pub mod handlers;
+pub mod admin;
+pub mod github_copilot;
+pub mod refresher;
+pub mod providers;
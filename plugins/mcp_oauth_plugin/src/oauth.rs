@@ -3,7 +3,8 @@ use rand::Rng;
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 use anyhow::{Context, Result, anyhow};
-use chrono::{DateTime, Utc};
+use chrono::Utc;
+use mcp_errors::{ErrorCode, McpToolError};
 
 use crate::token_store::{OAuthToken, TokenStore}; // Updated import
 
@@ -20,13 +21,13 @@ impl PKCEVerifier {
         // Generate random verifier (43-128 characters)
         let mut rng = rand::thread_rng();
         let random_bytes: Vec<u8> = (0..32).map(|_| rng.gen()).collect();
-        let verifier = URL_SAFE_NO_PAD.encode(&random_bytes);
+        let verifier = URL_SAFE_NO_PAD.encode(random_bytes);
 
         // Generate challenge (SHA256 of verifier)
         let mut hasher = Sha256::new();
         hasher.update(verifier.as_bytes());
         let challenge_bytes = hasher.finalize();
-        let challenge = URL_SAFE_NO_PAD.encode(&challenge_bytes);
+        let challenge = URL_SAFE_NO_PAD.encode(challenge_bytes);
 
         Self { verifier, challenge }
     }
@@ -40,21 +41,16 @@ pub struct AuthorizationUrl {
 }
 
 /// OAuth authentication type
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 #[serde(rename_all = "lowercase")]
 pub enum AuthType {
     /// API key authentication
+    #[default]
     ApiKey,
     /// OAuth 2.0 authentication
     OAuth,
 }
 
-impl Default for AuthType {
-    fn default() -> Self {
-        AuthType::ApiKey
-    }
-}
-
 
 /// OAuth provider configuration
 #[derive(Debug, Clone, Deserialize, Serialize)]
@@ -65,6 +61,10 @@ pub struct OAuthConfig {
     pub token_url: String,
     pub redirect_uri: String,
     pub scopes: Vec<String>,
+    /// Device authorization endpoint, for providers that support the
+    /// device-code grant (RFC 8628)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_auth_url: Option<String>,
 }
 
 impl OAuthConfig {
@@ -81,6 +81,7 @@ impl OAuthConfig {
                 "user:profile".to_string(),
                 "user:inference".to_string(),
             ],
+            device_auth_url: None,
         }
     }
 
@@ -111,6 +112,21 @@ impl OAuthConfig {
                 "email".to_string(),
                 "offline_access".to_string(),
             ],
+            device_auth_url: None,
+        }
+    }
+
+    /// GitHub OAuth app using the device-code grant, which needs no redirect
+    /// URL. GitHub OAuth app tokens don't expire and have no refresh token.
+    pub fn github(client_id: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            client_secret: None,
+            auth_url: "https://github.com/login/oauth/authorize".to_string(),
+            token_url: "https://github.com/login/oauth/access_token".to_string(),
+            redirect_uri: String::new(),
+            scopes: vec!["read:user".to_string()],
+            device_auth_url: Some("https://github.com/login/device/code".to_string()),
         }
     }
 
@@ -138,10 +154,42 @@ impl OAuthConfig {
                 "https://www.googleapis.com/auth/userinfo.email".to_string(),
                 "https://www.googleapis.com/auth/userinfo.profile".to_string(),
             ],
+            device_auth_url: None,
         })
     }
 }
 
+/// A pending device-code sign-in. Show `user_code` and `verification_uri`
+/// to the user, then poll with the `device_code`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCode {
+    pub device_code: String,
+    pub user_code: String,
+    pub verification_uri: String,
+    /// `verification_uri` with the user code filled in, if the provider offers it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verification_uri_complete: Option<String>,
+    pub expires_in: u64,
+    /// Seconds to wait between polls
+    #[serde(default = "default_device_poll_interval")]
+    pub interval: u64,
+}
+
+fn default_device_poll_interval() -> u64 {
+    5
+}
+
+/// Result of one device-code poll
+#[derive(Debug, Clone)]
+pub enum DevicePoll {
+    /// The user hasn't approved the code yet
+    Pending,
+    /// Polling too fast; wait this many seconds between polls from now on
+    SlowDown { interval: u64 },
+    /// Approved; the token has been saved
+    Complete(OAuthToken),
+}
+
 /// OAuth client for handling authentication flows
 pub struct OAuthClient {
     config: OAuthConfig,
@@ -330,7 +378,9 @@ impl OAuthClient {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Token exchange failed: {} - {}", status, body));
+            return Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, format!("Token exchange failed: {} - {}", status, body))
+                .with_details(serde_json::json!({ "status": status.as_u16() }))
+                .into());
         }
 
         let token_response: TokenResponse = response.json().await
@@ -348,15 +398,15 @@ impl OAuthClient {
         };
 
         // Save token
-        self.token_store.save(token.clone())?;
+        self.token_store.save(token.clone()).await?;
 
         Ok(token)
     }
 
     /// Refresh an access token
     pub async fn refresh_token(&self, provider_id: &str) -> Result<OAuthToken> {
-        let existing_token = self.token_store.get(provider_id)
-            .context("No token found for provider")?;
+        let existing_token = self.token_store.get(provider_id).await
+            .ok_or_else(|| McpToolError::new(ErrorCode::OAuthTokenNotFound, format!("No token found for provider {}", provider_id)))?;
 
         #[derive(Deserialize)]
         struct TokenResponse {
@@ -427,7 +477,9 @@ impl OAuthClient {
         if !response.status().is_success() {
             let status = response.status();
             let body = response.text().await.unwrap_or_default();
-            return Err(anyhow!("Token refresh failed: {} - {}", status, body));
+            return Err(McpToolError::new(ErrorCode::OAuthRefreshFailed, format!("Token refresh failed: {} - {}", status, body))
+                .with_details(serde_json::json!({ "status": status.as_u16() }))
+                .into());
         }
 
         // Debug: Log the raw response body
@@ -451,7 +503,7 @@ impl OAuthClient {
         };
 
         // Save refreshed token
-        self.token_store.save(token.clone())?;
+        self.token_store.save(token.clone()).await?;
 
         Ok(token)
     }
@@ -461,16 +513,18 @@ impl OAuthClient {
     pub async fn load_code_assist(&self, access_token: &str) -> Result<String> {
         #[derive(Serialize)]
         struct LoadCodeAssistRequest {
-            #[serde(skip_serializing_if = "Option::is_none")]
-            cloudaicompanionProject: Option<String>,
+            #[serde(rename = "cloudaicompanionProject", skip_serializing_if = "Option::is_none")]
+            cloudaicompanion_project: Option<String>,
             metadata: ClientMetadata,
         }
 
         #[derive(Serialize)]
         struct ClientMetadata {
-            ideType: String,
+            #[serde(rename = "ideType")]
+            ide_type: String,
             platform: String,
-            pluginType: String,
+            #[serde(rename = "pluginType")]
+            plugin_type: String,
         }
 
         #[derive(Deserialize)]
@@ -491,11 +545,11 @@ impl OAuthClient {
         }
 
         let request = LoadCodeAssistRequest {
-            cloudaicompanionProject: project_id.clone(),
+            cloudaicompanion_project: project_id.clone(),
             metadata: ClientMetadata {
-                ideType: "IDE_UNSPECIFIED".to_string(),
+                ide_type: "IDE_UNSPECIFIED".to_string(),
                 platform: "PLATFORM_UNSPECIFIED".to_string(),
-                pluginType: "GEMINI".to_string(),
+                plugin_type: "GEMINI".to_string(),
             },
         };
 
@@ -540,8 +594,8 @@ impl OAuthClient {
 
     /// Get a valid access token (refreshing if needed)
     pub async fn get_valid_token(&self, provider_id: &str) -> Result<String> {
-        let token = self.token_store.get(provider_id)
-            .context("No token found for provider")?;
+        let token = self.token_store.get(provider_id).await
+            .ok_or_else(|| McpToolError::new(ErrorCode::OAuthTokenNotFound, format!("No token found for provider {}", provider_id)))?;
 
         if token.needs_refresh() {
             let refreshed = self.refresh_token(provider_id).await?;
@@ -579,6 +633,98 @@ impl OAuthClient {
 
         Ok(api_key_response.raw_key)
     }
+
+    /// Starts a device-code sign-in (RFC 8628)
+    pub async fn start_device_authorization(&self) -> Result<DeviceCode> {
+        let device_auth_url = self.config.device_auth_url.as_deref().ok_or_else(|| {
+            McpToolError::new(ErrorCode::InvalidArgument, "This provider does not support device sign-in")
+        })?;
+        let scope = self.config.scopes.join(" ");
+        let response = self.http_client
+            .post(device_auth_url)
+            .header("Accept", "application/json")
+            .form(&[("client_id", self.config.client_id.as_str()), ("scope", scope.as_str())])
+            .send()
+            .await
+            .context("Failed to start device sign-in")?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            return Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, format!("Device sign-in failed: {} - {}", status, body))
+                .with_details(serde_json::json!({ "status": status.as_u16() }))
+                .into());
+        }
+
+        response.json().await
+            .context("Failed to parse device code response")
+    }
+
+    /// Asks once whether the user has approved `device_code`, saving the
+    /// token under `provider_id` when they have
+    pub async fn poll_device_authorization(&self, provider_id: &str, device_code: &str) -> Result<DevicePoll> {
+        #[derive(Deserialize)]
+        struct PollResponse {
+            access_token: Option<String>,
+            refresh_token: Option<String>,
+            expires_in: Option<i64>,
+            error: Option<String>,
+            error_description: Option<String>,
+            interval: Option<u64>,
+        }
+
+        let mut form = vec![
+            ("grant_type", "urn:ietf:params:oauth:grant-type:device_code"),
+            ("client_id", self.config.client_id.as_str()),
+            ("device_code", device_code),
+        ];
+        if let Some(client_secret) = &self.config.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+        // Pending polls come back as 400s with an `error` field, so the
+        // status alone says nothing.
+        let response: PollResponse = self.http_client
+            .post(&self.config.token_url)
+            .header("Accept", "application/json")
+            .form(&form)
+            .send()
+            .await
+            .context("Failed to poll device sign-in")?
+            .json()
+            .await
+            .context("Failed to parse device token response")?;
+
+        if let Some(access_token) = response.access_token {
+            let token = OAuthToken {
+                provider_id: provider_id.to_string(),
+                access_token,
+                refresh_token: response.refresh_token.unwrap_or_default(),
+                // No expiry means the token doesn't expire (GitHub OAuth apps)
+                expires_at: Utc::now() + response.expires_in
+                    .map_or_else(|| chrono::Duration::days(365 * 100), chrono::Duration::seconds),
+                enterprise_url: None,
+                project_id: None,
+            };
+            self.token_store.save(token.clone()).await?;
+            return Ok(DevicePoll::Complete(token));
+        }
+
+        match response.error.as_deref() {
+            Some("authorization_pending") => Ok(DevicePoll::Pending),
+            Some("slow_down") => Ok(DevicePoll::SlowDown { interval: response.interval.unwrap_or(10) }),
+            Some(error) => {
+                let message = match error {
+                    "expired_token" => "The device code expired; start the sign-in again".to_string(),
+                    "access_denied" => "The sign-in was denied".to_string(),
+                    _ => format!("Device sign-in failed: {}", response.error_description.as_deref().unwrap_or(error)),
+                };
+                Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, message)
+                    .with_details(serde_json::json!({ "error": error }))
+                    .into())
+            }
+            None => Err(anyhow!("Device sign-in returned neither a token nor an error")),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -597,10 +743,10 @@ mod tests {
         assert_ne!(pkce.verifier, pkce.challenge);
     }
 
-    #[test]
-    fn test_authorization_url() {
+    #[tokio::test]
+    async fn test_authorization_url() {
         let config = OAuthConfig::anthropic();
-        let token_store = TokenStore::new(std::env::temp_dir().join("test_tokens.json")).unwrap();
+        let token_store = TokenStore::new(std::env::temp_dir().join("test_tokens.json")).await.unwrap();
         let client = OAuthClient::new(config, token_store);
 
         let auth_url = client.get_authorization_url();
@@ -0,0 +1,163 @@
+//! Built-in OAuth provider presets.
+//!
+//! Deployments start from [`ProviderRegistry::with_presets`] and only add or
+//! override the providers they need, instead of writing auth and token URLs
+//! into `oauth_configs` by hand. Presets that need credentials from the
+//! environment are skipped when those variables are unset.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tokio::sync::RwLock;
+
+use crate::github_copilot::{self, GITHUB_PROVIDER_ID};
+use crate::oauth::OAuthConfig;
+
+/// A provider the plugin knows how to configure.
+pub struct ProviderPreset {
+    pub id: &'static str,
+    pub name: &'static str,
+    build: fn() -> Result<OAuthConfig>,
+}
+
+impl ProviderPreset {
+    /// The preset's config, or an error naming the missing setting.
+    pub fn config(&self) -> Result<OAuthConfig> {
+        (self.build)()
+    }
+}
+
+fn env(name: &str) -> Result<String> {
+    std::env::var(name)
+        .ok()
+        .filter(|value| !value.is_empty())
+        .with_context(|| format!("{} is not set", name))
+}
+
+fn github() -> Result<OAuthConfig> {
+    Ok(OAuthConfig::github(env("GITHUB_OAUTH_CLIENT_ID")?))
+}
+
+fn github_copilot() -> Result<OAuthConfig> {
+    let mut config = OAuthConfig::github(github_copilot::CLIENT_ID);
+    // The Copilot editor app has no redirect URL of ours registered.
+    config.auth_url = String::new();
+    Ok(config)
+}
+
+fn google() -> Result<OAuthConfig> {
+    Ok(OAuthConfig {
+        client_id: env("GOOGLE_OAUTH_CLIENT_ID")?,
+        client_secret: Some(env("GOOGLE_OAUTH_CLIENT_SECRET")?),
+        auth_url: "https://accounts.google.com/o/oauth2/v2/auth".to_string(),
+        token_url: "https://oauth2.googleapis.com/token".to_string(),
+        redirect_uri: String::new(),
+        scopes: vec!["openid".to_string(), "email".to_string(), "profile".to_string()],
+        device_auth_url: Some("https://oauth2.googleapis.com/device/code".to_string()),
+    })
+}
+
+fn anthropic() -> Result<OAuthConfig> {
+    Ok(OAuthConfig::anthropic())
+}
+
+fn anthropic_console() -> Result<OAuthConfig> {
+    Ok(OAuthConfig::anthropic_console())
+}
+
+fn openai_codex() -> Result<OAuthConfig> {
+    Ok(OAuthConfig::openai_codex())
+}
+
+const PRESETS: &[ProviderPreset] = &[
+    ProviderPreset { id: "github", name: "GitHub", build: github },
+    ProviderPreset { id: GITHUB_PROVIDER_ID, name: "GitHub Copilot", build: github_copilot },
+    ProviderPreset { id: "google", name: "Google", build: google },
+    ProviderPreset { id: "gemini", name: "Google Gemini", build: OAuthConfig::gemini },
+    ProviderPreset { id: "claude-max", name: "Anthropic (Claude Pro/Max)", build: anthropic },
+    ProviderPreset { id: "anthropic-console", name: "Anthropic Console", build: anthropic_console },
+    ProviderPreset { id: "openai-codex", name: "OpenAI (ChatGPT Plus/Pro)", build: openai_codex },
+];
+
+/// Every built-in preset.
+pub fn presets() -> &'static [ProviderPreset] {
+    PRESETS
+}
+
+pub fn preset(id: &str) -> Option<&'static ProviderPreset> {
+    PRESETS.iter().find(|preset| preset.id == id)
+}
+
+/// A configured provider as `list_providers` reports it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderInfo {
+    pub id: String,
+    pub name: String,
+    /// Supports the authorization-code redirect flow
+    pub authorization_code: bool,
+    /// Supports the device-code flow
+    pub device_code: bool,
+    /// A token is stored for this provider
+    pub signed_in: bool,
+}
+
+/// The configured providers, keyed by provider ID.
+#[derive(Debug, Clone, Default)]
+pub struct ProviderRegistry {
+    configs: HashMap<String, OAuthConfig>,
+}
+
+impl ProviderRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every preset whose settings are available.
+    pub fn with_presets() -> Self {
+        let mut registry = Self::new();
+        for preset in PRESETS {
+            match preset.config() {
+                Ok(config) => registry.insert(preset.id, config),
+                Err(e) => tracing::debug!("Skipping OAuth preset {}: {:#}", preset.id, e),
+            }
+        }
+        registry
+    }
+
+    /// Adds a provider, replacing any preset with the same ID.
+    pub fn insert(&mut self, id: impl Into<String>, config: OAuthConfig) {
+        self.configs.insert(id.into(), config);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&OAuthConfig> {
+        self.configs.get(id)
+    }
+
+    pub fn configs(&self) -> &HashMap<String, OAuthConfig> {
+        &self.configs
+    }
+
+    /// The configs in the shape `PluginAppState::oauth_configs` takes.
+    pub fn into_shared(self) -> Arc<RwLock<HashMap<String, OAuthConfig>>> {
+        Arc::new(RwLock::new(self.configs))
+    }
+}
+
+/// Describes each provider in `configs`, sorted by ID. `signed_in` says
+/// whether a token is stored for it.
+pub fn describe(configs: &HashMap<String, OAuthConfig>, signed_in: impl Fn(&str) -> bool) -> Vec<ProviderInfo> {
+    let mut providers: Vec<ProviderInfo> = configs
+        .iter()
+        .map(|(id, config)| ProviderInfo {
+            id: id.clone(),
+            name: preset(id).map_or_else(|| id.clone(), |preset| preset.name.to_string()),
+            authorization_code: !config.auth_url.is_empty(),
+            device_code: config.device_auth_url.is_some(),
+            signed_in: signed_in(id),
+        })
+        .collect();
+    providers.sort_by(|a, b| a.id.cmp(&b.id));
+    providers
+}
@@ -0,0 +1,213 @@
+//! Background token refresh.
+//!
+//! `TokenRefresher` checks the [`TokenStore`] every minute and exchanges the
+//! refresh token of any token that expires within its provider's lead time,
+//! so callers find a valid access token instead of refreshing on demand. A
+//! provider whose refresh fails is retried with exponential backoff, and every
+//! outcome is logged and broadcast as a [`RefreshEvent`].
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use serde::Serialize;
+use tokio::sync::broadcast;
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::oauth::{OAuthClient, OAuthConfig};
+use crate::token_store::{OAuthToken, TokenStore};
+
+/// How often stored tokens are checked.
+pub const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// How long before expiry a token is refreshed unless its provider sets
+/// otherwise. Longer than `OAuthToken::needs_refresh`'s buffer, so the
+/// background refresh normally wins.
+pub const DEFAULT_LEAD_TIME: Duration = Duration::from_secs(10 * 60);
+
+const MIN_RETRY_DELAY: Duration = Duration::from_secs(30);
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(30 * 60);
+const EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Outcome of a background refresh.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RefreshEvent {
+    Refreshed {
+        provider_id: String,
+        expires_at: DateTime<Utc>,
+    },
+    Failed {
+        provider_id: String,
+        error: McpToolError,
+        /// Consecutive failures for this provider, including this one.
+        attempts: u32,
+        retry_in_secs: u64,
+    },
+}
+
+struct Provider {
+    config: OAuthConfig,
+    lead_time: Duration,
+}
+
+// Failure state of one provider.
+struct Backoff {
+    attempts: u32,
+    retry_at: Instant,
+}
+
+/// Refreshes tokens for the providers it was given configs for. Tokens of
+/// other providers, and tokens without a refresh token, are left alone.
+pub struct TokenRefresher {
+    token_store: TokenStore,
+    providers: HashMap<String, Provider>,
+    events: broadcast::Sender<RefreshEvent>,
+}
+
+impl TokenRefresher {
+    pub fn new(token_store: TokenStore) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            token_store,
+            providers: HashMap::new(),
+            events,
+        }
+    }
+
+    /// Refreshes `provider_id`'s token with `config`, `DEFAULT_LEAD_TIME`
+    /// before it expires.
+    pub fn with_provider(self, provider_id: impl Into<String>, config: OAuthConfig) -> Self {
+        self.with_provider_lead_time(provider_id, config, DEFAULT_LEAD_TIME)
+    }
+
+    /// Like `with_provider`, refreshing `lead_time` before expiry.
+    pub fn with_provider_lead_time(mut self, provider_id: impl Into<String>, config: OAuthConfig, lead_time: Duration) -> Self {
+        self.providers.insert(provider_id.into(), Provider { config, lead_time });
+        self
+    }
+
+    /// Receives an event for every refresh attempt made after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<RefreshEvent> {
+        self.events.subscribe()
+    }
+
+    /// Checks tokens now and then every `CHECK_INTERVAL` until the task is
+    /// aborted. Must be called inside a Tokio runtime.
+    pub fn spawn(self: Arc<Self>) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut backoff: HashMap<String, Backoff> = HashMap::new();
+            let mut interval = tokio::time::interval(CHECK_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.check(&mut backoff).await;
+            }
+        })
+    }
+
+    async fn check(&self, backoff: &mut HashMap<String, Backoff>) {
+        let tokens = self.token_store.all().await;
+        let now = Instant::now();
+        for (provider_id, token) in tokens {
+            let Some(provider) = self.providers.get(&provider_id) else {
+                continue;
+            };
+            if !is_due(&token, provider.lead_time) {
+                continue;
+            }
+            if backoff.get(&provider_id).is_some_and(|failed| failed.retry_at > now) {
+                continue;
+            }
+
+            let client = OAuthClient::new(provider.config.clone(), self.token_store.clone());
+            match client.refresh_token(&provider_id).await {
+                Ok(refreshed) => {
+                    backoff.remove(&provider_id);
+                    tracing::info!("Refreshed OAuth token for {}", provider_id);
+                    let _ = self.events.send(RefreshEvent::Refreshed {
+                        provider_id,
+                        expires_at: refreshed.expires_at,
+                    });
+                }
+                Err(e) => {
+                    let error = e.downcast::<McpToolError>().unwrap_or_else(|e| {
+                        McpToolError::new(ErrorCode::OAuthRefreshFailed, format!("{:#}", e))
+                    });
+                    let attempts = backoff.get(&provider_id).map_or(1, |failed| failed.attempts + 1);
+                    let delay = retry_delay(attempts);
+                    backoff.insert(
+                        provider_id.clone(),
+                        Backoff {
+                            attempts,
+                            retry_at: now + delay,
+                        },
+                    );
+                    tracing::warn!(
+                        "OAuth token refresh for {} failed (attempt {}, retrying in {}s): {}",
+                        provider_id,
+                        attempts,
+                        delay.as_secs(),
+                        error
+                    );
+                    let _ = self.events.send(RefreshEvent::Failed {
+                        provider_id,
+                        error,
+                        attempts,
+                        retry_in_secs: delay.as_secs(),
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Whether `token` should be refreshed now.
+fn is_due(token: &OAuthToken, lead_time: Duration) -> bool {
+    if token.refresh_token.is_empty() {
+        return false;
+    }
+    let lead_time = chrono::Duration::from_std(lead_time).unwrap_or(chrono::Duration::MAX);
+    Utc::now() + lead_time >= token.expires_at
+}
+
+// 30s, 1m, 2m, ... capped at 30 minutes.
+fn retry_delay(attempts: u32) -> Duration {
+    MIN_RETRY_DELAY
+        .saturating_mul(1 << attempts.saturating_sub(1).min(16))
+        .min(MAX_RETRY_DELAY)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn token(refresh_token: &str, expires_in: chrono::Duration) -> OAuthToken {
+        OAuthToken {
+            provider_id: "test".to_string(),
+            access_token: "access".to_string(),
+            refresh_token: refresh_token.to_string(),
+            expires_at: Utc::now() + expires_in,
+            enterprise_url: None,
+            project_id: None,
+        }
+    }
+
+    #[test]
+    fn test_is_due() {
+        let lead_time = Duration::from_secs(600);
+        assert!(is_due(&token("refresh", chrono::Duration::minutes(5)), lead_time));
+        assert!(!is_due(&token("refresh", chrono::Duration::hours(1)), lead_time));
+        // Nothing to exchange without a refresh token.
+        assert!(!is_due(&token("", chrono::Duration::minutes(5)), lead_time));
+    }
+
+    #[test]
+    fn test_retry_delay() {
+        assert_eq!(retry_delay(1), Duration::from_secs(30));
+        assert_eq!(retry_delay(2), Duration::from_secs(60));
+        assert_eq!(retry_delay(3), Duration::from_secs(120));
+        assert_eq!(retry_delay(50), MAX_RETRY_DELAY);
+    }
+}
@@ -1,12 +1,28 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::fs;
-use std::path::PathBuf;
-use std::sync::Arc; // Kept std::sync::Arc
-use tokio::sync::RwLock; // Changed from std::sync::RwLock
-use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
+use tokio::fs;
+use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use anyhow::{anyhow, Context, Result};
 use chrono::{DateTime, Utc};
 
+/// Keychain service token entries are stored under.
+const KEYRING_SERVICE: &str = "copilot_mcp_tool-oauth";
+/// Keychain entry listing the stored provider IDs, since keychains can't be
+/// enumerated portably.
+const KEYRING_INDEX: &str = "__providers__";
+
+/// How long a login may take between `oauth_start` and the callback, unless
+/// `MCP_OAUTH_STATE_TTL_SECS` says otherwise.
+pub const DEFAULT_CSRF_TTL: Duration = Duration::from_secs(10 * 60);
+/// How often `spawn_csrf_cleanup` drops expired states.
+pub const CSRF_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
 /// OAuth token information
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OAuthToken {
@@ -40,167 +56,453 @@ impl OAuthToken {
     }
 }
 
-/// Token storage - persists to JSON file
+/// Where tokens are persisted.
+#[async_trait]
+pub trait TokenStorage: Send + Sync + std::fmt::Debug {
+    /// Loads every stored token, keyed by provider ID.
+    async fn load(&self) -> Result<HashMap<String, OAuthToken>>;
+    /// Replaces the stored tokens with `tokens`.
+    async fn save(&self, tokens: &HashMap<String, OAuthToken>) -> Result<()>;
+}
+
+/// Tokens in a JSON file readable only by the owner.
+#[derive(Debug, Clone)]
+pub struct FileStorage {
+    path: PathBuf,
+}
+
+impl FileStorage {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+#[async_trait]
+impl TokenStorage for FileStorage {
+    async fn load(&self) -> Result<HashMap<String, OAuthToken>> {
+        let content = match fs::read_to_string(&self.path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(HashMap::new()),
+            Err(e) => return Err(e).context("Failed to read token file"),
+        };
+        serde_json::from_str(&content)
+            .context("Failed to parse token file")
+    }
+
+    async fn save(&self, tokens: &HashMap<String, OAuthToken>) -> Result<()> {
+        let json = serde_json::to_string_pretty(tokens)
+            .context("Failed to serialize tokens")?;
+        write_private(&self.path, json)
+            .await
+            .context("Failed to write token file")
+    }
+}
+
+// Writes `contents` to `path` and restricts it to the owner (0600).
+async fn write_private(path: &Path, contents: String) -> std::io::Result<()> {
+    fs::write(path, contents).await?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, std::fs::Permissions::from_mode(0o600)).await?;
+    }
+    Ok(())
+}
+
+/// Tokens in the OS keychain (Keychain, Credential Manager, Secret Service),
+/// one entry per provider. Windows caps an entry at 2560 bytes, so the
+/// tokens are not stored as a single blob.
+#[derive(Debug, Clone)]
+pub struct KeyringStorage {
+    service: String,
+}
+
+impl KeyringStorage {
+    pub fn new(service: impl Into<String>) -> Self {
+        Self { service: service.into() }
+    }
+
+    fn entry(&self, name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(&self.service, name)
+            .with_context(|| format!("Failed to open keychain entry '{}'", name))
+    }
+
+    fn read(&self, name: &str) -> Result<Option<String>> {
+        match self.entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read '{}' from the keychain: {}", name, e)),
+        }
+    }
+
+    fn index(&self) -> Result<Vec<String>> {
+        match self.read(KEYRING_INDEX)? {
+            Some(index) => serde_json::from_str(&index).context("Failed to parse the keychain token index"),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    fn load_blocking(&self) -> Result<HashMap<String, OAuthToken>> {
+        let mut tokens = HashMap::new();
+        for provider_id in self.index()? {
+            // An entry removed behind our back just drops out.
+            if let Some(token) = self.read(&provider_id)? {
+                let token = serde_json::from_str(&token)
+                    .with_context(|| format!("Failed to parse the keychain token for {}", provider_id))?;
+                tokens.insert(provider_id, token);
+            }
+        }
+        Ok(tokens)
+    }
+
+    fn save_blocking(&self, tokens: &HashMap<String, OAuthToken>) -> Result<()> {
+        for (provider_id, token) in tokens {
+            let token = serde_json::to_string(token).context("Failed to serialize token")?;
+            self.entry(provider_id)?
+                .set_password(&token)
+                .map_err(|e| anyhow!("Failed to store the token for {} in the keychain: {}", provider_id, e))?;
+        }
+        for provider_id in self.index()? {
+            if tokens.contains_key(&provider_id) {
+                continue;
+            }
+            match self.entry(&provider_id)?.delete_credential() {
+                Ok(()) | Err(keyring::Error::NoEntry) => {}
+                Err(e) => return Err(anyhow!("Failed to delete the token for {} from the keychain: {}", provider_id, e)),
+            }
+        }
+        let mut index: Vec<&String> = tokens.keys().collect();
+        index.sort();
+        self.entry(KEYRING_INDEX)?
+            .set_password(&serde_json::to_string(&index)?)
+            .map_err(|e| anyhow!("Failed to update the keychain token index: {}", e))
+    }
+}
+
+// The keychain APIs block (and may wait on an unlock prompt), so they run on
+// the blocking pool.
+#[async_trait]
+impl TokenStorage for KeyringStorage {
+    async fn load(&self) -> Result<HashMap<String, OAuthToken>> {
+        let storage = self.clone();
+        tokio::task::spawn_blocking(move || storage.load_blocking()).await?
+    }
+
+    async fn save(&self, tokens: &HashMap<String, OAuthToken>) -> Result<()> {
+        let storage = self.clone();
+        let tokens = tokens.clone();
+        tokio::task::spawn_blocking(move || storage.save_blocking(&tokens)).await?
+    }
+}
+
+/// Which `TokenStorage` the default token store uses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    #[default]
+    File,
+    Keyring,
+}
+
+impl StorageBackend {
+    /// `MCP_OAUTH_TOKEN_STORAGE`: `file` (default) or `keyring`.
+    pub fn from_env() -> Result<Self> {
+        match std::env::var("MCP_OAUTH_TOKEN_STORAGE").ok().as_deref().map(str::trim) {
+            None | Some("") | Some("file") => Ok(Self::File),
+            Some("keyring") => Ok(Self::Keyring),
+            Some(other) => Err(anyhow!("Unknown MCP_OAUTH_TOKEN_STORAGE '{}'. Expected file or keyring", other)),
+        }
+    }
+}
+
+/// A login started by `oauth_start` that is waiting for its callback.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CsrfState {
+    pub provider_id: String,
+    pub created_at: DateTime<Utc>,
+    /// PKCE code verifier to send with the code exchange. Empty for states
+    /// saved before PKCE was used.
+    #[serde(default)]
+    pub pkce_verifier: String,
+}
+
+/// Counts of authorization-code logins since the store was created.
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct AuthFlowMetrics {
+    /// Logins waiting for their callback
+    pub pending: usize,
+    pub started: u64,
+    /// Callbacks that matched a live state
+    pub completed: u64,
+    /// States that expired before their callback arrived
+    pub expired: u64,
+}
+
+#[derive(Debug, Default)]
+struct AuthFlowCounters {
+    started: AtomicU64,
+    completed: AtomicU64,
+    expired: AtomicU64,
+}
+
+/// Token storage with an in-memory cache
 #[derive(Debug, Clone)]
 pub struct TokenStore {
-    /// Path to token storage file
-    file_path: PathBuf,
+    /// Where tokens are persisted
+    storage: Arc<dyn TokenStorage>,
     /// In-memory cache of tokens
     tokens: Arc<RwLock<HashMap<String, OAuthToken>>>,
-    /// In-memory cache of CSRF states (PKCE verifier) to provider_id mappings
-    csrf_states: Arc<RwLock<HashMap<String, String>>>,
+    /// Pending logins by CSRF state
+    csrf_states: Arc<RwLock<HashMap<String, CsrfState>>>,
+    csrf_ttl: Duration,
+    /// Where pending logins are kept across restarts, if anywhere
+    csrf_file: Option<PathBuf>,
+    auth_flows: Arc<AuthFlowCounters>,
 }
 
 impl TokenStore {
-    /// Create a new token store
+    /// Create a new token store backed by a JSON file
     /// Loads existing tokens from file if it exists
-    pub fn new(file_path: PathBuf) -> Result<Self> {
-        let tokens = if file_path.exists() {
-            let content = fs::read_to_string(&file_path)
-                .context("Failed to read token file")?;
-            serde_json::from_str(&content)
-                .context("Failed to parse token file")?
-        } else {
-            HashMap::new()
-        };
+    pub async fn new(file_path: PathBuf) -> Result<Self> {
+        Self::with_storage(Arc::new(FileStorage::new(file_path))).await
+    }
 
+    /// Create a token store over `storage`, loading the tokens it holds
+    pub async fn with_storage(storage: Arc<dyn TokenStorage>) -> Result<Self> {
+        let tokens = storage.load().await?;
         Ok(Self {
-            file_path,
+            storage,
             tokens: Arc::new(RwLock::new(tokens)),
-            csrf_states: Arc::new(RwLock::new(HashMap::new())), // Initialize csrf_states
+            csrf_states: Arc::new(RwLock::new(HashMap::new())),
+            csrf_ttl: DEFAULT_CSRF_TTL,
+            csrf_file: None,
+            auth_flows: Arc::new(AuthFlowCounters::default()),
         })
     }
 
+    /// Expires pending logins `ttl` after they start.
+    pub fn with_csrf_ttl(mut self, ttl: Duration) -> Self {
+        self.csrf_ttl = ttl;
+        self
+    }
+
+    /// Keeps pending logins in `path`, so a restart between `oauth_start` and
+    /// the callback doesn't break the login. Loads the states already there,
+    /// dropping expired ones.
+    pub async fn with_csrf_file(mut self, path: PathBuf) -> Result<Self> {
+        let states: HashMap<String, CsrfState> = match fs::read_to_string(&path).await {
+            Ok(content) => serde_json::from_str(&content).context("Failed to parse CSRF state file")?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(e).context("Failed to read CSRF state file"),
+        };
+        self.csrf_states = Arc::new(RwLock::new(
+            states.into_iter().filter(|(_, state)| !self.is_csrf_expired(state)).collect(),
+        ));
+        self.csrf_file = Some(path);
+        Ok(self)
+    }
+
     /// Get default token store path
     /// ~/.claude-code-mux/oauth_tokens.json
-    pub fn default_path() -> Result<PathBuf> {
+    pub async fn default_path() -> Result<PathBuf> {
         let home = dirs::home_dir()
             .context("Failed to get home directory")?;
         let config_dir = home.join(".claude-code-mux");
         fs::create_dir_all(&config_dir)
+            .await
             .context("Failed to create config directory")?;
         Ok(config_dir.join("oauth_tokens.json"))
     }
 
-    /// Create a token store at the default location
-    pub fn default() -> Result<Self> {
-        let path = Self::default_path()?;
-        Self::new(path)
+    /// Create a token store with the backend chosen by
+    /// `MCP_OAUTH_TOKEN_STORAGE`. The file backend uses the default path; the
+    /// keyring backend first moves any tokens from that file into the
+    /// keychain and deletes the file. Pending logins are kept in
+    /// `oauth_states.json` next to it and expire after
+    /// `MCP_OAUTH_STATE_TTL_SECS` (default 10 minutes).
+    pub async fn default() -> Result<Self> {
+        let path = Self::default_path().await?;
+        let states_path = path.with_file_name("oauth_states.json");
+        let store = match StorageBackend::from_env()? {
+            StorageBackend::File => Self::new(path).await?,
+            StorageBackend::Keyring => {
+                let keyring = KeyringStorage::new(KEYRING_SERVICE);
+                migrate(&FileStorage::new(path), &keyring).await?;
+                Self::with_storage(Arc::new(keyring)).await?
+            }
+        };
+        let ttl = std::env::var("MCP_OAUTH_STATE_TTL_SECS")
+            .ok()
+            .and_then(|secs| secs.parse().ok())
+            .map_or(DEFAULT_CSRF_TTL, Duration::from_secs);
+        store.with_csrf_ttl(ttl).with_csrf_file(states_path).await
     }
 
     /// Save token for a provider
-    pub fn save(&self, token: OAuthToken) -> Result<()> {
-        let provider_id = token.provider_id.clone();
-
-        // Update in-memory cache
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let mut tokens = self.tokens.write().await;
-            tokens.insert(provider_id, token);
-        });
-
-        // Persist to file
-        self.persist()?;
-
-        Ok(())
+    pub async fn save(&self, token: OAuthToken) -> Result<()> {
+        // The write lock is held while persisting, so concurrent saves reach
+        // storage in the order they update the cache.
+        let mut tokens = self.tokens.write().await;
+        tokens.insert(token.provider_id.clone(), token);
+        self.storage.save(&tokens).await
     }
 
     /// Get token for a provider
-    pub fn get(&self, provider_id: &str) -> Option<OAuthToken> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let tokens = self.tokens.read().await;
-            tokens.get(provider_id).cloned()
-        })
+    pub async fn get(&self, provider_id: &str) -> Option<OAuthToken> {
+        self.tokens.read().await.get(provider_id).cloned()
     }
 
-    /// Remove token for a provider
-    pub fn remove(&self, provider_id: &str) -> Result<()> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let mut tokens = self.tokens.write().await;
-            tokens.remove(provider_id);
-            Ok(()) as anyhow::Result<()> // Explicitly cast to anyhow::Result<()>
-        })?;
-
-        // Persist to file
-        self.persist()?;
+    /// Get token for a provider from synchronous code, such as a plugin ABI
+    /// entry point. Panics if called from within an async runtime; use `get`
+    /// there.
+    pub fn blocking_get(&self, provider_id: &str) -> Option<OAuthToken> {
+        self.tokens.blocking_read().get(provider_id).cloned()
+    }
 
+    /// Remove token for a provider
+    pub async fn remove(&self, provider_id: &str) -> Result<()> {
+        let mut tokens = self.tokens.write().await;
+        if tokens.remove(provider_id).is_some() {
+            self.storage.save(&tokens).await?;
+        }
         Ok(())
     }
 
     /// Remove all tokens
-    pub fn remove_all_tokens(&self) -> Result<()> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let mut tokens = self.tokens.write().await;
-            tokens.clear();
-            Ok(()) as anyhow::Result<()> // Explicitly cast to anyhow::Result<()>
-        })?;
-
-        // Persist to file
-        self.persist()?;
-
-        Ok(())
+    pub async fn remove_all_tokens(&self) -> Result<()> {
+        let mut tokens = self.tokens.write().await;
+        tokens.clear();
+        self.storage.save(&tokens).await
     }
 
     /// List all provider IDs that have tokens
-    pub fn list_providers(&self) -> Vec<String> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let tokens = self.tokens.read().await;
-            tokens.keys().cloned().collect()
-        })
+    pub async fn list_providers(&self) -> Vec<String> {
+        self.tokens.read().await.keys().cloned().collect()
     }
 
     /// Get all tokens
-    pub fn all(&self) -> HashMap<String, OAuthToken> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let tokens = self.tokens.read().await;
-            tokens.clone()
-        })
+    pub async fn all(&self) -> HashMap<String, OAuthToken> {
+        self.tokens.read().await.clone()
     }
 
-    /// Persist tokens to file
-    fn persist(&self) -> Result<()> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let tokens = self.tokens.read().await;
-            let json = serde_json::to_string_pretty(&*tokens)
-                .context("Failed to serialize tokens")?;
+    fn is_csrf_expired(&self, state: &CsrfState) -> bool {
+        let ttl = chrono::Duration::from_std(self.csrf_ttl).unwrap_or(chrono::Duration::MAX);
+        Utc::now() >= state.created_at + ttl
+    }
 
-            fs::write(&self.file_path, json)
-                .context("Failed to write token file")?;
+    // Writes the pending logins to the CSRF file, if there is one.
+    async fn persist_csrf_states(&self, states: &HashMap<String, CsrfState>) -> Result<()> {
+        let Some(path) = &self.csrf_file else {
+            return Ok(());
+        };
+        let json = serde_json::to_string_pretty(states).context("Failed to serialize CSRF states")?;
+        write_private(path, json).await.context("Failed to write CSRF state file")
+    }
 
-            // Set file permissions to 0600 (owner read/write only)
-            #[cfg(unix)]
-            {
-                use std::os::unix::fs::PermissionsExt;
-                let mut perms = fs::metadata(&self.file_path)?.permissions();
-                perms.set_mode(0o600);
-                fs::set_permissions(&self.file_path, perms)?;
-            }
-            Ok(())
-        })
+    /// Save a CSRF token associated with a provider_id, along with the PKCE
+    /// verifier the callback needs
+    pub async fn save_csrf_token(&self, provider_id: String, state: String, pkce_verifier: String) -> Result<()> {
+        let mut states = self.csrf_states.write().await;
+        states.insert(
+            state,
+            CsrfState {
+                provider_id,
+                created_at: Utc::now(),
+                pkce_verifier,
+            },
+        );
+        self.auth_flows.started.fetch_add(1, Ordering::Relaxed);
+        self.persist_csrf_states(&states).await
     }
 
-    /// Save a CSRF token (PKCE state) associated with a provider_id
-    pub fn save_csrf_token(&self, provider_id: String, state: String) {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let mut csrf_states = self.csrf_states.write().await;
-            csrf_states.insert(state, provider_id);
-        })
+    /// Get the provider_id associated with a CSRF token (PKCE state), unless
+    /// it has expired
+    pub async fn get_csrf_token_provider(&self, state: &str) -> Option<String> {
+        let states = self.csrf_states.read().await;
+        states
+            .get(state)
+            .filter(|state| !self.is_csrf_expired(state))
+            .map(|state| state.provider_id.clone())
     }
 
-    /// Get the provider_id associated with a CSRF token (PKCE state)
-    pub fn get_csrf_token_provider(&self, state: &str) -> Option<String> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let csrf_states = self.csrf_states.read().await;
-            csrf_states.get(state).cloned()
-        })
+    /// Retrieve and remove a CSRF token (PKCE state). Each state can be used
+    /// once; an expired one is removed and `None` returned.
+    pub async fn retrieve_csrf_token(&self, state: &str) -> Option<CsrfState> {
+        let mut states = self.csrf_states.write().await;
+        let removed = states.remove(state)?;
+        if let Err(e) = self.persist_csrf_states(&states).await {
+            tracing::warn!("{:#}", e);
+        }
+        if self.is_csrf_expired(&removed) {
+            self.auth_flows.expired.fetch_add(1, Ordering::Relaxed);
+            return None;
+        }
+        self.auth_flows.completed.fetch_add(1, Ordering::Relaxed);
+        Some(removed)
+    }
+
+    /// Drops expired pending logins and returns how many there were.
+    pub async fn purge_expired_csrf_states(&self) -> Result<usize> {
+        let mut states = self.csrf_states.write().await;
+        let before = states.len();
+        states.retain(|_, state| !self.is_csrf_expired(state));
+        let purged = before - states.len();
+        if purged > 0 {
+            self.auth_flows.expired.fetch_add(purged as u64, Ordering::Relaxed);
+            self.persist_csrf_states(&states).await?;
+        }
+        Ok(purged)
     }
 
-    /// Retrieve and remove a CSRF token (PKCE state)
-    pub fn retrieve_csrf_token(&self, state: &str) -> Option<String> {
-        tokio::runtime::Handle::current().block_on(async { // block_on for async RwLock
-            let mut csrf_states = self.csrf_states.write().await;
-            csrf_states.remove(state)
+    /// Purges expired pending logins every `CSRF_CLEANUP_INTERVAL` until the
+    /// task is aborted.
+    pub fn spawn_csrf_cleanup(&self) -> JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(CSRF_CLEANUP_INTERVAL);
+            loop {
+                interval.tick().await;
+                match store.purge_expired_csrf_states().await {
+                    Ok(0) => {}
+                    Ok(purged) => tracing::debug!("Dropped {} expired OAuth login state(s)", purged),
+                    Err(e) => tracing::warn!("Failed to purge expired OAuth login states: {:#}", e),
+                }
+            }
         })
     }
+
+    /// Counts of authorization-code logins, for monitoring stuck flows.
+    pub async fn auth_flow_metrics(&self) -> AuthFlowMetrics {
+        AuthFlowMetrics {
+            pending: self.csrf_states.read().await.len(),
+            started: self.auth_flows.started.load(Ordering::Relaxed),
+            completed: self.auth_flows.completed.load(Ordering::Relaxed),
+            expired: self.auth_flows.expired.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Moves tokens from the JSON file into `target`, then deletes the file.
+/// Tokens already in `target` win over the file's. Does nothing if there is
+/// no file.
+pub async fn migrate(file: &FileStorage, target: &dyn TokenStorage) -> Result<()> {
+    if !fs::try_exists(file.path()).await.unwrap_or(false) {
+        return Ok(());
+    }
+    let mut tokens = file.load().await?;
+    if !tokens.is_empty() {
+        tokens.extend(target.load().await?);
+        target.save(&tokens).await?;
+    }
+    fs::remove_file(file.path())
+        .await
+        .with_context(|| format!("Failed to remove {} after migrating its tokens", file.path().display()))?;
+    tracing::info!("Moved {} OAuth token(s) from {} to the keychain", tokens.len(), file.path().display());
+    Ok(())
 }
 
 #[cfg(test)]
@@ -208,32 +510,94 @@ mod tests {
     use super::*;
     use tempfile::TempDir;
 
-    #[test]
-    fn test_token_store() {
-        let rt = tokio::runtime::Runtime::new().unwrap(); // Create a Tokio runtime
-        rt.block_on(async { // Block on the async test
-            let temp_dir = TempDir::new().unwrap();
-            let token_path = temp_dir.path().join("tokens.json");
-            let store = TokenStore::new(token_path).unwrap();
-
-            let token = OAuthToken {
-                provider_id: "test-provider".to_string(),
-                access_token: "access-123".to_string(),
-                refresh_token: "refresh-456".to_string(),
-                expires_at: Utc::now() + chrono::Duration::hours(1),
-                enterprise_url: None,
-                project_id: None,
-            };
-
-            store.save(token.clone()).unwrap();
-
-            let retrieved = store.get("test-provider").unwrap();
-            assert_eq!(retrieved.access_token, "access-123");
-            assert_eq!(retrieved.refresh_token, "refresh-456");
-
-            store.remove("test-provider").unwrap();
-            assert!(store.get("test-provider").is_none());
-        });
+    #[tokio::test]
+    async fn test_token_store() {
+        let temp_dir = TempDir::new().unwrap();
+        let token_path = temp_dir.path().join("tokens.json");
+        let store = TokenStore::new(token_path.clone()).await.unwrap();
+
+        let token = OAuthToken {
+            provider_id: "test-provider".to_string(),
+            access_token: "access-123".to_string(),
+            refresh_token: "refresh-456".to_string(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            enterprise_url: None,
+            project_id: None,
+        };
+
+        store.save(token.clone()).await.unwrap();
+
+        let retrieved = store.get("test-provider").await.unwrap();
+        assert_eq!(retrieved.access_token, "access-123");
+        assert_eq!(retrieved.refresh_token, "refresh-456");
+
+        // A second store sees what the first persisted.
+        let reloaded = TokenStore::new(token_path).await.unwrap();
+        assert!(reloaded.get("test-provider").await.is_some());
+
+        store.remove("test-provider").await.unwrap();
+        assert!(store.get("test-provider").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_csrf_state_expiry() {
+        let temp_dir = TempDir::new().unwrap();
+        let states_path = temp_dir.path().join("states.json");
+        let store = TokenStore::new(temp_dir.path().join("tokens.json"))
+            .await
+            .unwrap()
+            .with_csrf_file(states_path.clone())
+            .await
+            .unwrap();
+
+        store.save_csrf_token("github".to_string(), "live".to_string(), "verifier".to_string()).await.unwrap();
+        // Survives a restart.
+        let restarted = TokenStore::new(temp_dir.path().join("tokens.json"))
+            .await
+            .unwrap()
+            .with_csrf_file(states_path)
+            .await
+            .unwrap();
+        assert_eq!(restarted.get_csrf_token_provider("live").await.as_deref(), Some("github"));
+        let live = restarted.retrieve_csrf_token("live").await.unwrap();
+        assert_eq!((live.provider_id.as_str(), live.pkce_verifier.as_str()), ("github", "verifier"));
+        assert!(restarted.retrieve_csrf_token("live").await.is_none());
+
+        let store = store.with_csrf_ttl(Duration::ZERO);
+        store.save_csrf_token("github".to_string(), "stale".to_string(), "verifier".to_string()).await.unwrap();
+        assert!(store.get_csrf_token_provider("stale").await.is_none());
+        assert_eq!(store.purge_expired_csrf_states().await.unwrap(), 2);
+        let metrics = store.auth_flow_metrics().await;
+        assert_eq!((metrics.pending, metrics.started, metrics.expired), (0, 2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_migrate() {
+        let temp_dir = TempDir::new().unwrap();
+        let token = |provider_id: &str, access_token: &str| OAuthToken {
+            provider_id: provider_id.to_string(),
+            access_token: access_token.to_string(),
+            refresh_token: String::new(),
+            expires_at: Utc::now() + chrono::Duration::hours(1),
+            enterprise_url: None,
+            project_id: None,
+        };
+        let file = FileStorage::new(temp_dir.path().join("tokens.json"));
+        let target = FileStorage::new(temp_dir.path().join("target.json"));
+        file.save(&HashMap::from([
+            ("a".to_string(), token("a", "old")),
+            ("b".to_string(), token("b", "from-file")),
+        ]))
+        .await
+        .unwrap();
+        target.save(&HashMap::from([("a".to_string(), token("a", "new"))])).await.unwrap();
+
+        migrate(&file, &target).await.unwrap();
+
+        let migrated = target.load().await.unwrap();
+        assert_eq!(migrated["a"].access_token, "new");
+        assert_eq!(migrated["b"].access_token, "from-file");
+        assert!(!file.path().exists());
     }
 
     #[test]
@@ -2,10 +2,28 @@ use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int, c_void};
 use serde_json::{json, Value};
 use std::collections::HashMap;
+use std::sync::RwLock;
 
 use hbb_common::log;
 use hbb_common::{ResultType, bail};
 use serde_derive::{Deserialize, Serialize}; // Added for InitInfo
+use mcp_errors::{ErrorCode, McpToolError};
+
+// Plugin logging goes through the host's log callback once initialized, so
+// messages land in the host's log; before init it falls back to hbb_common::log.
+macro_rules! plugin_info {
+    ($($arg:tt)*) => { host_log("info", &format!($($arg)*)) };
+}
+
+macro_rules! plugin_warn {
+    ($($arg:tt)*) => { host_log("warn", &format!($($arg)*)) };
+}
+
+macro_rules! plugin_error {
+    ($($arg:tt)*) => { host_log("error", &format!($($arg)*)) };
+}
+
+mod obs;
 
 // Helper function to convert Rust String to C-compatible string
 fn to_c_string(s: String) -> *mut c_char {
@@ -79,7 +97,7 @@ struct Callbacks {
     ) -> c_int,
 }
 
-#[derive(Serialize, Deserialize)] // Added Deserialize for InitInfo
+#[derive(Serialize, Deserialize, Default, Clone, Debug)] // Added Deserialize for InitInfo
 #[repr(C)]
 struct InitInfo {
     is_server: bool,
@@ -145,55 +163,308 @@ impl PluginReturn {
     }
 }
 
+impl From<McpToolError> for PluginReturn {
+    /// Carries the stable code both as the (negative) return code and in the
+    /// JSON message, so the host can forward it to MCP clients unchanged.
+    fn from(err: McpToolError) -> Self {
+        Self {
+            code: err.plugin_code(),
+            msg: str_to_cstr_ret(&err.to_json().to_string()),
+        }
+    }
+}
+
 // =============================================================================
 // Core Plugin ABI implementations
 // =============================================================================
 
-static mut GLOBAL_INIT_DATA: Option<InitData> = None;
-static mut PLUGIN_ID: Option<String> = None;
+/// Plugin state captured from the host in `init`/`reset`, plus the OBS
+/// connection. Only owned data is kept so the state is `Send + Sync`; the
+/// host's `InitData` strings are borrowed and must not outlive the call.
+/// Connection settings are not cached here: `ObsSettings::load` reads them
+/// from the host on each call.
+struct PluginState {
+    version: String,
+    info: InitInfo,
+    callbacks: Callbacks,
+    obs: obs::ConnectionSlot,
+}
+
+static PLUGIN_STATE: RwLock<Option<PluginState>> = RwLock::new(None);
+
+/// Copyable handle to the host callbacks, usable from any thread
+/// (e.g. the background OBS connection task).
+#[derive(Copy, Clone)]
+pub struct HostHandle {
+    callbacks: Callbacks,
+}
+
+// Callbacks only holds `extern "C"` fn pointers, which the host guarantees
+// are callable from any thread for the lifetime of the plugin.
+unsafe impl Send for HostHandle {}
+unsafe impl Sync for HostHandle {}
+
+impl HostHandle {
+    /// Returns the handle if the plugin has been initialized.
+    pub fn get() -> Option<Self> {
+        PLUGIN_STATE
+            .read()
+            .ok()?
+            .as_ref()
+            .map(|state| HostHandle { callbacks: state.callbacks })
+    }
+
+    /// Logs through the host. `level` is one of error/warn/info/debug/trace.
+    pub fn log(&self, level: &str, msg: &str) {
+        let (Ok(level), Ok(msg)) = (CString::new(level), CString::new(msg)) else {
+            return;
+        };
+        (self.callbacks.log)(level.as_ptr(), msg.as_ptr());
+    }
+
+    /// Sends a message to the host (or a peer) on behalf of this plugin.
+    pub fn msg(&self, peer: &str, target: &str, content: &[u8]) -> PluginReturn {
+        let Some(id) = plugin_id() else {
+            return PluginReturn::from_err("Plugin is not initialized");
+        };
+        let (Ok(peer), Ok(target), Ok(id)) = (CString::new(peer), CString::new(target), CString::new(id)) else {
+            return PluginReturn::from_err("Invalid message argument");
+        };
+        (self.callbacks.msg)(
+            peer.as_ptr(),
+            target.as_ptr(),
+            id.as_ptr(),
+            content.as_ptr() as *const c_void,
+            content.len(),
+        )
+    }
+
+    /// Reads a config value from the host. `peer` is empty for local settings.
+    /// Values are read on every call so changes made in the host UI apply
+    /// without reinitializing the plugin.
+    pub fn get_conf(&self, peer: &str, key: &str) -> Option<String> {
+        let id = plugin_id()?;
+        let (Ok(peer), Ok(id), Ok(key)) = (CString::new(peer), CString::new(id), CString::new(key)) else {
+            return None;
+        };
+        let value = (self.callbacks.get_conf)(peer.as_ptr(), id.as_ptr(), key.as_ptr());
+        // The returned string is owned by the host; copy it and leave it alone.
+        from_c_string(value)
+    }
+
+    /// Persists a local config value through the host's config message
+    /// target, the only write path the plugin ABI offers.
+    pub fn set_conf(&self, key: &str, value: &str) -> Result<(), McpToolError> {
+        let id = plugin_id().ok_or_else(|| McpToolError::new(ErrorCode::Internal, "Plugin is not initialized"))?;
+        let content = json!({ "id": id, "type": "shared", "key": key, "value": value }).to_string();
+        let mut ret = self.msg("", MSG_TO_CONFIG_TARGET, content.as_bytes());
+        if ret.is_success() {
+            return Ok(());
+        }
+        let (code, msg) = ret.get_code_msg("Host rejected the config change");
+        free_c_ptr(ret.msg as *mut c_char);
+        Err(McpToolError::new(ErrorCode::Internal, msg).with_details(json!({ "key": key, "host_code": code })))
+    }
+}
+
+fn host_log(level: &str, msg: &str) {
+    match HostHandle::get() {
+        Some(host) => host.log(level, msg),
+        None => match level {
+            "error" => log::error!("{}", msg),
+            "warn" => log::warn!("{}", msg),
+            _ => log::info!("{}", msg),
+        },
+    }
+}
+
+// =============================================================================
+// Runtime settings (read from the host via get_conf)
+// =============================================================================
+
+const CONF_OBS_HOST: &str = "obs_host";
+const CONF_OBS_PORT: &str = "obs_port";
+const CONF_OBS_PASSWORD: &str = "obs_password";
+const CONF_OBS_AUTO_CONNECT: &str = "obs_auto_connect";
+
+const CONF_KEYS: &[&str] = &[CONF_OBS_HOST, CONF_OBS_PORT, CONF_OBS_PASSWORD, CONF_OBS_AUTO_CONNECT];
+
+/// Host message target for config writes.
+const MSG_TO_CONFIG_TARGET: &str = "config";
+
+const DEFAULT_OBS_HOST: &str = "localhost";
+const DEFAULT_OBS_PORT: u16 = 4455;
+
+/// OBS WebSocket connection settings as currently configured in the host.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObsSettings {
+    pub host: String,
+    pub port: u16,
+    #[serde(skip)]
+    pub password: Option<String>,
+    pub auto_connect: bool,
+}
+
+impl ObsSettings {
+    /// Loads the local settings from the host, falling back to defaults for
+    /// missing or unparsable values.
+    pub fn load() -> Self {
+        let host = HostHandle::get();
+        let get = |key: &str| {
+            host.and_then(|h| h.get_conf("", key))
+                .map(|v| v.trim().to_string())
+                .filter(|v| !v.is_empty())
+        };
+        let port = match get(CONF_OBS_PORT).map(|v| v.parse::<u16>()) {
+            Some(Ok(port)) => port,
+            Some(Err(e)) => {
+                plugin_warn!("Invalid {} value, using {}: {}", CONF_OBS_PORT, DEFAULT_OBS_PORT, e);
+                DEFAULT_OBS_PORT
+            }
+            None => DEFAULT_OBS_PORT,
+        };
+        Self {
+            host: get(CONF_OBS_HOST).unwrap_or_else(|| DEFAULT_OBS_HOST.to_string()),
+            port,
+            password: get(CONF_OBS_PASSWORD),
+            auto_connect: matches!(get(CONF_OBS_AUTO_CONNECT).as_deref(), Some("true" | "1" | "Y")),
+        }
+    }
+
+    /// Applies one config change, checking the value. Returns the value to
+    /// persist, normalized.
+    fn apply(&mut self, key: &str, value: &Value) -> Result<String, McpToolError> {
+        let invalid = |reason: &str| {
+            McpToolError::new(ErrorCode::InvalidArgument, format!("Invalid {}: {}", key, reason))
+                .with_details(json!({ "key": key }))
+        };
+        match key {
+            CONF_OBS_HOST => {
+                let host = value.as_str().map(str::trim).unwrap_or_default();
+                if host.is_empty() || host.contains(char::is_whitespace) || host.contains('/') {
+                    return Err(invalid("expected a host name or IP address"));
+                }
+                self.host = host.to_string();
+                Ok(self.host.clone())
+            }
+            CONF_OBS_PORT => {
+                let port = match value {
+                    Value::Number(port) => port.as_u64().and_then(|port| u16::try_from(port).ok()),
+                    Value::String(port) => port.trim().parse().ok(),
+                    _ => None,
+                };
+                self.port = port.filter(|&port| port != 0).ok_or_else(|| invalid("expected a port from 1 to 65535"))?;
+                Ok(self.port.to_string())
+            }
+            CONF_OBS_PASSWORD => {
+                let password = value.as_str().ok_or_else(|| invalid("expected a string"))?;
+                self.password = Some(password.to_string()).filter(|password| !password.is_empty());
+                Ok(password.to_string())
+            }
+            CONF_OBS_AUTO_CONNECT => {
+                self.auto_connect = match value {
+                    Value::Bool(flag) => *flag,
+                    Value::String(flag) if matches!(flag.as_str(), "true" | "1" | "Y") => true,
+                    Value::String(flag) if matches!(flag.as_str(), "false" | "0" | "N") => false,
+                    _ => return Err(invalid("expected true or false")),
+                };
+                Ok(self.auto_connect.to_string())
+            }
+            _ => Err(McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown config key '{}'", key))
+                .with_details(json!({ "key": key, "known_keys": CONF_KEYS }))),
+        }
+    }
+}
+
+/// The OBS connection slot, while the plugin is initialized.
+fn obs_connection() -> Option<obs::ConnectionSlot> {
+    PLUGIN_STATE.read().ok()?.as_ref().map(|state| state.obs.clone())
+}
+
+/// The plugin ID assigned by the host in `init`.
+fn plugin_id() -> Option<String> {
+    PLUGIN_STATE
+        .read()
+        .ok()?
+        .as_ref()
+        .map(|state| state.info.id.clone())
+        .filter(|id| !id.is_empty())
+}
+
+// Copies what the plugin needs out of the host-owned InitData.
+fn state_from_init_data(data: &InitData) -> PluginState {
+    let info_str = from_c_string(data.info).unwrap_or_default();
+    PluginState {
+        version: from_c_string(data.version).unwrap_or_default(),
+        info: serde_json::from_str(&info_str).unwrap_or_default(),
+        callbacks: data.cbs,
+        obs: obs::ConnectionSlot::default(),
+    }
+}
 
 #[no_mangle]
 pub extern "C" fn init(data_ptr: *const InitData) -> PluginReturn {
-    log::info!("Plugin 'mcpdesk_obs_plugin' init called");
+    plugin_info!("Plugin 'mcpdesk_obs_plugin' init called");
     if data_ptr.is_null() {
         return PluginReturn::from_err("InitData is null");
     }
-    unsafe {
-        let data = &*data_ptr;
-        GLOBAL_INIT_DATA = Some(*data); // Store a copy if needed, or just use the reference
-        let info_str = from_c_string(data.info).unwrap_or_default();
-        let init_info: InitInfo = serde_json::from_str(&info_str).unwrap_or_default();
-        PLUGIN_ID = Some(init_info.id); // Assuming info contains plugin ID
+    let state = state_from_init_data(unsafe { &*data_ptr });
+    plugin_info!(
+        "Plugin 'mcpdesk_obs_plugin' initialized: id={}, version={}, is_server={}",
+        state.info.id, state.version, state.info.is_server
+    );
+    match PLUGIN_STATE.write() {
+        Ok(mut guard) => *guard = Some(state),
+        Err(_) => return PluginReturn::from_err("Plugin state lock poisoned"),
+    }
+    if ObsSettings::load().auto_connect {
+        // Connecting can take a while; don't hold up the host.
+        std::thread::spawn(|| {
+            if let Err(e) = obs::run(obs::connect()) {
+                plugin_warn!("OBS auto-connect failed: {}", e);
+            }
+        });
     }
     PluginReturn::SUCCESS
 }
 
 #[no_mangle]
 pub extern "C" fn reset(data_ptr: *const InitData) -> PluginReturn {
-    log::info!("Plugin 'mcpdesk_obs_plugin' reset called");
+    plugin_info!("Plugin 'mcpdesk_obs_plugin' reset called");
     if data_ptr.is_null() {
         return PluginReturn::from_err("InitData is null");
     }
-    unsafe {
-        let data = &*data_ptr;
-        GLOBAL_INIT_DATA = Some(*data);
+    let mut state = state_from_init_data(unsafe { &*data_ptr });
+    match PLUGIN_STATE.write() {
+        Ok(mut guard) => {
+            // The connection survives a reset; changed settings reconnect on the next call.
+            if let Some(previous) = guard.take() {
+                state.obs = previous.obs;
+            }
+            *guard = Some(state);
+        }
+        Err(_) => return PluginReturn::from_err("Plugin state lock poisoned"),
     }
     PluginReturn::SUCCESS
 }
 
 #[no_mangle]
 pub extern "C" fn clear() -> PluginReturn {
-    log::info!("Plugin 'mcpdesk_obs_plugin' clear called");
-    unsafe {
-        GLOBAL_INIT_DATA = None;
-        PLUGIN_ID = None;
+    plugin_info!("Plugin 'mcpdesk_obs_plugin' clear called");
+    let previous = match PLUGIN_STATE.write() {
+        Ok(mut guard) => guard.take(),
+        Err(_) => return PluginReturn::from_err("Plugin state lock poisoned"),
+    };
+    if let Some(previous) = previous {
+        // Closed on the plugin runtime, which owns the socket.
+        let _ = obs::run(obs::disconnect(previous.obs));
     }
     PluginReturn::SUCCESS
 }
 
 #[no_mangle]
 pub extern "C" fn desc() -> *const c_char {
-    log::info!("Plugin 'mcpdesk_obs_plugin' desc called");
+    plugin_info!("Plugin 'mcpdesk_obs_plugin' desc called");
     let desc_str = json!({
         "id": "obs", // Matches plugin_obs name
         "name": "OBS Control Plugin",
@@ -202,7 +473,13 @@ pub extern "C" fn desc() -> *const c_char {
         "platforms": "windows|linux|macos",
         "listen_events": ["obs_control_request"], // Custom event for MCP commands
         "config": {
-            "test_config": "test_value"
+            "local": [
+                { "key": CONF_OBS_HOST, "default": DEFAULT_OBS_HOST, "description": "OBS WebSocket host" },
+                { "key": CONF_OBS_PORT, "default": DEFAULT_OBS_PORT.to_string(), "description": "OBS WebSocket port" },
+                { "key": CONF_OBS_PASSWORD, "default": "", "description": "OBS WebSocket password" },
+                { "key": CONF_OBS_AUTO_CONNECT, "default": "false", "description": "Connect to OBS when the plugin loads" }
+            ],
+            "peer": []
         }
     }).to_string();
     str_to_cstr_ret(&desc_str)
@@ -217,42 +494,125 @@ pub extern "C" fn call(
 ) -> PluginReturn {
     let method = match from_c_string(method_ptr) {
         Some(m) => m,
-        None => return PluginReturn::from_err("Method is null"),
+        None => return McpToolError::new(ErrorCode::InvalidArgument, "Method is null").into(),
     };
     let peer = from_c_string(peer_ptr).unwrap_or_default();
     let args_slice = unsafe { std::slice::from_raw_parts(args_ptr as *const u8, args_len) };
     let args_str = String::from_utf8_lossy(args_slice);
 
-    log::info!(
+    plugin_info!(
         "Plugin 'mcpdesk_obs_plugin' call called: method={}, peer={}, args={}",
         method, peer, args_str
     );
 
-    // Dispatch based on method
-    match method.as_str() {
+    let args: Value = serde_json::from_str(&args_str).unwrap_or_default();
+    let result = match method.as_str() {
         // OBS Control Commands
-        "obs_start_streaming" => mcpdesk_obs_start_streaming(),
-        "obs_stop_streaming" => mcpdesk_obs_stop_streaming(),
-        "obs_set_scene" => {
-            let args_json: Value = serde_json::from_str(&args_str).unwrap_or_default();
-            let scene_name = args_json["scene_name"].as_str().unwrap_or_default();
-            mcpdesk_obs_set_scene(str_to_cstr_ret(scene_name))
-        }
-        "obs_set_source_visibility" => {
-            let args_json: Value = serde_json::from_str(&args_str).unwrap_or_default();
-            let scene_name_ptr = args_json["scene_name"].as_str().map_or(std::ptr::null(), |s| str_to_cstr_ret(s));
-            let source_name = args_json["source_name"].as_str().unwrap_or_default();
-            let visible = args_json["visible"].as_bool().unwrap_or(false);
-            mcpdesk_obs_set_source_visibility(scene_name_ptr, str_to_cstr_ret(source_name), visible)
-        }
+        "obs_start_streaming" => obs::run(obs::start_streaming()),
+        "obs_stop_streaming" => obs::run(obs::stop_streaming()),
+        "obs_start_recording" => obs::run(obs::start_recording()),
+        "obs_stop_recording" => obs::run(obs::stop_recording()),
+        "obs_pause_recording" => obs::run(obs::pause_recording()),
+        "obs_save_replay_buffer" => obs::run(obs::save_replay_buffer()),
+        "obs_set_scene" => required_str(&args, "scene_name").and_then(|scene| obs::run(obs::set_scene(scene))),
+        "obs_set_scene_collection" => required_str(&args, "collection_name")
+            .and_then(|collection| obs::run(obs::set_scene_collection(collection))),
+        "obs_set_profile" => required_str(&args, "profile_name").and_then(|profile| obs::run(obs::set_profile(profile))),
+        "obs_set_source_visibility" => required_str(&args, "source_name").and_then(|source| {
+            let scene = optional_str(&args, "scene_name");
+            let visible = args["visible"].as_bool().unwrap_or(false);
+            obs::run(obs::set_source_visibility(scene, source, visible))
+        }),
+        "obs_set_input_mute" => required_str(&args, "input_name").and_then(|input| {
+            let muted = args["muted"].as_bool().unwrap_or(true);
+            obs::run(obs::set_input_mute(input, muted))
+        }),
+        "obs_set_input_volume" => required_str(&args, "input_name").and_then(|input| {
+            let volume = match (args["volume_db"].as_f64(), args["volume_mul"].as_f64()) {
+                (Some(db), None) => obs::Volume::Db(db as f32),
+                (None, Some(mul)) => obs::Volume::Mul(mul as f32),
+                _ => {
+                    return Err(McpToolError::new(
+                        ErrorCode::InvalidArgument,
+                        "Give exactly one of 'volume_db' or 'volume_mul'",
+                    ))
+                }
+            };
+            obs::run(obs::set_input_volume(input, volume))
+        }),
+        "set_conf" => set_conf(&args),
         "obs_set_streaming_settings" => {
-            let args_json: Value = serde_json::from_str(&args_str).unwrap_or_default();
-            mcpdesk_obs_set_streaming_settings(str_to_cstr_ret(&args_json.to_string()))
+            let service_type = optional_str(&args, "service_type").unwrap_or_else(|| "rtmp_custom".to_string());
+            let settings = args.get("settings").cloned().unwrap_or_else(|| args.clone());
+            obs::run(obs::set_streaming_settings(service_type, settings))
+        }
+        _ => Err(McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown method: {}", method))),
+    };
+    match result {
+        Ok(_) => PluginReturn::SUCCESS,
+        Err(e) => {
+            plugin_error!("{} failed: {}", method, e);
+            e.into()
         }
-        _ => PluginReturn::from_err(&format!("Unknown method: {}", method)),
     }
 }
 
+fn required_str(args: &Value, key: &str) -> Result<String, McpToolError> {
+    optional_str(args, key).ok_or_else(|| McpToolError::new(ErrorCode::InvalidArgument, format!("Missing '{}'", key)))
+}
+
+fn optional_str(args: &Value, key: &str) -> Option<String> {
+    args[key].as_str().filter(|value| !value.is_empty()).map(str::to_string)
+}
+
+// Validates `{"values": {key: value, ...}, "test_connection": bool}`, checks
+// OBS accepts the resulting settings unless told not to, then persists them.
+// Nothing is written if any value is bad.
+fn set_conf(args: &Value) -> Result<Value, McpToolError> {
+    let host = HostHandle::get().ok_or_else(|| McpToolError::new(ErrorCode::Internal, "Plugin is not initialized"))?;
+    let values = args["values"]
+        .as_object()
+        .filter(|values| !values.is_empty())
+        .ok_or_else(|| McpToolError::new(ErrorCode::InvalidArgument, "Expected a non-empty 'values' object"))?;
+    let mut settings = ObsSettings::load();
+    let mut changes = Vec::new();
+    for (key, value) in values {
+        changes.push((key.as_str(), settings.apply(key, value)?));
+    }
+    let connection_changed = changes.iter().any(|(key, _)| *key != CONF_OBS_AUTO_CONNECT);
+    if connection_changed && args["test_connection"].as_bool().unwrap_or(true) {
+        obs::run(obs::test_connection(settings.clone()))?;
+    }
+    for (key, value) in &changes {
+        host.set_conf(key, value)?;
+    }
+    plugin_info!("OBS settings updated: {:?}", changes.iter().map(|(key, _)| key).collect::<Vec<_>>());
+    Ok(Value::Null)
+}
+
+// One config value, or all of them without a key. The password is never
+// returned, only whether one is set.
+fn get_conf(args: &Value) -> Result<Value, McpToolError> {
+    let settings = ObsSettings::load();
+    let mut all = json!(settings);
+    all["password_set"] = json!(settings.password.is_some());
+    let Some(key) = optional_str(args, "key") else {
+        return Ok(all);
+    };
+    let value = match key.as_str() {
+        CONF_OBS_HOST => json!(settings.host),
+        CONF_OBS_PORT => json!(settings.port),
+        CONF_OBS_PASSWORD => json!(settings.password.is_some()),
+        CONF_OBS_AUTO_CONNECT => json!(settings.auto_connect),
+        _ => {
+            return Err(McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown config key '{}'", key))
+                .with_details(json!({ "key": key, "known_keys": CONF_KEYS })))
+        }
+    };
+    let name = if key == CONF_OBS_PASSWORD { "password_set" } else { "value" };
+    Ok(json!({ "key": key, name: value }))
+}
+
 #[no_mangle]
 pub extern "C" fn call_with_out_data(
     method_ptr: *const c_char,
@@ -264,212 +624,47 @@ pub extern "C" fn call_with_out_data(
 ) -> PluginReturn {
     let method = match from_c_string(method_ptr) {
         Some(m) => m,
-        None => return PluginReturn::from_err("Method is null"),
+        None => return McpToolError::new(ErrorCode::InvalidArgument, "Method is null").into(),
     };
     let peer = from_c_string(peer_ptr).unwrap_or_default();
     let args_slice = unsafe { std::slice::from_raw_parts(args_ptr as *const u8, args_len) };
     let args_str = String::from_utf8_lossy(args_slice);
 
-    log::info!(
+    plugin_info!(
         "Plugin 'mcpdesk_obs_plugin' call_with_out_data called: method={}, peer={}, args={}",
         method, peer, args_str
     );
 
-    let mut result_json_ptr: *mut c_char = std::ptr::null_mut();
-    let mut result_code = 0;
-
-    match method.as_str() {
-        "obs_get_scenes" => {
-            let code = mcpdesk_obs_get_scenes(&mut result_json_ptr);
-            result_code = code;
-        }
-        "obs_get_sources" => {
-            let args_json: Value = serde_json::from_str(&args_str).unwrap_or_default();
-            let scene_name_ptr = args_json["scene_name"].as_str().map_or(std::ptr::null(), |s| str_to_cstr_ret(s));
-            let code = mcpdesk_obs_get_sources(scene_name_ptr, &mut result_json_ptr);
-            result_code = code;
-        }
-        "obs_get_streaming_status" => {
-            let code = mcpdesk_obs_get_streaming_status(&mut result_json_ptr);
-            result_code = code;
-        }
-        _ => return PluginReturn::from_err(&format!("Unknown method with output: {}", method)),
-    }
-
-    if result_code == 0 {
-        if result_json_ptr.is_null() {
-            return PluginReturn::from_err("Plugin returned null output");
-        }
-        unsafe {
-            let cstr = CStr::from_ptr(result_json_ptr);
-            *out_ptr = result_json_ptr as *mut c_void;
-            *out_len_ptr = cstr.to_bytes().len();
-        }
-        PluginReturn::SUCCESS
-    } else {
-        PluginReturn::from_err("Plugin call failed")
-    }
-}
-
-// =============================================================================
-// Existing mcpdesk_obs_* functions (moved from the original lib.rs content)
-// =============================================================================
-
-/// Starts streaming in OBS.
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_start_streaming() -> c_int {
-    // Placeholder: Implement actual OBS interaction here
-    log::info!("mcpdesk_obs_start_streaming called");
-    // Example of success/failure
-    0 // Success
-}
-
-/// Stops streaming in OBS.
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_stop_streaming() -> c_int {
-    // Placeholder: Implement actual OBS interaction here
-    log::info!("mcpdesk_obs_stop_streaming called");
-    0 // Success
-}
-
-/// Sets the active scene in OBS.
-/// `scene_name_ptr`: C-string for the scene name.
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_set_scene(scene_name_ptr: *const c_char) -> c_int {
-    let scene_name = match from_c_string(scene_name_ptr) {
-        Some(name) => name,
-        None => {
-            log::error!("Error: Scene name is NULL.");
-            return -1; // Invalid argument
+    let args: Value = serde_json::from_str(&args_str).unwrap_or_default();
+    let result = match method.as_str() {
+        "obs_get_scenes" => obs::run(obs::get_scenes()),
+        "obs_list_scene_collections" => obs::run(obs::list_scene_collections()),
+        "obs_list_profiles" => obs::run(obs::list_profiles()),
+        "obs_get_sources" => obs::run(obs::get_sources(optional_str(&args, "scene_name"))),
+        "obs_get_streaming_status" => obs::run(obs::get_streaming_status()),
+        "obs_get_recording_status" => obs::run(obs::get_recording_status()),
+        "obs_get_audio_levels" => obs::run(obs::get_audio_levels()),
+        "get_conf" => get_conf(&args),
+        "obs_get_settings" => get_conf(&Value::Null),
+        _ => {
+            return McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown method with output: {}", method))
+                .into()
         }
     };
-    // Placeholder: Implement actual OBS interaction here
-    log::info!("mcpdesk_obs_set_scene called with scene: {}", scene_name);
-    0 // Success
-}
 
-/// Gets a list of available scenes in OBS.
-/// `output_json_ptr`: A pointer to a C-string pointer. The function allocates memory for a JSON string (array of scene names) and sets `*output_json_ptr` to point to it.
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_get_scenes(output_json_ptr: *mut *mut c_char) -> c_int {
-    // Placeholder: Implement actual OBS interaction here
-    log::info!("mcpdesk_obs_get_scenes called");
-    let scenes = vec!["Scene 1", "Scene 2", "My Game Scene"];
-    let json_output = json!(scenes).to_string();
-
-    unsafe {
-        *output_json_ptr = to_c_string(json_output);
-    }
-    0 // Success
-}
-
-/// Sets the visibility of a source in the current scene.
-/// `scene_name_ptr`: C-string for the scene name (can be NULL for current scene).
-/// `source_name_ptr`: C-string for the source name.
-/// `visible`: `bool` (C `_Bool` or `int` interpreted as bool).
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_set_source_visibility(
-    scene_name_ptr: *const c_char,
-    source_name_ptr: *const c_char,
-    visible: bool,
-) -> c_int {
-    let scene_name = from_c_string(scene_name_ptr); // Option<String>
-    let source_name = match from_c_string(source_name_ptr) {
-        Some(name) => name,
-        None => {
-            log::error!("Error: Source name is NULL.");
-            return -1; // Invalid argument
+    match result {
+        Ok(value) => {
+            let output = value.to_string();
+            let len = output.len();
+            unsafe {
+                *out_ptr = to_c_string(output) as *mut c_void;
+                *out_len_ptr = len;
+            }
+            PluginReturn::SUCCESS
         }
-    };
-
-    // Placeholder: Implement actual OBS interaction here
-    match scene_name {
-        Some(name) => log::info!(
-            "mcpdesk_obs_set_source_visibility called for scene: {}, source: {}, visible: {}",
-            name, source_name, visible
-        ),
-        None => log::info!(
-            "mcpdesk_obs_set_source_visibility called for current scene, source: {}, visible: {}",
-            source_name, visible
-        ),
-    }
-    0 // Success
-}
-
-/// Gets a list of sources in the current scene.
-/// `scene_name_ptr`: C-string for the scene name (can be NULL for current scene).
-/// `output_json_ptr`: A pointer to a C-string pointer for a JSON string (array of source names).
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_get_sources(
-    scene_name_ptr: *const c_char,
-    output_json_ptr: *mut *mut c_char,
-) -> c_int {
-    let scene_name = from_c_string(scene_name_ptr); // Option<String>
-
-    // Placeholder: Implement actual OBS interaction here
-    let sources = match scene_name {
-        Some(name) => {
-            log::info!("mcpdesk_obs_get_sources called for scene: {}", name);
-            vec![format!("Source A in {}", name), format!("Source B in {}", name)]
-        }
-        None => {
-            log::info!("mcpdesk_obs_get_sources called for current scene");
-            vec!["Main Cam", "Screen Capture", "Microphone"]
-        }
-    };
-    let json_output = json!(sources).to_string();
-
-    unsafe {
-        *output_json_ptr = to_c_string(json_output);
-    }
-    0 // Success
-}
-
-/// Sets streaming quality/output settings.
-/// `settings_json_ptr`: C-string for a JSON object representing settings.
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_set_streaming_settings(settings_json_ptr: *const c_char) -> c_int {
-    let settings_json = match from_c_string(settings_json_ptr) {
-        Some(json_str) => json_str,
-        None => {
-            log::error!("Error: Settings JSON is NULL.");
-            return -1; // Invalid argument
-        }
-    };
-
-    let settings: Value = match serde_json::from_str(&settings_json) {
-        Ok(val) => val,
         Err(e) => {
-            log::error!("Error parsing settings JSON: {}", e);
-            return -2; // JSON parsing error
+            plugin_error!("{} failed: {}", method, e);
+            e.into()
         }
-    };
-
-    // Placeholder: Implement actual OBS interaction here
-    log::info!(
-        "mcpdesk_obs_set_streaming_settings called with settings: {:?}",
-        settings
-    );
-    0 // Success
-}
-
-/// Gets current streaming status (active/inactive, bitrate, FPS).
-/// `output_json_ptr`: A pointer to a C-string pointer for a JSON string (status details).
-/// Returns 0 on success, non-zero on error.
-pub extern "C" fn mcpdesk_obs_get_streaming_status(output_json_ptr: *mut *mut c_char) -> c_int {
-    // Placeholder: Implement actual OBS interaction here
-    log::info!("mcpdesk_obs_get_streaming_status called");
-    let status = json!({
-        "streaming_active": true,
-        "bitrate": 5000,
-        "fps": 60.0,
-        "output_skipped_frames": 10,
-        "output_total_frames": 10000,
-    })
-    .to_string();
-
-    unsafe {
-        *output_json_ptr = to_c_string(status);
     }
-    0 // Success
-}
\ No newline at end of file
+}
@@ -0,0 +1,325 @@
+//! OBS WebSocket v5 backend, built on `obws`.
+//!
+//! The plugin ABI is synchronous, so requests run on a small runtime owned by
+//! the plugin and the calling thread waits for the result. One connection,
+//! held in the plugin state, is shared by all calls; it is made on first use
+//! and remade when the host settings change or the socket drops.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, LazyLock};
+use std::time::Duration;
+
+use mcp_errors::{ErrorCode, McpToolError};
+pub use obws::requests::inputs::Volume;
+use obws::Client;
+use serde_json::{json, Value};
+use tokio::runtime::Runtime;
+use tokio::sync::Mutex;
+
+use crate::ObsSettings;
+
+/// Longest a single call may take, connecting included.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+static RUNTIME: LazyLock<std::io::Result<Runtime>> = LazyLock::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("obs-websocket")
+        .enable_all()
+        .build()
+});
+
+/// The plugin's OBS connection, empty until first use.
+pub type ConnectionSlot = Arc<Mutex<Option<Connection>>>;
+
+pub struct Connection {
+    // host, port and password the client was connected with.
+    target: (String, u16, Option<String>),
+    client: Client,
+}
+
+type ObsFuture<'c, T> = Pin<Box<dyn Future<Output = obws::Result<T>> + Send + 'c>>;
+
+/// Runs `future` on the plugin runtime and waits for it. Safe to call from a
+/// host thread that is itself inside an async runtime.
+pub fn run<T: Send + 'static>(
+    future: impl Future<Output = Result<T, McpToolError>> + Send + 'static,
+) -> Result<T, McpToolError> {
+    let runtime = RUNTIME
+        .as_ref()
+        .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Cannot start the OBS runtime: {}", e)))?;
+    let (sender, receiver) = std::sync::mpsc::channel();
+    runtime.spawn(async move {
+        let result = match tokio::time::timeout(REQUEST_TIMEOUT, future).await {
+            Ok(result) => result,
+            Err(_) => Err(McpToolError::new(ErrorCode::Timeout, "OBS did not respond in time")),
+        };
+        let _ = sender.send(result);
+    });
+    receiver
+        .recv()
+        .map_err(|_| McpToolError::new(ErrorCode::Internal, "OBS request was dropped"))?
+}
+
+/// Sends one request over the shared connection, connecting first if needed.
+async fn request<T>(f: impl for<'c> FnOnce(&'c Client) -> ObsFuture<'c, T>) -> Result<T, McpToolError> {
+    let slot = crate::obs_connection()
+        .ok_or_else(|| McpToolError::new(ErrorCode::ObsNotConnected, "Plugin is not initialized"))?;
+    let settings = ObsSettings::load();
+    let target = (settings.host.clone(), settings.port, settings.password.clone());
+    let mut connection = slot.lock().await;
+    let current = match connection.take() {
+        Some(current) if current.target == target => current,
+        _ => {
+            let client = Client::connect(&settings.host, settings.port, settings.password.as_deref())
+                .await
+                .map_err(|e| {
+                    McpToolError::new(
+                        ErrorCode::ObsNotConnected,
+                        format!("Cannot connect to OBS at {}:{}: {}", settings.host, settings.port, e),
+                    )
+                })?;
+            plugin_info!("Connected to OBS at {}:{}", settings.host, settings.port);
+            Connection { target, client }
+        }
+    };
+    match f(&current.client).await {
+        Ok(value) => {
+            *connection = Some(current);
+            Ok(value)
+        }
+        // OBS refused the request; the connection itself is fine.
+        Err(e @ obws::Error::Api { .. }) => {
+            *connection = Some(current);
+            Err(McpToolError::new(ErrorCode::ObsRequestFailed, e.to_string()))
+        }
+        // Anything else may mean the socket is gone; reconnect next time.
+        Err(e) => Err(McpToolError::new(ErrorCode::ObsNotConnected, e.to_string())),
+    }
+}
+
+/// Connects now, for the `obs_auto_connect` setting.
+pub async fn connect() -> Result<(), McpToolError> {
+    request(|client| Box::pin(async move { client.general().version().await })).await?;
+    Ok(())
+}
+
+/// Connects with `settings` on a separate connection and asks OBS for its
+/// version, to check settings before they are saved. Returns the version.
+pub async fn test_connection(settings: ObsSettings) -> Result<Value, McpToolError> {
+    let client = Client::connect(&settings.host, settings.port, settings.password.as_deref())
+        .await
+        .map_err(|e| {
+            McpToolError::new(
+                ErrorCode::ObsNotConnected,
+                format!("Cannot connect to OBS at {}:{}: {}", settings.host, settings.port, e),
+            )
+            .with_details(json!({ "host": settings.host, "port": settings.port }))
+        })?;
+    let version = client
+        .general()
+        .version()
+        .await
+        .map_err(|e| McpToolError::new(ErrorCode::ObsRequestFailed, e.to_string()))?;
+    Ok(json!({ "obs_version": version.obs_version.to_string() }))
+}
+
+/// Closes the connection in `slot`, if any.
+pub async fn disconnect(slot: ConnectionSlot) -> Result<(), McpToolError> {
+    slot.lock().await.take();
+    Ok(())
+}
+
+pub async fn start_streaming() -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.streaming().start().await })).await?;
+    Ok(Value::Null)
+}
+
+pub async fn stop_streaming() -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.streaming().stop().await })).await?;
+    Ok(Value::Null)
+}
+
+pub async fn start_recording() -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.recording().start().await })).await?;
+    Ok(Value::Null)
+}
+
+/// Stops recording and returns the path of the finished file.
+pub async fn stop_recording() -> Result<Value, McpToolError> {
+    let path = request(|client| Box::pin(async move { client.recording().stop().await })).await?;
+    plugin_info!("OBS recording saved to {}", path);
+    Ok(json!({ "output_path": path }))
+}
+
+pub async fn pause_recording() -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.recording().pause().await })).await?;
+    Ok(Value::Null)
+}
+
+/// Saves the replay buffer; it must already be running in OBS.
+pub async fn save_replay_buffer() -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.replay_buffer().save().await })).await?;
+    Ok(Value::Null)
+}
+
+pub async fn get_recording_status() -> Result<Value, McpToolError> {
+    let (status, replay_buffer_active) = request(|client| {
+        Box::pin(async move { Ok((client.recording().status().await?, client.replay_buffer().status().await?)) })
+    })
+    .await?;
+    Ok(json!({
+        "recording_active": status.active,
+        "recording_paused": status.paused,
+        "duration_ms": status.duration.whole_milliseconds(),
+        "bytes": status.bytes,
+        "replay_buffer_active": replay_buffer_active,
+    }))
+}
+
+pub async fn set_scene(scene: String) -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.scenes().set_current_program_scene(&scene).await })).await?;
+    Ok(Value::Null)
+}
+
+/// Scene names in the order OBS lists them.
+pub async fn get_scenes() -> Result<Value, McpToolError> {
+    let scenes = request(|client| Box::pin(async move { client.scenes().list().await })).await?;
+    let names: Vec<String> = scenes.scenes.into_iter().rev().map(|scene| scene.name).collect();
+    Ok(json!(names))
+}
+
+/// Scene collections and which one is active.
+pub async fn list_scene_collections() -> Result<Value, McpToolError> {
+    let collections = request(|client| Box::pin(async move { client.scene_collections().list().await })).await?;
+    Ok(json!({ "current": collections.current, "collections": collections.collections }))
+}
+
+/// Switches scene collection. OBS finishes loading it before replying.
+pub async fn set_scene_collection(collection: String) -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.scene_collections().set_current(&collection).await })).await?;
+    Ok(Value::Null)
+}
+
+/// Profiles and which one is active.
+pub async fn list_profiles() -> Result<Value, McpToolError> {
+    let profiles = request(|client| Box::pin(async move { client.profiles().list().await })).await?;
+    Ok(json!({ "current": profiles.current, "profiles": profiles.profiles }))
+}
+
+pub async fn set_profile(profile: String) -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.profiles().set_current(&profile).await })).await?;
+    Ok(Value::Null)
+}
+
+// The named scene, or the current program scene.
+async fn scene_or_current(scene: Option<String>) -> Result<String, McpToolError> {
+    match scene {
+        Some(scene) => Ok(scene),
+        None => request(|client| Box::pin(async move { client.scenes().current_program_scene().await })).await,
+    }
+}
+
+/// Source names in a scene, defaulting to the current one.
+pub async fn get_sources(scene: Option<String>) -> Result<Value, McpToolError> {
+    let scene = scene_or_current(scene).await?;
+    let items = request(|client| Box::pin(async move { client.scene_items().list(&scene).await })).await?;
+    let names: Vec<String> = items.into_iter().map(|item| item.source_name).collect();
+    Ok(json!(names))
+}
+
+pub async fn set_source_visibility(scene: Option<String>, source: String, visible: bool) -> Result<Value, McpToolError> {
+    let scene = scene_or_current(scene).await?;
+    request(|client| {
+        Box::pin(async move {
+            let item_id = client
+                .scene_items()
+                .id(obws::requests::scene_items::Id {
+                    scene: &scene,
+                    source: &source,
+                    search_offset: None,
+                })
+                .await?;
+            client
+                .scene_items()
+                .set_enabled(obws::requests::scene_items::SetEnabled {
+                    scene: &scene,
+                    item_id,
+                    enabled: visible,
+                })
+                .await
+        })
+    })
+    .await?;
+    Ok(Value::Null)
+}
+
+/// Sets the stream destination. `settings` is passed to OBS as is, e.g.
+/// `{"server": "rtmp://...", "key": "..."}` for `rtmp_custom`.
+pub async fn set_streaming_settings(service_type: String, settings: Value) -> Result<Value, McpToolError> {
+    request(|client| {
+        Box::pin(async move { client.config().set_stream_service_settings(&service_type, &settings).await })
+    })
+    .await?;
+    Ok(Value::Null)
+}
+
+pub async fn get_streaming_status() -> Result<Value, McpToolError> {
+    let (status, stats) = request(|client| {
+        Box::pin(async move { Ok((client.streaming().status().await?, client.general().stats().await?)) })
+    })
+    .await?;
+    Ok(json!({
+        "streaming_active": status.active,
+        "reconnecting": status.reconnecting,
+        "duration_ms": status.duration.whole_milliseconds(),
+        "congestion": status.congestion,
+        "bytes": status.bytes,
+        "fps": stats.active_fps,
+        "output_skipped_frames": status.skipped_frames,
+        "output_total_frames": status.total_frames,
+    }))
+}
+
+pub async fn set_input_mute(input: String, muted: bool) -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.inputs().set_muted(&input, muted).await })).await?;
+    Ok(Value::Null)
+}
+
+/// Sets an input's fader, either in dB (`0` is full volume) or as a
+/// multiplier (`1.0` is full volume).
+pub async fn set_input_volume(input: String, volume: Volume) -> Result<Value, McpToolError> {
+    request(|client| Box::pin(async move { client.inputs().set_volume(&input, volume).await })).await?;
+    Ok(Value::Null)
+}
+
+/// Fader level and mute state of every input that has audio. These are the
+/// mixer settings, not live peak meters.
+pub async fn get_audio_levels() -> Result<Value, McpToolError> {
+    let levels = request(|client| {
+        Box::pin(async move {
+            let mut levels = Vec::new();
+            for input in client.inputs().list(None).await? {
+                // Inputs without audio reject volume requests.
+                let volume = match client.inputs().volume(&input.name).await {
+                    Ok(volume) => volume,
+                    Err(obws::Error::Api { .. }) => continue,
+                    Err(e) => return Err(e),
+                };
+                let muted = client.inputs().muted(&input.name).await?;
+                levels.push(json!({
+                    "input_name": input.name,
+                    "kind": input.kind,
+                    "volume_db": volume.db,
+                    "volume_mul": volume.mul,
+                    "muted": muted,
+                }));
+            }
+            Ok(levels)
+        })
+    })
+    .await?;
+    Ok(json!(levels))
+}
+
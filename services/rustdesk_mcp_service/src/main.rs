@@ -1,160 +1,453 @@
-use rmcp::model::{Request, Content, Tool as RmcpTool, CallToolResult, ListToolsResult, PaginatedRequestParam, ServerResult, ClientInfo, CallToolRequestMethod, Implementation, InitializeResult};
-use rmcp::service::{Service, RoleServer, RequestContext, NotificationContext, RunningService};
-use rmcp::model::{ClientRequest, ClientResult, ClientNotification, ServerInfo, ErrorData as McpError};
+use rmcp::model::{Content, Tool as RmcpTool, CallToolResult, ListToolsResult, ServerResult, CallToolRequestMethod, Implementation, InitializeResult};
+use rmcp::service::{Service, RoleServer, RequestContext, NotificationContext};
+use rmcp::model::{ClientRequest, ClientNotification, ErrorData as McpError, JsonObject};
 use rmcp::handler::server::tool::{ToolRouter, CallToolHandler, ToolCallContext};
 use rmcp::handler::server::router::tool::{CallToolHandlerExt, IntoToolRoute};
 use rmcp::transport::io;
 
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
 use serde_json::Value;
 use tokio::runtime::Runtime;
-use anyhow::Result;
-use async_trait::async_trait;
+use anyhow::{anyhow, Result};
 use log::{info, error};
 use futures::{FutureExt, future::BoxFuture};
+use mcp_errors::{ErrorCode, McpToolError};
 
+use std::net::SocketAddr;
 use std::sync::Arc;
-use std::future::Future;
 
-// Placeholder for RustDesk integration
+mod remote_input;
 mod rustdesk_integration;
+mod screenshot;
+mod transport;
 
-// Define your MCP commands
-pub struct ConnectToPeer;
+use rustdesk_integration::{ConnType, SessionRegistry};
 
 // =========================================================================
-// Custom ConnectToPeer Handler
-// This struct will implement CallToolHandler
+// Tool inputs
+// Each tool's input schema is derived from its input struct.
 // =========================================================================
+
+#[derive(Deserialize, JsonSchema)]
+struct ConnectToPeerInput {
+    /// The ID of the RustDesk peer to connect to.
+    peer_id: String,
+    /// The password for the remote peer, if it needs one.
+    password: Option<String>,
+    /// The connection type: Default, FileTransfer, PortForward, ViewCamera or Terminal.
+    conn_type: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ListSessionsInput {}
+
+#[derive(Deserialize, JsonSchema)]
+struct SessionIdInput {
+    /// Session ID returned by connect_to_peer.
+    session_id: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SendKeysInput {
+    /// Session ID returned by connect_to_peer.
+    session_id: String,
+    /// Text to type first.
+    text: Option<String>,
+    /// Combos to press in order, e.g. ["ctrl+a", "enter"].
+    #[serde(default)]
+    keys: Vec<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SendClickInput {
+    /// Session ID returned by connect_to_peer.
+    session_id: String,
+    x: Option<i32>,
+    y: Option<i32>,
+    #[serde(default)]
+    button: remote_input::ClickButton,
+    #[serde(default)]
+    double: bool,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct SetClipboardInput {
+    /// Session ID returned by connect_to_peer.
+    session_id: String,
+    text: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct CaptureScreenshotInput {
+    /// Session ID returned by connect_to_peer. Omit to capture the local screen.
+    session_id: Option<String>,
+    /// Scale the image down to at most this many pixels wide.
+    max_width: Option<u32>,
+}
+
+fn schema_for<T: JsonSchema>() -> Arc<JsonObject> {
+    let schema = schemars::schema_for!(T);
+    let object = serde_json::to_value(schema)
+        .ok()
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default();
+    Arc::new(object)
+}
+
+// Bad arguments fail the request with invalid_params rather than the tool.
+fn parse_arguments<T: DeserializeOwned>(arguments: &Option<JsonObject>) -> Result<T, McpError> {
+    let value = Value::Object(arguments.clone().unwrap_or_default());
+    serde_json::from_value(value).map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, e.to_string()).into())
+}
+
+fn json_result(result: Result<impl serde::Serialize, McpToolError>) -> Result<CallToolResult, McpError> {
+    match result {
+        Ok(value) => Ok(CallToolResult::success(vec![Content::json(value)?])),
+        Err(e) => Ok(e.into_call_tool_result()),
+    }
+}
+
+// =========================================================================
+// Tool handlers
+// =========================================================================
+
 #[derive(Clone)]
 struct ConnectToPeerCallHandler;
 
-#[async_trait]
 impl CallToolHandler<RustdeskMcpService, ()> for ConnectToPeerCallHandler {
     fn call(
         self,
         context: ToolCallContext<'_, RustdeskMcpService>,
     ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
         async move {
-            info!("Executing connect_to_peer command with request: {:?}", context.arguments);
+            // Never log the arguments: they carry the peer password.
+            let input: ConnectToPeerInput = parse_arguments(&context.arguments)?;
+            let conn_type: ConnType = input.conn_type.as_deref().unwrap_or("Default").parse()?;
 
-            let args = context.arguments.unwrap(); // Unwrap once
+            info!("Attempting to connect to peer: {} with conn_type: {:?}", input.peer_id, conn_type);
+            match context.service.sessions.connect(&input.peer_id, input.password.as_deref(), conn_type).await {
+                Ok(session) => Ok(CallToolResult::success(vec![Content::json(session)?])),
+                Err(e) => {
+                    error!("Connection to peer {} failed: {}", input.peer_id, e);
+                    Ok(e.into_call_tool_result())
+                }
+            }
+        }.boxed()
+    }
+}
+
+#[derive(Clone)]
+struct ListSessionsCallHandler;
+
+impl CallToolHandler<RustdeskMcpService, ()> for ListSessionsCallHandler {
+    fn call(
+        self,
+        context: ToolCallContext<'_, RustdeskMcpService>,
+    ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
+        async move {
+            let sessions = context.service.sessions.list();
+            json_result(Ok(serde_json::json!({ "sessions": sessions })))
+        }.boxed()
+    }
+}
 
-            let peer_id = args.get("peer_id").unwrap()
-                .as_str()
-                .ok_or_else(|| McpError::invalid_params("peer_id is required", None))?
-                .to_string();
-            let password = args.get("password").unwrap()
-                .as_str()
-                .map(|s| s.to_string());
-            let conn_type = args.get("conn_type").unwrap()
-                .as_str()
-                .unwrap_or("Default")
-                .to_string();
+#[derive(Clone)]
+struct GetSessionStatusCallHandler;
 
-            info!("Attempting to connect to peer: {} with conn_type: {}", peer_id, conn_type);
+impl CallToolHandler<RustdeskMcpService, ()> for GetSessionStatusCallHandler {
+    fn call(
+        self,
+        context: ToolCallContext<'_, RustdeskMcpService>,
+    ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
+        async move {
+            let input: SessionIdInput = parse_arguments(&context.arguments)?;
+            json_result(context.service.sessions.get(&input.session_id))
+        }.boxed()
+    }
+}
 
-            let session_id = uuid::Uuid::new_v4().to_string();
-            info!("Successfully 'connected' to peer: {}, session_id: {}", peer_id, session_id);
+#[derive(Clone)]
+struct DisconnectPeerCallHandler;
 
-            Ok(CallToolResult::success(vec![Content::text(session_id)]))
+impl CallToolHandler<RustdeskMcpService, ()> for DisconnectPeerCallHandler {
+    fn call(
+        self,
+        context: ToolCallContext<'_, RustdeskMcpService>,
+    ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
+        async move {
+            let input: SessionIdInput = parse_arguments(&context.arguments)?;
+            json_result(context.service.sessions.disconnect(&input.session_id).await)
         }.boxed()
     }
 }
 
+// Keyboard, mouse and clipboard tools for a connected session.
+#[derive(Clone, Copy)]
+enum InputTool {
+    SendKeys,
+    SendClick,
+    SetClipboard,
+    GetClipboard,
+}
+
+// An input tool's parsed arguments.
+enum InputAction {
+    SendKeys(SendKeysInput),
+    SendClick(SendClickInput),
+    SetClipboard(SetClipboardInput),
+    GetClipboard(SessionIdInput),
+}
+
+impl InputTool {
+    fn schema(self) -> Arc<JsonObject> {
+        match self {
+            InputTool::SendKeys => schema_for::<SendKeysInput>(),
+            InputTool::SendClick => schema_for::<SendClickInput>(),
+            InputTool::SetClipboard => schema_for::<SetClipboardInput>(),
+            InputTool::GetClipboard => schema_for::<SessionIdInput>(),
+        }
+    }
+
+    fn parse(self, arguments: &Option<JsonObject>) -> Result<InputAction, McpError> {
+        Ok(match self {
+            InputTool::SendKeys => InputAction::SendKeys(parse_arguments(arguments)?),
+            InputTool::SendClick => InputAction::SendClick(parse_arguments(arguments)?),
+            InputTool::SetClipboard => InputAction::SetClipboard(parse_arguments(arguments)?),
+            InputTool::GetClipboard => InputAction::GetClipboard(parse_arguments(arguments)?),
+        })
+    }
+}
+
+impl InputAction {
+    fn session_id(&self) -> &str {
+        match self {
+            InputAction::SendKeys(input) => &input.session_id,
+            InputAction::SendClick(input) => &input.session_id,
+            InputAction::SetClipboard(input) => &input.session_id,
+            InputAction::GetClipboard(input) => &input.session_id,
+        }
+    }
+
+    fn run(self) -> Result<Value, McpToolError> {
+        match self {
+            InputAction::SendKeys(input) => {
+                if input.text.is_none() && input.keys.is_empty() {
+                    return Err(McpToolError::new(ErrorCode::InvalidArgument, "Give text, keys, or both"));
+                }
+                remote_input::send_keys(input.text.as_deref(), &input.keys)?;
+            }
+            InputAction::SendClick(input) => {
+                let position = match (input.x, input.y) {
+                    (Some(x), Some(y)) => Some((x, y)),
+                    (None, None) => None,
+                    _ => return Err(McpToolError::new(ErrorCode::InvalidArgument, "Give both x and y, or neither")),
+                };
+                remote_input::send_click(position, input.button, input.double)?;
+            }
+            InputAction::SetClipboard(input) => remote_input::set_clipboard(&input.text)?,
+            InputAction::GetClipboard(_) => return Ok(serde_json::json!({ "text": remote_input::get_clipboard()? })),
+        }
+        Ok(serde_json::json!({ "ok": true }))
+    }
+}
+
+impl CallToolHandler<RustdeskMcpService, ()> for InputTool {
+    fn call(
+        self,
+        context: ToolCallContext<'_, RustdeskMcpService>,
+    ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
+        async move {
+            let action = self.parse(&context.arguments)?;
+            if let Err(e) = context.service.sessions.require_connected(action.session_id()) {
+                return Ok(e.into_call_tool_result());
+            }
+            // enigo and arboard block, and some platforms want them off the async threads.
+            let result = tokio::task::spawn_blocking(move || action.run())
+                .await
+                .unwrap_or_else(|e| Err(McpToolError::new(ErrorCode::Internal, e.to_string())));
+            json_result(result)
+        }.boxed()
+    }
+}
+
+#[derive(Clone)]
+struct CaptureScreenshotCallHandler;
+
+impl CallToolHandler<RustdeskMcpService, ()> for CaptureScreenshotCallHandler {
+    fn call(
+        self,
+        context: ToolCallContext<'_, RustdeskMcpService>,
+    ) -> BoxFuture<'_, Result<CallToolResult, McpError>> {
+        async move {
+            let input: CaptureScreenshotInput = parse_arguments(&context.arguments)?;
+            let peer_id = match &input.session_id {
+                Some(session_id) => match context.service.sessions.require_connected(session_id) {
+                    Ok(session) => Some(session.peer_id),
+                    Err(e) => return Ok(e.into_call_tool_result()),
+                },
+                None => None,
+            };
+            let captured = tokio::task::spawn_blocking(move || {
+                let image = match &peer_id {
+                    Some(peer_id) => screenshot::capture_session(peer_id)?,
+                    None => screenshot::capture_local()?,
+                };
+                screenshot::encode_png(image, input.max_width)
+            })
+            .await
+            .unwrap_or_else(|e| Err(McpToolError::new(ErrorCode::Internal, e.to_string())));
+            match captured {
+                Ok(png) => Ok(CallToolResult::success(vec![Content::image(png, "image/png")])),
+                Err(e) => Ok(e.into_call_tool_result()),
+            }
+        }.boxed()
+    }
+}
+
+fn tool(name: &str, description: &str, input_schema: Arc<JsonObject>) -> RmcpTool {
+    RmcpTool {
+        name: name.to_string().into(),
+        title: None,
+        description: Some(description.to_string().into()),
+        input_schema,
+        output_schema: None,
+        annotations: None,
+        icons: None,
+        meta: None,
+    }
+}
+
 // =========================================================================
 // Refactored Service Implementation
 // =========================================================================
 
+#[derive(Clone)]
 struct RustdeskMcpService {
     tool_router: ToolRouter<Self>,
-    // Other state for the service, if any
+    sessions: SessionRegistry,
 }
 
 impl RustdeskMcpService {
     fn new() -> Self {
         let mut tool_router = ToolRouter::new();
-        
-        let connect_to_peer_attr = RmcpTool {
-            name: "connect_to_peer".into(),
-            title: None,
-            description: Some("Initiates a connection to a specified RustDesk peer.".into()),
-            input_schema: Arc::new(serde_json::json!({
-                "type": "object",
-                "properties": {
-                    "peer_id": {
-                        "type": "string",
-                        "description": "The ID of the RustDesk peer to connect to."
-                    },
-                    "password": {
-                        "type": "string",
-                        "description": "The password for the remote peer (optional)."
-                    },
-                    "conn_type": {
-                        "type": "string",
-                        "description": "The connection type (e.g., 'Default', 'FileTransfer', 'Terminal')."
-                    }
-                },
-                "required": ["peer_id"]
-            }).as_object().unwrap().clone()), // Convert to Arc<JsonObject>
-            output_schema: None,
-            annotations: None,
-            icons: None,
-            meta: None,
-        };
 
         let mut builder = ConnectToPeerCallHandler.name("connect_to_peer");
-        builder.attr = connect_to_peer_attr;
+        builder.attr = tool(
+            "connect_to_peer",
+            "Opens a RustDesk session to a peer and returns its session ID and state. Fails with rustdesk.auth_failed on a wrong password and rustdesk.peer_offline when the peer can't be reached.",
+            schema_for::<ConnectToPeerInput>(),
+        );
+        tool_router.add_route(builder.into_tool_route());
+
+        let mut builder = ListSessionsCallHandler.name("list_sessions");
+        builder.attr = tool(
+            "list_sessions",
+            "Lists the RustDesk sessions opened by this service, with their state. Ended sessions stay listed.",
+            schema_for::<ListSessionsInput>(),
+        );
+        tool_router.add_route(builder.into_tool_route());
+
+        let mut builder = GetSessionStatusCallHandler.name("get_session_status");
+        builder.attr = tool(
+            "get_session_status",
+            "Reports a RustDesk session's state: connecting, connected, failed (with the error) or closed.",
+            schema_for::<SessionIdInput>(),
+        );
+        tool_router.add_route(builder.into_tool_route());
+
+        let mut builder = DisconnectPeerCallHandler.name("disconnect_peer");
+        builder.attr = tool(
+            "disconnect_peer",
+            "Closes a RustDesk session opened by connect_to_peer and returns its final state.",
+            schema_for::<SessionIdInput>(),
+        );
+        tool_router.add_route(builder.into_tool_route());
+
+        let input_tools = [
+            (
+                InputTool::SendKeys,
+                "send_keys",
+                "Types text and presses key combos in a connected session. The session window must have focus.",
+            ),
+            (
+                InputTool::SendClick,
+                "send_click",
+                "Clicks in a connected session, at local screen coordinates inside the session window or at the pointer.",
+            ),
+            (
+                InputTool::SetClipboard,
+                "set_remote_clipboard",
+                "Puts text on the clipboard; RustDesk copies it to the peer if clipboard sync is enabled.",
+            ),
+            (
+                InputTool::GetClipboard,
+                "get_remote_clipboard",
+                "Reads the clipboard, which RustDesk keeps in step with the peer's when clipboard sync is enabled.",
+            ),
+        ];
+        for (handler, name, description) in input_tools {
+            let mut builder = handler.name(name);
+            builder.attr = tool(name, description, handler.schema());
+            tool_router.add_route(builder.into_tool_route());
+        }
+
+        let mut builder = CaptureScreenshotCallHandler.name("capture_screenshot");
+        builder.attr = tool(
+            "capture_screenshot",
+            "Captures a connected session's window, or the local screen without a session_id, as a PNG image.",
+            schema_for::<CaptureScreenshotInput>(),
+        );
         tool_router.add_route(builder.into_tool_route());
 
         Self {
             tool_router,
+            sessions: SessionRegistry::from_env(),
         }
     }
 }
 
 // Dummy service implementation for now, will refine based on `ConnectToPeer`
 impl Service<RoleServer> for RustdeskMcpService {
-    fn handle_request(
+    async fn handle_request(
         &self,
         request: ClientRequest, // R::PeerReq
         context: RequestContext<RoleServer>,
-    ) -> impl Future<Output = Result<ServerResult, McpError>> + Send + '_ {
-        async move {
-            match request {
-                ClientRequest::CallToolRequest(req) => {
-                    info!("Received CallToolRequest: {:?}", req);
-                    // Use the tool_router to dispatch the call
-                    let tool_call_context = ToolCallContext::new(
-                        self,
-                        req,
-                        context
-                    );
-                    self.tool_router.call(tool_call_context).await
-                        .map(ServerResult::CallToolResult)
-                }
-                ClientRequest::ListToolsRequest(_req) => {
-                    info!("Received ListToolsRequest");
-                    let tools = self.tool_router.list_all();
-                    Ok(ServerResult::ListToolsResult(ListToolsResult { tools, next_cursor: None }))
-                }
-                _
-                => {
-                    error!("Unhandled ClientRequest: {:?}", request);
-                    Err(McpError::method_not_found::<CallToolRequestMethod>())
-                }
+    ) -> Result<ServerResult, McpError> {
+        match request {
+            ClientRequest::CallToolRequest(req) => {
+                // Only the name: the arguments may carry a peer password.
+                info!("Received CallToolRequest: {}", req.params.name);
+                // Use the tool_router to dispatch the call
+                let tool_call_context = ToolCallContext::new(
+                    self,
+                    req.params,
+                    context
+                );
+                self.tool_router.call(tool_call_context).await
+                    .map(ServerResult::CallToolResult)
+            }
+            ClientRequest::ListToolsRequest(_req) => {
+                info!("Received ListToolsRequest");
+                let tools = self.tool_router.list_all();
+                Ok(ServerResult::ListToolsResult(ListToolsResult { tools, next_cursor: None }))
+            }
+            _
+            => {
+                error!("Unhandled ClientRequest: {:?}", request);
+                Err(McpError::method_not_found::<CallToolRequestMethod>())
             }
         }
     }
 
-    fn handle_notification(
+    async fn handle_notification(
         &self,
         notification: ClientNotification, // R::PeerNot
-        context: NotificationContext<RoleServer>,
-    ) -> impl Future<Output = Result<(), McpError>> + Send + '_ {
-        async move {
-            info!("Received ClientNotification: {:?}", notification);
-            Ok(())
-        }
+        _context: NotificationContext<RoleServer>,
+    ) -> Result<(), McpError> {
+        info!("Received ClientNotification: {:?}", notification);
+        Ok(())
     }
 
     fn get_info(&self) -> InitializeResult { // R::Info which is ServerInfo, which is InitializeResult
@@ -173,24 +466,73 @@ impl Service<RoleServer> for RustdeskMcpService {
     }
 }
 
+/// Transports to serve. With no flags the service serves stdio only.
+#[derive(Debug, Default)]
+struct ServiceOptions {
+    // Serve one session over stdin/stdout; the process exits when it ends.
+    stdio: bool,
+    // Loopback address for the TCP listener, advertised in the lock file.
+    bind: Option<SocketAddr>,
+    // Loopback address for the WebSocket listener.
+    ws_addr: Option<SocketAddr>,
+}
+
+fn parse_service_options(args: &[String]) -> Result<ServiceOptions> {
+    let mut options = ServiceOptions::default();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--stdio" => options.stdio = true,
+            "--bind" => {
+                let addr = iter.next().ok_or_else(|| anyhow!("--bind requires an address"))?;
+                options.bind = Some(addr.parse()?);
+            }
+            "--ws" => {
+                let addr = iter.next().ok_or_else(|| anyhow!("--ws requires an address"))?;
+                options.ws_addr = Some(addr.parse()?);
+            }
+            other => return Err(anyhow!("Unknown option '{}'", other)),
+        }
+    }
+    if options.bind.is_none() && options.ws_addr.is_none() {
+        options.stdio = true;
+    }
+    Ok(options)
+}
+
 fn main() -> Result<()> {
     // Initialize logging
     env_logger::init();
     info!("RustDesk MCP Service starting...");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let options = parse_service_options(&args)?;
 
     let runtime = Runtime::new()?;
     runtime.block_on(async {
         let service = RustdeskMcpService::new();
-
-        // Start the MCP server using rmcp::service::serve_server
-        // For standard IO (Unix pipes or Windows named pipes), use rmcp::transport::io::stdio()
-        // The server_path mentioned before was likely for a specific transport implementation.
-        // For now, we'll use stdio (stdin/stdout) as the transport.
-        info!("Starting MCP server using standard I/O...");
-        if let Err(e) = rmcp::service::serve_server(service, io::stdio()).await {
-            error!("Failed to start MCP server: {:?}", e);
-            return Err(e.into());
+        let (tcp, ws) = transport::bind(options.bind, options.ws_addr).await?;
+        if let Some(listener) = tcp {
+            tokio::spawn(transport::serve_tcp(listener, service.clone()));
         }
-        Ok(())
+        if let Some(listener) = ws {
+            tokio::spawn(transport::serve_websocket(listener, service.clone()));
+        }
+
+        let result = if options.stdio {
+            // Logs go to stderr, so stdout carries only protocol messages.
+            info!("Starting MCP server using standard I/O...");
+            match rmcp::service::serve_server(service, io::stdio()).await {
+                Ok(running) => running.waiting().await.map(|_| ()).map_err(anyhow::Error::from),
+                Err(e) => {
+                    error!("Failed to start MCP server: {:?}", e);
+                    Err(e.into())
+                }
+            }
+        } else {
+            tokio::signal::ctrl_c().await.map_err(anyhow::Error::from)
+        };
+        transport::remove_own_lock_file()?;
+        info!("RustDesk MCP Service stopped.");
+        result
     })
 }
@@ -0,0 +1,144 @@
+//! Keyboard, mouse and clipboard for an open session.
+//!
+//! The RustDesk client forwards input made on its session window to the
+//! remote machine and keeps the two clipboards in sync, so input is injected
+//! locally with `enigo` and the clipboard is read and written locally with
+//! `arboard`. The session window must have focus for keys and clicks to reach
+//! the peer, and clipboard sync must be enabled in RustDesk.
+
+use arboard::Clipboard;
+use enigo::{Button, Coordinate, Direction, Enigo, Key, Keyboard, Mouse, Settings};
+use mcp_errors::{ErrorCode, McpToolError};
+use schemars::JsonSchema;
+use serde::Deserialize;
+
+/// Mouse button for `send_click`.
+#[derive(Debug, Clone, Copy, Default, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ClickButton {
+    #[default]
+    Left,
+    Right,
+    Middle,
+}
+
+fn input_error(e: impl std::fmt::Display) -> McpToolError {
+    McpToolError::new(ErrorCode::CommandFailed, format!("Input injection failed: {}", e))
+}
+
+fn clipboard_error(e: impl std::fmt::Display) -> McpToolError {
+    McpToolError::new(ErrorCode::CommandFailed, format!("Clipboard access failed: {}", e))
+}
+
+fn enigo() -> Result<Enigo, McpToolError> {
+    Enigo::new(&Settings::default()).map_err(input_error)
+}
+
+// One key name: a modifier, a named key, F1-F12, or a single character.
+fn parse_key(name: &str) -> Result<Key, McpToolError> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "ctrl" | "control" => Key::Control,
+        "alt" | "option" => Key::Alt,
+        "shift" => Key::Shift,
+        "meta" | "cmd" | "command" | "win" | "super" => Key::Meta,
+        "enter" | "return" => Key::Return,
+        "tab" => Key::Tab,
+        "esc" | "escape" => Key::Escape,
+        "backspace" => Key::Backspace,
+        "delete" | "del" => Key::Delete,
+        "space" => Key::Space,
+        "up" => Key::UpArrow,
+        "down" => Key::DownArrow,
+        "left" => Key::LeftArrow,
+        "right" => Key::RightArrow,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "f1" => Key::F1,
+        "f2" => Key::F2,
+        "f3" => Key::F3,
+        "f4" => Key::F4,
+        "f5" => Key::F5,
+        "f6" => Key::F6,
+        "f7" => Key::F7,
+        "f8" => Key::F8,
+        "f9" => Key::F9,
+        "f10" => Key::F10,
+        "f11" => Key::F11,
+        "f12" => Key::F12,
+        _ => {
+            let mut chars = name.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Key::Unicode(c),
+                _ => {
+                    return Err(McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown key '{}'", name)));
+                }
+            }
+        }
+    };
+    Ok(key)
+}
+
+/// Types `text`, then presses each combo in `keys` in order. A combo is key
+/// names joined by `+`, e.g. `ctrl+c` or `enter`; every key but the last is
+/// held while the last is pressed.
+pub fn send_keys(text: Option<&str>, keys: &[String]) -> Result<(), McpToolError> {
+    // Parse everything first so a bad combo sends nothing.
+    let combos = keys
+        .iter()
+        .map(|combo| combo.split('+').map(str::trim).map(parse_key).collect::<Result<Vec<_>, _>>())
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut enigo = enigo()?;
+    if let Some(text) = text.filter(|text| !text.is_empty()) {
+        enigo.text(text).map_err(input_error)?;
+    }
+    for combo in combos {
+        let Some((last, held)) = combo.split_last() else {
+            continue;
+        };
+        for key in held {
+            enigo.key(*key, Direction::Press).map_err(input_error)?;
+        }
+        let pressed = enigo.key(*last, Direction::Click);
+        // Release modifiers even if the click failed, so none stay stuck.
+        for key in held.iter().rev() {
+            let _ = enigo.key(*key, Direction::Release);
+        }
+        pressed.map_err(input_error)?;
+    }
+    Ok(())
+}
+
+/// Clicks at `(x, y)` in local screen coordinates, where the session window
+/// shows the remote screen, or at the pointer if no position is given.
+pub fn send_click(position: Option<(i32, i32)>, button: ClickButton, double: bool) -> Result<(), McpToolError> {
+    let mut enigo = enigo()?;
+    if let Some((x, y)) = position {
+        enigo.move_mouse(x, y, Coordinate::Abs).map_err(input_error)?;
+    }
+    let button = match button {
+        ClickButton::Left => Button::Left,
+        ClickButton::Right => Button::Right,
+        ClickButton::Middle => Button::Middle,
+    };
+    for _ in 0..if double { 2 } else { 1 } {
+        enigo.button(button, Direction::Click).map_err(input_error)?;
+    }
+    Ok(())
+}
+
+/// Sets the clipboard; RustDesk copies it to the peer.
+pub fn set_clipboard(text: &str) -> Result<(), McpToolError> {
+    Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text)).map_err(clipboard_error)
+}
+
+/// Reads the clipboard, which RustDesk keeps in step with the peer's.
+pub fn get_clipboard() -> Result<String, McpToolError> {
+    match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+        Ok(text) => Ok(text),
+        // An empty or non-text clipboard.
+        Err(arboard::Error::ContentNotAvailable) => Ok(String::new()),
+        Err(e) => Err(clipboard_error(e)),
+    }
+}
@@ -1,2 +1,336 @@
-// Placeholder for RustDesk integration logic
-// This module will contain functions to interact with the RustDesk library.
+//! RustDesk sessions driven through the RustDesk client's command line.
+//!
+//! `rustdesk --connect <id>` (or `--file-transfer`, `--port-forward`, ...)
+//! opens a session window and keeps running while it is open. The client
+//! reports failures such as a wrong password or an offline peer in its output
+//! and then exits, so a session counts as connected once the client has run
+//! for `CONNECT_SETTLE` without reporting one. A client that exits cleanly
+//! straight away has handed the session to an already running RustDesk
+//! instance, which this service cannot follow; it is reported as closed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use log::{debug, info, warn};
+use mcp_errors::{ErrorCode, McpToolError};
+use serde::Serialize;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+use tokio::sync::{mpsc, oneshot};
+
+/// How long the client must run without reporting an error before the
+/// session counts as connected.
+pub const CONNECT_SETTLE: Duration = Duration::from_secs(8);
+/// How long `disconnect` waits for the client to exit after killing it.
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// What kind of session to open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnType {
+    Default,
+    FileTransfer,
+    PortForward,
+    ViewCamera,
+    Terminal,
+}
+
+impl ConnType {
+    fn flag(&self) -> &'static str {
+        match self {
+            ConnType::Default => "--connect",
+            ConnType::FileTransfer => "--file-transfer",
+            ConnType::PortForward => "--port-forward",
+            ConnType::ViewCamera => "--view-camera",
+            ConnType::Terminal => "--terminal",
+        }
+    }
+}
+
+impl FromStr for ConnType {
+    type Err = McpToolError;
+
+    /// Accepts the names RustDesk uses (`Default`, `FileTransfer`, ...) in any
+    /// case, with or without underscores.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().replace('_', "").as_str() {
+            "default" => Ok(ConnType::Default),
+            "filetransfer" => Ok(ConnType::FileTransfer),
+            "portforward" => Ok(ConnType::PortForward),
+            "viewcamera" => Ok(ConnType::ViewCamera),
+            "terminal" => Ok(ConnType::Terminal),
+            _ => Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("Unknown conn_type '{}'. Expected Default, FileTransfer, PortForward, ViewCamera or Terminal", s),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum SessionState {
+    Connecting,
+    Connected,
+    Failed { error: McpToolError },
+    Closed,
+}
+
+impl SessionState {
+    fn is_open(&self) -> bool {
+        matches!(self, SessionState::Connecting | SessionState::Connected)
+    }
+}
+
+/// A session as the tools report it.
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionInfo {
+    pub session_id: String,
+    pub peer_id: String,
+    pub conn_type: ConnType,
+    pub started_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub state: SessionState,
+}
+
+struct Session {
+    info: SessionInfo,
+    // Tells the session's monitor task to kill the client; taken on disconnect.
+    stop: Option<oneshot::Sender<()>>,
+}
+
+/// The sessions this service has opened, by session ID. Ended sessions stay
+/// listed with their final state.
+#[derive(Clone)]
+pub struct SessionRegistry {
+    binary: Arc<PathBuf>,
+    sessions: Arc<Mutex<HashMap<String, Session>>>,
+}
+
+impl SessionRegistry {
+    /// Runs the RustDesk client at `RUSTDESK_PATH`, or `rustdesk` from `PATH`.
+    pub fn from_env() -> Self {
+        let binary = std::env::var_os("RUSTDESK_PATH")
+            .filter(|path| !path.is_empty())
+            .map_or_else(|| PathBuf::from("rustdesk"), PathBuf::from);
+        Self {
+            binary: Arc::new(binary),
+            sessions: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    pub fn get(&self, session_id: &str) -> Result<SessionInfo, McpToolError> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(session_id)
+            .map(|session| session.info.clone())
+            .ok_or_else(|| McpToolError::new(ErrorCode::SessionNotFound, format!("No session '{}'", session_id)))
+    }
+
+    /// The session, if it is connected. Tools that act on the peer use this.
+    pub fn require_connected(&self, session_id: &str) -> Result<SessionInfo, McpToolError> {
+        let session = self.get(session_id)?;
+        match &session.state {
+            SessionState::Connected => Ok(session),
+            _ => Err(McpToolError::new(
+                ErrorCode::SessionNotFound,
+                format!("Session '{}' is not connected", session_id),
+            )
+            .with_details(serde_json::to_value(&session).unwrap_or_default())),
+        }
+    }
+
+    /// Every session, oldest first.
+    pub fn list(&self) -> Vec<SessionInfo> {
+        let mut sessions: Vec<SessionInfo> =
+            self.sessions.lock().unwrap().values().map(|session| session.info.clone()).collect();
+        sessions.sort_by_key(|session| session.started_at);
+        sessions
+    }
+
+    fn set_state(&self, session_id: &str, state: SessionState) {
+        if let Some(session) = self.sessions.lock().unwrap().get_mut(session_id) {
+            session.info.state = state;
+        }
+    }
+
+    /// Closes a session by killing its client, and returns its final state.
+    /// A session that has already ended is returned as it is.
+    pub async fn disconnect(&self, session_id: &str) -> Result<SessionInfo, McpToolError> {
+        let stop = {
+            let mut sessions = self.sessions.lock().unwrap();
+            let session = sessions
+                .get_mut(session_id)
+                .ok_or_else(|| McpToolError::new(ErrorCode::SessionNotFound, format!("No session '{}'", session_id)))?;
+            session.stop.take()
+        };
+        if let Some(stop) = stop {
+            info!("Disconnecting RustDesk session {}", session_id);
+            let _ = stop.send(());
+        }
+        let deadline = tokio::time::Instant::now() + DISCONNECT_TIMEOUT;
+        loop {
+            let session = self.get(session_id)?;
+            if !session.state.is_open() {
+                return Ok(session);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(McpToolError::new(
+                    ErrorCode::Timeout,
+                    format!("RustDesk session {} did not close in time", session_id),
+                ));
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+
+    /// Opens a session to `peer_id` and waits until it connects, fails, or
+    /// `CONNECT_SETTLE` passes. Wrong passwords fail with
+    /// `rustdesk.auth_failed` and unreachable peers with `rustdesk.peer_offline`.
+    pub async fn connect(&self, peer_id: &str, password: Option<&str>, conn_type: ConnType) -> Result<SessionInfo, McpToolError> {
+        // Anything starting with '-' would be read as another flag.
+        if peer_id.is_empty() || peer_id.starts_with('-') {
+            return Err(McpToolError::new(ErrorCode::InvalidArgument, format!("Invalid peer ID '{}'", peer_id)));
+        }
+        let mut command = Command::new(self.binary.as_ref());
+        command.arg(conn_type.flag()).arg(peer_id);
+        if let Some(password) = password {
+            command.arg("--password").arg(password);
+        }
+        command.stdin(Stdio::null()).stdout(Stdio::piped()).stderr(Stdio::piped());
+        let mut child = command.spawn().map_err(|e| {
+            let code = if e.kind() == std::io::ErrorKind::NotFound { ErrorCode::FileNotFound } else { ErrorCode::CommandFailed };
+            McpToolError::new(
+                code,
+                format!("Cannot run the RustDesk client at {}: {}. Set RUSTDESK_PATH", self.binary.display(), e),
+            )
+        })?;
+
+        let session_id = uuid::Uuid::new_v4().to_string();
+        let (stop_tx, mut stop) = oneshot::channel();
+        self.sessions.lock().unwrap().insert(
+            session_id.clone(),
+            Session {
+                info: SessionInfo {
+                    session_id: session_id.clone(),
+                    peer_id: peer_id.to_string(),
+                    conn_type,
+                    started_at: Utc::now(),
+                    state: SessionState::Connecting,
+                },
+                stop: Some(stop_tx),
+            },
+        );
+        info!("Opening RustDesk session {} to peer {} ({:?})", session_id, peer_id, conn_type);
+
+        // Output from both streams, line by line, until the client exits.
+        let (lines_tx, mut lines) = mpsc::unbounded_channel();
+        if let Some(stdout) = child.stdout.take() {
+            forward_lines(stdout, lines_tx.clone());
+        }
+        if let Some(stderr) = child.stderr.take() {
+            forward_lines(stderr, lines_tx);
+        }
+
+        let (outcome_tx, outcome) = oneshot::channel();
+        let registry = self.clone();
+        let id = session_id.clone();
+        tokio::spawn(async move {
+            let mut outcome_tx = Some(outcome_tx);
+            let mut failure = None;
+            let mut lines_open = true;
+            let mut stopped = false;
+            let status = loop {
+                tokio::select! {
+                    line = lines.recv(), if lines_open => match line {
+                        Some(line) => {
+                            debug!("rustdesk[{}]: {}", id, line);
+                            if failure.is_none() {
+                                if let Some(error) = classify(&line) {
+                                    warn!("RustDesk session {} failed: {}", id, error);
+                                    registry.set_state(&id, SessionState::Failed { error: error.clone() });
+                                    if let Some(tx) = outcome_tx.take() {
+                                        let _ = tx.send(SessionState::Failed { error: error.clone() });
+                                    }
+                                    failure = Some(error);
+                                }
+                            }
+                        }
+                        None => lines_open = false,
+                    },
+                    Ok(()) = &mut stop, if !stopped => {
+                        stopped = true;
+                        if let Err(e) = child.start_kill() {
+                            warn!("Failed to kill the RustDesk client for session {}: {}", id, e);
+                        }
+                    }
+                    status = child.wait() => break status,
+                }
+            };
+            let state = match (failure, status) {
+                (Some(error), _) => SessionState::Failed { error },
+                (None, _) if stopped => SessionState::Closed,
+                (None, Ok(status)) if status.success() => SessionState::Closed,
+                (None, Ok(status)) => SessionState::Failed {
+                    error: McpToolError::new(ErrorCode::CommandFailed, format!("RustDesk exited with {}", status)),
+                },
+                (None, Err(e)) => SessionState::Failed {
+                    error: McpToolError::new(ErrorCode::CommandFailed, format!("Lost the RustDesk client: {}", e)),
+                },
+            };
+            info!("RustDesk session {} ended: {:?}", id, state);
+            registry.set_state(&id, state.clone());
+            if let Some(tx) = outcome_tx.take() {
+                let _ = tx.send(state);
+            }
+        });
+
+        match tokio::time::timeout(CONNECT_SETTLE, outcome).await {
+            Ok(Ok(SessionState::Failed { error })) => Err(error.with_details(serde_json::json!({ "session_id": session_id }))),
+            Ok(_) => self.get(&session_id),
+            // Still running without complaint.
+            Err(_) => {
+                let mut sessions = self.sessions.lock().unwrap();
+                let session = sessions.get_mut(&session_id).ok_or_else(|| {
+                    McpToolError::new(ErrorCode::SessionNotFound, format!("No session '{}'", session_id))
+                })?;
+                if matches!(session.info.state, SessionState::Connecting) {
+                    session.info.state = SessionState::Connected;
+                }
+                Ok(session.info.clone())
+            }
+        }
+    }
+}
+
+fn forward_lines(stream: impl AsyncRead + Unpin + Send + 'static, lines: mpsc::UnboundedSender<String>) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(stream).lines();
+        while let Ok(Some(line)) = reader.next_line().await {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+// Maps the client's failure messages to error codes.
+fn classify(line: &str) -> Option<McpToolError> {
+    let lower = line.to_ascii_lowercase();
+    let code = if lower.contains("wrong password") || lower.contains("password error") {
+        ErrorCode::PeerAuthFailed
+    } else if lower.contains("offline") || lower.contains("id does not exist") || lower.contains("not exist") {
+        ErrorCode::PeerOffline
+    } else if lower.contains("connection timed out") || lower.contains("timeout") {
+        ErrorCode::Timeout
+    } else {
+        return None;
+    };
+    Some(McpToolError::new(code, line.trim().to_string()))
+}
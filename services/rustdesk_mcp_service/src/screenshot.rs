@@ -0,0 +1,53 @@
+//! Screen capture with `xcap`: a session's window, found by the peer ID in
+//! its title, or the local primary monitor.
+
+use std::io::Cursor;
+
+use base64::Engine;
+use mcp_errors::{ErrorCode, McpToolError};
+use xcap::image::{imageops::FilterType, DynamicImage, ImageFormat, RgbaImage};
+use xcap::{Monitor, Window};
+
+fn capture_error(e: impl std::fmt::Display) -> McpToolError {
+    McpToolError::new(ErrorCode::CommandFailed, format!("Screen capture failed: {}", e))
+}
+
+/// The RustDesk window showing `peer_id`. RustDesk puts the peer ID in the
+/// title of every session window.
+pub fn capture_session(peer_id: &str) -> Result<RgbaImage, McpToolError> {
+    let window = Window::all()
+        .map_err(capture_error)?
+        .into_iter()
+        .find(|window| !window.is_minimized() && window.title().contains(peer_id))
+        .ok_or_else(|| {
+            McpToolError::new(
+                ErrorCode::SessionNotFound,
+                format!("No visible RustDesk window for peer {}; is it minimized?", peer_id),
+            )
+        })?;
+    window.capture_image().map_err(capture_error)
+}
+
+/// The primary monitor, or the first one if none is marked primary.
+pub fn capture_local() -> Result<RgbaImage, McpToolError> {
+    let monitors = Monitor::all().map_err(capture_error)?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| monitor.is_primary())
+        .or_else(|| monitors.first())
+        .ok_or_else(|| McpToolError::new(ErrorCode::UnsupportedPlatform, "No monitor to capture"))?;
+    monitor.capture_image().map_err(capture_error)
+}
+
+/// Encodes `image` as base64 PNG, scaled down to at most `max_width` pixels
+/// wide if given.
+pub fn encode_png(image: RgbaImage, max_width: Option<u32>) -> Result<String, McpToolError> {
+    let mut image = DynamicImage::ImageRgba8(image);
+    if let Some(max_width) = max_width.filter(|&max_width| max_width > 0 && max_width < image.width()) {
+        let height = (image.height() as u64 * max_width as u64 / image.width() as u64).max(1) as u32;
+        image = image.resize_exact(max_width, height, FilterType::Triangle);
+    }
+    let mut png = Vec::new();
+    image.write_to(&mut Cursor::new(&mut png), ImageFormat::Png).map_err(capture_error)?;
+    Ok(base64::engine::general_purpose::STANDARD.encode(png))
+}
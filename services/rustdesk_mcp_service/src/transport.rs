@@ -0,0 +1,219 @@
+//! Network transports for the service, next to stdio: a TCP listener
+//! advertised through a lock file, as the main server does, and a WebSocket
+//! listener for GUI clients.
+//!
+//! Every connection gets its own MCP session over the one shared
+//! `SessionRegistry`, so a GUI client sees the sessions a stdio host opened.
+//! Listeners only bind to loopback addresses: the tools control the desktop.
+
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Result};
+use mcp_common::framing;
+use mcp_common::lock::{self, LockData};
+use mcp_common::runtime_paths;
+use futures::{SinkExt, StreamExt};
+use log::{error, info, warn};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_tungstenite::tungstenite::handshake::server::{ErrorResponse, Request, Response};
+use tokio_tungstenite::tungstenite::http::StatusCode;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::RustdeskMcpService;
+
+const LOCK_FILE_NAME: &str = "rustdesk_mcp_service.lock";
+
+/// Size of the in-memory pipe between a WebSocket and the MCP service.
+const BRIDGE_BUFFER_SIZE: usize = 64 * 1024;
+
+pub fn lock_file_path() -> Result<PathBuf> {
+    Ok(runtime_paths::runtime_dir()?.join(LOCK_FILE_NAME))
+}
+
+/// Removes the lock file if this process wrote it.
+pub fn remove_own_lock_file() -> Result<()> {
+    lock::remove_own(&lock_file_path()?)
+}
+
+async fn bind_loopback(addr: SocketAddr) -> Result<TcpListener> {
+    if !addr.ip().is_loopback() {
+        return Err(anyhow!("Refusing to listen on {}: the RustDesk service only binds to loopback addresses", addr));
+    }
+    Ok(TcpListener::bind(addr).await?)
+}
+
+/// Binds the TCP listener and the optional WebSocket listener, then writes
+/// the lock file with their ports.
+pub async fn bind(tcp: Option<SocketAddr>, ws: Option<SocketAddr>) -> Result<(Option<TcpListener>, Option<TcpListener>)> {
+    let tcp = match tcp {
+        Some(addr) => Some(bind_loopback(addr).await?),
+        None => None,
+    };
+    let ws = match ws {
+        Some(addr) => Some(bind_loopback(addr).await?),
+        None => None,
+    };
+    if let Some(tcp) = &tcp {
+        lock::write(
+            &lock_file_path()?,
+            &LockData {
+                pid: std::process::id(),
+                port: tcp.local_addr()?.port(),
+                ws_port: ws.as_ref().map(TcpListener::local_addr).transpose()?.map(|addr| addr.port()),
+                ..LockData::default()
+            },
+        )?;
+        info!("MCP server listening on tcp://{}", tcp.local_addr()?);
+    }
+    if let Some(ws) = &ws {
+        info!("MCP server listening on ws://{}", ws.local_addr()?);
+    }
+    Ok((tcp, ws))
+}
+
+async fn serve_session<T>(service: RustdeskMcpService, io: T, peer: &str)
+where
+    T: AsyncRead + AsyncWrite + Send + 'static,
+{
+    match rmcp::service::serve_server(service, io).await {
+        Ok(running) => {
+            if let Err(e) = running.waiting().await {
+                warn!("Session with {} ended abnormally: {:?}", peer, e);
+            }
+        }
+        Err(e) => warn!("Session with {} failed to initialize: {:?}", peer, e),
+    }
+    info!("Connection from {} closed", peer);
+}
+
+/// Accepts newline-delimited or `Content-Length` framed JSON-RPC over TCP.
+pub async fn serve_tcp(listener: TcpListener, service: RustdeskMcpService) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("TCP accept failed: {}", e);
+                continue;
+            }
+        };
+        info!("Accepted TCP connection from {}", addr);
+        let service = service.clone();
+        tokio::spawn(async move {
+            let peer = addr.to_string();
+            match framing::detect(&stream).await {
+                Ok(detected) => serve_session(service, framing::bridge(stream, detected), &peer).await,
+                Err(e) => warn!("Connection from {} failed before sending data: {}", peer, e),
+            }
+        });
+    }
+}
+
+// Browsers let any page open a WebSocket to localhost, so only local pages
+// and clients that send no Origin (native GUIs) may connect.
+// The signature is tungstenite's handshake callback.
+#[allow(clippy::result_large_err)]
+fn check_origin(request: &Request, response: Response) -> Result<Response, ErrorResponse> {
+    let Some(origin) = request.headers().get("origin") else {
+        return Ok(response);
+    };
+    let allowed = origin
+        .to_str()
+        .ok()
+        .and_then(|origin| url::Url::parse(origin).ok())
+        .is_some_and(|origin| matches!(origin.host_str(), Some("localhost" | "127.0.0.1" | "[::1]")));
+    if allowed {
+        return Ok(response);
+    }
+    let mut rejection = ErrorResponse::new(Some("Origin not allowed".to_string()));
+    *rejection.status_mut() = StatusCode::FORBIDDEN;
+    Err(rejection)
+}
+
+/// Accepts WebSocket connections; each text message is one JSON-RPC message.
+pub async fn serve_websocket(listener: TcpListener, service: RustdeskMcpService) {
+    loop {
+        let (stream, addr) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                error!("WebSocket accept failed: {}", e);
+                continue;
+            }
+        };
+        let service = service.clone();
+        tokio::spawn(async move {
+            match tokio_tungstenite::accept_hdr_async(stream, check_origin).await {
+                Ok(socket) => {
+                    info!("Accepted WebSocket connection from {}", addr);
+                    bridge_websocket(service, socket, addr.to_string()).await;
+                }
+                Err(e) => warn!("WebSocket handshake with {} failed: {}", addr, e),
+            }
+        });
+    }
+}
+
+// Runs the session on one end of an in-memory pipe and moves messages
+// between the other end and the socket.
+async fn bridge_websocket(
+    service: RustdeskMcpService,
+    socket: tokio_tungstenite::WebSocketStream<TcpStream>,
+    peer: String,
+) {
+    let (service_side, bridge_side) = tokio::io::duplex(BRIDGE_BUFFER_SIZE);
+    let session = tokio::spawn({
+        let peer = peer.clone();
+        async move { serve_session(service, service_side, &peer).await }
+    });
+    let (bridge_read, mut bridge_write) = tokio::io::split(bridge_side);
+    let mut lines = BufReader::new(bridge_read).lines();
+    let (mut socket_write, mut socket_read) = socket.split();
+
+    loop {
+        tokio::select! {
+            incoming = socket_read.next() => {
+                let text = match incoming {
+                    Some(Ok(Message::Text(text))) => text.to_string(),
+                    Some(Ok(Message::Binary(bytes))) => match String::from_utf8(bytes.to_vec()) {
+                        Ok(text) => text,
+                        Err(_) => {
+                            warn!("Dropping WebSocket from {}: binary message is not UTF-8", peer);
+                            break;
+                        }
+                    },
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => {
+                        warn!("WebSocket from {} failed: {}", peer, e);
+                        break;
+                    }
+                };
+                // Re-encode compactly so newlines inside the message can't split it.
+                let line = match serde_json::from_str::<serde_json::Value>(&text) {
+                    Ok(message) => message.to_string(),
+                    Err(e) => {
+                        warn!("Dropping WebSocket from {} after invalid JSON: {}", peer, e);
+                        break;
+                    }
+                };
+                if bridge_write.write_all(format!("{}\n", line).as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            outgoing = lines.next_line() => match outgoing {
+                Ok(Some(line)) => {
+                    if socket_write.send(Message::text(line)).await.is_err() {
+                        break;
+                    }
+                }
+                _ => break,
+            },
+        }
+    }
+    let _ = socket_write.close().await;
+    // The session sees end of input once both halves of the pipe are gone.
+    drop(bridge_write);
+    drop(lines);
+    let _ = session.await;
+}
@@ -0,0 +1,187 @@
+//! `agent_run`: hands a task to the LLM driver along with this server's tools
+//! as OpenAI function definitions, runs the calls the model asks for, feeds
+//! their results back, and stops when the model answers.
+//!
+//! Only tools the policy lets run without confirmation are offered, so the
+//! model can never confirm a destructive call on the user's behalf. The
+//! OpenAI driver calls functions natively; the others use a fenced
+//! `tool_call` block in the reply (see `Copilot::chat_with_functions`).
+
+use std::future::Future;
+
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{CallToolResult, JsonObject, Tool};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::copilot::{AgentMessage, Copilot, FunctionDefinition, TokenUsage};
+use crate::llm_usage::MeteredLlm;
+use crate::progress::ProgressReporter;
+
+const DEFAULT_MAX_STEPS: usize = 8;
+const MAX_STEPS: usize = 32;
+/// Longest tool result fed back to the model, in characters.
+const MAX_RESULT_CHARS: usize = 8_000;
+// Never offered: the agent itself, and the raw LLM call.
+const EXCLUDED_TOOLS: &[&str] = &["agent_run", "chat_completion"];
+
+const SYSTEM_PROMPT: &str = "You complete tasks with the functions of an MCP server. \
+    Call functions to find out what you need, then answer the task directly and concisely. \
+    If a function fails, try another way or explain what went wrong.";
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct AgentRunInput {
+    /// What to do, in natural language.
+    pub task: String,
+    /// Model id understood by the configured driver, e.g. `gpt-4o-mini`.
+    pub model: String,
+    /// Only offer these tools (default: every tool the policy allows without confirmation).
+    pub tools: Option<Vec<String>>,
+    /// Tool calls allowed before the run stops (default 8, at most 32).
+    pub max_steps: Option<usize>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct AgentStep {
+    pub tool: String,
+    pub arguments: Value,
+    pub is_error: bool,
+    /// The result as the model saw it.
+    pub result: String,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct AgentRunOutput {
+    /// The model's final answer; missing when it reached `max_steps` first.
+    pub answer: Option<String>,
+    /// Every tool call, in order.
+    pub steps: Vec<AgentStep>,
+    /// Tokens used across all turns, when the driver reports them.
+    pub usage: Option<TokenUsage>,
+}
+
+// OpenAI only accepts names of up to 64 letters, digits, `_` and `-`.
+fn is_function_name(name: &str) -> bool {
+    !name.is_empty() && name.len() <= 64 && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+fn function_definition(tool: &Tool) -> Option<FunctionDefinition> {
+    if !is_function_name(&tool.name) {
+        return None;
+    }
+    let mut parameters = tool.input_schema.as_ref().clone();
+    parameters.remove("$schema");
+    parameters.remove("title");
+    Some(FunctionDefinition {
+        name: tool.name.to_string(),
+        description: tool.description.as_deref().unwrap_or_default().to_string(),
+        parameters: Value::Object(parameters),
+    })
+}
+
+// What the model sees of a result: its structured content, else its text.
+fn result_text(result: &CallToolResult) -> String {
+    let text = match &result.structured_content {
+        Some(structured) => structured.to_string(),
+        None => result
+            .content
+            .iter()
+            .map(|content| content.as_text().map(|text| text.text.clone()).unwrap_or_else(|| "[non-text content]".to_string()))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    match text.char_indices().nth(MAX_RESULT_CHARS) {
+        Some((end, _)) => format!("{}\n[truncated]", &text[..end]),
+        None => text,
+    }
+}
+
+/// Runs `input.task` to an answer. `tools` are the tools the caller may
+/// offer; `call_tool` runs one of them.
+pub async fn run<F, Fut>(
+    llm: &MeteredLlm,
+    tools: Vec<Tool>,
+    input: AgentRunInput,
+    progress: Option<ProgressReporter>,
+    call_tool: F,
+) -> Result<AgentRunOutput, McpToolError>
+where
+    F: Fn(String, JsonObject) -> Fut,
+    Fut: Future<Output = CallToolResult>,
+{
+    let offered: Vec<Tool> = tools
+        .into_iter()
+        .filter(|tool| !EXCLUDED_TOOLS.contains(&tool.name.as_ref()))
+        .filter(|tool| input.tools.as_ref().is_none_or(|names| names.iter().any(|name| *name == tool.name)))
+        .collect();
+    if let Some(names) = &input.tools {
+        let unavailable: Vec<&str> = names
+            .iter()
+            .filter(|name| !offered.iter().any(|tool| tool.name == name.as_str()))
+            .map(String::as_str)
+            .collect();
+        if !unavailable.is_empty() {
+            return Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("These tools don't exist or need confirmation, so the agent can't use them: {}", unavailable.join(", ")),
+            ));
+        }
+    }
+    let functions: Vec<FunctionDefinition> = offered.iter().filter_map(function_definition).collect();
+    let max_steps = input.max_steps.unwrap_or(DEFAULT_MAX_STEPS).clamp(1, MAX_STEPS);
+
+    let mut messages = vec![AgentMessage::System(SYSTEM_PROMPT.to_string()), AgentMessage::User(input.task)];
+    let mut steps: Vec<AgentStep> = Vec::new();
+    let mut usage: Option<TokenUsage> = None;
+    loop {
+        let turn = llm.chat_with_functions(messages.clone(), &functions, input.model.clone()).await?;
+        if let Some(turn_usage) = turn.usage {
+            *usage.get_or_insert_with(TokenUsage::default) += turn_usage;
+        }
+        if turn.calls.is_empty() {
+            return Ok(AgentRunOutput {
+                answer: Some(turn.content),
+                steps,
+                usage,
+            });
+        }
+        if steps.len() + turn.calls.len() > max_steps {
+            tracing::info!("agent_run stopped after {} tool calls without an answer", steps.len());
+            return Ok(AgentRunOutput { answer: None, steps, usage });
+        }
+
+        messages.push(AgentMessage::Assistant {
+            content: turn.content,
+            calls: turn.calls.clone(),
+        });
+        for call in turn.calls {
+            if let Some(progress) = &progress {
+                progress
+                    .report((steps.len() + 1) as f64, Some(max_steps as f64), format!("Calling {}", call.name))
+                    .await;
+            }
+            let (is_error, result) = match call.arguments.as_object() {
+                _ if !functions.iter().any(|function| function.name == call.name) => {
+                    (true, format!("{} is not one of the available functions", call.name))
+                }
+                Some(arguments) => {
+                    let result = call_tool(call.name.clone(), arguments.clone()).await;
+                    (result.is_error.unwrap_or(false), result_text(&result))
+                }
+                None => (true, "The arguments must be a JSON object".to_string()),
+            };
+            messages.push(AgentMessage::Result {
+                call_id: call.id,
+                name: call.name.clone(),
+                content: if is_error { format!("Error: {}", result) } else { result.clone() },
+            });
+            steps.push(AgentStep {
+                tool: call.name,
+                arguments: call.arguments,
+                is_error,
+                result,
+            });
+        }
+    }
+}
@@ -0,0 +1,177 @@
+//! Audit trail of tool calls: one JSON line per `tools/call` in
+//! `audit.jsonl` in the data dir, rotated by size like the server log.
+//!
+//! Arguments are recorded only as a SHA-256 hash, so the trail shows what ran
+//! and when without keeping file contents, prompts or secrets.
+
+use std::env;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use mcp_common::runtime_paths;
+use mcp_errors::McpToolError;
+use rmcp::model::JsonObject;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::logs::{self, LogRetention, RotatingWriter};
+
+const DEFAULT_MAX_SIZE_MB: u64 = 10;
+const DEFAULT_MAX_FILES: usize = 5;
+const DEFAULT_QUERY_LIMIT: usize = 100;
+const MAX_QUERY_LIMIT: usize = 1000;
+
+/// One audited tool call.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    /// Client name from `initialize`, when it sent one.
+    pub caller: Option<String>,
+    pub session: Option<String>,
+    pub tool: String,
+    /// SHA-256 of the arguments as received, hex-encoded.
+    pub arguments_sha256: String,
+    pub duration_ms: u64,
+    pub success: bool,
+    /// Stable error code of a failed call, e.g. `common.timeout`.
+    pub error_code: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+pub struct GetAuditLogInput {
+    /// Only calls to this tool.
+    pub tool: Option<String>,
+    /// Oldest call to return: an RFC 3339 time, or an age such as `15m` or `2h`.
+    pub since: Option<String>,
+    /// Only failed calls.
+    #[serde(default)]
+    pub errors_only: bool,
+    /// Most recent calls to return (default 100, at most 1000).
+    pub limit: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct AuditLogOutput {
+    pub path: String,
+    /// Newest first.
+    pub entries: Vec<AuditEntry>,
+}
+
+/// Hashes a call's arguments for the audit trail.
+pub fn hash_arguments(arguments: Option<&JsonObject>) -> String {
+    let serialized = arguments
+        .and_then(|arguments| serde_json::to_vec(arguments).ok())
+        .unwrap_or_default();
+    Sha256::digest(&serialized)
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+/// Appends audit entries to the instance's audit file.
+pub struct AuditLog {
+    path: PathBuf,
+    // None when auditing is disabled or the file couldn't be opened.
+    writer: Mutex<Option<RotatingWriter>>,
+}
+
+impl AuditLog {
+    /// On unless `MCP_AUDIT_ENABLED=0`. The file rotates past
+    /// `MCP_AUDIT_MAX_SIZE_MB` (default 10), keeping `MCP_AUDIT_MAX_FILES`
+    /// rotated files (default 5).
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+        let path = runtime_paths::audit_log_path().unwrap_or_else(|_| PathBuf::from("audit.jsonl"));
+        let enabled = !matches!(env::var("MCP_AUDIT_ENABLED").as_deref(), Ok("0" | "false"));
+        let retention = LogRetention {
+            max_size_bytes: var::<u64>("MCP_AUDIT_MAX_SIZE_MB").unwrap_or(DEFAULT_MAX_SIZE_MB) * 1024 * 1024,
+            max_files: var("MCP_AUDIT_MAX_FILES").unwrap_or(DEFAULT_MAX_FILES),
+            max_age: None,
+        };
+        let writer = if enabled {
+            match RotatingWriter::append(path.clone(), retention) {
+                Ok(writer) => Some(writer),
+                Err(e) => {
+                    tracing::warn!("Audit log disabled: cannot open {}: {}", path.display(), e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        Self {
+            path,
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&self, entry: &AuditEntry) {
+        let mut writer = self.writer.lock().unwrap();
+        let Some(writer) = writer.as_mut() else {
+            return;
+        };
+        let written = serde_json::to_vec(entry)
+            .map_err(io::Error::other)
+            .and_then(|mut line| {
+                line.push(b'\n');
+                writer.write_all(&line)?;
+                writer.flush()
+            });
+        if let Err(e) = written {
+            tracing::warn!("Failed to write audit entry for {}: {}", entry.tool, e);
+        }
+    }
+}
+
+/// Reads the audit file at `path` and its rotations, newest entries first.
+pub fn query(path: &Path, input: &GetAuditLogInput) -> Result<Vec<AuditEntry>, McpToolError> {
+    let since = input.since.as_deref().map(crate::metrics::parse_since).transpose()?;
+    let limit = input.limit.unwrap_or(DEFAULT_QUERY_LIMIT).clamp(1, MAX_QUERY_LIMIT);
+
+    let mut rotated = logs::rotated_files(path).unwrap_or_default();
+    rotated.sort_by(|a, b| b.modified.cmp(&a.modified));
+    let files = std::iter::once(path.to_path_buf()).chain(rotated.into_iter().map(|file| file.path));
+
+    let mut entries = Vec::new();
+    for file in files {
+        let mut matching: Vec<AuditEntry> = read_entries(&file)?
+            .into_iter()
+            .filter(|entry| input.tool.as_deref().is_none_or(|tool| entry.tool == tool))
+            .filter(|entry| !input.errors_only || !entry.success)
+            .filter(|entry| since.is_none_or(|since| entry.timestamp >= since))
+            .collect();
+        matching.reverse();
+        entries.extend(matching);
+        if entries.len() >= limit {
+            break;
+        }
+    }
+    entries.truncate(limit);
+    Ok(entries)
+}
+
+// Entries of one file, oldest first. Lines that don't parse are skipped.
+fn read_entries(path: &Path) -> io::Result<Vec<AuditEntry>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for line in BufReader::new(file).lines() {
+        if let Ok(entry) = serde_json::from_str(&line?) {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
@@ -0,0 +1,23 @@
+//! Shared-secret authentication for the TCP listener.
+//!
+//! `start` generates a random token and stores it in the lock file, which only
+//! the owning user can read. Clients present it in `initialize` under
+//! `capabilities.experimental.auth.token`; connections without it are refused.
+
+use rmcp::model::InitializeRequestParam;
+
+pub use mcp_common::secret::{generate_token, token_matches};
+
+/// Key used in the `experimental` capabilities map to carry the token.
+pub const AUTH_CAPABILITY: &str = "auth";
+
+/// Reads the token a client offered in its `initialize` request.
+pub fn offered_token(request: &InitializeRequestParam) -> Option<&str> {
+    request
+        .capabilities
+        .experimental
+        .as_ref()?
+        .get(AUTH_CAPABILITY)?
+        .get("token")?
+        .as_str()
+}
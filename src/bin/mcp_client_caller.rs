@@ -0,0 +1,213 @@
+//! End-to-end check of the MCP server: starts one (or connects to one that
+//! is already running), initializes, lists the tools and calls one.
+//!
+//! ```text
+//! mcp_client_caller [--server-cmd "<command>"] [--port <port>] [--call <tool>] [--args <json>]
+//! ```
+//!
+//! By default it starts `copilot_mcp_tool serve` from the directory this
+//! binary is in, as a private instance so a server the user runs is left
+//! alone. `--server-cmd` runs another command line instead (split on
+//! whitespace, e.g. `cargo run --bin copilot_mcp_tool -- serve`). The port is
+//! taken from the server's `SERVER_PORT:` line on stderr, or from its lock
+//! file for servers that don't print one. `--port` skips starting a server
+//! and connects to `localhost:<port>`.
+
+use std::env;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use mcp_common::framing::Framing;
+use mcp_common::{jsonrpc, lock, runtime_paths, secret};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::process::{Child, Command};
+
+/// How long a starting server has to report its port; `cargo run` may compile first.
+const STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+const INSTANCE: &str = "mcp-client-caller";
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|index| args.get(index + 1))
+        .map(String::as_str)
+}
+
+// `copilot_mcp_tool` next to this binary, with the platform's extension.
+fn default_server_binary() -> Result<PathBuf> {
+    let exe = env::current_exe()?;
+    let server = exe.with_file_name(format!("copilot_mcp_tool{}", env::consts::EXE_SUFFIX));
+    if !server.exists() {
+        return Err(anyhow!(
+            "No server binary at {}. Build it first or pass --server-cmd.",
+            server.display()
+        ));
+    }
+    Ok(server)
+}
+
+fn server_command(server_cmd: Option<&str>) -> Result<Command> {
+    let mut command = match server_cmd {
+        Some(line) => {
+            let mut parts = line.split_whitespace();
+            let program = parts.next().ok_or_else(|| anyhow!("--server-cmd is empty"))?;
+            let mut command = Command::new(program);
+            command.args(parts);
+            command
+        }
+        None => {
+            let mut command = Command::new(default_server_binary()?);
+            command.arg("serve");
+            command
+        }
+    };
+    command.stdin(Stdio::null()).stderr(Stdio::piped()).kill_on_drop(true);
+    Ok(command)
+}
+
+// Waits for the server to announce its port on stderr, or to write its lock
+// file, then keeps forwarding its stderr so the pipe never fills up.
+async fn discover_port(child: &mut Child) -> Result<u16> {
+    let stderr = child.stderr.take().ok_or_else(|| anyhow!("Server stderr is not captured"))?;
+    let mut lines = BufReader::new(stderr).lines();
+    let lock_path = runtime_paths::lock_file_path()?;
+    let pid = child.id();
+    let deadline = tokio::time::Instant::now() + STARTUP_TIMEOUT;
+    let port = loop {
+        tokio::select! {
+            line = lines.next_line() => match line? {
+                Some(line) => match lock::parse_port_announcement(&line) {
+                    Some(port) => break port,
+                    None => eprintln!("[server] {}", line),
+                },
+                None => {
+                    let status = child.wait().await?;
+                    return Err(anyhow!("Server exited before it was listening ({})", status));
+                }
+            },
+            _ = tokio::time::sleep(Duration::from_millis(200)) => {
+                if let Ok(lock) = lock::read(&lock_path) {
+                    if Some(lock.pid) == pid {
+                        break lock.port;
+                    }
+                }
+                if tokio::time::Instant::now() >= deadline {
+                    return Err(anyhow!("Server did not report a port within {}s", STARTUP_TIMEOUT.as_secs()));
+                }
+            }
+        }
+    };
+    tokio::spawn(async move {
+        while let Ok(Some(line)) = lines.next_line().await {
+            eprintln!("[server] {}", line);
+        }
+    });
+    Ok(port)
+}
+
+// The token for a server we didn't start: `MCP_AUTH_TOKEN`, or the lock file
+// of the server on that port.
+fn token_for(port: u16) -> Option<String> {
+    env::var("MCP_AUTH_TOKEN").ok().or_else(|| {
+        let lock = lock::read(&runtime_paths::lock_file_path().ok()?).ok()?;
+        (lock.port == port).then_some(lock.token).flatten()
+    })
+}
+
+struct Session {
+    reader: BufReader<tokio::net::tcp::OwnedReadHalf>,
+    writer: tokio::net::tcp::OwnedWriteHalf,
+    next_id: u64,
+}
+
+impl Session {
+    async fn request(&mut self, method: &str, params: Value) -> Result<Value> {
+        self.next_id += 1;
+        let id = json!(self.next_id);
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        jsonrpc::write_message(&mut self.writer, Framing::Newline, &request).await?;
+        let response = jsonrpc::read_response(&mut self.reader, &mut self.writer, Framing::Newline, &id).await?;
+        match response.get("error") {
+            Some(error) => Err(anyhow!("{} failed: {}", method, error)),
+            None => Ok(response["result"].clone()),
+        }
+    }
+}
+
+async fn run(args: &[String]) -> Result<()> {
+    let (mut server, port, token) = match flag_value(args, "--port") {
+        Some(port) => {
+            let port: u16 = port.parse().map_err(|_| anyhow!("Invalid --port '{}'", port))?;
+            (None, port, token_for(port))
+        }
+        None => {
+            runtime_paths::set_instance(INSTANCE)?;
+            let token = secret::generate_token();
+            let mut child = server_command(flag_value(args, "--server-cmd"))?
+                .env("MCP_INSTANCE", INSTANCE)
+                .env("MCP_AUTH_TOKEN", &token)
+                .spawn()?;
+            let port = discover_port(&mut child).await?;
+            (Some(child), port, Some(token))
+        }
+    };
+    println!("Connecting to localhost:{}", port);
+
+    let result = async {
+        let (reader, writer) = TcpStream::connect(("localhost", port)).await?.into_split();
+        let mut session = Session { reader: BufReader::new(reader), writer, next_id: 0 };
+        let initialize = session
+            .request(
+                "initialize",
+                json!({
+                    "protocolVersion": "2025-03-26",
+                    "capabilities": { "experimental": { "auth": { "token": token } } },
+                    "clientInfo": { "name": "mcp_client_caller", "version": env!("CARGO_PKG_VERSION") },
+                }),
+            )
+            .await?;
+        println!("Server: {}", initialize["serverInfo"]);
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        jsonrpc::write_message(&mut session.writer, Framing::Newline, &initialized).await?;
+
+        let tools = session.request("tools/list", json!({})).await?;
+        let names: Vec<&str> = tools["tools"]
+            .as_array()
+            .map(|tools| tools.iter().filter_map(|tool| tool["name"].as_str()).collect())
+            .unwrap_or_default();
+        println!("Tools: {}", names.join(", "));
+
+        let tool = flag_value(args, "--call").unwrap_or("echo_message");
+        let arguments: Value = match flag_value(args, "--args") {
+            Some(json) => serde_json::from_str(json).map_err(|e| anyhow!("--args is not valid JSON: {}", e))?,
+            None => json!({ "message": "Hello from mcp_client_caller" }),
+        };
+        let result = session.request("tools/call", json!({ "name": tool, "arguments": arguments })).await?;
+        println!("{}", serde_json::to_string_pretty(&result)?);
+        Ok::<_, anyhow::Error>(())
+    }
+    .await;
+
+    if let Some(child) = &mut server {
+        let pid = child.id();
+        child.kill().await?;
+        // Killed servers can't remove their own lock file.
+        if let Ok(path) = runtime_paths::lock_file_path() {
+            if lock::read(&path).is_ok_and(|lock| Some(lock.pid) == pid) {
+                lock::remove(&path)?;
+            }
+        }
+    }
+    result
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    run(&args).await
+}
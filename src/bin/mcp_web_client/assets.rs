@@ -0,0 +1,104 @@
+//! Templates and static files, compiled into the binary so the web client
+//! runs from any directory.
+//!
+//! Set `MCP_WEB_ASSETS_DIR` to a directory with `templates/` and `static/`
+//! (such as the repository root) to serve them from disk instead; templates
+//! are then re-read on every render, so edits show up on reload.
+
+use std::borrow::Cow;
+use std::env;
+use std::path::{Component, Path as FsPath, PathBuf};
+use std::sync::LazyLock;
+
+use axum::extract::Path;
+use axum::http::{header, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::get;
+use axum::Router;
+use rust_embed::RustEmbed;
+use tera::{Context, Tera};
+
+use crate::auth;
+
+#[derive(RustEmbed)]
+#[folder = "templates/"]
+struct Templates;
+
+#[derive(RustEmbed)]
+#[folder = "static/"]
+struct Static;
+
+static EMBEDDED: LazyLock<Tera> = LazyLock::new(|| {
+    let mut tera = Tera::default();
+    let templates: Vec<(String, String)> = Templates::iter()
+        .filter_map(|name| {
+            let file = Templates::get(&name)?;
+            Some((name.to_string(), String::from_utf8_lossy(&file.data).into_owned()))
+        })
+        .collect();
+    tera.add_raw_templates(templates).expect("embedded templates are valid");
+    tera.autoescape_on(vec![".html"]);
+    tera
+});
+
+fn override_dir() -> Option<PathBuf> {
+    env::var_os("MCP_WEB_ASSETS_DIR").map(PathBuf::from)
+}
+
+fn load_from_disk(dir: &FsPath) -> tera::Result<Tera> {
+    let mut tera = Tera::new(&format!("{}/templates/**/*.html", dir.display()))?;
+    tera.autoescape_on(vec![".html"]);
+    Ok(tera)
+}
+
+/// Renders a page, or a 500 naming the template error. Pages rendered for a
+/// signed-in user also see `user` and `csrf_token`.
+pub fn render(template: &str, context: &Context) -> Response {
+    let mut context = context.clone();
+    if let Some(current) = auth::current() {
+        context.insert("user", &current.user);
+        context.insert("csrf_token", &current.csrf_token);
+    }
+    let rendered = match override_dir() {
+        Some(dir) => load_from_disk(&dir).and_then(|tera| tera.render(template, &context)),
+        None => EMBEDDED.render(template, &context),
+    };
+    match rendered {
+        Ok(page) => Html(page).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to render {}: {:?}", template, e);
+            (StatusCode::INTERNAL_SERVER_ERROR, format!("Failed to render {}: {}", template, e)).into_response()
+        }
+    }
+}
+
+/// Serves `/static/*path`.
+pub fn router() -> Router {
+    Router::new().route("/static/*path", get(static_handler))
+}
+
+fn content_type(path: &str) -> &'static str {
+    match path.rsplit('.').next() {
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") => "text/javascript; charset=utf-8",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("ico") => "image/x-icon",
+        _ => "application/octet-stream",
+    }
+}
+
+async fn static_handler(Path(path): Path<String>) -> Response {
+    let data: Option<Cow<'static, [u8]>> = match override_dir() {
+        // Only plain relative paths, so requests can't climb out of static/.
+        Some(dir) if FsPath::new(&path).components().all(|part| matches!(part, Component::Normal(_))) => {
+            tokio::fs::read(dir.join("static").join(&path)).await.ok().map(Cow::Owned)
+        }
+        Some(_) => None,
+        None => Static::get(&path).map(|file| file.data),
+    };
+    match data {
+        Some(data) => ([(header::CONTENT_TYPE, content_type(&path))], data.into_owned()).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
@@ -0,0 +1,418 @@
+//! Sign-in and CSRF protection. The web client can call any tool, including
+//! destructive ones like `kill_process`, so every page needs a session.
+//!
+//! Users sign in with the token from the server's lock file, or with a
+//! device code from one of the OAuth plugin's providers when their account
+//! is listed in `MCP_WEB_ALLOWED_USERS` (`github:octocat,google:me@example.com`).
+//! Sessions live in memory, so restarting the web client signs everyone out.
+//!
+//! Every POST must carry the session's CSRF token, either as a `csrf_token`
+//! form field or an `X-CSRF-Token` header. Templates rendered during a
+//! request see it as `csrf_token`.
+
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, LazyLock, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, Context as _};
+use async_trait::async_trait;
+use axum::body::{to_bytes, Body};
+use axum::extract::{Path, Query, Request};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Redirect, Response};
+use axum::routing::{get, post};
+use axum::{Form, Router};
+use mcp_common::{lock, runtime_paths, secret};
+use mcp_oauth_plugin::oauth::{DeviceCode, DevicePoll, OAuthClient, OAuthConfig};
+use mcp_oauth_plugin::providers::{self, ProviderRegistry};
+use mcp_oauth_plugin::token_store::{OAuthToken, TokenStorage, TokenStore};
+use serde::{Deserialize, Serialize};
+use tera::Context;
+
+use crate::assets;
+
+const SESSION_COOKIE: &str = "mcp_web_session";
+const SESSION_TTL: Duration = Duration::from_secs(12 * 60 * 60);
+const CSRF_FIELD: &str = "csrf_token";
+const CSRF_HEADER: &str = "x-csrf-token";
+
+/// Largest form body read while looking for the CSRF token.
+const MAX_FORM_BYTES: usize = 16 * 1024 * 1024;
+
+/// Where each provider says who signed in: the user-info endpoint and the
+/// field naming the account.
+const IDENTITIES: &[(&str, &str, &str)] = &[
+    ("github", "https://api.github.com/user", "login"),
+    ("google", "https://openidconnect.googleapis.com/v1/userinfo", "email"),
+];
+
+struct Session {
+    user: String,
+    csrf_token: String,
+    expires_at: Instant,
+}
+
+// A device-code sign-in waiting for the user to approve it.
+struct DeviceLogin {
+    provider: String,
+    code: DeviceCode,
+    next: String,
+    expires_at: Instant,
+    next_poll_at: Instant,
+}
+
+static SESSIONS: LazyLock<Mutex<HashMap<String, Session>>> = LazyLock::new(Default::default);
+static DEVICE_LOGINS: LazyLock<Mutex<HashMap<String, DeviceLogin>>> = LazyLock::new(Default::default);
+
+/// The signed-in user and their CSRF token, for the request being handled.
+#[derive(Debug, Clone)]
+pub struct Current {
+    pub user: String,
+    pub csrf_token: String,
+}
+
+tokio::task_local! {
+    static CURRENT: Current;
+}
+
+/// The session of the request being handled, if it has one.
+pub fn current() -> Option<Current> {
+    CURRENT.try_with(Clone::clone).ok()
+}
+
+/// The sign-in pages, which work without a session.
+pub fn router() -> Router {
+    Router::new()
+        .route("/login", get(login_page))
+        .route("/login/token", post(token_login))
+        .route("/login/device/:provider", post(start_device_login))
+        .route("/login/device", get(device_login_page))
+        .route("/logout", post(logout))
+}
+
+// Tokens from sign-ins only prove who the user is; they are never stored.
+#[derive(Debug)]
+struct DiscardStorage;
+
+#[async_trait]
+impl TokenStorage for DiscardStorage {
+    async fn load(&self) -> anyhow::Result<HashMap<String, OAuthToken>> {
+        Ok(HashMap::new())
+    }
+
+    async fn save(&self, _tokens: &HashMap<String, OAuthToken>) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+fn allowed_users() -> Vec<String> {
+    env::var("MCP_WEB_ALLOWED_USERS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|user| user.trim().to_string())
+        .filter(|user| !user.is_empty())
+        .collect()
+}
+
+// Providers offered on the sign-in page: configured for device codes, with a
+// known user-info endpoint and at least one allowed account.
+fn login_providers() -> HashMap<String, OAuthConfig> {
+    let allowed = allowed_users();
+    ProviderRegistry::with_presets()
+        .configs()
+        .iter()
+        .filter(|(id, config)| {
+            config.device_auth_url.is_some()
+                && IDENTITIES.iter().any(|(provider, ..)| provider == id)
+                && allowed.iter().any(|user| user.starts_with(&format!("{}:", id)))
+        })
+        .map(|(id, config)| (id.clone(), config.clone()))
+        .collect()
+}
+
+// Only paths on this site, so `next` can't send the user elsewhere.
+fn safe_next(next: Option<&str>) -> String {
+    match next {
+        Some(next) if next.starts_with('/') && !next.starts_with("//") && !next.starts_with("/\\") => next.to_string(),
+        _ => "/".to_string(),
+    }
+}
+
+fn session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .find_map(|cookie| cookie.trim().strip_prefix(SESSION_COOKIE)?.strip_prefix('='))
+        .map(str::to_string)
+}
+
+fn session_for(headers: &HeaderMap) -> Option<Current> {
+    let id = session_id(headers)?;
+    let mut sessions = SESSIONS.lock().unwrap();
+    let session = sessions.get(&id)?;
+    if session.expires_at <= Instant::now() {
+        sessions.remove(&id);
+        return None;
+    }
+    Some(Current { user: session.user.clone(), csrf_token: session.csrf_token.clone() })
+}
+
+// Starts a session for `user` and sends them on to `next`.
+fn sign_in(user: String, next: &str) -> Response {
+    let id = secret::generate_token();
+    let now = Instant::now();
+    {
+        let mut sessions = SESSIONS.lock().unwrap();
+        sessions.retain(|_, session| session.expires_at > now);
+        sessions.insert(id.clone(), Session { user: user.clone(), csrf_token: secret::generate_token(), expires_at: now + SESSION_TTL });
+    }
+    tracing::info!("{} signed in", user);
+    let cookie = format!("{}={}; Path=/; HttpOnly; SameSite=Strict; Max-Age={}", SESSION_COOKIE, id, SESSION_TTL.as_secs());
+    let mut response = Redirect::to(next).into_response();
+    response.headers_mut().insert(header::SET_COOKIE, HeaderValue::from_str(&cookie).expect("cookie is ASCII"));
+    response
+}
+
+/// Sends visitors without a session to `/login`, rejects POSTs without the
+/// session's CSRF token, and makes the session available to templates.
+pub async fn require_session(request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    if path == "/login" || path.starts_with("/login/") || path.starts_with("/static/") {
+        return next.run(request).await;
+    }
+    let Some(current) = session_for(request.headers()) else {
+        if request.method() == Method::GET {
+            let target = request.uri().path_and_query().map_or("/", |target| target.as_str());
+            let query: String = url::form_urlencoded::Serializer::new(String::new()).append_pair("next", target).finish();
+            return Redirect::to(&format!("/login?{}", query)).into_response();
+        }
+        return (StatusCode::UNAUTHORIZED, "Sign in first").into_response();
+    };
+    let request = if matches!(*request.method(), Method::GET | Method::HEAD) {
+        request
+    } else {
+        match check_csrf(request, &current.csrf_token).await {
+            Ok(request) => request,
+            Err(response) => return response,
+        }
+    };
+    CURRENT.scope(current, next.run(request)).await
+}
+
+// Finds the CSRF token in the header or the form body, putting the body back.
+async fn check_csrf(request: Request, expected: &str) -> Result<Request, Response> {
+    let rejected = || (StatusCode::FORBIDDEN, "Missing or invalid CSRF token").into_response();
+    if let Some(offered) = request.headers().get(CSRF_HEADER) {
+        return match offered.to_str() {
+            Ok(offered) if secret::token_matches(expected, offered) => Ok(request),
+            _ => Err(rejected()),
+        };
+    }
+    let is_form = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/x-www-form-urlencoded"));
+    if !is_form {
+        return Err(rejected());
+    }
+    let (parts, body) = request.into_parts();
+    let bytes = to_bytes(body, MAX_FORM_BYTES)
+        .await
+        .map_err(|_| (StatusCode::PAYLOAD_TOO_LARGE, "Form too large").into_response())?;
+    let matches = url::form_urlencoded::parse(&bytes)
+        .find(|(name, _)| name == CSRF_FIELD)
+        .is_some_and(|(_, offered)| secret::token_matches(expected, &offered));
+    if !matches {
+        return Err(rejected());
+    }
+    Ok(Request::from_parts(parts, Body::from(bytes)))
+}
+
+#[derive(Debug, Serialize)]
+struct LoginProvider {
+    id: String,
+    name: String,
+}
+
+fn render_login(next: &str, error: Option<String>) -> Response {
+    let mut providers: Vec<LoginProvider> = login_providers()
+        .into_keys()
+        .map(|id| LoginProvider { name: providers::preset(&id).map_or_else(|| id.clone(), |preset| preset.name.to_string()), id })
+        .collect();
+    providers.sort_by(|a, b| a.id.cmp(&b.id));
+    let mut context = Context::new();
+    context.insert("next", next);
+    context.insert("providers", &providers);
+    context.insert("error", &error);
+    assets::render("login.html", &context)
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LoginQuery {
+    next: Option<String>,
+}
+
+async fn login_page(Query(query): Query<LoginQuery>) -> Response {
+    render_login(&safe_next(query.next.as_deref()), None)
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenForm {
+    token: String,
+    next: Option<String>,
+}
+
+// Signs in whoever can read the server's lock file.
+async fn token_login(Form(form): Form<TokenForm>) -> Response {
+    let next = safe_next(form.next.as_deref());
+    let expected = match runtime_paths::lock_file_path().map_err(anyhow::Error::from).and_then(|path| lock::read(&path)) {
+        Ok(lock_data) => lock_data.token,
+        Err(e) => return render_login(&next, Some(format!("Failed to read the MCP server's lock file. Is the server running? Error: {}", e))),
+    };
+    match expected {
+        Some(expected) if secret::token_matches(&expected, form.token.trim()) => sign_in("local user".to_string(), &next),
+        Some(_) => render_login(&next, Some("That is not the server's token.".to_string())),
+        None => render_login(&next, Some("The MCP server's lock file has no token.".to_string())),
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceForm {
+    next: Option<String>,
+}
+
+async fn start_device_login(Path(provider): Path<String>, Form(form): Form<DeviceForm>) -> Response {
+    let next = safe_next(form.next.as_deref());
+    let Some(config) = login_providers().remove(&provider) else {
+        return render_login(&next, Some(format!("Signing in with {} is not enabled", provider)));
+    };
+    let code = match device_client(config).await {
+        Ok(client) => client.start_device_authorization().await,
+        Err(e) => Err(e),
+    };
+    let code = match code {
+        Ok(code) => code,
+        Err(e) => {
+            tracing::error!("Device sign-in with {} failed to start: {:?}", provider, e);
+            return render_login(&next, Some(format!("Failed to start signing in with {}: {:#}", provider, e)));
+        }
+    };
+    let id = secret::generate_token();
+    let now = Instant::now();
+    let mut logins = DEVICE_LOGINS.lock().unwrap();
+    logins.retain(|_, login| login.expires_at > now);
+    logins.insert(
+        id.clone(),
+        DeviceLogin { provider, expires_at: now + Duration::from_secs(code.expires_in), next_poll_at: now, code, next },
+    );
+    Redirect::to(&format!("/login/device?id={}", id)).into_response()
+}
+
+async fn device_client(config: OAuthConfig) -> anyhow::Result<OAuthClient> {
+    Ok(OAuthClient::new(config, TokenStore::with_storage(Arc::new(DiscardStorage)).await?))
+}
+
+#[derive(Debug, Deserialize)]
+struct DeviceQuery {
+    id: String,
+}
+
+// Shows the user code, asking the provider whether it was approved each time
+// the page refreshes.
+async fn device_login_page(Query(query): Query<DeviceQuery>) -> Response {
+    let (provider, device_code, next, poll_due) = {
+        let logins = DEVICE_LOGINS.lock().unwrap();
+        let Some(login) = logins.get(&query.id).filter(|login| login.expires_at > Instant::now()) else {
+            return render_login("/", Some("The sign-in expired; start it again.".to_string()));
+        };
+        (login.provider.clone(), login.code.device_code.clone(), login.next.clone(), login.next_poll_at <= Instant::now())
+    };
+
+    if poll_due {
+        match poll_device_login(&provider, &device_code).await {
+            Ok(DevicePoll::Complete(token)) => {
+                DEVICE_LOGINS.lock().unwrap().remove(&query.id);
+                return match verify_identity(&provider, &token.access_token).await {
+                    Ok(user) => sign_in(user, &next),
+                    Err(e) => {
+                        tracing::warn!("Sign-in with {} refused: {:#}", provider, e);
+                        render_login(&next, Some(format!("{:#}", e)))
+                    }
+                };
+            }
+            Ok(DevicePoll::Pending) => {}
+            Ok(DevicePoll::SlowDown { interval }) => {
+                if let Some(login) = DEVICE_LOGINS.lock().unwrap().get_mut(&query.id) {
+                    login.code.interval = interval;
+                }
+            }
+            Err(e) => {
+                DEVICE_LOGINS.lock().unwrap().remove(&query.id);
+                return render_login(&next, Some(format!("Signing in with {} failed: {:#}", provider, e)));
+            }
+        }
+    }
+
+    let mut logins = DEVICE_LOGINS.lock().unwrap();
+    let Some(login) = logins.get_mut(&query.id) else {
+        return render_login(&next, Some("The sign-in expired; start it again.".to_string()));
+    };
+    if poll_due {
+        login.next_poll_at = Instant::now() + Duration::from_secs(login.code.interval);
+    }
+    let mut context = Context::new();
+    context.insert("name", &providers::preset(&provider).map_or(provider.as_str(), |preset| preset.name));
+    context.insert("user_code", &login.code.user_code);
+    context.insert("verification_uri", &login.code.verification_uri);
+    if let Some(complete) = &login.code.verification_uri_complete {
+        context.insert("verification_uri_complete", complete);
+    }
+    context.insert("interval", &login.code.interval);
+    drop(logins);
+    assets::render("login_device.html", &context)
+}
+
+async fn poll_device_login(provider: &str, device_code: &str) -> anyhow::Result<DevicePoll> {
+    let config = login_providers().remove(provider).ok_or_else(|| anyhow!("Signing in with {} is not enabled", provider))?;
+    device_client(config).await?.poll_device_authorization(provider, device_code).await
+}
+
+// The `provider:account` the token belongs to, if that account is allowed in.
+async fn verify_identity(provider: &str, access_token: &str) -> anyhow::Result<String> {
+    let (_, userinfo_url, field) = IDENTITIES
+        .iter()
+        .find(|(id, ..)| *id == provider)
+        .ok_or_else(|| anyhow!("{} can't say who signed in", provider))?;
+    let userinfo: serde_json::Value = reqwest::Client::new()
+        .get(*userinfo_url)
+        .bearer_auth(access_token)
+        .header(header::USER_AGENT.as_str(), "mcp_web_client")
+        .send()
+        .await
+        .and_then(reqwest::Response::error_for_status)
+        .with_context(|| format!("Failed to look up the {} account", provider))?
+        .json()
+        .await
+        .with_context(|| format!("Failed to parse the {} account", provider))?;
+    let account = userinfo[*field].as_str().ok_or_else(|| anyhow!("{} did not name the account", provider))?;
+    let user = format!("{}:{}", provider, account);
+    if !allowed_users().iter().any(|allowed| allowed.eq_ignore_ascii_case(&user)) {
+        return Err(anyhow!("{} is not allowed to use this web client", user));
+    }
+    Ok(user)
+}
+
+async fn logout(headers: HeaderMap) -> Response {
+    if let Some(id) = session_id(&headers) {
+        SESSIONS.lock().unwrap().remove(&id);
+    }
+    let cookie = format!("{}=; Path=/; HttpOnly; SameSite=Strict; Max-Age=0", SESSION_COOKIE);
+    let mut response = Redirect::to("/login").into_response();
+    response.headers_mut().insert(header::SET_COOKIE, HeaderValue::from_str(&cookie).expect("cookie is ASCII"));
+    response
+}
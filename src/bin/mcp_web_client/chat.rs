@@ -0,0 +1,201 @@
+//! `/chat`: a conversation with the server's `chat_completion` tool.
+//!
+//! The browser keeps the conversation and POSTs it to `/chat/turn`, which
+//! answers with Server-Sent Events: `delta` as the reply streams in, then
+//! `message` for each message to add to the conversation. The LLM driver has
+//! no native function calling, so the system prompt lists the server's tools
+//! and asks for a fenced `tool_call` block instead; the web client runs the
+//! call, reports it with `tool_call` and `tool_result`, and feeds the result
+//! back until the model answers without one.
+
+use std::convert::Infallible;
+use std::env;
+
+use anyhow::anyhow;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::{stream, Stream};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tera::Context;
+use tokio::sync::mpsc;
+
+use crate::{assets, list_tools, output_items, pool, send_mcp_request, OutputItem};
+
+const DEFAULT_MODEL: &str = "gpt-4o-mini";
+
+/// Tool calls the model may make before it has to answer.
+const MAX_TOOL_ROUNDS: usize = 5;
+
+/// Longest tool result fed back to the model, in characters.
+const MAX_RESULT_CHARS: usize = 8_000;
+
+const TOOL_CALL_FENCE: &str = "```tool_call";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Message {
+    role: String,
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TurnRequest {
+    messages: Vec<Message>,
+    model: Option<String>,
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/chat", get(chat_handler))
+        .route("/chat/turn", post(turn_handler))
+}
+
+fn default_model() -> String {
+    env::var("MCP_WEB_CHAT_MODEL").unwrap_or_else(|_| DEFAULT_MODEL.to_string())
+}
+
+async fn chat_handler() -> impl IntoResponse {
+    let mut context = Context::new();
+    context.insert("model", &default_model());
+    assets::render("chat.html", &context)
+}
+
+// Describes every tool but chat_completion itself, and how to call one.
+fn system_prompt(tools: &[Value]) -> String {
+    let mut prompt = String::from(
+        "You can call tools on an MCP server. To call one, reply with only a fenced block:\n\
+         ```tool_call\n{\"name\": \"<tool name>\", \"arguments\": { ... }}\n```\n\
+         The result comes back in the next message. Call one tool at a time. \
+         Tools marked dangerous need \"confirm\": true in their arguments; only pass it when the user asked for that action. \
+         When you can answer, reply normally without a tool_call block.\n\nTools:\n",
+    );
+    for tool in tools.iter().filter(|tool| tool["name"] != "chat_completion") {
+        prompt.push_str(&format!(
+            "- {}: {}\n  arguments schema: {}\n",
+            tool["name"].as_str().unwrap_or_default(),
+            tool["description"].as_str().unwrap_or_default(),
+            tool["inputSchema"],
+        ));
+    }
+    prompt
+}
+
+// The `{ "name", "arguments" }` in the reply's tool_call block, if any.
+fn parse_tool_call(reply: &str) -> Option<Result<(String, Value), String>> {
+    let start = reply.find(TOOL_CALL_FENCE)? + TOOL_CALL_FENCE.len();
+    let body = &reply[start..];
+    let body = &body[..body.find("```").unwrap_or(body.len())];
+    let call: Value = match serde_json::from_str(body.trim()) {
+        Ok(call) => call,
+        Err(e) => return Some(Err(format!("The tool_call block is not valid JSON: {}", e))),
+    };
+    match call["name"].as_str() {
+        Some(name) => Some(Ok((name.to_string(), call.get("arguments").cloned().unwrap_or_else(|| json!({}))))),
+        None => Some(Err("The tool_call block has no \"name\"".to_string())),
+    }
+}
+
+// What the model sees of a tool result: its text, with binary parts named.
+fn result_text(items: &[OutputItem]) -> String {
+    let text: String = items
+        .iter()
+        .map(|item| match item {
+            OutputItem::Text { text } => text.clone(),
+            OutputItem::Image { mime_type, .. } => format!("[{} image, shown to the user]", mime_type),
+            OutputItem::Blob { name, mime_type, size, .. } => format!("[{} ({}, {} bytes), offered to the user]", name, mime_type, size),
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    match text.char_indices().nth(MAX_RESULT_CHARS) {
+        Some((end, _)) => format!("{}\n[truncated]", &text[..end]),
+        None => text,
+    }
+}
+
+fn json_event(name: &str, data: Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
+
+// Sends the conversation to chat_completion, forwarding the reply as it streams.
+async fn complete(messages: &[Message], model: &str, events: &mpsc::UnboundedSender<Event>) -> anyhow::Result<String> {
+    let (progress, mut deltas) = mpsc::unbounded_channel();
+    let forward = {
+        let events = events.clone();
+        tokio::spawn(async move {
+            while let Some(delta) = deltas.recv().await {
+                let _ = events.send(json_event("delta", json!({ "text": delta })));
+            }
+        })
+    };
+    let params = json!({ "name": "chat_completion", "arguments": { "messages": messages, "model": model } });
+    let response = pool::POOL.request_with_progress("tools/call", params, Some(progress)).await;
+    let _ = forward.await;
+    let response = response?;
+    let result = &response["result"];
+    if result["isError"].as_bool().unwrap_or(false) {
+        return Err(anyhow!("chat_completion failed: {}", result["content"][0]["text"].as_str().unwrap_or_default()));
+    }
+    let structured = if result["structuredContent"].is_null() {
+        serde_json::from_str(result["content"][0]["text"].as_str().unwrap_or_default())?
+    } else {
+        result["structuredContent"].clone()
+    };
+    Ok(structured["choices"][0]["message"]["content"].as_str().unwrap_or_default().to_string())
+}
+
+// Runs one user turn, including any tool calls, sending events as it goes.
+async fn run_turn(request: TurnRequest, events: mpsc::UnboundedSender<Event>) -> anyhow::Result<()> {
+    let model = request.model.filter(|model| !model.is_empty()).unwrap_or_else(default_model);
+    let tools = list_tools().await?;
+    let mut conversation = vec![Message { role: "system".to_string(), content: system_prompt(&tools) }];
+    conversation.extend(request.messages.into_iter().filter(|message| message.role != "system"));
+
+    for _ in 0..=MAX_TOOL_ROUNDS {
+        let reply = complete(&conversation, &model, &events).await?;
+        let assistant = Message { role: "assistant".to_string(), content: reply };
+        let _ = events.send(json_event("message", json!(assistant)));
+        let call = parse_tool_call(&assistant.content);
+        conversation.push(assistant);
+
+        let feedback = match call {
+            None => return Ok(()),
+            Some(Err(e)) => e,
+            Some(Ok((name, arguments))) => {
+                let _ = events.send(json_event("tool_call", json!({ "name": name, "arguments": arguments })));
+                let (items, is_error) = match send_mcp_request("tools/call", json!({ "name": name, "arguments": arguments })).await {
+                    Ok(response) => {
+                        let result = &response["result"];
+                        (output_items(result), result["isError"].as_bool().unwrap_or(false))
+                    }
+                    Err(e) => (vec![OutputItem::Text { text: e.to_string() }], true),
+                };
+                let _ = events.send(json_event("tool_result", json!({ "name": name, "items": items, "is_error": is_error })));
+                format!("Result of {}{}:\n{}", name, if is_error { " (error)" } else { "" }, result_text(&items))
+            }
+        };
+        let message = Message { role: "user".to_string(), content: feedback };
+        let _ = events.send(json_event("message", json!(message)));
+        conversation.push(message);
+    }
+    Err(anyhow!("Stopped after {} tool calls without an answer", MAX_TOOL_ROUNDS))
+}
+
+async fn turn_handler(Json(request): Json<TurnRequest>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let (sender, receiver) = mpsc::unbounded_channel();
+    tokio::spawn(async move {
+        let done = match run_turn(request, sender.clone()).await {
+            Ok(()) => json_event("done", json!({})),
+            Err(e) => {
+                tracing::error!("Chat turn failed: {:?}", e);
+                json_event("error", json!({ "message": format!("{:#}", e) }))
+            }
+        };
+        let _ = sender.send(done);
+    });
+    let events = stream::unfold(receiver, |mut receiver| async move {
+        receiver.recv().await.map(|event| (Ok(event), receiver))
+    });
+    Sse::new(events).keep_alive(KeepAlive::default())
+}
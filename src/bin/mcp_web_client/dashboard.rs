@@ -0,0 +1,140 @@
+//! `/dashboard`: a live view of the MCP server. `/dashboard/events` streams
+//! Server-Sent Events built from the server's own tools: `metrics` (new
+//! samples from get_system_metrics), `connections` (established TCP
+//! connections to the server's port, from list_ports) and `calls` (the latest
+//! entries of get_audit_log).
+
+use std::convert::Infallible;
+use std::env;
+use std::time::Duration;
+
+use anyhow::anyhow;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use futures::{stream, Stream, StreamExt};
+use mcp_common::{lock, runtime_paths};
+use serde_json::{json, Value};
+use tera::Context;
+
+use crate::{assets, send_mcp_request};
+
+const DEFAULT_INTERVAL_SECS: u64 = 2;
+
+/// Metrics history sent when a dashboard opens, so charts start filled.
+const INITIAL_HISTORY: &str = "10m";
+
+const RECENT_CALLS: usize = 20;
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/dashboard", get(dashboard_handler))
+        .route("/dashboard/events", get(events_handler))
+}
+
+async fn dashboard_handler() -> impl IntoResponse {
+    let mut context = Context::new();
+    context.insert("interval_secs", &interval().as_secs());
+    assets::render("dashboard.html", &context)
+}
+
+fn interval() -> Duration {
+    let secs = env::var("MCP_DASHBOARD_INTERVAL_SECS")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .filter(|&secs| secs > 0)
+        .unwrap_or(DEFAULT_INTERVAL_SECS);
+    Duration::from_secs(secs)
+}
+
+// A tool's structured output, or its text parsed as JSON.
+async fn tool_output(name: &str, arguments: Value) -> anyhow::Result<Value> {
+    let response = send_mcp_request("tools/call", json!({ "name": name, "arguments": arguments })).await?;
+    let result = &response["result"];
+    let text = result["content"][0]["text"].as_str().unwrap_or_default();
+    if result["isError"].as_bool().unwrap_or(false) {
+        return Err(anyhow!("{} failed: {}", name, text));
+    }
+    if !result["structuredContent"].is_null() {
+        return Ok(result["structuredContent"].clone());
+    }
+    Ok(serde_json::from_str(text)?)
+}
+
+// Metric samples newer than `since`, oldest first.
+async fn metrics(since: Option<&str>) -> anyhow::Result<Vec<Value>> {
+    let output = tool_output("get_system_metrics", json!({ "since": since.unwrap_or(INITIAL_HISTORY) })).await?;
+    let samples = output["samples"].as_array().cloned().unwrap_or_default();
+    // `since` is inclusive, so the last sample already sent comes back.
+    Ok(samples
+        .into_iter()
+        .filter(|sample| since.is_none_or(|since| sample["timestamp"].as_str() != Some(since)))
+        .collect())
+}
+
+async fn connections() -> anyhow::Result<Vec<Value>> {
+    let port = lock::read(&runtime_paths::lock_file_path()?)?.port;
+    let output = tool_output("list_ports", json!({})).await?;
+    Ok(output["connections"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|connection| {
+            connection["local_port"] == port
+                && connection["status"].as_str().is_some_and(|status| status.eq_ignore_ascii_case("established"))
+        })
+        .cloned()
+        .collect())
+}
+
+async fn recent_calls() -> anyhow::Result<Value> {
+    let output = tool_output("get_audit_log", json!({ "limit": RECENT_CALLS })).await?;
+    Ok(output["entries"].clone())
+}
+
+fn json_event(name: &str, data: &Value) -> Event {
+    Event::default().event(name).data(data.to_string())
+}
+
+fn error_event(what: &str, e: anyhow::Error) -> Event {
+    json_event("error", &json!({ "source": what, "message": format!("{:#}", e) }))
+}
+
+// One round of events; `last_sample` is the timestamp of the newest sample sent.
+async fn snapshot(last_sample: &mut Option<String>) -> Vec<Event> {
+    let (samples, connections, calls) = tokio::join!(metrics(last_sample.as_deref()), connections(), recent_calls());
+    let mut events = Vec::new();
+    match samples {
+        Ok(samples) => {
+            if let Some(newest) = samples.last().and_then(|sample| sample["timestamp"].as_str()) {
+                *last_sample = Some(newest.to_string());
+            }
+            if !samples.is_empty() {
+                events.push(json_event("metrics", &Value::Array(samples)));
+            }
+        }
+        Err(e) => events.push(error_event("metrics", e)),
+    }
+    match connections {
+        Ok(connections) => events.push(json_event("connections", &Value::Array(connections))),
+        Err(e) => events.push(error_event("connections", e)),
+    }
+    match calls {
+        Ok(calls) => events.push(json_event("calls", &calls)),
+        Err(e) => events.push(error_event("calls", e)),
+    }
+    events
+}
+
+async fn events_handler() -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let interval = interval();
+    let rounds = stream::unfold((None, true), move |(mut last_sample, first)| async move {
+        if !first {
+            tokio::time::sleep(interval).await;
+        }
+        let events = snapshot(&mut last_sample).await;
+        Some((stream::iter(events.into_iter().map(Ok)), (last_sample, false)))
+    });
+    Sse::new(rounds.flatten()).keep_alive(KeepAlive::default())
+}
@@ -0,0 +1,187 @@
+//! HTML forms generated from tool input schemas, and the conversion of
+//! submitted form fields back into tool arguments.
+//!
+//! Schemas come from `schemars`, so optional fields are `["T", "null"]` or
+//! `anyOf` with a null branch, and enums are `$ref`s into `$defs`.
+
+use std::collections::HashMap;
+
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FieldKind {
+    Text,
+    Integer,
+    Number,
+    Checkbox,
+    Select,
+    /// An array of strings, one per line.
+    Lines,
+    /// Anything else, typed as JSON.
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+    pub required: bool,
+    pub description: Option<String>,
+    pub options: Vec<String>,
+    /// What the input shows: the last submitted value, else the default.
+    pub value: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ToolForm {
+    pub name: String,
+    pub description: String,
+    pub fields: Vec<Field>,
+}
+
+// Follows a local `$ref` and unwraps the non-null branch of an optional.
+fn resolve<'a>(schema: &'a Value, root: &'a Value) -> &'a Value {
+    if let Some(reference) = schema["$ref"].as_str() {
+        let target = reference
+            .strip_prefix("#/$defs/")
+            .map(|name| &root["$defs"][name])
+            .or_else(|| reference.strip_prefix("#/definitions/").map(|name| &root["definitions"][name]));
+        if let Some(target) = target.filter(|target| !target.is_null()) {
+            return resolve(target, root);
+        }
+    }
+    for key in ["anyOf", "oneOf"] {
+        if let Some(branches) = schema[key].as_array() {
+            if let Some(branch) = branches.iter().find(|branch| branch["type"] != "null") {
+                return resolve(branch, root);
+            }
+        }
+    }
+    schema
+}
+
+// `"type": "string"` or the non-null entry of `"type": ["string", "null"]`.
+fn schema_type(schema: &Value) -> Option<&str> {
+    match &schema["type"] {
+        Value::String(name) => Some(name),
+        Value::Array(names) => names.iter().filter_map(Value::as_str).find(|name| *name != "null"),
+        _ => None,
+    }
+}
+
+fn display(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        Value::Array(items) if items.iter().all(Value::is_string) => {
+            items.iter().filter_map(Value::as_str).collect::<Vec<_>>().join("\n")
+        }
+        other => other.to_string(),
+    }
+}
+
+impl Field {
+    fn from_schema(name: &str, schema: &Value, root: &Value, required: bool) -> Self {
+        let resolved = resolve(schema, root);
+        let options: Vec<String> = resolved["enum"].as_array().map(|values| values.iter().map(display).collect()).unwrap_or_default();
+        let kind = match schema_type(resolved) {
+            _ if !options.is_empty() => FieldKind::Select,
+            Some("boolean") => FieldKind::Checkbox,
+            Some("integer") => FieldKind::Integer,
+            Some("number") => FieldKind::Number,
+            Some("array") if schema_type(resolve(&resolved["items"], root)) == Some("string") => FieldKind::Lines,
+            Some("array" | "object") => FieldKind::Json,
+            _ => FieldKind::Text,
+        };
+        let description = schema["description"]
+            .as_str()
+            .or_else(|| resolved["description"].as_str())
+            .map(str::to_string);
+        let default = if schema["default"].is_null() { &resolved["default"] } else { &schema["default"] };
+        Field {
+            name: name.to_string(),
+            kind,
+            required,
+            description,
+            options,
+            value: display(default),
+        }
+    }
+
+    // `None` when an optional field was left empty.
+    fn argument(&self, submitted: Option<&str>) -> Result<Option<Value>, String> {
+        if self.kind == FieldKind::Checkbox {
+            return Ok(Some(Value::Bool(submitted.is_some())));
+        }
+        let Some(text) = submitted.filter(|text| !text.trim().is_empty()) else {
+            return if self.required { Err(format!("{} is required", self.name)) } else { Ok(None) };
+        };
+        let value = match self.kind {
+            FieldKind::Text | FieldKind::Select => Value::String(text.to_string()),
+            FieldKind::Integer => {
+                let number: i64 = text.trim().parse().map_err(|_| format!("{} must be an integer", self.name))?;
+                Value::from(number)
+            }
+            FieldKind::Number => {
+                let number: f64 = text.trim().parse().map_err(|_| format!("{} must be a number", self.name))?;
+                Value::from(number)
+            }
+            FieldKind::Lines => Value::Array(
+                text.lines()
+                    .map(str::trim)
+                    .filter(|line| !line.is_empty())
+                    .map(|line| Value::String(line.to_string()))
+                    .collect(),
+            ),
+            FieldKind::Json => serde_json::from_str(text).map_err(|e| format!("{} is not valid JSON: {}", self.name, e))?,
+            FieldKind::Checkbox => unreachable!(),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl ToolForm {
+    /// Builds the form for one entry of `tools/list`.
+    pub fn from_tool(tool: &Value) -> Self {
+        let schema = &tool["inputSchema"];
+        let required: Vec<&str> = schema["required"].as_array().map(|names| names.iter().filter_map(Value::as_str).collect()).unwrap_or_default();
+        let fields = schema["properties"]
+            .as_object()
+            .map(|properties| {
+                properties
+                    .iter()
+                    .map(|(name, property)| Field::from_schema(name, property, schema, required.contains(&name.as_str())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        ToolForm {
+            name: tool["name"].as_str().unwrap_or_default().to_string(),
+            description: tool["description"].as_str().unwrap_or_default().to_string(),
+            fields,
+        }
+    }
+
+    /// Shows `submitted` in the inputs instead of the defaults.
+    pub fn fill(&mut self, submitted: &HashMap<String, String>) {
+        for field in &mut self.fields {
+            match field.kind {
+                // An unchecked box isn't submitted at all.
+                FieldKind::Checkbox => field.value = submitted.contains_key(&field.name).to_string(),
+                _ => field.value = submitted.get(&field.name).cloned().unwrap_or_default(),
+            }
+        }
+    }
+
+    /// Converts submitted fields into the tool's arguments.
+    pub fn arguments(&self, submitted: &HashMap<String, String>) -> Result<Map<String, Value>, String> {
+        let mut arguments = Map::new();
+        for field in &self.fields {
+            if let Some(value) = field.argument(submitted.get(&field.name).map(String::as_str))? {
+                arguments.insert(field.name.clone(), value);
+            }
+        }
+        Ok(arguments)
+    }
+}
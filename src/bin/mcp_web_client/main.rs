@@ -0,0 +1,239 @@
+use axum::{
+    extract::Path,
+    routing::{get, post},
+    Router,
+    response::{IntoResponse, Response},
+    Form,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use tracing_subscriber;
+use tracing;
+use tera::Context;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+mod assets;
+mod auth;
+mod chat;
+mod dashboard;
+mod forms;
+mod pool;
+
+use forms::ToolForm;
+
+// Address of the MCP server. This should ideally be configurable (e.g., via environment variable).
+// For now, hardcode it to a common local address.
+// const MCP_SERVER_ADDR: &str = "127.0.0.1:21230"; // Using port from previous Python run
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    // Initialize tracing
+    tracing_subscriber::fmt().init();
+
+    tracing::info!("Starting MCP Web Client.");
+
+    // build our application with routes
+    let app = Router::new()
+        .route("/", get(index_handler))
+        .route("/process", post(process_handler))
+        .route("/tools", get(tools_handler))
+        .route("/tools/:name", get(tool_handler).post(call_tool_handler))
+        .merge(dashboard::router())
+        .merge(chat::router())
+        .merge(assets::router())
+        .merge(auth::router())
+        .layer(axum::middleware::from_fn(auth::require_session));
+
+    // run it with hyper on localhost:3000
+    let addr = SocketAddr::from(([127, 0, 0, 1], 3000));
+    tracing::info!("listening on {}", addr);
+
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app).await?;
+
+    Ok(())
+}
+
+async fn index_handler() -> impl IntoResponse {
+    assets::render("index.html", &Context::new())
+}
+
+#[derive(Debug, Deserialize)]
+struct ProcessForm {
+    content: String,
+}
+
+// One renderable piece of a tool result.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum OutputItem {
+    Text { text: String },
+    Image { mime_type: String, data: String },
+    Blob { name: String, mime_type: String, data: String, size: usize },
+}
+
+// Only raster formats are inlined as <img>; anything else is offered as a download.
+const INLINE_IMAGE_TYPES: &[&str] = &["image/png", "image/jpeg", "image/gif", "image/webp"];
+
+fn output_items(result: &Value) -> Vec<OutputItem> {
+    let Some(content) = result["content"].as_array() else {
+        return vec![OutputItem::Text { text: result.to_string() }];
+    };
+    let max_encoded = copilot_mcp_tool::content::max_binary_bytes() / 3 * 4 + 4;
+    content
+        .iter()
+        .enumerate()
+        .map(|(index, item)| {
+            let (data, mime_type, name) = match item["type"].as_str() {
+                Some("text") => return OutputItem::Text { text: item["text"].as_str().unwrap_or_default().to_string() },
+                Some("image") => (item["data"].as_str(), item["mimeType"].as_str(), None),
+                Some("resource") if item["resource"]["blob"].is_string() => (
+                    item["resource"]["blob"].as_str(),
+                    item["resource"]["mimeType"].as_str(),
+                    item["resource"]["uri"].as_str(),
+                ),
+                Some("resource") => {
+                    return OutputItem::Text { text: item["resource"]["text"].as_str().unwrap_or_default().to_string() }
+                }
+                _ => return OutputItem::Text { text: item.to_string() },
+            };
+            let data = data.unwrap_or_default().to_string();
+            let mime_type = mime_type.unwrap_or("application/octet-stream").to_string();
+            if data.len() > max_encoded {
+                return OutputItem::Text { text: format!("[{} content too large to display]", mime_type) };
+            }
+            if name.is_none() && INLINE_IMAGE_TYPES.contains(&mime_type.as_str()) {
+                return OutputItem::Image { mime_type, data };
+            }
+            let extension = copilot_mcp_tool::content::extension_for(&mime_type);
+            let name = name
+                .and_then(|uri| uri.rsplit('/').next())
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .unwrap_or_else(|| format!("content-{}.{}", index, extension));
+            OutputItem::Blob { name, mime_type, size: data.len() / 4 * 3, data }
+        })
+        .collect()
+}
+
+async fn process_handler(Form(form): Form<ProcessForm>) -> impl IntoResponse {
+    tracing::info!("Received content: {}", form.content);
+
+    let params = json!({ "name": "echo_message", "arguments": { "message": form.content } });
+    let items = match send_mcp_request("tools/call", params).await {
+        Ok(response) => {
+            tracing::info!("MCP Server Response: {:?}", response);
+            output_items(&response["result"])
+        },
+        Err(e) => {
+            tracing::error!("Error communicating with MCP server: {:?}", e);
+            vec![OutputItem::Text { text: format!("Error communicating with MCP server: {}", e) }]
+        }
+    };
+
+    let mut context = Context::new();
+    context.insert("items", &items);
+    assets::render("index.html", &context)
+}
+
+// Every tool the server offers, following `tools/list` pagination.
+async fn list_tools() -> Result<Vec<Value>, anyhow::Error> {
+    let mut tools = Vec::new();
+    let mut cursor: Option<String> = None;
+    loop {
+        let params = match &cursor {
+            Some(cursor) => json!({ "cursor": cursor }),
+            None => json!({}),
+        };
+        let response = send_mcp_request("tools/list", params).await?;
+        let result = &response["result"];
+        tools.extend(result["tools"].as_array().cloned().unwrap_or_default());
+        match result["nextCursor"].as_str() {
+            Some(next) => cursor = Some(next.to_string()),
+            None => return Ok(tools),
+        }
+    }
+}
+
+async fn find_tool(name: &str) -> Result<ToolForm, anyhow::Error> {
+    list_tools()
+        .await?
+        .iter()
+        .find(|tool| tool["name"] == name)
+        .map(ToolForm::from_tool)
+        .ok_or_else(|| anyhow::anyhow!("The MCP server has no tool named {}", name))
+}
+
+// Lists every tool with a form generated from its input schema.
+async fn tools_handler() -> impl IntoResponse {
+    let mut context = Context::new();
+    match list_tools().await {
+        Ok(tools) => context.insert("tools", &tools.iter().map(ToolForm::from_tool).collect::<Vec<_>>()),
+        Err(e) => {
+            tracing::error!("Error listing tools: {:?}", e);
+            context.insert("tools", &Vec::<ToolForm>::new());
+            context.insert("error", &format!("Error communicating with MCP server: {}", e));
+        }
+    }
+    assets::render("tools.html", &context)
+}
+
+fn render_tool(tool: &ToolForm, items: Option<Vec<OutputItem>>, is_error: bool, error: Option<String>) -> Response {
+    let mut context = Context::new();
+    context.insert("tool", tool);
+    context.insert("items", &items);
+    context.insert("is_error", &is_error);
+    context.insert("error", &error);
+    assets::render("tool.html", &context)
+}
+
+async fn tool_handler(Path(name): Path<String>) -> impl IntoResponse {
+    match find_tool(&name).await {
+        Ok(tool) => render_tool(&tool, None, false, None),
+        Err(e) => tools_error(e),
+    }
+}
+
+// Calls a tool with the fields of its generated form.
+async fn call_tool_handler(Path(name): Path<String>, Form(submitted): Form<HashMap<String, String>>) -> impl IntoResponse {
+    let mut tool = match find_tool(&name).await {
+        Ok(tool) => tool,
+        Err(e) => return tools_error(e),
+    };
+    let arguments = tool.arguments(&submitted);
+    tool.fill(&submitted);
+    let arguments = match arguments {
+        Ok(arguments) => arguments,
+        Err(e) => return render_tool(&tool, None, false, Some(e)),
+    };
+
+    tracing::info!("Calling tool {}", name);
+    let params = json!({ "name": name, "arguments": arguments });
+    match send_mcp_request("tools/call", params).await {
+        Ok(response) => {
+            let result = &response["result"];
+            let is_error = result["isError"].as_bool().unwrap_or(false);
+            render_tool(&tool, Some(output_items(result)), is_error, None)
+        }
+        Err(e) => {
+            tracing::error!("Error calling tool {}: {:?}", name, e);
+            render_tool(&tool, None, false, Some(format!("Error communicating with MCP server: {}", e)))
+        }
+    }
+}
+
+fn tools_error(e: anyhow::Error) -> Response {
+    let mut context = Context::new();
+    context.insert("tools", &Vec::<ToolForm>::new());
+    context.insert("error", &e.to_string());
+    assets::render("tools.html", &context)
+}
+
+// Sends a JSON-RPC request on a pooled session with the MCP server.
+async fn send_mcp_request(method: &str, params: Value) -> Result<Value, anyhow::Error> {
+    tracing::info!("Sending MCP request: {}", method);
+    let response = pool::POOL.request(method, params).await?;
+    tracing::debug!("Received MCP response: {}", response);
+    Ok(response)
+}
@@ -0,0 +1,247 @@
+//! Initialized sessions with the MCP server, kept open between page loads.
+//!
+//! A handler checks a connection out, sends its request and puts it back.
+//! Connections are opened lazily from the lock file, so the web client can
+//! start before the server and follows it across restarts. A connection that
+//! dies while idle is dropped; a request that fails on a reused connection is
+//! retried once on a fresh one.
+//!
+//! Requests may ask for progress: the request id doubles as the progress
+//! token, and `notifications/progress` messages for it are forwarded.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, LazyLock, Mutex};
+
+use anyhow::{anyhow, Result};
+use mcp_common::framing::{self, Framing};
+use mcp_common::{jsonrpc, lock, runtime_paths};
+use serde_json::{json, Value};
+use tokio::io::BufReader;
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
+
+/// Idle connections kept open unless `MCP_WEB_POOL_SIZE` says otherwise.
+const DEFAULT_POOL_SIZE: usize = 4;
+
+pub static POOL: LazyLock<Pool> = LazyLock::new(|| {
+    let max_idle = std::env::var("MCP_WEB_POOL_SIZE")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_POOL_SIZE);
+    Pool { idle: Mutex::new(Vec::new()), max_idle }
+});
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+type Writer = Arc<tokio::sync::Mutex<OwnedWriteHalf>>;
+/// Receives the `message` of each progress notification for a request.
+pub type ProgressSender = mpsc::UnboundedSender<String>;
+type Progress = Arc<Mutex<HashMap<u64, ProgressSender>>>;
+
+// Failures of the connection itself, as opposed to errors the server returned.
+#[derive(Debug)]
+struct Disconnected(anyhow::Error);
+
+impl std::fmt::Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.0)
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+struct Connection {
+    writer: Writer,
+    pending: Pending,
+    progress: Progress,
+    next_id: AtomicU64,
+    closed: Arc<AtomicBool>,
+    reader_task: JoinHandle<()>,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        self.reader_task.abort();
+    }
+}
+
+impl Connection {
+    // Connects to the server in the lock file and completes `initialize`.
+    async fn open() -> Result<Self> {
+        let lock_data = lock::read(&runtime_paths::lock_file_path()?)
+            .map_err(|e| anyhow!("Failed to read MCP server lock file. Is the server running? Error: {}", e))?;
+        if lock_data.tls_cert.is_some() {
+            return Err(anyhow!("The MCP server requires TLS, which the web client does not support yet"));
+        }
+
+        tracing::info!("Connecting to MCP server at {}:{}", lock_data.host(), lock_data.port);
+        let stream = lock_data.connect().await?;
+        let (reader, mut writer) = stream.into_split();
+        let mut reader = BufReader::new(reader);
+
+        let init_request = json!({
+            "jsonrpc": "2.0",
+            "id": 0,
+            "method": "initialize",
+            "params": {
+                "protocolVersion": "2025-03-26",
+                "capabilities": {
+                    "roots": { "listChanged": true },
+                    "experimental": { "auth": { "token": lock_data.token } }
+                },
+                "clientInfo": { "name": "mcp_web_client", "version": "0.1.0" }
+            },
+        });
+        jsonrpc::write_message(&mut writer, Framing::Newline, &init_request).await?;
+        let init_response = jsonrpc::read_response(&mut reader, &mut writer, Framing::Newline, &json!(0)).await?;
+        if let Some(error) = init_response.get("error") {
+            return Err(anyhow!("MCP server refused the connection: {}", error));
+        }
+        let initialized = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        jsonrpc::write_message(&mut writer, Framing::Newline, &initialized).await?;
+        tracing::info!("Connected to MCP server.");
+
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(writer));
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let progress: Progress = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let reader_task = tokio::spawn(read_messages(reader, writer.clone(), pending.clone(), progress.clone(), closed.clone()));
+        Ok(Connection {
+            writer,
+            pending,
+            progress,
+            next_id: AtomicU64::new(1),
+            closed,
+            reader_task,
+        })
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+
+    async fn request(&self, method: &str, mut params: Value, progress: Option<ProgressSender>) -> Result<Value> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, sender);
+        if let Some(progress) = progress {
+            params["_meta"]["progressToken"] = json!(id);
+            self.progress.lock().unwrap().insert(id, progress);
+        }
+        let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let sent = jsonrpc::write_message(&mut *self.writer.lock().await, Framing::Newline, &request).await;
+        let result = match sent {
+            Ok(()) => receiver
+                .await
+                .map_err(|_| Disconnected(anyhow!("MCP server closed the connection")).into()),
+            Err(e) => {
+                self.pending.lock().unwrap().remove(&id);
+                self.closed.store(true, Ordering::Relaxed);
+                Err(Disconnected(e.into()).into())
+            }
+        };
+        self.progress.lock().unwrap().remove(&id);
+        result
+    }
+}
+
+// Routes responses to their requests and answers the server's pings, which
+// also keeps idle connections alive.
+async fn read_messages(
+    mut reader: BufReader<OwnedReadHalf>,
+    writer: Writer,
+    pending: Pending,
+    progress: Progress,
+    closed: Arc<AtomicBool>,
+) {
+    loop {
+        let body = match framing::read_frame(&mut reader, Framing::Newline).await {
+            Ok(Some(body)) => body,
+            _ => break,
+        };
+        let Ok(message) = serde_json::from_slice::<Value>(&body) else {
+            continue;
+        };
+        match (message.get("id"), message.get("method").and_then(Value::as_str)) {
+            (Some(id), Some(method)) => {
+                let reply = jsonrpc::server_request_reply(id.clone(), method);
+                if jsonrpc::write_message(&mut *writer.lock().await, Framing::Newline, &reply).await.is_err() {
+                    break;
+                }
+            }
+            (Some(id), None) => {
+                let sender = id.as_u64().and_then(|id| pending.lock().unwrap().remove(&id));
+                if let Some(sender) = sender {
+                    let _ = sender.send(message);
+                }
+            }
+            (None, Some("notifications/progress")) => {
+                let params = &message["params"];
+                let sender = params["progressToken"].as_u64().and_then(|id| progress.lock().unwrap().get(&id).cloned());
+                if let (Some(sender), Some(text)) = (sender, params["message"].as_str()) {
+                    let _ = sender.send(text.to_string());
+                }
+            }
+            // Other notifications have no page to go to.
+            (None, _) => {}
+        }
+    }
+    closed.store(true, Ordering::Relaxed);
+    // Dropping the senders fails every request still waiting.
+    pending.lock().unwrap().clear();
+}
+
+pub struct Pool {
+    idle: Mutex<Vec<Connection>>,
+    max_idle: usize,
+}
+
+impl Pool {
+    fn checkout(&self) -> Option<Connection> {
+        let mut idle = self.idle.lock().unwrap();
+        while let Some(connection) = idle.pop() {
+            if !connection.is_closed() {
+                return Some(connection);
+            }
+        }
+        None
+    }
+
+    fn checkin(&self, connection: Connection) {
+        let mut idle = self.idle.lock().unwrap();
+        if !connection.is_closed() && idle.len() < self.max_idle {
+            idle.push(connection);
+        }
+    }
+
+    /// Sends a request on a pooled session and returns the whole response.
+    pub async fn request(&self, method: &str, params: Value) -> Result<Value> {
+        self.request_with_progress(method, params, None).await
+    }
+
+    /// Like [`Pool::request`], forwarding the request's progress messages to `progress`.
+    pub async fn request_with_progress(&self, method: &str, params: Value, progress: Option<ProgressSender>) -> Result<Value> {
+        let (connection, reused) = match self.checkout() {
+            Some(connection) => (connection, true),
+            None => (Connection::open().await?, false),
+        };
+        let result = connection.request(method, params.clone(), progress.clone()).await;
+        let (connection, result) = match result {
+            // The server may have restarted since the connection was opened.
+            Err(e) if reused && e.is::<Disconnected>() => {
+                tracing::info!("Pooled MCP connection failed ({}); reconnecting", e);
+                let connection = Connection::open().await?;
+                let result = connection.request(method, params, progress).await;
+                (connection, result)
+            }
+            other => (connection, other),
+        };
+        self.checkin(connection);
+        let response = result?;
+        if let Some(error) = response.get("error") {
+            return Err(anyhow!("MCP Server Error: {:?}", error));
+        }
+        Ok(response)
+    }
+}
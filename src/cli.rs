@@ -0,0 +1,296 @@
+//! Command-line interface: every command and flag, parsed with clap so each
+//! one has `--help`, and so shell completions and man pages can be generated
+//! from the same definitions.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::Result;
+use clap::{Args, CommandFactory, Parser, Subcommand};
+use clap_complete::Shell;
+use mcp_common::framing::Framing;
+
+use crate::install::{self, InstallTarget};
+use crate::output::OutputFormat;
+use crate::ServerOptions;
+
+pub const BIN_NAME: &str = "copilot_mcp_tool";
+
+// The repo's `FromStr` impls return anyhow errors, which clap can't take directly.
+fn parsed<T: FromStr<Err = anyhow::Error>>(value: &str) -> Result<T, String> {
+    value.parse().map_err(|e: anyhow::Error| e.to_string())
+}
+
+/// An MCP server with system, file and LLM tools, and the client commands to
+/// manage and call it.
+#[derive(Debug, Parser)]
+#[command(name = BIN_NAME, version)]
+pub struct Cli {
+    /// Server instance to manage or connect to (default: `MCP_INSTANCE`, or `default`).
+    #[arg(long, global = true, value_name = "NAME")]
+    pub instance: Option<String>,
+    /// Directory the server's logs are written to and read from (default: `MCP_LOG_DIR`, or the runtime dir).
+    #[arg(long, global = true, value_name = "DIR")]
+    pub log_dir: Option<PathBuf>,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Run the server in the foreground.
+    #[command(alias = "server")]
+    Serve(ServerOptions),
+    /// Start the server in the background.
+    Start(ServerOptions),
+    /// Stop the background server, letting in-flight calls finish.
+    Stop,
+    /// Show whether the server is running (every instance, unless one is selected).
+    Status(StatusArgs),
+    /// List the server's tools.
+    List(ListArgs),
+    /// Call a tool.
+    Call(CallArgs),
+    /// Interactive shell for calling tools.
+    Repl(ConnectArgs),
+    /// Run a YAML pipeline of tool calls.
+    Run(PipelineArgs),
+    /// Register this binary as a stdio server with an MCP host.
+    Install(InstallArgs),
+    /// Remove this binary from an MCP host's configuration.
+    Uninstall(InstallArgs),
+    /// Run the server as a user service that starts at login.
+    InstallService(ServerOptions),
+    /// Stop and remove the user service.
+    UninstallService,
+    /// Local usage counts.
+    Telemetry {
+        #[command(subcommand)]
+        action: Option<TelemetryCommand>,
+    },
+    /// Show the end of a server log.
+    Logs(LogsArgs),
+    /// Show recent tool calls from the audit log.
+    Audit(AuditArgs),
+    /// Check the config, lock file, server process, port, health and plugins.
+    Doctor(DoctorArgs),
+    /// Store secrets the config file refers to in the OS keychain.
+    Secret {
+        #[command(subcommand)]
+        action: SecretCommand,
+    },
+    /// Locate or validate the config file.
+    Config {
+        #[command(subcommand)]
+        action: Option<ConfigCommand>,
+    },
+    /// Sign in to GitHub Copilot for `MCP_LLM_DRIVER=github`.
+    Copilot {
+        #[command(subcommand)]
+        action: Option<CopilotCommand>,
+    },
+    /// Print a shell completion script.
+    Completions {
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    /// Write man pages for every command.
+    Man {
+        /// Directory to write the pages to.
+        #[arg(default_value = "man")]
+        dir: PathBuf,
+    },
+}
+
+#[derive(Debug, Default, Args)]
+pub struct StatusArgs {
+    /// Print the status as data instead of a sentence.
+    #[arg(long, value_enum)]
+    pub output: Option<OutputFormat>,
+}
+
+/// How client commands reach the server.
+#[derive(Debug, Default, Args)]
+pub struct ConnectArgs {
+    /// Message framing: newline or content-length.
+    #[arg(long, value_parser = parsed::<Framing>, default_value = "newline")]
+    pub framing: Framing,
+    /// Give up on a request after this many seconds (default: `MCP_CLIENT_TIMEOUT_SECS`).
+    #[arg(long, value_name = "SECS")]
+    pub timeout: Option<u64>,
+    /// Connect to a remote server over TLS, with the token from `MCP_AUTH_TOKEN`.
+    #[arg(long, value_name = "HOST:PORT")]
+    pub remote: Option<String>,
+    /// Also trust this CA or self-signed certificate (PEM).
+    #[arg(long, value_name = "PEM")]
+    pub tls_ca: Option<PathBuf>,
+}
+
+#[derive(Debug, Args)]
+pub struct ListArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+    /// Output format.
+    #[arg(long, value_enum, default_value = "json")]
+    pub output: OutputFormat,
+}
+
+#[derive(Debug, Args)]
+pub struct CallArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+    /// Output format.
+    #[arg(long, value_enum, default_value = "json")]
+    pub output: OutputFormat,
+    /// Write binary content to files in this directory.
+    #[arg(long, value_name = "DIR")]
+    pub save_binary: Option<PathBuf>,
+    /// Make the calls in a JSON array of `{ "name", "arguments" }` objects, from a file or '-' for stdin.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["tool", "params", "params_file"])]
+    pub batch: Option<String>,
+    /// Read arguments from a JSON object in a file.
+    #[arg(long, value_name = "FILE")]
+    pub params_file: Vec<PathBuf>,
+    /// Arguments as a JSON object, or '-' to read one from stdin.
+    #[arg(long, alias = "json", value_name = "JSON")]
+    pub params: Vec<String>,
+    /// Tool to call.
+    #[arg(required_unless_present = "batch")]
+    pub tool: Option<String>,
+    /// Arguments as key=value, converted to the types the tool's schema expects.
+    /// They override `--params` and `--params-file`.
+    #[arg(value_name = "KEY=VALUE")]
+    pub arguments: Vec<String>,
+}
+
+#[derive(Debug, Args)]
+pub struct PipelineArgs {
+    #[command(flatten)]
+    pub connect: ConnectArgs,
+    /// Pipeline file, or '-' for stdin.
+    pub pipeline: String,
+    /// Override a pipeline variable; values that parse as JSON keep their type.
+    #[arg(long, value_name = "KEY=VALUE")]
+    pub var: Vec<String>,
+    /// Print the calls without making them.
+    #[arg(long)]
+    pub dry_run: bool,
+    /// Print one JSON report instead of progress lines.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct InstallArgs {
+    /// Host to configure: claude, vscode or cursor.
+    #[arg(long, value_parser = parsed::<InstallTarget>)]
+    pub target: InstallTarget,
+    /// Name of the server entry.
+    #[arg(long, default_value = install::DEFAULT_SERVER_NAME)]
+    pub name: String,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum TelemetryCommand {
+    /// Start counting commands and tool calls, locally.
+    Enable,
+    /// Stop counting.
+    Disable,
+    /// Show whether telemetry is enabled (the default).
+    Status,
+    /// Print the counts as JSON, or write them to a file.
+    Export { file: Option<PathBuf> },
+    /// Clear the counts.
+    Reset,
+}
+
+#[derive(Debug, Args)]
+pub struct LogsArgs {
+    /// Lines from the end to show.
+    #[arg(short = 'n', long, default_value_t = crate::DEFAULT_LOG_LINES)]
+    pub lines: usize,
+    /// Keep printing lines as they are written.
+    #[arg(short, long)]
+    pub follow: bool,
+    /// List the log files and their sizes instead.
+    #[arg(long)]
+    pub list: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct AuditArgs {
+    /// Only calls to this tool.
+    #[arg(long)]
+    pub tool: Option<String>,
+    /// Oldest call to show: an RFC 3339 time, or an age such as 15m or 2h.
+    #[arg(long, value_name = "AGE|TIME")]
+    pub since: Option<String>,
+    /// Only failed calls.
+    #[arg(long)]
+    pub errors: bool,
+    /// Most recent calls to show.
+    #[arg(short = 'n', long)]
+    pub limit: Option<usize>,
+    /// Print the entries as JSON.
+    #[arg(long)]
+    pub json: bool,
+}
+
+#[derive(Debug, Args)]
+pub struct DoctorArgs {
+    /// Remove a lock file left behind by a server that is gone.
+    #[arg(long)]
+    pub fix: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum SecretCommand {
+    /// Store a secret, read from stdin.
+    Set { name: String },
+    /// Remove a stored secret.
+    Delete { name: String },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum ConfigCommand {
+    /// Print where the config file is (the default).
+    Path,
+    /// Load the config file and report whether it is valid.
+    Check,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CopilotCommand {
+    /// Sign in with a device code.
+    Login,
+    /// Remove the stored tokens.
+    Logout,
+    /// Show whether a Copilot session is available (the default).
+    Status,
+}
+
+pub fn print_completions(shell: Shell) {
+    clap_complete::generate(shell, &mut Cli::command(), BIN_NAME, &mut io::stdout());
+}
+
+/// Writes `copilot_mcp_tool.1` and a `copilot_mcp_tool-<command>.1` page for
+/// every subcommand to `dir`, returning how many were written.
+pub fn write_man_pages(dir: &Path) -> Result<usize> {
+    fs::create_dir_all(dir)?;
+    let command = Cli::command();
+    let mut written = 0;
+    let mut pages = vec![(BIN_NAME.to_string(), command.clone())];
+    for subcommand in command.get_subcommands() {
+        let name = format!("{}-{}", BIN_NAME, subcommand.get_name());
+        pages.push((name.clone(), subcommand.clone().name(name)));
+    }
+    for (name, page) in pages {
+        let mut buffer = Vec::new();
+        clap_mangen::Man::new(page).render(&mut buffer)?;
+        fs::write(dir.join(format!("{}.1", name)), buffer)?;
+        written += 1;
+    }
+    Ok(written)
+}
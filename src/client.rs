@@ -1,14 +1,36 @@
-use std::net::{Shutdown, TcpStream};
-use std::io::{BufRead, BufReader, Write};
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::io::IsTerminal;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
 use anyhow::{Result, anyhow};
+use mcp_common::framing::{self, Framing};
+use mcp_common::{jsonrpc, lock, runtime_paths};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
+use tokio::net::TcpStream;
+use tokio_rustls::TlsConnector;
+use tokio::sync::{mpsc, oneshot};
+use tokio::task::JoinHandle;
 
+use crate::elicitation;
+use crate::keepalive::KeepaliveConfig;
+
+use mcp_errors::ErrorCode;
 use rmcp::model::{
+    CallToolResult,
+    ListToolsResult,
+    Tool,
     InitializeRequestParam,
     Implementation,
     ClientCapabilities,
+    ExperimentalCapabilities,
     RootsCapabilities,
     ProtocolVersion,
 };
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
@@ -47,53 +69,622 @@ pub struct RpcError {
     pub message: String,
     pub data: Option<Value>,
 }
+
+impl RpcError {
+    /// The server's stable error code, when it sent one in `data`.
+    pub fn error_code(&self) -> Option<ErrorCode> {
+        self.data.as_ref().and_then(mcp_errors::code_of)
+    }
+}
 // endregion: --- Types for JSON-RPC
 
+// region:    --- Client errors
+
+/// A tool as advertised by `tools/list`.
+pub type ToolDescriptor = Tool;
+
+#[derive(Debug)]
+pub enum ClientError {
+    /// The server rejected the request with a JSON-RPC error.
+    Rpc(RpcError),
+    /// The tool ran but reported a failure (`isError` in its result).
+    Tool { code: Option<ErrorCode>, message: String },
+    /// The reply did not have the expected shape.
+    InvalidResponse(serde_json::Error),
+    /// Connection problems and timeouts.
+    Transport(anyhow::Error),
+}
+
+impl Display for ClientError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClientError::Rpc(e) => write!(f, "Server error {}: {}", e.code, e.message),
+            ClientError::Tool { code: Some(code), message } => write!(f, "Tool error ({}): {}", code.as_str(), message),
+            ClientError::Tool { code: None, message } => write!(f, "Tool error: {}", message),
+            ClientError::InvalidResponse(e) => write!(f, "Invalid response: {}", e),
+            ClientError::Transport(e) => write!(f, "{:#}", e),
+        }
+    }
+}
+
+impl std::error::Error for ClientError {}
+
+impl From<anyhow::Error> for ClientError {
+    fn from(e: anyhow::Error) -> Self {
+        ClientError::Transport(e)
+    }
+}
+
+impl From<serde_json::Error> for ClientError {
+    fn from(e: serde_json::Error) -> Self {
+        ClientError::InvalidResponse(e)
+    }
+}
+
+// Structured tool errors carry `{ "error", "code" }`; others only have text content.
+fn tool_error(result: &CallToolResult) -> ClientError {
+    let structured = result.structured_content.as_ref();
+    let message = structured
+        .and_then(|value| value.get("error"))
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .unwrap_or_else(|| text_content(result));
+    ClientError::Tool {
+        code: structured.and_then(mcp_errors::code_of),
+        message,
+    }
+}
+
+fn text_content(result: &CallToolResult) -> String {
+    result
+        .content
+        .iter()
+        .filter_map(|item| item.raw.as_text().map(|text| text.text.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+// endregion: --- Client errors
+
 // region:    --- MCP Client
+
+type Pending = Arc<Mutex<HashMap<u64, oneshot::Sender<Value>>>>;
+type ReadHalf = Box<dyn AsyncRead + Send + Unpin>;
+type WriteHalf = Box<dyn AsyncWrite + Send + Unpin>;
+type Writer = Arc<tokio::sync::Mutex<WriteHalf>>;
+
+const DEFAULT_RECONNECT_ATTEMPTS: u32 = 5;
+const RECONNECT_INITIAL_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(8);
+
+/// Where the client connects, kept so it can find the server again.
+#[derive(Debug, Clone)]
+pub enum Endpoint {
+    /// The local server in the lock file. The file is read again on every
+    /// connect, so a restarted server is found on its new port with its new token.
+    Local { trusted: Vec<PathBuf> },
+    /// A remote server over TLS, authenticated with `MCP_AUTH_TOKEN`.
+    Remote { host: String, port: u16, trusted: Vec<PathBuf> },
+}
+
+impl Endpoint {
+    // Opens a stream to the server; returns it with the token `initialize`
+    // must present and a description for messages.
+    async fn dial(&self) -> Result<(ReadHalf, WriteHalf, Option<String>, String)> {
+        match self {
+            Endpoint::Local { trusted } => {
+                let lock = lock::read(&runtime_paths::lock_file_path()?)
+                    .map_err(|e| anyhow!("Server is not running ({}). Use `start` first.", e))?;
+                let (read_half, write_half, label) = match &lock.tls_cert {
+                    Some(cert) => {
+                        let mut trusted = trusted.clone();
+                        trusted.push(cert.clone());
+                        let (read_half, write_half) = dial_tls(lock.host(), lock.port, &trusted).await?;
+                        (read_half, write_half, format!("{}:{} (TLS)", lock.host(), lock.port))
+                    }
+                    None => {
+                        let (read_half, write_half) = lock.connect().await?.into_split();
+                        (Box::new(read_half) as ReadHalf, Box::new(write_half) as WriteHalf, format!("{}:{}", lock.host(), lock.port))
+                    }
+                };
+                Ok((read_half, write_half, lock.token, label))
+            }
+            Endpoint::Remote { host, port, trusted } => {
+                let (read_half, write_half) = dial_tls(host, *port, trusted).await?;
+                Ok((read_half, write_half, env::var("MCP_AUTH_TOKEN").ok(), format!("{}:{} (TLS)", host, port)))
+            }
+        }
+    }
+}
+
+async fn dial_tls(host: &str, port: u16, trusted: &[PathBuf]) -> Result<(ReadHalf, WriteHalf)> {
+    let connector = crate::tls::connector(trusted)?;
+    let stream = TcpStream::connect((host, port)).await?;
+    let stream = connector
+        .connect(crate::tls::server_name(host)?, stream)
+        .await
+        .map_err(|e| anyhow!("TLS handshake with {}:{} failed: {}", host, port, e))?;
+    let (read_half, write_half) = tokio::io::split(stream);
+    Ok((Box::new(read_half), Box::new(write_half)))
+}
+
+/// How hard the client tries to get back to a server that went away:
+/// `MCP_CLIENT_RECONNECT_ATTEMPTS` connects (0 disables reconnecting), with
+/// the delay between them doubling from half a second up to 8 seconds.
+#[derive(Debug, Clone, Copy)]
+struct ReconnectPolicy {
+    attempts: u32,
+}
+
+impl ReconnectPolicy {
+    fn from_env() -> Self {
+        let attempts = env::var("MCP_CLIENT_RECONNECT_ATTEMPTS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_RECONNECT_ATTEMPTS);
+        Self { attempts }
+    }
+}
+
+// The connection failed under a request, as opposed to the server answering
+// with an error. `sent` says whether the request may have reached the server.
+#[derive(Debug)]
+struct Disconnected {
+    sent: bool,
+    reason: anyhow::Error,
+}
+
+impl Display for Disconnected {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:#}", self.reason)
+    }
+}
+
+impl std::error::Error for Disconnected {}
+
+// The connection went away after the request was sent.
+fn closed() -> anyhow::Error {
+    Disconnected { sent: true, reason: anyhow!("Server closed the connection") }.into()
+}
+
+// Directories offered to servers that ask for `roots/list`, from
+// `MCP_CLIENT_ROOTS` (a PATH-style list). Without it the client declares no
+// roots, and the server's own roots apply.
+fn client_roots() -> Option<Vec<Value>> {
+    let paths = env::var_os("MCP_CLIENT_ROOTS").filter(|paths| !paths.is_empty())?;
+    let roots = env::split_paths(&paths)
+        .filter_map(|path| std::path::absolute(path).ok())
+        .filter_map(|path| {
+            let uri = url::Url::from_directory_path(&path).ok()?;
+            let name = path.file_name().map(|name| name.to_string_lossy().into_owned());
+            Some(serde_json::json!({ "uri": uri.to_string(), "name": name }))
+        })
+        .collect();
+    Some(roots)
+}
+
+// Answers a request the server sent: pings, and `roots/list`.
+fn server_request_reply(id: Value, method: &str) -> Value {
+    match (method, client_roots()) {
+        ("roots/list", Some(roots)) => serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": roots } }),
+        _ => jsonrpc::server_request_reply(id, method),
+    }
+}
+
+// Elicitation prompts are answered on the terminal, so the capability is only
+// declared when someone is there to answer them.
+fn can_elicit() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+fn initialized() -> RpcNotification<'static> {
+    RpcNotification {
+        jsonrpc: "2.0",
+        method: "notifications/initialized",
+        params: None,
+    }
+}
+
+// One socket to the server and the requests waiting on it.
+struct Connection {
+    writer: Writer,
+    pending: Pending,
+    // Set by the reader once the server has gone; nothing more will be answered.
+    closed: Arc<AtomicBool>,
+    reader_task: JoinHandle<()>,
+    // Counts reconnects, so concurrent requests reconnect only once.
+    generation: u64,
+}
+
+impl Drop for Connection {
+    fn drop(&mut self) {
+        // The write half shuts the socket down when it is dropped.
+        self.reader_task.abort();
+    }
+}
+
+impl Connection {
+    fn new(read_half: ReadHalf, write_half: WriteHalf, framing: Framing, notifications: mpsc::UnboundedSender<Value>, generation: u64) -> Self {
+        let writer: Writer = Arc::new(tokio::sync::Mutex::new(write_half));
+        let pending: Pending = Arc::new(Mutex::new(HashMap::new()));
+        let closed = Arc::new(AtomicBool::new(false));
+        let reader_task = tokio::spawn(read_messages(
+            BufReader::new(read_half),
+            framing,
+            writer.clone(),
+            pending.clone(),
+            closed.clone(),
+            notifications,
+        ));
+        Connection { writer, pending, closed, reader_task, generation }
+    }
+
+    // Called after registering `ids`: a connection that already closed would
+    // never answer them, and writing to it may still appear to succeed.
+    fn check_open(&self, ids: &[u64]) -> Result<()> {
+        if self.closed.load(Ordering::SeqCst) {
+            self.forget(ids);
+            return Err(Disconnected { sent: false, reason: anyhow!("Server closed the connection") }.into());
+        }
+        Ok(())
+    }
+
+    fn forget(&self, ids: &[u64]) {
+        let mut pending = self.pending.lock().unwrap();
+        for id in ids {
+            pending.remove(id);
+        }
+    }
+}
+
+/// Async MCP client over TCP. Requests get increasing ids and may run
+/// concurrently; a background task routes each response to its request,
+/// answers server pings and forwards server notifications.
+///
+/// A client opened on an [`Endpoint`] survives server restarts: when the
+/// connection drops it reconnects with backoff, repeats `initialize`, and
+/// sends the failed request again. A `tools/call` that may already have
+/// reached the server is not sent again, since the tool may not be safe to
+/// run twice; only calls that never left the client are.
 pub struct McpClient {
-    stream: Option<TcpStream>,
+    connection: Mutex<Arc<Connection>>,
+    // Held while reconnecting, so concurrent failures reconnect once.
+    reconnecting: tokio::sync::Mutex<()>,
+    endpoint: Option<Endpoint>,
+    framing: Framing,
+    next_id: AtomicU64,
+    notification_sender: mpsc::UnboundedSender<Value>,
+    notifications: Option<mpsc::UnboundedReceiver<Value>>,
+    // Session token issued by the server, offered again on reconnect to resume.
+    session_token: Mutex<Option<String>>,
+    // Shared secret from the server's lock file, sent with `initialize`.
+    auth_token: Mutex<Option<String>>,
+    // How far the handshake got, to repeat it after reconnecting.
+    initialize_sent: AtomicBool,
+    initialized_sent: AtomicBool,
+    keepalive: KeepaliveConfig,
+    timeout: Option<Duration>,
+    reconnect_policy: ReconnectPolicy,
 }
 
 impl McpClient {
-    pub fn new() -> Self {
-        McpClient { stream: None }
+    /// Connects to `endpoint` and reconnects there if the connection drops.
+    /// Requests time out after `MCP_CLIENT_TIMEOUT_SECS` if set; a quiet
+    /// server is pinged on the keepalive schedule either way.
+    pub async fn open(endpoint: Endpoint, framing: Framing) -> Result<Self> {
+        let (read_half, write_half, auth_token, label) = endpoint.dial().await?;
+        tracing::info!("Client connected to {}", label);
+        let mut client = Self::from_halves(read_half, write_half, framing);
+        client.set_auth_token(auth_token);
+        client.endpoint = Some(endpoint);
+        Ok(client)
     }
 
-    pub fn connect(&mut self, port: u16) -> Result<()> {
-        let stream = TcpStream::connect(format!("localhost:{}", port))?;
-        println!("Client connected to localhost:{}", port);
-        self.stream = Some(stream);
-        Ok(())
+    /// Connects to the server on `localhost:<port>`, without reconnecting.
+    pub async fn connect(port: u16, framing: Framing) -> Result<Self> {
+        let stream = TcpStream::connect(format!("localhost:{}", port)).await?;
+        tracing::info!("Client connected to localhost:{}", port);
+        let (read_half, write_half) = stream.into_split();
+        Ok(Self::from_halves(Box::new(read_half), Box::new(write_half), framing))
+    }
+
+    /// Connects to `host:port` over TLS, verifying that the server's
+    /// certificate is trusted by `connector` and issued for `host`.
+    pub async fn connect_tls(host: &str, port: u16, connector: &TlsConnector, framing: Framing) -> Result<Self> {
+        let stream = TcpStream::connect((host, port)).await?;
+        let stream = connector
+            .connect(crate::tls::server_name(host)?, stream)
+            .await
+            .map_err(|e| anyhow!("TLS handshake with {}:{} failed: {}", host, port, e))?;
+        tracing::info!("Client connected to {}:{} (TLS)", host, port);
+        let (read_half, write_half) = tokio::io::split(stream);
+        Ok(Self::from_halves(Box::new(read_half), Box::new(write_half), framing))
     }
 
-    fn send_request(&mut self, method: &str, params: Value) -> Result<()> {
-        let stream = self.stream.as_mut().ok_or_else(|| anyhow!("Not connected"))?;
+    fn from_halves(read_half: ReadHalf, write_half: WriteHalf, framing: Framing) -> Self {
+        let (notification_sender, notifications) = mpsc::unbounded_channel();
+        let connection = Connection::new(read_half, write_half, framing, notification_sender.clone(), 0);
+        let timeout = env::var("MCP_CLIENT_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&secs| secs > 0)
+            .map(Duration::from_secs);
+        McpClient {
+            connection: Mutex::new(Arc::new(connection)),
+            reconnecting: tokio::sync::Mutex::new(()),
+            endpoint: None,
+            framing,
+            next_id: AtomicU64::new(1),
+            notification_sender,
+            notifications: Some(notifications),
+            session_token: Mutex::new(None),
+            auth_token: Mutex::new(None),
+            initialize_sent: AtomicBool::new(false),
+            initialized_sent: AtomicBool::new(false),
+            keepalive: KeepaliveConfig::from_env(),
+            timeout,
+            reconnect_policy: ReconnectPolicy::from_env(),
+        }
+    }
+
+    pub fn session_token(&self) -> Option<String> {
+        self.session_token.lock().unwrap().clone()
+    }
+
+    /// Sets a token from a previous connection so the next `initialize` resumes it.
+    pub fn set_session_token(&mut self, token: Option<String>) {
+        *self.session_token.get_mut().unwrap() = token;
+    }
+
+    /// Sets the token `initialize` presents to a server that requires one.
+    pub fn set_auth_token(&mut self, token: Option<String>) {
+        *self.auth_token.get_mut().unwrap() = token;
+    }
+
+    /// Overrides the per-request timeout; `None` waits as long as the server
+    /// keeps answering pings.
+    pub fn set_timeout(&mut self, timeout: Option<Duration>) {
+        self.timeout = timeout;
+    }
+
+    /// Notifications sent by the server (progress, events). Can be taken
+    /// once, and keep arriving across reconnects.
+    pub fn take_notifications(&mut self) -> Option<mpsc::UnboundedReceiver<Value>> {
+        self.notifications.take()
+    }
+
+    fn current(&self) -> Arc<Connection> {
+        self.connection.lock().unwrap().clone()
+    }
+
+    async fn send_message(&self, connection: &Connection, message: &impl Serialize) -> Result<()> {
+        write_message(&connection.writer, self.framing, message)
+            .await
+            .map_err(|e| Disconnected { sent: false, reason: e }.into())
+    }
+
+    async fn send_request(&self, connection: &Connection, method: &str, params: Value) -> Result<(u64, oneshot::Receiver<Value>)> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = oneshot::channel();
+        // Registered first so a fast reply can't arrive before anyone waits for it.
+        connection.pending.lock().unwrap().insert(id, sender);
+        connection.check_open(&[id])?;
         let request = RpcRequest {
             jsonrpc: "2.0",
-            id: 1, // Using a fixed ID for simplicity in this example client
+            id,
             method,
             params,
         };
-        let mut json_req = serde_json::to_string(&request)?;
-        json_req.push('\n');
-        stream.write_all(json_req.as_bytes())?;
-        Ok(())
+        if let Err(e) = self.send_message(connection, &request).await {
+            connection.forget(&[id]);
+            return Err(e);
+        }
+        Ok((id, receiver))
     }
 
-    pub fn receive_response(&mut self) -> Result<RpcResponse> {
-        let stream = self.stream.as_mut().ok_or_else(|| anyhow!("Not connected"))?;
-        let mut reader = BufReader::new(stream);
-        let mut response_str = String::new();
-        reader.read_line(&mut response_str)?;
-        let response: RpcResponse = serde_json::from_str(&response_str)?;
-        Ok(response)
+    // Returns whether the server answered within the keepalive timeout.
+    async fn ping(&self, connection: &Connection) -> Result<bool> {
+        let (id, receiver) = self.send_request(connection, "ping", serde_json::json!({})).await?;
+        match tokio::time::timeout(self.keepalive.timeout, receiver).await {
+            Ok(Ok(_)) => Ok(true),
+            Ok(Err(_)) => Err(closed()),
+            Err(_) => {
+                connection.forget(&[id]);
+                Ok(false)
+            }
+        }
+    }
+
+    // Waits for a reply, pinging the server whenever it has been quiet for the
+    // keepalive interval; fails once it misses too many pings in a row.
+    async fn wait_for(&self, connection: &Connection, mut receiver: oneshot::Receiver<Value>) -> Result<Value> {
+        if !self.keepalive.is_enabled() {
+            return receiver.await.map_err(|_| closed());
+        }
+        let mut misses = 0;
+        loop {
+            match tokio::time::timeout(self.keepalive.interval, &mut receiver).await {
+                Ok(reply) => return reply.map_err(|_| closed()),
+                Err(_) if self.ping(connection).await.map_err(|_| closed())? => misses = 0,
+                Err(_) => {
+                    misses += 1;
+                    if misses >= self.keepalive.max_misses {
+                        return Err(Disconnected {
+                            sent: true,
+                            reason: anyhow!("Server stopped responding (no reply to {} pings)", self.keepalive.max_misses),
+                        }
+                        .into());
+                    }
+                }
+            }
+        }
+    }
+
+    // Waits for every reply, within the request timeout if there is one.
+    async fn wait_all(&self, connection: &Connection, ids: &[u64], receivers: Vec<oneshot::Receiver<Value>>, what: &str) -> Result<Vec<Value>> {
+        let wait = async {
+            let mut replies = Vec::with_capacity(receivers.len());
+            for receiver in receivers {
+                replies.push(self.wait_for(connection, receiver).await?);
+            }
+            Ok::<_, anyhow::Error>(replies)
+        };
+        match self.timeout {
+            Some(timeout) => match tokio::time::timeout(timeout, wait).await {
+                Ok(replies) => replies,
+                Err(_) => {
+                    connection.forget(ids);
+                    Err(anyhow!("Timed out after {}s waiting for {}", timeout.as_secs(), what))
+                }
+            },
+            None => wait.await,
+        }
+    }
+
+    async fn request_once(&self, method: &str, params: Value) -> Result<RpcResponse> {
+        let connection = self.current();
+        let (id, receiver) = self.send_request(&connection, method, params).await?;
+        let mut replies = self.wait_all(&connection, &[id], vec![receiver], method).await?;
+        Ok(serde_json::from_value(replies.remove(0))?)
+    }
+
+    // Whether a request for `methods` that failed with `error` may be sent
+    // again after reconnecting.
+    fn can_retry<'a>(&self, error: &anyhow::Error, mut methods: impl Iterator<Item = &'a str>) -> bool {
+        let Some(disconnected) = error.downcast_ref::<Disconnected>() else {
+            return false;
+        };
+        self.endpoint.is_some()
+            && self.reconnect_policy.attempts > 0
+            && (!disconnected.sent || methods.all(|method| method != "tools/call"))
+    }
+
+    /// Sends a request and waits for the response with the same id.
+    pub async fn request(&self, method: &str, params: Value) -> Result<RpcResponse> {
+        let generation = self.current().generation;
+        match self.request_once(method, params.clone()).await {
+            Err(e) if self.can_retry(&e, std::iter::once(method)) => {
+                tracing::warn!("Connection to the server failed during {}: {:#}", method, e);
+                self.reconnect(generation).await?;
+                self.request_once(method, params).await
+            }
+            result => result,
+        }
+    }
+
+    async fn request_batch_once(&self, requests: &[(&str, Value)]) -> Result<Vec<RpcResponse>> {
+        let connection = self.current();
+        let mut ids = Vec::with_capacity(requests.len());
+        let mut receivers = Vec::with_capacity(requests.len());
+        let mut batch = Vec::with_capacity(requests.len());
+        {
+            let mut pending = connection.pending.lock().unwrap();
+            for (method, params) in requests {
+                let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+                let (sender, receiver) = oneshot::channel();
+                pending.insert(id, sender);
+                ids.push(id);
+                receivers.push(receiver);
+                batch.push(RpcRequest { jsonrpc: "2.0", id, method: *method, params: params.clone() });
+            }
+        }
+        connection.check_open(&ids)?;
+        if let Err(e) = self.send_message(&connection, &batch).await {
+            connection.forget(&ids);
+            return Err(e);
+        }
+        let what = format!("a batch of {} requests", requests.len());
+        let replies = self.wait_all(&connection, &ids, receivers, &what).await?;
+        replies.into_iter().map(|reply| Ok(serde_json::from_value(reply)?)).collect()
+    }
+
+    /// Sends several requests as one JSON-RPC batch and waits for all of their
+    /// responses, returned in the order of `requests`.
+    pub async fn request_batch(&self, requests: Vec<(&str, Value)>) -> Result<Vec<RpcResponse>> {
+        let generation = self.current().generation;
+        match self.request_batch_once(&requests).await {
+            Err(e) if self.can_retry(&e, requests.iter().map(|(method, _)| *method)) => {
+                tracing::warn!("Connection to the server failed during a batch: {:#}", e);
+                self.reconnect(generation).await?;
+                self.request_batch_once(&requests).await
+            }
+            result => result,
+        }
+    }
+
+    // Replaces the connection of generation `failed` with a new one to the
+    // endpoint, backing off between attempts, and repeats the handshake.
+    async fn reconnect(&self, failed: u64) -> Result<()> {
+        let _reconnecting = self.reconnecting.lock().await;
+        if self.current().generation != failed {
+            // Another request already reconnected.
+            return Ok(());
+        }
+        let endpoint = self.endpoint.as_ref().ok_or_else(|| anyhow!("The client has no endpoint to reconnect to"))?;
+        let mut delay = RECONNECT_INITIAL_DELAY;
+        let mut last_error = anyhow!("no attempts made");
+        for attempt in 1..=self.reconnect_policy.attempts {
+            tokio::time::sleep(delay).await;
+            delay = (delay * 2).min(RECONNECT_MAX_DELAY);
+            let (read_half, write_half, auth_token, label) = match endpoint.dial().await {
+                Ok(dialed) => dialed,
+                Err(e) => {
+                    tracing::debug!("Reconnect attempt {} failed: {:#}", attempt, e);
+                    last_error = e;
+                    continue;
+                }
+            };
+            let connection = Connection::new(read_half, write_half, self.framing, self.notification_sender.clone(), failed + 1);
+            *self.connection.lock().unwrap() = Arc::new(connection);
+            *self.auth_token.lock().unwrap() = auth_token;
+            match self.handshake().await {
+                Ok(()) => {
+                    eprintln!("Reconnected to {}", label);
+                    return Ok(());
+                }
+                Err(e) => {
+                    tracing::debug!("Handshake after reconnect attempt {} failed: {:#}", attempt, e);
+                    last_error = e;
+                }
+            }
+        }
+        Err(anyhow!(
+            "Lost the connection to the server and failed to reconnect after {} attempts: {:#}",
+            self.reconnect_policy.attempts,
+            last_error
+        ))
+    }
+
+    // Repeats as much of the handshake as the caller had done.
+    async fn handshake(&self) -> Result<()> {
+        if self.initialize_sent.load(Ordering::Relaxed) {
+            let response = self.request_once("initialize", self.initialize_params()?).await?;
+            self.remember_session(&response)?;
+        }
+        if self.initialized_sent.load(Ordering::Relaxed) {
+            self.send_message(&self.current(), &initialized()).await?;
+        }
+        Ok(())
     }
 
-    pub fn initialize(&mut self) -> Result<RpcResponse> {
+    fn initialize_params(&self) -> Result<Value> {
+        let mut experimental = ExperimentalCapabilities::new();
+        if let Some(token) = self.session_token() {
+            let mut session = serde_json::Map::new();
+            session.insert("resume".to_string(), Value::String(token));
+            experimental.insert("session".to_string(), session);
+        }
+        if let Some(token) = self.auth_token.lock().unwrap().clone() {
+            let mut auth = serde_json::Map::new();
+            auth.insert("token".to_string(), Value::String(token));
+            experimental.insert("auth".to_string(), auth);
+        }
+        let experimental = (!experimental.is_empty()).then_some(experimental);
         let params = InitializeRequestParam {
             protocol_version: ProtocolVersion::LATEST,
             capabilities: ClientCapabilities {
-                roots: Some(RootsCapabilities { list_changed: Some(true) }),
+                roots: client_roots().map(|_| RootsCapabilities { list_changed: None }),
+                elicitation: can_elicit().then(Default::default),
+                experimental,
                 ..Default::default()
             },
             client_info: Implementation {
@@ -102,44 +693,207 @@ impl McpClient {
                 ..Default::default()
             },
         };
-        self.send_request("initialize", serde_json::to_value(params)?)
-            ?;
-        self.receive_response()
+        Ok(serde_json::to_value(params)?)
     }
 
-    pub fn initialized_notification(&mut self) -> Result<()> {
-        let stream = self.stream.as_mut().ok_or_else(|| anyhow!("Not connected"))?;
-        let notification = RpcNotification {
-            jsonrpc: "2.0",
-            method: "notifications/initialized",
-            params: None,
-        };
-        let mut json_req = serde_json::to_string(&notification)?;
-        json_req.push('\n');
-        stream.write_all(json_req.as_bytes())?;
-        Ok(())
+    // Keeps the session token the server issued, to resume after reconnecting.
+    fn remember_session(&self, response: &RpcResponse) -> Result<()> {
+        match &response.result {
+            RpcResult::Success { result } => {
+                if let Some(token) = result["capabilities"]["experimental"]["session"]["token"].as_str() {
+                    *self.session_token.lock().unwrap() = Some(token.to_string());
+                }
+                Ok(())
+            }
+            RpcResult::Error { error } => Err(anyhow!("Server refused initialize: {}", error.message)),
+        }
+    }
+
+    /// Sends `initialize`. Safe to repeat: it is sent again, resuming the
+    /// session, after every reconnect.
+    pub async fn initialize(&self) -> Result<RpcResponse> {
+        let response = self.request("initialize", self.initialize_params()?).await?;
+        if let RpcResult::Success { .. } = &response.result {
+            self.remember_session(&response)?;
+            self.initialize_sent.store(true, Ordering::Relaxed);
+        }
+        Ok(response)
     }
 
-    pub fn list_tools(&mut self) -> Result<RpcResponse> {
-        self.send_request("tools/list", serde_json::json!({}))?;
-        self.receive_response()
+    pub async fn initialized_notification(&self) -> Result<()> {
+        self.initialized_sent.store(true, Ordering::Relaxed);
+        let connection = self.current();
+        match self.send_message(&connection, &initialized()).await {
+            // Reconnecting repeats the notification.
+            Err(e) if self.can_retry(&e, std::iter::empty()) => self.reconnect(connection.generation).await,
+            result => result,
+        }
+    }
+
+    /// Sends a request and deserializes its result, turning JSON-RPC errors
+    /// into `ClientError::Rpc`.
+    pub async fn request_typed<T: DeserializeOwned>(&self, method: &str, params: Value) -> Result<T, ClientError> {
+        match self.request(method, params).await?.result {
+            RpcResult::Success { result } => Ok(serde_json::from_value(result)?),
+            RpcResult::Error { error } => Err(ClientError::Rpc(error)),
+        }
+    }
+
+    /// Every tool the server offers, following `nextCursor` across pages.
+    pub async fn list_tools(&self) -> Result<Vec<ToolDescriptor>, ClientError> {
+        let mut tools = Vec::new();
+        let mut cursor: Option<String> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => serde_json::json!({ "cursor": cursor }),
+                None => serde_json::json!({}),
+            };
+            let page: ListToolsResult = self.request_typed("tools/list", params).await?;
+            tools.extend(page.tools);
+            match page.next_cursor {
+                Some(next) => cursor = Some(next),
+                None => return Ok(tools),
+            }
+        }
     }
 
-    pub fn call_tool(&mut self, tool_name: &str, tool_params: Value) -> Result<RpcResponse> {
-        let params = serde_json::json!({
+    /// Calls a tool and returns its result as sent, including failed results.
+    /// With `progress_token` set, the server streams progress notifications
+    /// for the call, which arrive through `take_notifications`.
+    #[tracing::instrument(name = "client.call_tool", skip(self, tool_params, progress_token))]
+    pub async fn call_tool(
+        &self,
+        tool_name: &str,
+        tool_params: Value,
+        progress_token: Option<&str>,
+    ) -> Result<CallToolResult, ClientError> {
+        let mut meta = serde_json::Map::new();
+        if let Some(token) = progress_token {
+            meta.insert("progressToken".to_string(), token.into());
+        }
+        crate::otel::inject(&mut meta);
+        let mut params = serde_json::json!({
             "name": tool_name,
             "arguments": tool_params,
         });
-        self.send_request("tools/call", params)?;
-        self.receive_response()
+        if !meta.is_empty() {
+            params["_meta"] = Value::Object(meta);
+        }
+        self.request_typed("tools/call", params).await
+    }
+
+    /// Calls several tools in one round trip. Results come back in the order
+    /// of `calls`, and one call failing doesn't fail the others.
+    #[tracing::instrument(name = "client.call_tools", skip_all, fields(calls = calls.len()))]
+    pub async fn call_tools(&self, calls: Vec<(String, Value)>) -> Result<Vec<Result<CallToolResult, ClientError>>, ClientError> {
+        let mut meta = serde_json::Map::new();
+        crate::otel::inject(&mut meta);
+        let requests = calls
+            .into_iter()
+            .map(|(name, arguments)| {
+                let mut params = serde_json::json!({ "name": name, "arguments": arguments });
+                if !meta.is_empty() {
+                    params["_meta"] = Value::Object(meta.clone());
+                }
+                ("tools/call", params)
+            })
+            .collect();
+        Ok(self
+            .request_batch(requests)
+            .await?
+            .into_iter()
+            .map(|response| match response.result {
+                RpcResult::Success { result } => Ok(serde_json::from_value(result)?),
+                RpcResult::Error { error } => Err(ClientError::Rpc(error)),
+            })
+            .collect())
+    }
+
+    /// Calls a tool and deserializes its structured output (or, for tools
+    /// without one, its text content parsed as JSON) into `T`. A failed
+    /// result becomes `ClientError::Tool`.
+    pub async fn call_tool_as<T: DeserializeOwned>(&self, tool_name: &str, tool_params: Value) -> Result<T, ClientError> {
+        let result = self.call_tool(tool_name, tool_params, None).await?;
+        if result.is_error == Some(true) {
+            return Err(tool_error(&result));
+        }
+        let output = match result.structured_content {
+            Some(structured) => structured,
+            None => serde_json::from_str(&text_content(&result))?,
+        };
+        Ok(serde_json::from_value(output)?)
     }
 }
 
-impl Drop for McpClient {
-    fn drop(&mut self) {
-        if let Some(stream) = self.stream.as_ref() {
-            let _ = stream.shutdown(Shutdown::Both);
+async fn write_message(writer: &Writer, framing: Framing, message: &impl Serialize) -> Result<()> {
+    jsonrpc::write_message(&mut *writer.lock().await, framing, message).await?;
+    Ok(())
+}
+
+async fn read_messages(
+    mut reader: BufReader<ReadHalf>,
+    framing: Framing,
+    writer: Writer,
+    pending: Pending,
+    closed: Arc<AtomicBool>,
+    notifications: mpsc::UnboundedSender<Value>,
+) {
+    'read: loop {
+        let body = match framing::read_frame(&mut reader, framing).await {
+            Ok(Some(body)) => body,
+            Ok(None) => break,
+            Err(e) => {
+                tracing::warn!("Dropping connection with bad frame: {}", e);
+                break;
+            }
+        };
+        let messages = match serde_json::from_slice(&body) {
+            // Responses to a batch arrive together.
+            Ok(Value::Array(messages)) => messages,
+            Ok(message) => vec![message],
+            Err(e) => {
+                tracing::warn!("Ignoring invalid message from server: {}", e);
+                continue;
+            }
+        };
+        for message in messages {
+            match (message.get("id"), message.get("method").and_then(Value::as_str)) {
+                // Waiting for the user must not hold up other replies.
+                (Some(id), Some("elicitation/create")) => {
+                    let (id, writer) = (id.clone(), writer.clone());
+                    let params = message.get("params").cloned().unwrap_or(Value::Null);
+                    tokio::spawn(async move {
+                        let result = tokio::task::spawn_blocking(move || elicitation::prompt(&params))
+                            .await
+                            .unwrap_or_else(|_| serde_json::json!({ "action": "cancel" }));
+                        let reply = serde_json::json!({ "jsonrpc": "2.0", "id": id, "result": result });
+                        let _ = write_message(&writer, framing, &reply).await;
+                    });
+                }
+                (Some(id), Some(method)) => {
+                    let reply = server_request_reply(id.clone(), method);
+                    if write_message(&writer, framing, &reply).await.is_err() {
+                        break 'read;
+                    }
+                }
+                (Some(id), None) => {
+                    let sender = id.as_u64().and_then(|id| pending.lock().unwrap().remove(&id));
+                    match sender {
+                        Some(sender) => {
+                            let _ = sender.send(message);
+                        }
+                        // Late reply to a request that already timed out.
+                        None => tracing::debug!("Ignoring response to unknown request {}", id),
+                    }
+                }
+                (None, _) => {
+                    let _ = notifications.send(message);
+                }
+            }
         }
     }
+    // Dropping the senders fails every request still waiting.
+    closed.store(true, Ordering::SeqCst);
+    pending.lock().unwrap().clear();
 }
 // endregion: --- MCP Client
@@ -0,0 +1,106 @@
+//! Roots the client declares through `roots/list`. A connection's file tools
+//! and file resources only reach the parts of the server's roots that lie
+//! under them; clients without the roots capability get the server's roots.
+//!
+//! Roots are fetched once the client is initialized, and again whenever it
+//! sends `notifications/roots/list_changed`. Until a client that declared the
+//! capability has listed them, file access waits for them and then fails
+//! closed: a client whose roots can't be listed reaches nothing.
+
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use rmcp::service::Peer;
+use rmcp::RoleServer;
+use tokio::sync::watch;
+
+use copilot_mcp_tool::file_system::FileSystemCommand;
+
+/// How long file access waits for a client's first `roots/list` answer.
+const LIST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// One connection's view of the file system.
+pub struct ClientRoots {
+    // None until a client with the roots capability has listed them.
+    files: RwLock<Option<Arc<dyn FileSystemCommand>>>,
+    // True once the client declared the roots capability in `initialize`.
+    declared: watch::Sender<bool>,
+    // True once the first listing has finished, successfully or not.
+    listed: watch::Sender<bool>,
+}
+
+impl Default for ClientRoots {
+    fn default() -> Self {
+        Self {
+            files: RwLock::new(None),
+            declared: watch::channel(false).0,
+            listed: watch::channel(false).0,
+        }
+    }
+}
+
+impl ClientRoots {
+    /// Records that the client declared the roots capability, so file access
+    /// is held back until its roots are known.
+    pub fn declare(&self) {
+        self.declared.send_replace(true);
+    }
+
+    /// The file tools for this connection: `configured` for clients without
+    /// the roots capability, otherwise `configured` restricted to the
+    /// client's roots. Waits for the first listing; if it doesn't arrive,
+    /// the result reaches no paths.
+    pub async fn files(&self, configured: &Arc<dyn FileSystemCommand>) -> Arc<dyn FileSystemCommand> {
+        if !*self.declared.borrow() {
+            return configured.clone();
+        }
+        let mut listed = self.listed.subscribe();
+        if tokio::time::timeout(LIST_TIMEOUT, listed.wait_for(|listed| *listed)).await.is_err() {
+            tracing::warn!("The client has not listed its roots; denying file access");
+        }
+        self.files
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| configured.restricted_to(&[]))
+    }
+
+    /// Asks a client that declared the roots capability for its roots and
+    /// restricts the file tools to them.
+    pub async fn refresh(&self, peer: &Peer<RoleServer>, configured: &Arc<dyn FileSystemCommand>) {
+        if !peer.peer_info().is_some_and(|info| info.capabilities.roots.is_some()) {
+            return;
+        }
+        self.declare();
+        let files = match peer.list_roots().await {
+            Ok(result) => {
+                let paths: Vec<PathBuf> = result.roots.iter().filter_map(|root| file_path(&root.uri)).collect();
+                let files = configured.restricted_to(&paths);
+                tracing::info!(
+                    "Client declared {} roots; file tools are limited to {:?}",
+                    result.roots.len(),
+                    files.roots()
+                );
+                files
+            }
+            Err(e) => {
+                // Unknown roots mean no roots, never the server's.
+                tracing::warn!("Could not list the client's roots, so file tools reach nothing: {}", e);
+                configured.restricted_to(&[])
+            }
+        };
+        *self.files.write().unwrap() = Some(files);
+        self.listed.send_replace(true);
+    }
+}
+
+// Roots are `file://` URIs; the spec allows no others, but ignore them rather than fail.
+fn file_path(uri: &str) -> Option<PathBuf> {
+    let url = url::Url::parse(uri).ok()?;
+    if url.scheme() != "file" {
+        tracing::warn!("Ignoring client root that is not a file:// URI: {}", uri);
+        return None;
+    }
+    url.to_file_path().ok()
+}
@@ -0,0 +1,171 @@
+//! Optional JSON config file with `${ENV_VAR}` and `${secret:NAME}` interpolation.
+//!
+//! ```json
+//! {
+//!   "env": {
+//!     "OPENAI_API_KEY": "${secret:openai_api_key}",
+//!     "GEMINI_CLIENT_SECRET": "${secret:gemini_client_secret}",
+//!     "MCP_SANDBOX_WORKDIR": "${HOME}/projects",
+//!     "MCP_TOOL_LIMITS": "${TOOL_LIMITS:-chat_completion=2}"
+//!   }
+//! }
+//! ```
+//!
+//! Entries under `env` are exported to the process environment (variables
+//! that are already set win), so every setting read from the environment can
+//! live in the config file. Secrets are kept in the OS keychain and never
+//! appear in the file; `$${` writes a literal `${`.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+/// Keychain service name secrets are stored under.
+const SECRET_SERVICE: &str = "copilot_mcp_tool";
+
+/// Source of `${secret:NAME}` values.
+pub trait SecretProvider {
+    fn get(&self, name: &str) -> Result<Option<String>>;
+    fn set(&self, name: &str, value: &str) -> Result<()>;
+    /// Returns whether a secret was removed.
+    fn delete(&self, name: &str) -> Result<bool>;
+}
+
+/// Secrets in the OS keychain (Keychain, Credential Manager, Secret Service).
+pub struct KeyringSecrets;
+
+impl KeyringSecrets {
+    fn entry(name: &str) -> Result<keyring::Entry> {
+        keyring::Entry::new(SECRET_SERVICE, name).with_context(|| format!("Failed to open keychain entry '{}'", name))
+    }
+}
+
+impl SecretProvider for KeyringSecrets {
+    fn get(&self, name: &str) -> Result<Option<String>> {
+        match Self::entry(name)?.get_password() {
+            Ok(value) => Ok(Some(value)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(anyhow!("Failed to read secret '{}' from the keychain: {}", name, e)),
+        }
+    }
+
+    fn set(&self, name: &str, value: &str) -> Result<()> {
+        Self::entry(name)?
+            .set_password(value)
+            .map_err(|e| anyhow!("Failed to store secret '{}' in the keychain: {}", name, e))
+    }
+
+    fn delete(&self, name: &str) -> Result<bool> {
+        match Self::entry(name)?.delete_credential() {
+            Ok(()) => Ok(true),
+            Err(keyring::Error::NoEntry) => Ok(false),
+            Err(e) => Err(anyhow!("Failed to delete secret '{}' from the keychain: {}", name, e)),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+}
+
+impl AppConfig {
+    /// `MCP_CONFIG` if set, otherwise `<config dir>/copilot_mcp_tool/config.json`.
+    pub fn path() -> Result<PathBuf> {
+        if let Some(path) = env::var_os("MCP_CONFIG") {
+            return Ok(PathBuf::from(path));
+        }
+        Ok(dirs::config_dir()
+            .context("Failed to get config directory")?
+            .join("copilot_mcp_tool")
+            .join("config.json"))
+    }
+
+    /// Loads and interpolates the config file. A missing file is an empty config.
+    pub fn load(secrets: &dyn SecretProvider) -> Result<Self> {
+        let path = Self::path()?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut value: Value =
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+        interpolate_value(&mut value, secrets).with_context(|| format!("In {}", path.display()))?;
+        serde_json::from_value(value).with_context(|| format!("Invalid config in {}", path.display()))
+    }
+
+    /// Exports `env` entries that aren't already set in the environment.
+    /// Call before any threads are started.
+    pub fn apply_env(&self) {
+        for (name, value) in &self.env {
+            if env::var_os(name).is_none() {
+                env::set_var(name, value);
+            }
+        }
+    }
+}
+
+fn interpolate_value(value: &mut Value, secrets: &dyn SecretProvider) -> Result<()> {
+    match value {
+        Value::String(s) => *s = interpolate(s, secrets)?,
+        Value::Array(items) => {
+            for item in items {
+                interpolate_value(item, secrets)?;
+            }
+        }
+        Value::Object(map) => {
+            for item in map.values_mut() {
+                interpolate_value(item, secrets)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expands `${VAR}`, `${VAR:-default}` and `${secret:NAME}` in `input`.
+pub fn interpolate(input: &str, secrets: &dyn SecretProvider) -> Result<String> {
+    let mut output = String::with_capacity(input.len());
+    let mut rest = input;
+    while let Some(start) = rest.find('$') {
+        output.push_str(&rest[..start]);
+        let tail = &rest[start..];
+        if let Some(after) = tail.strip_prefix("$${") {
+            output.push_str("${");
+            rest = after;
+        } else if let Some(after) = tail.strip_prefix("${") {
+            let end = after
+                .find('}')
+                .ok_or_else(|| anyhow!("Unterminated '${{' in config value"))?;
+            output.push_str(&resolve(&after[..end], secrets)?);
+            rest = &after[end + 1..];
+        } else {
+            output.push('$');
+            rest = &tail[1..];
+        }
+    }
+    output.push_str(rest);
+    Ok(output)
+}
+
+fn resolve(reference: &str, secrets: &dyn SecretProvider) -> Result<String> {
+    if let Some(name) = reference.strip_prefix("secret:") {
+        return secrets
+            .get(name)?
+            .ok_or_else(|| anyhow!("Secret '{}' is not set. Store it with `secret set {}`", name, name));
+    }
+    let (name, default) = match reference.split_once(":-") {
+        Some((name, default)) => (name, Some(default)),
+        None => (reference, None),
+    };
+    env::var(name)
+        .ok()
+        .or_else(|| default.map(str::to_string))
+        .ok_or_else(|| anyhow!("Environment variable '{}' is not set", name))
+}
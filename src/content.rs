@@ -0,0 +1,72 @@
+//! Helpers for image and binary tool results.
+//!
+//! MCP carries binary data base64-encoded: images as `image` content, other
+//! files as embedded `resource` content with a `blob`. Payloads over the size
+//! limit are rejected rather than sent as one oversized JSON-RPC message.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{Content, ResourceContents};
+
+const DEFAULT_MAX_BINARY_MB: usize = 10;
+
+/// Largest raw (pre-base64) payload a single result may carry. `MCP_MAX_BINARY_MB` overrides it.
+pub fn max_binary_bytes() -> usize {
+    std::env::var("MCP_MAX_BINARY_MB")
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(DEFAULT_MAX_BINARY_MB)
+        * 1024
+        * 1024
+}
+
+fn check_size(len: usize) -> Result<(), McpToolError> {
+    let limit = max_binary_bytes();
+    if len > limit {
+        return Err(McpToolError::new(
+            ErrorCode::ContentTooLarge,
+            format!("Binary content of {} bytes exceeds the {} byte limit", len, limit),
+        )
+        .with_details(serde_json::json!({ "size": len, "limit": limit })));
+    }
+    Ok(())
+}
+
+/// Image content, e.g. a screenshot. `mime_type` is something like `image/png`.
+pub fn image(bytes: &[u8], mime_type: &str) -> Result<Content, McpToolError> {
+    check_size(bytes.len())?;
+    Ok(Content::image(STANDARD.encode(bytes), mime_type.to_string()))
+}
+
+/// Arbitrary binary data, e.g. a downloaded file, as an embedded blob resource.
+pub fn blob(uri: &str, bytes: &[u8], mime_type: Option<&str>) -> Result<Content, McpToolError> {
+    check_size(bytes.len())?;
+    Ok(Content::resource(ResourceContents::BlobResourceContents {
+        uri: uri.to_string(),
+        mime_type: mime_type.map(str::to_string),
+        blob: STANDARD.encode(bytes),
+        meta: None,
+    }))
+}
+
+pub fn decode_base64(data: &str) -> Result<Vec<u8>, McpToolError> {
+    STANDARD
+        .decode(data.trim())
+        .map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, format!("Invalid base64 data: {}", e)))
+}
+
+/// Best-effort file extension for a MIME type, used when saving content to disk.
+pub fn extension_for(mime_type: &str) -> &'static str {
+    match mime_type {
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/svg+xml" => "svg",
+        "application/pdf" => "pdf",
+        "application/json" => "json",
+        "text/plain" => "txt",
+        _ => "bin",
+    }
+}
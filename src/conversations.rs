@@ -0,0 +1,262 @@
+//! Saved chat history for `chat_completion`.
+//!
+//! A call that names a `conversation` is sent that conversation's earlier
+//! messages first, and its own messages and the reply are appended to it.
+//! Conversations live in `conversations.sqlite3` in the data dir and record
+//! the session that started them, so `list_conversations` can show a
+//! session its own.
+
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Utc};
+use mcp_common::runtime_paths;
+use mcp_errors::{ErrorCode, McpToolError};
+use rusqlite::{params, Connection, OptionalExtension};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::copilot::Message;
+
+const MAX_ID_LEN: usize = 128;
+const TITLE_CHARS: usize = 80;
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS conversations (
+        id TEXT PRIMARY KEY,
+        session TEXT,
+        model TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        updated_at TEXT NOT NULL
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        conversation_id TEXT NOT NULL REFERENCES conversations(id) ON DELETE CASCADE,
+        position INTEGER NOT NULL,
+        role TEXT NOT NULL,
+        content TEXT NOT NULL,
+        PRIMARY KEY (conversation_id, position)
+    );
+    CREATE INDEX IF NOT EXISTS conversations_by_session ON conversations(session);
+";
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ListConversationsInput {
+    /// List every conversation, not just those this session started.
+    #[serde(default)]
+    pub all: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ConversationIdInput {
+    pub id: String,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct ConversationSummary {
+    pub id: String,
+    /// The session that started it.
+    pub session: Option<String>,
+    /// Model of the latest reply.
+    pub model: String,
+    /// The start of the first user message.
+    pub title: String,
+    pub messages: usize,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct ListConversationsOutput {
+    /// Most recently updated first.
+    pub conversations: Vec<ConversationSummary>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct ConversationOutput {
+    pub id: String,
+    pub session: Option<String>,
+    pub model: String,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: Vec<Message>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct DeleteConversationOutput {
+    pub id: String,
+    /// How many messages were deleted with it.
+    pub messages: usize,
+}
+
+fn storage_error(e: rusqlite::Error) -> McpToolError {
+    McpToolError::new(ErrorCode::Io, format!("Conversation store error: {}", e))
+}
+
+fn not_found(id: &str) -> McpToolError {
+    McpToolError::new(ErrorCode::ConversationNotFound, format!("No conversation '{}'", id))
+}
+
+pub fn check_id(id: &str) -> Result<(), McpToolError> {
+    if id.trim().is_empty() || id.len() > MAX_ID_LEN {
+        return Err(McpToolError::new(
+            ErrorCode::InvalidArgument,
+            format!("Conversation ids must be 1 to {} bytes and not blank", MAX_ID_LEN),
+        ));
+    }
+    Ok(())
+}
+
+fn title(first_message: Option<String>) -> String {
+    let text = first_message.unwrap_or_default();
+    let line = text.lines().next().unwrap_or_default();
+    match line.char_indices().nth(TITLE_CHARS) {
+        Some((end, _)) => format!("{}…", &line[..end]),
+        None => line.to_string(),
+    }
+}
+
+pub struct ConversationStore {
+    db: Mutex<Connection>,
+}
+
+impl ConversationStore {
+    pub fn open(path: &Path) -> rusqlite::Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens the store in the data dir. If that fails, history is kept in
+    /// memory until the server stops.
+    pub fn open_default() -> Self {
+        let opened = runtime_paths::conversations_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| Self::open(&path).map_err(|e| format!("{}: {}", path.display(), e)));
+        opened.unwrap_or_else(|e| {
+            tracing::warn!("Could not open the conversation store ({}); history will not be saved", e);
+            Self::init(Connection::open_in_memory().expect("in-memory SQLite database"))
+                .expect("conversation schema")
+        })
+    }
+
+    fn init(db: Connection) -> rusqlite::Result<Self> {
+        db.pragma_update(None, "foreign_keys", true)?;
+        db.execute_batch(SCHEMA)?;
+        Ok(Self { db: Mutex::new(db) })
+    }
+
+    /// The messages of conversation `id` in order; empty if it doesn't exist yet.
+    pub fn history(&self, id: &str) -> Result<Vec<Message>, McpToolError> {
+        let db = self.db.lock().unwrap();
+        Self::messages(&db, id).map_err(storage_error)
+    }
+
+    fn messages(db: &Connection, id: &str) -> rusqlite::Result<Vec<Message>> {
+        let mut statement = db.prepare_cached(
+            "SELECT role, content FROM messages WHERE conversation_id = ?1 ORDER BY position",
+        )?;
+        let rows = statement.query_map(params![id], |row| {
+            Ok(Message {
+                role: row.get(0)?,
+                content: row.get(1)?,
+            })
+        })?;
+        rows.collect()
+    }
+
+    /// Appends `messages` to conversation `id`, starting it for `session` if
+    /// it is new.
+    pub fn append(&self, id: &str, session: Option<&str>, model: &str, messages: &[Message]) -> Result<(), McpToolError> {
+        let mut db = self.db.lock().unwrap();
+        Self::insert(&mut db, id, session, model, messages).map_err(storage_error)
+    }
+
+    fn insert(db: &mut Connection, id: &str, session: Option<&str>, model: &str, messages: &[Message]) -> rusqlite::Result<()> {
+        let transaction = db.transaction()?;
+        transaction.execute(
+            "INSERT INTO conversations (id, session, model, created_at, updated_at) VALUES (?1, ?2, ?3, ?4, ?4)
+             ON CONFLICT(id) DO UPDATE SET model = excluded.model, updated_at = excluded.updated_at",
+            params![id, session, model, Utc::now()],
+        )?;
+        let next: i64 = transaction.query_row(
+            "SELECT COALESCE(MAX(position) + 1, 0) FROM messages WHERE conversation_id = ?1",
+            params![id],
+            |row| row.get(0),
+        )?;
+        {
+            let mut statement = transaction.prepare_cached(
+                "INSERT INTO messages (conversation_id, position, role, content) VALUES (?1, ?2, ?3, ?4)",
+            )?;
+            for (offset, message) in messages.iter().enumerate() {
+                statement.execute(params![id, next + offset as i64, message.role, message.content])?;
+            }
+        }
+        transaction.commit()
+    }
+
+    /// Conversations started by `session`, or all of them when it is `None`.
+    pub fn list(&self, session: Option<&str>) -> Result<Vec<ConversationSummary>, McpToolError> {
+        let db = self.db.lock().unwrap();
+        Self::summaries(&db, session).map_err(storage_error)
+    }
+
+    fn summaries(db: &Connection, session: Option<&str>) -> rusqlite::Result<Vec<ConversationSummary>> {
+        let mut statement = db.prepare_cached(
+            "SELECT c.id, c.session, c.model, c.created_at, c.updated_at,
+                    (SELECT COUNT(*) FROM messages m WHERE m.conversation_id = c.id),
+                    (SELECT content FROM messages m WHERE m.conversation_id = c.id AND m.role = 'user'
+                     ORDER BY position LIMIT 1)
+             FROM conversations c
+             WHERE ?1 IS NULL OR c.session = ?1
+             ORDER BY c.updated_at DESC",
+        )?;
+        let rows = statement.query_map(params![session], |row| {
+            Ok(ConversationSummary {
+                id: row.get(0)?,
+                session: row.get(1)?,
+                model: row.get(2)?,
+                created_at: row.get(3)?,
+                updated_at: row.get(4)?,
+                messages: row.get::<_, i64>(5)? as usize,
+                title: title(row.get(6)?),
+            })
+        })?;
+        rows.collect()
+    }
+
+    pub fn get(&self, id: &str) -> Result<ConversationOutput, McpToolError> {
+        let db = self.db.lock().unwrap();
+        let conversation: Option<(Option<String>, String, DateTime<Utc>, DateTime<Utc>)> = db
+            .query_row(
+                "SELECT session, model, created_at, updated_at FROM conversations WHERE id = ?1",
+                params![id],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+            )
+            .optional()
+            .map_err(storage_error)?;
+        let (session, model, created_at, updated_at) = conversation.ok_or_else(|| not_found(id))?;
+        Ok(ConversationOutput {
+            id: id.to_string(),
+            session,
+            model,
+            created_at,
+            updated_at,
+            messages: Self::messages(&db, id).map_err(storage_error)?,
+        })
+    }
+
+    pub fn delete(&self, id: &str) -> Result<DeleteConversationOutput, McpToolError> {
+        let db = self.db.lock().unwrap();
+        let messages: i64 = db
+            .query_row("SELECT COUNT(*) FROM messages WHERE conversation_id = ?1", params![id], |row| row.get(0))
+            .map_err(storage_error)?;
+        let deleted = db
+            .execute("DELETE FROM conversations WHERE id = ?1", params![id])
+            .map_err(storage_error)?;
+        if deleted == 0 {
+            return Err(not_found(id));
+        }
+        Ok(DeleteConversationOutput {
+            id: id.to_string(),
+            messages: messages as usize,
+        })
+    }
+}
@@ -3,25 +3,195 @@ use async_trait::async_trait;
 //     Agent, ChatResponse, CopilotError, Embedding, Message, Model,
 // }; // Will redefine/map types
 use rmcp::handler::client::ClientHandler;
+use rmcp::model::{
+    ClientCapabilities, ClientInfo, Content, CreateMessageRequestParam, CreateMessageResult, Role, SamplingMessage,
+};
+use rmcp::service::{RequestContext, RoleClient};
+use rmcp::ErrorData as McpError;
 use std::fmt::Display;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
 use async_openai::config::OpenAIConfig;
+use chrono::{DateTime, Utc};
+use futures::{Stream, StreamExt};
+use mcp_errors::{ErrorCode, McpToolError};
+use schemars::JsonSchema;
 
 // --- Common/Abstracted Types for LLM Interaction ---
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub struct Message {
+    /// `system`, `user` or `assistant`.
     pub role: String,
     pub content: String,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub struct ChatChoice {
     pub message: Message,
     pub finish_reason: Option<String>,
 }
 
-#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+/// Tokens a request used, as the provider reports them.
+#[derive(Debug, Clone, Copy, Default, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub struct TokenUsage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+}
+
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+    }
+}
+
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
 pub struct ChatResponse {
     pub choices: Vec<ChatChoice>,
+    /// Missing when the provider doesn't report it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+/// One streamed piece of a choice. `delta` is appended to the text so far;
+/// `role` and `finish_reason` arrive on the first and last chunk respectively.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
+pub struct ChatChunk {
+    pub index: u32,
+    pub role: Option<String>,
+    pub delta: String,
+    pub finish_reason: Option<String>,
+    /// Usage reported along the way; `collect_stream` adds it up.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<TokenUsage>,
+}
+
+pub type ChatStream = Pin<Box<dyn Stream<Item = Result<ChatChunk, CopilotError>> + Send>>;
+
+/// Drains a chunk stream into a full response, calling `on_chunk` as pieces arrive.
+pub async fn collect_stream(
+    mut stream: ChatStream,
+    mut on_chunk: impl FnMut(&ChatChunk),
+) -> Result<ChatResponse, CopilotError> {
+    let mut choices: Vec<ChatChoice> = Vec::new();
+    let mut usage: Option<TokenUsage> = None;
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk?;
+        on_chunk(&chunk);
+        if let Some(chunk_usage) = chunk.usage {
+            *usage.get_or_insert_with(TokenUsage::default) += chunk_usage;
+        }
+        let index = chunk.index as usize;
+        while choices.len() <= index {
+            choices.push(ChatChoice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: String::new(),
+                },
+                finish_reason: None,
+            });
+        }
+        let choice = &mut choices[index];
+        if let Some(role) = chunk.role {
+            choice.message.role = role;
+        }
+        choice.message.content.push_str(&chunk.delta);
+        if chunk.finish_reason.is_some() {
+            choice.finish_reason = chunk.finish_reason;
+        }
+    }
+    Ok(ChatResponse { choices, usage })
+}
+
+// --- Function Calling ---
+/// A function the model may call, in OpenAI's function-definition form.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub struct FunctionDefinition {
+    pub name: String,
+    pub description: String,
+    /// JSON Schema of the arguments object.
+    pub parameters: serde_json::Value,
+}
+
+/// A call the model asked for.
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize, JsonSchema)]
+pub struct ToolCall {
+    /// Pairs the call with its result.
+    pub id: String,
+    pub name: String,
+    pub arguments: serde_json::Value,
+}
+
+/// A message in a conversation where the model can call functions.
+#[derive(Debug, Clone)]
+pub enum AgentMessage {
+    System(String),
+    User(String),
+    Assistant { content: String, calls: Vec<ToolCall> },
+    /// What the call with `call_id` returned.
+    Result { call_id: String, name: String, content: String },
+}
+
+/// The model's reply in such a conversation: calls to run, or an answer
+/// when `calls` is empty.
+#[derive(Debug, Clone)]
+pub struct AgentTurn {
+    pub content: String,
+    pub calls: Vec<ToolCall>,
+    pub usage: Option<TokenUsage>,
+}
+
+const TOOL_CALL_FENCE: &str = "```tool_call";
+
+// For drivers without native function calling: the functions are described
+// in a system prompt, calls are fenced `tool_call` blocks in the reply, and
+// results come back as user messages.
+fn prompted_messages(messages: Vec<AgentMessage>, functions: &[FunctionDefinition]) -> Vec<Message> {
+    let message = |role: &str, content: String| Message { role: role.to_string(), content };
+    let mut prompted = Vec::new();
+    if !functions.is_empty() {
+        let mut instructions = String::from(
+            "You can call functions. To call one, reply with only a fenced block:\n\
+             ```tool_call\n{\"name\": \"<function name>\", \"arguments\": { ... }}\n```\n\
+             The result comes back in the next message. Call one function at a time. \
+             When you can answer, reply normally without a tool_call block.\n\nFunctions:\n",
+        );
+        for function in functions {
+            instructions.push_str(&serde_json::json!({ "type": "function", "function": function }).to_string());
+            instructions.push('\n');
+        }
+        prompted.push(message("system", instructions));
+    }
+    for agent_message in messages {
+        prompted.push(match agent_message {
+            AgentMessage::System(content) => message("system", content),
+            AgentMessage::User(content) => message("user", content),
+            // The reply already holds its tool_call block.
+            AgentMessage::Assistant { content, .. } => message("assistant", content),
+            AgentMessage::Result { name, content, .. } => message("user", format!("Result of {}:\n{}", name, content)),
+        });
+    }
+    prompted
+}
+
+// The call in a reply's tool_call block, if it has one.
+fn parse_fenced_call(reply: &str) -> Result<Option<ToolCall>, CopilotError> {
+    let Some(start) = reply.find(TOOL_CALL_FENCE) else {
+        return Ok(None);
+    };
+    let body = &reply[start + TOOL_CALL_FENCE.len()..];
+    let body = &body[..body.find("```").unwrap_or(body.len())];
+    let call: serde_json::Value = serde_json::from_str(body.trim())
+        .map_err(|e| CopilotError::InvalidResponse(format!("The model's tool_call block is not valid JSON: {}", e)))?;
+    let name = call["name"]
+        .as_str()
+        .ok_or_else(|| CopilotError::InvalidResponse("The model's tool_call block has no \"name\"".to_string()))?;
+    Ok(Some(ToolCall {
+        id: uuid::Uuid::new_v4().to_string(),
+        name: name.to_string(),
+        arguments: call.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({})),
+    }))
 }
 
 // --- Error Handling ---
@@ -29,6 +199,14 @@ pub struct ChatResponse {
 pub enum CopilotError {
     OpenAIError(async_openai::error::OpenAIError),
     GitHubCopilotError(Box<dyn std::error::Error + Send + Sync>), // For errors from copilot_client crate
+    /// A REST backend (Anthropic, Gemini) answered with an error status.
+    Api { provider: &'static str, status: u16, body: String },
+    Request(reqwest::Error),
+    InvalidResponse(String),
+    /// The backend doesn't offer the operation, e.g. embeddings on Anthropic.
+    Unsupported(String),
+    /// The daily LLM budget is used up.
+    BudgetExceeded(String),
     Other(String),
 }
 
@@ -37,6 +215,11 @@ impl Display for CopilotError {
         match self {
             CopilotError::OpenAIError(e) => write!(f, "OpenAI Error: {}", e),
             CopilotError::GitHubCopilotError(e) => write!(f, "GitHub Copilot Error: {}", e),
+            CopilotError::Api { provider, status, body } => write!(f, "{} Error: {} - {}", provider, status, body),
+            CopilotError::Request(e) => write!(f, "Request Error: {}", e),
+            CopilotError::InvalidResponse(msg) => write!(f, "Invalid Response: {}", msg),
+            CopilotError::Unsupported(msg) => write!(f, "Unsupported: {}", msg),
+            CopilotError::BudgetExceeded(msg) => write!(f, "Budget Exceeded: {}", msg),
             CopilotError::Other(msg) => write!(f, "Other Error: {}", msg),
         }
     }
@@ -44,6 +227,43 @@ impl Display for CopilotError {
 
 impl std::error::Error for CopilotError {}
 
+impl CopilotError {
+    /// Stable error code for clients that branch on failures.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            CopilotError::OpenAIError(async_openai::error::OpenAIError::ApiError(api)) => {
+                match api.r#type.as_deref() {
+                    Some("invalid_request_error") => ErrorCode::InvalidArgument,
+                    Some("authentication_error") | Some("invalid_api_key") => ErrorCode::LlmAuthFailed,
+                    _ => ErrorCode::LlmRequestFailed,
+                }
+            }
+            CopilotError::OpenAIError(async_openai::error::OpenAIError::JSONDeserialize(_)) => {
+                ErrorCode::LlmInvalidResponse
+            }
+            // Sign-in failures keep their own code, e.g. `oauth.token_not_found`.
+            CopilotError::GitHubCopilotError(e) => e
+                .downcast_ref::<McpToolError>()
+                .map(|e| e.code)
+                .unwrap_or(ErrorCode::LlmRequestFailed),
+            CopilotError::OpenAIError(_) => ErrorCode::LlmRequestFailed,
+            CopilotError::Api { status: 401 | 403, .. } => ErrorCode::LlmAuthFailed,
+            CopilotError::Api { status: 400, .. } => ErrorCode::InvalidArgument,
+            CopilotError::Api { .. } | CopilotError::Request(_) => ErrorCode::LlmRequestFailed,
+            CopilotError::InvalidResponse(_) => ErrorCode::LlmInvalidResponse,
+            CopilotError::Unsupported(_) => ErrorCode::NotImplemented,
+            CopilotError::BudgetExceeded(_) => ErrorCode::LlmBudgetExceeded,
+            CopilotError::Other(_) => ErrorCode::Internal,
+        }
+    }
+}
+
+impl From<CopilotError> for McpToolError {
+    fn from(e: CopilotError) -> Self {
+        McpToolError::new(e.code(), e.to_string())
+    }
+}
+
 
 // --- The Copilot Trait (LLM Abstraction) ---
 #[async_trait]
@@ -53,6 +273,45 @@ pub trait Copilot: Send + Sync {
         messages: Vec<Message>,
         model_id: String,
     ) -> Result<ChatResponse, CopilotError>;
+
+    /// Like `chat_completion`, but yields the reply as it is generated.
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError>;
+
+    /// One turn of a conversation in which the model may call `functions`
+    /// instead of answering. By default the functions are described in the
+    /// prompt and a fenced `tool_call` block in the reply is read as a call,
+    /// one per turn; drivers with native function calling override this.
+    async fn chat_with_functions(
+        &self,
+        messages: Vec<AgentMessage>,
+        functions: &[FunctionDefinition],
+        model_id: String,
+    ) -> Result<AgentTurn, CopilotError> {
+        let response = self.chat_completion(prompted_messages(messages, functions), model_id).await?;
+        let content = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content)
+            .ok_or_else(|| CopilotError::InvalidResponse("The model returned no choices".to_string()))?;
+        let calls = parse_fenced_call(&content)?.into_iter().collect();
+        Ok(AgentTurn {
+            content,
+            calls,
+            usage: response.usage,
+        })
+    }
+
+    /// Embeds each input, returning one vector per input in the same order.
+    async fn embeddings(
+        &self,
+        inputs: Vec<String>,
+        model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError>;
 }
 
 
@@ -64,7 +323,15 @@ use async_openai::{
         ChatCompletionRequestUserMessageArgs,
         ChatCompletionRequestSystemMessageArgs,
         ChatCompletionRequestAssistantMessageArgs,
+        ChatCompletionRequestToolMessageArgs,
+        ChatCompletionMessageToolCall,
+        ChatCompletionTool,
+        ChatCompletionToolType,
+        FunctionCall as OpenAIFunctionCall,
+        FunctionObject,
+        CreateChatCompletionRequest,
         CreateChatCompletionRequestArgs,
+        CreateEmbeddingRequestArgs,
     },
 };
 
@@ -78,15 +345,8 @@ impl OpenAICopliotClient {
             client: OpenAIClient::new(),
         }
     }
-}
 
-#[async_trait]
-impl Copilot for OpenAICopliotClient {
-    async fn chat_completion(
-        &self,
-        messages: Vec<Message>,
-        model_id: String,
-    ) -> Result<ChatResponse, CopilotError> {
+    fn build_request(messages: Vec<Message>, model_id: String) -> Result<CreateChatCompletionRequest, CopilotError> {
         let openai_messages: Vec<ChatCompletionRequestMessage> = messages
             .into_iter()
             .map(|msg| {
@@ -107,13 +367,72 @@ impl Copilot for OpenAICopliotClient {
             })
             .collect();
 
-        let request = CreateChatCompletionRequestArgs::default()
+        CreateChatCompletionRequestArgs::default()
             .model(model_id)
             .messages(openai_messages)
             .build()
-            .map_err(|e| CopilotError::Other(e.to_string()))?;
+            .map_err(|e| CopilotError::Other(e.to_string()))
+    }
+
+    fn agent_message(message: AgentMessage) -> Result<ChatCompletionRequestMessage, CopilotError> {
+        let invalid = |e: async_openai::error::OpenAIError| CopilotError::Other(e.to_string());
+        let message: ChatCompletionRequestMessage = match message {
+            AgentMessage::System(content) => ChatCompletionRequestSystemMessageArgs::default()
+                .content(content)
+                .build()
+                .map_err(invalid)?
+                .into(),
+            AgentMessage::User(content) => ChatCompletionRequestUserMessageArgs::default()
+                .content(content)
+                .build()
+                .map_err(invalid)?
+                .into(),
+            AgentMessage::Assistant { content, calls } => {
+                let mut assistant = ChatCompletionRequestAssistantMessageArgs::default();
+                if !content.is_empty() {
+                    assistant.content(content);
+                }
+                if !calls.is_empty() {
+                    assistant.tool_calls(
+                        calls
+                            .into_iter()
+                            .map(|call| ChatCompletionMessageToolCall {
+                                id: call.id,
+                                r#type: ChatCompletionToolType::Function,
+                                function: OpenAIFunctionCall {
+                                    name: call.name,
+                                    arguments: call.arguments.to_string(),
+                                },
+                            })
+                            .collect::<Vec<_>>(),
+                    );
+                }
+                assistant.build().map_err(invalid)?.into()
+            }
+            AgentMessage::Result { call_id, content, .. } => ChatCompletionRequestToolMessageArgs::default()
+                .content(content)
+                .tool_call_id(call_id)
+                .build()
+                .map_err(invalid)?
+                .into(),
+        };
+        Ok(message)
+    }
+}
 
+#[async_trait]
+impl Copilot for OpenAICopliotClient {
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatResponse, CopilotError> {
+        let request = Self::build_request(messages, model_id)?;
         let response = self.client.chat().create(request).await.map_err(CopilotError::OpenAIError)?;
+        let usage = response.usage.as_ref().map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+        });
 
         let choices: Vec<ChatChoice> = response.choices.into_iter().map(|choice| {
             ChatChoice {
@@ -125,30 +444,612 @@ impl Copilot for OpenAICopliotClient {
             }
         }).collect();
 
-        Ok(ChatResponse { choices })
+        Ok(ChatResponse { choices, usage })
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError> {
+        let request = Self::build_request(messages, model_id)?;
+        let stream = self.client.chat().create_stream(request).await.map_err(CopilotError::OpenAIError)?;
+        let chunks = stream.flat_map(|item| {
+            let chunks: Vec<Result<ChatChunk, CopilotError>> = match item {
+                Ok(response) => response
+                    .choices
+                    .into_iter()
+                    .map(|choice| {
+                        Ok(ChatChunk {
+                            index: choice.index,
+                            role: choice.delta.role.map(|role| role.to_string()),
+                            delta: choice.delta.content.unwrap_or_default(),
+                            finish_reason: choice.finish_reason.map(|r| format!("{:?}", r)),
+                            usage: None,
+                        })
+                    })
+                    .collect(),
+                Err(e) => vec![Err(CopilotError::OpenAIError(e))],
+            };
+            futures::stream::iter(chunks)
+        });
+        Ok(Box::pin(chunks))
+    }
+
+    async fn chat_with_functions(
+        &self,
+        messages: Vec<AgentMessage>,
+        functions: &[FunctionDefinition],
+        model_id: String,
+    ) -> Result<AgentTurn, CopilotError> {
+        let messages = messages.into_iter().map(Self::agent_message).collect::<Result<Vec<_>, _>>()?;
+        let tools: Vec<ChatCompletionTool> = functions
+            .iter()
+            .map(|function| ChatCompletionTool {
+                r#type: ChatCompletionToolType::Function,
+                function: FunctionObject {
+                    name: function.name.clone(),
+                    description: Some(function.description.clone()),
+                    parameters: Some(function.parameters.clone()),
+                },
+            })
+            .collect();
+        let mut request = CreateChatCompletionRequestArgs::default();
+        request.model(model_id).messages(messages);
+        if !tools.is_empty() {
+            request.tools(tools);
+        }
+        let request = request.build().map_err(|e| CopilotError::Other(e.to_string()))?;
+        let response = self.client.chat().create(request).await.map_err(CopilotError::OpenAIError)?;
+        let usage = response.usage.as_ref().map(|usage| TokenUsage {
+            prompt_tokens: usage.prompt_tokens.into(),
+            completion_tokens: usage.completion_tokens.into(),
+        });
+        let message = response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .ok_or_else(|| CopilotError::InvalidResponse("OpenAI returned no choices".to_string()))?;
+        let calls = message
+            .tool_calls
+            .unwrap_or_default()
+            .into_iter()
+            .map(|call| {
+                let arguments = serde_json::from_str(&call.function.arguments).map_err(|e| {
+                    CopilotError::InvalidResponse(format!("Arguments for {} are not valid JSON: {}", call.function.name, e))
+                })?;
+                Ok(ToolCall {
+                    id: call.id,
+                    name: call.function.name,
+                    arguments,
+                })
+            })
+            .collect::<Result<Vec<_>, CopilotError>>()?;
+        Ok(AgentTurn {
+            content: message.content.unwrap_or_default(),
+            calls,
+            usage,
+        })
+    }
+
+    async fn embeddings(
+        &self,
+        inputs: Vec<String>,
+        model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError> {
+        let request = CreateEmbeddingRequestArgs::default()
+            .model(model_id)
+            .input(inputs)
+            .build()
+            .map_err(|e| CopilotError::Other(e.to_string()))?;
+        let mut response = self.client.embeddings().create(request).await.map_err(CopilotError::OpenAIError)?;
+        response.data.sort_by_key(|embedding| embedding.index);
+        Ok(response.data.into_iter().map(|embedding| embedding.embedding).collect())
+    }
+}
+
+
+// --- Server-Sent Events helper for the REST backends ---
+type ByteStream = Pin<Box<dyn Stream<Item = reqwest::Result<bytes::Bytes>> + Send>>;
+
+/// Splits an SSE response body into the payloads of its `data:` lines.
+fn sse_data(response: reqwest::Response) -> impl Stream<Item = Result<String, CopilotError>> + Send {
+    let bytes: ByteStream = Box::pin(response.bytes_stream());
+    futures::stream::unfold((bytes, Vec::<u8>::new()), |(mut bytes, mut buffer)| async move {
+        loop {
+            if let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if let Some(data) = line.trim_end().strip_prefix("data:") {
+                    return Some((Ok(data.trim_start().to_string()), (bytes, buffer)));
+                }
+                continue;
+            }
+            match bytes.next().await {
+                Some(Ok(chunk)) => buffer.extend_from_slice(&chunk),
+                Some(Err(e)) => return Some((Err(CopilotError::Request(e)), (bytes, buffer))),
+                None => return None,
+            }
+        }
+    })
+}
+
+async fn check_status(provider: &'static str, response: reqwest::Response) -> Result<reqwest::Response, CopilotError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    let body = response.text().await.unwrap_or_default();
+    Err(CopilotError::Api {
+        provider,
+        status: status.as_u16(),
+        body,
+    })
+}
+
+fn parse_json<T: serde::de::DeserializeOwned>(provider: &str, data: &str) -> Result<T, CopilotError> {
+    serde_json::from_str(data).map_err(|e| CopilotError::InvalidResponse(format!("{}: {}", provider, e)))
+}
+
+// Both REST backends take the system prompt separately from the conversation.
+fn split_system(messages: Vec<Message>) -> (Option<String>, Vec<Message>) {
+    let (system, conversation): (Vec<Message>, Vec<Message>) =
+        messages.into_iter().partition(|msg| msg.role == "system");
+    let system = system.into_iter().map(|msg| msg.content).collect::<Vec<_>>().join("\n\n");
+    ((!system.is_empty()).then_some(system), conversation)
+}
+
+
+// --- Anthropic Client Implementation ---
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+/// The Messages API requires a limit; this is generous for tool use.
+const ANTHROPIC_MAX_TOKENS: u32 = 4096;
+
+pub struct AnthropicClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(serde::Deserialize)]
+struct AnthropicResponse {
+    #[serde(default)]
+    content: Vec<AnthropicContent>,
+    stop_reason: Option<String>,
+    usage: Option<AnthropicUsage>,
+}
+
+#[derive(serde::Deserialize, Default)]
+struct AnthropicUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+}
+
+impl From<AnthropicUsage> for TokenUsage {
+    fn from(usage: AnthropicUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.input_tokens,
+            completion_tokens: usage.output_tokens,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+struct AnthropicContent {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(serde::Deserialize)]
+struct AnthropicEvent {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    delta: Option<serde_json::Value>,
+    #[serde(default)]
+    error: Option<serde_json::Value>,
+    // Input tokens arrive in `message_start`, output tokens in `message_delta`.
+    #[serde(default)]
+    message: Option<serde_json::Value>,
+    #[serde(default)]
+    usage: Option<serde_json::Value>,
+}
+
+impl AnthropicEvent {
+    fn usage(&self) -> Option<TokenUsage> {
+        let usage = self.usage.as_ref().or_else(|| self.message.as_ref()?.get("usage"))?;
+        let usage: AnthropicUsage = serde_json::from_value(usage.clone()).ok()?;
+        Some(usage.into())
+    }
+}
+
+impl AnthropicClient {
+    /// Reads `ANTHROPIC_API_KEY`, and `ANTHROPIC_BASE_URL` for proxies.
+    pub fn from_env() -> Result<Self, CopilotError> {
+        let api_key = std::env::var("ANTHROPIC_API_KEY")
+            .map_err(|_| CopilotError::Other("ANTHROPIC_API_KEY is not set".to_string()))?;
+        let base_url = std::env::var("ANTHROPIC_BASE_URL").unwrap_or_else(|_| "https://api.anthropic.com".to_string());
+        Ok(Self {
+            http: reqwest::Client::new(),
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    async fn send(&self, messages: Vec<Message>, model_id: String, stream: bool) -> Result<reqwest::Response, CopilotError> {
+        let (system, conversation) = split_system(messages);
+        let mut body = serde_json::json!({
+            "model": model_id,
+            "max_tokens": ANTHROPIC_MAX_TOKENS,
+            "messages": conversation,
+            "stream": stream,
+        });
+        if let Some(system) = system {
+            body["system"] = system.into();
+        }
+        let response = self
+            .http
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(&body)
+            .send()
+            .await
+            .map_err(CopilotError::Request)?;
+        check_status("Anthropic", response).await
+    }
+}
+
+#[async_trait]
+impl Copilot for AnthropicClient {
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatResponse, CopilotError> {
+        let response = self.send(messages, model_id, false).await?;
+        let text = response.text().await.map_err(CopilotError::Request)?;
+        let response: AnthropicResponse = parse_json("Anthropic", &text)?;
+        // A message is a list of content blocks; only text blocks are requested.
+        let content = response.content.into_iter().map(|block| block.text).collect::<String>();
+        Ok(ChatResponse {
+            choices: vec![ChatChoice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content,
+                },
+                finish_reason: response.stop_reason,
+            }],
+            usage: response.usage.map(TokenUsage::from),
+        })
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError> {
+        let response = self.send(messages, model_id, true).await?;
+        let chunks = sse_data(response).filter_map(|data| async move {
+            let event: AnthropicEvent = match data.and_then(|data| parse_json("Anthropic", &data)) {
+                Ok(event) => event,
+                Err(e) => return Some(Err(e)),
+            };
+            let usage = event.usage();
+            let delta = event.delta.unwrap_or_default();
+            let text = |key: &str| delta.get(key).and_then(|value| value.as_str()).map(str::to_string);
+            match event.kind.as_str() {
+                "message_start" => Some(Ok(ChatChunk {
+                    index: 0,
+                    role: Some("assistant".to_string()),
+                    delta: String::new(),
+                    finish_reason: None,
+                    usage,
+                })),
+                "content_block_delta" => text("text").map(|delta| {
+                    Ok(ChatChunk {
+                        index: 0,
+                        role: None,
+                        delta,
+                        finish_reason: None,
+                        usage: None,
+                    })
+                }),
+                "message_delta" => Some(Ok(ChatChunk {
+                    index: 0,
+                    role: None,
+                    delta: String::new(),
+                    finish_reason: text("stop_reason"),
+                    usage,
+                })),
+                "error" => Some(Err(CopilotError::InvalidResponse(format!(
+                    "Anthropic stream error: {}",
+                    event.error.unwrap_or_default()
+                )))),
+                _ => None,
+            }
+        });
+        Ok(Box::pin(chunks))
+    }
+
+    async fn embeddings(
+        &self,
+        _inputs: Vec<String>,
+        _model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError> {
+        Err(CopilotError::Unsupported(
+            "Anthropic has no embeddings API; use the openai, gemini or github driver".to_string(),
+        ))
+    }
+}
+
+
+// --- Gemini Client Implementation ---
+pub struct GeminiClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiResponse {
+    #[serde(default)]
+    candidates: Vec<GeminiCandidate>,
+    usage_metadata: Option<GeminiUsage>,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiUsage {
+    #[serde(default)]
+    prompt_token_count: u64,
+    #[serde(default)]
+    candidates_token_count: u64,
+}
+
+impl From<GeminiUsage> for TokenUsage {
+    fn from(usage: GeminiUsage) -> Self {
+        TokenUsage {
+            prompt_tokens: usage.prompt_token_count,
+            completion_tokens: usage.candidates_token_count,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GeminiCandidate {
+    #[serde(default)]
+    index: u32,
+    content: Option<GeminiContent>,
+    finish_reason: Option<String>,
+}
+
+#[derive(serde::Deserialize)]
+struct GeminiContent {
+    #[serde(default)]
+    parts: Vec<GeminiPart>,
+}
+
+#[derive(serde::Deserialize)]
+struct GeminiPart {
+    #[serde(default)]
+    text: String,
+}
+
+#[derive(serde::Deserialize)]
+struct GeminiEmbeddings {
+    #[serde(default)]
+    embeddings: Vec<GeminiEmbedding>,
+}
+
+#[derive(serde::Deserialize)]
+struct GeminiEmbedding {
+    values: Vec<f32>,
+}
+
+impl GeminiCandidate {
+    fn text(&self) -> String {
+        self.content
+            .iter()
+            .flat_map(|content| &content.parts)
+            .map(|part| part.text.as_str())
+            .collect()
+    }
+}
+
+impl GeminiClient {
+    /// Reads `GEMINI_API_KEY` (or `GOOGLE_API_KEY`), and `GEMINI_BASE_URL`.
+    pub fn from_env() -> Result<Self, CopilotError> {
+        let api_key = std::env::var("GEMINI_API_KEY")
+            .or_else(|_| std::env::var("GOOGLE_API_KEY"))
+            .map_err(|_| CopilotError::Other("GEMINI_API_KEY is not set".to_string()))?;
+        let base_url = std::env::var("GEMINI_BASE_URL")
+            .unwrap_or_else(|_| "https://generativelanguage.googleapis.com/v1beta".to_string());
+        Ok(Self {
+            http: reqwest::Client::new(),
+            api_key,
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    async fn send(&self, messages: Vec<Message>, model_id: String, method: &str) -> Result<reqwest::Response, CopilotError> {
+        let (system, conversation) = split_system(messages);
+        // Gemini calls the assistant role "model".
+        let contents: Vec<serde_json::Value> = conversation
+            .into_iter()
+            .map(|msg| {
+                let role = if msg.role == "assistant" { "model" } else { "user" };
+                serde_json::json!({ "role": role, "parts": [{ "text": msg.content }] })
+            })
+            .collect();
+        let mut body = serde_json::json!({ "contents": contents });
+        if let Some(system) = system {
+            body["systemInstruction"] = serde_json::json!({ "parts": [{ "text": system }] });
+        }
+        let response = self
+            .http
+            .post(format!("{}/models/{}:{}", self.base_url, model_id, method))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(CopilotError::Request)?;
+        check_status("Gemini", response).await
+    }
+}
+
+#[async_trait]
+impl Copilot for GeminiClient {
+    async fn chat_completion(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatResponse, CopilotError> {
+        let response = self.send(messages, model_id, "generateContent").await?;
+        let text = response.text().await.map_err(CopilotError::Request)?;
+        let response: GeminiResponse = parse_json("Gemini", &text)?;
+        let usage = response.usage_metadata.map(TokenUsage::from);
+        let choices = response
+            .candidates
+            .into_iter()
+            .map(|candidate| ChatChoice {
+                message: Message {
+                    role: "assistant".to_string(),
+                    content: candidate.text(),
+                },
+                finish_reason: candidate.finish_reason,
+            })
+            .collect();
+        Ok(ChatResponse { choices, usage })
+    }
+
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError> {
+        let response = self.send(messages, model_id, "streamGenerateContent?alt=sse").await?;
+        // Every event is a complete response holding the next piece of each
+        // candidate. Usage is a running total, so only the final event's counts.
+        let chunks = sse_data(response).flat_map(|data| {
+            let chunks: Vec<Result<ChatChunk, CopilotError>> =
+                match data.and_then(|data| parse_json::<GeminiResponse>("Gemini", &data)) {
+                    Ok(response) => {
+                        let mut usage = response.usage_metadata.map(TokenUsage::from);
+                        response
+                            .candidates
+                            .into_iter()
+                            .map(|candidate| {
+                                let finished = candidate.finish_reason.is_some();
+                                Ok(ChatChunk {
+                                    index: candidate.index,
+                                    role: Some("assistant".to_string()),
+                                    delta: candidate.text(),
+                                    usage: if finished { usage.take() } else { None },
+                                    finish_reason: candidate.finish_reason,
+                                })
+                            })
+                            .collect()
+                    }
+                    Err(e) => vec![Err(e)],
+                };
+            futures::stream::iter(chunks)
+        });
+        Ok(Box::pin(chunks))
+    }
+
+    async fn embeddings(
+        &self,
+        inputs: Vec<String>,
+        model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError> {
+        let count = inputs.len();
+        let requests: Vec<serde_json::Value> = inputs
+            .into_iter()
+            .map(|text| serde_json::json!({ "model": format!("models/{}", model_id), "content": { "parts": [{ "text": text }] } }))
+            .collect();
+        let response = self
+            .http
+            .post(format!("{}/models/{}:batchEmbedContents", self.base_url, model_id))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&serde_json::json!({ "requests": requests }))
+            .send()
+            .await
+            .map_err(CopilotError::Request)?;
+        let text = check_status("Gemini", response).await?.text().await.map_err(CopilotError::Request)?;
+        let response: GeminiEmbeddings = parse_json("Gemini", &text)?;
+        if response.embeddings.len() != count {
+            return Err(CopilotError::InvalidResponse(format!(
+                "Gemini returned {} embeddings for {} inputs",
+                response.embeddings.len(),
+                count
+            )));
+        }
+        Ok(response.embeddings.into_iter().map(|embedding| embedding.values).collect())
     }
 }
 
 
 // --- GitHub Copilot Client Implementation ---
 use copilot_client::CopilotClient as GitHubCopilotClientRaw; // Use a different name to avoid conflict
+use mcp_oauth_plugin::github_copilot::GitHubCopilotAuth;
+use mcp_oauth_plugin::token_store::TokenStore;
 
 pub struct GitHubCopilotClient {
-    client: GitHubCopilotClientRaw,
+    auth: GitHubCopilotAuth,
+    editor_version: String,
+    // copilot_client exchanges the GitHub token for a session token once, at
+    // construction; it is rebuilt whenever that session token expires.
+    client: tokio::sync::Mutex<(DateTime<Utc>, Arc<GitHubCopilotClientRaw>)>,
 }
 
 impl GitHubCopilotClient {
+    /// Signs in with the GitHub token found by [`GitHubCopilotAuth`]. Fails
+    /// with `oauth.token_not_found` until `copilot login` has been run, unless
+    /// a token is available from `GITHUB_TOKEN`, a Copilot editor plugin or `gh`.
     pub async fn new(editor_version: String) -> Result<Self, CopilotError> {
-        // This is where the problematic token retrieval happens.
-        // For now, we will initialize with a dummy token to allow compilation,
-        // but this part will need active debugging.
-        let github_token = "dummy_token".to_string(); // Placeholder
-        let client = GitHubCopilotClientRaw::new_with_models(
-            github_token,
+        let token_store = TokenStore::default().await.map_err(|e| CopilotError::Other(e.to_string()))?;
+        let auth = GitHubCopilotAuth::new(token_store);
+        let (expires_at, client) = Self::connect(&auth, &editor_version).await?;
+        Ok(Self {
+            auth,
             editor_version,
-        ).await.map_err(|e| CopilotError::GitHubCopilotError(e.into()))?;
+            client: tokio::sync::Mutex::new((expires_at, Arc::new(client))),
+        })
+    }
 
-        Ok(Self { client })
+    async fn connect(
+        auth: &GitHubCopilotAuth,
+        editor_version: &str,
+    ) -> Result<(DateTime<Utc>, GitHubCopilotClientRaw), CopilotError> {
+        // Exchanging first confirms the account has Copilot access and caches
+        // the session token, with its expiry, in the token store.
+        let session = auth.copilot_token().await.map_err(|e| match e.downcast::<McpToolError>() {
+            Ok(e) => CopilotError::GitHubCopilotError(Box::new(e)),
+            Err(e) => CopilotError::GitHubCopilotError(e.into()),
+        })?;
+        let github_token = auth
+            .github_token()
+            .await
+            .ok_or_else(|| CopilotError::Other("GitHub token disappeared during sign-in".to_string()))?;
+        let client = GitHubCopilotClientRaw::new_with_models(github_token, editor_version.to_string())
+            .await
+            .map_err(|e| CopilotError::GitHubCopilotError(e.into()))?;
+        Ok((session.expires_at, client))
+    }
+
+    async fn client(&self) -> Result<Arc<GitHubCopilotClientRaw>, CopilotError> {
+        let mut current = self.client.lock().await;
+        if Utc::now() + chrono::Duration::minutes(5) >= current.0 {
+            let (expires_at, client) = Self::connect(&self.auth, &self.editor_version).await?;
+            *current = (expires_at, Arc::new(client));
+        }
+        Ok(current.1.clone())
     }
 }
 
@@ -167,7 +1068,7 @@ impl Copilot for GitHubCopilotClient {
             }
         }).collect();
 
-        let response = self.client.chat_completion(gh_messages, model_id).await
+        let response = self.client().await?.chat_completion(gh_messages, model_id).await
             .map_err(|e| CopilotError::GitHubCopilotError(e.into()))?;
 
         // Map copilot_client's ChatResponse to our generic ChatResponse
@@ -181,7 +1082,49 @@ impl Copilot for GitHubCopilotClient {
             }
         }).collect();
 
-        Ok(ChatResponse { choices })
+        // copilot_client doesn't expose token usage.
+        Ok(ChatResponse { choices, usage: None })
+    }
+
+    // copilot_client only returns whole responses, so each choice arrives as one chunk.
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError> {
+        let response = self.chat_completion(messages, model_id).await?;
+        let mut usage = response.usage;
+        let chunks: Vec<Result<ChatChunk, CopilotError>> = response
+            .choices
+            .into_iter()
+            .enumerate()
+            .map(|(index, choice)| {
+                Ok(ChatChunk {
+                    index: index as u32,
+                    role: Some(choice.message.role),
+                    delta: choice.message.content,
+                    finish_reason: choice.finish_reason,
+                    usage: usage.take(),
+                })
+            })
+            .collect();
+        Ok(Box::pin(futures::stream::iter(chunks)))
+    }
+
+    // The Copilot embeddings endpoint picks its own model.
+    async fn embeddings(
+        &self,
+        inputs: Vec<String>,
+        _model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError> {
+        let mut embeddings = self
+            .client()
+            .await?
+            .get_embeddings(inputs)
+            .await
+            .map_err(|e| CopilotError::GitHubCopilotError(e.into()))?;
+        embeddings.sort_by_key(|embedding| embedding.index);
+        Ok(embeddings.into_iter().map(|embedding| embedding.embedding).collect())
     }
 }
 
@@ -190,10 +1133,60 @@ impl Copilot for GitHubCopilotClient {
 pub enum LlmDriver {
     OpenAI(OpenAICopliotClient),
     GitHub(GitHubCopilotClient),
+    Anthropic(AnthropicClient),
+    Gemini(GeminiClient),
+}
+
+impl LlmDriver {
+    /// The `MCP_LLM_DRIVER` value that selects this driver.
+    pub fn name(&self) -> &'static str {
+        match self {
+            LlmDriver::OpenAI(_) => "openai",
+            LlmDriver::GitHub(_) => "github",
+            LlmDriver::Anthropic(_) => "anthropic",
+            LlmDriver::Gemini(_) => "gemini",
+        }
+    }
+
+    /// The model `embeddings` uses: `MCP_EMBEDDING_MODEL`, or the driver's
+    /// usual embedding model.
+    pub fn embedding_model(&self) -> String {
+        std::env::var("MCP_EMBEDDING_MODEL")
+            .ok()
+            .filter(|model| !model.is_empty())
+            .unwrap_or_else(|| {
+                match self {
+                    LlmDriver::OpenAI(_) | LlmDriver::Anthropic(_) => "text-embedding-3-small",
+                    LlmDriver::GitHub(_) => "copilot-text-embedding-ada-002",
+                    LlmDriver::Gemini(_) => "text-embedding-004",
+                }
+                .to_string()
+            })
+    }
+
+    /// Picks the backend from `MCP_LLM_DRIVER`: `openai` (the default, keyed by
+    /// `OPENAI_API_KEY`), `github`, `anthropic` or `gemini`.
+    pub async fn from_env() -> Result<Self, CopilotError> {
+        let driver = std::env::var("MCP_LLM_DRIVER").unwrap_or_default().to_ascii_lowercase();
+        match driver.as_str() {
+            "" | "openai" => Ok(LlmDriver::OpenAI(OpenAICopliotClient::new())),
+            "github" => {
+                let editor_version = format!("copilot_mcp_tool/{}", env!("CARGO_PKG_VERSION"));
+                Ok(LlmDriver::GitHub(GitHubCopilotClient::new(editor_version).await?))
+            }
+            "anthropic" => Ok(LlmDriver::Anthropic(AnthropicClient::from_env()?)),
+            "gemini" => Ok(LlmDriver::Gemini(GeminiClient::from_env()?)),
+            other => Err(CopilotError::Other(format!(
+                "Unknown MCP_LLM_DRIVER '{}'. Expected openai, github, anthropic or gemini",
+                other
+            ))),
+        }
+    }
 }
 
 #[async_trait]
 impl Copilot for LlmDriver {
+    #[tracing::instrument(name = "llm.chat_completion", skip(self, messages), fields(driver = self.name()))]
     async fn chat_completion(
         &self,
         messages: Vec<Message>,
@@ -202,10 +1195,127 @@ impl Copilot for LlmDriver {
         match self {
             LlmDriver::OpenAI(client) => client.chat_completion(messages, model_id).await,
             LlmDriver::GitHub(client) => client.chat_completion(messages, model_id).await,
+            LlmDriver::Anthropic(client) => client.chat_completion(messages, model_id).await,
+            LlmDriver::Gemini(client) => client.chat_completion(messages, model_id).await,
+        }
+    }
+
+    // Covers opening the stream; the chunks arrive after the span closes.
+    #[tracing::instrument(name = "llm.chat_completion_stream", skip(self, messages), fields(driver = self.name()))]
+    async fn chat_completion_stream(
+        &self,
+        messages: Vec<Message>,
+        model_id: String,
+    ) -> Result<ChatStream, CopilotError> {
+        match self {
+            LlmDriver::OpenAI(client) => client.chat_completion_stream(messages, model_id).await,
+            LlmDriver::GitHub(client) => client.chat_completion_stream(messages, model_id).await,
+            LlmDriver::Anthropic(client) => client.chat_completion_stream(messages, model_id).await,
+            LlmDriver::Gemini(client) => client.chat_completion_stream(messages, model_id).await,
+        }
+    }
+
+    #[tracing::instrument(name = "llm.chat_with_functions", skip(self, messages, functions), fields(driver = self.name()))]
+    async fn chat_with_functions(
+        &self,
+        messages: Vec<AgentMessage>,
+        functions: &[FunctionDefinition],
+        model_id: String,
+    ) -> Result<AgentTurn, CopilotError> {
+        match self {
+            LlmDriver::OpenAI(client) => client.chat_with_functions(messages, functions, model_id).await,
+            LlmDriver::GitHub(client) => client.chat_with_functions(messages, functions, model_id).await,
+            LlmDriver::Anthropic(client) => client.chat_with_functions(messages, functions, model_id).await,
+            LlmDriver::Gemini(client) => client.chat_with_functions(messages, functions, model_id).await,
+        }
+    }
+
+    #[tracing::instrument(name = "llm.embeddings", skip(self, inputs), fields(driver = self.name(), inputs = inputs.len()))]
+    async fn embeddings(
+        &self,
+        inputs: Vec<String>,
+        model_id: String,
+    ) -> Result<Vec<Vec<f32>>, CopilotError> {
+        match self {
+            LlmDriver::OpenAI(client) => client.embeddings(inputs, model_id).await,
+            LlmDriver::GitHub(client) => client.embeddings(inputs, model_id).await,
+            LlmDriver::Anthropic(client) => client.embeddings(inputs, model_id).await,
+            LlmDriver::Gemini(client) => client.embeddings(inputs, model_id).await,
         }
     }
 }
 
 
-pub struct MyClientHandler;
-impl ClientHandler for MyClientHandler {}
+/// Client side of MCP sampling: answers a server's `sampling/createMessage`
+/// with an LLM driver, so the server's tools can use this host's model.
+/// Always uses `model`; the server's model hints name models of whatever
+/// provider it had in mind, which the driver may not offer. Only text
+/// messages are supported.
+pub struct LlmClientHandler {
+    llm: Arc<LlmDriver>,
+    model: String,
+}
+
+impl LlmClientHandler {
+    pub fn new(llm: Arc<LlmDriver>, model: impl Into<String>) -> Self {
+        Self { llm, model: model.into() }
+    }
+}
+
+impl ClientHandler for LlmClientHandler {
+    fn get_info(&self) -> ClientInfo {
+        ClientInfo {
+            capabilities: ClientCapabilities::builder().enable_sampling().build(),
+            ..Default::default()
+        }
+    }
+
+    fn create_message(
+        &self,
+        params: CreateMessageRequestParam,
+        _context: RequestContext<RoleClient>,
+    ) -> impl Future<Output = Result<CreateMessageResult, McpError>> + Send + '_ {
+        async move {
+            let mut messages = Vec::new();
+            if let Some(system_prompt) = params.system_prompt {
+                messages.push(Message {
+                    role: "system".to_string(),
+                    content: system_prompt,
+                });
+            }
+            for message in params.messages {
+                let content = message
+                    .content
+                    .as_text()
+                    .map(|text| text.text.clone())
+                    .ok_or_else(|| McpError::invalid_params("Only text sampling messages are supported", None))?;
+                let role = match message.role {
+                    Role::User => "user",
+                    Role::Assistant => "assistant",
+                };
+                messages.push(Message {
+                    role: role.to_string(),
+                    content,
+                });
+            }
+            let response = self
+                .llm
+                .chat_completion(messages, self.model.clone())
+                .await
+                .map_err(McpToolError::from)?;
+            let choice = response
+                .choices
+                .into_iter()
+                .next()
+                .ok_or_else(|| McpToolError::new(ErrorCode::LlmInvalidResponse, "The model returned no choices"))?;
+            Ok(CreateMessageResult {
+                model: self.model.clone(),
+                stop_reason: choice.finish_reason,
+                message: SamplingMessage {
+                    role: Role::Assistant,
+                    content: Content::text(choice.message.content),
+                },
+            })
+        }
+    }
+}
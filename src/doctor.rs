@@ -0,0 +1,200 @@
+//! `doctor`: checks the pieces a working server needs and says what to do
+//! about the ones that are broken.
+//!
+//! In order: the config file, the instance's lock file, whether its PID is
+//! alive, whether its port accepts connections, the server's `health`
+//! report, and the plugins it loaded. Later checks are skipped when an
+//! earlier one means they can't pass.
+
+use std::path::Path;
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use mcp_common::lock::{self, LockData};
+use mcp_common::runtime_paths;
+use serde_json::json;
+
+use crate::cli::{ConnectArgs, DoctorArgs};
+use crate::config::{AppConfig, KeyringSecrets};
+use crate::health::{self, HealthReport};
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(3);
+const HEALTH_TIMEOUT_SECS: u64 = 5;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Warn,
+    Fail,
+}
+
+#[derive(Default)]
+struct Report {
+    problems: usize,
+}
+
+impl Report {
+    fn check(&mut self, outcome: Outcome, message: impl AsRef<str>) {
+        let label = match outcome {
+            Outcome::Ok => "ok  ",
+            Outcome::Warn => "warn",
+            Outcome::Fail => "FAIL",
+        };
+        if outcome == Outcome::Fail {
+            self.problems += 1;
+        }
+        println!("[{}] {}", label, message.as_ref());
+    }
+}
+
+fn check_config(report: &mut Report) {
+    let path = match AppConfig::path() {
+        Ok(path) => path,
+        Err(e) => return report.check(Outcome::Fail, format!("Config: {:#}", e)),
+    };
+    match AppConfig::load(&KeyringSecrets) {
+        Ok(_) if !path.exists() => report.check(Outcome::Ok, format!("Config: no file at {} (defaults apply)", path.display())),
+        Ok(config) => report.check(
+            Outcome::Ok,
+            format!("Config: {} is valid ({} env entries)", path.display(), config.env.len()),
+        ),
+        Err(e) => report.check(Outcome::Fail, format!("Config: {:#}", e)),
+    }
+}
+
+// Without a running server there is no health report, so only check that
+// the plugin directories exist.
+fn check_plugin_dirs(report: &mut Report) {
+    for variable in ["MCP_PLUGIN_DIR", "MCP_WASM_PLUGIN_DIR"] {
+        let Some(dir) = std::env::var_os(variable).filter(|dir| !dir.is_empty()) else {
+            continue;
+        };
+        let dir = Path::new(&dir);
+        if dir.is_dir() {
+            report.check(Outcome::Ok, format!("Plugins: {} is {}", variable, dir.display()));
+        } else {
+            report.check(Outcome::Warn, format!("Plugins: {} is {}, which is not a directory", variable, dir.display()));
+        }
+    }
+}
+
+fn check_plugins(report: &mut Report, health: &HealthReport) {
+    for dir in &health.plugin_dirs {
+        if !dir.is_dir() {
+            report.check(Outcome::Warn, format!("Plugins: {} is not a directory", dir.display()));
+        }
+    }
+    for plugin in &health.plugins {
+        match &plugin.error {
+            Some(error) => report.check(Outcome::Fail, format!("Plugin {}: {}", plugin.path.display(), error)),
+            None => report.check(
+                Outcome::Ok,
+                format!("Plugin {}: {}", plugin.path.display(), plugin.tools.join(", ")),
+            ),
+        }
+    }
+}
+
+fn check_health(report: &mut Report, health: &HealthReport) {
+    let outcome = if health.status == "ok" { Outcome::Ok } else { Outcome::Warn };
+    report.check(
+        outcome,
+        format!(
+            "Health: {} (version {}, up {}s, {} tools, {} calls in flight, {:.1}% CPU, {} MB)",
+            health.status,
+            health.version,
+            health.uptime_secs,
+            health.tools,
+            health.in_flight_calls,
+            health.cpu_usage_percent,
+            health.memory_kb / 1024
+        ),
+    );
+    if health.version != env!("CARGO_PKG_VERSION") {
+        report.check(
+            Outcome::Warn,
+            format!("Server runs version {}, this binary is {}; restart it to upgrade", health.version, env!("CARGO_PKG_VERSION")),
+        );
+    }
+}
+
+async fn check_server(report: &mut Report, lock: &LockData) {
+    let address = format!("{}:{}", lock.host(), lock.port);
+    match tokio::time::timeout(CONNECT_TIMEOUT, lock.connect()).await {
+        Ok(Ok(_)) => report.check(Outcome::Ok, format!("Port: {} accepts connections", address)),
+        Ok(Err(e)) => {
+            report.check(Outcome::Fail, format!("Port: cannot connect to {}: {}", address, e));
+            return;
+        }
+        Err(_) => {
+            report.check(Outcome::Fail, format!("Port: {} did not answer within {}s", address, CONNECT_TIMEOUT.as_secs()));
+            return;
+        }
+    }
+
+    let connect = ConnectArgs { timeout: Some(HEALTH_TIMEOUT_SECS), ..Default::default() };
+    let client = match crate::connect_client(&connect).await {
+        Ok(client) => client,
+        Err(e) => return report.check(Outcome::Fail, format!("Health: {:#}", e)),
+    };
+    // The token unlocks the plugin details.
+    match client.request_typed::<HealthReport>(health::METHOD, json!({ "token": lock.token })).await {
+        Ok(health) => {
+            check_health(report, &health);
+            check_plugins(report, &health);
+        }
+        Err(e) => report.check(Outcome::Fail, format!("Health: {} (servers older than this binary don't answer it)", e)),
+    }
+}
+
+/// Runs every check for the selected instance and fails if any of them did.
+pub fn run(args: &DoctorArgs) -> Result<()> {
+    let mut report = Report::default();
+    check_config(&mut report);
+
+    let lock_path = runtime_paths::lock_file_path()?;
+    let instance = runtime_paths::instance();
+    let lock = match lock::read(&lock_path) {
+        Ok(lock) => lock,
+        Err(_) if !lock_path.exists() => {
+            report.check(
+                Outcome::Warn,
+                format!("Lock file: none at {}; instance '{}' is not running", lock_path.display(), instance),
+            );
+            check_plugin_dirs(&mut report);
+            return finish(report);
+        }
+        Err(e) => {
+            report.check(Outcome::Fail, format!("Lock file: {} is unreadable: {}", lock_path.display(), e));
+            return finish(report);
+        }
+    };
+    report.check(Outcome::Ok, format!("Lock file: {}", lock_path.display()));
+
+    if !crate::is_process_running(lock.pid) {
+        if args.fix {
+            lock::remove(&lock_path)?;
+            report.check(Outcome::Ok, format!("PID: {} is gone; removed the stale lock file", lock.pid));
+        } else {
+            report.check(
+                Outcome::Fail,
+                format!("PID: {} is gone but its lock file remains; run `doctor --fix` or `start`", lock.pid),
+            );
+        }
+        check_plugin_dirs(&mut report);
+        return finish(report);
+    }
+    report.check(Outcome::Ok, format!("PID: {} is running", lock.pid));
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(check_server(&mut report, &lock));
+    finish(report)
+}
+
+fn finish(report: Report) -> Result<()> {
+    match report.problems {
+        0 => Ok(()),
+        1 => Err(anyhow!("doctor found 1 problem")),
+        problems => Err(anyhow!("doctor found {} problems", problems)),
+    }
+}
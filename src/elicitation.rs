@@ -0,0 +1,188 @@
+//! MCP elicitation (`elicitation/create`): a tool call can stop to ask the
+//! user for confirmation or for arguments it is missing, through the client.
+//!
+//! On the server, calls that need `confirm: true` ask the user instead of
+//! failing, and required arguments the call left out are asked for, when the
+//! client declares the elicitation capability. Elicitation forms only hold
+//! flat strings, numbers and booleans, so other arguments are never asked for.
+//!
+//! On the CLI, `prompt` renders the form on the terminal.
+
+use std::io::{self, BufRead, Write};
+
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{CreateElicitationRequestParam, ElicitationAction, JsonObject, Tool};
+use rmcp::service::Peer;
+use rmcp::RoleServer;
+use serde_json::{json, Map, Value};
+
+use crate::policy::CONFIRM_ARGUMENT;
+
+/// Property schema keywords an elicitation form accepts.
+const FORM_KEYWORDS: &[&str] = &["type", "title", "description", "minimum", "maximum", "enum", "default"];
+
+// --- Server side ---
+
+pub fn is_supported(peer: &Peer<RoleServer>) -> bool {
+    peer.peer_info().is_some_and(|info| info.capabilities.elicitation.is_some())
+}
+
+// Sends one form and returns what the user entered, or `None` if they
+// declined or dismissed it.
+async fn ask(peer: &Peer<RoleServer>, message: String, properties: Map<String, Value>) -> Result<Option<JsonObject>, McpToolError> {
+    let required: Vec<&String> = properties.keys().collect();
+    let params: CreateElicitationRequestParam = serde_json::from_value(json!({
+        "message": message,
+        "requestedSchema": { "type": "object", "properties": properties, "required": required },
+    }))
+    .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Invalid elicitation form: {}", e)))?;
+    let result = peer
+        .create_elicitation(params)
+        .await
+        .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("The client could not ask the user: {}", e)))?;
+    Ok(match result.action {
+        ElicitationAction::Accept => Some(result.content.and_then(|content| content.as_object().cloned()).unwrap_or_default()),
+        ElicitationAction::Decline | ElicitationAction::Cancel => None,
+    })
+}
+
+/// Asks the user to allow a call to `tool` with `arguments`.
+pub async fn confirm(peer: &Peer<RoleServer>, tool: &str, arguments: Option<&JsonObject>) -> Result<bool, McpToolError> {
+    let arguments = Value::Object(arguments.cloned().unwrap_or_default());
+    let mut properties = Map::new();
+    properties.insert(
+        CONFIRM_ARGUMENT.to_string(),
+        json!({ "type": "boolean", "title": "Confirm", "description": format!("Run {}", tool) }),
+    );
+    let answer = ask(peer, format!("Allow the destructive tool '{}' to run with {}?", tool, arguments), properties).await?;
+    Ok(answer.is_some_and(|answer| answer.get(CONFIRM_ARGUMENT).and_then(Value::as_bool) == Some(true)))
+}
+
+/// Asks the user for the required arguments of `tool` that the call left
+/// out, and adds their answers to `arguments`. Fails with `common.cancelled`
+/// if the user declines.
+pub async fn fill_missing_arguments(peer: &Peer<RoleServer>, tool: &Tool, arguments: &mut JsonObject) -> Result<(), McpToolError> {
+    let schema = &tool.input_schema;
+    let properties = schema.get("properties").and_then(Value::as_object);
+    let mut missing = Map::new();
+    for name in schema.get("required").and_then(Value::as_array).into_iter().flatten().filter_map(Value::as_str) {
+        if arguments.contains_key(name) {
+            continue;
+        }
+        if let Some(property) = properties.and_then(|properties| properties.get(name)).and_then(form_property) {
+            missing.insert(name.to_string(), property);
+        }
+    }
+    if missing.is_empty() {
+        return Ok(());
+    }
+    let names: Vec<&str> = missing.keys().map(String::as_str).collect();
+    let message = format!("'{}' needs {} to run.", tool.name, names.join(", "));
+    match ask(peer, message, missing).await? {
+        Some(answer) => {
+            arguments.extend(answer);
+            Ok(())
+        }
+        None => Err(McpToolError::new(
+            ErrorCode::Cancelled,
+            format!("The user did not provide the arguments '{}' needs", tool.name),
+        )),
+    }
+}
+
+// The parts of an argument's schema a form can show, if it is a flat
+// string, number, integer or boolean.
+fn form_property(schema: &Value) -> Option<Value> {
+    let schema = schema.as_object()?;
+    let kind = match schema.get("type")? {
+        Value::String(kind) => kind.as_str(),
+        // `Option<T>` fields are `[T, "null"]`.
+        Value::Array(kinds) => kinds.iter().filter_map(Value::as_str).find(|kind| *kind != "null")?,
+        _ => return None,
+    };
+    if !matches!(kind, "string" | "number" | "integer" | "boolean") {
+        return None;
+    }
+    let mut property: Map<String, Value> = schema
+        .iter()
+        .filter(|(keyword, _)| FORM_KEYWORDS.contains(&keyword.as_str()))
+        .map(|(keyword, value)| (keyword.clone(), value.clone()))
+        .collect();
+    property.insert("type".to_string(), Value::String(kind.to_string()));
+    Some(Value::Object(property))
+}
+
+// --- Client side ---
+
+/// Shows an `elicitation/create` form on the terminal and returns the
+/// result to send back. An empty answer to a field without a default
+/// declines the form; end of input cancels it.
+pub fn prompt(params: &Value) -> Value {
+    let stdin = io::stdin();
+    let mut input = stdin.lock();
+    let mut stderr = io::stderr();
+    let _ = writeln!(stderr, "\n{}", params["message"].as_str().unwrap_or("The server asks for input."));
+
+    let mut content = Map::new();
+    let properties = params["requestedSchema"]["properties"].as_object().cloned().unwrap_or_default();
+    for (name, schema) in &properties {
+        loop {
+            let label = schema["title"].as_str().unwrap_or(name);
+            let mut hint = match schema["type"].as_str() {
+                Some("boolean") => "y/n".to_string(),
+                Some(kind) => kind.to_string(),
+                None => "value".to_string(),
+            };
+            if let Some(choices) = schema["enum"].as_array() {
+                hint = choices.iter().map(|choice| choice.as_str().map(str::to_string).unwrap_or(choice.to_string())).collect::<Vec<_>>().join("/");
+            }
+            if let Some(description) = schema["description"].as_str() {
+                let _ = writeln!(stderr, "  {}", description);
+            }
+            let _ = write!(stderr, "{} [{}]: ", label, hint);
+            let _ = stderr.flush();
+
+            let mut line = String::new();
+            if !matches!(input.read_line(&mut line), Ok(read) if read > 0) {
+                return json!({ "action": "cancel" });
+            }
+            let answer = line.trim();
+            if answer.is_empty() {
+                match schema.get("default") {
+                    Some(default) => {
+                        content.insert(name.clone(), default.clone());
+                        break;
+                    }
+                    None => return json!({ "action": "decline" }),
+                }
+            }
+            match parse_answer(schema, answer) {
+                Some(value) => {
+                    content.insert(name.clone(), value);
+                    break;
+                }
+                None => {
+                    let _ = writeln!(stderr, "  Not a valid {}; try again.", hint);
+                }
+            }
+        }
+    }
+    json!({ "action": "accept", "content": content })
+}
+
+fn parse_answer(schema: &Value, answer: &str) -> Option<Value> {
+    let value = match schema["type"].as_str() {
+        Some("boolean") => match answer.to_ascii_lowercase().as_str() {
+            "y" | "yes" | "true" => Value::Bool(true),
+            "n" | "no" | "false" => Value::Bool(false),
+            _ => return None,
+        },
+        Some("integer") => Value::from(answer.parse::<i64>().ok()?),
+        Some("number") => Value::from(answer.parse::<f64>().ok()?),
+        _ => Value::String(answer.to_string()),
+    };
+    match schema["enum"].as_array() {
+        Some(choices) if !choices.contains(&value) => None,
+        _ => Some(value),
+    }
+}
@@ -0,0 +1,608 @@
+//! File system tools: read, write, list, stat and search files under a set of
+//! allowed root directories.
+//!
+//! Roots come from `MCP_FS_ROOTS` (a `PATH`-style list) and default to the
+//! sandbox working directory. A client that declares roots of its own only
+//! reaches the parts of these that lie under them. Every path is resolved,
+//! following `..` and symlinks, before it is checked against the roots; writes
+//! must also pass the sandbox's write check.
+
+use std::env;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::CallToolResult;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+use crate::sandbox::{self, Sandbox};
+
+/// Bytes `read_file` returns when the call doesn't ask for fewer.
+pub const DEFAULT_READ_BYTES: u64 = 1024 * 1024;
+const MAX_LIST_ENTRIES: usize = 10_000;
+const DEFAULT_SEARCH_RESULTS: usize = 100;
+const MAX_SEARCH_RESULTS: usize = 1000;
+// Files larger than this are matched by name only when searching contents.
+const MAX_SEARCH_FILE_BYTES: u64 = 10 * 1024 * 1024;
+
+// --- Input/Output Structs for FileSystemCommand Trait ---
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ReadFileInput {
+    /// File to read; relative paths are resolved against the first root.
+    pub path: String,
+    /// Byte offset to start reading at.
+    #[serde(default)]
+    pub offset: u64,
+    /// Maximum bytes to read (default and maximum 1 MiB).
+    pub length: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ReadFileOutput {
+    pub path: String,
+    pub content: String,
+    pub offset: u64,
+    pub size: u64,
+    /// True when the file continues past what was returned.
+    pub truncated: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct WriteFileInput {
+    pub path: String,
+    pub content: String,
+    /// Append instead of replacing the file.
+    #[serde(default)]
+    pub append: bool,
+    /// Create missing parent directories.
+    #[serde(default)]
+    pub create_dirs: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct WriteFileOutput {
+    pub path: String,
+    pub bytes_written: u64,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ListDirectoryInput {
+    /// Directory to list (default: the first root).
+    pub path: Option<String>,
+    /// Include entries whose names start with a dot.
+    #[serde(default)]
+    pub include_hidden: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct DirectoryEntry {
+    pub name: String,
+    /// `file`, `directory`, `symlink` or `other`.
+    pub kind: String,
+    pub size: u64,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ListDirectoryOutput {
+    pub path: String,
+    pub entries: Vec<DirectoryEntry>,
+    pub truncated: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct StatPathInput {
+    pub path: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct PathInfo {
+    pub path: String,
+    /// `file`, `directory`, `symlink` or `other`.
+    pub kind: String,
+    pub size: u64,
+    pub readonly: bool,
+    /// RFC 3339 timestamps, where the platform reports them.
+    pub modified: Option<String>,
+    pub created: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SearchFilesInput {
+    /// Directory to search recursively (default: the first root).
+    pub path: Option<String>,
+    /// File name pattern; `*` matches any run of characters, `?` one character.
+    #[serde(default = "match_all")]
+    pub pattern: String,
+    /// Only report files containing this text, with the matching lines.
+    pub contains: Option<String>,
+    /// Maximum matches (default 100, at most 1000).
+    pub max_results: Option<usize>,
+}
+
+fn match_all() -> String {
+    "*".to_string()
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SearchMatch {
+    pub path: String,
+    /// 1-based line number of a `contains` match.
+    pub line: Option<usize>,
+    pub text: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SearchFilesOutput {
+    pub matches: Vec<SearchMatch>,
+    pub truncated: bool,
+}
+
+// --- FileSystemCommand Trait Definition ---
+
+#[async_trait]
+pub trait FileSystemCommand: Send + Sync + 'static {
+    // Read a UTF-8 file, or a byte range of it
+    async fn read_file(&self, input: ReadFileInput) -> CallToolResult;
+
+    // Create, replace or append to a file
+    async fn write_file(&self, input: WriteFileInput) -> CallToolResult;
+
+    // List the entries of a directory
+    async fn list_directory(&self, input: ListDirectoryInput) -> CallToolResult;
+
+    // Report the type, size and timestamps of a path
+    async fn stat_path(&self, input: StatPathInput) -> CallToolResult;
+
+    // Find files by name and, optionally, content
+    async fn search_files(&self, input: SearchFilesInput) -> CallToolResult;
+
+    // Directories the tools may touch, in canonical form
+    fn roots(&self) -> &[PathBuf];
+
+    // The same tools, limited to where the roots overlap `client_roots`
+    fn restricted_to(&self, client_roots: &[PathBuf]) -> Arc<dyn FileSystemCommand>;
+}
+
+// Makes `options` refuse to open a symlink as the final path component.
+fn no_follow(options: &mut fs::OpenOptions) -> &mut fs::OpenOptions {
+    #[cfg(unix)]
+    options.custom_flags(libc::O_NOFOLLOW);
+    options
+}
+
+// Opening a symlink with `O_NOFOLLOW` fails with `ELOOP`.
+fn symlink_error(e: std::io::Error, path: &Path) -> McpToolError {
+    #[cfg(unix)]
+    if e.raw_os_error() == Some(libc::ELOOP) {
+        return McpToolError::new(ErrorCode::SandboxViolation, format!("{} is a symlink; refusing to follow it", path.display()));
+    }
+    let _ = path;
+    McpToolError::from(e)
+}
+
+// --- LocalFileSystem Implementation ---
+
+pub struct LocalFileSystem {
+    sandbox: Arc<Sandbox>,
+    // Canonical forms of the allowed roots; the first one resolves relative paths.
+    roots: Vec<PathBuf>,
+}
+
+impl LocalFileSystem {
+    pub fn new(sandbox: Arc<Sandbox>, roots: Vec<PathBuf>) -> Self {
+        let roots = roots.iter().filter_map(|root| root.canonicalize().ok()).collect();
+        Self { sandbox, roots }
+    }
+
+    /// Roots from `MCP_FS_ROOTS`, or the sandbox working directory.
+    pub fn from_env(sandbox: Arc<Sandbox>) -> Self {
+        let roots = env::var_os("MCP_FS_ROOTS")
+            .map(|paths| env::split_paths(&paths).collect::<Vec<_>>())
+            .filter(|roots| !roots.is_empty())
+            .unwrap_or_else(|| vec![sandbox.config().workdir.clone()]);
+        Self::new(sandbox, roots)
+    }
+
+    // Resolves `path` and checks it lies under a root. The target itself may
+    // not exist yet; its nearest existing ancestor is canonicalized instead,
+    // and a dangling symlink is refused rather than followed.
+    fn resolve(&self, path: Option<&str>) -> Result<PathBuf, McpToolError> {
+        let first_root = self
+            .roots
+            .first()
+            .ok_or_else(|| McpToolError::new(ErrorCode::PermissionDenied, "No file system roots are configured, or none overlap the client's roots"))?;
+        let path = match path {
+            Some(path) if Path::new(path).is_absolute() => PathBuf::from(path),
+            Some(path) => first_root.join(path),
+            None => first_root.clone(),
+        };
+
+        let resolved = sandbox::resolve_nearest(&path).map_err(|e| match e.kind() {
+            std::io::ErrorKind::InvalidInput => McpToolError::new(ErrorCode::SandboxViolation, e.to_string()),
+            _ => McpToolError::from(e),
+        })?;
+
+        if self.roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(McpToolError::new(
+                ErrorCode::SandboxViolation,
+                format!("{} is outside the allowed roots", path.display()),
+            )
+            .with_details(serde_json::json!({ "roots": self.roots })))
+        }
+    }
+
+    fn resolve_existing(&self, path: Option<&str>) -> Result<PathBuf, McpToolError> {
+        let resolved = self.resolve(path)?;
+        if !resolved.exists() {
+            return Err(McpToolError::new(
+                ErrorCode::FileNotFound,
+                format!("{} does not exist", resolved.display()),
+            ));
+        }
+        Ok(resolved)
+    }
+
+    async fn read(&self, input: ReadFileInput) -> Result<ReadFileOutput, McpToolError> {
+        let path = self.resolve_existing(Some(&input.path))?;
+        let mut file = fs::File::open(&path).await?;
+        let size = file.metadata().await?.len();
+        let offset = input.offset.min(size);
+        let length = input.length.unwrap_or(DEFAULT_READ_BYTES).min(DEFAULT_READ_BYTES);
+        file.seek(std::io::SeekFrom::Start(offset)).await?;
+        let mut bytes = Vec::with_capacity(length.min(size - offset) as usize);
+        file.take(length).read_to_end(&mut bytes).await?;
+
+        let content = String::from_utf8(bytes).map_err(|_| {
+            McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("{} is not UTF-8 text; read it as a file:// resource instead", path.display()),
+            )
+        })?;
+        Ok(ReadFileOutput {
+            truncated: offset + (content.len() as u64) < size,
+            path: path.display().to_string(),
+            content,
+            offset,
+            size,
+        })
+    }
+
+    async fn write(&self, input: WriteFileInput) -> Result<WriteFileOutput, McpToolError> {
+        let path = self.resolve(Some(&input.path))?;
+        let path = self.sandbox.check_write_path(&path)?;
+        if path.is_dir() {
+            return Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("{} is a directory", path.display()),
+            ));
+        }
+        if let Some(parent) = path.parent().filter(|parent| !parent.exists()) {
+            if !input.create_dirs {
+                return Err(McpToolError::new(
+                    ErrorCode::FileNotFound,
+                    format!("{} does not exist; pass create_dirs to create it", parent.display()),
+                ));
+            }
+            fs::create_dir_all(parent).await?;
+        }
+
+        if input.append {
+            // A symlink planted since `resolve` must not be followed out of the roots.
+            let mut file = no_follow(fs::OpenOptions::new().create(true).append(true))
+                .open(&path)
+                .await
+                .map_err(|e| symlink_error(e, &path))?;
+            file.write_all(input.content.as_bytes()).await?;
+            file.flush().await?;
+        } else {
+            // Written next to the target and renamed, so readers never see half a file.
+            let partial = path.with_file_name(format!(
+                ".{}.partial",
+                path.file_name().unwrap_or_default().to_string_lossy()
+            ));
+            // A leftover partial, or a symlink planted in its place, is removed
+            // (never followed); `create_new` refuses anything that reappears.
+            let _ = fs::remove_file(&partial).await;
+            let mut file = no_follow(fs::OpenOptions::new().write(true).create_new(true))
+                .open(&partial)
+                .await
+                .map_err(|e| symlink_error(e, &partial))?;
+            let written = async {
+                file.write_all(input.content.as_bytes()).await?;
+                file.flush().await?;
+                drop(file);
+                fs::rename(&partial, &path).await
+            };
+            if let Err(e) = written.await {
+                let _ = fs::remove_file(&partial).await;
+                return Err(e.into());
+            }
+        }
+        Ok(WriteFileOutput {
+            path: path.display().to_string(),
+            bytes_written: input.content.len() as u64,
+        })
+    }
+
+    async fn list(&self, input: ListDirectoryInput) -> Result<ListDirectoryOutput, McpToolError> {
+        let path = self.resolve_existing(input.path.as_deref())?;
+        let mut reader = fs::read_dir(&path).await?;
+        let mut entries = Vec::new();
+        let mut truncated = false;
+        while let Some(entry) = reader.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !input.include_hidden && name.starts_with('.') {
+                continue;
+            }
+            if entries.len() == MAX_LIST_ENTRIES {
+                truncated = true;
+                break;
+            }
+            let metadata = fs::symlink_metadata(entry.path()).await?;
+            entries.push(DirectoryEntry {
+                name,
+                kind: kind_of(&metadata).to_string(),
+                size: metadata.len(),
+            });
+        }
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(ListDirectoryOutput {
+            path: path.display().to_string(),
+            entries,
+            truncated,
+        })
+    }
+
+    async fn stat(&self, input: StatPathInput) -> Result<PathInfo, McpToolError> {
+        let path = self.resolve_existing(Some(&input.path))?;
+        let metadata = fs::symlink_metadata(&path).await?;
+        Ok(PathInfo {
+            path: path.display().to_string(),
+            kind: kind_of(&metadata).to_string(),
+            size: metadata.len(),
+            readonly: metadata.permissions().readonly(),
+            modified: metadata.modified().ok().map(rfc3339),
+            created: metadata.created().ok().map(rfc3339),
+        })
+    }
+
+    fn search(&self, input: SearchFilesInput) -> Result<SearchFilesOutput, McpToolError> {
+        let root = self.resolve_existing(input.path.as_deref())?;
+        let limit = input.max_results.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, MAX_SEARCH_RESULTS);
+        let mut matches = Vec::new();
+
+        // Symlinks aren't followed, so the walk can't leave the root.
+        for entry in walkdir::WalkDir::new(&root).into_iter().filter_map(Result::ok) {
+            if !entry.file_type().is_file() || !wildcard_match(&input.pattern, &entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            let path = entry.path().display().to_string();
+            let Some(needle) = &input.contains else {
+                matches.push(SearchMatch { path, line: None, text: None });
+                if matches.len() == limit {
+                    return Ok(SearchFilesOutput { matches, truncated: true });
+                }
+                continue;
+            };
+            if entry.metadata().map(|metadata| metadata.len() > MAX_SEARCH_FILE_BYTES).unwrap_or(true) {
+                continue;
+            }
+            let Ok(file) = std::fs::File::open(entry.path()) else {
+                continue;
+            };
+            // Lines that aren't UTF-8 end the scan of that file.
+            for (index, line) in BufReader::new(file).lines().map_while(Result::ok).enumerate() {
+                if line.contains(needle.as_str()) {
+                    matches.push(SearchMatch {
+                        path: path.clone(),
+                        line: Some(index + 1),
+                        text: Some(line),
+                    });
+                    if matches.len() == limit {
+                        return Ok(SearchFilesOutput { matches, truncated: true });
+                    }
+                }
+            }
+        }
+        Ok(SearchFilesOutput { matches, truncated: false })
+    }
+}
+
+fn kind_of(metadata: &std::fs::Metadata) -> &'static str {
+    let file_type = metadata.file_type();
+    if file_type.is_symlink() {
+        "symlink"
+    } else if file_type.is_dir() {
+        "directory"
+    } else if file_type.is_file() {
+        "file"
+    } else {
+        "other"
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
+
+/// Matches a file name against a pattern where `*` is any run of characters
+/// and `?` is exactly one.
+pub fn wildcard_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    // Where the last `*` was, and how much of the name it has taken so far.
+    let mut backtrack: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, taken)) => {
+                    p = star + 1;
+                    n = taken + 1;
+                    backtrack = Some((star, taken + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+fn structured_output<T: Serialize>(output: Result<T, McpToolError>) -> CallToolResult {
+    match output.and_then(|output| {
+        serde_json::to_value(output)
+            .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Failed to serialize output: {}", e)))
+    }) {
+        Ok(value) => CallToolResult::structured(value),
+        Err(e) => e.into_call_tool_result(),
+    }
+}
+
+#[async_trait]
+impl FileSystemCommand for LocalFileSystem {
+    fn roots(&self) -> &[PathBuf] {
+        &self.roots
+    }
+
+    fn restricted_to(&self, client_roots: &[PathBuf]) -> Arc<dyn FileSystemCommand> {
+        // In the client's order, so its first root resolves relative paths.
+        let mut roots = Vec::new();
+        for client_root in client_roots.iter().filter_map(|root| root.canonicalize().ok()) {
+            for root in &self.roots {
+                let overlap = if client_root.starts_with(root) {
+                    &client_root
+                } else if root.starts_with(&client_root) {
+                    root
+                } else {
+                    continue;
+                };
+                if !roots.contains(overlap) {
+                    roots.push(overlap.clone());
+                }
+            }
+        }
+        Arc::new(LocalFileSystem {
+            sandbox: self.sandbox.clone(),
+            roots,
+        })
+    }
+
+    async fn read_file(&self, input: ReadFileInput) -> CallToolResult {
+        structured_output(self.read(input).await)
+    }
+
+    async fn write_file(&self, input: WriteFileInput) -> CallToolResult {
+        structured_output(self.write(input).await)
+    }
+
+    async fn list_directory(&self, input: ListDirectoryInput) -> CallToolResult {
+        structured_output(self.list(input).await)
+    }
+
+    async fn stat_path(&self, input: StatPathInput) -> CallToolResult {
+        structured_output(self.stat(input).await)
+    }
+
+    async fn search_files(&self, input: SearchFilesInput) -> CallToolResult {
+        // Walking a large tree is blocking work.
+        structured_output(tokio::task::block_in_place(|| self.search(input)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sandbox::{SandboxConfig, SandboxProfile};
+    use tempfile::TempDir;
+
+    fn file_system(root: &Path) -> LocalFileSystem {
+        let sandbox = Sandbox::new(SandboxConfig {
+            profile: SandboxProfile::Workdir,
+            workdir: root.to_path_buf(),
+            extra_writable: Vec::new(),
+            allow_network: true,
+            max_processes: 16,
+            strict: false,
+        });
+        LocalFileSystem::new(Arc::new(sandbox), vec![root.to_path_buf()])
+    }
+
+    fn write_input(path: &str, append: bool) -> WriteFileInput {
+        WriteFileInput {
+            path: path.to_string(),
+            content: "data".to_string(),
+            append,
+            create_dirs: false,
+        }
+    }
+
+    #[test]
+    fn test_resolve() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let files = file_system(&root);
+        assert_eq!(files.resolve(Some("new.txt")).unwrap(), root.join("new.txt"));
+        assert_eq!(files.resolve(None).unwrap(), root);
+        let e = files.resolve(Some("../escape.txt")).unwrap_err();
+        assert_eq!(e.code, ErrorCode::SandboxViolation);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_resolve_refuses_symlink_escapes() {
+        let dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let files = file_system(&root);
+
+        std::os::unix::fs::symlink(outside.path(), root.join("out")).unwrap();
+        assert_eq!(files.resolve(Some("out/file.txt")).unwrap_err().code, ErrorCode::SandboxViolation);
+
+        std::os::unix::fs::symlink(outside.path().join("planted.txt"), root.join("dangling")).unwrap();
+        assert_eq!(files.resolve(Some("dangling")).unwrap_err().code, ErrorCode::SandboxViolation);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_write_does_not_follow_symlinks() {
+        let dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let files = file_system(&root);
+        let planted = outside.path().join("planted.txt");
+
+        // Neither an append nor a replace goes through a dangling link.
+        std::os::unix::fs::symlink(&planted, root.join("log.txt")).unwrap();
+        assert!(files.write(write_input("log.txt", true)).await.is_err());
+        assert!(files.write(write_input("log.txt", false)).await.is_err());
+        assert!(!planted.exists());
+
+        // A link planted where the partial file goes is replaced, not followed.
+        std::os::unix::fs::symlink(&planted, root.join(".notes.txt.partial")).unwrap();
+        files.write(write_input("notes.txt", false)).await.unwrap();
+        assert!(!planted.exists());
+        assert_eq!(std::fs::read_to_string(root.join("notes.txt")).unwrap(), "data");
+        assert!(root.join(".notes.txt.partial").symlink_metadata().is_err());
+
+        files.write(write_input("notes.txt", true)).await.unwrap();
+        assert_eq!(std::fs::read_to_string(root.join("notes.txt")).unwrap(), "datadata");
+    }
+}
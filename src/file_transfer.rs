@@ -0,0 +1,176 @@
+//! Chunked file download and upload.
+//!
+//! Downloads are ranged `resources/read` calls on `file://` URIs, e.g.
+//! `file:///home/me/build.log?offset=1048576&length=1048576`. Each chunk comes
+//! back as a blob whose `_meta` says where it sits in the file. Uploads go
+//! through the `upload_file_chunk` tool: chunks are appended in order to a
+//! `.upload` file, which is renamed into place when the last chunk arrives.
+//! Neither side ever holds more than one chunk in memory.
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use copilot_mcp_tool::content;
+use copilot_mcp_tool::sandbox::Sandbox;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{Meta, ResourceContents};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+
+/// Chunk size used when a read doesn't ask for one.
+pub const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+pub const FILE_URI_TEMPLATE: &str = "file:///{path}{?offset,length}";
+
+const UPLOAD_SUFFIX: &str = "upload";
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct UploadFileChunkInput {
+    /// Destination path; relative paths are resolved against the working directory.
+    pub path: String,
+    /// Byte offset of this chunk. Must equal the bytes received so far; 0 starts over.
+    pub offset: u64,
+    /// Base64-encoded chunk data.
+    pub data: String,
+    /// Set on the last chunk to move the file into place.
+    #[serde(default)]
+    pub complete: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct UploadStatus {
+    /// Final path of the file.
+    pub path: PathBuf,
+    /// Bytes received so far.
+    pub received: u64,
+    pub complete: bool,
+}
+
+struct ChunkRequest {
+    path: PathBuf,
+    offset: u64,
+    length: u64,
+}
+
+fn parse_file_uri(uri: &str) -> Result<ChunkRequest, McpToolError> {
+    let invalid = |reason: &str| McpToolError::new(ErrorCode::InvalidArgument, format!("Invalid file URI '{}': {}", uri, reason));
+    let url = url::Url::parse(uri).map_err(|e| invalid(&e.to_string()))?;
+    if url.scheme() != "file" {
+        return Err(invalid("only file:// URIs are supported"));
+    }
+    let path = url.to_file_path().map_err(|_| invalid("not a local path"))?;
+
+    let mut request = ChunkRequest {
+        path,
+        offset: 0,
+        length: DEFAULT_CHUNK_SIZE,
+    };
+    for (key, value) in url.query_pairs() {
+        let number = value.parse::<u64>().map_err(|_| invalid(&format!("'{}' must be a number", key)))?;
+        match key.as_ref() {
+            "offset" => request.offset = number,
+            "length" => request.length = number,
+            _ => {}
+        }
+    }
+    let max = content::max_binary_bytes() as u64;
+    if request.length == 0 || request.length > max {
+        return Err(invalid(&format!("length must be between 1 and {}", max)));
+    }
+    Ok(request)
+}
+
+/// MIME type guessed from a file's extension.
+pub fn mime_type(path: &Path) -> &'static str {
+    path.extension()
+        .and_then(|extension| extension.to_str())
+        .map(|extension| match extension {
+            "png" => "image/png",
+            "jpg" | "jpeg" => "image/jpeg",
+            "json" => "application/json",
+            "txt" | "log" | "md" => "text/plain",
+            "pdf" => "application/pdf",
+            _ => "application/octet-stream",
+        })
+        .unwrap_or("application/octet-stream")
+}
+
+/// Reads one chunk of a file as a blob resource. The file must be readable
+/// under the sandbox or lie under one of the file system `roots`.
+pub async fn read_chunk(sandbox: &Sandbox, roots: &[PathBuf], uri: &str) -> Result<ResourceContents, McpToolError> {
+    let request = parse_file_uri(uri)?;
+    let path = match sandbox.check_read_path(&request.path) {
+        Ok(path) => path,
+        Err(e) => match request.path.canonicalize() {
+            Ok(path) if roots.iter().any(|root| path.starts_with(root)) => path,
+            _ => return Err(e),
+        },
+    };
+
+    let mut file = fs::File::open(&path).await?;
+    let total_size = file.metadata().await?.len();
+    let offset = request.offset.min(total_size);
+    file.seek(SeekFrom::Start(offset)).await?;
+    let mut chunk = Vec::with_capacity(request.length.min(total_size - offset) as usize);
+    file.take(request.length).read_to_end(&mut chunk).await?;
+
+    let next_offset = offset + chunk.len() as u64;
+    let mut meta = Meta::new();
+    meta.insert("offset".to_string(), offset.into());
+    meta.insert("length".to_string(), (chunk.len() as u64).into());
+    meta.insert("total_size".to_string(), total_size.into());
+    meta.insert("eof".to_string(), (next_offset >= total_size).into());
+
+    Ok(ResourceContents::BlobResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some(mime_type(&path).to_string()),
+        blob: base64::Engine::encode(&base64::engine::general_purpose::STANDARD, &chunk),
+        meta: Some(meta),
+    })
+}
+
+/// Appends one uploaded chunk, moving the file into place on the last one.
+pub async fn write_chunk(sandbox: &Sandbox, input: UploadFileChunkInput) -> Result<UploadStatus, McpToolError> {
+    let path = sandbox.check_write_path(Path::new(&input.path))?;
+    let partial = path.with_extension(match path.extension() {
+        Some(extension) => format!("{}.{}", extension.to_string_lossy(), UPLOAD_SUFFIX),
+        None => UPLOAD_SUFFIX.to_string(),
+    });
+    let data = content::decode_base64(&input.data)?;
+    if data.len() > content::max_binary_bytes() {
+        return Err(McpToolError::new(
+            ErrorCode::ContentTooLarge,
+            format!("Chunk of {} bytes exceeds the {} byte limit", data.len(), content::max_binary_bytes()),
+        ));
+    }
+
+    let mut file = if input.offset == 0 {
+        fs::File::create(&partial).await?
+    } else {
+        let received = fs::metadata(&partial).await.map(|m| m.len()).unwrap_or(0);
+        if received != input.offset {
+            // Tells the client where to resume after a dropped chunk.
+            return Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("Expected a chunk at offset {}, got {}", received, input.offset),
+            )
+            .with_details(serde_json::json!({ "expected_offset": received })));
+        }
+        fs::OpenOptions::new().append(true).open(&partial).await?
+    };
+    file.write_all(&data).await?;
+    file.flush().await?;
+    let received = input.offset + data.len() as u64;
+
+    if input.complete {
+        drop(file);
+        fs::rename(&partial, &path).await?;
+    }
+    Ok(UploadStatus {
+        path,
+        received,
+        complete: input.complete,
+    })
+}
@@ -0,0 +1,120 @@
+//! The `health` JSON-RPC method: whether the server is up and how it is
+//! doing, for `doctor` and for liveness probes.
+//!
+//! It is answered by the framing bridge in front of every session, so it
+//! works before (and without) `initialize` and needs no auth token. Without
+//! one it reveals nothing beyond the status, version, counts and the
+//! server's own resource usage. The process id and the plugin paths and
+//! load errors are only reported to callers that pass the server's auth
+//! token as `token`.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use mcp_common::framing::LocalMethods;
+use serde::{Deserialize, Serialize};
+
+use crate::auth;
+use crate::plugins::PluginStatus;
+use crate::ServerState;
+
+pub const METHOD: &str = "health";
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HealthReport {
+    /// `ok`, or `draining` once the server is stopping.
+    pub status: String,
+    pub version: String,
+    /// Only reported with the auth token.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub uptime_secs: u64,
+    /// Built-in and plugin tools currently registered.
+    pub tools: usize,
+    pub in_flight_calls: usize,
+    /// The server process's CPU usage since the previous health check.
+    pub cpu_usage_percent: f32,
+    pub memory_kb: u64,
+    /// Directories plugins are loaded from; only reported with the auth token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugin_dirs: Vec<PathBuf>,
+    /// Only reported with the auth token.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<PluginStatus>,
+}
+
+/// Server-wide state for health reports.
+pub struct Health {
+    started: Instant,
+    // Kept across reports: CPU usage is measured between two refreshes.
+    system: Mutex<sysinfo::System>,
+}
+
+impl Health {
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            system: Mutex::new(sysinfo::System::new()),
+        }
+    }
+
+    /// The report for `state`; `detailed` adds the process id and plugins.
+    pub fn report(&self, state: &ServerState, detailed: bool) -> HealthReport {
+        let pid = std::process::id();
+        let (cpu_usage_percent, memory_kb) = {
+            let mut system = self.system.lock().unwrap();
+            let sysinfo_pid = sysinfo::Pid::from_u32(pid);
+            system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[sysinfo_pid]), true);
+            system
+                .process(sysinfo_pid)
+                .map(|process| (process.cpu_usage(), process.memory() / 1024))
+                .unwrap_or_default()
+        };
+        HealthReport {
+            status: if state.shutdown.is_draining() { "draining" } else { "ok" }.to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            pid: detailed.then_some(pid),
+            uptime_secs: self.started.elapsed().as_secs(),
+            tools: state.tools.tool_count(),
+            in_flight_calls: state.shutdown.in_flight(),
+            cpu_usage_percent,
+            memory_kb,
+            plugin_dirs: if detailed {
+                state.plugins.iter().map(|host| host.dir().to_path_buf()).collect()
+            } else {
+                Vec::new()
+            },
+            plugins: if detailed {
+                state.plugins.iter().flat_map(|host| host.status()).collect()
+            } else {
+                Vec::new()
+            },
+        }
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The methods the bridge answers for `state`'s sessions. Transports
+/// without an `auth_token` are private to this machine's user and always
+/// get the detailed report.
+pub fn local_methods(state: Arc<ServerState>, auth_token: Option<Arc<str>>) -> LocalMethods {
+    Arc::new(move |method, params| {
+        if method != METHOD {
+            return None;
+        }
+        let detailed = match &auth_token {
+            Some(expected) => params
+                .get("token")
+                .and_then(|token| token.as_str())
+                .is_some_and(|offered| auth::token_matches(expected, offered)),
+            None => true,
+        };
+        Some(serde_json::to_value(state.health.report(&state, detailed)).unwrap_or_default())
+    })
+}
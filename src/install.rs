@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::PathBuf;
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::{json, Map, Value};
+
+/// Name under which the server is registered in host configs by default.
+pub const DEFAULT_SERVER_NAME: &str = "copilot_mcp_tool";
+
+/// Arguments hosts use to spawn this binary as a stdio MCP server.
+const STDIO_SERVER_ARGS: &[&str] = &["serve", "--stdio"];
+
+/// MCP hosts whose configuration `install` knows how to edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InstallTarget {
+    Claude,
+    VsCode,
+    Cursor,
+}
+
+impl std::str::FromStr for InstallTarget {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "claude" => Ok(InstallTarget::Claude),
+            "vscode" => Ok(InstallTarget::VsCode),
+            "cursor" => Ok(InstallTarget::Cursor),
+            other => Err(anyhow!("Unknown install target '{}'. Expected claude, vscode or cursor", other)),
+        }
+    }
+}
+
+impl InstallTarget {
+    /// Location of the host's MCP config file.
+    pub fn config_path(&self) -> Result<PathBuf> {
+        match self {
+            InstallTarget::Claude => {
+                let base = if cfg!(target_os = "macos") {
+                    dirs::home_dir()
+                        .context("Failed to get home directory")?
+                        .join("Library")
+                        .join("Application Support")
+                } else {
+                    // %APPDATA% on Windows, ~/.config elsewhere.
+                    dirs::config_dir().context("Failed to get config directory")?
+                };
+                Ok(base.join("Claude").join("claude_desktop_config.json"))
+            }
+            // VS Code reads MCP servers from the workspace.
+            InstallTarget::VsCode => Ok(std::env::current_dir()?.join(".vscode").join("mcp.json")),
+            InstallTarget::Cursor => Ok(dirs::home_dir()
+                .context("Failed to get home directory")?
+                .join(".cursor")
+                .join("mcp.json")),
+        }
+    }
+
+    // VS Code keys servers under "servers", the others under "mcpServers".
+    fn servers_key(&self) -> &'static str {
+        match self {
+            InstallTarget::VsCode => "servers",
+            InstallTarget::Claude | InstallTarget::Cursor => "mcpServers",
+        }
+    }
+
+    fn server_entry(&self, command: &str) -> Value {
+        let args: Vec<&str> = STDIO_SERVER_ARGS.to_vec();
+        match self {
+            InstallTarget::VsCode => json!({ "type": "stdio", "command": command, "args": args }),
+            InstallTarget::Claude | InstallTarget::Cursor => json!({ "command": command, "args": args }),
+        }
+    }
+}
+
+fn read_config(path: &PathBuf) -> Result<Map<String, Value>> {
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+    let content = fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    if content.trim().is_empty() {
+        return Ok(Map::new());
+    }
+    match serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))? {
+        Value::Object(map) => Ok(map),
+        _ => Err(anyhow!("{} does not contain a JSON object", path.display())),
+    }
+}
+
+fn write_config(path: &PathBuf, config: &Map<String, Value>) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    if path.exists() {
+        // Keep the previous version around in case the host rejects the edit.
+        fs::copy(path, path.with_extension("json.bak"))?;
+    }
+    fs::write(path, serde_json::to_string_pretty(config)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(())
+}
+
+/// Adds or updates this binary's entry in the host config. Returns the config path.
+pub fn install(target: InstallTarget, name: &str) -> Result<PathBuf> {
+    let path = target.config_path()?;
+    let mut config = read_config(&path)?;
+
+    let command = std::env::current_exe()?.to_string_lossy().into_owned();
+    let servers = config
+        .entry(target.servers_key())
+        .or_insert_with(|| Value::Object(Map::new()))
+        .as_object_mut()
+        .ok_or_else(|| anyhow!("'{}' in {} is not an object", target.servers_key(), path.display()))?;
+    servers.insert(name.to_string(), target.server_entry(&command));
+
+    write_config(&path, &config)?;
+    Ok(path)
+}
+
+/// Removes this binary's entry from the host config.
+/// Returns the config path and whether an entry was removed.
+pub fn uninstall(target: InstallTarget, name: &str) -> Result<(PathBuf, bool)> {
+    let path = target.config_path()?;
+    if !path.exists() {
+        return Ok((path, false));
+    }
+    let mut config = read_config(&path)?;
+    let removed = config
+        .get_mut(target.servers_key())
+        .and_then(Value::as_object_mut)
+        .map(|servers| servers.remove(name).is_some())
+        .unwrap_or(false);
+    if removed {
+        write_config(&path, &config)?;
+    }
+    Ok((path, removed))
+}
@@ -0,0 +1,282 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{CallToolResult, JsonObject};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use tokio::task::AbortHandle;
+
+/// Argument that turns any tool call into a background job.
+pub const ASYNC_ARGUMENT: &str = "async";
+
+/// Finished jobs are deleted this long after they end.
+const JOB_RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum JobState {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// The server stopped while the job was running. It is not re-run, since
+    /// it may already have had side effects.
+    Interrupted,
+}
+
+impl JobState {
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobState::Queued | JobState::Running)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JobRecord {
+    pub id: String,
+    pub tool: String,
+    pub arguments: JsonObject,
+    pub state: JobState,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<CallToolResult>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<McpToolError>,
+}
+
+/// A job's status without its (possibly large) result or arguments.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct JobSummary {
+    pub job_id: String,
+    pub tool: String,
+    pub state: JobState,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[schemars(with = "Option<serde_json::Value>")]
+    pub error: Option<McpToolError>,
+}
+
+impl JobRecord {
+    pub fn job_summary(&self) -> JobSummary {
+        JobSummary {
+            job_id: self.id.clone(),
+            tool: self.tool.clone(),
+            state: self.state,
+            created_at: self.created_at,
+            started_at: self.started_at,
+            finished_at: self.finished_at,
+            error: self.error.clone(),
+        }
+    }
+
+    /// [`JobRecord::job_summary`] as JSON, for results, events and error details.
+    pub fn summary(&self) -> serde_json::Value {
+        serde_json::to_value(self.job_summary()).unwrap_or_default()
+    }
+}
+
+/// Background tool calls, persisted one JSON file per job so queued work
+/// survives a server restart.
+pub struct JobStore {
+    dir: PathBuf,
+    jobs: Mutex<HashMap<String, JobRecord>>,
+    running: Mutex<HashMap<String, AbortHandle>>,
+}
+
+impl JobStore {
+    /// Loads jobs left by a previous run. Jobs that were running are marked
+    /// interrupted; queued jobs are returned by `take_queued` to be restarted.
+    pub fn load(dir: PathBuf) -> Result<Self> {
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        let store = Self {
+            dir,
+            jobs: Mutex::new(HashMap::new()),
+            running: Mutex::new(HashMap::new()),
+        };
+        for entry in fs::read_dir(&store.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let record = fs::read_to_string(&path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<JobRecord>(&content).ok());
+            let Some(mut record) = record else {
+                tracing::warn!("Skipping unreadable job file {}", path.display());
+                continue;
+            };
+            if record.state == JobState::Running {
+                record.state = JobState::Interrupted;
+                record.finished_at = Some(Utc::now());
+                store.persist(&record);
+            }
+            store.jobs.lock().unwrap().insert(record.id.clone(), record);
+        }
+        Ok(store)
+    }
+
+    fn path_for(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn persist(&self, record: &JobRecord) {
+        let result = serde_json::to_vec(record)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(mcp_common::runtime_paths::write_private_file(&self.path_for(&record.id), &json)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to persist job {}: {}", record.id, e);
+        }
+    }
+
+    fn update(&self, id: &str, change: impl FnOnce(&mut JobRecord)) -> Option<JobRecord> {
+        let record = {
+            let mut jobs = self.jobs.lock().unwrap();
+            let record = jobs.get_mut(id)?;
+            change(record);
+            record.clone()
+        };
+        self.persist(&record);
+        Some(record)
+    }
+
+    pub fn submit(&self, tool: &str, arguments: JsonObject) -> JobRecord {
+        let record = JobRecord {
+            id: uuid::Uuid::new_v4().to_string(),
+            tool: tool.to_string(),
+            arguments,
+            state: JobState::Queued,
+            created_at: Utc::now(),
+            started_at: None,
+            finished_at: None,
+            result: None,
+            error: None,
+        };
+        self.persist(&record);
+        self.jobs.lock().unwrap().insert(record.id.clone(), record.clone());
+        record
+    }
+
+    /// Queued jobs from a previous run, oldest first.
+    pub fn take_queued(&self) -> Vec<JobRecord> {
+        let mut queued: Vec<JobRecord> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| record.state == JobState::Queued)
+            .cloned()
+            .collect();
+        queued.sort_by_key(|record| record.created_at);
+        queued
+    }
+
+    pub fn track(&self, id: &str, handle: AbortHandle) {
+        // The task may already have finished on another thread.
+        let finished = self.get(id).map(|record| record.state.is_finished()).unwrap_or(true);
+        if !finished {
+            self.running.lock().unwrap().insert(id.to_string(), handle);
+        }
+    }
+
+    pub fn mark_running(&self, id: &str) {
+        self.update(id, |record| {
+            record.state = JobState::Running;
+            record.started_at = Some(Utc::now());
+        });
+    }
+
+    pub fn finish(&self, id: &str, outcome: Result<CallToolResult, McpToolError>) -> Option<JobRecord> {
+        self.running.lock().unwrap().remove(id);
+        self.update(id, |record| {
+            // A cancelled job keeps its state even if the tool raced to completion.
+            if record.state == JobState::Cancelled {
+                return;
+            }
+            record.finished_at = Some(Utc::now());
+            match outcome {
+                Ok(result) => {
+                    record.state = JobState::Completed;
+                    record.result = Some(result);
+                }
+                Err(error) => {
+                    record.state = JobState::Failed;
+                    record.error = Some(error);
+                }
+            }
+        })
+    }
+
+    pub fn get(&self, id: &str) -> Result<JobRecord, McpToolError> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| McpToolError::new(ErrorCode::JobNotFound, format!("No job with id '{}'", id)))
+    }
+
+    pub fn list(&self) -> Vec<JobRecord> {
+        let mut jobs: Vec<JobRecord> = self.jobs.lock().unwrap().values().cloned().collect();
+        jobs.sort_by_key(|record| record.created_at);
+        jobs
+    }
+
+    pub fn cancel(&self, id: &str) -> Result<JobRecord, McpToolError> {
+        let record = self.get(id)?;
+        if record.state.is_finished() {
+            return Ok(record);
+        }
+        if let Some(handle) = self.running.lock().unwrap().remove(id) {
+            handle.abort();
+        }
+        self.update(id, |record| {
+            record.state = JobState::Cancelled;
+            record.finished_at = Some(Utc::now());
+        })
+        .ok_or_else(|| McpToolError::new(ErrorCode::JobNotFound, format!("No job with id '{}'", id)))
+    }
+
+    fn remove_expired(&self) {
+        let now = Utc::now();
+        let expired: Vec<String> = self
+            .jobs
+            .lock()
+            .unwrap()
+            .values()
+            .filter(|record| {
+                record.state.is_finished()
+                    && record
+                        .finished_at
+                        .and_then(|finished| (now - finished).to_std().ok())
+                        .is_some_and(|age| age > JOB_RETENTION)
+            })
+            .map(|record| record.id.clone())
+            .collect();
+        for id in expired {
+            self.jobs.lock().unwrap().remove(&id);
+            let _ = fs::remove_file(self.path_for(&id));
+        }
+    }
+
+    /// Periodically deletes finished jobs past the retention period.
+    pub fn spawn_reaper(self: std::sync::Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(60 * 60));
+            loop {
+                interval.tick().await;
+                self.remove_expired();
+            }
+        });
+    }
+}
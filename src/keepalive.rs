@@ -0,0 +1,73 @@
+use std::env;
+use std::time::Duration;
+
+use rmcp::model::{PingRequest, ServerRequest};
+use rmcp::service::{Peer, RoleServer, RunningServiceCancellationToken};
+
+const DEFAULT_INTERVAL_SECS: u64 = 30;
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_MAX_MISSES: u32 = 2;
+
+/// Ping schedule for detecting clients that silently went away (sleeping
+/// laptops, dropped VPNs), whose half-open sockets otherwise never close.
+#[derive(Debug, Clone, Copy)]
+pub struct KeepaliveConfig {
+    pub interval: Duration,
+    pub timeout: Duration,
+    /// Consecutive unanswered pings before the connection is closed.
+    pub max_misses: u32,
+}
+
+impl KeepaliveConfig {
+    /// `MCP_PING_INTERVAL_SECS` (0 disables pings), `MCP_PING_TIMEOUT_SECS`
+    /// and `MCP_PING_MAX_MISSES`.
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| env::var(name).ok().and_then(|value| value.parse().ok()).unwrap_or(default);
+        Self {
+            interval: Duration::from_secs(read("MCP_PING_INTERVAL_SECS", DEFAULT_INTERVAL_SECS)),
+            timeout: Duration::from_secs(read("MCP_PING_TIMEOUT_SECS", DEFAULT_TIMEOUT_SECS).max(1)),
+            max_misses: read("MCP_PING_MAX_MISSES", DEFAULT_MAX_MISSES as u64).max(1) as u32,
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.interval.is_zero()
+    }
+}
+
+/// Pings the client until it stops answering, then cancels the session so the
+/// connection's tasks are cleaned up. Returns once the session is cancelled.
+pub async fn monitor(
+    peer: Peer<RoleServer>,
+    cancellation: RunningServiceCancellationToken,
+    config: KeepaliveConfig,
+    label: String,
+) {
+    let mut interval = tokio::time::interval(config.interval);
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    // The first tick fires immediately; the client has only just connected.
+    interval.tick().await;
+
+    let mut misses = 0;
+    loop {
+        interval.tick().await;
+        let ping = peer.send_request(ServerRequest::PingRequest(PingRequest::default()));
+        match tokio::time::timeout(config.timeout, ping).await {
+            Ok(Ok(_)) => misses = 0,
+            Ok(Err(e)) => {
+                // The transport is already gone; the session is shutting down.
+                tracing::debug!("Ping to {} failed: {}", label, e);
+                break;
+            }
+            Err(_) => {
+                misses += 1;
+                tracing::warn!("Ping to {} timed out ({}/{})", label, misses, config.max_misses);
+                if misses >= config.max_misses {
+                    tracing::warn!("Closing unresponsive connection {}", label);
+                    break;
+                }
+            }
+        }
+    }
+    cancellation.cancel();
+}
@@ -1,7 +1,10 @@
 use rmcp::{handler::server::{ServerHandler, tool::ToolRouter}, tool_router};
+use rmcp::model::{CallToolResult, Content};
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::sync::Arc;
 use crate::level3_tool_module::{EchoTool, EchoInput}; // Import EchoTool and EchoInput
+use crate::registry::ToolRegistry;
 
 #[derive(Clone)]
 pub struct TimeTool {
@@ -9,7 +12,7 @@ pub struct TimeTool {
     pub echo_tool: Arc<EchoTool>, // Add EchoTool as a client
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct TimeInput {
     pub location: String,
 }
@@ -39,4 +42,11 @@ pub fn new_time_tool(echo_tool: Arc<EchoTool>) -> TimeTool {
         tool_router: ToolRouter::new(),
         echo_tool,
     }
-}
\ No newline at end of file
+}
+
+pub fn register_tools<C: Send + 'static>(registry: &mut ToolRegistry<C>, time_tool: Arc<TimeTool>) {
+    registry.register("get_time_in_location", "Reports the current time in a location.", move |_, input: TimeInput| {
+        let time_tool = time_tool.clone();
+        async move { Ok(CallToolResult::success(vec![Content::text(time_tool.get_time_in_location(input).await)])) }
+    });
+}
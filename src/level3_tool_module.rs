@@ -1,12 +1,16 @@
 use rmcp::{handler::server::{ServerHandler, tool::ToolRouter}, tool_router};
+use rmcp::model::{CallToolResult, Content};
+use schemars::JsonSchema;
 use serde::Deserialize;
+use std::sync::Arc;
+use crate::registry::ToolRegistry;
 
 #[derive(Clone)]
 pub struct EchoTool {
     tool_router: ToolRouter<Self>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct EchoInput {
     pub message: String,
 }
@@ -25,3 +29,10 @@ pub fn new_echo_tool() -> EchoTool {
         tool_router: ToolRouter::new(),
     }
 }
+
+pub fn register_tools<C: Send + 'static>(registry: &mut ToolRegistry<C>, echo_tool: Arc<EchoTool>) {
+    registry.register("echo", "Echoes the message back with an `Echo:` prefix.", move |_, input: EchoInput| {
+        let echo_tool = echo_tool.clone();
+        async move { Ok(CallToolResult::success(vec![Content::text(echo_tool.echo(input).await)])) }
+    });
+}
@@ -1 +1,5 @@
-pub mod system_commands;
\ No newline at end of file
+pub mod content;
+pub mod file_system;
+pub mod network_commands;
+pub mod sandbox;
+pub mod system_commands;
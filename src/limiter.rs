@@ -0,0 +1,373 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mcp_errors::{ErrorCode, McpToolError};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+
+/// Waiting callers allowed per tool when a limit doesn't say otherwise.
+const DEFAULT_MAX_QUEUE: usize = 16;
+
+/// Heavy tools that are limited out of the box; `MCP_TOOL_LIMITS` overrides these.
+/// `index_documents` rewrites the whole vector store, so calls take turns.
+const DEFAULT_LIMITS: &[(&str, usize)] = &[("execute_command", 4), ("chat_completion", 4), ("index_documents", 1)];
+
+/// Calls of any tools that may run at once; `MCP_MAX_CONCURRENT_CALLS` overrides it.
+const DEFAULT_GLOBAL_LIMIT: ToolLimit = ToolLimit {
+    max_concurrent: 64,
+    max_queue: 256,
+};
+
+/// Rate limit key that applies to all calls together.
+const ALL_TOOLS: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolLimit {
+    pub max_concurrent: usize,
+    pub max_queue: usize,
+}
+
+/// At most `calls` calls per `period`, with bursts of up to `calls`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimit {
+    pub calls: u32,
+    pub period: Duration,
+}
+
+// Token bucket holding up to `calls` tokens, refilled continuously.
+struct TokenBucket {
+    limit: RateLimit,
+    // Tokens left and when they were counted.
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(limit: RateLimit) -> Self {
+        Self {
+            limit,
+            state: Mutex::new((f64::from(limit.calls), Instant::now())),
+        }
+    }
+
+    fn per_second(&self) -> f64 {
+        f64::from(self.limit.calls) / self.limit.period.as_secs_f64().max(f64::EPSILON)
+    }
+
+    // Takes a token, or says how long until one is available.
+    fn try_take(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        let (tokens, counted_at) = *state;
+        let tokens = (tokens + now.duration_since(counted_at).as_secs_f64() * self.per_second())
+            .min(f64::from(self.limit.calls));
+        if tokens >= 1.0 {
+            *state = (tokens - 1.0, now);
+            Ok(())
+        } else {
+            *state = (tokens, now);
+            Err(Duration::from_secs_f64((1.0 - tokens) / self.per_second()))
+        }
+    }
+
+    // Returns a token taken for a call that was then refused elsewhere.
+    fn give_back(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.0 = (state.0 + 1.0).min(f64::from(self.limit.calls));
+    }
+}
+
+// One FIFO lane per limited tool. `queue` holds the tickets of waiting
+// callers in arrival order, which matches the order the (fair) semaphore
+// hands out permits.
+struct Lane {
+    limit: ToolLimit,
+    semaphore: Arc<Semaphore>,
+    queue: Mutex<VecDeque<u64>>,
+    // Bumped whenever someone leaves the queue so waiters can report progress.
+    moved: watch::Sender<u64>,
+}
+
+impl Lane {
+    fn new(limit: ToolLimit) -> Self {
+        Self {
+            limit,
+            semaphore: Arc::new(Semaphore::new(limit.max_concurrent)),
+            queue: Mutex::new(VecDeque::new()),
+            moved: watch::channel(0).0,
+        }
+    }
+}
+
+/// Protects the host from runaway callers: caps how many calls of a tool, and
+/// of all tools together, run at once across the whole server, and how often
+/// tools may be called. Tools without limits run immediately.
+pub struct ToolLimiter {
+    lanes: HashMap<String, Lane>,
+    global: Option<Lane>,
+    rates: HashMap<String, TokenBucket>,
+    next_ticket: AtomicU64,
+}
+
+/// Slots held by a running call; dropping it lets queued calls in.
+pub struct CallPermit {
+    _tool: Option<OwnedSemaphorePermit>,
+    _global: Option<OwnedSemaphorePermit>,
+}
+
+impl ToolLimiter {
+    pub fn new(
+        limits: HashMap<String, ToolLimit>,
+        global: Option<ToolLimit>,
+        rates: HashMap<String, RateLimit>,
+    ) -> Self {
+        let lanes = limits
+            .into_iter()
+            .filter(|(_, limit)| limit.max_concurrent > 0)
+            .map(|(tool, limit)| (tool, Lane::new(limit)))
+            .collect();
+        let rates = rates
+            .into_iter()
+            .filter(|(_, limit)| limit.calls > 0)
+            .map(|(tool, limit)| (tool, TokenBucket::new(limit)))
+            .collect();
+        Self {
+            lanes,
+            global: global.filter(|limit| limit.max_concurrent > 0).map(Lane::new),
+            rates,
+            next_ticket: AtomicU64::new(0),
+        }
+    }
+
+    /// Built-in limits, overridden by `MCP_TOOL_LIMITS`, e.g.
+    /// `chat_completion=2:8,execute_command=4` (concurrency, optional queue
+    /// length), and `MCP_MAX_CONCURRENT_CALLS` (`64:256` by default, 0 for no
+    /// cap). Rate limits come from `MCP_TOOL_RATE_LIMITS`, e.g.
+    /// `execute_command=10/1m,*=300/1m`, where `*` counts all calls together.
+    pub fn from_env() -> Self {
+        let mut limits: HashMap<String, ToolLimit> = DEFAULT_LIMITS
+            .iter()
+            .map(|(tool, max_concurrent)| {
+                (
+                    tool.to_string(),
+                    ToolLimit {
+                        max_concurrent: *max_concurrent,
+                        max_queue: DEFAULT_MAX_QUEUE,
+                    },
+                )
+            })
+            .collect();
+        if let Ok(spec) = env::var("MCP_TOOL_LIMITS") {
+            for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                match parse_limit(entry) {
+                    Some((tool, limit)) => {
+                        limits.insert(tool, limit);
+                    }
+                    None => tracing::warn!("Ignoring invalid MCP_TOOL_LIMITS entry '{}'", entry),
+                }
+            }
+        }
+        let global = match env::var("MCP_MAX_CONCURRENT_CALLS") {
+            Ok(spec) => match parse_counts(&spec, DEFAULT_GLOBAL_LIMIT.max_queue) {
+                Some(limit) => Some(limit),
+                None => {
+                    tracing::warn!("Ignoring invalid MCP_MAX_CONCURRENT_CALLS '{}'", spec);
+                    Some(DEFAULT_GLOBAL_LIMIT)
+                }
+            },
+            Err(_) => Some(DEFAULT_GLOBAL_LIMIT),
+        };
+        let mut rates = HashMap::new();
+        if let Ok(spec) = env::var("MCP_TOOL_RATE_LIMITS") {
+            for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                match parse_rate(entry) {
+                    Some((tool, limit)) => {
+                        rates.insert(tool, limit);
+                    }
+                    None => tracing::warn!("Ignoring invalid MCP_TOOL_RATE_LIMITS entry '{}'", entry),
+                }
+            }
+        }
+        Self::new(limits, global, rates)
+    }
+
+    /// Counts a call against the rate limits of `tool` and of all tools.
+    /// Fails with `common.rate_limited`, saying when to retry, if either is
+    /// used up.
+    pub fn check_rate(&self, tool: &str) -> Result<(), McpToolError> {
+        let tool_bucket = self.rates.get(tool);
+        if let Some(bucket) = tool_bucket {
+            bucket.try_take().map_err(|wait| rate_limited(tool, bucket.limit, wait))?;
+        }
+        if let Some(bucket) = self.rates.get(ALL_TOOLS) {
+            if let Err(wait) = bucket.try_take() {
+                if let Some(tool_bucket) = tool_bucket {
+                    tool_bucket.give_back();
+                }
+                return Err(rate_limited("all tools", bucket.limit, wait));
+            }
+        }
+        Ok(())
+    }
+
+    /// Waits for a slot to run `tool`, then for a slot among all calls.
+    /// While queued, `on_queued` is called with the number of calls ahead of
+    /// this one each time it changes. Fails immediately if a queue is already
+    /// full.
+    pub async fn acquire(&self, tool: &str, mut on_queued: impl FnMut(usize)) -> Result<CallPermit, McpToolError> {
+        let tool_permit = match self.lanes.get(tool) {
+            Some(lane) => Some(self.wait(lane, tool, &mut on_queued).await?),
+            None => None,
+        };
+        let global_permit = match &self.global {
+            Some(lane) => Some(self.wait(lane, "all tools", &mut on_queued).await?),
+            None => None,
+        };
+        Ok(CallPermit {
+            _tool: tool_permit,
+            _global: global_permit,
+        })
+    }
+
+    /// Like `acquire`, but for a call made by another call that already
+    /// holds a slot among all calls: waits only for a slot to run `tool`, so
+    /// nested calls can't starve each other of global slots.
+    pub async fn acquire_nested(&self, tool: &str) -> Result<CallPermit, McpToolError> {
+        let tool_permit = match self.lanes.get(tool) {
+            Some(lane) => Some(self.wait(lane, tool, &mut |_| {}).await?),
+            None => None,
+        };
+        Ok(CallPermit {
+            _tool: tool_permit,
+            _global: None,
+        })
+    }
+
+    async fn wait(
+        &self,
+        lane: &Lane,
+        name: &str,
+        on_queued: &mut impl FnMut(usize),
+    ) -> Result<OwnedSemaphorePermit, McpToolError> {
+        if let Ok(permit) = lane.semaphore.clone().try_acquire_owned() {
+            return Ok(permit);
+        }
+
+        let ticket = self.next_ticket.fetch_add(1, Ordering::Relaxed);
+        {
+            let mut queue = lane.queue.lock().unwrap();
+            if queue.len() >= lane.limit.max_queue {
+                return Err(McpToolError::new(
+                    ErrorCode::Busy,
+                    format!("Too many '{}' calls in progress; try again later", name),
+                )
+                .with_details(serde_json::json!({
+                    "max_concurrent": lane.limit.max_concurrent,
+                    "max_queue": lane.limit.max_queue,
+                })));
+            }
+            queue.push_back(ticket);
+        }
+        // Leaves the queue even if the caller gives up while waiting.
+        let _dequeue = Dequeue { lane, ticket };
+
+        let mut moved = lane.moved.subscribe();
+        let mut last_position = None;
+        let acquire = lane.semaphore.clone().acquire_owned();
+        tokio::pin!(acquire);
+        loop {
+            let position = lane.queue.lock().unwrap().iter().position(|t| *t == ticket);
+            if let Some(ahead) = position.filter(|_| position != last_position) {
+                on_queued(ahead);
+                last_position = position;
+            }
+            tokio::select! {
+                permit = &mut acquire => {
+                    return permit.map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()));
+                }
+                changed = moved.changed() => {
+                    if changed.is_err() {
+                        return Err(McpToolError::new(ErrorCode::Internal, "Tool limiter shut down"));
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn rate_limited(name: &str, limit: RateLimit, wait: Duration) -> McpToolError {
+    let retry_after_secs = wait.as_secs_f64().ceil().max(1.0) as u64;
+    McpToolError::new(
+        ErrorCode::RateLimited,
+        format!(
+            "Rate limit for {} reached ({} calls per {}s); retry in {}s",
+            name,
+            limit.calls,
+            limit.period.as_secs(),
+            retry_after_secs
+        ),
+    )
+    .with_details(serde_json::json!({
+        "retry_after_secs": retry_after_secs,
+        "calls": limit.calls,
+        "period_secs": limit.period.as_secs(),
+    }))
+}
+
+struct Dequeue<'a> {
+    lane: &'a Lane,
+    ticket: u64,
+}
+
+impl Drop for Dequeue<'_> {
+    fn drop(&mut self) {
+        self.lane.queue.lock().unwrap().retain(|t| *t != self.ticket);
+        self.lane.moved.send_modify(|generation| *generation += 1);
+    }
+}
+
+// `tool=concurrency` or `tool=concurrency:queue`.
+fn parse_limit(entry: &str) -> Option<(String, ToolLimit)> {
+    let (tool, limit) = entry.split_once('=')?;
+    Some((tool.trim().to_string(), parse_counts(limit, DEFAULT_MAX_QUEUE)?))
+}
+
+// `concurrency` or `concurrency:queue`.
+fn parse_counts(limit: &str, default_queue: usize) -> Option<ToolLimit> {
+    let (max_concurrent, max_queue) = match limit.split_once(':') {
+        Some((concurrent, queue)) => (concurrent.trim().parse().ok()?, queue.trim().parse().ok()?),
+        None => (limit.trim().parse().ok()?, default_queue),
+    };
+    Some(ToolLimit {
+        max_concurrent,
+        max_queue,
+    })
+}
+
+// `tool=calls/period`, where the period is a number of seconds with an
+// optional `s`, `m` or `h` suffix, e.g. `execute_command=10/1m`.
+fn parse_rate(entry: &str) -> Option<(String, RateLimit)> {
+    let (tool, rate) = entry.split_once('=')?;
+    let (calls, period) = rate.split_once('/')?;
+    let period = period.trim();
+    let split = period.find(|c: char| !c.is_ascii_digit()).unwrap_or(period.len());
+    let (number, unit) = period.split_at(split);
+    let number: u64 = number.parse().ok()?;
+    let seconds = match unit {
+        "" | "s" => number,
+        "m" => number * 60,
+        "h" => number * 60 * 60,
+        _ => return None,
+    };
+    if seconds == 0 {
+        return None;
+    }
+    Some((
+        tool.trim().to_string(),
+        RateLimit {
+            calls: calls.trim().parse().ok()?,
+            period: Duration::from_secs(seconds),
+        },
+    ))
+}
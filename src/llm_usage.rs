@@ -0,0 +1,387 @@
+//! Token and cost accounting for the LLM driver, with daily budgets.
+//!
+//! `MeteredLlm` wraps the configured `LlmDriver`. Every request is counted
+//! per day (UTC), driver and model, with the tokens the provider reports and
+//! a cost estimated from a price table. Totals are saved to `llm_usage.json`
+//! in the data dir. Once today's estimated spend reaches
+//! `MCP_LLM_DAILY_BUDGET_USD`, or today's tokens reach
+//! `MCP_LLM_DAILY_TOKEN_BUDGET`, requests fail with `llm.budget_exceeded`
+//! until midnight UTC.
+
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{Days, NaiveDate, Utc};
+use futures::StreamExt;
+use mcp_common::runtime_paths;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::copilot::{
+    AgentMessage, AgentTurn, ChatChunk, ChatResponse, ChatStream, Copilot, CopilotError, FunctionDefinition, LlmDriver,
+    Message, TokenUsage,
+};
+
+// Days of history kept in the usage file.
+const KEPT_DAYS: u64 = 90;
+const MAX_REPORT_DAYS: u32 = 90;
+
+/// USD per million prompt and completion tokens, by model id prefix. The
+/// longest matching prefix wins, so dated snapshots share their model's price.
+const PRICES: &[(&str, f64, f64)] = &[
+    ("gpt-4o-mini", 0.15, 0.60),
+    ("gpt-4o", 2.50, 10.00),
+    ("gpt-4.1-nano", 0.10, 0.40),
+    ("gpt-4.1-mini", 0.40, 1.60),
+    ("gpt-4.1", 2.00, 8.00),
+    ("gpt-4-turbo", 10.00, 30.00),
+    ("gpt-3.5-turbo", 0.50, 1.50),
+    ("o3-mini", 1.10, 4.40),
+    ("o4-mini", 1.10, 4.40),
+    ("text-embedding-3-small", 0.02, 0.0),
+    ("text-embedding-3-large", 0.13, 0.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-3-5-haiku", 0.80, 4.00),
+    ("claude-3-5-sonnet", 3.00, 15.00),
+    ("claude-3-7-sonnet", 3.00, 15.00),
+    ("claude-sonnet-4", 3.00, 15.00),
+    ("claude-opus-4", 15.00, 75.00),
+    ("gemini-1.5-flash", 0.075, 0.30),
+    ("gemini-1.5-pro", 1.25, 5.00),
+    ("gemini-2.0-flash", 0.10, 0.40),
+    ("gemini-2.5-flash", 0.30, 2.50),
+    ("gemini-2.5-pro", 1.25, 10.00),
+    ("text-embedding-004", 0.0, 0.0),
+];
+
+#[derive(Debug, Clone, Copy)]
+struct Price {
+    prompt: f64,
+    completion: f64,
+}
+
+impl Price {
+    // Requests through the `github` driver are covered by the Copilot subscription.
+    const FREE: Price = Price {
+        prompt: 0.0,
+        completion: 0.0,
+    };
+
+    fn cost(&self, usage: &TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt + usage.completion_tokens as f64 * self.completion) / 1_000_000.0
+    }
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+pub struct ModelUsage {
+    pub driver: String,
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated; zero for models without a known price.
+    pub cost_usd: f64,
+    /// Whether the model has a known price.
+    pub priced: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct DayUsage {
+    pub date: NaiveDate,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_usd: f64,
+    pub models: Vec<ModelUsage>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct BudgetStatus {
+    /// `MCP_LLM_DAILY_BUDGET_USD`, if set.
+    pub daily_usd: Option<f64>,
+    /// `MCP_LLM_DAILY_TOKEN_BUDGET`, if set.
+    pub daily_tokens: Option<u64>,
+    pub spent_today_usd: f64,
+    pub tokens_today: u64,
+    /// Whether requests are refused until midnight UTC.
+    pub exceeded: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetLlmUsageInput {
+    /// Days to report, counting back from today (default 1, at most 90).
+    pub days: Option<u32>,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct LlmUsageOutput {
+    /// Most recent first; days without requests are left out.
+    pub days: Vec<DayUsage>,
+    pub budget: BudgetStatus,
+}
+
+fn parse_price(entry: &str) -> Option<(String, Price)> {
+    let (model, rates) = entry.split_once('=')?;
+    let (prompt, completion) = rates.split_once('/')?;
+    let price = Price {
+        prompt: prompt.trim().parse().ok().filter(|rate: &f64| *rate >= 0.0)?,
+        completion: completion.trim().parse().ok().filter(|rate: &f64| *rate >= 0.0)?,
+    };
+    Some((model.trim().to_string(), price)).filter(|(model, _)| !model.is_empty())
+}
+
+fn env_limit<T: std::str::FromStr>(name: &str) -> Option<T> {
+    let value = env::var(name).ok().filter(|value| !value.trim().is_empty())?;
+    let limit = value.trim().parse().ok();
+    if limit.is_none() {
+        tracing::warn!("Ignoring invalid {} '{}'", name, value);
+    }
+    limit
+}
+
+pub struct UsageTracker {
+    path: PathBuf,
+    prices: BTreeMap<String, Price>,
+    daily_usd: Option<f64>,
+    daily_tokens: Option<u64>,
+    ledger: Mutex<BTreeMap<NaiveDate, Vec<ModelUsage>>>,
+}
+
+impl UsageTracker {
+    /// Loads saved usage from the data dir. Prices can be added or replaced
+    /// with `MCP_LLM_PRICES`, e.g. `gpt-4o=2.5/10,my-model=1/2`, in USD per
+    /// million prompt/completion tokens.
+    pub fn from_env() -> Self {
+        let mut prices: BTreeMap<String, Price> = PRICES
+            .iter()
+            .map(|(model, prompt, completion)| {
+                (
+                    model.to_string(),
+                    Price {
+                        prompt: *prompt,
+                        completion: *completion,
+                    },
+                )
+            })
+            .collect();
+        if let Ok(spec) = env::var("MCP_LLM_PRICES") {
+            for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                match parse_price(entry) {
+                    Some((model, price)) => {
+                        prices.insert(model, price);
+                    }
+                    None => tracing::warn!("Ignoring invalid MCP_LLM_PRICES entry '{}'", entry),
+                }
+            }
+        }
+
+        let path = runtime_paths::llm_usage_path().unwrap_or_else(|_| env::temp_dir().join("copilot_mcp_llm_usage.json"));
+        let ledger = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable LLM usage file {}: {}", path.display(), e);
+                BTreeMap::new()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => {
+                tracing::warn!("Could not read LLM usage file {}: {}", path.display(), e);
+                BTreeMap::new()
+            }
+        };
+        Self {
+            path,
+            prices,
+            daily_usd: env_limit("MCP_LLM_DAILY_BUDGET_USD"),
+            daily_tokens: env_limit("MCP_LLM_DAILY_TOKEN_BUDGET"),
+            ledger: Mutex::new(ledger),
+        }
+    }
+
+    fn price(&self, driver: &str, model: &str) -> Option<Price> {
+        if driver == "github" {
+            return Some(Price::FREE);
+        }
+        self.prices
+            .iter()
+            .filter(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, price)| *price)
+    }
+
+    fn today() -> NaiveDate {
+        Utc::now().date_naive()
+    }
+
+    fn budget(&self, ledger: &BTreeMap<NaiveDate, Vec<ModelUsage>>) -> BudgetStatus {
+        let today = ledger.get(&Self::today()).map(Vec::as_slice).unwrap_or_default();
+        let spent_today_usd: f64 = today.iter().map(|usage| usage.cost_usd).sum();
+        let tokens_today: u64 = today.iter().map(|usage| usage.prompt_tokens + usage.completion_tokens).sum();
+        let exceeded = self.daily_usd.is_some_and(|budget| spent_today_usd >= budget)
+            || self.daily_tokens.is_some_and(|budget| tokens_today >= budget);
+        BudgetStatus {
+            daily_usd: self.daily_usd,
+            daily_tokens: self.daily_tokens,
+            spent_today_usd,
+            tokens_today,
+            exceeded,
+        }
+    }
+
+    /// Fails if today's budget is used up.
+    pub fn check_budget(&self) -> Result<(), CopilotError> {
+        let status = self.budget(&self.ledger.lock().unwrap());
+        if !status.exceeded {
+            return Ok(());
+        }
+        let spent = match (status.daily_usd, status.daily_tokens) {
+            (Some(budget), _) if status.spent_today_usd >= budget => {
+                format!("${:.2} of ${:.2} spent", status.spent_today_usd, budget)
+            }
+            (_, Some(budget)) => format!("{} of {} tokens used", status.tokens_today, budget),
+            _ => String::new(),
+        };
+        Err(CopilotError::BudgetExceeded(format!(
+            "Today's LLM budget is used up ({}); it resets at midnight UTC",
+            spent
+        )))
+    }
+
+    /// Adds `requests` and `usage` to today's totals for `driver` and `model`.
+    pub fn record(&self, driver: &str, model: &str, requests: u64, usage: Option<&TokenUsage>) {
+        let price = self.price(driver, model);
+        let usage = usage.copied().unwrap_or_default();
+        let mut ledger = self.ledger.lock().unwrap();
+        let today = ledger.entry(Self::today()).or_default();
+        let index = match today.iter().position(|entry| entry.driver == driver && entry.model == model) {
+            Some(index) => index,
+            None => {
+                today.push(ModelUsage {
+                    driver: driver.to_string(),
+                    model: model.to_string(),
+                    requests: 0,
+                    prompt_tokens: 0,
+                    completion_tokens: 0,
+                    cost_usd: 0.0,
+                    priced: price.is_some(),
+                });
+                today.len() - 1
+            }
+        };
+        let entry = &mut today[index];
+        entry.requests += requests;
+        entry.prompt_tokens += usage.prompt_tokens;
+        entry.completion_tokens += usage.completion_tokens;
+        entry.cost_usd += price.map(|price| price.cost(&usage)).unwrap_or_default();
+
+        if let Some(oldest) = Self::today().checked_sub_days(Days::new(KEPT_DAYS)) {
+            ledger.retain(|date, _| *date > oldest);
+        }
+        let result = serde_json::to_vec(&*ledger)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(runtime_paths::write_private_file(&self.path, &json)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save LLM usage {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Usage over the last `days` days, and where today stands against the budget.
+    pub fn report(&self, input: GetLlmUsageInput) -> LlmUsageOutput {
+        let days = input.days.unwrap_or(1).clamp(1, MAX_REPORT_DAYS);
+        let ledger = self.ledger.lock().unwrap();
+        let first = Self::today()
+            .checked_sub_days(Days::new(u64::from(days) - 1))
+            .unwrap_or(NaiveDate::MIN);
+        let days = ledger
+            .range(first..)
+            .rev()
+            .map(|(date, models)| {
+                let mut models = models.clone();
+                models.sort_by(|a, b| b.cost_usd.total_cmp(&a.cost_usd).then(b.requests.cmp(&a.requests)));
+                DayUsage {
+                    date: *date,
+                    requests: models.iter().map(|usage| usage.requests).sum(),
+                    prompt_tokens: models.iter().map(|usage| usage.prompt_tokens).sum(),
+                    completion_tokens: models.iter().map(|usage| usage.completion_tokens).sum(),
+                    cost_usd: models.iter().map(|usage| usage.cost_usd).sum(),
+                    models,
+                }
+            })
+            .collect();
+        LlmUsageOutput {
+            days,
+            budget: self.budget(&ledger),
+        }
+    }
+}
+
+/// The LLM driver with every request checked against the budget and counted.
+pub struct MeteredLlm {
+    driver: LlmDriver,
+    usage: Arc<UsageTracker>,
+}
+
+impl MeteredLlm {
+    pub fn new(driver: LlmDriver, usage: Arc<UsageTracker>) -> Self {
+        Self { driver, usage }
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.driver.name()
+    }
+
+    pub fn embedding_model(&self) -> String {
+        self.driver.embedding_model()
+    }
+}
+
+#[async_trait]
+impl Copilot for MeteredLlm {
+    async fn chat_completion(&self, messages: Vec<Message>, model_id: String) -> Result<ChatResponse, CopilotError> {
+        self.usage.check_budget()?;
+        let response = self.driver.chat_completion(messages, model_id.clone()).await?;
+        self.usage.record(self.name(), &model_id, 1, response.usage.as_ref());
+        Ok(response)
+    }
+
+    // The request is counted when the stream opens; tokens as chunks report them.
+    async fn chat_completion_stream(&self, messages: Vec<Message>, model_id: String) -> Result<ChatStream, CopilotError> {
+        self.usage.check_budget()?;
+        let stream = self.driver.chat_completion_stream(messages, model_id.clone()).await?;
+        self.usage.record(self.name(), &model_id, 1, None);
+        let usage = self.usage.clone();
+        let driver = self.name();
+        Ok(Box::pin(stream.inspect(move |chunk| {
+            if let Ok(ChatChunk {
+                usage: Some(chunk_usage),
+                ..
+            }) = chunk
+            {
+                usage.record(driver, &model_id, 0, Some(chunk_usage));
+            }
+        })))
+    }
+
+    async fn chat_with_functions(
+        &self,
+        messages: Vec<AgentMessage>,
+        functions: &[FunctionDefinition],
+        model_id: String,
+    ) -> Result<AgentTurn, CopilotError> {
+        self.usage.check_budget()?;
+        let turn = self.driver.chat_with_functions(messages, functions, model_id.clone()).await?;
+        self.usage.record(self.name(), &model_id, 1, turn.usage.as_ref());
+        Ok(turn)
+    }
+
+    // Embedding responses carry no token counts here, so only requests are counted.
+    async fn embeddings(&self, inputs: Vec<String>, model_id: String) -> Result<Vec<Vec<f32>>, CopilotError> {
+        self.usage.check_budget()?;
+        let embeddings = self.driver.embeddings(inputs, model_id.clone()).await?;
+        self.usage.record(self.name(), &model_id, 1, None);
+        Ok(embeddings)
+    }
+}
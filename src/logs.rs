@@ -0,0 +1,324 @@
+use std::env;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use chrono::{DateTime, Days, Local, Timelike};
+use mcp_common::runtime_paths;
+
+const DEFAULT_MAX_SIZE_MB: u64 = 10;
+const DEFAULT_MAX_FILES: usize = 5;
+const DEFAULT_MAX_AGE_DAYS: u64 = 14;
+
+/// How the server writes its log file: one JSON object per event, or the
+/// human-readable lines it prints to a terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum LogFormat {
+    Json,
+    Pretty,
+}
+
+impl LogFormat {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            LogFormat::Json => "json",
+            LogFormat::Pretty => "pretty",
+        }
+    }
+}
+
+/// Whether logs are also rotated when the hour or the day changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogRotation {
+    Never,
+    Hourly,
+    Daily,
+}
+
+impl LogRotation {
+    /// When a log opened at `now` is next rotated, if ever.
+    fn next_after(self, now: DateTime<Local>) -> Option<DateTime<Local>> {
+        match self {
+            LogRotation::Never => None,
+            LogRotation::Hourly => {
+                let hour = now.with_minute(0)?.with_second(0)?.with_nanosecond(0)?;
+                Some(hour + chrono::Duration::hours(1))
+            }
+            LogRotation::Daily => (now.date_naive() + Days::new(1))
+                .and_hms_opt(0, 0, 0)?
+                .and_local_timezone(Local)
+                .earliest(),
+        }
+    }
+}
+
+impl std::str::FromStr for LogRotation {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "never" => Ok(LogRotation::Never),
+            "hourly" => Ok(LogRotation::Hourly),
+            "daily" => Ok(LogRotation::Daily),
+            other => Err(anyhow::anyhow!("Unknown log rotation '{}'. Expected never, hourly or daily", other)),
+        }
+    }
+}
+
+/// When to rotate logs and how many rotated files to keep.
+#[derive(Debug, Clone)]
+pub struct LogRetention {
+    pub max_size_bytes: u64,
+    /// Rotation by time, on top of rotation by size.
+    pub rotation: LogRotation,
+    /// Rotated files kept per log, newest first.
+    pub max_files: usize,
+    /// Rotated files older than this are removed; `None` keeps them regardless of age.
+    pub max_age: Option<Duration>,
+}
+
+impl LogRetention {
+    /// Reads `MCP_LOG_MAX_SIZE_MB`, `MCP_LOG_ROTATION` (`never`, `hourly` or
+    /// `daily`), `MCP_LOG_MAX_FILES` and `MCP_LOG_MAX_AGE_DAYS` (0 disables
+    /// the age limit).
+    pub fn from_env() -> Self {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+        let max_age_days = var("MCP_LOG_MAX_AGE_DAYS").unwrap_or(DEFAULT_MAX_AGE_DAYS);
+        Self {
+            max_size_bytes: var::<u64>("MCP_LOG_MAX_SIZE_MB").unwrap_or(DEFAULT_MAX_SIZE_MB) * 1024 * 1024,
+            rotation: var("MCP_LOG_ROTATION").unwrap_or(LogRotation::Daily),
+            max_files: var("MCP_LOG_MAX_FILES").unwrap_or(DEFAULT_MAX_FILES),
+            max_age: (max_age_days > 0).then(|| Duration::from_secs(max_age_days * 24 * 60 * 60)),
+        }
+    }
+}
+
+/// Moves a non-empty log aside as `<name>.<timestamp>` and prunes old rotations.
+pub fn rotate(path: &Path, retention: &LogRetention) -> io::Result<()> {
+    if fs::metadata(path).map(|m| m.len() > 0).unwrap_or(false) {
+        let stamp = Local::now().format("%Y%m%d-%H%M%S%.3f");
+        let mut rotated = path.as_os_str().to_owned();
+        rotated.push(format!(".{}", stamp));
+        fs::rename(path, rotated)?;
+    }
+    prune(path, retention)
+}
+
+fn prune(path: &Path, retention: &LogRetention) -> io::Result<()> {
+    let mut rotated = rotated_files(path)?;
+    rotated.sort_by(|a, b| b.modified.cmp(&a.modified));
+    let now = SystemTime::now();
+    for (index, file) in rotated.iter().enumerate() {
+        let too_old = retention
+            .max_age
+            .zip(now.duration_since(file.modified).ok())
+            .map(|(max_age, age)| age > max_age)
+            .unwrap_or(false);
+        if index >= retention.max_files || too_old {
+            fs::remove_file(&file.path)?;
+        }
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+pub struct LogFileInfo {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+/// Rotated copies of `path` (`<name>.<timestamp>`), in no particular order.
+pub fn rotated_files(path: &Path) -> io::Result<Vec<LogFileInfo>> {
+    let (Some(dir), Some(name)) = (path.parent(), path.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let prefix = format!("{}.", name.to_string_lossy());
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            let metadata = entry.metadata()?;
+            files.push(LogFileInfo {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+    }
+    Ok(files)
+}
+
+/// All server logs in the log dir, current and rotated, newest first.
+pub fn list_log_files() -> io::Result<Vec<LogFileInfo>> {
+    let dir = runtime_paths::log_dir()?;
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if name.starts_with("copilot_mcp_server.") && name.contains(".log") {
+            let metadata = entry.metadata()?;
+            files.push(LogFileInfo {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified()?,
+            });
+        }
+    }
+    files.sort_by(|a, b| b.modified.cmp(&a.modified));
+    Ok(files)
+}
+
+pub fn print_log_list() -> io::Result<()> {
+    let files = list_log_files()?;
+    if files.is_empty() {
+        println!("No server logs found in {}", runtime_paths::log_dir()?.display());
+        return Ok(());
+    }
+    for file in files {
+        let modified: DateTime<Local> = file.modified.into();
+        println!(
+            "{}  {:>10}  {}",
+            modified.format("%Y-%m-%d %H:%M:%S"),
+            file.size,
+            file.path.display()
+        );
+    }
+    Ok(())
+}
+
+const TAIL_CHUNK: u64 = 64 * 1024;
+
+/// Output of the `get_server_logs` tool.
+#[derive(Debug, serde::Serialize, schemars::JsonSchema)]
+pub struct ServerLogsOutput {
+    pub path: String,
+    /// Oldest first.
+    pub lines: Vec<String>,
+}
+
+/// The last `lines` lines of `path`, oldest first. Reads backwards from the
+/// end, so large logs cost no more than the lines returned. A missing file
+/// has no lines.
+pub fn tail(path: &Path, lines: usize) -> io::Result<Vec<String>> {
+    let mut file = match File::open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let len = file.metadata()?.len();
+    let mut start = len;
+    let mut buffer = Vec::new();
+    // One more newline than lines wanted, since the file usually ends with one.
+    while start > 0 && buffer.iter().filter(|&&b| b == b'\n').count() <= lines {
+        let chunk = TAIL_CHUNK.min(start);
+        start -= chunk;
+        file.seek(SeekFrom::Start(start))?;
+        let mut bytes = vec![0; chunk as usize];
+        file.read_exact(&mut bytes)?;
+        bytes.extend_from_slice(&buffer);
+        buffer = bytes;
+    }
+    let text = String::from_utf8_lossy(&buffer);
+    let all: Vec<&str> = text.lines().collect();
+    Ok(all[all.len().saturating_sub(lines)..].iter().map(|line| line.to_string()).collect())
+}
+
+/// Prints the last `lines` lines of `path`, then keeps printing what is
+/// appended until interrupted. Starts over when the file is rotated.
+pub fn follow(path: &Path, lines: usize) -> io::Result<()> {
+    for line in tail(path, lines)? {
+        println!("{}", line);
+    }
+    let mut position = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    let mut stdout = io::stdout();
+    loop {
+        std::thread::sleep(Duration::from_millis(500));
+        let len = match fs::metadata(path) {
+            Ok(metadata) => metadata.len(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e),
+        };
+        if len < position {
+            // Rotated: the file was replaced by a new, shorter one.
+            position = 0;
+        }
+        if len == position {
+            continue;
+        }
+        let mut file = File::open(path)?;
+        file.seek(SeekFrom::Start(position))?;
+        position += io::copy(&mut file.take(len - position), &mut stdout)?;
+        stdout.flush()?;
+    }
+}
+
+/// File writer for the server's tracing output that rotates once the file
+/// grows past `max_size_bytes`, and when the hour or day changes if the
+/// retention asks for it.
+pub struct RotatingWriter {
+    path: PathBuf,
+    retention: LogRetention,
+    file: File,
+    written: u64,
+    next_roll: Option<DateTime<Local>>,
+}
+
+impl RotatingWriter {
+    /// Opens `path`, rotating whatever the previous run left behind.
+    pub fn open(path: PathBuf, retention: LogRetention) -> io::Result<Self> {
+        rotate(&path, &retention)?;
+        let file = runtime_paths::create_private_file(&path)?;
+        let next_roll = retention.rotation.next_after(Local::now());
+        Ok(Self {
+            path,
+            retention,
+            file,
+            written: 0,
+            next_roll,
+        })
+    }
+
+    /// Opens `path` for appending, keeping what earlier runs wrote.
+    pub fn append(path: PathBuf, retention: LogRetention) -> io::Result<Self> {
+        let file = runtime_paths::append_private_file(&path)?;
+        let written = file.metadata()?.len();
+        let next_roll = retention.rotation.next_after(Local::now());
+        Ok(Self {
+            path,
+            retention,
+            file,
+            written,
+            next_roll,
+        })
+    }
+
+    fn roll(&mut self) -> io::Result<()> {
+        self.file.flush()?;
+        rotate(&self.path, &self.retention)?;
+        self.file = runtime_paths::create_private_file(&self.path)?;
+        self.written = 0;
+        self.next_roll = self.retention.rotation.next_after(Local::now());
+        Ok(())
+    }
+}
+
+impl Write for RotatingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let too_big = self.written + buf.len() as u64 > self.retention.max_size_bytes;
+        let too_old = self.next_roll.is_some_and(|next_roll| Local::now() >= next_roll);
+        if self.written > 0 && (too_big || too_old) {
+            self.roll()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
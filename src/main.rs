@@ -0,0 +1,2494 @@
+mod agent;
+mod audit;
+mod auth;
+mod cli;
+mod client;
+mod client_roots;
+mod config;
+mod conversations;
+mod copilot;
+mod doctor;
+mod elicitation;
+mod file_transfer;
+mod health;
+mod install;
+mod jobs;
+mod keepalive;
+mod level2_tool_module;
+mod level3_tool_module;
+mod limiter;
+mod llm_usage;
+mod logs;
+mod metrics;
+mod monitoring;
+mod notifications;
+mod oauth_tools;
+mod otel;
+mod output;
+mod pipeline;
+mod plugins;
+mod policy;
+mod progress;
+mod registry;
+mod repl;
+mod resources;
+mod result_cache;
+mod sampling;
+mod service;
+mod session;
+mod shutdown;
+mod telemetry;
+mod tls;
+mod tool_server_module;
+mod transport;
+mod vector_store;
+mod wasm_plugins;
+
+use std::env;
+use std::fs;
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use rmcp::model::{
+    AnnotateAble, CallToolRequestParam, CallToolResult, Content, Implementation, InitializeRequestParam,
+    InitializeResult, JsonObject, ListResourceTemplatesResult, ListResourcesResult, ListToolsResult,
+    PaginatedRequestParam, RawResourceTemplate, ReadResourceRequestParam, ReadResourceResult,
+    ServerCapabilities, ServerInfo, Tool,
+};
+use rmcp::service::{NotificationContext, Peer, RequestContext, RoleServer};
+use rmcp::{ErrorData as McpError, ServerHandler, ServiceExt};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+use tracing::Instrument;
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+
+use mcp_errors::{ErrorCode, McpToolError};
+use copilot_mcp_tool::file_system::{
+    FileSystemCommand, ListDirectoryInput, ListDirectoryOutput, LocalFileSystem, PathInfo, ReadFileInput, ReadFileOutput,
+    SearchFilesInput, SearchFilesOutput, StatPathInput, WriteFileInput, WriteFileOutput,
+};
+use mcp_common::framing::{self, Framing};
+use copilot_mcp_tool::network_commands::{
+    HttpProbeInput, HttpProbeOutput, LibNetworkCommand, NetworkCommand, PingHostInput, PingHostOutput, ResolveDnsInput,
+    ResolveDnsOutput,
+};
+use mcp_common::lock::{self, LockData};
+use mcp_common::runtime_paths;
+use copilot_mcp_tool::sandbox::Sandbox;
+use copilot_mcp_tool::system_commands::{
+    BinSystemCommand, DiskUsageOutput, ExecConfig, ExecuteCommandInput, ExecuteCommandOutput, GetProcessInfoInput,
+    GetProcessTreeInput, KillProcessInput, KillProcessOutput, ListPortsOutput, MemoryUsageOutput, ProcessDetails,
+    ProcessTreeOutput, SystemCommand,
+};
+use agent::{AgentRunInput, AgentRunOutput};
+use audit::{AuditEntry, AuditLog, AuditLogOutput, GetAuditLogInput};
+use clap::{CommandFactory, FromArgMatches};
+use cli::{
+    AuditArgs, CallArgs, Cli, Command as CliCommand, ConfigCommand, ConnectArgs, CopilotCommand, InstallArgs, LogsArgs, SecretCommand, StatusArgs,
+    TelemetryCommand,
+};
+use client::{Endpoint, McpClient};
+use output::OutputFormat;
+use copilot::{ChatResponse, Copilot, CopilotError, LlmDriver};
+use file_transfer::{UploadFileChunkInput, UploadStatus};
+use config::{AppConfig, KeyringSecrets, SecretProvider};
+use conversations::{
+    ConversationIdInput, ConversationOutput, ConversationStore, DeleteConversationOutput, ListConversationsInput,
+    ListConversationsOutput,
+};
+use notifications::{NotificationHub, SubscriptionFilter};
+use session::{PendingResultsOutput, SessionCache, SessionContextOutput, SESSION_CAPABILITY};
+use shutdown::Shutdown;
+use jobs::{JobRecord, JobStore, JobSummary};
+use keepalive::KeepaliveConfig;
+use limiter::ToolLimiter;
+use llm_usage::{GetLlmUsageInput, LlmUsageOutput, MeteredLlm, UsageTracker};
+use metrics::{GetSystemMetricsInput, MetricsHistory, SystemMetricsOutput};
+use policy::{Permission, ToolPolicy};
+use progress::ProgressReporter;
+use registry::{schema_for, ToolRegistry};
+use result_cache::ResultCache;
+use telemetry::Telemetry;
+use vector_store::{IndexDocumentsInput, IndexDocumentsOutput, SemanticSearchInput, SemanticSearchOutput, VectorStore};
+
+// --- Lock File Management ---
+
+fn read_lock_file() -> Result<LockData> {
+    lock::read(&runtime_paths::lock_file_path()?)
+}
+
+fn write_lock_file(data: &LockData) -> Result<()> {
+    lock::write(&runtime_paths::lock_file_path()?, data)
+}
+
+fn remove_lock_file() -> Result<()> {
+    Ok(lock::remove(&runtime_paths::lock_file_path()?)?)
+}
+
+// Connects `call`, `list`, `repl` and `run` to the local server from the lock
+// file, or to `--remote <host:port>` over TLS with the token from
+// `MCP_AUTH_TOKEN`. `--tls-ca <pem>` trusts an extra CA or self-signed certificate.
+async fn connect_client(connect: &ConnectArgs) -> Result<McpClient> {
+    let trusted: Vec<PathBuf> = connect.tls_ca.iter().cloned().collect();
+    let endpoint = match &connect.remote {
+        Some(remote) => {
+            let (host, port) = remote
+                .rsplit_once(':')
+                .ok_or_else(|| anyhow!("--remote expects <host>:<port>"))?;
+            let port: u16 = port.parse().map_err(|_| anyhow!("Invalid port in --remote '{}'", remote))?;
+            let host = host.trim_start_matches('[').trim_end_matches(']').to_string();
+            Endpoint::Remote { host, port, trusted }
+        }
+        None => Endpoint::Local { trusted },
+    };
+    let mut client = McpClient::open(endpoint, connect.framing).await?;
+    if let Some(secs) = connect.timeout {
+        client.set_timeout(Some(Duration::from_secs(secs)));
+    }
+    Ok(client)
+}
+
+fn is_process_running(pid: u32) -> bool {
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::Some(&[sysinfo::Pid::from_u32(pid)]), true);
+    system.process(sysinfo::Pid::from_u32(pid)).is_some()
+}
+
+// --- End Lock File Management ---
+
+// --- Tool Inputs ---
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct EchoMessageInput {
+    pub message: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetPendingResultsInput {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SetContextInput {
+    /// Variable to set, e.g. `cwd` or `obs_scene`.
+    pub key: String,
+    /// New value; `null` removes the variable.
+    pub value: Value,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetContextInput {
+    /// Variable to read; all of them when omitted.
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetMemoryUsageInput {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetDiskUsageInput {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ListPortsInput {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetServerLogsInput {
+    /// Number of lines from the end (default 100, at most 1000).
+    pub lines: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SummarizeLogsInput {
+    /// Number of lines from the end to summarize (default 100, at most 1000).
+    pub lines: Option<usize>,
+    /// What to look for, e.g. "failed tool calls".
+    pub focus: Option<String>,
+    /// Longest summary to ask for, in tokens (default 500).
+    pub max_tokens: Option<u32>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SummarizeLogsOutput {
+    pub summary: String,
+    /// The host model that wrote the summary.
+    pub model: String,
+    /// Log lines summarized.
+    pub lines: usize,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ChatCompletionInput {
+    pub messages: Vec<copilot::Message>,
+    /// Model id understood by the configured driver, e.g. `gpt-4o-mini`.
+    pub model: String,
+    /// Continues the saved conversation with this id: its earlier messages
+    /// are sent first, and these messages and the reply are added to it. An
+    /// unknown id starts a new conversation.
+    pub conversation: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct EventTopicsInput {
+    /// Exact topics or prefixes ending in `*`, e.g. `obs.*`.
+    pub topics: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct EventTopicsOutput {
+    /// This client's subscription after the change; empty means every topic.
+    pub topics: Vec<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct JobStatusInput {
+    /// Job to report on; all jobs when omitted.
+    #[serde(default)]
+    pub job_id: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct JobIdInput {
+    pub job_id: String,
+}
+
+// --- Tools ---
+
+const DEFAULT_LOG_LINES: usize = 100;
+const MAX_LOG_LINES: usize = 1000;
+const SUMMARY_MAX_TOKENS: u32 = 500;
+
+// Wraps a tool's typed output as structured content matching its output schema.
+fn structured<T: Serialize>(output: T) -> Result<CallToolResult, McpError> {
+    let value = serde_json::to_value(output).map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()))?;
+    Ok(CallToolResult::structured(value))
+}
+
+fn build_tool_registry() -> ToolRegistry<EchoServerTool> {
+    let mut tools = ToolRegistry::new().with_page_size_from_env();
+
+    tools.register("echo_message", "Echoes the given message back to the caller.", |_, input: EchoMessageInput| async move {
+        Ok(CallToolResult::success(vec![Content::text(input.message)]))
+    });
+    let kill_process = tools.register(
+        "kill_process",
+        "Kills a process by PID.",
+        |server: EchoServerTool, input: KillProcessInput| async move { Ok(server.state.system.kill_process(input).await) },
+    );
+    kill_process.output_schema = Some(schema_for::<KillProcessOutput>());
+    policy::mark_dangerous(kill_process);
+    tools
+        .register(
+            "get_memory_usage",
+            "Reports physical memory and swap usage in KB.",
+            |server: EchoServerTool, _: GetMemoryUsageInput| async move { Ok(server.state.system.get_memory_usage().await) },
+        )
+        .output_schema = Some(schema_for::<MemoryUsageOutput>());
+    tools
+        .register(
+            "get_disk_usage",
+            "Reports total and available space (GB) for each mounted disk.",
+            |server: EchoServerTool, _: GetDiskUsageInput| async move { Ok(server.state.system.get_disk_usage().await) },
+        )
+        .output_schema = Some(schema_for::<DiskUsageOutput>());
+    tools
+        .register(
+            "list_ports",
+            "Lists open TCP and UDP sockets with their state and owning process, where known.",
+            |server: EchoServerTool, _: ListPortsInput| async move { Ok(server.state.system.list_ports().await) },
+        )
+        .output_schema = Some(schema_for::<ListPortsOutput>());
+    tools
+        .register(
+            "ping_host",
+            "Measures TCP connect latency and loss to a host and port (default 443).",
+            |server: EchoServerTool, input: PingHostInput| async move { Ok(server.state.network.ping_host(input).await) },
+        )
+        .output_schema = Some(schema_for::<PingHostOutput>());
+    tools
+        .register(
+            "resolve_dns",
+            "Resolves a host name to its IPv4 and IPv6 addresses with the system resolver.",
+            |server: EchoServerTool, input: ResolveDnsInput| async move { Ok(server.state.network.resolve_dns(input).await) },
+        )
+        .output_schema = Some(schema_for::<ResolveDnsOutput>());
+    tools
+        .register(
+            "http_probe",
+            "Requests an http(s) URL and reports the status, latency and final URL after redirects.",
+            |server: EchoServerTool, input: HttpProbeInput| async move { Ok(server.state.network.http_probe(input).await) },
+        )
+        .output_schema = Some(schema_for::<HttpProbeOutput>());
+    tools
+        .register(
+            "get_system_metrics",
+            "Returns CPU, memory, disk and network usage sampled over time, optionally averaged to a coarser resolution.",
+            |server: EchoServerTool, input: GetSystemMetricsInput| async move {
+                match server.state.metrics.query(&input) {
+                    Ok(output) => structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<SystemMetricsOutput>());
+    tools
+        .register(
+            "get_process_tree",
+            "Shows processes as a parent/child tree, with CPU and memory totals for each subtree.",
+            |server: EchoServerTool, input: GetProcessTreeInput| async move {
+                Ok(server.state.system.get_process_tree(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<ProcessTreeOutput>());
+    tools
+        .register(
+            "get_process_info",
+            "Details one process: command line, working directory, start time, children, open files and optionally its environment.",
+            |server: EchoServerTool, input: GetProcessInfoInput| async move {
+                Ok(server.state.system.get_process_info(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<ProcessDetails>());
+    tools
+        .register(
+            "read_file",
+            "Reads a UTF-8 text file, or a byte range of it, under the allowed roots.",
+            |server: EchoServerTool, mut input: ReadFileInput| async move {
+                input.path = server.session_path(&input.path);
+                Ok(server.files().await.read_file(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<ReadFileOutput>());
+    let write_file = tools.register(
+        "write_file",
+        "Creates, replaces or appends to a text file under the allowed roots.",
+        |server: EchoServerTool, mut input: WriteFileInput| async move {
+            input.path = server.session_path(&input.path);
+            Ok(server.files().await.write_file(input).await)
+        },
+    );
+    write_file.output_schema = Some(schema_for::<WriteFileOutput>());
+    policy::mark_dangerous(write_file);
+    tools
+        .register(
+            "list_directory",
+            "Lists a directory's entries with their kind and size.",
+            |server: EchoServerTool, mut input: ListDirectoryInput| async move {
+                input.path = server.session_dir(input.path);
+                Ok(server.files().await.list_directory(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<ListDirectoryOutput>());
+    tools
+        .register(
+            "stat_path",
+            "Reports a path's kind, size, permissions and timestamps.",
+            |server: EchoServerTool, mut input: StatPathInput| async move {
+                input.path = server.session_path(&input.path);
+                Ok(server.files().await.stat_path(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<PathInfo>());
+    tools
+        .register(
+            "search_files",
+            "Finds files by name pattern under a directory, optionally only those containing some text.",
+            |server: EchoServerTool, mut input: SearchFilesInput| async move {
+                input.path = server.session_dir(input.path);
+                Ok(server.files().await.search_files(input).await)
+            },
+        )
+        .output_schema = Some(schema_for::<SearchFilesOutput>());
+    // Opt-in: a client that can run arbitrary programs can do anything the sandbox allows.
+    if ExecConfig::from_env().enabled {
+        let tool = tools.register(
+            "execute_command",
+            "Runs a program (not through a shell) in the sandbox and returns its exit code, stdout and stderr.",
+            |server: EchoServerTool, mut input: ExecuteCommandInput| async move {
+                input.cwd = server.session_dir(input.cwd);
+                Ok(server.state.system.execute_command(input, server.cancel.clone()).await)
+            },
+        );
+        tool.output_schema = Some(schema_for::<ExecuteCommandOutput>());
+        policy::mark_dangerous(tool);
+    }
+    tools
+        .register(
+            "get_server_logs",
+            "Returns the last lines of this server's log.",
+            |_, input: GetServerLogsInput| async move {
+                let lines = input.lines.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES);
+                let output = tokio::task::spawn_blocking(move || {
+                    let path = runtime_paths::server_log_path()?;
+                    let lines = logs::tail(&path, lines)?;
+                    Ok::<_, std::io::Error>(logs::ServerLogsOutput {
+                        path: path.display().to_string(),
+                        lines,
+                    })
+                })
+                .await
+                .map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()))?;
+                match output {
+                    Ok(output) => structured(output),
+                    Err(e) => Ok(McpToolError::from(e).into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<logs::ServerLogsOutput>());
+    tools
+        .register(
+            "summarize_logs",
+            "Summarizes the end of this server's log with the client's own model (MCP sampling); needs no LLM keys on the server.",
+            |server: EchoServerTool, input: SummarizeLogsInput| async move {
+                let peer = server
+                    .peer
+                    .clone()
+                    .ok_or_else(|| McpToolError::new(ErrorCode::NotImplemented, "summarize_logs needs a connected client; it can't run as a job"))?;
+                let lines = input.lines.unwrap_or(DEFAULT_LOG_LINES).min(MAX_LOG_LINES);
+                let log = tokio::task::spawn_blocking(move || logs::tail(&runtime_paths::server_log_path()?, lines))
+                    .await
+                    .map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()))?
+                    .map_err(McpToolError::from)?;
+                if log.is_empty() {
+                    return Ok(McpToolError::new(ErrorCode::ResourceNotFound, "The server log is empty").into_call_tool_result());
+                }
+                let mut prompt = format!("Summarize these {} lines of an MCP server's log.", log.len());
+                if let Some(focus) = &input.focus {
+                    prompt.push_str(&format!(" Focus on: {}.", focus));
+                }
+                prompt.push_str("\n\n");
+                prompt.push_str(&log.join("\n"));
+                let sampled = sampling::complete(
+                    &peer,
+                    "You summarize server logs for an operator: what happened, what failed and why, in a few short paragraphs.",
+                    prompt,
+                    input.max_tokens.unwrap_or(SUMMARY_MAX_TOKENS),
+                )
+                .await;
+                match sampled {
+                    Ok(sampled) => structured(SummarizeLogsOutput {
+                        summary: sampled.text,
+                        model: sampled.model,
+                        lines: log.len(),
+                    }),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<SummarizeLogsOutput>());
+    tools
+        .register(
+            "get_audit_log",
+            "Returns recent tool calls from the audit log, newest first, optionally filtered by tool, age or failure.",
+            |server: EchoServerTool, input: GetAuditLogInput| async move {
+                let path = server.state.audit.path().to_path_buf();
+                let output = tokio::task::spawn_blocking(move || {
+                    audit::query(&path, &input).map(|entries| AuditLogOutput {
+                        path: path.display().to_string(),
+                        entries,
+                    })
+                })
+                .await
+                .map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()))?;
+                match output {
+                    Ok(output) => structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<AuditLogOutput>());
+    tools
+        .register(
+            "chat_completion",
+            "Sends a chat conversation to the configured LLM driver (MCP_LLM_DRIVER) and returns its choices.",
+            |server: EchoServerTool, input: ChatCompletionInput| async move {
+                let llm = server.llm().await?;
+                let mut messages = match &input.conversation {
+                    Some(id) => match conversations::check_id(id).and_then(|()| server.state.conversations.history(id)) {
+                        Ok(history) => history,
+                        Err(e) => return Ok(e.into_call_tool_result()),
+                    },
+                    None => Vec::new(),
+                };
+                messages.extend(input.messages.iter().cloned());
+                // Clients that sent a progress token see the reply as it is generated.
+                let response = match server.progress.clone() {
+                    Some(progress) => {
+                        async {
+                            let stream = llm.chat_completion_stream(messages, input.model.clone()).await?;
+                            let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+                            let forward = tokio::spawn(async move {
+                                let mut received = 0.0;
+                                while let Some(delta) = receiver.recv().await {
+                                    received += 1.0;
+                                    progress.report(received, None, delta).await;
+                                }
+                            });
+                            let response = copilot::collect_stream(stream, |chunk| {
+                                if !chunk.delta.is_empty() {
+                                    let _ = sender.send(chunk.delta.clone());
+                                }
+                            })
+                            .await;
+                            drop(sender);
+                            let _ = forward.await;
+                            response
+                        }
+                        .await
+                    }
+                    None => llm.chat_completion(messages, input.model.clone()).await,
+                };
+                let response = match response {
+                    Ok(response) => response,
+                    Err(e) => return Ok(McpToolError::from(e).into_call_tool_result()),
+                };
+                if let Some(usage) = &response.usage {
+                    server.state.monitoring.record_llm_tokens(llm.name(), usage);
+                }
+                if let Some(id) = &input.conversation {
+                    let mut exchange = input.messages;
+                    exchange.extend(response.choices.first().map(|choice| choice.message.clone()));
+                    let session = server.current_session();
+                    if let Err(e) = server.state.conversations.append(id, session.as_deref(), &input.model, &exchange) {
+                        return Ok(e.into_call_tool_result());
+                    }
+                }
+                structured(response)
+            },
+        )
+        .output_schema = Some(schema_for::<ChatResponse>());
+    tools
+        .register(
+            "get_llm_usage",
+            "Reports LLM requests, tokens and estimated cost per day, driver and model, and today's spend against the daily budget.",
+            |server: EchoServerTool, input: GetLlmUsageInput| async move {
+                structured(server.state.llm_usage.report(input))
+            },
+        )
+        .output_schema = Some(schema_for::<LlmUsageOutput>());
+    tools
+        .register(
+            "list_conversations",
+            "Lists the conversations this session saved through chat_completion, or every saved conversation.",
+            |server: EchoServerTool, input: ListConversationsInput| async move {
+                let session = if input.all { None } else { server.current_session() };
+                if !input.all && session.is_none() {
+                    return Err(McpError::invalid_request("No session bound to this connection", None));
+                }
+                match server.state.conversations.list(session.as_deref()) {
+                    Ok(conversations) => structured(ListConversationsOutput { conversations }),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<ListConversationsOutput>());
+    tools
+        .register(
+            "get_conversation",
+            "Returns every message of a saved conversation.",
+            |server: EchoServerTool, input: ConversationIdInput| async move {
+                match server.state.conversations.get(&input.id) {
+                    Ok(conversation) => structured(conversation),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<ConversationOutput>());
+    let delete_conversation = tools.register(
+        "delete_conversation",
+        "Deletes a saved conversation and its messages.",
+        |server: EchoServerTool, input: ConversationIdInput| async move {
+            match server.state.conversations.delete(&input.id) {
+                Ok(output) => structured(output),
+                Err(e) => Ok(e.into_call_tool_result()),
+            }
+        },
+    );
+    delete_conversation.output_schema = Some(schema_for::<DeleteConversationOutput>());
+    policy::mark_dangerous(delete_conversation);
+    tools
+        .register(
+            "agent_run",
+            "Lets the configured LLM driver complete a task by calling this server's tools, and returns the answer with every call it made. Only tools that run without confirmation are offered.",
+            |server: EchoServerTool, input: AgentRunInput| async move {
+                let llm = server.llm().await?;
+                let tools: Vec<Tool> = server
+                    .state
+                    .tools
+                    .list()
+                    .into_iter()
+                    .filter(|tool| server.state.policy.permission(tool) == Permission::Allow)
+                    .collect();
+                let call_tool = |name: String, arguments: JsonObject| {
+                    let server = server.clone();
+                    async move { server.call_nested(name, arguments).await }
+                };
+                match agent::run(llm, tools, input, server.progress.clone(), call_tool).await {
+                    Ok(output) => {
+                        if let Some(usage) = &output.usage {
+                            server.state.monitoring.record_llm_tokens(llm.name(), usage);
+                        }
+                        structured(output)
+                    }
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<AgentRunOutput>());
+    tools
+        .register(
+            "index_documents",
+            "Embeds text files under the allowed roots, and optionally the solfunmeme memes, into the local vector store for semantic_search.",
+            |server: EchoServerTool, mut input: IndexDocumentsInput| async move {
+                let llm = server.llm().await?;
+                input.paths = input.paths.iter().map(|path| server.session_path(path)).collect();
+                match server.state.vectors.index(llm, server.files().await.roots().to_vec(), input).await {
+                    Ok(output) => structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<IndexDocumentsOutput>());
+    tools
+        .register(
+            "semantic_search",
+            "Finds indexed file chunks and memes closest in meaning to a query, using the embeddings from index_documents.",
+            |server: EchoServerTool, input: SemanticSearchInput| async move {
+                let llm = server.llm().await?;
+                match server.state.vectors.search(llm, input).await {
+                    Ok(output) => structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<SemanticSearchOutput>());
+    tools
+        .register(
+            "get_pending_results",
+            "Returns tool results that completed while this session was disconnected.",
+            |server: EchoServerTool, _: GetPendingResultsInput| async move {
+                let token = server
+                    .current_session()
+                    .ok_or_else(|| McpError::invalid_request("No session bound to this connection", None))?;
+                let results = server.state.sessions.take_pending(&token);
+                structured(PendingResultsOutput { results })
+            },
+        )
+        .output_schema = Some(schema_for::<PendingResultsOutput>());
+    tools
+        .register(
+            "set_context",
+            "Sets a variable in this session's context, which later calls can read; `cwd` sets the directory relative file paths resolve against.",
+            |server: EchoServerTool, input: SetContextInput| async move {
+                let token = server
+                    .current_session()
+                    .ok_or_else(|| McpError::invalid_request("No session bound to this connection", None))?;
+                let value = if input.key == session::CWD_VARIABLE && !input.value.is_null() {
+                    match server.check_session_cwd(&input.value) {
+                        Ok(cwd) => cwd,
+                        Err(e) => return Ok(e.into_call_tool_result()),
+                    }
+                } else {
+                    input.value
+                };
+                if let Err(e) = server.state.sessions.set_context(&token, &input.key, value) {
+                    return Ok(e.into_call_tool_result());
+                }
+                structured(SessionContextOutput {
+                    context: server.state.sessions.context(&token),
+                })
+            },
+        )
+        .output_schema = Some(schema_for::<SessionContextOutput>());
+    tools
+        .register(
+            "get_context",
+            "Returns this session's context variables, or just the one named.",
+            |server: EchoServerTool, input: GetContextInput| async move {
+                let token = server
+                    .current_session()
+                    .ok_or_else(|| McpError::invalid_request("No session bound to this connection", None))?;
+                let mut context = server.state.sessions.context(&token);
+                if let Some(key) = input.key {
+                    context.retain(|name, _| *name == key);
+                }
+                structured(SessionContextOutput { context })
+            },
+        )
+        .output_schema = Some(schema_for::<SessionContextOutput>());
+    tools
+        .register(
+            "subscribe_events",
+            "Restricts server event notifications to the given topics (all topics when none are set).",
+            |server: EchoServerTool, input: EventTopicsInput| async move {
+                server.subscriptions.subscribe(&input.topics);
+                structured(EventTopicsOutput {
+                    topics: server.subscriptions.patterns(),
+                })
+            },
+        )
+        .output_schema = Some(schema_for::<EventTopicsOutput>());
+    tools
+        .register(
+            "unsubscribe_events",
+            "Removes topics from this client's event subscription.",
+            |server: EchoServerTool, input: EventTopicsInput| async move {
+                server.subscriptions.unsubscribe(&input.topics);
+                structured(EventTopicsOutput {
+                    topics: server.subscriptions.patterns(),
+                })
+            },
+        )
+        .output_schema = Some(schema_for::<EventTopicsOutput>());
+    // job_status and job_result return differently shaped results per call,
+    // so they have no output schema.
+    tools.register(
+        "job_status",
+        "Reports the state of a background job, or of all jobs when no id is given. \
+         Start a job by calling any tool with `async: true`.",
+        |server: EchoServerTool, input: JobStatusInput| async move {
+            Ok(match input.job_id {
+                Some(id) => CallToolResult::structured(server.state.jobs.get(&id)?.summary()),
+                None => {
+                    let jobs: Vec<Value> = server.state.jobs.list().iter().map(JobRecord::summary).collect();
+                    CallToolResult::structured(serde_json::json!({ "jobs": jobs }))
+                }
+            })
+        },
+    );
+    tools.register(
+        "job_result",
+        "Returns the result of a finished background job.",
+        |server: EchoServerTool, input: JobIdInput| async move {
+            let job = server.state.jobs.get(&input.job_id)?;
+            match (job.result, job.error) {
+                (Some(result), _) => Ok(result),
+                (None, Some(error)) => Ok(error.into_call_tool_result()),
+                (None, None) => Ok(McpToolError::new(
+                    ErrorCode::JobNotFinished,
+                    format!("Job '{}' has no result (state: {:?})", job.id, job.state),
+                )
+                .with_details(job.summary())
+                .into_call_tool_result()),
+            }
+        },
+    );
+    tools
+        .register(
+            "job_cancel",
+            "Cancels a queued or running background job.",
+            |server: EchoServerTool, input: JobIdInput| async move {
+                structured(server.state.jobs.cancel(&input.job_id)?.job_summary())
+            },
+        )
+        .output_schema = Some(schema_for::<JobSummary>());
+    tools
+        .register(
+            "upload_file_chunk",
+            "Uploads a file in base64 chunks sent in order; set `complete` on the last one. \
+             Download files with ranged resources/read on file:// URIs.",
+            |server: EchoServerTool, input: UploadFileChunkInput| async move {
+                match file_transfer::write_chunk(&server.state.sandbox, input).await {
+                    Ok(status) => structured(status),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<UploadStatus>());
+
+    // The demo tool chain: weather may consult time, which may consult echo.
+    let echo_tool = Arc::new(level3_tool_module::new_echo_tool());
+    let time_tool = Arc::new(level2_tool_module::new_time_tool(echo_tool.clone()));
+    let weather_tool = Arc::new(tool_server_module::new_weather_tool(time_tool.clone()));
+    level3_tool_module::register_tools(&mut tools, echo_tool);
+    level2_tool_module::register_tools(&mut tools, time_tool);
+    tool_server_module::register_tools(&mut tools, weather_tool);
+    oauth_tools::register_tools(&mut tools);
+
+    tools
+}
+
+// --- MCP Server ---
+
+/// Server-wide subsystems shared by every connection.
+pub struct ServerState {
+    pub system: Arc<dyn SystemCommand>,
+    pub files: Arc<dyn FileSystemCommand>,
+    pub network: Arc<dyn NetworkCommand>,
+    pub sandbox: Arc<Sandbox>,
+    pub sessions: Arc<SessionCache>,
+    pub events: Arc<NotificationHub>,
+    pub telemetry: Arc<Telemetry>,
+    pub limiter: Arc<ToolLimiter>,
+    pub jobs: Arc<JobStore>,
+    pub cache: Arc<ResultCache>,
+    pub metrics: Arc<MetricsHistory>,
+    pub monitoring: Arc<monitoring::Monitoring>,
+    pub audit: Arc<AuditLog>,
+    pub health: health::Health,
+    pub plugins: Vec<Arc<plugins::PluginHost>>,
+    pub keepalive: KeepaliveConfig,
+    pub tools: Arc<ToolRegistry<EchoServerTool>>,
+    pub shutdown: Arc<Shutdown>,
+    pub policy: ToolPolicy,
+    // Created on first use; building the GitHub driver needs a network round trip.
+    pub llm: tokio::sync::OnceCell<MeteredLlm>,
+    pub llm_usage: Arc<UsageTracker>,
+    pub vectors: Arc<VectorStore>,
+    pub conversations: Arc<ConversationStore>,
+    pub oauth: oauth_tools::OAuthAccounts,
+}
+
+/// How long a cancelled call may keep running to clean up before it is dropped.
+const CANCEL_GRACE_PERIOD: Duration = Duration::from_secs(2);
+
+fn cancelled(tool_name: &str) -> McpToolError {
+    McpToolError::new(ErrorCode::Cancelled, format!("Call to {} was cancelled by the client", tool_name))
+}
+
+// Recovers the tool error behind an `McpError` from a call run inside the server.
+fn tool_error(e: McpError) -> McpToolError {
+    let code = e.data.as_ref().and_then(mcp_errors::code_of).unwrap_or(ErrorCode::Internal);
+    McpToolError::new(code, e.message.to_string())
+}
+
+#[derive(Clone)]
+pub struct EchoServerTool {
+    state: Arc<ServerState>,
+    // Session token bound to this connection once `initialize` has run.
+    session_token: Arc<Mutex<Option<String>>>,
+    subscriptions: Arc<SubscriptionFilter>,
+    // The file system as limited by the client's roots.
+    client_roots: Arc<client_roots::ClientRoots>,
+    // Set on the per-call clone when the client sent a progress token.
+    progress: Option<ProgressReporter>,
+    // Set on the per-call clone; cancelled when the client sends
+    // `notifications/cancelled` for the call.
+    cancel: CancellationToken,
+    // Set on the per-call clone; lets tools send requests such as sampling
+    // back to the client. Background jobs have none.
+    peer: Option<Peer<RoleServer>>,
+    // Token `initialize` must present; only set for TCP connections.
+    auth_token: Option<Arc<str>>,
+}
+
+impl EchoServerTool {
+    pub fn new(state: Arc<ServerState>) -> Self {
+        Self {
+            state,
+            session_token: Arc::new(Mutex::new(None)),
+            subscriptions: Arc::new(SubscriptionFilter::default()),
+            client_roots: Arc::new(client_roots::ClientRoots::default()),
+            progress: None,
+            cancel: CancellationToken::new(),
+            peer: None,
+            auth_token: None,
+        }
+    }
+
+    /// Requires clients to present `token` in `initialize`.
+    pub fn with_auth_token(mut self, token: Arc<str>) -> Self {
+        self.auth_token = Some(token);
+        self
+    }
+
+    fn current_session(&self) -> Option<String> {
+        self.session_token.lock().unwrap().clone()
+    }
+
+    // The LLM driver, created on first use and metered against the daily budget.
+    async fn llm(&self) -> Result<&MeteredLlm, McpToolError> {
+        self.state
+            .llm
+            .get_or_try_init(|| async {
+                let driver = LlmDriver::from_env().await?;
+                Ok::<_, CopilotError>(MeteredLlm::new(driver, self.state.llm_usage.clone()))
+            })
+            .await
+            .map_err(McpToolError::from)
+    }
+
+    // File tools for this connection, limited to the client's roots.
+    async fn files(&self) -> Arc<dyn FileSystemCommand> {
+        self.client_roots.files(&self.state.files).await
+    }
+
+    // Fetches the client's roots in the background: the client may not answer
+    // until the notification that prompted this has been handled.
+    fn refresh_client_roots(&self, peer: Peer<RoleServer>) {
+        let client_roots = self.client_roots.clone();
+        let configured = self.state.files.clone();
+        tokio::spawn(async move { client_roots.refresh(&peer, &configured).await });
+    }
+
+    // The session's `cwd` context variable, if set.
+    fn session_cwd(&self) -> Option<PathBuf> {
+        let token = self.current_session()?;
+        let cwd = self.state.sessions.context_variable(&token, session::CWD_VARIABLE)?;
+        cwd.as_str().map(PathBuf::from)
+    }
+
+    // Resolves a relative path from a file tool against the session's `cwd`.
+    // The sandbox still checks the result.
+    fn session_path(&self, path: &str) -> String {
+        match self.session_cwd() {
+            Some(cwd) if Path::new(path).is_relative() => cwd.join(path).to_string_lossy().into_owned(),
+            _ => path.to_string(),
+        }
+    }
+
+    // Like `session_path`, for directories that default to the session's `cwd`.
+    fn session_dir(&self, path: Option<String>) -> Option<String> {
+        match path {
+            Some(path) => Some(self.session_path(&path)),
+            None => self.session_cwd().map(|cwd| cwd.to_string_lossy().into_owned()),
+        }
+    }
+
+    // A new `cwd` must be a readable directory; it is stored canonicalized.
+    fn check_session_cwd(&self, value: &Value) -> Result<Value, McpToolError> {
+        let cwd = value
+            .as_str()
+            .ok_or_else(|| McpToolError::new(ErrorCode::InvalidArgument, "cwd must be a path"))?;
+        let dir = self.state.sandbox.check_read_path(Path::new(&self.session_path(cwd)))?;
+        if !dir.is_dir() {
+            return Err(McpToolError::new(ErrorCode::InvalidArgument, format!("{} is not a directory", dir.display())));
+        }
+        Ok(Value::String(dir.to_string_lossy().into_owned()))
+    }
+
+    // Checks a call against the tool policy. Clients that support elicitation
+    // are asked for the required arguments the call left out, and for
+    // confirmation instead of failing with `common.confirmation_required`.
+    async fn authorize_call(
+        &self,
+        tool: &Tool,
+        arguments: &mut Option<JsonObject>,
+        peer: &Peer<RoleServer>,
+    ) -> Result<(), McpToolError> {
+        let unconfirmed = match self.state.policy.check(tool, arguments.as_mut()) {
+            Ok(()) => None,
+            Err(e) if e.code == ErrorCode::ConfirmationRequired && elicitation::is_supported(peer) => Some(e),
+            Err(e) => return Err(e),
+        };
+        if !elicitation::is_supported(peer) {
+            return Ok(());
+        }
+        elicitation::fill_missing_arguments(peer, tool, arguments.get_or_insert_with(JsonObject::new)).await?;
+        let Some(refused) = unconfirmed else {
+            return Ok(());
+        };
+        match elicitation::confirm(peer, &tool.name, arguments.as_ref()).await {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(McpToolError::new(
+                ErrorCode::Cancelled,
+                format!("The user did not allow '{}' to run", tool.name),
+            )),
+            Err(e) => {
+                tracing::warn!("Could not ask the user to confirm {}: {}", tool.name, e);
+                Err(refused)
+            }
+        }
+    }
+
+    // Tool calls made by `agent_run` on the caller's behalf. They pass the same
+    // policy, rate limit, cancellation and audit checks as the caller's own.
+    async fn call_nested(&self, name: String, arguments: JsonObject) -> CallToolResult {
+        self.state.telemetry.record_tool_call(&name);
+        let started = std::time::Instant::now();
+        let arguments_sha256 = audit::hash_arguments(Some(&arguments));
+        let caller = self
+            .peer
+            .as_ref()
+            .and_then(|peer| peer.peer_info())
+            .map(|info| info.client_info.name.clone());
+        let outcome = async {
+            let mut arguments = Some(arguments);
+            if let Some(tool) = self.state.tools.get(&name) {
+                match &self.peer {
+                    Some(peer) => self.authorize_call(&tool, &mut arguments, peer).await?,
+                    None => self.state.policy.check(&tool, arguments.as_mut())?,
+                }
+            }
+            self.state.limiter.check_rate(&name)?;
+            let _permit = self.state.limiter.acquire_nested(&name).await?;
+            let request = CallToolRequestParam { name: name.clone().into(), arguments };
+            tokio::select! {
+                result = self.execute_tool(request) => result.map_err(tool_error),
+                _ = async {
+                    self.cancel.cancelled().await;
+                    tokio::time::sleep(CANCEL_GRACE_PERIOD).await;
+                } => Err(cancelled(&name)),
+            }
+        }
+        .await;
+        let result = outcome.unwrap_or_else(|e| {
+            tracing::warn!("Nested call to {} failed: {}", name, e);
+            e.into_call_tool_result()
+        });
+        self.record_call(name, arguments_sha256, caller, started, Ok(&result));
+        result
+    }
+
+    // Adds a finished call to the monitoring counters and the audit log, and
+    // returns its error code.
+    fn record_call(
+        &self,
+        tool: String,
+        arguments_sha256: String,
+        caller: Option<String>,
+        started: std::time::Instant,
+        result: Result<&CallToolResult, &McpError>,
+    ) -> Option<String> {
+        let error_code = match result {
+            Ok(result) if result.is_error == Some(true) => Some(
+                result
+                    .structured_content
+                    .as_ref()
+                    .and_then(mcp_errors::code_of)
+                    .map_or_else(|| "unknown".to_string(), |code| code.as_str().to_string()),
+            ),
+            Ok(_) => None,
+            Err(e) => Some(
+                e.data
+                    .as_ref()
+                    .and_then(mcp_errors::code_of)
+                    .map_or_else(|| e.code.0.to_string(), |code| code.as_str().to_string()),
+            ),
+        };
+        let duration = started.elapsed();
+        self.state.monitoring.record_tool_call(&tool, duration, error_code.as_deref());
+        self.state.audit.record(&AuditEntry {
+            timestamp: chrono::Utc::now(),
+            caller,
+            session: self.current_session(),
+            tool,
+            arguments_sha256,
+            duration_ms: duration.as_millis() as u64,
+            success: error_code.is_none(),
+            error_code: error_code.clone(),
+        });
+        error_code
+    }
+
+    // Runs the tool on its own task so the result survives a dropped connection
+    // and can be handed back to the client when it resumes the session.
+    async fn dispatch_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> Result<CallToolResult, McpError> {
+        let mut request = request;
+        let tool_name = request.name.to_string();
+        let Some(_in_flight) = self.state.shutdown.track() else {
+            return Ok(McpToolError::new(ErrorCode::ShuttingDown, "Server is shutting down").into_call_tool_result());
+        };
+        // Unknown tools fall through to the registry, which reports them.
+        if let Some(tool) = self.state.tools.get(&tool_name) {
+            if let Err(e) = self.authorize_call(&tool, &mut request.arguments, &context.peer).await {
+                tracing::warn!("Refused call to {}: {}", tool_name, e);
+                return Ok(e.into_call_tool_result());
+            }
+        }
+        // Counted on arrival, so background jobs are limited too.
+        if let Err(e) = self.state.limiter.check_rate(&tool_name) {
+            tracing::warn!("Rate limited call to {}: {}", tool_name, e);
+            return Ok(e.into_call_tool_result());
+        }
+
+        // `async: true` turns the call into a background job and returns its id.
+        let run_async = request
+            .arguments
+            .as_mut()
+            .and_then(|arguments| arguments.remove(jobs::ASYNC_ARGUMENT))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        if run_async {
+            let job = self.state.jobs.submit(&tool_name, request.arguments.unwrap_or_default());
+            self.spawn_job(job.clone());
+            return Ok(CallToolResult::structured(job.summary()));
+        }
+
+        let bypass_cache = request
+            .arguments
+            .as_mut()
+            .and_then(|arguments| arguments.remove(result_cache::BYPASS_ARGUMENT))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        // Cache keys don't include the session's `cwd`, which relative paths depend on.
+        let cached = self.state.cache.is_cached(&tool_name) && self.session_cwd().is_none();
+        // Clients with narrower roots must not see results cached for wider ones.
+        let roots = if cached { self.files().await.roots().to_vec() } else { Vec::new() };
+        if cached && !bypass_cache {
+            let hit = self.state.cache.get(&tool_name, request.arguments.as_ref(), &roots);
+            self.state.telemetry.record_cache_lookup(&tool_name, hit.is_some());
+            if let Some(result) = hit {
+                return Ok(result);
+            }
+        }
+
+        // Heavy tools wait for a slot; callers that asked for progress hear
+        // their queue position while they wait.
+        let progress = ProgressReporter::from_context(&context);
+        // Captured now: results that finish after a disconnect are buffered for it.
+        let session = self.current_session();
+        let cancel = CancellationToken::new();
+        // rmcp cancels every request when its connection drops. A call in a
+        // session keeps running then, so a resumed client can collect it.
+        tokio::spawn({
+            let cancel = cancel.clone();
+            let request_ct = context.ct.clone();
+            let peer = context.peer.clone();
+            let resumable = session.is_some();
+            async move {
+                request_ct.cancelled().await;
+                if !(resumable && peer.is_transport_closed()) {
+                    cancel.cancel();
+                }
+            }
+        });
+        let queued = self.state.limiter.acquire(&tool_name, |ahead| {
+            if let Some(progress) = progress.clone() {
+                tokio::spawn(async move {
+                    progress.report(0.0, None, format!("Queued behind {} other call(s)", ahead)).await;
+                });
+            }
+        });
+        let permit = tokio::select! {
+            permit = queued => permit?,
+            _ = cancel.cancelled() => return Ok(cancelled(&tool_name).into_call_tool_result()),
+        };
+
+        let mut server = self.clone();
+        server.progress = progress;
+        server.cancel = cancel.clone();
+        server.peer = Some(context.peer.clone());
+        let handle = tokio::spawn(async move {
+            let _permit = permit;
+            let arguments = request.arguments.clone().filter(|_| cached);
+            // Tools that watch the token get a moment to clean up; the rest
+            // are dropped once it has passed.
+            let result = tokio::select! {
+                result = server.execute_tool(request) => result,
+                _ = async {
+                    cancel.cancelled().await;
+                    tokio::time::sleep(CANCEL_GRACE_PERIOD).await;
+                } => return Ok(cancelled(&tool_name).into_call_tool_result()),
+            };
+            if let (true, Ok(call_result)) = (cached, &result) {
+                server.state.cache.insert(&tool_name, arguments.as_ref(), &roots, call_result);
+            }
+            if let (Some(token), Ok(call_result)) = (session, &result) {
+                server.state.sessions.record_result(&token, &tool_name, call_result);
+            }
+            result
+        });
+        handle
+            .await
+            .map_err(|e| McpError::internal_error(format!("Tool task failed: {}", e), None))?
+    }
+
+    fn spawn_job(&self, job: JobRecord) {
+        let server = self.clone();
+        let id = job.id.clone();
+        let handle = tokio::spawn(async move {
+            let outcome = match server.state.limiter.acquire(&job.tool, |_| {}).await {
+                Ok(_permit) => {
+                    server.state.jobs.mark_running(&job.id);
+                    let request = CallToolRequestParam {
+                        name: job.tool.clone().into(),
+                        arguments: Some(job.arguments.clone()),
+                    };
+                    server.execute_tool(request).await.map_err(tool_error)
+                }
+                Err(e) => Err(e),
+            };
+            if let Some(record) = server.state.jobs.finish(&job.id, outcome) {
+                server.state.events.publish("jobs.finished", record.summary());
+            }
+        });
+        self.state.jobs.track(&id, handle.abort_handle());
+    }
+
+    async fn execute_tool(&self, request: CallToolRequestParam) -> Result<CallToolResult, McpError> {
+        self.state
+            .tools
+            .call(self.clone(), &request.name, request.arguments)
+            .await
+    }
+}
+
+impl ServerHandler for EchoServerTool {
+    fn get_info(&self) -> ServerInfo {
+        ServerInfo {
+            capabilities: ServerCapabilities::builder()
+                .enable_tools()
+                .enable_resources()
+                .enable_logging()
+                .build(),
+            server_info: Implementation {
+                name: "copilot_mcp_tool".to_string(),
+                version: env!("CARGO_PKG_VERSION").to_string(),
+                ..Default::default()
+            },
+            instructions: Some("Echo and system management tools.".to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn initialize(
+        &self,
+        request: InitializeRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<InitializeResult, McpError>> + Send + '_ {
+        async move {
+            if let Some(expected) = &self.auth_token {
+                let authorized = auth::offered_token(&request).is_some_and(|offered| auth::token_matches(expected, offered));
+                if !authorized {
+                    tracing::warn!("Rejected client without a valid auth token");
+                    return Err(McpToolError::new(
+                        ErrorCode::PermissionDenied,
+                        "Missing or invalid auth token; read it from the server's lock file",
+                    )
+                    .into());
+                }
+            }
+
+            let resume_token = request
+                .capabilities
+                .experimental
+                .as_ref()
+                .and_then(|experimental| experimental.get(SESSION_CAPABILITY))
+                .and_then(|session| session.get("resume"))
+                .and_then(|token| token.as_str())
+                .map(str::to_string);
+
+            let (token, resumed) = self.state.sessions.open(resume_token.as_deref());
+            *self.session_token.lock().unwrap() = Some(token.clone());
+
+            if request.capabilities.roots.is_some() {
+                self.client_roots.declare();
+            }
+            if context.peer.peer_info().is_none() {
+                context.peer.set_peer_info(request);
+            }
+
+            let mut info = self.get_info();
+            info.capabilities
+                .experimental
+                .get_or_insert_with(Default::default)
+                .insert(
+                    SESSION_CAPABILITY.to_string(),
+                    serde_json::json!({
+                        "token": token,
+                        "resumed": resumed,
+                        "grace_period_secs": self.state.sessions.grace_period().as_secs(),
+                    })
+                    .as_object()
+                    .cloned()
+                    .unwrap_or_default(),
+                );
+            Ok(info)
+        }
+    }
+
+    fn on_initialized(&self, context: NotificationContext<RoleServer>) -> impl Future<Output = ()> + Send + '_ {
+        async move {
+            self.refresh_client_roots(context.peer.clone());
+            if let Some(token) = self.current_session() {
+                self.state
+                    .events
+                    .spawn_forwarder(context.peer.clone(), token.clone(), self.subscriptions.clone());
+                self.state
+                    .events
+                    .publish("server.client_connected", serde_json::json!({ "session": token }));
+            }
+        }
+    }
+
+    fn on_roots_list_changed(&self, context: NotificationContext<RoleServer>) -> impl Future<Output = ()> + Send + '_ {
+        async move {
+            self.refresh_client_roots(context.peer);
+        }
+    }
+
+    fn list_tools(
+        &self,
+        request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ListToolsResult, McpError>> + Send + '_ {
+        async move {
+            let cursor = request.as_ref().and_then(|request| request.cursor.as_deref());
+            let (tools, next_cursor) = self.state.tools.page(cursor)?;
+            let mut result = ListToolsResult::with_all_items(tools);
+            result.next_cursor = next_cursor;
+            Ok(result)
+        }
+    }
+
+    fn list_resources(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ListResourcesResult, McpError>> + Send + '_ {
+        async move {
+            let files = self.files().await;
+            let resources = resources::list(files.roots(), &self.state.metrics).await;
+            Ok(ListResourcesResult::with_all_items(resources))
+        }
+    }
+
+    fn list_resource_templates(
+        &self,
+        _request: Option<PaginatedRequestParam>,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ListResourceTemplatesResult, McpError>> + Send + '_ {
+        async move {
+            let template = RawResourceTemplate {
+                uri_template: file_transfer::FILE_URI_TEMPLATE.to_string(),
+                name: "file".to_string(),
+                title: None,
+                description: Some(format!(
+                    "A file in the working directory, read in chunks of up to `length` bytes (default {}).",
+                    file_transfer::DEFAULT_CHUNK_SIZE
+                )),
+                mime_type: None,
+            };
+            Ok(ListResourceTemplatesResult::with_all_items(vec![template.no_annotation()]))
+        }
+    }
+
+    fn read_resource(
+        &self,
+        request: ReadResourceRequestParam,
+        _context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<ReadResourceResult, McpError>> + Send + '_ {
+        async move {
+            let files = self.files().await;
+            let contents = resources::read(
+                &request.uri,
+                &self.state.sandbox,
+                files.roots(),
+                &self.state.metrics,
+            )
+            .await?;
+            Ok(ReadResourceResult { contents: vec![contents] })
+        }
+    }
+
+    fn call_tool(
+        &self,
+        request: CallToolRequestParam,
+        context: RequestContext<RoleServer>,
+    ) -> impl Future<Output = Result<CallToolResult, McpError>> + Send + '_ {
+        let span = tracing::info_span!("call_tool", tool = %request.name, error_code = tracing::field::Empty);
+        otel::continue_trace(&span, &context.meta);
+        async move {
+            tracing::info!("call_tool: {}", request.name);
+            self.state.telemetry.record_tool_call(&request.name);
+            let started = std::time::Instant::now();
+            let tool = request.name.to_string();
+            let arguments_sha256 = audit::hash_arguments(request.arguments.as_ref());
+            let caller = context.peer.peer_info().map(|info| info.client_info.name.clone());
+            let result = self.dispatch_tool(request, context).await;
+            if let Some(code) = self.record_call(tool, arguments_sha256, caller, started, result.as_ref()) {
+                tracing::Span::current().record("error_code", code.as_str());
+            }
+            result
+        }
+        .instrument(span)
+    }
+}
+
+/// Options accepted by the `serve`, `start` and `install-service` commands.
+#[derive(Debug, Default, clap::Args)]
+struct ServerOptions {
+    /// Serve one session over stdin/stdout instead of listening on TCP.
+    #[arg(long)]
+    stdio: bool,
+    /// Also serve the gRPC transport on this address (requires the `grpc` feature).
+    #[arg(long = "grpc", value_name = "ADDR")]
+    grpc_addr: Option<SocketAddr>,
+    /// Also serve the streamable-HTTP transport on this address.
+    #[arg(long = "http", value_name = "ADDR")]
+    http_addr: Option<SocketAddr>,
+    /// Serve Prometheus metrics at `/metrics` on this address.
+    #[arg(long = "metrics", value_name = "ADDR")]
+    metrics_addr: Option<SocketAddr>,
+    /// Where the TCP listener binds (default: an ephemeral loopback port).
+    #[arg(long, value_name = "ADDR")]
+    bind: Option<SocketAddr>,
+    /// PEM certificate chain to serve TLS with; needs --tls-key.
+    #[arg(long, value_name = "PEM")]
+    tls_cert: Option<PathBuf>,
+    /// PEM private key for --tls-cert.
+    #[arg(long, value_name = "PEM")]
+    tls_key: Option<PathBuf>,
+    /// Let dangerous tools run without `confirm: true`.
+    #[arg(long = "unsafe")]
+    allow_unsafe: bool,
+    /// Format of the log (default: json in the log file, pretty on a terminal).
+    #[arg(long, value_enum, value_name = "FORMAT")]
+    log_format: Option<logs::LogFormat>,
+}
+
+impl ServerOptions {
+    // Checks the combinations clap can't express and makes paths absolute,
+    // since a background server may run from another directory.
+    fn validate(mut self) -> Result<Self> {
+        let listeners = [self.grpc_addr, self.http_addr, self.metrics_addr, self.bind];
+        if self.stdio && (listeners.iter().any(Option::is_some) || self.tls_cert.is_some()) {
+            return Err(anyhow!("--stdio cannot be combined with --grpc, --http, --metrics, --bind or TLS"));
+        }
+        if self.tls_cert.is_some() != self.tls_key.is_some() {
+            return Err(anyhow!("--tls-cert and --tls-key must be given together"));
+        }
+        // Plain TCP would expose the auth token and every tool call to the network.
+        if self.bind.is_some_and(|addr| !addr.ip().is_loopback()) && self.tls_cert.is_none() {
+            return Err(anyhow!("Refusing to listen on a non-loopback address without TLS; pass --tls-cert and --tls-key"));
+        }
+        // The HTTP and gRPC transports don't speak TLS, so they stay on loopback.
+        for (flag, addr) in [("--http", self.http_addr), ("--grpc", self.grpc_addr)] {
+            if addr.is_some_and(|addr| !addr.ip().is_loopback()) {
+                return Err(anyhow!(
+                    "Refusing to serve {} on a non-loopback address without TLS; bind it to 127.0.0.1 behind a TLS proxy",
+                    flag
+                ));
+            }
+        }
+        self.tls_cert = self.tls_cert.map(fs::canonicalize).transpose()?;
+        self.tls_key = self.tls_key.map(fs::canonicalize).transpose()?;
+        Ok(self)
+    }
+
+    // The flags that give these options again, for a server started in another process.
+    fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if self.stdio {
+            args.push("--stdio".to_string());
+        }
+        if self.allow_unsafe {
+            args.push("--unsafe".to_string());
+        }
+        let values = [
+            ("--grpc", self.grpc_addr.map(|addr| addr.to_string())),
+            ("--http", self.http_addr.map(|addr| addr.to_string())),
+            ("--metrics", self.metrics_addr.map(|addr| addr.to_string())),
+            ("--bind", self.bind.map(|addr| addr.to_string())),
+            ("--tls-cert", self.tls_cert.as_ref().map(|path| path.to_string_lossy().into_owned())),
+            ("--tls-key", self.tls_key.as_ref().map(|path| path.to_string_lossy().into_owned())),
+            ("--log-format", self.log_format.map(|format| format.as_str().to_string())),
+        ];
+        for (flag, value) in values {
+            if let Some(value) = value {
+                args.extend([flag.to_string(), value]);
+            }
+        }
+        args
+    }
+}
+
+// TLS streams can't be peeked, so the framing is read from the decrypted
+// stream's buffer instead.
+async fn serve_tls(server: EchoServerTool, acceptor: tokio_rustls::TlsAcceptor, stream: tokio::net::TcpStream, addr: SocketAddr) {
+    let stream = match acceptor.accept(stream).await {
+        Ok(stream) => stream,
+        Err(e) => {
+            tracing::warn!("TLS handshake with {} failed: {}", addr, e);
+            return;
+        }
+    };
+    let mut io = tokio::io::BufReader::new(stream);
+    match framing::detect_buffered(&mut io).await {
+        Ok(Framing::Newline) => serve_connection(server, io, Framing::Newline, addr.to_string(), "tls", true).await,
+        Ok(Framing::ContentLength) => serve_connection(server, io, Framing::ContentLength, addr.to_string(), "tls+content-length", true).await,
+        Err(e) => tracing::warn!("Connection from {} failed before sending data: {}", addr, e),
+    }
+}
+
+/// Runs one MCP session over any byte stream in `framing` and detaches its
+/// session when the peer goes away. `pings` enables keepalive pings for
+/// transports that have no other way to notice a vanished peer. The framing
+/// bridge in front of the session answers `health` itself.
+async fn serve_connection<T>(server: EchoServerTool, io: T, framing: Framing, peer: String, transport: &'static str, pings: bool)
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + Unpin + 'static,
+{
+    let session_token = server.session_token.clone();
+    let state = server.state.clone();
+    state.telemetry.record_transport(transport);
+    let _connection = state.monitoring.track_connection(transport);
+    let io = framing::bridge_with(io, framing, Some(health::local_methods(state.clone(), server.auth_token.clone())));
+    match server.serve(io).await {
+        Ok(running) => {
+            // Once in-flight calls have drained, a stopping server closes every session.
+            let cancellation = running.cancellation_token();
+            let shutdown = state.shutdown.clone();
+            let closer = tokio::spawn(async move {
+                shutdown.closed().await;
+                cancellation.cancel();
+            });
+            let keepalive = (pings && state.keepalive.is_enabled()).then(|| {
+                tokio::spawn(keepalive::monitor(
+                    running.peer().clone(),
+                    running.cancellation_token(),
+                    state.keepalive,
+                    peer.clone(),
+                ))
+            });
+            if let Err(e) = running.waiting().await {
+                tracing::error!("Connection {} ended with error: {:?}", peer, e);
+            }
+            if let Some(keepalive) = keepalive {
+                keepalive.abort();
+            }
+            closer.abort();
+        }
+        Err(e) => tracing::error!("Failed to start MCP session for {}: {:?}", peer, e),
+    }
+    // Cloned, not taken: calls still running look it up to buffer their results.
+    let token = session_token.lock().unwrap().clone();
+    if let Some(token) = token {
+        state.sessions.detach(&token);
+        state
+            .events
+            .publish("server.client_disconnected", serde_json::json!({ "session": token }));
+    }
+    tracing::info!("Connection from {} closed", peer);
+}
+
+// Builds the shared server state and starts its background tasks.
+fn build_state(jobs: JobStore, policy: ToolPolicy) -> Arc<ServerState> {
+    let sandbox = Arc::new(Sandbox::from_env());
+    let plugins: Vec<Arc<plugins::PluginHost>> = [
+        plugins::PluginHost::native_from_env(),
+        plugins::PluginHost::wasm_from_env(sandbox.clone()),
+    ]
+    .into_iter()
+    .flatten()
+    .map(Arc::new)
+    .collect();
+    let state = Arc::new(ServerState {
+        system: Arc::new(BinSystemCommand::new(sandbox.clone(), ExecConfig::from_env())),
+        files: Arc::new(LocalFileSystem::from_env(sandbox.clone())),
+        network: Arc::new(LibNetworkCommand),
+        sandbox,
+        sessions: Arc::new(SessionCache::new(session::DEFAULT_GRACE_PERIOD)),
+        events: Arc::new(NotificationHub::new()),
+        telemetry: Arc::new(Telemetry::load_default()),
+        limiter: Arc::new(ToolLimiter::from_env()),
+        cache: Arc::new(ResultCache::from_env()),
+        metrics: Arc::new(MetricsHistory::from_env()),
+        monitoring: Arc::new(monitoring::Monitoring::new()),
+        audit: Arc::new(AuditLog::from_env()),
+        health: health::Health::new(),
+        plugins,
+        keepalive: KeepaliveConfig::from_env(),
+        tools: Arc::new(build_tool_registry()),
+        shutdown: Arc::new(Shutdown::new()),
+        policy,
+        llm: tokio::sync::OnceCell::new(),
+        llm_usage: Arc::new(UsageTracker::from_env()),
+        vectors: Arc::new(VectorStore::load_default()),
+        conversations: Arc::new(ConversationStore::open_default()),
+        oauth: oauth_tools::OAuthAccounts::default(),
+        jobs: Arc::new(jobs),
+    });
+    state.sessions.clone().spawn_reaper();
+    state.jobs.clone().spawn_reaper();
+    state.telemetry.clone().spawn_flusher();
+    state.metrics.clone().spawn_sampler();
+    for host in &state.plugins {
+        host.clone().spawn_watcher(state.tools.clone(), state.events.clone());
+    }
+    state
+}
+
+async fn run_server(options: ServerOptions) -> Result<()> {
+    if options.stdio {
+        return run_stdio_server(ToolPolicy::from_env(options.allow_unsafe)).await;
+    }
+
+    let acceptor = match (&options.tls_cert, &options.tls_key) {
+        (Some(cert), Some(key)) => Some(tls::acceptor(cert, key)?),
+        _ => None,
+    };
+    let bind = options.bind.unwrap_or(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 0));
+    let listener = tokio::net::TcpListener::bind(bind).await?;
+    let local_addr = listener.local_addr()?;
+    // Remote agents can't read the lock file, so a fixed token may be configured.
+    let token: Arc<str> = env::var("MCP_AUTH_TOKEN")
+        .ok()
+        .filter(|token| !token.is_empty())
+        .unwrap_or_else(auth::generate_token)
+        .into();
+    let ip = local_addr.ip();
+    write_lock_file(&LockData {
+        pid: std::process::id(),
+        port: local_addr.port(),
+        token: Some(token.to_string()),
+        host: (!ip.is_loopback() && !ip.is_unspecified()).then(|| ip.to_string()),
+        tls_cert: options.tls_cert.clone(),
+        ws_port: None,
+    })?;
+    // Not through tracing: it may be writing to the log file instead of stderr.
+    eprintln!("{} {}", lock::PORT_ANNOUNCEMENT, local_addr.port());
+    tracing::info!(
+        "MCP server listening on {}{}",
+        local_addr,
+        if acceptor.is_some() { " (TLS)" } else { "" }
+    );
+
+    let jobs = JobStore::load(runtime_paths::jobs_dir()?)?;
+    if options.allow_unsafe {
+        tracing::warn!("--unsafe: dangerous tools run without confirmation");
+    }
+    let state = build_state(jobs, ToolPolicy::from_env(options.allow_unsafe));
+    for job in state.jobs.take_queued() {
+        tracing::info!("Resuming queued job {} ({})", job.id, job.tool);
+        EchoServerTool::new(state.clone()).spawn_job(job);
+    }
+
+    if let Some(addr) = options.grpc_addr {
+        spawn_grpc_transport(addr, state.clone(), token.clone())?;
+    }
+    if let Some(addr) = options.http_addr {
+        spawn_http_transport(addr, state.clone(), token.clone());
+    }
+    if let Some(addr) = options.metrics_addr {
+        spawn_metrics_endpoint(addr, state.clone());
+    }
+
+    let stop_requested = shutdown::signal();
+    tokio::pin!(stop_requested);
+    loop {
+        let (stream, addr) = tokio::select! {
+            accepted = listener.accept() => accepted?,
+            _ = &mut stop_requested => break,
+        };
+        tracing::info!("Accepted connection from {}", addr);
+        let server = EchoServerTool::new(state.clone()).with_auth_token(token.clone());
+        let acceptor = acceptor.clone();
+        tokio::spawn(async move {
+            if let Some(acceptor) = acceptor {
+                return serve_tls(server, acceptor, stream, addr).await;
+            }
+            match framing::detect(&stream).await {
+                Ok(Framing::Newline) => serve_connection(server, stream, Framing::Newline, addr.to_string(), "tcp", true).await,
+                Ok(Framing::ContentLength) => {
+                    serve_connection(server, stream, Framing::ContentLength, addr.to_string(), "tcp+content-length", true).await
+                }
+                Err(e) => tracing::warn!("Connection from {} failed before sending data: {}", addr, e),
+            }
+        });
+    }
+
+    drop(listener);
+    tracing::info!("Shutting down; waiting for {} in-flight call(s)", state.shutdown.in_flight());
+    if !state.shutdown.drain(shutdown::drain_timeout()).await {
+        tracing::warn!("Abandoning {} call(s) still running", state.shutdown.in_flight());
+    }
+    state.shutdown.close();
+    if let Err(e) = state.telemetry.flush() {
+        tracing::warn!("Failed to save telemetry: {}", e);
+    }
+    // `start` may already have replaced a stale lock with a new server's.
+    lock::remove_own(&runtime_paths::lock_file_path()?)?;
+    // Let the sessions send their final messages before the runtime stops.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    tracing::info!("Server stopped.");
+    Ok(())
+}
+
+/// Serves a single session over stdin/stdout for hosts that spawn the server
+/// themselves. No lock file is written, so it runs alongside a TCP server.
+async fn run_stdio_server(policy: ToolPolicy) -> Result<()> {
+    // Hosts may run several instances at once, so each keeps its jobs in its
+    // own directory instead of sharing (and resuming) the TCP server's.
+    let jobs_dir = runtime_paths::runtime_dir()?
+        .join("stdio-jobs")
+        .join(std::process::id().to_string());
+    let state = build_state(JobStore::load(jobs_dir.clone())?, policy);
+    tracing::info!("MCP server serving on stdio");
+
+    let io = tokio::io::join(tokio::io::stdin(), tokio::io::stdout());
+    // No pings: the host owns our lifetime and closes stdin when it goes away.
+    serve_connection(EchoServerTool::new(state), io, Framing::Newline, "stdio".to_string(), "stdio", false).await;
+    let _ = fs::remove_dir_all(jobs_dir);
+    Ok(())
+}
+
+fn spawn_http_transport(addr: SocketAddr, state: Arc<ServerState>, token: Arc<str>) {
+    tokio::spawn(async move {
+        let result = transport::http::serve(addr, token.clone(), move |io, peer| {
+            let server = EchoServerTool::new(state.clone()).with_auth_token(token.clone());
+            // HTTP sessions expire after an idle timeout instead of pinging.
+            tokio::spawn(serve_connection(server, io, Framing::Newline, peer, "http", false));
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::error!("HTTP transport stopped: {:?}", e);
+        }
+    });
+}
+
+fn spawn_metrics_endpoint(addr: SocketAddr, state: Arc<ServerState>) {
+    tokio::spawn(async move {
+        if let Err(e) = monitoring::serve(addr, state.monitoring.clone()).await {
+            tracing::error!("Metrics endpoint stopped: {:?}", e);
+        }
+    });
+}
+
+#[cfg(feature = "grpc")]
+fn spawn_grpc_transport(addr: SocketAddr, state: Arc<ServerState>, token: Arc<str>) -> Result<()> {
+    tokio::spawn(async move {
+        let result = transport::grpc::serve(addr, move |io, peer| {
+            let server = EchoServerTool::new(state.clone()).with_auth_token(token.clone());
+            tokio::spawn(serve_connection(server, io, Framing::Newline, peer, "grpc", true));
+        })
+        .await;
+        if let Err(e) = result {
+            tracing::error!("gRPC transport stopped: {:?}", e);
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(feature = "grpc"))]
+fn spawn_grpc_transport(_addr: SocketAddr, _state: Arc<ServerState>, _token: Arc<str>) -> Result<()> {
+    Err(anyhow!("--grpc requires building with `--features grpc`"))
+}
+
+// --- Server Process Management ---
+
+fn start_server(options: ServerOptions) -> Result<()> {
+    // Checked here so mistakes are reported before the server detaches.
+    let options = options.validate()?;
+    if options.stdio {
+        return Err(anyhow!("`start` runs a TCP server; use `serve --stdio` instead"));
+    }
+
+    if let Ok(lock) = read_lock_file() {
+        if is_process_running(lock.pid) {
+            println!("Server is already RUNNING on port {} (PID: {}).", lock.port, lock.pid);
+            return Ok(());
+        }
+        remove_lock_file()?;
+    }
+
+    // The server writes everything worth keeping, panics included, to its own log.
+    let mut command = Command::new(env::current_exe()?);
+    command.arg("server").args(["--instance", runtime_paths::instance()]);
+    if let Some(dir) = runtime_paths::configured_log_dir() {
+        command.arg("--log-dir").arg(dir);
+    }
+    command
+        .args(options.to_args())
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+
+    println!("Server starting in background...");
+    for _ in 0..50 {
+        std::thread::sleep(Duration::from_millis(100));
+        if let Ok(lock) = read_lock_file() {
+            println!("Server is RUNNING on port {} (PID: {}).", lock.port, lock.pid);
+            return Ok(());
+        }
+    }
+    Err(anyhow!(
+        "Server did not report a port in time. Check {}",
+        runtime_paths::server_log_path()?.display()
+    ))
+}
+
+fn stop_server() -> Result<()> {
+    let lock = match read_lock_file() {
+        Ok(lock) => lock,
+        Err(_) => {
+            println!("Server is STOPPED.");
+            return Ok(());
+        }
+    };
+
+    println!("Stopping server (PID: {})...", lock.pid);
+    if request_stop(lock.pid) {
+        // The server drains in-flight calls, then removes its lock file and exits.
+        let deadline = std::time::Instant::now() + shutdown::drain_timeout() + Duration::from_secs(5);
+        while std::time::Instant::now() < deadline {
+            if !is_process_running(lock.pid) {
+                remove_lock_file_of(lock.pid)?;
+                println!("Server stopped.");
+                return Ok(());
+            }
+            std::thread::sleep(Duration::from_millis(200));
+        }
+        println!("Server did not stop in time; killing it.");
+    }
+    let mut system = sysinfo::System::new();
+    system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+    if let Some(process) = system.process(sysinfo::Pid::from_u32(lock.pid)) {
+        process.kill();
+    }
+    remove_lock_file_of(lock.pid)?;
+    println!("Server stopped.");
+    Ok(())
+}
+
+// Asks the server to shut down gracefully. Returns false where that isn't
+// possible (Windows has no SIGTERM for detached processes).
+#[cfg(unix)]
+fn request_stop(pid: u32) -> bool {
+    // SAFETY: kill(2) has no memory-safety preconditions.
+    unsafe { libc::kill(pid as libc::pid_t, libc::SIGTERM) == 0 }
+}
+
+#[cfg(not(unix))]
+fn request_stop(_pid: u32) -> bool {
+    false
+}
+
+// Removes the lock file unless a newer server has already replaced it.
+fn remove_lock_file_of(pid: u32) -> Result<()> {
+    match read_lock_file() {
+        Ok(lock) if lock.pid != pid => Ok(()),
+        _ => remove_lock_file(),
+    }
+}
+
+fn instance_status(name: &str, lock: &LockData) -> Value {
+    serde_json::json!({ "instance": name, "running": true, "port": lock.port, "pid": lock.pid })
+}
+
+fn show_status(output: Option<OutputFormat>) -> Result<()> {
+    if let Some(output) = output {
+        let status = match read_lock_file() {
+            Ok(lock) if is_process_running(lock.pid) => instance_status(runtime_paths::instance(), &lock),
+            stale => {
+                if stale.is_ok() {
+                    remove_lock_file()?;
+                }
+                serde_json::json!({ "instance": runtime_paths::instance(), "running": false })
+            }
+        };
+        return output.print(&status);
+    }
+    match read_lock_file() {
+        Ok(lock) if is_process_running(lock.pid) => {
+            println!("Server is RUNNING on port {} (PID: {}).", lock.port, lock.pid);
+        }
+        Ok(_) => {
+            remove_lock_file()?;
+            println!("Server is STOPPED (removed stale lock file).");
+        }
+        Err(_) => println!("Server is STOPPED."),
+    }
+    Ok(())
+}
+
+fn show_all_status(output: Option<OutputFormat>) -> Result<()> {
+    if let Some(output) = output {
+        let mut instances = Vec::new();
+        for (name, path) in runtime_paths::instance_lock_files()? {
+            match lock::read(&path) {
+                Ok(lock) if is_process_running(lock.pid) => instances.push(instance_status(&name, &lock)),
+                _ => fs::remove_file(&path)?,
+            }
+        }
+        return output.print(&serde_json::json!({ "running": !instances.is_empty(), "instances": instances }));
+    }
+    let mut running = 0;
+    for (name, path) in runtime_paths::instance_lock_files()? {
+        match lock::read(&path) {
+            Ok(lock) if is_process_running(lock.pid) => {
+                running += 1;
+                println!("Instance '{}' is RUNNING on port {} (PID: {}).", name, lock.port, lock.pid);
+            }
+            _ => {
+                fs::remove_file(&path)?;
+                println!("Instance '{}' is STOPPED (removed stale lock file).", name);
+            }
+        }
+    }
+    if running == 0 {
+        println!("Server is STOPPED.");
+    }
+    Ok(())
+}
+
+// --- Client Commands ---
+
+fn parse_key_value_params(pairs: &[String], schema: Option<&JsonObject>) -> Result<Value> {
+    let properties = schema.and_then(|schema| schema.get("properties"));
+    let mut params = serde_json::Map::new();
+    for pair in pairs {
+        let (key, value) = pair
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid parameter '{}', expected key=value", pair))?;
+        let property = properties.and_then(|properties| properties.get(key));
+        params.insert(key.to_string(), coerce_param(key, value, property)?);
+    }
+    Ok(Value::Object(params))
+}
+
+// JSON types a schema property accepts, looking through `anyOf`/`oneOf`.
+fn schema_types(property: &Value) -> Vec<&str> {
+    match property.get("type") {
+        Some(Value::String(kind)) => vec![kind.as_str()],
+        Some(Value::Array(kinds)) => kinds.iter().filter_map(Value::as_str).collect(),
+        _ => ["anyOf", "oneOf"]
+            .iter()
+            .filter_map(|key| property.get(*key).and_then(Value::as_array))
+            .flatten()
+            .flat_map(schema_types)
+            .collect(),
+    }
+}
+
+// Converts a key=value string to the type the tool's input schema declares,
+// so `pid=1234` is sent as a number. Keys the schema doesn't describe stay strings.
+fn coerce_param(key: &str, raw: &str, property: Option<&Value>) -> Result<Value> {
+    let Some(property) = property else {
+        return Ok(Value::String(raw.to_string()));
+    };
+    let types = schema_types(property);
+    if types.contains(&"string") {
+        return Ok(Value::String(raw.to_string()));
+    }
+    let parsed = serde_json::from_str::<Value>(raw).ok();
+    if types.is_empty() {
+        // Untyped properties ($ref to an enum or struct): JSON if it parses, else text.
+        return Ok(parsed.unwrap_or_else(|| Value::String(raw.to_string())));
+    }
+    let accepts = |value: &Value| {
+        types.iter().any(|kind| match *kind {
+            "integer" => value.is_i64() || value.is_u64(),
+            "number" => value.is_number(),
+            "boolean" => value.is_boolean(),
+            "array" => value.is_array(),
+            "object" => value.is_object(),
+            "null" => value.is_null(),
+            _ => false,
+        })
+    };
+    parsed
+        .filter(accepts)
+        .ok_or_else(|| anyhow!("Parameter '{}' expects {}, got '{}'", key, types.join(" or "), raw))
+}
+
+fn parse_json_params(source: &str, content: &str) -> Result<serde_json::Map<String, Value>> {
+    match serde_json::from_str(content).map_err(|e| anyhow!("Invalid JSON in {}: {}", source, e))? {
+        Value::Object(map) => Ok(map),
+        _ => Err(anyhow!("Parameters in {} must be a JSON object", source)),
+    }
+}
+
+// Builds call arguments from `--params-file <path>`, `--params <json>|-` (stdin;
+// `--json` is an alias) and key=value pairs. key=value pairs are applied last,
+// override the JSON, and are typed by the tool's input schema when given.
+fn parse_call_params(call: &CallArgs, schema: Option<&JsonObject>) -> Result<Value> {
+    let mut params = serde_json::Map::new();
+    for path in &call.params_file {
+        let content = fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        params.extend(parse_json_params(&path.display().to_string(), &content)?);
+    }
+    for value in &call.params {
+        if value == "-" {
+            let content = std::io::read_to_string(std::io::stdin())?;
+            params.extend(parse_json_params("stdin", &content)?);
+        } else {
+            params.extend(parse_json_params("--params", value)?);
+        }
+    }
+    if let Value::Object(overrides) = parse_key_value_params(&call.arguments, schema)? {
+        params.extend(overrides);
+    }
+    Ok(Value::Object(params))
+}
+
+// Reads the calls for `call --batch <file>|-`: a JSON array of
+// `{ "name": ..., "arguments": { ... } }` objects.
+fn parse_batch_calls(source: &str) -> Result<Vec<(String, Value)>> {
+    let content = if source == "-" {
+        std::io::read_to_string(std::io::stdin())?
+    } else {
+        fs::read_to_string(source).map_err(|e| anyhow!("Failed to read {}: {}", source, e))?
+    };
+    let calls = match serde_json::from_str(&content).map_err(|e| anyhow!("Invalid JSON in {}: {}", source, e))? {
+        Value::Array(calls) if !calls.is_empty() => calls,
+        _ => return Err(anyhow!("The batch in {} must be a non-empty JSON array of calls", source)),
+    };
+    calls
+        .into_iter()
+        .enumerate()
+        .map(|(index, call)| {
+            let name = call["name"].as_str().ok_or_else(|| anyhow!("Call {} in {} has no \"name\"", index, source))?;
+            let arguments = call.get("arguments").cloned().unwrap_or_else(|| serde_json::json!({}));
+            if !arguments.is_object() {
+                return Err(anyhow!("The arguments of call {} in {} must be a JSON object", index, source));
+            }
+            Ok((name.to_string(), arguments))
+        })
+        .collect()
+}
+
+// Base64 payloads shorter than this are printed as-is.
+const INLINE_BINARY_CHARS: usize = 256;
+
+// Replaces image/blob payloads in a tools/call response with a short note,
+// writing them to `save_dir` when given, so binary results don't flood the terminal.
+fn extract_binary_content(response: &mut Value, tool_name: &str, save_dir: Option<&std::path::Path>) -> Result<()> {
+    let Some(items) = response["content"].as_array_mut() else {
+        return Ok(());
+    };
+    for (index, item) in items.iter_mut().enumerate() {
+        let (pointer, mime_type) = match item["type"].as_str() {
+            Some("image") => ("/data", item["mimeType"].as_str().map(str::to_string)),
+            Some("resource") => ("/resource/blob", item["resource"]["mimeType"].as_str().map(str::to_string)),
+            _ => continue,
+        };
+        let Some(data) = item.pointer_mut(pointer) else { continue };
+        let Some(encoded) = data.as_str() else { continue };
+        let bytes = copilot_mcp_tool::content::decode_base64(encoded)?;
+        let encoded_len = encoded.len();
+        match save_dir {
+            Some(dir) => {
+                fs::create_dir_all(dir)?;
+                let extension = copilot_mcp_tool::content::extension_for(mime_type.as_deref().unwrap_or_default());
+                let path = dir.join(format!("{}-{}.{}", tool_name, index, extension));
+                fs::write(&path, &bytes)?;
+                *data = Value::String(format!("<{} bytes saved to {}>", bytes.len(), path.display()));
+            }
+            None if encoded_len > INLINE_BINARY_CHARS => {
+                *data = Value::String(format!("<{} bytes of binary data; use --save-binary <dir> to save>", bytes.len()));
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+// `list` when `call` is `None`.
+fn run_client_command(connect: &ConnectArgs, output: OutputFormat, call: Option<&CallArgs>) -> Result<()> {
+    let save_dir = call.and_then(|call| call.save_binary.as_deref());
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let mut client = connect_client(connect).await?;
+        client.initialize().await?;
+        client.initialized_notification().await?;
+
+        // Progress arrives while the call is still running; show it as it comes.
+        let showed_progress = Arc::new(AtomicBool::new(false));
+        if let Some(mut notifications) = client.take_notifications() {
+            let showed_progress = showed_progress.clone();
+            tokio::spawn(async move {
+                while let Some(notification) = notifications.recv().await {
+                    if notification["method"] == "notifications/progress" {
+                        if let Some(message) = notification["params"]["message"].as_str() {
+                            eprint!("{}", message);
+                            showed_progress.store(true, Ordering::Relaxed);
+                        }
+                    }
+                }
+            });
+        }
+
+        // Only a single call's result is shown as the tool's own output.
+        let single_call = call.is_some_and(|call| call.batch.is_none());
+        let response = match call {
+            None => serde_json::json!({ "tools": client.list_tools().await? }),
+            Some(CallArgs { batch: Some(source), .. }) => {
+                let calls = parse_batch_calls(source)?;
+                let names: Vec<String> = calls.iter().map(|(name, _)| name.clone()).collect();
+                let results = client.call_tools(calls).await?;
+                let mut responses = Vec::with_capacity(results.len());
+                for (index, (name, result)) in names.iter().zip(results).enumerate() {
+                    responses.push(match result {
+                        Ok(result) => {
+                            let mut response = serde_json::to_value(result)?;
+                            extract_binary_content(&mut response, &format!("{}-{}", name, index), save_dir)?;
+                            response
+                        }
+                        Err(e) => serde_json::json!({ "error": e.to_string() }),
+                    });
+                }
+                Value::Array(responses)
+            }
+            Some(call) => {
+                // clap requires a tool unless --batch is given.
+                let tool_name = call.tool.as_deref().unwrap_or_default();
+                let tools = client.list_tools().await?;
+                let schema = tools.iter().find(|tool| tool.name == tool_name).map(|tool| tool.input_schema.as_ref());
+                let params = parse_call_params(call, schema)?;
+                let progress_token = uuid::Uuid::new_v4().to_string();
+                let mut response = serde_json::to_value(client.call_tool(tool_name, params, Some(&progress_token)).await?)?;
+                extract_binary_content(&mut response, tool_name, save_dir)?;
+                response
+            }
+        };
+
+        if showed_progress.load(Ordering::Relaxed) {
+            eprintln!();
+        }
+        if single_call {
+            output.print_tool_result(&response)
+        } else {
+            output.print(&response)
+        }
+    })
+}
+
+fn run_install_command(args: &InstallArgs, uninstall: bool) -> Result<()> {
+    let (target, name) = (args.target, args.name.as_str());
+
+    if uninstall {
+        let (path, removed) = install::uninstall(target, name)?;
+        if removed {
+            println!("Removed '{}' from {}", name, path.display());
+        } else {
+            println!("'{}' is not configured in {}", name, path.display());
+        }
+    } else {
+        let path = install::install(target, name)?;
+        println!("Configured '{}' in {}. Restart the host to pick it up.", name, path.display());
+    }
+    Ok(())
+}
+
+// Installs the service with `options`, or removes it when there are none.
+fn run_service_command(options: Option<ServerOptions>) -> Result<()> {
+    let name = service::service_name();
+    let Some(options) = options else {
+        let (path, removed) = service::uninstall()?;
+        if removed {
+            println!("Removed service '{}' ({})", name, path.display());
+        } else {
+            println!("Service '{}' is not installed", name);
+        }
+        return Ok(());
+    };
+
+    // The options are passed to the server, so check them now.
+    let options = options.validate()?;
+    if options.stdio {
+        return Err(anyhow!("The service runs a TCP server; --stdio cannot be used"));
+    }
+    if let Ok(lock) = read_lock_file() {
+        if is_process_running(lock.pid) {
+            return Err(anyhow!(
+                "Server instance '{}' is already running (PID: {}). Run `stop` first",
+                runtime_paths::instance(),
+                lock.pid
+            ));
+        }
+    }
+    let path = service::install(&options.to_args())?;
+    println!("Installed service '{}' ({}). It starts now and at every login.", name, path.display());
+    Ok(())
+}
+
+fn run_telemetry_command(action: TelemetryCommand) -> Result<()> {
+    let telemetry = Telemetry::load(Telemetry::default_path()?)?;
+    match action {
+        TelemetryCommand::Enable => {
+            telemetry.set_enabled(true)?;
+            println!("Telemetry enabled. Usage counts are stored locally in {}", Telemetry::default_path()?.display());
+        }
+        TelemetryCommand::Disable => {
+            telemetry.set_enabled(false)?;
+            println!("Telemetry disabled.");
+        }
+        TelemetryCommand::Reset => {
+            telemetry.reset()?;
+            println!("Telemetry counts cleared.");
+        }
+        TelemetryCommand::Status => {
+            let data = telemetry.snapshot();
+            println!("Telemetry is {}.", if data.enabled { "ENABLED" } else { "DISABLED" });
+        }
+        TelemetryCommand::Export { file } => {
+            let json = serde_json::to_string_pretty(&telemetry.snapshot())?;
+            match file {
+                Some(path) => {
+                    fs::write(&path, json)?;
+                    println!("Telemetry exported to {}", path.display());
+                }
+                None => println!("{}", json),
+            }
+        }
+    }
+    Ok(())
+}
+
+fn run_secret_command(action: SecretCommand) -> Result<()> {
+    let secrets = KeyringSecrets;
+    match action {
+        SecretCommand::Set { name } => {
+            use std::io::IsTerminal;
+            if std::io::stdin().is_terminal() {
+                eprint!("Value for '{}': ", name);
+            }
+            let mut value = String::new();
+            std::io::stdin().read_line(&mut value)?;
+            let value = value.trim_end_matches(['\r', '\n']);
+            if value.is_empty() {
+                return Err(anyhow!("Refusing to store an empty secret"));
+            }
+            secrets.set(&name, value)?;
+            println!("Stored secret '{}'. Reference it in the config as ${{secret:{}}}", name, name);
+        }
+        SecretCommand::Delete { name } => {
+            if secrets.delete(&name)? {
+                println!("Deleted secret '{}'.", name);
+            } else {
+                println!("Secret '{}' is not set.", name);
+            }
+        }
+    }
+    Ok(())
+}
+
+// The server runs in the background, so signing in to Copilot is a separate,
+// interactive step; the token it stores is picked up by `MCP_LLM_DRIVER=github`.
+fn run_copilot_command(action: CopilotCommand) -> Result<()> {
+    use mcp_oauth_plugin::github_copilot::{GitHubCopilotAuth, COPILOT_SESSION_PROVIDER_ID, GITHUB_PROVIDER_ID};
+    use mcp_oauth_plugin::token_store::TokenStore;
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let token_store = TokenStore::default().await?;
+        let auth = GitHubCopilotAuth::new(token_store.clone());
+        match action {
+            CopilotCommand::Login => {
+                let code = auth.start_device_flow().await?;
+                println!("Open {} and enter the code {}", code.verification_uri, code.user_code);
+                auth.poll_device_flow(&code).await?;
+                let session = auth.copilot_token().await?;
+                println!("Signed in to GitHub Copilot (session valid until {}).", session.expires_at);
+            }
+            CopilotCommand::Logout => {
+                token_store.remove(GITHUB_PROVIDER_ID).await?;
+                token_store.remove(COPILOT_SESSION_PROVIDER_ID).await?;
+                println!("Removed the stored GitHub Copilot tokens.");
+            }
+            CopilotCommand::Status => match auth.copilot_token().await {
+                Ok(session) => println!("GitHub Copilot: signed in (session valid until {}).", session.expires_at),
+                Err(e) => println!("GitHub Copilot: not available ({:#}).", e),
+            },
+        }
+        Ok(())
+    })
+}
+
+fn run_logs_command(args: LogsArgs) -> Result<()> {
+    if args.list {
+        return Ok(logs::print_log_list()?);
+    }
+    let (log, lines) = (args.log, args.lines);
+    let path = log.path()?;
+    if args.follow {
+        eprintln!("Following {} (Ctrl-C to stop)", path.display());
+        return Ok(logs::follow(&path, lines)?);
+    }
+    for line in logs::tail(&path, lines)? {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+fn run_audit_command(args: AuditArgs) -> Result<()> {
+    let input = GetAuditLogInput {
+        tool: args.tool,
+        since: args.since,
+        errors_only: args.errors,
+        limit: args.limit,
+    };
+    let path = runtime_paths::audit_log_path()?;
+    let entries = audit::query(&path, &input)?;
+    if args.json {
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+    if entries.is_empty() {
+        println!("No matching tool calls in {}", path.display());
+        return Ok(());
+    }
+    // Oldest first, like `logs`.
+    for entry in entries.iter().rev() {
+        let timestamp: chrono::DateTime<chrono::Local> = entry.timestamp.into();
+        println!(
+            "{}  {:<24} {:>7} ms  {:<28} {}",
+            timestamp.format("%Y-%m-%d %H:%M:%S"),
+            entry.tool,
+            entry.duration_ms,
+            entry.error_code.as_deref().unwrap_or("ok"),
+            entry.caller.as_deref().unwrap_or("-"),
+        );
+    }
+    Ok(())
+}
+
+fn run_config_command(action: ConfigCommand) -> Result<()> {
+    match action {
+        ConfigCommand::Path => println!("{}", AppConfig::path()?.display()),
+        ConfigCommand::Check => {
+            let config = AppConfig::load(&KeyringSecrets)?;
+            println!(
+                "{} is valid ({} env entries).",
+                AppConfig::path()?.display(),
+                config.env.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+// Interactive runs log to the terminal; background runs write a rotated log
+// file, through a background thread so a slow disk doesn't hold up tool
+// calls. Keep the returned guard until exit: dropping it flushes the file.
+fn init_server_logging(format: Option<logs::LogFormat>) -> Result<Option<tracing_appender::non_blocking::WorkerGuard>> {
+    use std::io::IsTerminal;
+    use tracing_subscriber::fmt::writer::BoxMakeWriter;
+    use tracing_subscriber::Layer;
+
+    // Never log to stdout: it carries the protocol in stdio mode.
+    let to_terminal = std::io::stderr().is_terminal();
+    let (writer, guard) = if to_terminal {
+        (BoxMakeWriter::new(std::io::stderr), None)
+    } else {
+        let file = logs::RotatingWriter::open(runtime_paths::server_log_path()?, logs::LogRetention::from_env())?;
+        let (writer, guard) = tracing_appender::non_blocking(file);
+        (BoxMakeWriter::new(writer), Some(guard))
+    };
+    let default_format = if to_terminal { logs::LogFormat::Pretty } else { logs::LogFormat::Json };
+    let layer = tracing_subscriber::fmt::layer().with_ansi(to_terminal).with_writer(writer);
+    let layer = match format.unwrap_or(default_format) {
+        logs::LogFormat::Json => layer.json().boxed(),
+        logs::LogFormat::Pretty => layer.boxed(),
+    };
+    tracing_subscriber::registry()
+        .with(LevelFilter::INFO)
+        .with(otel::layer())
+        .with(layer)
+        .init();
+
+    if !to_terminal {
+        // Nobody reads a background server's stderr, so panics go to the log too.
+        let default_hook = std::panic::take_hook();
+        std::panic::set_hook(Box::new(move |info| {
+            tracing::error!("{}", info);
+            default_hook(info);
+        }));
+    }
+    Ok(guard)
+}
+
+// Client commands log nothing, but export their spans when OpenTelemetry is on.
+fn init_client_tracing() {
+    if otel::is_enabled() {
+        tracing_subscriber::registry()
+            .with(LevelFilter::INFO)
+            .with(otel::layer())
+            .init();
+    }
+}
+
+fn main_dispatcher() -> Result<()> {
+    let matches = Cli::command().get_matches();
+    // Telemetry counts commands by name, without their arguments.
+    let command = matches.subcommand_name().unwrap_or("status").to_string();
+    let cli = Cli::from_arg_matches(&matches)?;
+    if let Some(name) = &cli.instance {
+        runtime_paths::set_instance(name)?;
+    }
+    if let Some(dir) = &cli.log_dir {
+        runtime_paths::set_log_dir(dir)?;
+    }
+
+    // Before any threads exist: exporting the config's env entries is not thread-safe.
+    if !matches!(command.as_str(), "secret" | "config" | "completions" | "man") {
+        match AppConfig::load(&KeyringSecrets) {
+            Ok(config) => config.apply_env(),
+            Err(e) => eprintln!("Warning: ignoring config file: {:#}", e),
+        }
+    }
+
+    if let Err(e) = runtime_paths::migrate_legacy_files() {
+        eprintln!("Warning: could not prepare the runtime directory: {}", e);
+    }
+
+    if !matches!(command.as_str(), "telemetry" | "serve") {
+        let telemetry = Telemetry::load_default();
+        if telemetry.is_enabled() {
+            telemetry.record_command(&command);
+            let _ = telemetry.flush();
+        }
+    }
+
+    // Every call a client command makes belongs to the command's trace.
+    let _command_span = matches!(command.as_str(), "list" | "call" | "repl" | "run" | "doctor").then(|| {
+        init_client_tracing();
+        tracing::info_span!("cli", command = %command).entered()
+    });
+
+    match cli.command.unwrap_or(CliCommand::Status(StatusArgs::default())) {
+        CliCommand::Serve(options) => {
+            let _log_guard = init_server_logging(options.log_format)?;
+            // Logged as well as returned, since a background server's stderr goes nowhere.
+            let result = options.validate().and_then(|options| {
+                let runtime = tokio::runtime::Runtime::new()?;
+                runtime.block_on(run_server(options))
+            });
+            if let Err(e) = &result {
+                tracing::error!("Server stopped: {:#}", e);
+            }
+            result
+        }
+        CliCommand::Start(options) => start_server(options),
+        CliCommand::Stop => stop_server(),
+        // Without an instance, `status` reports every instance.
+        CliCommand::Status(args) if cli.instance.is_none() && env::var_os("MCP_INSTANCE").is_none() => show_all_status(args.output),
+        CliCommand::Status(args) => show_status(args.output),
+        CliCommand::List(args) => run_client_command(&args.connect, args.output, None),
+        CliCommand::Call(args) => run_client_command(&args.connect, args.output, Some(&args)),
+        CliCommand::Repl(connect) => repl::run(&connect),
+        CliCommand::Run(args) => pipeline::run(&args),
+        CliCommand::Install(args) => run_install_command(&args, false),
+        CliCommand::Uninstall(args) => run_install_command(&args, true),
+        CliCommand::InstallService(options) => run_service_command(Some(options)),
+        CliCommand::UninstallService => run_service_command(None),
+        CliCommand::Telemetry { action } => run_telemetry_command(action.unwrap_or(TelemetryCommand::Status)),
+        CliCommand::Secret { action } => run_secret_command(action),
+        CliCommand::Config { action } => run_config_command(action.unwrap_or(ConfigCommand::Path)),
+        CliCommand::Copilot { action } => run_copilot_command(action.unwrap_or(CopilotCommand::Status)),
+        CliCommand::Logs(args) => run_logs_command(args),
+        CliCommand::Audit(args) => run_audit_command(args),
+        CliCommand::Doctor(args) => doctor::run(&args),
+        CliCommand::Completions { shell } => {
+            cli::print_completions(shell);
+            Ok(())
+        }
+        CliCommand::Man { dir } => {
+            let written = cli::write_man_pages(&dir)?;
+            println!("Wrote {} man pages to {}", written, dir.display());
+            Ok(())
+        }
+    }
+}
+
+fn main() -> Result<()> {
+    dotenv::dotenv().ok();
+    let result = main_dispatcher();
+    otel::shutdown();
+    result
+}
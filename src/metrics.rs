@@ -0,0 +1,282 @@
+use std::collections::VecDeque;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_INTERVAL_SECS: u64 = 10;
+/// One hour at the default interval.
+const DEFAULT_HISTORY: usize = 360;
+
+const BYTES_PER_KB: u64 = 1024;
+const BYTES_PER_GB: u64 = 1024 * 1024 * 1024;
+
+/// One reading of the machine's load.
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct MetricSample {
+    pub timestamp: DateTime<Utc>,
+    pub cpu_usage_percent: f32,
+    pub memory_used_kb: u64,
+    pub memory_total_kb: u64,
+    pub swap_used_kb: u64,
+    /// Summed over all mounted disks.
+    pub disk_used_gb: u64,
+    pub disk_total_gb: u64,
+    /// Summed over all interfaces, averaged since the previous sample.
+    pub network_received_bytes_per_sec: u64,
+    pub network_transmitted_bytes_per_sec: u64,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetSystemMetricsInput {
+    /// Oldest sample to return: an RFC 3339 time, or an age such as `90s`,
+    /// `15m` or `2h`. Default: the whole history.
+    pub since: Option<String>,
+    /// Seconds per returned point; samples in each window are averaged.
+    /// Default: every sample.
+    pub resolution: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SystemMetricsOutput {
+    /// Seconds between samples; 0 when sampling is disabled.
+    pub interval_secs: u64,
+    pub resolution_secs: u64,
+    pub samples: Vec<MetricSample>,
+}
+
+/// Samples the machine every `interval` and keeps the latest `capacity`
+/// samples, so tools can return trends instead of a single snapshot.
+pub struct MetricsHistory {
+    interval: Duration,
+    capacity: usize,
+    samples: Mutex<VecDeque<MetricSample>>,
+}
+
+impl MetricsHistory {
+    pub fn new(interval: Duration, capacity: usize) -> Self {
+        Self {
+            interval,
+            capacity,
+            samples: Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// `MCP_METRICS_INTERVAL_SECS` (default 10, 0 disables sampling) and
+    /// `MCP_METRICS_HISTORY` samples kept (default 360).
+    pub fn from_env() -> Self {
+        let interval = env::var("MCP_METRICS_INTERVAL_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_INTERVAL_SECS);
+        let capacity = env::var("MCP_METRICS_HISTORY")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&capacity| capacity > 0)
+            .unwrap_or(DEFAULT_HISTORY);
+        Self::new(Duration::from_secs(interval), capacity)
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.interval.is_zero()
+    }
+
+    fn record(&self, sample: MetricSample) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == self.capacity {
+            samples.pop_front();
+        }
+        samples.push_back(sample);
+    }
+
+    /// Takes a sample every interval for the life of the server.
+    pub fn spawn_sampler(self: Arc<Self>) {
+        if !self.is_enabled() {
+            return;
+        }
+        tokio::spawn(async move {
+            let mut probe = Some(Probe::new());
+            let mut interval = tokio::time::interval(self.interval);
+            loop {
+                interval.tick().await;
+                let Some(mut current) = probe.take() else {
+                    return;
+                };
+                // Enumerating disks can stall on unresponsive network filesystems.
+                match tokio::task::spawn_blocking(move || {
+                    let sample = current.sample();
+                    (current, sample)
+                })
+                .await
+                {
+                    Ok((current, sample)) => {
+                        probe = Some(current);
+                        self.record(sample);
+                    }
+                    Err(e) => {
+                        tracing::warn!("Metrics sampler stopped: {}", e);
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    /// The most recent sample, if any has been taken.
+    pub fn latest(&self) -> Option<MetricSample> {
+        self.samples.lock().unwrap().back().cloned()
+    }
+
+    /// Samples since `input.since`, averaged into `input.resolution` windows.
+    pub fn query(&self, input: &GetSystemMetricsInput) -> Result<SystemMetricsOutput, McpToolError> {
+        let since = input.since.as_deref().map(parse_since).transpose()?;
+        let samples: Vec<MetricSample> = self
+            .samples
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|sample| since.is_none_or(|since| sample.timestamp >= since))
+            .cloned()
+            .collect();
+
+        let interval_secs = self.interval.as_secs();
+        let resolution_secs = input.resolution.unwrap_or(interval_secs).max(interval_secs).max(1);
+        let samples = if resolution_secs <= interval_secs {
+            samples
+        } else {
+            downsample(samples, resolution_secs)
+        };
+        Ok(SystemMetricsOutput {
+            interval_secs,
+            resolution_secs,
+            samples,
+        })
+    }
+}
+
+// The sysinfo handles live across samples: CPU usage and network rates are
+// measured against the previous refresh.
+struct Probe {
+    system: sysinfo::System,
+    networks: sysinfo::Networks,
+    disks: sysinfo::Disks,
+    last_network: Option<(Instant, u64, u64)>,
+}
+
+impl Probe {
+    fn new() -> Self {
+        Self {
+            system: sysinfo::System::new(),
+            networks: sysinfo::Networks::new_with_refreshed_list(),
+            disks: sysinfo::Disks::new_with_refreshed_list(),
+            last_network: None,
+        }
+    }
+
+    fn sample(&mut self) -> MetricSample {
+        self.system.refresh_cpu_usage();
+        self.system.refresh_memory();
+        self.disks.refresh(true);
+        self.networks.refresh(true);
+
+        let now = Instant::now();
+        let (received, transmitted) = self
+            .networks
+            .iter()
+            .fold((0, 0), |(rx, tx), (_, data)| (rx + data.total_received(), tx + data.total_transmitted()));
+        let rates = self.last_network.map(|(at, last_rx, last_tx)| {
+            let secs = now.duration_since(at).as_secs_f64().max(f64::EPSILON);
+            (
+                (received.saturating_sub(last_rx) as f64 / secs) as u64,
+                (transmitted.saturating_sub(last_tx) as f64 / secs) as u64,
+            )
+        });
+        self.last_network = Some((now, received, transmitted));
+
+        let (disk_total, disk_available) = self
+            .disks
+            .list()
+            .iter()
+            .fold((0, 0), |(total, available), disk| (total + disk.total_space(), available + disk.available_space()));
+        let (network_received_bytes_per_sec, network_transmitted_bytes_per_sec) = rates.unwrap_or_default();
+        MetricSample {
+            timestamp: Utc::now(),
+            cpu_usage_percent: self.system.global_cpu_usage(),
+            memory_used_kb: self.system.used_memory() / BYTES_PER_KB,
+            memory_total_kb: self.system.total_memory() / BYTES_PER_KB,
+            swap_used_kb: self.system.used_swap() / BYTES_PER_KB,
+            disk_used_gb: disk_total.saturating_sub(disk_available) / BYTES_PER_GB,
+            disk_total_gb: disk_total / BYTES_PER_GB,
+            network_received_bytes_per_sec,
+            network_transmitted_bytes_per_sec,
+        }
+    }
+}
+
+/// Accepts an RFC 3339 time or an age: a number with an `s`, `m`, `h` or `d` suffix.
+pub fn parse_since(since: &str) -> Result<DateTime<Utc>, McpToolError> {
+    if let Ok(time) = DateTime::parse_from_rfc3339(since) {
+        return Ok(time.with_timezone(&Utc));
+    }
+    let invalid = || {
+        McpToolError::new(
+            ErrorCode::InvalidArgument,
+            format!("Invalid since '{}': expected an RFC 3339 time or an age like 15m", since),
+        )
+    };
+    let split = since.find(|c: char| !c.is_ascii_digit()).unwrap_or(since.len());
+    let (number, unit) = since.split_at(split);
+    let number: i64 = number.parse().map_err(|_| invalid())?;
+    let age = match unit {
+        "" | "s" => chrono::Duration::seconds(number),
+        "m" => chrono::Duration::minutes(number),
+        "h" => chrono::Duration::hours(number),
+        "d" => chrono::Duration::days(number),
+        _ => return Err(invalid()),
+    };
+    Ok(Utc::now() - age)
+}
+
+// Averages the samples in each `resolution_secs` window; each point is
+// stamped with the start of its window.
+fn downsample(samples: Vec<MetricSample>, resolution_secs: u64) -> Vec<MetricSample> {
+    let resolution = resolution_secs as i64;
+    let mut points = Vec::new();
+    let mut window: Vec<MetricSample> = Vec::new();
+    let mut window_start = None;
+    for sample in samples {
+        let start = sample.timestamp.timestamp().div_euclid(resolution) * resolution;
+        if window_start.is_some_and(|current| current != start) {
+            points.extend(average(&window, window_start));
+            window.clear();
+        }
+        window_start = Some(start);
+        window.push(sample);
+    }
+    points.extend(average(&window, window_start));
+    points
+}
+
+fn average(window: &[MetricSample], start: Option<i64>) -> Option<MetricSample> {
+    let timestamp = DateTime::from_timestamp(start?, 0)?;
+    let count = window.len();
+    if count == 0 {
+        return None;
+    }
+    let mean = |value: fn(&MetricSample) -> u64| window.iter().map(value).sum::<u64>() / count as u64;
+    Some(MetricSample {
+        timestamp,
+        cpu_usage_percent: window.iter().map(|sample| sample.cpu_usage_percent).sum::<f32>() / count as f32,
+        memory_used_kb: mean(|sample| sample.memory_used_kb),
+        memory_total_kb: mean(|sample| sample.memory_total_kb),
+        swap_used_kb: mean(|sample| sample.swap_used_kb),
+        disk_used_gb: mean(|sample| sample.disk_used_gb),
+        disk_total_gb: mean(|sample| sample.disk_total_gb),
+        network_received_bytes_per_sec: mean(|sample| sample.network_received_bytes_per_sec),
+        network_transmitted_bytes_per_sec: mean(|sample| sample.network_transmitted_bytes_per_sec),
+    })
+}
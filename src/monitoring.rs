@@ -0,0 +1,138 @@
+//! Prometheus metrics for `serve --metrics <addr>`: tool calls, errors and
+//! durations, open connections, and LLM token usage, served as text at
+//! `GET /metrics` for Prometheus (and so Grafana) to scrape.
+//!
+//! The counters are always kept; they cost a few atomic adds per call. Only
+//! the endpoint is optional. It has no authentication, so bind it to an
+//! address only trusted scrapers can reach.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::Result;
+use axum::extract::State;
+use axum::http::{header, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::Router;
+use prometheus::{Encoder, HistogramOpts, HistogramVec, IntCounterVec, IntGaugeVec, Opts, Registry, TextEncoder};
+
+use crate::copilot::TokenUsage;
+
+/// Tool calls range from microseconds (`echo_message`) to minutes (LLM calls,
+/// long commands).
+const DURATION_BUCKETS: &[f64] = &[0.005, 0.025, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 300.0];
+
+pub struct Monitoring {
+    registry: Registry,
+    tool_calls: IntCounterVec,
+    tool_errors: IntCounterVec,
+    tool_duration: HistogramVec,
+    connections: IntGaugeVec,
+    llm_tokens: IntCounterVec,
+}
+
+impl Monitoring {
+    pub fn new() -> Self {
+        let registry = Registry::new_custom(Some("mcp".to_string()), None).expect("the prefix is valid");
+        let tool_calls = IntCounterVec::new(Opts::new("tool_calls_total", "Tool calls, by tool."), &["tool"])
+            .expect("metric options are valid");
+        let tool_errors = IntCounterVec::new(
+            Opts::new("tool_errors_total", "Tool calls that failed, by tool and error code."),
+            &["tool", "code"],
+        )
+        .expect("metric options are valid");
+        let tool_duration = HistogramVec::new(
+            HistogramOpts::new("tool_call_duration_seconds", "How long tool calls took, by tool.")
+                .buckets(DURATION_BUCKETS.to_vec()),
+            &["tool"],
+        )
+        .expect("metric options are valid");
+        let connections = IntGaugeVec::new(
+            Opts::new("active_connections", "Open client connections, by transport."),
+            &["transport"],
+        )
+        .expect("metric options are valid");
+        let llm_tokens = IntCounterVec::new(
+            Opts::new("llm_tokens_total", "LLM tokens used by chat_completion, by driver and kind (prompt or completion)."),
+            &["driver", "kind"],
+        )
+        .expect("metric options are valid");
+        for collector in [
+            Box::new(tool_calls.clone()) as Box<dyn prometheus::core::Collector>,
+            Box::new(tool_errors.clone()),
+            Box::new(tool_duration.clone()),
+            Box::new(connections.clone()),
+            Box::new(llm_tokens.clone()),
+        ] {
+            registry.register(collector).expect("metric names are unique");
+        }
+        Self {
+            registry,
+            tool_calls,
+            tool_errors,
+            tool_duration,
+            connections,
+            llm_tokens,
+        }
+    }
+
+    /// Records a finished call; `error_code` is set when it failed.
+    pub fn record_tool_call(&self, tool: &str, duration: Duration, error_code: Option<&str>) {
+        self.tool_calls.with_label_values(&[tool]).inc();
+        self.tool_duration.with_label_values(&[tool]).observe(duration.as_secs_f64());
+        if let Some(code) = error_code {
+            self.tool_errors.with_label_values(&[tool, code]).inc();
+        }
+    }
+
+    /// Counts a connection as open until the returned guard is dropped.
+    pub fn track_connection(&self, transport: &str) -> ConnectionGuard {
+        let gauge = self.connections.with_label_values(&[transport]);
+        gauge.inc();
+        ConnectionGuard(gauge)
+    }
+
+    pub fn record_llm_tokens(&self, driver: &str, usage: &TokenUsage) {
+        self.llm_tokens.with_label_values(&[driver, "prompt"]).inc_by(usage.prompt_tokens);
+        self.llm_tokens.with_label_values(&[driver, "completion"]).inc_by(usage.completion_tokens);
+    }
+
+    /// Every metric in the Prometheus text format.
+    pub fn render(&self) -> Result<String> {
+        let mut buffer = Vec::new();
+        TextEncoder::new().encode(&self.registry.gather(), &mut buffer)?;
+        Ok(String::from_utf8(buffer)?)
+    }
+}
+
+impl Default for Monitoring {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub struct ConnectionGuard(prometheus::IntGauge);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.dec();
+    }
+}
+
+async fn metrics(State(monitoring): State<Arc<Monitoring>>) -> impl IntoResponse {
+    match monitoring.render() {
+        Ok(text) => (StatusCode::OK, [(header::CONTENT_TYPE, prometheus::TEXT_FORMAT)], text).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+/// Serves `GET /metrics` on `addr` until the process exits.
+pub async fn serve(addr: SocketAddr, monitoring: Arc<Monitoring>) -> Result<()> {
+    let app = Router::new().route("/metrics", get(metrics)).with_state(monitoring);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    tracing::info!("Prometheus metrics at http://{}/metrics", listener.local_addr()?);
+    axum::serve(listener, app).await?;
+    Ok(())
+}
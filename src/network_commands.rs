@@ -0,0 +1,292 @@
+//! Network diagnostics run from the machine hosting the server: TCP ping,
+//! DNS lookups and HTTP probes.
+//!
+//! `ping_host` times TCP handshakes rather than sending ICMP echoes, which
+//! would need raw sockets and elevated privileges.
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::CallToolResult;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+const DEFAULT_PING_PORT: u16 = 443;
+const DEFAULT_PING_COUNT: u32 = 4;
+const MAX_PING_COUNT: u32 = 20;
+const PING_GAP: Duration = Duration::from_millis(200);
+const DEFAULT_TIMEOUT_MS: u64 = 5000;
+const MAX_TIMEOUT_MS: u64 = 60_000;
+const MAX_REDIRECTS: usize = 10;
+
+// --- Input/Output Structs for NetworkCommand Trait ---
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct PingHostInput {
+    /// Host name or IP address.
+    pub host: String,
+    /// TCP port to connect to (default 443).
+    pub port: Option<u16>,
+    /// Connection attempts (default 4, at most 20).
+    pub count: Option<u32>,
+    /// Per-attempt timeout in milliseconds (default 5000).
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct PingAttempt {
+    pub seq: u32,
+    /// Handshake time, or None when the attempt failed.
+    pub time_ms: Option<f64>,
+    pub error: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct PingHostOutput {
+    pub host: String,
+    pub address: String,
+    pub port: u16,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_percent: f64,
+    pub min_ms: Option<f64>,
+    pub avg_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    pub attempts: Vec<PingAttempt>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ResolveDnsInput {
+    pub host: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ResolveDnsOutput {
+    pub host: String,
+    pub ipv4: Vec<String>,
+    pub ipv6: Vec<String>,
+    pub duration_ms: f64,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct HttpProbeInput {
+    /// `http://` or `https://` URL.
+    pub url: String,
+    /// `GET` (default) or `HEAD`.
+    pub method: Option<String>,
+    /// Whole-request timeout in milliseconds (default 5000).
+    pub timeout_ms: Option<u64>,
+    /// Follow up to 10 redirects (default true).
+    pub follow_redirects: Option<bool>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct HttpProbeOutput {
+    pub url: String,
+    /// Where the request ended up after redirects.
+    pub final_url: String,
+    pub status: u16,
+    pub success: bool,
+    /// Time until the response headers arrived.
+    pub duration_ms: f64,
+    pub content_type: Option<String>,
+    pub content_length: Option<u64>,
+    pub server: Option<String>,
+}
+
+// --- NetworkCommand Trait Definition ---
+
+#[async_trait]
+pub trait NetworkCommand: Send + Sync + 'static {
+    // Time TCP handshakes to a host
+    async fn ping_host(&self, input: PingHostInput) -> CallToolResult;
+
+    // Resolve a host name with the system resolver
+    async fn resolve_dns(&self, input: ResolveDnsInput) -> CallToolResult;
+
+    // Request a URL and report status and latency
+    async fn http_probe(&self, input: HttpProbeInput) -> CallToolResult;
+}
+
+// --- LibNetworkCommand Implementation (using tokio, reqwest) ---
+pub struct LibNetworkCommand;
+
+fn timeout_of(timeout_ms: Option<u64>) -> Duration {
+    Duration::from_millis(timeout_ms.unwrap_or(DEFAULT_TIMEOUT_MS).clamp(1, MAX_TIMEOUT_MS))
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+async fn lookup(host: &str) -> Result<Vec<IpAddr>, McpToolError> {
+    // Brackets are allowed around IPv6 literals.
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    if let Ok(address) = host.parse::<IpAddr>() {
+        return Ok(vec![address]);
+    }
+    let addresses: Vec<IpAddr> = tokio::net::lookup_host((host, 0))
+        .await
+        .map_err(|e| McpToolError::new(ErrorCode::DnsResolutionFailed, format!("Cannot resolve {}: {}", host, e)))?
+        .map(|address| address.ip())
+        .collect();
+    if addresses.is_empty() {
+        return Err(McpToolError::new(
+            ErrorCode::DnsResolutionFailed,
+            format!("{} has no addresses", host),
+        ));
+    }
+    Ok(addresses)
+}
+
+impl LibNetworkCommand {
+    async fn ping(input: PingHostInput) -> Result<PingHostOutput, McpToolError> {
+        let address = lookup(&input.host).await?[0];
+        let port = input.port.unwrap_or(DEFAULT_PING_PORT);
+        let target = SocketAddr::new(address, port);
+        let count = input.count.unwrap_or(DEFAULT_PING_COUNT).clamp(1, MAX_PING_COUNT);
+        let timeout = timeout_of(input.timeout_ms);
+
+        let mut attempts = Vec::with_capacity(count as usize);
+        for seq in 1..=count {
+            if seq > 1 {
+                tokio::time::sleep(PING_GAP).await;
+            }
+            let started = Instant::now();
+            let attempt = match tokio::time::timeout(timeout, tokio::net::TcpStream::connect(target)).await {
+                Ok(Ok(_)) => PingAttempt {
+                    seq,
+                    time_ms: Some(millis(started.elapsed())),
+                    error: None,
+                },
+                Ok(Err(e)) => PingAttempt {
+                    seq,
+                    time_ms: None,
+                    error: Some(e.to_string()),
+                },
+                Err(_) => PingAttempt {
+                    seq,
+                    time_ms: None,
+                    error: Some(format!("timed out after {} ms", timeout.as_millis())),
+                },
+            };
+            attempts.push(attempt);
+        }
+
+        let times: Vec<f64> = attempts.iter().filter_map(|attempt| attempt.time_ms).collect();
+        let received = times.len() as u32;
+        Ok(PingHostOutput {
+            host: input.host,
+            address: address.to_string(),
+            port,
+            sent: count,
+            received,
+            loss_percent: f64::from(count - received) * 100.0 / f64::from(count),
+            min_ms: times.iter().copied().reduce(f64::min),
+            avg_ms: (!times.is_empty()).then(|| times.iter().sum::<f64>() / times.len() as f64),
+            max_ms: times.iter().copied().reduce(f64::max),
+            attempts,
+        })
+    }
+
+    async fn resolve(input: ResolveDnsInput) -> Result<ResolveDnsOutput, McpToolError> {
+        let started = Instant::now();
+        let addresses = lookup(&input.host).await?;
+        let duration_ms = millis(started.elapsed());
+        let mut ipv4: Vec<String> = Vec::new();
+        let mut ipv6: Vec<String> = Vec::new();
+        for address in addresses {
+            let list = if address.is_ipv4() { &mut ipv4 } else { &mut ipv6 };
+            let address = address.to_string();
+            if !list.contains(&address) {
+                list.push(address);
+            }
+        }
+        Ok(ResolveDnsOutput {
+            host: input.host,
+            ipv4,
+            ipv6,
+            duration_ms,
+        })
+    }
+
+    async fn probe(input: HttpProbeInput) -> Result<HttpProbeOutput, McpToolError> {
+        let invalid = |reason: String| McpToolError::new(ErrorCode::InvalidArgument, reason);
+        let url = url::Url::parse(&input.url).map_err(|e| invalid(format!("Invalid URL '{}': {}", input.url, e)))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid(format!("Only http and https URLs can be probed, got '{}'", url.scheme())));
+        }
+        let method = match input.method.as_deref().map(str::to_ascii_uppercase).as_deref() {
+            None | Some("GET") => reqwest::Method::GET,
+            Some("HEAD") => reqwest::Method::HEAD,
+            Some(other) => return Err(invalid(format!("Unsupported method '{}'. Expected GET or HEAD", other))),
+        };
+        let redirects = if input.follow_redirects.unwrap_or(true) {
+            reqwest::redirect::Policy::limited(MAX_REDIRECTS)
+        } else {
+            reqwest::redirect::Policy::none()
+        };
+        let timeout = timeout_of(input.timeout_ms);
+        let client = reqwest::Client::builder()
+            .redirect(redirects)
+            .timeout(timeout)
+            .build()
+            .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Cannot build HTTP client: {}", e)))?;
+
+        let started = Instant::now();
+        let response = client.request(method, url.clone()).send().await.map_err(|e| {
+            let code = if e.is_timeout() {
+                ErrorCode::Timeout
+            } else {
+                ErrorCode::HostUnreachable
+            };
+            McpToolError::new(code, format!("Request to {} failed: {}", url, e))
+        })?;
+        let duration_ms = millis(started.elapsed());
+
+        let header = |name: reqwest::header::HeaderName| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string)
+        };
+        Ok(HttpProbeOutput {
+            url: input.url,
+            final_url: response.url().to_string(),
+            status: response.status().as_u16(),
+            success: response.status().is_success(),
+            duration_ms,
+            content_type: header(reqwest::header::CONTENT_TYPE),
+            content_length: response.content_length(),
+            server: header(reqwest::header::SERVER),
+        })
+    }
+}
+
+fn structured_output<T: Serialize>(output: Result<T, McpToolError>) -> CallToolResult {
+    match output.and_then(|output| {
+        serde_json::to_value(output)
+            .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Failed to serialize output: {}", e)))
+    }) {
+        Ok(value) => CallToolResult::structured(value),
+        Err(e) => e.into_call_tool_result(),
+    }
+}
+
+#[async_trait]
+impl NetworkCommand for LibNetworkCommand {
+    async fn ping_host(&self, input: PingHostInput) -> CallToolResult {
+        structured_output(Self::ping(input).await)
+    }
+
+    async fn resolve_dns(&self, input: ResolveDnsInput) -> CallToolResult {
+        structured_output(Self::resolve(input).await)
+    }
+
+    async fn http_probe(&self, input: HttpProbeInput) -> CallToolResult {
+        structured_output(Self::probe(input).await)
+    }
+}
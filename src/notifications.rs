@@ -0,0 +1,127 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use rmcp::model::{LoggingLevel, LoggingMessageNotificationParam};
+use rmcp::service::{Peer, RoleServer};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+/// Capacity of the broadcast channel; slow clients skip the oldest events.
+const EVENT_CHANNEL_CAPACITY: usize = 1024;
+
+/// An event published by a server subsystem.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerEvent {
+    /// Dotted topic, e.g. `process.exited`, `obs.stream_started`, `oauth.refresh_failed`.
+    pub topic: String,
+    pub payload: Value,
+    /// Session tokens to deliver to; `None` broadcasts to every subscribed client.
+    #[serde(skip)]
+    pub targets: Option<Vec<String>>,
+}
+
+/// Pub/sub hub fanning server events out to connected clients as MCP notifications.
+pub struct NotificationHub {
+    sender: broadcast::Sender<ServerEvent>,
+}
+
+impl NotificationHub {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { sender }
+    }
+
+    /// Publishes an event to every client subscribed to its topic.
+    pub fn publish(&self, topic: impl Into<String>, payload: Value) {
+        self.send(ServerEvent {
+            topic: topic.into(),
+            payload,
+            targets: None,
+        });
+    }
+
+    /// Publishes an event only to the given sessions.
+    pub fn publish_to(&self, targets: Vec<String>, topic: impl Into<String>, payload: Value) {
+        self.send(ServerEvent {
+            topic: topic.into(),
+            payload,
+            targets: Some(targets),
+        });
+    }
+
+    fn send(&self, event: ServerEvent) {
+        // An error only means nobody is listening right now.
+        let _ = self.sender.send(event);
+    }
+
+    /// Forwards matching events to `peer` until the peer disconnects.
+    pub fn spawn_forwarder(&self, peer: Peer<RoleServer>, session_token: String, filter: Arc<SubscriptionFilter>) {
+        let mut receiver = self.sender.subscribe();
+        tokio::spawn(async move {
+            loop {
+                let event = match receiver.recv().await {
+                    Ok(event) => event,
+                    Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                        tracing::warn!("Session {} lagged, skipped {} events", session_token, skipped);
+                        continue;
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                if let Some(targets) = &event.targets {
+                    if !targets.iter().any(|target| target == &session_token) {
+                        continue;
+                    }
+                }
+                if !filter.matches(&event.topic) {
+                    continue;
+                }
+                let notification = LoggingMessageNotificationParam {
+                    level: LoggingLevel::Info,
+                    logger: Some(event.topic.clone()),
+                    data: event.payload.clone(),
+                };
+                if peer.notify_logging_message(notification).await.is_err() {
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Per-client topic filter. Patterns are exact topics or prefixes ending in `*`.
+/// An empty filter matches every topic.
+#[derive(Debug, Default)]
+pub struct SubscriptionFilter {
+    patterns: Mutex<HashSet<String>>,
+}
+
+impl SubscriptionFilter {
+    pub fn subscribe(&self, patterns: &[String]) {
+        self.patterns.lock().unwrap().extend(patterns.iter().cloned());
+    }
+
+    pub fn unsubscribe(&self, patterns: &[String]) {
+        let mut current = self.patterns.lock().unwrap();
+        for pattern in patterns {
+            current.remove(pattern);
+        }
+    }
+
+    pub fn patterns(&self) -> Vec<String> {
+        let mut patterns: Vec<String> = self.patterns.lock().unwrap().iter().cloned().collect();
+        patterns.sort();
+        patterns
+    }
+
+    pub fn matches(&self, topic: &str) -> bool {
+        let patterns = self.patterns.lock().unwrap();
+        if patterns.is_empty() {
+            return true;
+        }
+        patterns.iter().any(|pattern| match pattern.strip_suffix('*') {
+            Some(prefix) => topic.starts_with(prefix),
+            None => pattern == topic,
+        })
+    }
+}
@@ -0,0 +1,261 @@
+//! MCP tools over the OAuth token store, so agents and the CLI can manage
+//! credentials through the server instead of the plugin's HTTP routes:
+//! `oauth_list_providers`, `oauth_get_token_status`, `oauth_revoke` and
+//! `oauth_start_login`.
+//!
+//! Tokens themselves are never returned, only whether they exist and when
+//! they expire.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use mcp_errors::{ErrorCode, McpToolError};
+use mcp_oauth_plugin::oauth::{DevicePoll, OAuthClient, OAuthConfig};
+use mcp_oauth_plugin::providers::{self, ProviderRegistry};
+use mcp_oauth_plugin::token_store::TokenStore;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::notifications::NotificationHub;
+use crate::registry::{schema_for, ToolRegistry};
+use crate::EchoServerTool;
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Default)]
+pub struct OAuthListProvidersInput {}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthProviderInput {
+    /// Provider ID from `oauth_list_providers`, e.g. `github-copilot`.
+    pub provider: String,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthProvider {
+    pub id: String,
+    pub name: String,
+    /// Signs in through a browser redirect.
+    pub authorization_code: bool,
+    /// Signs in with a device code; `oauth_start_login` works.
+    pub device_code: bool,
+    pub signed_in: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthProvidersOutput {
+    pub providers: Vec<OAuthProvider>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthTokenStatus {
+    pub provider: String,
+    pub signed_in: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expired: bool,
+    /// Expires within five minutes.
+    pub needs_refresh: bool,
+    pub has_refresh_token: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthRevokeOutput {
+    pub provider: String,
+    /// False when no token was stored.
+    pub removed: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct OAuthLoginOutput {
+    pub provider: String,
+    /// Code the user enters at `verification_uri`.
+    pub user_code: String,
+    pub verification_uri: String,
+    pub verification_uri_complete: Option<String>,
+    pub expires_in_secs: u64,
+}
+
+/// The token store and provider configs, loaded on first use.
+#[derive(Default)]
+pub struct OAuthAccounts {
+    store: tokio::sync::OnceCell<TokenStore>,
+    providers: std::sync::OnceLock<ProviderRegistry>,
+}
+
+impl OAuthAccounts {
+    async fn store(&self) -> Result<&TokenStore, McpToolError> {
+        self.store
+            .get_or_try_init(|| async {
+                let store = TokenStore::default()
+                    .await
+                    .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Cannot open the token store: {:#}", e)))?;
+                store.spawn_csrf_cleanup();
+                Ok(store)
+            })
+            .await
+    }
+
+    fn providers(&self) -> &ProviderRegistry {
+        self.providers.get_or_init(ProviderRegistry::with_presets)
+    }
+
+    fn config(&self, provider: &str) -> Result<OAuthConfig, McpToolError> {
+        self.providers().get(provider).cloned().ok_or_else(|| {
+            McpToolError::new(ErrorCode::OAuthProviderNotFound, format!("Unknown OAuth provider '{}'", provider))
+        })
+    }
+
+    pub async fn list_providers(&self) -> Result<OAuthProvidersOutput, McpToolError> {
+        let tokens = self.store().await?.all().await;
+        let providers = providers::describe(self.providers().configs(), |id| tokens.contains_key(id));
+        Ok(OAuthProvidersOutput {
+            providers: providers
+                .into_iter()
+                .map(|provider| OAuthProvider {
+                    id: provider.id,
+                    name: provider.name,
+                    authorization_code: provider.authorization_code,
+                    device_code: provider.device_code,
+                    signed_in: provider.signed_in,
+                })
+                .collect(),
+        })
+    }
+
+    pub async fn token_status(&self, provider: &str) -> Result<OAuthTokenStatus, McpToolError> {
+        let token = self.store().await?.get(provider).await;
+        Ok(OAuthTokenStatus {
+            provider: provider.to_string(),
+            signed_in: token.is_some(),
+            expires_at: token.as_ref().map(|token| token.expires_at),
+            expired: token.as_ref().is_some_and(|token| token.is_expired()),
+            needs_refresh: token.as_ref().is_some_and(|token| token.needs_refresh()),
+            has_refresh_token: token.as_ref().is_some_and(|token| !token.refresh_token.is_empty()),
+        })
+    }
+
+    /// Deletes the stored token. The provider is not told; revoke the grant
+    /// in the provider's settings to invalidate copies elsewhere.
+    pub async fn revoke(&self, provider: &str) -> Result<OAuthRevokeOutput, McpToolError> {
+        let store = self.store().await?;
+        let removed = store.get(provider).await.is_some();
+        store
+            .remove(provider)
+            .await
+            .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("{:#}", e)))?;
+        Ok(OAuthRevokeOutput {
+            provider: provider.to_string(),
+            removed,
+        })
+    }
+
+    /// Starts a device-code sign-in and waits for approval in the background.
+    /// `oauth.login_completed` or `oauth.login_failed` is published when it ends.
+    pub async fn start_login(&self, provider: &str, events: Arc<NotificationHub>) -> Result<OAuthLoginOutput, McpToolError> {
+        let config = self.config(provider)?;
+        if config.device_auth_url.is_none() {
+            return Err(McpToolError::new(
+                ErrorCode::NotImplemented,
+                format!("'{}' has no device sign-in; sign in through the OAuth web pages", provider),
+            ));
+        }
+        let client = OAuthClient::new(config, self.store().await?.clone());
+        let code = client.start_device_authorization().await.map_err(into_tool_error)?;
+
+        let output = OAuthLoginOutput {
+            provider: provider.to_string(),
+            user_code: code.user_code.clone(),
+            verification_uri: code.verification_uri.clone(),
+            verification_uri_complete: code.verification_uri_complete.clone(),
+            expires_in_secs: code.expires_in,
+        };
+        let provider = provider.to_string();
+        tokio::spawn(async move {
+            let deadline = tokio::time::Instant::now() + Duration::from_secs(code.expires_in);
+            let mut interval = Duration::from_secs(code.interval.max(1));
+            let result = loop {
+                if tokio::time::Instant::now() >= deadline {
+                    break Err(McpToolError::new(ErrorCode::OAuthExchangeFailed, "The device code expired before it was approved"));
+                }
+                tokio::time::sleep(interval).await;
+                match client.poll_device_authorization(&provider, &code.device_code).await {
+                    Ok(DevicePoll::Pending) => {}
+                    Ok(DevicePoll::SlowDown { interval: slower }) => interval = Duration::from_secs(slower),
+                    Ok(DevicePoll::Complete(token)) => break Ok(token),
+                    Err(e) => break Err(into_tool_error(e)),
+                }
+            };
+            match result {
+                Ok(token) => {
+                    tracing::info!("OAuth sign-in to {} completed", provider);
+                    events.publish(
+                        "oauth.login_completed",
+                        json!({ "provider": provider, "expires_at": token.expires_at }),
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!("OAuth sign-in to {} failed: {}", provider, e);
+                    events.publish("oauth.login_failed", json!({ "provider": provider, "error": e.to_json() }));
+                }
+            }
+        });
+        Ok(output)
+    }
+}
+
+fn into_tool_error(e: anyhow::Error) -> McpToolError {
+    e.downcast::<McpToolError>()
+        .unwrap_or_else(|e| McpToolError::new(ErrorCode::OAuthExchangeFailed, format!("{:#}", e)))
+}
+
+pub fn register_tools(tools: &mut ToolRegistry<EchoServerTool>) {
+    tools
+        .register(
+            "oauth_list_providers",
+            "Lists the OAuth providers the server can sign in to and whether a token is stored for each.",
+            |server: EchoServerTool, _: OAuthListProvidersInput| async move {
+                match server.state.oauth.list_providers().await {
+                    Ok(output) => crate::structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<OAuthProvidersOutput>());
+    tools
+        .register(
+            "oauth_get_token_status",
+            "Reports whether a provider's token is stored and when it expires. Never returns the token.",
+            |server: EchoServerTool, input: OAuthProviderInput| async move {
+                match server.state.oauth.token_status(&input.provider).await {
+                    Ok(output) => crate::structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<OAuthTokenStatus>());
+    let revoke = tools.register(
+        "oauth_revoke",
+        "Deletes the stored token for a provider, signing the server out of it.",
+        |server: EchoServerTool, input: OAuthProviderInput| async move {
+            match server.state.oauth.revoke(&input.provider).await {
+                Ok(output) => crate::structured(output),
+                Err(e) => Ok(e.into_call_tool_result()),
+            }
+        },
+    );
+    revoke.output_schema = Some(schema_for::<OAuthRevokeOutput>());
+    crate::policy::mark_dangerous(revoke);
+    tools
+        .register(
+            "oauth_start_login",
+            "Starts a device-code sign-in: returns a code for the user to enter at a URL. \
+             Publishes oauth.login_completed or oauth.login_failed when it ends.",
+            |server: EchoServerTool, input: OAuthProviderInput| async move {
+                match server.state.oauth.start_login(&input.provider, server.state.events.clone()).await {
+                    Ok(output) => crate::structured(output),
+                    Err(e) => Ok(e.into_call_tool_result()),
+                }
+            },
+        )
+        .output_schema = Some(schema_for::<OAuthLoginOutput>());
+}
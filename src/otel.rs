@@ -0,0 +1,135 @@
+//! OpenTelemetry tracing: `tracing` spans exported over OTLP, and the trace
+//! context carried from the CLI to the server in a request's `_meta`.
+//!
+//! Export is on when `OTEL_EXPORTER_OTLP_ENDPOINT` (or
+//! `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is set, and uses OTLP over HTTP.
+//! Spans are tagged with `OTEL_SERVICE_NAME`, default `copilot_mcp_tool`.
+//! The client puts the W3C `traceparent` and `tracestate` of its call span in
+//! `_meta`, and the server's `call_tool` span continues that trace, so one
+//! trace covers the CLI, the server, the tool and the LLM backend.
+//!
+//! The batch exporter runs on a small runtime of its own, so any command can
+//! turn tracing on before (or without) starting one.
+
+use std::env;
+use std::sync::OnceLock;
+
+use opentelemetry::propagation::{Extractor, Injector};
+use opentelemetry::trace::TracerProvider as _;
+use opentelemetry::KeyValue;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{Tracer, TracerProvider};
+use opentelemetry_sdk::Resource;
+use serde_json::{Map, Value};
+use tracing::Subscriber;
+use tracing_opentelemetry::{OpenTelemetryLayer, OpenTelemetrySpanExt};
+use tracing_subscriber::registry::LookupSpan;
+
+const DEFAULT_SERVICE_NAME: &str = "copilot_mcp_tool";
+
+struct Exporter {
+    provider: TracerProvider,
+    // Keeps the batch exporter's task alive.
+    _runtime: tokio::runtime::Runtime,
+}
+
+static EXPORTER: OnceLock<Exporter> = OnceLock::new();
+
+pub fn is_enabled() -> bool {
+    ["OTEL_EXPORTER_OTLP_ENDPOINT", "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT"]
+        .iter()
+        .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+fn start() -> anyhow::Result<Exporter> {
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("otel-export")
+        .enable_all()
+        .build()?;
+    let service_name = env::var("OTEL_SERVICE_NAME").unwrap_or_else(|_| DEFAULT_SERVICE_NAME.to_string());
+    let provider = {
+        // The exporter and batch processor spawn their tasks on the current runtime.
+        let _entered = runtime.enter();
+        let exporter = opentelemetry_otlp::SpanExporter::builder().with_http().build()?;
+        TracerProvider::builder()
+            .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+            .with_resource(Resource::new([
+                KeyValue::new("service.name", service_name),
+                KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+            ]))
+            .build()
+    };
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+    Ok(Exporter {
+        provider,
+        _runtime: runtime,
+    })
+}
+
+/// The layer that sends spans to the OTLP endpoint, or `None` when export is
+/// off or the exporter can't be set up.
+pub fn layer<S>() -> Option<OpenTelemetryLayer<S, Tracer>>
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    if !is_enabled() {
+        return None;
+    }
+    if EXPORTER.get().is_none() {
+        match start() {
+            Ok(exporter) => {
+                let _ = EXPORTER.set(exporter);
+            }
+            Err(e) => {
+                eprintln!("Warning: OpenTelemetry export disabled: {:#}", e);
+                return None;
+            }
+        }
+    }
+    let tracer = EXPORTER.get()?.provider.tracer(DEFAULT_SERVICE_NAME);
+    Some(tracing_opentelemetry::layer().with_tracer(tracer))
+}
+
+/// Sends the spans still buffered. Call once, outside any async runtime,
+/// before the process exits.
+pub fn shutdown() {
+    if let Some(exporter) = EXPORTER.get() {
+        if let Err(e) = exporter.provider.shutdown() {
+            eprintln!("Warning: failed to export the last spans: {}", e);
+        }
+    }
+}
+
+struct MetaInjector<'a>(&'a mut Map<String, Value>);
+
+impl Injector for MetaInjector<'_> {
+    fn set(&mut self, key: &str, value: String) {
+        self.0.insert(key.to_string(), Value::String(value));
+    }
+}
+
+struct MetaExtractor<'a>(&'a Map<String, Value>);
+
+impl Extractor for MetaExtractor<'_> {
+    fn get(&self, key: &str) -> Option<&str> {
+        self.0.get(key).and_then(Value::as_str)
+    }
+
+    fn keys(&self) -> Vec<&str> {
+        self.0.keys().map(String::as_str).collect()
+    }
+}
+
+/// Adds the current span's trace context to a request's `_meta`. Does
+/// nothing while export is off.
+pub fn inject(meta: &mut Map<String, Value>) {
+    let context = tracing::Span::current().context();
+    opentelemetry::global::get_text_map_propagator(|propagator| propagator.inject_context(&context, &mut MetaInjector(meta)));
+}
+
+/// Makes `span` a child of the trace context in a request's `_meta`, if any.
+pub fn continue_trace(span: &tracing::Span, meta: &Map<String, Value>) {
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| propagator.extract(&MetaExtractor(meta)));
+    span.set_parent(parent);
+}
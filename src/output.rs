@@ -0,0 +1,147 @@
+//! `--output json|yaml|table` for the commands scripts read: `list`, `call`
+//! and `status`.
+//!
+//! `table` lays out arrays of objects as aligned columns of their scalar
+//! fields, and an object's own scalar fields as key/value rows. Nested values
+//! that don't fit a column are shown as compact JSON.
+
+use std::fmt::Write as _;
+
+use anyhow::Result;
+use serde_json::{Map, Value};
+
+/// Widest a table cell gets before it is cut short.
+const MAX_CELL_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+    #[default]
+    Json,
+    Yaml,
+    Table,
+}
+
+impl OutputFormat {
+    pub fn render(self, value: &Value) -> Result<String> {
+        Ok(match self {
+            OutputFormat::Json => serde_json::to_string_pretty(value)?,
+            OutputFormat::Yaml => serde_yaml::to_string(value)?.trim_end().to_string(),
+            OutputFormat::Table => render_table(value),
+        })
+    }
+
+    pub fn print(self, value: &Value) -> Result<()> {
+        println!("{}", self.render(value)?);
+        Ok(())
+    }
+
+    /// Prints a `tools/call` result. Tables show the tool's structured
+    /// output, or its text for tools without one.
+    pub fn print_tool_result(self, result: &Value) -> Result<()> {
+        if self != OutputFormat::Table {
+            return self.print(result);
+        }
+        if !result["structuredContent"].is_null() {
+            return self.print(&result["structuredContent"]);
+        }
+        for item in result["content"].as_array().into_iter().flatten() {
+            match item["text"].as_str() {
+                Some(text) => println!("{}", text),
+                None => println!("{}", render_table(item)),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_scalar(value: &Value) -> bool {
+    !matches!(value, Value::Array(_) | Value::Object(_))
+}
+
+fn is_table(value: &Value) -> bool {
+    value.as_array().is_some_and(|rows| !rows.is_empty() && rows.iter().all(Value::is_object))
+}
+
+fn cell(value: &Value) -> String {
+    let text = match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    };
+    let text = text.replace(['\n', '\r', '\t'], " ");
+    match text.char_indices().nth(MAX_CELL_WIDTH - 1) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+fn write_rows(out: &mut String, rows: &[Vec<String>]) {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| rows.iter().filter_map(|row| row.get(column)).map(|text| text.chars().count()).max().unwrap_or_default())
+        .collect();
+    for row in rows {
+        let line: Vec<String> = row
+            .iter()
+            .zip(&widths)
+            .map(|(text, width)| format!("{:width$}", text, width = width))
+            .collect();
+        let _ = writeln!(out, "{}", line.join("  ").trim_end());
+    }
+}
+
+// Columns are the fields that are scalar in every row that has them, in
+// first-seen order.
+fn write_table(out: &mut String, rows: &[Value]) {
+    let mut columns: Vec<&str> = Vec::new();
+    for row in rows.iter().filter_map(Value::as_object) {
+        for (key, value) in row {
+            if !columns.contains(&key.as_str()) && is_scalar(value) {
+                columns.push(key);
+            }
+        }
+    }
+    columns.retain(|column| rows.iter().all(|row| row.get(*column).is_none_or(is_scalar)));
+    if columns.is_empty() {
+        for row in rows {
+            let _ = writeln!(out, "{}", row);
+        }
+        return;
+    }
+    let mut lines = vec![columns.iter().map(|column| column.to_uppercase()).collect::<Vec<_>>()];
+    for row in rows {
+        lines.push(columns.iter().map(|column| cell(row.get(*column).unwrap_or(&Value::Null))).collect());
+    }
+    write_rows(out, &lines);
+}
+
+fn write_object(out: &mut String, object: &Map<String, Value>) {
+    let fields: Vec<Vec<String>> = object
+        .iter()
+        .filter(|(_, value)| !is_table(value))
+        .map(|(key, value)| vec![format!("{}:", key), cell(value)])
+        .collect();
+    write_rows(out, &fields);
+    for (key, value) in object.iter().filter(|(_, value)| is_table(value)) {
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let _ = writeln!(out, "{}:", key);
+        write_table(out, value.as_array().map(Vec::as_slice).unwrap_or_default());
+    }
+}
+
+pub fn render_table(value: &Value) -> String {
+    let mut out = String::new();
+    match value {
+        Value::Array(rows) if is_table(value) => write_table(&mut out, rows),
+        Value::Array(items) => {
+            for item in items {
+                let _ = writeln!(out, "{}", cell(item));
+            }
+        }
+        Value::Object(object) => write_object(&mut out, object),
+        scalar => out.push_str(&cell(scalar)),
+    }
+    out.trim_end().to_string()
+}
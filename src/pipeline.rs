@@ -0,0 +1,240 @@
+//! `run <pipeline.yaml>`: a sequence of tool calls on one connection, where
+//! later steps use the output of earlier ones.
+//!
+//! ```yaml
+//! vars:
+//!   parent: 4321
+//! steps:
+//!   - id: tree
+//!     tool: get_process_tree
+//!     arguments: { pid: "${vars.parent}", max_depth: 1 }
+//!   - tool: kill_process
+//!     arguments: { pid: "${tree.roots[0].children[0].pid}", confirm: true }
+//! ```
+//!
+//! A string that is exactly one `${...}` becomes the referenced value with its
+//! JSON type; references inside longer strings are spliced in as text. A
+//! step's output is its structured content, else its text parsed as JSON,
+//! else the text itself.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use rmcp::model::CallToolResult;
+use serde::Deserialize;
+use serde_json::{json, Map, Value};
+
+use crate::cli::PipelineArgs;
+
+#[derive(Debug, Deserialize)]
+struct Pipeline {
+    #[serde(default)]
+    vars: BTreeMap<String, Value>,
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Step {
+    /// Name later steps use to refer to this step's output; defaults to `step<N>`.
+    id: Option<String>,
+    tool: String,
+    #[serde(default = "empty_object")]
+    arguments: Value,
+    /// Keep going when the tool reports an error.
+    #[serde(default)]
+    continue_on_error: bool,
+}
+
+fn empty_object() -> Value {
+    json!({})
+}
+
+impl Pipeline {
+    fn load(path: &str) -> Result<Self> {
+        let source = if path == "-" {
+            std::io::read_to_string(std::io::stdin())?
+        } else {
+            fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))?
+        };
+        let pipeline: Pipeline = serde_yaml::from_str(&source).map_err(|e| anyhow!("Invalid pipeline {}: {}", path, e))?;
+        let mut ids = HashSet::new();
+        for (index, step) in pipeline.steps.iter().enumerate() {
+            let id = step_id(step, index);
+            if id == "vars" || !ids.insert(id.clone()) {
+                return Err(anyhow!("Step id '{}' is reserved or used twice", id));
+            }
+        }
+        Ok(pipeline)
+    }
+}
+
+fn step_id(step: &Step, index: usize) -> String {
+    step.id.clone().unwrap_or_else(|| format!("step{}", index + 1))
+}
+
+// Follows `a.b[0].c` into `value`.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let mut current = value;
+    for segment in path.split('.').filter(|segment| !segment.is_empty()) {
+        let (key, indices) = segment.split_once('[').unwrap_or((segment, ""));
+        if !key.is_empty() {
+            current = current.get(key)?;
+        }
+        for index in indices.split('[').filter(|index| !index.is_empty()) {
+            current = current.get(index.trim_end_matches(']').parse::<usize>().ok()?)?;
+        }
+    }
+    Some(current)
+}
+
+// Resolves one `${scope.path}` reference against the outputs so far.
+fn resolve(reference: &str, scope: &Map<String, Value>) -> Result<Value> {
+    let (name, path) = reference.split_at(reference.find(['.', '[']).unwrap_or(reference.len()));
+    let root = scope
+        .get(name)
+        .ok_or_else(|| anyhow!("${{{}}} refers to '{}', which is not a variable or an earlier step", reference, name))?;
+    lookup(root, path.trim_start_matches('.'))
+        .cloned()
+        .ok_or_else(|| anyhow!("${{{}}} matches nothing in the output of '{}'", reference, name))
+}
+
+fn substitute_str(text: &str, scope: &Map<String, Value>) -> Result<Value> {
+    let trimmed = text.trim();
+    if let Some(reference) = trimmed.strip_prefix("${").and_then(|rest| rest.strip_suffix('}')) {
+        if !reference.contains("${") {
+            return resolve(reference, scope);
+        }
+    }
+    let mut output = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| anyhow!("Unterminated ${{ in '{}'", text))?;
+        output.push_str(&rest[..start]);
+        match resolve(&rest[start + 2..start + end], scope)? {
+            Value::String(value) => output.push_str(&value),
+            value => output.push_str(&value.to_string()),
+        }
+        rest = &rest[start + end + 1..];
+    }
+    output.push_str(rest);
+    Ok(Value::String(output))
+}
+
+fn substitute(value: &Value, scope: &Map<String, Value>) -> Result<Value> {
+    Ok(match value {
+        Value::String(text) => substitute_str(text, scope)?,
+        Value::Array(items) => Value::Array(items.iter().map(|item| substitute(item, scope)).collect::<Result<_>>()?),
+        Value::Object(fields) => Value::Object(
+            fields
+                .iter()
+                .map(|(key, field)| Ok((key.clone(), substitute(field, scope)?)))
+                .collect::<Result<_>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+// What later steps see of a result.
+fn step_output(result: &CallToolResult) -> Value {
+    if let Some(structured) = &result.structured_content {
+        return structured.clone();
+    }
+    let text = result
+        .content
+        .iter()
+        .filter_map(|item| item.raw.as_text().map(|text| text.text.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n");
+    serde_json::from_str(&text).unwrap_or(Value::String(text))
+}
+
+fn parse_vars(assignments: &[String], vars: &mut BTreeMap<String, Value>) -> Result<()> {
+    for assignment in assignments {
+        let (key, value) = assignment
+            .split_once('=')
+            .ok_or_else(|| anyhow!("--var expects key=value, got '{}'", assignment))?;
+        // `--var pid=42` should give a number, like `pid: 42` in the file would.
+        let value = serde_json::from_str(value).unwrap_or_else(|_| Value::String(value.to_string()));
+        vars.insert(key.to_string(), value);
+    }
+    Ok(())
+}
+
+/// Runs the pipeline file. With `--dry-run` it prints the calls it would
+/// make, resolving only `vars`; with `--json` it prints one JSON report
+/// instead of progress lines. Fails when a step fails.
+pub fn run(args: &PipelineArgs) -> Result<()> {
+    let mut pipeline = Pipeline::load(&args.pipeline)?;
+    parse_vars(&args.var, &mut pipeline.vars)?;
+    let (dry_run, as_json) = (args.dry_run, args.json);
+
+    let mut scope = Map::new();
+    scope.insert("vars".to_string(), json!(pipeline.vars));
+
+    if dry_run {
+        let mut plan = Vec::new();
+        for (index, step) in pipeline.steps.iter().enumerate() {
+            // Only `vars` exist before anything runs; other references stay as written.
+            let arguments = substitute(&step.arguments, &scope).unwrap_or_else(|_| step.arguments.clone());
+            plan.push(json!({ "id": step_id(step, index), "tool": step.tool, "arguments": arguments }));
+        }
+        if as_json {
+            println!("{}", serde_json::to_string_pretty(&json!({ "dry_run": true, "steps": plan }))?);
+        } else {
+            for (index, step) in plan.iter().enumerate() {
+                let (id, tool) = (step["id"].as_str().unwrap_or_default(), step["tool"].as_str().unwrap_or_default());
+                println!("[{}/{}] {}: {} {}", index + 1, plan.len(), id, tool, step["arguments"]);
+            }
+        }
+        return Ok(());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        let client = crate::connect_client(&args.connect).await?;
+        client.initialize().await?;
+        client.initialized_notification().await?;
+
+        let total = pipeline.steps.len();
+        let mut report = Vec::with_capacity(total);
+        let mut failed = None;
+        for (index, step) in pipeline.steps.iter().enumerate() {
+            let id = step_id(step, index);
+            let arguments = match substitute(&step.arguments, &scope) {
+                Ok(arguments) => arguments,
+                Err(e) => {
+                    report.push(json!({ "id": id, "tool": step.tool, "error": e.to_string() }));
+                    failed = Some(format!("Step '{}': {}", id, e));
+                    break;
+                }
+            };
+            if !as_json {
+                eprintln!("[{}/{}] {}: {}", index + 1, total, id, step.tool);
+            }
+            let (output, is_error) = match client.call_tool(&step.tool, arguments.clone(), None).await {
+                Ok(result) => (step_output(&result), result.is_error == Some(true)),
+                Err(e) => (Value::String(e.to_string()), true),
+            };
+            if !as_json {
+                println!("{}", serde_json::to_string_pretty(&output)?);
+            }
+            report.push(json!({ "id": id, "tool": step.tool, "arguments": arguments, "output": output, "is_error": is_error }));
+            scope.insert(id.clone(), output);
+            if is_error && !step.continue_on_error {
+                failed = Some(format!("Step '{}' ({}) failed", id, step.tool));
+                break;
+            }
+        }
+
+        if as_json {
+            println!("{}", serde_json::to_string_pretty(&json!({ "ok": failed.is_none(), "steps": report }))?);
+        }
+        match failed {
+            Some(message) => Err(anyhow!(message)),
+            None => Ok(()),
+        }
+    })
+}
@@ -0,0 +1,426 @@
+//! Tool plugins: files in a plugin directory are loaded at startup and
+//! whenever the directory changes, and their tools appear in `tools/list` next
+//! to the built-in ones. Removing a file unloads its tools; replacing it
+//! reloads them. No restart is needed.
+//!
+//! Two kinds of plugin are supported, each with its own directory:
+//!
+//! - Native shared libraries in `MCP_PLUGIN_DIR`, described below.
+//! - WebAssembly components in `MCP_WASM_PLUGIN_DIR`; see `wasm_plugins`.
+//!
+//! A native plugin exports a C ABI in the style of the `mcpdesk_obs_plugin`
+//! one:
+//!
+//! ```c
+//! uint32_t mcp_plugin_abi_version(void);   // must return 1
+//! char *register_tools(void);              // JSON array of tool definitions
+//! char *call_tool(const char *name, const char *arguments_json);
+//! void free_string(char *s);               // frees strings returned above
+//! ```
+//!
+//! A tool definition is `{"name", "description", "input_schema",
+//! "output_schema"?, "dangerous"?}`. `call_tool` returns a `CallToolResult` as
+//! JSON; failures are results with `isError` set. Calls run on the blocking
+//! pool, so a plugin may block but should not take long.
+//!
+//! Each native plugin is loaded from a private copy in the runtime dir. The
+//! dynamic loader hands back the already-open library for a path it has seen,
+//! and overwriting a mapped library crashes the process, so the file in the
+//! plugin directory itself is never opened. The copy is deleted once the
+//! last call into that version has returned.
+
+use std::collections::HashMap;
+use std::ffi::{c_char, CStr, CString};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use anyhow::{anyhow, Context, Result};
+use copilot_mcp_tool::sandbox::Sandbox;
+use mcp_common::runtime_paths;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{CallToolResult, JsonObject, Tool};
+use rmcp::ErrorData as McpError;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::notifications::NotificationHub;
+use crate::policy;
+use crate::registry::{DynamicHandler, ToolRegistry};
+use crate::wasm_plugins::WasmRuntime;
+
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+
+/// How often plugin directories are checked for changes.
+const SCAN_INTERVAL: Duration = Duration::from_secs(5);
+// A file modified more recently than this may still be being copied in.
+const SETTLE_TIME: Duration = Duration::from_secs(1);
+
+type AbiVersionFn = unsafe extern "C" fn() -> u32;
+type RegisterToolsFn = unsafe extern "C" fn() -> *mut c_char;
+type CallToolFn = unsafe extern "C" fn(*const c_char, *const c_char) -> *mut c_char;
+type FreeStringFn = unsafe extern "C" fn(*mut c_char);
+
+/// A tool as a plugin describes it.
+#[derive(Deserialize)]
+pub struct ToolDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub input_schema: JsonObject,
+    pub output_schema: Option<JsonObject>,
+    #[serde(default)]
+    pub dangerous: bool,
+}
+
+impl ToolDefinition {
+    fn into_tool(self) -> Tool {
+        let mut tool = Tool {
+            name: self.name.into(),
+            title: None,
+            description: Some(self.description.into()),
+            input_schema: Arc::new(self.input_schema),
+            output_schema: self.output_schema.map(Arc::new),
+            annotations: None,
+            icons: None,
+            meta: None,
+        };
+        if self.dangerous {
+            policy::mark_dangerous(&mut tool);
+        }
+        tool
+    }
+}
+
+/// A loaded plugin, called with a tool name and JSON arguments. Calls run on
+/// the blocking pool. Tool handlers hold the plugin through an `Arc`, so it
+/// stays loaded until calls into it have returned.
+pub trait PluginBackend: Send + Sync + 'static {
+    fn call(&self, name: &str, arguments: &str) -> Result<CallToolResult, McpToolError>;
+}
+
+// Numbers the private copies of plugins loaded by this process.
+static NEXT_COPY: AtomicU64 = AtomicU64::new(0);
+
+/// A plugin library opened from its own copy, which is removed after the
+/// library is closed.
+struct PluginLibrary {
+    library: Option<libloading::Library>,
+    copy: PathBuf,
+}
+
+impl PluginLibrary {
+    fn open(path: &Path) -> Result<Self> {
+        let file_name = path.file_name().ok_or_else(|| anyhow!("{} is not a file", path.display()))?;
+        let copy = runtime_paths::plugin_copies_dir()
+            .context("Failed to create the plugin copies directory")?
+            .join(format!(
+                "{}-{}-{}",
+                std::process::id(),
+                NEXT_COPY.fetch_add(1, Ordering::Relaxed),
+                file_name.to_string_lossy()
+            ));
+        let contents = std::fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+        runtime_paths::write_private_file(&copy, &contents)
+            .with_context(|| format!("Failed to copy {} to {}", path.display(), copy.display()))?;
+        let mut library = Self { library: None, copy };
+        // SAFETY: loading runs the library's initializers. Only libraries the
+        // user placed in the plugin directory are loaded.
+        library.library = Some(
+            unsafe { libloading::Library::new(&library.copy) }
+                .with_context(|| format!("Failed to load {}", path.display()))?,
+        );
+        Ok(library)
+    }
+
+    fn get(&self) -> &libloading::Library {
+        self.library.as_ref().expect("plugin library is open until dropped")
+    }
+}
+
+impl Drop for PluginLibrary {
+    fn drop(&mut self) {
+        // Closed first: Windows won't delete a loaded library.
+        drop(self.library.take());
+        if let Err(e) = std::fs::remove_file(&self.copy) {
+            tracing::warn!("Could not remove plugin copy {}: {}", self.copy.display(), e);
+        }
+    }
+}
+
+/// An open native plugin library.
+struct NativePlugin {
+    call_tool: CallToolFn,
+    free_string: FreeStringFn,
+    // Declared last: the function pointers above point into it.
+    _library: PluginLibrary,
+}
+
+impl NativePlugin {
+    fn open(path: &Path) -> Result<(Self, Vec<ToolDefinition>)> {
+        let library = PluginLibrary::open(path)?;
+        // SAFETY: the symbol types are the documented plugin ABI.
+        let (version, register_tools, call_tool, free_string) = unsafe {
+            (
+                *library.get().get::<AbiVersionFn>(b"mcp_plugin_abi_version\0")?,
+                *library.get().get::<RegisterToolsFn>(b"register_tools\0")?,
+                *library.get().get::<CallToolFn>(b"call_tool\0")?,
+                *library.get().get::<FreeStringFn>(b"free_string\0")?,
+            )
+        };
+        // SAFETY: as above.
+        let version = unsafe { version() };
+        if version != PLUGIN_ABI_VERSION {
+            return Err(anyhow!(
+                "{} uses plugin ABI {}, expected {}",
+                path.display(),
+                version,
+                PLUGIN_ABI_VERSION
+            ));
+        }
+        let plugin = Self {
+            call_tool,
+            free_string,
+            _library: library,
+        };
+        // SAFETY: as above.
+        let definitions = plugin
+            .take_string(unsafe { register_tools() })
+            .ok_or_else(|| anyhow!("{}: register_tools returned null", path.display()))?;
+        let definitions = serde_json::from_str(&definitions)
+            .with_context(|| format!("{}: register_tools returned invalid tool definitions", path.display()))?;
+        Ok((plugin, definitions))
+    }
+
+    // Copies a string returned by the plugin and hands it back to be freed.
+    fn take_string(&self, ptr: *mut c_char) -> Option<String> {
+        if ptr.is_null() {
+            return None;
+        }
+        // SAFETY: non-null strings returned by the plugin are NUL-terminated
+        // and stay valid until passed to free_string.
+        let value = unsafe { CStr::from_ptr(ptr) }.to_string_lossy().into_owned();
+        // SAFETY: ptr came from this plugin and is freed once.
+        unsafe { (self.free_string)(ptr) };
+        Some(value)
+    }
+}
+
+impl PluginBackend for NativePlugin {
+    fn call(&self, name: &str, arguments: &str) -> Result<CallToolResult, McpToolError> {
+        let name = CString::new(name).map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, e.to_string()))?;
+        let arguments = CString::new(arguments).map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, e.to_string()))?;
+        // SAFETY: both arguments are valid NUL-terminated strings for the call.
+        let output = self
+            .take_string(unsafe { (self.call_tool)(name.as_ptr(), arguments.as_ptr()) })
+            .ok_or_else(|| McpToolError::new(ErrorCode::Internal, "Plugin returned no result"))?;
+        parse_result(&output)
+    }
+}
+
+/// Parses the JSON `CallToolResult` a plugin returned.
+pub fn parse_result(output: &str) -> Result<CallToolResult, McpToolError> {
+    serde_json::from_str(output)
+        .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Plugin returned an invalid result: {}", e)))
+}
+
+enum PluginKind {
+    Native,
+    Wasm(Arc<WasmRuntime>),
+}
+
+struct LoadedPlugin {
+    modified: SystemTime,
+    tools: Vec<String>,
+    // Why the plugin failed to load, if it did.
+    error: Option<String>,
+}
+
+/// A plugin file as the `health` method reports it.
+#[derive(Debug, serde::Serialize, Deserialize)]
+pub struct PluginStatus {
+    pub path: PathBuf,
+    pub tools: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Watches one plugin directory and keeps the registry's tools from it in
+/// sync with its contents.
+pub struct PluginHost {
+    dir: PathBuf,
+    kind: PluginKind,
+    loaded: Mutex<HashMap<PathBuf, LoadedPlugin>>,
+}
+
+impl PluginHost {
+    fn new(dir: PathBuf, kind: PluginKind) -> Self {
+        Self {
+            dir,
+            kind,
+            loaded: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Native plugins are off unless `MCP_PLUGIN_DIR` names a directory;
+    /// loading native code is never implicit.
+    pub fn native_from_env() -> Option<Self> {
+        let dir = std::env::var_os("MCP_PLUGIN_DIR").filter(|dir| !dir.is_empty())?;
+        Some(Self::new(PathBuf::from(dir), PluginKind::Native))
+    }
+
+    /// WebAssembly plugins from `MCP_WASM_PLUGIN_DIR`, if set. Directories
+    /// granted to plugins must pass the server's sandbox.
+    pub fn wasm_from_env(sandbox: Arc<Sandbox>) -> Option<Self> {
+        let dir = std::env::var_os("MCP_WASM_PLUGIN_DIR").filter(|dir| !dir.is_empty())?;
+        match WasmRuntime::from_env(sandbox) {
+            Ok(runtime) => Some(Self::new(PathBuf::from(dir), PluginKind::Wasm(Arc::new(runtime)))),
+            Err(e) => {
+                tracing::warn!("WebAssembly plugins disabled: {:#}", e);
+                None
+            }
+        }
+    }
+
+    /// Loads the plugins now and then rescans every `SCAN_INTERVAL`.
+    pub fn spawn_watcher<C: Send + 'static>(self: Arc<Self>, tools: Arc<ToolRegistry<C>>, events: Arc<NotificationHub>) {
+        tracing::info!("Loading tool plugins from {}", self.dir.display());
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(SCAN_INTERVAL);
+            loop {
+                interval.tick().await;
+                let host = self.clone();
+                let tools = tools.clone();
+                let events = events.clone();
+                // dlopen, plugin initializers and wasm compilation all block.
+                if let Err(e) = tokio::task::spawn_blocking(move || host.scan(&tools, &events)).await {
+                    tracing::warn!("Plugin watcher stopped: {}", e);
+                    return;
+                }
+            }
+        });
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Every plugin file seen so far, with its tools or load error.
+    pub fn status(&self) -> Vec<PluginStatus> {
+        let loaded = self.loaded.lock().unwrap();
+        let mut status: Vec<PluginStatus> = loaded
+            .iter()
+            .map(|(path, plugin)| PluginStatus {
+                path: path.clone(),
+                tools: plugin.tools.clone(),
+                error: plugin.error.clone(),
+            })
+            .collect();
+        status.sort_by(|a, b| a.path.cmp(&b.path));
+        status
+    }
+
+    fn extension(&self) -> &'static str {
+        match self.kind {
+            PluginKind::Native => std::env::consts::DLL_EXTENSION,
+            PluginKind::Wasm(_) => "wasm",
+        }
+    }
+
+    // Plugin files in the directory with their modification times.
+    fn plugin_files(&self) -> HashMap<PathBuf, SystemTime> {
+        let Ok(entries) = std::fs::read_dir(&self.dir) else {
+            return HashMap::new();
+        };
+        entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == self.extension()))
+            .filter_map(|path| {
+                let modified = path.metadata().and_then(|metadata| metadata.modified()).ok()?;
+                Some((path, modified))
+            })
+            .collect()
+    }
+
+    fn scan<C: Send + 'static>(&self, registry: &ToolRegistry<C>, events: &NotificationHub) {
+        let present = self.plugin_files();
+        let mut loaded = self.loaded.lock().unwrap();
+
+        let gone: Vec<PathBuf> = loaded
+            .iter()
+            .filter(|(path, plugin)| present.get(*path) != Some(&plugin.modified))
+            .map(|(path, _)| path.clone())
+            .collect();
+        for path in gone {
+            if let Some(plugin) = loaded.remove(&path) {
+                for name in &plugin.tools {
+                    registry.remove_dynamic(name);
+                }
+                tracing::info!("Unloaded plugin {}", path.display());
+                events.publish("plugins.unloaded", json!({ "path": path, "tools": plugin.tools }));
+            }
+        }
+
+        let now = SystemTime::now();
+        for (path, modified) in present {
+            if loaded.contains_key(&path) || now.duration_since(modified).is_ok_and(|age| age < SETTLE_TIME) {
+                continue;
+            }
+            let (tools, error) = match self.load(&path, registry) {
+                Ok(tools) => {
+                    tracing::info!("Loaded plugin {} with tools {:?}", path.display(), tools);
+                    events.publish("plugins.loaded", json!({ "path": path, "tools": tools }));
+                    (tools, None)
+                }
+                Err(e) => {
+                    tracing::warn!("{:#}", e);
+                    let error = format!("{:#}", e);
+                    events.publish("plugins.failed", json!({ "path": path, "error": error }));
+                    // Remembered so a broken plugin isn't retried until it changes.
+                    (Vec::new(), Some(error))
+                }
+            };
+            loaded.insert(path, LoadedPlugin { modified, tools, error });
+        }
+    }
+
+    // Registers the plugin's tools, returning their names.
+    fn load<C: Send + 'static>(&self, path: &Path, registry: &ToolRegistry<C>) -> Result<Vec<String>> {
+        let (backend, definitions): (Arc<dyn PluginBackend>, Vec<ToolDefinition>) = match &self.kind {
+            PluginKind::Native => {
+                let (plugin, definitions) = NativePlugin::open(path)?;
+                (Arc::new(plugin), definitions)
+            }
+            PluginKind::Wasm(runtime) => {
+                let (plugin, definitions) = runtime.load(path)?;
+                (Arc::new(plugin), definitions)
+            }
+        };
+        let mut names = Vec::new();
+        for definition in definitions {
+            let tool = definition.into_tool();
+            let name = tool.name.to_string();
+            match registry.add_dynamic(tool, handler(backend.clone(), name.clone())) {
+                Ok(()) => names.push(name),
+                Err(e) => tracing::warn!("{}: skipping tool: {}", path.display(), e),
+            }
+        }
+        Ok(names)
+    }
+}
+
+fn handler(backend: Arc<dyn PluginBackend>, name: String) -> DynamicHandler {
+    Arc::new(move |arguments| {
+        let backend = backend.clone();
+        let name = name.clone();
+        Box::pin(async move {
+            let arguments = serde_json::to_string(&arguments.unwrap_or_default())
+                .map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, e.to_string()))?;
+            let result = tokio::task::spawn_blocking(move || backend.call(&name, &arguments))
+                .await
+                .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Plugin call panicked: {}", e)))?;
+            result.map_err(McpError::from)
+        })
+    })
+}
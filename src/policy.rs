@@ -0,0 +1,196 @@
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{JsonObject, Tool, ToolAnnotations};
+
+/// Argument a caller sets to `true` to run a tool whose policy is `confirm`.
+pub const CONFIRM_ARGUMENT: &str = "confirm";
+
+/// Rule that applies to tools the policy doesn't name.
+const WILDCARD: &str = "*";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Allow,
+    /// Runs only when the call carries `confirm: true`.
+    Confirm,
+    Deny,
+}
+
+impl FromStr for Permission {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "allow" => Ok(Permission::Allow),
+            "confirm" => Ok(Permission::Confirm),
+            "deny" => Ok(Permission::Deny),
+            other => Err(anyhow::anyhow!("Unknown permission '{}'. Expected allow, confirm or deny", other)),
+        }
+    }
+}
+
+/// Marks a tool as destructive. Dangerous tools need confirmation unless the
+/// server runs with `--unsafe`.
+pub fn mark_dangerous(tool: &mut Tool) {
+    let annotations = tool.annotations.get_or_insert_with(ToolAnnotations::default);
+    annotations.destructive_hint = Some(true);
+    annotations.read_only_hint = Some(false);
+}
+
+pub fn is_dangerous(tool: &Tool) -> bool {
+    tool.annotations
+        .as_ref()
+        .and_then(|annotations| annotations.destructive_hint)
+        .unwrap_or(false)
+}
+
+/// Decides which tools a client may call, checked before every call.
+#[derive(Debug, Clone)]
+pub struct ToolPolicy {
+    rules: HashMap<String, Permission>,
+    // Lets dangerous tools run without confirmation.
+    allow_unsafe: bool,
+}
+
+impl ToolPolicy {
+    pub fn new(rules: HashMap<String, Permission>, allow_unsafe: bool) -> Self {
+        Self { rules, allow_unsafe }
+    }
+
+    /// Reads `MCP_TOOL_POLICY`, e.g. `kill_process=deny,list_processes=allow,*=confirm`.
+    /// `*` sets the rule for unnamed tools. Malformed entries are skipped with a warning.
+    pub fn from_env(allow_unsafe: bool) -> Self {
+        let mut rules = HashMap::new();
+        if let Ok(spec) = env::var("MCP_TOOL_POLICY") {
+            for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let parsed = entry
+                    .split_once(['=', ':'])
+                    .ok_or_else(|| anyhow::anyhow!("expected <tool>=<permission>"))
+                    .and_then(|(tool, permission)| Ok((tool.trim().to_string(), permission.parse()?)));
+                match parsed {
+                    Ok((tool, permission)) => {
+                        rules.insert(tool, permission);
+                    }
+                    Err(e) => tracing::warn!("Ignoring MCP_TOOL_POLICY entry '{}': {}", entry, e),
+                }
+            }
+        }
+        Self::new(rules, allow_unsafe)
+    }
+
+    /// The permission in effect for `tool`. Without `--unsafe`, a dangerous
+    /// tool never runs unconfirmed, whatever its rule says.
+    pub fn permission(&self, tool: &Tool) -> Permission {
+        let rule = self
+            .rules
+            .get(tool.name.as_ref())
+            .or_else(|| self.rules.get(WILDCARD))
+            .copied();
+        match rule {
+            Some(Permission::Deny) => Permission::Deny,
+            _ if is_dangerous(tool) && !self.allow_unsafe => Permission::Confirm,
+            Some(permission) => permission,
+            None => Permission::Allow,
+        }
+    }
+
+    /// Checks a call against the policy, consuming its `confirm` argument.
+    pub fn check(&self, tool: &Tool, arguments: Option<&mut JsonObject>) -> Result<(), McpToolError> {
+        let confirmed = arguments
+            .and_then(|arguments| arguments.remove(CONFIRM_ARGUMENT))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false);
+        match self.permission(tool) {
+            Permission::Allow => Ok(()),
+            Permission::Confirm if confirmed => Ok(()),
+            Permission::Confirm => Err(McpToolError::new(
+                ErrorCode::ConfirmationRequired,
+                format!("Tool '{}' needs confirmation; call it again with `confirm: true`", tool.name),
+            )),
+            Permission::Deny => Err(McpToolError::new(
+                ErrorCode::ToolDenied,
+                format!("Tool '{}' is disabled by the server's tool policy", tool.name),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    fn tool(name: &'static str) -> Tool {
+        Tool::new(name, "", JsonObject::new())
+    }
+
+    fn dangerous(name: &'static str) -> Tool {
+        let mut tool = tool(name);
+        mark_dangerous(&mut tool);
+        tool
+    }
+
+    fn policy(rules: &[(&str, Permission)], allow_unsafe: bool) -> ToolPolicy {
+        let rules = rules.iter().map(|(tool, permission)| (tool.to_string(), *permission)).collect();
+        ToolPolicy::new(rules, allow_unsafe)
+    }
+
+    fn confirmed() -> JsonObject {
+        json!({ "confirm": true, "pid": 1 }).as_object().unwrap().clone()
+    }
+
+    #[test]
+    fn test_allow_confirm_deny() {
+        let policy = policy(
+            &[
+                ("list_processes", Permission::Allow),
+                ("get_disk_usage", Permission::Confirm),
+                ("execute_command", Permission::Deny),
+                (WILDCARD, Permission::Confirm),
+            ],
+            false,
+        );
+        assert!(policy.check(&tool("list_processes"), None).is_ok());
+
+        let e = policy.check(&tool("get_disk_usage"), None).unwrap_err();
+        assert_eq!(e.code, ErrorCode::ConfirmationRequired);
+        let mut arguments = confirmed();
+        assert!(policy.check(&tool("get_disk_usage"), Some(&mut arguments)).is_ok());
+        // The tool never sees `confirm`.
+        assert!(!arguments.contains_key(CONFIRM_ARGUMENT));
+        assert_eq!(arguments.get("pid"), Some(&Value::from(1)));
+
+        let e = policy.check(&tool("execute_command"), Some(&mut confirmed())).unwrap_err();
+        assert_eq!(e.code, ErrorCode::ToolDenied);
+
+        // Tools without a rule of their own follow `*`.
+        assert_eq!(policy.permission(&tool("get_time")), Permission::Confirm);
+        assert_eq!(self::policy(&[], false).permission(&tool("get_time")), Permission::Allow);
+    }
+
+    #[test]
+    fn test_dangerous_tools_need_confirmation_unless_unsafe() {
+        let allow_all = [(WILDCARD, Permission::Allow)];
+        let safe = policy(&allow_all, false);
+        assert_eq!(safe.permission(&dangerous("kill_process")), Permission::Confirm);
+        assert!(safe.check(&dangerous("kill_process"), None).is_err());
+        assert!(safe.check(&dangerous("kill_process"), Some(&mut confirmed())).is_ok());
+
+        let unsafe_policy = policy(&allow_all, true);
+        assert_eq!(unsafe_policy.permission(&dangerous("kill_process")), Permission::Allow);
+        assert!(unsafe_policy.check(&dangerous("kill_process"), None).is_ok());
+
+        // `--unsafe` never overrides deny.
+        let denied = policy(&[("kill_process", Permission::Deny)], true);
+        assert_eq!(denied.permission(&dangerous("kill_process")), Permission::Deny);
+    }
+
+    #[test]
+    fn test_permission_from_str() {
+        assert_eq!(" Confirm ".parse::<Permission>().unwrap(), Permission::Confirm);
+        assert!("maybe".parse::<Permission>().is_err());
+    }
+}
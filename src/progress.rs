@@ -0,0 +1,32 @@
+use rmcp::model::{ProgressNotificationParam, ProgressToken};
+use rmcp::service::{Peer, RequestContext, RoleServer};
+
+/// Sends `notifications/progress` for one request. Only exists when the
+/// client asked for progress by sending a progress token.
+#[derive(Clone)]
+pub struct ProgressReporter {
+    peer: Peer<RoleServer>,
+    token: ProgressToken,
+}
+
+impl ProgressReporter {
+    pub fn from_context(context: &RequestContext<RoleServer>) -> Option<Self> {
+        let token = context.meta.get_progress_token()?;
+        Some(Self {
+            peer: context.peer.clone(),
+            token,
+        })
+    }
+
+    /// Sends one update. `progress` must increase with every call. Failures are
+    /// ignored: the client may have gone away, and the tool carries on.
+    pub async fn report(&self, progress: f64, total: Option<f64>, message: impl Into<String>) {
+        let params = ProgressNotificationParam {
+            progress_token: self.token.clone(),
+            progress,
+            total,
+            message: Some(message.into()),
+        };
+        let _ = self.peer.notify_progress(params).await;
+    }
+}
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, RwLock};
+
+use futures::future::BoxFuture;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{CallToolResult, JsonObject, Tool};
+use rmcp::ErrorData as McpError;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Tools per `tools/list` page unless `MCP_TOOLS_PAGE_SIZE` says otherwise.
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+
+type Handler<C> = Arc<dyn Fn(C, Option<JsonObject>) -> BoxFuture<'static, Result<CallToolResult, McpError>> + Send + Sync>;
+
+struct Entry<C> {
+    tool: Tool,
+    handler: Handler<C>,
+}
+
+/// Handler of a tool added at runtime; it gets the arguments but no context.
+pub type DynamicHandler =
+    Arc<dyn Fn(Option<JsonObject>) -> BoxFuture<'static, Result<CallToolResult, McpError>> + Send + Sync>;
+
+/// Every tool the server offers. Tool modules register a name, input schema
+/// and handler at startup; `list_tools` and `call_tool` both read from here.
+/// `C` is the per-connection context handed to each handler.
+pub struct ToolRegistry<C> {
+    // Registration order is the order clients see in `tools/list`.
+    entries: Vec<Entry<C>>,
+    index: HashMap<String, usize>,
+    // Tools added and removed while the server runs (plugins), listed after
+    // the built-in ones.
+    dynamic: RwLock<Vec<Entry<C>>>,
+    page_size: usize,
+}
+
+impl<C: Send + 'static> ToolRegistry<C> {
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            index: HashMap::new(),
+            dynamic: RwLock::new(Vec::new()),
+            page_size: DEFAULT_PAGE_SIZE,
+        }
+    }
+
+    /// Reads the page size from `MCP_TOOLS_PAGE_SIZE`.
+    pub fn with_page_size_from_env(mut self) -> Self {
+        if let Some(page_size) = std::env::var("MCP_TOOLS_PAGE_SIZE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&page_size| page_size > 0)
+        {
+            self.page_size = page_size;
+        }
+        self
+    }
+
+    /// Adds a tool whose arguments deserialize into `I`. Returns the tool
+    /// definition so callers can fill in extras such as an output schema.
+    pub fn register<I, F, Fut>(&mut self, name: &str, description: &str, handler: F) -> &mut Tool
+    where
+        I: DeserializeOwned + JsonSchema,
+        F: Fn(C, I) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<CallToolResult, McpError>> + Send + 'static,
+    {
+        let tool = Tool {
+            name: name.to_string().into(),
+            title: None,
+            description: Some(description.to_string().into()),
+            input_schema: schema_for::<I>(),
+            output_schema: None,
+            annotations: None,
+            icons: None,
+            meta: None,
+        };
+        let handler: Handler<C> = Arc::new(move |context, arguments| match parse_arguments::<I>(arguments) {
+            Ok(input) => Box::pin(handler(context, input)),
+            Err(e) => Box::pin(std::future::ready(Err(e))),
+        });
+
+        let position = match self.index.get(name) {
+            Some(&position) => {
+                tracing::warn!("Tool '{}' registered twice; keeping the later handler", name);
+                self.entries[position] = Entry { tool, handler };
+                position
+            }
+            None => {
+                self.entries.push(Entry { tool, handler });
+                self.index.insert(name.to_string(), self.entries.len() - 1);
+                self.entries.len() - 1
+            }
+        };
+        &mut self.entries[position].tool
+    }
+
+    pub fn get(&self, name: &str) -> Option<Tool> {
+        match self.index.get(name) {
+            Some(&position) => Some(self.entries[position].tool.clone()),
+            None => self
+                .dynamic
+                .read()
+                .unwrap()
+                .iter()
+                .find(|entry| entry.tool.name == name)
+                .map(|entry| entry.tool.clone()),
+        }
+    }
+
+    /// Adds a tool while the server is running. Fails if a tool of that name
+    /// already exists.
+    pub fn add_dynamic(&self, tool: Tool, handler: DynamicHandler) -> Result<(), McpToolError> {
+        let mut dynamic = self.dynamic.write().unwrap();
+        if self.index.contains_key(tool.name.as_ref()) || dynamic.iter().any(|entry| entry.tool.name == tool.name) {
+            return Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("A tool named '{}' already exists", tool.name),
+            ));
+        }
+        let handler: Handler<C> = Arc::new(move |_, arguments| handler(arguments));
+        dynamic.push(Entry { tool, handler });
+        Ok(())
+    }
+
+    /// Removes a tool added with `add_dynamic`. Calls already running finish.
+    pub fn remove_dynamic(&self, name: &str) -> bool {
+        let mut dynamic = self.dynamic.write().unwrap();
+        let before = dynamic.len();
+        dynamic.retain(|entry| entry.tool.name != name);
+        dynamic.len() != before
+    }
+
+    /// Every tool, built-in ones first, in registration order.
+    pub fn list(&self) -> Vec<Tool> {
+        let dynamic = self.dynamic.read().unwrap();
+        self.entries.iter().chain(dynamic.iter()).map(|entry| entry.tool.clone()).collect()
+    }
+
+    /// How many tools are registered, built-in and dynamic.
+    pub fn tool_count(&self) -> usize {
+        self.entries.len() + self.dynamic.read().unwrap().len()
+    }
+
+    /// The page of tools starting at `cursor`, and the cursor of the next page
+    /// if there is one. Cursors are positions in registration order; built-in
+    /// tools keep theirs, but loading or unloading a plugin between pages can
+    /// shift the tools after them.
+    pub fn page(&self, cursor: Option<&str>) -> Result<(Vec<Tool>, Option<String>), McpToolError> {
+        let dynamic = self.dynamic.read().unwrap();
+        let total = self.entries.len() + dynamic.len();
+        let start = match cursor {
+            None => 0,
+            Some(cursor) => cursor
+                .parse::<usize>()
+                .ok()
+                .filter(|&start| start <= total)
+                .ok_or_else(|| McpToolError::new(ErrorCode::InvalidArgument, format!("Invalid cursor '{}'", cursor)))?,
+        };
+        let end = (start + self.page_size).min(total);
+        let tools = self
+            .entries
+            .iter()
+            .chain(dynamic.iter())
+            .skip(start)
+            .take(end - start)
+            .map(|entry| entry.tool.clone())
+            .collect();
+        let next_cursor = (end < total).then(|| end.to_string());
+        Ok((tools, next_cursor))
+    }
+
+    pub async fn call(&self, context: C, name: &str, arguments: Option<JsonObject>) -> Result<CallToolResult, McpError> {
+        let handler = match self.index.get(name) {
+            Some(&position) => Some(self.entries[position].handler.clone()),
+            None => self
+                .dynamic
+                .read()
+                .unwrap()
+                .iter()
+                .find(|entry| entry.tool.name == name)
+                .map(|entry| entry.handler.clone()),
+        }
+        .ok_or_else(|| McpToolError::new(ErrorCode::InvalidArgument, format!("Unknown tool: {}", name)))?;
+        handler(context, arguments).await
+    }
+}
+
+impl<C: Send + 'static> Default for ToolRegistry<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn schema_for<T: JsonSchema>() -> Arc<JsonObject> {
+    let schema = schemars::schema_for!(T);
+    let object = serde_json::to_value(schema)
+        .ok()
+        .and_then(|value| value.as_object().cloned())
+        .unwrap_or_default();
+    Arc::new(object)
+}
+
+pub fn parse_arguments<T: DeserializeOwned>(arguments: Option<JsonObject>) -> Result<T, McpError> {
+    let value = Value::Object(arguments.unwrap_or_default());
+    serde_json::from_value(value).map_err(|e| McpToolError::new(ErrorCode::InvalidArgument, e.to_string()).into())
+}
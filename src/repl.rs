@@ -0,0 +1,236 @@
+//! Interactive `repl`: one connection to the running server, tool calls typed
+//! as `<tool> key=value ...`, and server notifications printed as they arrive.
+
+use std::collections::BTreeMap;
+
+use anyhow::Result;
+use rmcp::model::CallToolResult;
+use rustyline::completion::Completer;
+use rustyline::error::ReadlineError;
+use rustyline::{Context, ExternalPrinter, Helper, Highlighter, Hinter, Validator};
+use serde_json::Value;
+
+use crate::cli::ConnectArgs;
+use crate::client::{ClientError, ToolDescriptor};
+use mcp_common::runtime_paths;
+
+const BUILTINS: &[&str] = &["help", "list", "reload", "quit", "exit"];
+
+const HELP: &str = "\
+Commands:
+  <tool> [key=value ...] [--json '<object>']   call a tool
+  list                                         list the server's tools
+  help [tool]                                  show this help, or a tool's arguments
+  reload                                       fetch the tool list again
+  quit | exit | Ctrl-D                         leave the repl";
+
+// Completes builtins and tool names in the first word, then the tool's
+// argument names as `key=`.
+#[derive(Helper, Hinter, Highlighter, Validator)]
+struct ToolCompleter {
+    arguments: BTreeMap<String, Vec<String>>,
+}
+
+impl ToolCompleter {
+    fn new(tools: &[ToolDescriptor]) -> Self {
+        let arguments = tools
+            .iter()
+            .map(|tool| {
+                let names = tool
+                    .input_schema
+                    .get("properties")
+                    .and_then(Value::as_object)
+                    .map(|properties| properties.keys().cloned().collect())
+                    .unwrap_or_default();
+                (tool.name.to_string(), names)
+            })
+            .collect();
+        Self { arguments }
+    }
+}
+
+impl Completer for ToolCompleter {
+    type Candidate = String;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+        let before = &line[..pos];
+        let start = before.rfind(char::is_whitespace).map(|index| index + 1).unwrap_or(0);
+        let word = &before[start..];
+        let candidates = if start == 0 {
+            BUILTINS
+                .iter()
+                .map(|name| name.to_string())
+                .chain(self.arguments.keys().cloned())
+                .filter(|name| name.starts_with(word))
+                .collect()
+        } else {
+            match before.split_whitespace().next().unwrap_or_default() {
+                // `help <tool>` completes tool names.
+                "help" => self.arguments.keys().filter(|name| name.starts_with(word)).cloned().collect(),
+                tool => self
+                    .arguments
+                    .get(tool)
+                    .into_iter()
+                    .flatten()
+                    .map(|name| format!("{}=", name))
+                    .filter(|candidate| candidate.starts_with(word))
+                    .collect(),
+            }
+        };
+        Ok((start, candidates))
+    }
+}
+
+fn print_tools(tools: &[ToolDescriptor]) {
+    let width = tools.iter().map(|tool| tool.name.len()).max().unwrap_or_default();
+    for tool in tools {
+        let description = tool.description.as_deref().unwrap_or_default();
+        let summary = description.lines().next().unwrap_or_default();
+        println!("  {:width$}  {}", tool.name, summary, width = width);
+    }
+}
+
+fn print_tool_help(tool: &ToolDescriptor) {
+    println!("{}", tool.name);
+    if let Some(description) = &tool.description {
+        println!("  {}", description);
+    }
+    let required: Vec<&str> = tool
+        .input_schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    let Some(properties) = tool.input_schema.get("properties").and_then(Value::as_object) else {
+        println!("  (no arguments)");
+        return;
+    };
+    for (name, property) in properties {
+        let kind = crate::schema_types(property).join(" | ");
+        let marker = if required.contains(&name.as_str()) { " (required)" } else { "" };
+        let description = property.get("description").and_then(Value::as_str).unwrap_or_default();
+        println!("  {}: {}{}  {}", name, if kind.is_empty() { "any" } else { kind.as_str() }, marker, description);
+    }
+}
+
+// Structured output is shown as JSON; otherwise the text content, with binary
+// payloads summarized the same way the `call` command does.
+fn print_result(tool_name: &str, result: &CallToolResult) -> Result<()> {
+    if result.is_error == Some(true) {
+        print!("Tool error: ");
+    }
+    if let Some(structured) = &result.structured_content {
+        println!("{}", serde_json::to_string_pretty(structured)?);
+        return Ok(());
+    }
+    let mut value = serde_json::to_value(result)?;
+    crate::extract_binary_content(&mut value, tool_name, None)?;
+    for item in value["content"].as_array().into_iter().flatten() {
+        match item["type"].as_str() {
+            Some("text") => println!("{}", item["text"].as_str().unwrap_or_default()),
+            _ => println!("{}", serde_json::to_string_pretty(item)?),
+        }
+    }
+    Ok(())
+}
+
+/// Runs the interactive shell against the background server until the user
+/// quits.
+pub fn run(connect: &ConnectArgs) -> Result<()> {
+    let runtime = tokio::runtime::Runtime::new()?;
+    let mut client = runtime.block_on(async {
+        let client = crate::connect_client(connect).await?;
+        client.initialize().await?;
+        client.initialized_notification().await?;
+        Ok::<_, anyhow::Error>(client)
+    })?;
+    let mut tools = runtime.block_on(client.list_tools())?;
+
+    let mut editor: rustyline::Editor<ToolCompleter, rustyline::history::DefaultHistory> = rustyline::Editor::new()?;
+    editor.set_helper(Some(ToolCompleter::new(&tools)));
+    let history = runtime_paths::repl_history_path().ok();
+    if let Some(path) = &history {
+        let _ = editor.load_history(path);
+    }
+
+    // Notifications arrive at any time, including while the prompt is shown;
+    // the external printer redraws the prompt after each one.
+    if let (Some(mut notifications), Ok(mut printer)) = (client.take_notifications(), editor.create_external_printer()) {
+        runtime.spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                let method = notification["method"].as_str().unwrap_or("notification");
+                let line = match notification["params"]["message"].as_str() {
+                    Some(message) if method == "notifications/progress" => message.to_string(),
+                    _ => format!("[{}] {}", method, notification["params"]),
+                };
+                let _ = printer.print(line);
+            }
+        });
+    }
+
+    println!("{} tools available. Type `help` for commands.", tools.len());
+    let mut call_count: u64 = 0;
+    loop {
+        let line = match editor.readline("mcp> ") {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let _ = editor.add_history_entry(line);
+        let Some(words) = shlex::split(line) else {
+            eprintln!("Unbalanced quotes");
+            continue;
+        };
+
+        match words[0].as_str() {
+            "quit" | "exit" => break,
+            "help" => match words.get(1) {
+                Some(name) => match tools.iter().find(|tool| tool.name == name.as_str()) {
+                    Some(tool) => print_tool_help(tool),
+                    None => eprintln!("Unknown tool '{}'", name),
+                },
+                None => println!("{}", HELP),
+            },
+            "list" => print_tools(&tools),
+            "reload" => match runtime.block_on(client.list_tools()) {
+                Ok(reloaded) => {
+                    tools = reloaded;
+                    editor.set_helper(Some(ToolCompleter::new(&tools)));
+                    println!("{} tools", tools.len());
+                }
+                Err(e) => eprintln!("Error: {}", e),
+            },
+            name => {
+                let schema = tools.iter().find(|tool| tool.name == name).map(|tool| tool.input_schema.as_ref());
+                if schema.is_none() {
+                    eprintln!("Unknown tool '{}'. Type `list` to see the available tools.", name);
+                    continue;
+                }
+                let params = match crate::parse_call_params(&words[1..], schema) {
+                    Ok(params) => params,
+                    Err(e) => {
+                        eprintln!("Error: {}", e);
+                        continue;
+                    }
+                };
+                call_count += 1;
+                let progress_token = format!("repl-{}", call_count);
+                match runtime.block_on(client.call_tool(name, params, Some(&progress_token))) {
+                    Ok(result) => print_result(name, &result)?,
+                    Err(ClientError::Transport(e)) => return Err(e),
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+        }
+    }
+
+    if let Some(path) = &history {
+        let _ = editor.save_history(path);
+    }
+    Ok(())
+}
@@ -0,0 +1,168 @@
+//! MCP resources exposed by `resources/list` and `resources/read`:
+//!
+//! - `file:///<path>`: files under the file system roots, read in chunks (see
+//!   `file_transfer`).
+//! - `log://server`: the tail of the server's own log.
+//! - `metrics://latest` and `metrics://history`: JSON snapshots from the
+//!   metrics sampler.
+
+use std::io::SeekFrom;
+use std::path::{Path, PathBuf};
+
+use mcp_common::runtime_paths;
+use copilot_mcp_tool::sandbox::Sandbox;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{AnnotateAble, RawResource, Resource, ResourceContents};
+use serde::Serialize;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+use crate::file_transfer;
+use crate::metrics::{GetSystemMetricsInput, MetricsHistory};
+
+pub const SERVER_LOG_URI: &str = "log://server";
+pub const METRICS_LATEST_URI: &str = "metrics://latest";
+pub const METRICS_HISTORY_URI: &str = "metrics://history";
+
+/// Bytes of the server log returned by a read.
+const LOG_TAIL_BYTES: u64 = 256 * 1024;
+// Files under the roots are listed only this deep; deeper files can still be
+// read through the file:// template.
+const MAX_FILE_DEPTH: usize = 3;
+const MAX_LISTED_FILES: usize = 500;
+
+fn resource(uri: &str, name: &str, description: &str, mime_type: &str) -> Resource {
+    let mut resource = RawResource::new(uri, name);
+    resource.description = Some(description.to_string());
+    resource.mime_type = Some(mime_type.to_string());
+    resource.no_annotation()
+}
+
+// Files under the roots, skipping hidden entries, up to MAX_LISTED_FILES.
+fn root_files(roots: &[PathBuf]) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    for root in roots {
+        let files = walkdir::WalkDir::new(root)
+            .max_depth(MAX_FILE_DEPTH)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file());
+        for entry in files {
+            if resources.len() == MAX_LISTED_FILES {
+                return resources;
+            }
+            let Ok(uri) = url::Url::from_file_path(entry.path()) else {
+                continue;
+            };
+            let name = entry.path().strip_prefix(root).unwrap_or(entry.path());
+            let mut resource = RawResource::new(uri.as_str(), name.to_string_lossy());
+            resource.mime_type = Some(file_transfer::mime_type(entry.path()).to_string());
+            resource.size = entry.metadata().ok().and_then(|metadata| u32::try_from(metadata.len()).ok());
+            resources.push(resource.no_annotation());
+        }
+    }
+    resources
+}
+
+/// Everything `resources/list` reports: the server log, metric snapshots when
+/// sampling is on, and files under the roots.
+pub async fn list(roots: &[PathBuf], metrics: &MetricsHistory) -> Vec<Resource> {
+    let mut resources = Vec::new();
+    if runtime_paths::server_log_path().is_ok_and(|path| path.exists()) {
+        resources.push(resource(
+            SERVER_LOG_URI,
+            "server.log",
+            "The end of this server's log.",
+            "text/plain",
+        ));
+    }
+    if metrics.is_enabled() {
+        resources.push(resource(
+            METRICS_LATEST_URI,
+            "metrics-latest",
+            "The most recent CPU, memory, disk and network sample.",
+            "application/json",
+        ));
+        resources.push(resource(
+            METRICS_HISTORY_URI,
+            "metrics-history",
+            "Every retained metrics sample, oldest first.",
+            "application/json",
+        ));
+    }
+    let roots = roots.to_vec();
+    // Walking the roots touches the disk; keep it off the async workers.
+    match tokio::task::spawn_blocking(move || root_files(&roots)).await {
+        Ok(files) => resources.extend(files),
+        Err(e) => tracing::warn!("Listing file resources failed: {}", e),
+    }
+    resources
+}
+
+/// Reads the resource at `uri`.
+pub async fn read(
+    uri: &str,
+    sandbox: &Sandbox,
+    roots: &[PathBuf],
+    metrics: &MetricsHistory,
+) -> Result<ResourceContents, McpToolError> {
+    match uri {
+        SERVER_LOG_URI => read_log_tail(uri, &runtime_paths::server_log_path()?).await,
+        METRICS_LATEST_URI => {
+            let sample = metrics.latest().ok_or_else(|| {
+                McpToolError::new(ErrorCode::ResourceNotFound, "No metrics have been sampled yet")
+            })?;
+            json_contents(uri, &sample)
+        }
+        METRICS_HISTORY_URI => {
+            let history = metrics.query(&GetSystemMetricsInput {
+                since: None,
+                resolution: None,
+            })?;
+            json_contents(uri, &history)
+        }
+        _ if uri.starts_with("file:") => file_transfer::read_chunk(sandbox, roots, uri).await,
+        _ => Err(McpToolError::new(
+            ErrorCode::ResourceNotFound,
+            format!("Unknown resource '{}'", uri),
+        )),
+    }
+}
+
+async fn read_log_tail(uri: &str, path: &Path) -> Result<ResourceContents, McpToolError> {
+    let mut file = tokio::fs::File::open(path).await.map_err(|e| match e.kind() {
+        std::io::ErrorKind::NotFound => {
+            McpToolError::new(ErrorCode::ResourceNotFound, format!("{} does not exist", path.display()))
+        }
+        _ => McpToolError::from(e),
+    })?;
+    let start = file.metadata().await?.len().saturating_sub(LOG_TAIL_BYTES);
+    file.seek(SeekFrom::Start(start)).await?;
+    let mut tail = Vec::new();
+    file.read_to_end(&mut tail).await?;
+
+    let mut text = String::from_utf8_lossy(&tail).into_owned();
+    // Drop the partial first line when the tail starts mid-file.
+    if start > 0 {
+        if let Some(newline) = text.find('\n') {
+            text.drain(..=newline);
+        }
+    }
+    Ok(ResourceContents::TextResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some("text/plain".to_string()),
+        text,
+        meta: None,
+    })
+}
+
+fn json_contents<T: Serialize>(uri: &str, value: &T) -> Result<ResourceContents, McpToolError> {
+    let text = serde_json::to_string_pretty(value)
+        .map_err(|e| McpToolError::new(ErrorCode::Internal, format!("Failed to serialize {}: {}", uri, e)))?;
+    Ok(ResourceContents::TextResourceContents {
+        uri: uri.to_string(),
+        mime_type: Some("application/json".to_string()),
+        text,
+        meta: None,
+    })
+}
@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use rmcp::model::{CallToolResult, JsonObject};
+use serde_json::Value;
+
+/// Argument that skips the cache for one call (the fresh result is still stored).
+pub const BYPASS_ARGUMENT: &str = "cache_bypass";
+
+/// Entries allowed before expired ones are swept out.
+const SWEEP_THRESHOLD: usize = 256;
+
+struct Entry {
+    result: CallToolResult,
+    expires_at: Instant,
+}
+
+/// Short-lived results for idempotent tools, keyed by tool name,
+/// canonicalized arguments and the caller's file system roots, since
+/// connections with different roots may see different results. Only tools
+/// with a TTL are cached, and only successful results are stored.
+pub struct ResultCache {
+    ttls: HashMap<String, Duration>,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+impl ResultCache {
+    pub fn new(ttls: HashMap<String, Duration>) -> Self {
+        Self {
+            ttls: ttls.into_iter().filter(|(_, ttl)| !ttl.is_zero()).collect(),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Nothing is cached unless `MCP_CACHE_TTLS` opts tools in, e.g.
+    /// `get_disk_usage=10,list_processes=2` (seconds).
+    pub fn from_env() -> Self {
+        let mut ttls = HashMap::new();
+        if let Ok(spec) = env::var("MCP_CACHE_TTLS") {
+            for entry in spec.split(',').map(str::trim).filter(|entry| !entry.is_empty()) {
+                let parsed = entry
+                    .split_once('=')
+                    .and_then(|(tool, secs)| Some((tool.trim().to_string(), secs.trim().parse::<u64>().ok()?)));
+                match parsed {
+                    Some((tool, secs)) => {
+                        ttls.insert(tool, Duration::from_secs(secs));
+                    }
+                    None => tracing::warn!("Ignoring invalid MCP_CACHE_TTLS entry '{}'", entry),
+                }
+            }
+        }
+        Self::new(ttls)
+    }
+
+    pub fn is_cached(&self, tool: &str) -> bool {
+        self.ttls.contains_key(tool)
+    }
+
+    pub fn get(&self, tool: &str, arguments: Option<&JsonObject>, roots: &[PathBuf]) -> Option<CallToolResult> {
+        let key = cache_key(tool, arguments, roots);
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(&key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.result.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, tool: &str, arguments: Option<&JsonObject>, roots: &[PathBuf], result: &CallToolResult) {
+        let Some(ttl) = self.ttls.get(tool) else {
+            return;
+        };
+        if result.is_error == Some(true) {
+            return;
+        }
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= SWEEP_THRESHOLD {
+            entries.retain(|_, entry| entry.expires_at > now);
+        }
+        entries.insert(
+            cache_key(tool, arguments, roots),
+            Entry {
+                result: result.clone(),
+                expires_at: now + *ttl,
+            },
+        );
+    }
+}
+
+fn cache_key(tool: &str, arguments: Option<&JsonObject>, roots: &[PathBuf]) -> String {
+    let arguments = arguments.cloned().map(Value::Object).unwrap_or(Value::Null);
+    let roots = Value::from(roots.iter().map(|root| root.to_string_lossy()).collect::<Vec<_>>());
+    format!("{}:{}:{}", tool, roots, canonicalize(&arguments))
+}
+
+// Same arguments in a different key order must hit the same entry.
+fn canonicalize(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            Value::Object(keys.into_iter().map(|key| (key.clone(), canonicalize(&map[key]))).collect())
+        }
+        Value::Array(items) => Value::Array(items.iter().map(canonicalize).collect()),
+        other => other.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+    use serde_json::json;
+
+    fn cache(ttl: Duration) -> ResultCache {
+        ResultCache::new(HashMap::from([("get_disk_usage".to_string(), ttl)]))
+    }
+
+    fn arguments(value: Value) -> JsonObject {
+        value.as_object().unwrap().clone()
+    }
+
+    fn result(text: &str) -> CallToolResult {
+        CallToolResult::success(vec![Content::text(text)])
+    }
+
+    #[test]
+    fn test_cache_key() {
+        let cache = cache(Duration::from_secs(60));
+        let roots = [PathBuf::from("/home/a")];
+        let stored = arguments(json!({ "path": "/", "options": { "human": true, "depth": 1 } }));
+        cache.insert("get_disk_usage", Some(&stored), &roots, &result("1G"));
+
+        // Key order doesn't matter, values and roots do.
+        let reordered = arguments(json!({ "options": { "depth": 1, "human": true }, "path": "/" }));
+        assert_eq!(cache.get("get_disk_usage", Some(&reordered), &roots), Some(result("1G")));
+        let other = arguments(json!({ "path": "/tmp" }));
+        assert!(cache.get("get_disk_usage", Some(&other), &roots).is_none());
+        assert!(cache.get("get_disk_usage", Some(&stored), &[PathBuf::from("/home/b")]).is_none());
+        assert!(cache.get("get_disk_usage", Some(&stored), &[]).is_none());
+    }
+
+    #[test]
+    fn test_entries_expire() {
+        let cache = cache(Duration::from_millis(10));
+        cache.insert("get_disk_usage", None, &[], &result("1G"));
+        assert!(cache.get("get_disk_usage", None, &[]).is_some());
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(cache.get("get_disk_usage", None, &[]).is_none());
+    }
+
+    #[test]
+    fn test_only_successful_results_of_cached_tools_are_stored() {
+        let cache = cache(Duration::from_secs(60));
+        assert!(cache.is_cached("get_disk_usage"));
+        assert!(!cache.is_cached("list_processes"));
+
+        cache.insert("list_processes", None, &[], &result("pid 1"));
+        assert!(cache.get("list_processes", None, &[]).is_none());
+        cache.insert("get_disk_usage", None, &[], &CallToolResult::error(vec![Content::text("failed")]));
+        assert!(cache.get("get_disk_usage", None, &[]).is_none());
+
+        // A zero TTL turns caching off.
+        let disabled = ResultCache::new(HashMap::from([("get_disk_usage".to_string(), Duration::ZERO)]));
+        assert!(!disabled.is_cached("get_disk_usage"));
+    }
+}
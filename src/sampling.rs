@@ -0,0 +1,60 @@
+//! Server-initiated LLM calls through the client (`sampling/createMessage`).
+//! Tools ask the host's model for a completion, so they work without API
+//! keys on the server. Only clients that declare the sampling capability are
+//! asked; hosts usually show each request to the user before running it.
+
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::{Content, CreateMessageRequestParam, Role, SamplingMessage};
+use rmcp::service::Peer;
+use rmcp::RoleServer;
+use schemars::JsonSchema;
+use serde::Serialize;
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SampledText {
+    pub text: String,
+    /// The model the host used.
+    pub model: String,
+}
+
+/// Asks the client's model to answer `prompt`, following `system_prompt`.
+pub async fn complete(
+    peer: &Peer<RoleServer>,
+    system_prompt: &str,
+    prompt: String,
+    max_tokens: u32,
+) -> Result<SampledText, McpToolError> {
+    if !peer.peer_info().is_some_and(|info| info.capabilities.sampling.is_some()) {
+        return Err(McpToolError::new(
+            ErrorCode::NotImplemented,
+            "The client does not support sampling, so this tool can't use its model",
+        ));
+    }
+    let params = CreateMessageRequestParam {
+        messages: vec![SamplingMessage {
+            role: Role::User,
+            content: Content::text(prompt),
+        }],
+        model_preferences: None,
+        system_prompt: Some(system_prompt.to_string()),
+        include_context: None,
+        temperature: None,
+        max_tokens,
+        stop_sequences: None,
+        metadata: None,
+    };
+    let result = peer
+        .create_message(params)
+        .await
+        .map_err(|e| McpToolError::new(ErrorCode::LlmRequestFailed, format!("The client's model did not answer: {}", e)))?;
+    let text = result
+        .message
+        .content
+        .as_text()
+        .map(|text| text.text.clone())
+        .ok_or_else(|| McpToolError::new(ErrorCode::LlmInvalidResponse, "The client's model answered without text"))?;
+    Ok(SampledText {
+        text,
+        model: result.model,
+    })
+}
@@ -0,0 +1,633 @@
+//! OS-level confinement for tools that run external programs or write files.
+//!
+//! Child processes are confined by the kernel: Landlock (filesystem) and a
+//! seccomp filter (sockets) on Linux, a Job Object on Windows.
+//! In-process file writes go through [`Sandbox::check_write_path`].
+//!
+//! Configured through environment variables (a `.env` file works too):
+//!
+//! - `MCP_SANDBOX_PROFILE`: `workdir` (default), `readonly` or `off`
+//! - `MCP_SANDBOX_WORKDIR`: directory tools may write to (default: current dir)
+//! - `MCP_SANDBOX_ALLOW_WRITE`: extra writable directories, `PATH`-style list
+//! - `MCP_SANDBOX_ALLOW_NETWORK`: `1` to let children open network sockets
+//! - `MCP_SANDBOX_MAX_PROCESSES`: process limit per command (Windows only, default 16)
+//! - `MCP_SANDBOX_STRICT`: `1` to refuse to run commands when part of the OS
+//!   sandbox is unavailable, instead of logging a warning and running them
+//!
+//! Out of scope on Windows: running children under a restricted token. std
+//! and tokio cannot start a process with `CreateProcessAsUser`, so there is no
+//! filesystem or network confinement there; children are started suspended,
+//! put in the job object, and only then resumed. Other platforms only get the
+//! path checks.
+
+use std::env;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Output};
+
+use mcp_errors::{ErrorCode, McpToolError};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+const DEFAULT_MAX_PROCESSES: u32 = 16;
+
+/// How much a sandboxed tool is allowed to do.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxProfile {
+    /// No confinement.
+    Off,
+    /// Read anywhere, write only inside the working directory.
+    Workdir,
+    /// Read anywhere, write nowhere.
+    ReadOnly,
+}
+
+impl std::str::FromStr for SandboxProfile {
+    type Err = McpToolError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "off" | "none" => Ok(SandboxProfile::Off),
+            "workdir" => Ok(SandboxProfile::Workdir),
+            "readonly" | "read-only" => Ok(SandboxProfile::ReadOnly),
+            other => Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("Unknown sandbox profile '{}'. Expected off, workdir or readonly", other),
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SandboxConfig {
+    pub profile: SandboxProfile,
+    pub workdir: PathBuf,
+    pub extra_writable: Vec<PathBuf>,
+    pub allow_network: bool,
+    pub max_processes: u32,
+    /// Refuse to run commands when part of the OS sandbox is unavailable.
+    pub strict: bool,
+}
+
+impl SandboxConfig {
+    pub fn from_env() -> Self {
+        let profile = env::var("MCP_SANDBOX_PROFILE")
+            .ok()
+            .and_then(|value| {
+                value
+                    .parse()
+                    .map_err(|e| tracing::warn!("{}; using the workdir profile", e))
+                    .ok()
+            })
+            .unwrap_or(SandboxProfile::Workdir);
+        let workdir = env::var_os("MCP_SANDBOX_WORKDIR")
+            .map(PathBuf::from)
+            .or_else(|| env::current_dir().ok())
+            .unwrap_or_else(env::temp_dir);
+        let extra_writable = env::var_os("MCP_SANDBOX_ALLOW_WRITE")
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default();
+        let allow_network = matches!(
+            env::var("MCP_SANDBOX_ALLOW_NETWORK").as_deref(),
+            Ok("1") | Ok("true") | Ok("yes")
+        );
+        let max_processes = env::var("MCP_SANDBOX_MAX_PROCESSES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_MAX_PROCESSES);
+        let strict = matches!(
+            env::var("MCP_SANDBOX_STRICT").as_deref(),
+            Ok("1") | Ok("true") | Ok("yes")
+        );
+
+        Self {
+            profile,
+            workdir,
+            extra_writable,
+            allow_network,
+            max_processes,
+            strict,
+        }
+    }
+}
+
+/// Applies a [`SandboxConfig`] to commands and file paths.
+pub struct Sandbox {
+    config: SandboxConfig,
+    // Canonical forms of `workdir` and `extra_writable`, used for path checks.
+    writable_roots: Vec<PathBuf>,
+    // Built once up front: the child must not allocate between fork and exec.
+    #[cfg(target_os = "linux")]
+    network_filter: Option<std::sync::Arc<seccompiler::BpfProgram>>,
+    // Confinement this system can't provide. In strict mode commands don't run.
+    unavailable: Vec<String>,
+}
+
+impl Sandbox {
+    pub fn new(config: SandboxConfig) -> Self {
+        let writable_roots = std::iter::once(&config.workdir)
+            .chain(config.extra_writable.iter())
+            .filter_map(|path| path.canonicalize().ok())
+            .collect();
+
+        let mut unavailable = Vec::new();
+        #[cfg(target_os = "linux")]
+        if config.profile != SandboxProfile::Off {
+            if let Err(e) = linux::filesystem_support() {
+                unavailable.push(format!("Landlock filesystem sandbox unavailable: {}", e));
+            }
+        }
+        #[cfg(target_os = "linux")]
+        let network_filter = if config.profile != SandboxProfile::Off && !config.allow_network {
+            match linux::network_filter() {
+                Ok(filter) => Some(std::sync::Arc::new(filter)),
+                Err(e) => {
+                    unavailable.push(format!("Network sandbox unavailable: {}", e));
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        if config.profile != SandboxProfile::Off && !cfg!(any(target_os = "linux", windows)) {
+            unavailable.push(format!(
+                "OS sandboxing is not supported on {}; only path checks are enforced",
+                env::consts::OS
+            ));
+        }
+        for reason in &unavailable {
+            if config.strict {
+                tracing::warn!("{}; MCP_SANDBOX_STRICT is set, so commands will not run", reason);
+            } else {
+                tracing::warn!("{}", reason);
+            }
+        }
+
+        Self {
+            config,
+            writable_roots,
+            #[cfg(target_os = "linux")]
+            network_filter,
+            unavailable,
+        }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(SandboxConfig::from_env())
+    }
+
+    pub fn config(&self) -> &SandboxConfig {
+        &self.config
+    }
+
+    /// Resolves `path` against the working directory and checks that the
+    /// active profile allows writing there.
+    pub fn check_write_path(&self, path: &Path) -> Result<PathBuf, McpToolError> {
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.config.workdir.join(path)
+        };
+        if self.config.profile == SandboxProfile::Off {
+            return Ok(path);
+        }
+
+        let violation = |reason: &str| {
+            McpToolError::new(
+                ErrorCode::SandboxViolation,
+                format!("Writing to {} is not allowed: {}", path.display(), reason),
+            )
+            .with_details(serde_json::json!({ "path": path, "profile": format!("{:?}", self.config.profile) }))
+        };
+        if self.config.profile == SandboxProfile::ReadOnly {
+            return Err(violation("the sandbox is read-only"));
+        }
+
+        // This also resolves `..` and symlinks out of the workdir.
+        let resolved = match resolve_nearest(&path) {
+            Ok(resolved) => resolved,
+            Err(e) if e.kind() == io::ErrorKind::InvalidInput => return Err(violation(&e.to_string())),
+            Err(e) => return Err(e.into()),
+        };
+
+        if self.writable_roots.iter().any(|root| resolved.starts_with(root)) {
+            Ok(resolved)
+        } else {
+            Err(violation("the path is outside the working directory"))
+        }
+    }
+
+    /// Checks that a file may be served to clients. Unless the sandbox is off,
+    /// only files under the working directory (or extra writable dirs) qualify.
+    pub fn check_read_path(&self, path: &Path) -> Result<PathBuf, McpToolError> {
+        let path = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            self.config.workdir.join(path)
+        };
+        let resolved = path.canonicalize().map_err(|e| match e.kind() {
+            io::ErrorKind::NotFound => {
+                McpToolError::new(ErrorCode::FileNotFound, format!("{} does not exist", path.display()))
+            }
+            _ => McpToolError::from(e),
+        })?;
+        if self.config.profile == SandboxProfile::Off
+            || self.writable_roots.iter().any(|root| resolved.starts_with(root))
+        {
+            Ok(resolved)
+        } else {
+            Err(McpToolError::new(
+                ErrorCode::SandboxViolation,
+                format!("Reading {} is not allowed: the path is outside the working directory", path.display()),
+            ))
+        }
+    }
+
+    /// Runs `command` to completion inside the sandbox and collects its output.
+    pub async fn output(&self, command: &mut tokio::process::Command) -> io::Result<Output> {
+        if self.config.profile == SandboxProfile::Off {
+            return command.output().await;
+        }
+        let (child, _guard) = self.spawn_confined(command)?;
+        // `_guard` kills anything the command left running once it is dropped here.
+        child.wait_with_output().await
+    }
+
+    /// Like [`Sandbox::output`], but keeps at most `limit` bytes of stdout and
+    /// of stderr. The rest is read and discarded so the child never blocks on
+    /// a full pipe.
+    pub async fn output_capped(&self, command: &mut tokio::process::Command, limit: usize) -> io::Result<CappedOutput> {
+        let (mut child, _guard) = if self.config.profile == SandboxProfile::Off {
+            let child = command
+                .kill_on_drop(true)
+                .stdout(std::process::Stdio::piped())
+                .stderr(std::process::Stdio::piped())
+                .spawn()?;
+            (child, ProcessGuard::default())
+        } else {
+            self.spawn_confined(command)?
+        };
+        let stdout = child.stdout.take();
+        let stderr = child.stderr.take();
+        let ((stdout, stdout_truncated), (stderr, stderr_truncated), status) =
+            tokio::try_join!(read_capped(stdout, limit), read_capped(stderr, limit), child.wait())?;
+        Ok(CappedOutput {
+            status,
+            stdout,
+            stderr,
+            stdout_truncated,
+            stderr_truncated,
+        })
+    }
+
+    // Starts `command` confined by the active profile, with piped output. It
+    // runs in the working directory unless the caller chose another one.
+    fn spawn_confined(&self, command: &mut tokio::process::Command) -> io::Result<(tokio::process::Child, ProcessGuard)> {
+        if let (true, Some(reason)) = (self.config.strict, self.unavailable.first()) {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{}; not running commands because MCP_SANDBOX_STRICT is set", reason),
+            ));
+        }
+        if command.as_std().get_current_dir().is_none() {
+            command.current_dir(&self.config.workdir);
+        }
+        command.kill_on_drop(true);
+
+        #[cfg(target_os = "linux")]
+        {
+            let profile = self.config.profile;
+            let writable = match profile {
+                SandboxProfile::Workdir => self.writable_roots.clone(),
+                _ => Vec::new(),
+            };
+            let mut ruleset = Some(linux::filesystem_ruleset(&writable, self.config.strict)?);
+            let network_filter = self.network_filter.clone();
+            // SAFETY: the ruleset and seccomp program are built before fork, so
+            // the child only makes the syscalls that confine it.
+            unsafe {
+                command.pre_exec(move || {
+                    if let Some(ruleset) = ruleset.take() {
+                        ruleset.restrict_self().map_err(io::Error::other)?;
+                    }
+                    if let Some(filter) = &network_filter {
+                        seccompiler::apply_filter(filter).map_err(io::Error::other)?;
+                    }
+                    Ok(())
+                });
+            }
+        }
+
+        // Held until the job is assigned, so the command can't start a
+        // process outside it. On failure the child is dropped, which kills it.
+        #[cfg(windows)]
+        command.creation_flags(windows::CREATE_SUSPENDED);
+
+        let child = command
+            .stdout(std::process::Stdio::piped())
+            .stderr(std::process::Stdio::piped())
+            .spawn()?;
+
+        #[cfg(windows)]
+        let guard = {
+            let (Some(handle), Some(process_id)) = (child.raw_handle(), child.id()) else {
+                return Err(io::Error::other("Command exited before it was started"));
+            };
+            let job = windows::assign_job(handle, self.config.max_processes)?;
+            windows::resume(process_id)?;
+            ProcessGuard { _job: Some(job) }
+        };
+        #[cfg(not(windows))]
+        let guard = ProcessGuard::default();
+        Ok((child, guard))
+    }
+}
+
+/// Output of [`Sandbox::output_capped`].
+#[derive(Debug)]
+pub struct CappedOutput {
+    pub status: ExitStatus,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+}
+
+// Keeps platform confinement alive while a child runs. On Windows, dropping
+// it closes the job object, which kills whatever the command left running.
+#[derive(Default)]
+struct ProcessGuard {
+    #[cfg(windows)]
+    _job: Option<windows::OwnedHandle>,
+}
+
+async fn read_capped<R: AsyncRead + Unpin>(reader: Option<R>, limit: usize) -> io::Result<(Vec<u8>, bool)> {
+    let Some(mut reader) = reader else {
+        return Ok((Vec::new(), false));
+    };
+    let mut kept = Vec::new();
+    (&mut reader).take(limit as u64).read_to_end(&mut kept).await?;
+    let discarded = tokio::io::copy(&mut reader, &mut tokio::io::sink()).await?;
+    Ok((kept, discarded > 0))
+}
+
+/// Canonicalizes `path`, which may not exist yet: its nearest existing
+/// ancestor is canonicalized and the missing components are appended. A
+/// symlink counts as existing even when its target doesn't, so a dangling
+/// link fails here instead of being written through to wherever it points.
+pub fn resolve_nearest(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path;
+    let mut rest = Vec::new();
+    while matches!(existing.symlink_metadata(), Err(e) if e.kind() == io::ErrorKind::NotFound) {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("{} cannot be resolved", path.display()),
+                ))
+            }
+        }
+    }
+    let mut resolved = existing.canonicalize().map_err(|e| match e.kind() {
+        io::ErrorKind::NotFound => io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} is a symlink to a path that does not exist", existing.display()),
+        ),
+        _ => e,
+    })?;
+    for name in rest.iter().rev() {
+        resolved.push(name);
+    }
+    Ok(resolved)
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::collections::BTreeMap;
+    use std::io;
+    use std::path::PathBuf;
+
+    use landlock::{
+        path_beneath_rules, Access, AccessFs, CompatLevel, Compatible, Ruleset, RulesetAttr, RulesetCreated,
+        RulesetCreatedAttr, ABI,
+    };
+    use seccompiler::{
+        BpfProgram, SeccompAction, SeccompCmpArgLen, SeccompCmpOp, SeccompCondition, SeccompFilter,
+        SeccompRule,
+    };
+
+    const LANDLOCK_ABI: ABI = ABI::V2;
+
+    /// Fails unless the kernel supports every Landlock restriction we use.
+    pub fn filesystem_support() -> io::Result<()> {
+        Ruleset::default()
+            .set_compatibility(CompatLevel::HardRequirement)
+            .handle_access(AccessFs::from_all(LANDLOCK_ABI))
+            .and_then(|ruleset| ruleset.create())
+            .map(drop)
+            .map_err(io::Error::other)
+    }
+
+    /// Ruleset making the filesystem read-only except for `writable` (and
+    /// /dev/null). Kernels without Landlock leave the child unconfined unless
+    /// `strict` is set, which makes confining the child fail instead.
+    pub fn filesystem_ruleset(writable: &[PathBuf], strict: bool) -> io::Result<RulesetCreated> {
+        let level = if strict { CompatLevel::HardRequirement } else { CompatLevel::BestEffort };
+        Ruleset::default()
+            .set_compatibility(level)
+            .handle_access(AccessFs::from_all(LANDLOCK_ABI))
+            .and_then(|ruleset| ruleset.create())
+            .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(["/"], AccessFs::from_read(LANDLOCK_ABI))))
+            .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(["/dev/null"], AccessFs::from_all(LANDLOCK_ABI))))
+            .and_then(|ruleset| ruleset.add_rules(path_beneath_rules(writable, AccessFs::from_all(LANDLOCK_ABI))))
+            .map_err(io::Error::other)
+    }
+
+    /// Seccomp filter that refuses to create IPv4/IPv6 sockets, so children
+    /// can neither reach the network nor listen on a port. Unix sockets are
+    /// refused too, since they reach local daemons such as Docker's, and so are
+    /// netlink and packet sockets. `socketpair` still works, so pipes between
+    /// a command's own processes are unaffected.
+    pub fn network_filter() -> io::Result<BpfProgram> {
+        let domain_is = |domain: i32| {
+            SeccompCondition::new(0, SeccompCmpArgLen::Dword, SeccompCmpOp::Eq, domain as u64)
+                .and_then(|condition| SeccompRule::new(vec![condition]))
+                .map_err(io::Error::other)
+        };
+        let rules = BTreeMap::from([(
+            libc::SYS_socket,
+            vec![
+                domain_is(libc::AF_INET)?,
+                domain_is(libc::AF_INET6)?,
+                domain_is(libc::AF_UNIX)?,
+                domain_is(libc::AF_NETLINK)?,
+                domain_is(libc::AF_PACKET)?,
+            ],
+        )]);
+        let arch = std::env::consts::ARCH.try_into().map_err(io::Error::other)?;
+        let filter = SeccompFilter::new(
+            rules,
+            SeccompAction::Allow,
+            SeccompAction::Errno(libc::EPERM as u32),
+            arch,
+        )
+        .map_err(io::Error::other)?;
+        filter.try_into().map_err(io::Error::other)
+    }
+}
+
+#[cfg(windows)]
+mod windows {
+    use std::io;
+    use std::os::windows::io::RawHandle;
+
+    use windows_sys::Win32::Foundation::{CloseHandle, FALSE, HANDLE, INVALID_HANDLE_VALUE};
+    use windows_sys::Win32::System::Diagnostics::ToolHelp::{
+        CreateToolhelp32Snapshot, Thread32First, Thread32Next, TH32CS_SNAPTHREAD, THREADENTRY32,
+    };
+    use windows_sys::Win32::System::Threading::{OpenThread, ResumeThread, THREAD_SUSPEND_RESUME};
+    use windows_sys::Win32::System::JobObjects::{
+        AssignProcessToJobObject, CreateJobObjectW, JobObjectExtendedLimitInformation,
+        SetInformationJobObject, JOBOBJECT_EXTENDED_LIMIT_INFORMATION,
+        JOB_OBJECT_LIMIT_ACTIVE_PROCESS, JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION,
+        JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE,
+    };
+
+    pub use windows_sys::Win32::System::Threading::CREATE_SUSPENDED;
+
+    /// Closes the handle when dropped. Closing a job kills every process
+    /// still in it.
+    pub struct OwnedHandle(HANDLE);
+
+    impl Drop for OwnedHandle {
+        fn drop(&mut self) {
+            unsafe {
+                CloseHandle(self.0);
+            }
+        }
+    }
+
+    // SAFETY: a job handle may be used and closed from any thread.
+    unsafe impl Send for OwnedHandle {}
+
+    pub fn assign_job(process: RawHandle, max_processes: u32) -> io::Result<OwnedHandle> {
+        unsafe {
+            let job = CreateJobObjectW(std::ptr::null(), std::ptr::null());
+            if job.is_null() {
+                return Err(io::Error::last_os_error());
+            }
+            let guard = OwnedHandle(job);
+
+            let mut info: JOBOBJECT_EXTENDED_LIMIT_INFORMATION = std::mem::zeroed();
+            info.BasicLimitInformation.LimitFlags = JOB_OBJECT_LIMIT_KILL_ON_JOB_CLOSE
+                | JOB_OBJECT_LIMIT_ACTIVE_PROCESS
+                | JOB_OBJECT_LIMIT_DIE_ON_UNHANDLED_EXCEPTION;
+            info.BasicLimitInformation.ActiveProcessLimit = max_processes;
+            if SetInformationJobObject(
+                job,
+                JobObjectExtendedLimitInformation,
+                &info as *const _ as *const std::ffi::c_void,
+                std::mem::size_of::<JOBOBJECT_EXTENDED_LIMIT_INFORMATION>() as u32,
+            ) == 0
+            {
+                return Err(io::Error::last_os_error());
+            }
+            if AssignProcessToJobObject(job, process as HANDLE) == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(guard)
+        }
+    }
+
+    /// Resumes a process started with `CREATE_SUSPENDED`. std doesn't keep the
+    /// main thread's handle, so its threads are found with a snapshot.
+    pub fn resume(process_id: u32) -> io::Result<()> {
+        let mut threads = Vec::new();
+        unsafe {
+            let snapshot = CreateToolhelp32Snapshot(TH32CS_SNAPTHREAD, 0);
+            if snapshot == INVALID_HANDLE_VALUE {
+                return Err(io::Error::last_os_error());
+            }
+            let _snapshot = OwnedHandle(snapshot);
+            let mut entry: THREADENTRY32 = std::mem::zeroed();
+            entry.dwSize = std::mem::size_of::<THREADENTRY32>() as u32;
+            let mut more = Thread32First(snapshot, &mut entry) != 0;
+            while more {
+                if entry.th32OwnerProcessID == process_id {
+                    threads.push(entry.th32ThreadID);
+                }
+                more = Thread32Next(snapshot, &mut entry) != 0;
+            }
+        }
+        if threads.is_empty() {
+            return Err(io::Error::other(format!("Process {} has no thread to resume", process_id)));
+        }
+        for thread_id in threads {
+            unsafe {
+                let thread = OpenThread(THREAD_SUSPEND_RESUME, FALSE, thread_id);
+                if thread.is_null() {
+                    return Err(io::Error::last_os_error());
+                }
+                let _thread = OwnedHandle(thread);
+                if ResumeThread(thread) == u32::MAX {
+                    return Err(io::Error::last_os_error());
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn workdir_sandbox(workdir: &Path) -> Sandbox {
+        Sandbox::new(SandboxConfig {
+            profile: SandboxProfile::Workdir,
+            workdir: workdir.to_path_buf(),
+            extra_writable: Vec::new(),
+            allow_network: true,
+            max_processes: DEFAULT_MAX_PROCESSES,
+            strict: false,
+        })
+    }
+
+    #[test]
+    fn test_resolve_nearest() {
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        assert_eq!(resolve_nearest(&root.join("a/b/new.txt")).unwrap(), root.join("a/b/new.txt"));
+        std::fs::create_dir(root.join("a")).unwrap();
+        assert_eq!(resolve_nearest(&root.join("a/../new.txt")).unwrap(), root.join("new.txt"));
+        // `..` can't be resolved past a directory that doesn't exist.
+        let e = resolve_nearest(&root.join("missing/../../escape.txt")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_write_path_refuses_symlink_escapes() {
+        let dir = TempDir::new().unwrap();
+        let outside = TempDir::new().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        let sandbox = workdir_sandbox(&root);
+        assert_eq!(sandbox.check_write_path(Path::new("new.txt")).unwrap(), root.join("new.txt"));
+
+        // A link to a directory outside the workdir.
+        std::os::unix::fs::symlink(outside.path(), root.join("out")).unwrap();
+        let e = sandbox.check_write_path(&root.join("out/file.txt")).unwrap_err();
+        assert_eq!(e.code, ErrorCode::SandboxViolation);
+
+        // A dangling link whose target is outside: writing would create it there.
+        std::os::unix::fs::symlink(outside.path().join("planted.txt"), root.join("dangling")).unwrap();
+        let e = sandbox.check_write_path(&root.join("dangling")).unwrap_err();
+        assert_eq!(e.code, ErrorCode::SandboxViolation);
+        assert!(!outside.path().join("planted.txt").exists());
+    }
+}
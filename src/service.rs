@@ -0,0 +1,304 @@
+//! Registers the server with the operating system's service manager so it
+//! starts at login and is restarted after a crash: a systemd user unit on
+//! Linux, a launchd agent on macOS and a Task Scheduler task on Windows.
+//!
+//! The binary does not implement the Windows service control protocol, so it
+//! cannot run under the Service Control Manager; a logon task gives the same
+//! survive-a-reboot behaviour without it.
+
+#[cfg(unix)]
+use std::fs;
+#[cfg(unix)]
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+
+use mcp_common::runtime_paths;
+
+/// Server options whose values are paths; services don't start in the
+/// caller's working directory, so these are made absolute.
+const PATH_FLAGS: &[&str] = &["--tls-cert", "--tls-key"];
+
+/// `copilot_mcp_tool` for the default instance, `copilot_mcp_tool-<name>` otherwise.
+pub fn service_name() -> String {
+    let instance = runtime_paths::instance();
+    if instance == runtime_paths::DEFAULT_INSTANCE {
+        crate::install::DEFAULT_SERVER_NAME.to_string()
+    } else {
+        format!("{}-{}", crate::install::DEFAULT_SERVER_NAME, instance)
+    }
+}
+
+// The command line the service manager runs: `server --instance <name> <args>`,
+// with `--log-dir` when one is configured.
+fn server_command(server_args: &[String]) -> Result<Vec<String>> {
+    let mut command = vec![
+        std::env::current_exe()?.to_string_lossy().into_owned(),
+        "server".to_string(),
+        "--instance".to_string(),
+        runtime_paths::instance().to_string(),
+    ];
+    if let Some(dir) = runtime_paths::configured_log_dir() {
+        command.extend(["--log-dir".to_string(), dir.to_string_lossy().into_owned()]);
+    }
+    let mut iter = server_args.iter();
+    while let Some(arg) = iter.next() {
+        command.push(arg.clone());
+        if PATH_FLAGS.contains(&arg.as_str()) {
+            if let Some(path) = iter.next() {
+                command.push(std::path::absolute(path)?.to_string_lossy().into_owned());
+            }
+        }
+    }
+    Ok(command)
+}
+
+fn run(program: &str, args: &[&str]) -> Result<()> {
+    let output = Command::new(program)
+        .args(args)
+        .output()
+        .with_context(|| format!("Failed to run {}", program))?;
+    if !output.status.success() {
+        return Err(anyhow!(
+            "{} {} failed: {}",
+            program,
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes the service definition, enables it and starts it. Returns the
+/// definition's path, or the task name on Windows.
+pub fn install(server_args: &[String]) -> Result<PathBuf> {
+    let command = server_command(server_args)?;
+    platform::install(&service_name(), &command)
+}
+
+/// Stops and removes the service. Returns its path and whether it existed.
+pub fn uninstall() -> Result<(PathBuf, bool)> {
+    platform::uninstall(&service_name())
+}
+
+#[cfg(unix)]
+fn write_definition(path: &Path, contents: &str) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, contents).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod platform {
+    use super::*;
+
+    fn unit_path(name: &str) -> Result<PathBuf> {
+        Ok(dirs::config_dir()
+            .context("Failed to get config directory")?
+            .join("systemd")
+            .join("user")
+            .join(format!("{}.service", name)))
+    }
+
+    // systemd splits ExecStart on whitespace and treats quotes and backslashes specially.
+    fn quote(arg: &str) -> String {
+        if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || matches!(c, '"' | '\\' | '\'' | '%' | '$')) {
+            return arg.to_string();
+        }
+        let escaped = arg
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('%', "%%")
+            .replace('$', "$$");
+        format!("\"{}\"", escaped)
+    }
+
+    fn unit(command: &[String]) -> String {
+        let exec: Vec<String> = command.iter().map(|arg| quote(arg)).collect();
+        format!(
+            "[Unit]\n\
+             Description=Copilot MCP tool server ({instance})\n\
+             After=network-online.target\n\
+             \n\
+             [Service]\n\
+             ExecStart={exec}\n\
+             Restart=on-failure\n\
+             RestartSec=5\n\
+             \n\
+             [Install]\n\
+             WantedBy=default.target\n",
+            instance = runtime_paths::instance(),
+            exec = exec.join(" "),
+        )
+    }
+
+    pub fn install(name: &str, command: &[String]) -> Result<PathBuf> {
+        let path = unit_path(name)?;
+        write_definition(&path, &unit(command))?;
+        let unit_name = format!("{}.service", name);
+        run("systemctl", &["--user", "daemon-reload"])?;
+        run("systemctl", &["--user", "enable", "--now", &unit_name])?;
+        Ok(path)
+    }
+
+    pub fn uninstall(name: &str) -> Result<(PathBuf, bool)> {
+        let path = unit_path(name)?;
+        if !path.exists() {
+            return Ok((path, false));
+        }
+        let unit_name = format!("{}.service", name);
+        // The unit may already be stopped or disabled by hand.
+        if let Err(e) = run("systemctl", &["--user", "disable", "--now", &unit_name]) {
+            tracing::warn!("{}", e);
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        run("systemctl", &["--user", "daemon-reload"])?;
+        Ok((path, true))
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod platform {
+    use super::*;
+
+    fn label(name: &str) -> String {
+        format!("com.github.jmikedupont2.{}", name)
+    }
+
+    fn plist_path(name: &str) -> Result<PathBuf> {
+        Ok(dirs::home_dir()
+            .context("Failed to get home directory")?
+            .join("Library")
+            .join("LaunchAgents")
+            .join(format!("{}.plist", label(name))))
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('&', "&amp;")
+            .replace('<', "&lt;")
+            .replace('>', "&gt;")
+            .replace('"', "&quot;")
+    }
+
+    fn plist(name: &str, command: &[String]) -> String {
+        let arguments: String = command
+            .iter()
+            .map(|arg| format!("        <string>{}</string>\n", escape(arg)))
+            .collect();
+        // launchd restarts the agent only when it exits with an error, so
+        // `copilot_mcp_tool stop` still stops it.
+        format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE plist PUBLIC "-//Apple//DTD PLIST 1.0//EN" "http://www.apple.com/DTDs/PropertyList-1.0.dtd">
+<plist version="1.0">
+<dict>
+    <key>Label</key>
+    <string>{label}</string>
+    <key>ProgramArguments</key>
+    <array>
+{arguments}    </array>
+    <key>RunAtLoad</key>
+    <true/>
+    <key>KeepAlive</key>
+    <dict>
+        <key>SuccessfulExit</key>
+        <false/>
+    </dict>
+</dict>
+</plist>
+"#,
+            label = escape(&label(name)),
+            arguments = arguments,
+        )
+    }
+
+    pub fn install(name: &str, command: &[String]) -> Result<PathBuf> {
+        let path = plist_path(name)?;
+        let target = path.to_string_lossy().into_owned();
+        if path.exists() {
+            // Reloading picks up the new definition.
+            let _ = run("launchctl", &["unload", &target]);
+        }
+        write_definition(&path, &plist(name, command))?;
+        run("launchctl", &["load", "-w", &target])?;
+        Ok(path)
+    }
+
+    pub fn uninstall(name: &str) -> Result<(PathBuf, bool)> {
+        let path = plist_path(name)?;
+        if !path.exists() {
+            return Ok((path, false));
+        }
+        let target = path.to_string_lossy().into_owned();
+        if let Err(e) = run("launchctl", &["unload", "-w", &target]) {
+            tracing::warn!("{}", e);
+        }
+        fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        Ok((path, true))
+    }
+}
+
+#[cfg(windows)]
+mod platform {
+    use super::*;
+
+    // Quoting as parsed by the C runtime's command-line splitter.
+    fn quote(arg: &str) -> String {
+        if !arg.is_empty() && !arg.contains(|c: char| c.is_whitespace() || c == '"') {
+            return arg.to_string();
+        }
+        let mut quoted = String::from("\"");
+        let mut backslashes = 0;
+        for c in arg.chars() {
+            match c {
+                '\\' => backslashes += 1,
+                '"' => {
+                    quoted.push_str(&"\\".repeat(backslashes * 2 + 1));
+                    quoted.push('"');
+                    backslashes = 0;
+                }
+                _ => {
+                    quoted.push_str(&"\\".repeat(backslashes));
+                    quoted.push(c);
+                    backslashes = 0;
+                }
+            }
+        }
+        quoted.push_str(&"\\".repeat(backslashes * 2));
+        quoted.push('"');
+        quoted
+    }
+
+    fn task_exists(name: &str) -> bool {
+        Command::new("schtasks")
+            .args(["/Query", "/TN", name])
+            .output()
+            .map(|output| output.status.success())
+            .unwrap_or(false)
+    }
+
+    pub fn install(name: &str, command: &[String]) -> Result<PathBuf> {
+        let command_line: Vec<String> = command.iter().map(|arg| quote(arg)).collect();
+        let command_line = command_line.join(" ");
+        run(
+            "schtasks",
+            &["/Create", "/F", "/SC", "ONLOGON", "/RL", "LIMITED", "/TN", name, "/TR", &command_line],
+        )?;
+        run("schtasks", &["/Run", "/TN", name])?;
+        Ok(PathBuf::from(name))
+    }
+
+    pub fn uninstall(name: &str) -> Result<(PathBuf, bool)> {
+        if !task_exists(name) {
+            return Ok((PathBuf::from(name), false));
+        }
+        // Ending the task fails harmlessly when it isn't running.
+        let _ = run("schtasks", &["/End", "/TN", name]);
+        run("schtasks", &["/Delete", "/F", "/TN", name])?;
+        Ok((PathBuf::from(name), true))
+    }
+}
@@ -0,0 +1,268 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::CallToolResult;
+use schemars::JsonSchema;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+/// Key used in the `experimental` capabilities map to negotiate session resumption.
+pub const SESSION_CAPABILITY: &str = "session";
+
+/// How long a disconnected session is kept before its buffered results are dropped.
+pub const DEFAULT_GRACE_PERIOD: Duration = Duration::from_secs(120);
+
+/// Upper bound on results buffered for a single detached session.
+const MAX_PENDING_RESULTS: usize = 256;
+
+/// Context variables a session may hold.
+const MAX_CONTEXT_VARIABLES: usize = 64;
+
+/// Context variable holding the directory relative file paths resolve against.
+pub const CWD_VARIABLE: &str = "cwd";
+
+/// A tool result that completed while its session had no live connection.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PendingResult {
+    pub tool: String,
+    pub result: CallToolResult,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PendingResultsOutput {
+    pub results: Vec<PendingResult>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct SessionContextOutput {
+    /// The session's context variables (only the one asked for, if any).
+    pub context: Map<String, Value>,
+}
+
+#[derive(Debug)]
+struct SessionEntry {
+    // None while a connection is attached.
+    detached_at: Option<Instant>,
+    pending: Vec<PendingResult>,
+    // Variables tools share across calls; kept when the session is resumed.
+    context: Map<String, Value>,
+}
+
+/// Short-lived cache of client sessions keyed by token.
+pub struct SessionCache {
+    grace_period: Duration,
+    sessions: Mutex<HashMap<String, SessionEntry>>,
+}
+
+impl SessionCache {
+    pub fn new(grace_period: Duration) -> Self {
+        Self {
+            grace_period,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn grace_period(&self) -> Duration {
+        self.grace_period
+    }
+
+    /// Attaches a connection to a session. Resumes `resume_token` if it is
+    /// detached and still within its grace period, otherwise opens a fresh
+    /// session; a session with a live connection is never taken over.
+    /// Returns the session token and whether it was resumed.
+    pub fn open(&self, resume_token: Option<&str>) -> (String, bool) {
+        let mut sessions = self.sessions.lock().unwrap();
+        if let Some(token) = resume_token {
+            if let Some(entry) = sessions.get_mut(token) {
+                if entry.detached_at.is_none() {
+                    tracing::warn!("Not resuming session {}: it is still connected", token);
+                } else if !Self::is_expired(entry, self.grace_period) {
+                    entry.detached_at = None;
+                    tracing::info!("Resumed session {} ({} pending results)", token, entry.pending.len());
+                    return (token.to_string(), true);
+                } else {
+                    sessions.remove(token);
+                }
+            }
+        }
+
+        let token = uuid::Uuid::new_v4().to_string();
+        sessions.insert(
+            token.clone(),
+            SessionEntry {
+                detached_at: None,
+                pending: Vec::new(),
+                context: Map::new(),
+            },
+        );
+        (token, false)
+    }
+
+    /// Marks a session as disconnected; it will be reaped after the grace period.
+    pub fn detach(&self, token: &str) {
+        if let Some(entry) = self.sessions.lock().unwrap().get_mut(token) {
+            entry.detached_at = Some(Instant::now());
+        }
+    }
+
+    /// Buffers a completed result if the session is currently detached.
+    pub fn record_result(&self, token: &str, tool: &str, result: &CallToolResult) {
+        let mut sessions = self.sessions.lock().unwrap();
+        let Some(entry) = sessions.get_mut(token) else {
+            return;
+        };
+        if entry.detached_at.is_none() {
+            return;
+        }
+        if entry.pending.len() >= MAX_PENDING_RESULTS {
+            entry.pending.remove(0);
+        }
+        entry.pending.push(PendingResult {
+            tool: tool.to_string(),
+            result: result.clone(),
+        });
+    }
+
+    /// Drains results buffered for a session.
+    pub fn take_pending(&self, token: &str) -> Vec<PendingResult> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get_mut(token)
+            .map(|entry| std::mem::take(&mut entry.pending))
+            .unwrap_or_default()
+    }
+
+    /// Sets a context variable of a session; `null` removes it.
+    pub fn set_context(&self, token: &str, key: &str, value: Value) -> Result<(), McpToolError> {
+        let mut sessions = self.sessions.lock().unwrap();
+        let entry = sessions
+            .get_mut(token)
+            .ok_or_else(|| McpToolError::new(ErrorCode::Internal, "Session is no longer open"))?;
+        if value.is_null() {
+            entry.context.remove(key);
+            return Ok(());
+        }
+        if !entry.context.contains_key(key) && entry.context.len() >= MAX_CONTEXT_VARIABLES {
+            return Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("A session holds at most {} context variables", MAX_CONTEXT_VARIABLES),
+            ));
+        }
+        entry.context.insert(key.to_string(), value);
+        Ok(())
+    }
+
+    /// A session's context variables; empty for unknown sessions.
+    pub fn context(&self, token: &str) -> Map<String, Value> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(token)
+            .map(|entry| entry.context.clone())
+            .unwrap_or_default()
+    }
+
+    /// One context variable of a session, if set.
+    pub fn context_variable(&self, token: &str, key: &str) -> Option<Value> {
+        self.sessions.lock().unwrap().get(token)?.context.get(key).cloned()
+    }
+
+    /// Drops detached sessions whose grace period has elapsed.
+    pub fn reap_expired(&self) {
+        let grace_period = self.grace_period;
+        self.sessions
+            .lock()
+            .unwrap()
+            .retain(|_, entry| !Self::is_expired(entry, grace_period));
+    }
+
+    /// Periodically reaps expired sessions for the lifetime of the server.
+    pub fn spawn_reaper(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(self.grace_period / 4);
+            loop {
+                interval.tick().await;
+                self.reap_expired();
+            }
+        });
+    }
+
+    fn is_expired(entry: &SessionEntry, grace_period: Duration) -> bool {
+        entry
+            .detached_at
+            .is_some_and(|detached_at| detached_at.elapsed() > grace_period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rmcp::model::Content;
+
+    fn result(text: &str) -> CallToolResult {
+        CallToolResult::success(vec![Content::text(text)])
+    }
+
+    #[test]
+    fn test_results_are_buffered_while_detached() {
+        let sessions = SessionCache::new(DEFAULT_GRACE_PERIOD);
+        let (token, resumed) = sessions.open(None);
+        assert!(!resumed);
+
+        // A live connection gets its results directly.
+        sessions.record_result(&token, "get_time", &result("now"));
+        assert!(sessions.take_pending(&token).is_empty());
+
+        sessions.detach(&token);
+        sessions.record_result(&token, "get_time", &result("later"));
+        sessions.set_context(&token, CWD_VARIABLE, Value::from("/tmp")).unwrap();
+        assert_eq!(sessions.open(Some(&token)), (token.clone(), true));
+
+        let pending = sessions.take_pending(&token);
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].tool, "get_time");
+        assert_eq!(pending[0].result, result("later"));
+        assert!(sessions.take_pending(&token).is_empty());
+        assert_eq!(sessions.context_variable(&token, CWD_VARIABLE), Some(Value::from("/tmp")));
+    }
+
+    #[test]
+    fn test_connected_session_is_not_taken_over() {
+        let sessions = SessionCache::new(DEFAULT_GRACE_PERIOD);
+        let (token, _) = sessions.open(None);
+        let (other, resumed) = sessions.open(Some(&token));
+        assert!(!resumed);
+        assert_ne!(other, token);
+    }
+
+    #[test]
+    fn test_expired_session_is_not_resumed() {
+        let sessions = SessionCache::new(Duration::from_millis(1));
+        let (token, _) = sessions.open(None);
+        sessions.detach(&token);
+        sessions.record_result(&token, "get_time", &result("lost"));
+        std::thread::sleep(Duration::from_millis(20));
+
+        let (other, resumed) = sessions.open(Some(&token));
+        assert!(!resumed);
+        assert_ne!(other, token);
+        assert!(sessions.take_pending(&token).is_empty());
+    }
+
+    #[test]
+    fn test_pending_results_are_capped() {
+        let sessions = SessionCache::new(DEFAULT_GRACE_PERIOD);
+        let (token, _) = sessions.open(None);
+        sessions.detach(&token);
+        for i in 0..MAX_PENDING_RESULTS + 1 {
+            sessions.record_result(&token, &i.to_string(), &result("done"));
+        }
+        let pending = sessions.take_pending(&token);
+        assert_eq!(pending.len(), MAX_PENDING_RESULTS);
+        // The oldest result is the one dropped.
+        assert_eq!(pending[0].tool, "1");
+    }
+}
@@ -0,0 +1,133 @@
+use std::env;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::{watch, Notify};
+
+const DEFAULT_DRAIN_TIMEOUT_SECS: u64 = 30;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Phase {
+    Running,
+    // New tool calls are refused while in-flight ones finish.
+    Draining,
+    // Connections should close now.
+    Closed,
+}
+
+/// Coordinates a graceful stop: refuse new tool calls, let in-flight ones
+/// finish, then close every connection.
+pub struct Shutdown {
+    phase: watch::Sender<Phase>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+/// Counts one tool call as in flight until dropped.
+pub struct CallGuard {
+    shutdown: Arc<Shutdown>,
+}
+
+impl Drop for CallGuard {
+    fn drop(&mut self) {
+        if self.shutdown.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.shutdown.idle.notify_waiters();
+        }
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            phase: watch::channel(Phase::Running).0,
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+
+    pub fn is_draining(&self) -> bool {
+        *self.phase.borrow() != Phase::Running
+    }
+
+    /// Marks a tool call as started, or returns `None` once shutdown has begun.
+    pub fn track(self: &Arc<Self>) -> Option<CallGuard> {
+        // Counted before checking, so `drain` can't miss a call that slips in.
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        let guard = CallGuard { shutdown: self.clone() };
+        (!self.is_draining()).then_some(guard)
+    }
+
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Stops new calls and waits up to `timeout` for the running ones.
+    /// Returns whether everything finished in time.
+    pub async fn drain(&self, timeout: Duration) -> bool {
+        self.phase.send_replace(Phase::Draining);
+        let idle = async {
+            loop {
+                let notified = self.idle.notified();
+                if self.in_flight() == 0 {
+                    return;
+                }
+                notified.await;
+            }
+        };
+        tokio::time::timeout(timeout, idle).await.is_ok()
+    }
+
+    /// Tells every connection to close.
+    pub fn close(&self) {
+        self.phase.send_replace(Phase::Closed);
+    }
+
+    /// Resolves once `close` has been called.
+    pub async fn closed(&self) {
+        let mut phase = self.phase.subscribe();
+        let _ = phase.wait_for(|phase| *phase == Phase::Closed).await;
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// How long `stop` lets in-flight calls run: `MCP_SHUTDOWN_TIMEOUT_SECS`
+/// (default 30).
+pub fn drain_timeout() -> Duration {
+    Duration::from_secs(
+        env::var("MCP_SHUTDOWN_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_DRAIN_TIMEOUT_SECS),
+    )
+}
+
+/// Resolves when the process is asked to stop: SIGTERM (sent by `stop`) or
+/// SIGINT on Unix, Ctrl-C elsewhere.
+pub async fn signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = terminate.recv() => {}
+                    _ = tokio::signal::ctrl_c() => {}
+                }
+            }
+            Err(e) => {
+                tracing::warn!("Cannot listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
@@ -2,7 +2,26 @@ use rmcp::model::CallToolResult;
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 use async_trait::async_trait;
+use mcp_errors::{ErrorCode, McpToolError};
 
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::sandbox::Sandbox;
+
+const BYTES_PER_KB: u64 = 1024;
+const BYTES_PER_GB: u64 = 1024 * 1024 * 1024;
+
+const DEFAULT_EXEC_TIMEOUT_SECS: u64 = 60;
+const DEFAULT_EXEC_MAX_OUTPUT_BYTES: usize = 1024 * 1024;
+// Enough for most programs to find their tools, locale and temp dir.
+const DEFAULT_EXEC_ENV: &[&str] = &[
+    "PATH", "HOME", "USER", "LANG", "LC_ALL", "TERM", "TMPDIR", "TEMP", "TMP", "SYSTEMROOT", "USERPROFILE", "PATHEXT",
+];
 
 // --- Input/Output Structs for SystemCommand Trait ---
 
@@ -11,6 +30,12 @@ pub struct KillProcessInput {
     pub pid: u32,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct KillProcessOutput {
+    pub pid: u32,
+    pub message: String,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct ProcessInfo {
     pub pid: u32,
@@ -23,6 +48,65 @@ pub struct ProcessInfo {
     // Add more fields as needed
 }
 
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetProcessInfoInput {
+    pub pid: u32,
+    /// Include the environment, which may contain secrets.
+    #[serde(default)]
+    pub include_environment: bool,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ProcessDetails {
+    pub pid: u32,
+    pub parent_pid: Option<u32>,
+    pub name: String,
+    pub status: String,
+    pub command_line: Vec<String>,
+    pub executable: Option<String>,
+    pub cwd: Option<String>,
+    /// RFC 3339 start time.
+    pub start_time: Option<String>,
+    pub run_time_secs: u64,
+    pub cpu_usage: f32,
+    pub memory_usage_kb: u64,
+    pub virtual_memory_usage_kb: u64,
+    pub children: Vec<u32>,
+    /// Number of open file descriptors or handles, where the platform reports it.
+    pub open_file_count: Option<u64>,
+    /// Targets of the open file descriptors (Linux only, first 256).
+    pub open_files: Option<Vec<String>>,
+    /// `NAME=value` entries, when requested.
+    pub environment: Option<Vec<String>>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct GetProcessTreeInput {
+    /// Process at the top of the tree (default: every process without a parent).
+    pub pid: Option<u32>,
+    /// Levels below the top to include (default: all).
+    pub max_depth: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ProcessTreeNode {
+    pub pid: u32,
+    pub name: String,
+    pub command_line: String,
+    pub cpu_usage: f32,
+    pub memory_usage_kb: u64,
+    /// This process and all of its descendants, including any below `max_depth`.
+    pub subtree_cpu_usage: f32,
+    pub subtree_memory_usage_kb: u64,
+    pub subtree_processes: usize,
+    pub children: Vec<ProcessTreeNode>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ProcessTreeOutput {
+    pub roots: Vec<ProcessTreeNode>,
+}
+
 #[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
 pub struct ListProcessesOutput {
     pub processes: Vec<ProcessInfo>,
@@ -69,6 +153,84 @@ pub struct ListPortsOutput {
     pub connections: Vec<PortConnection>,
 }
 
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ExecuteCommandInput {
+    /// Program to run, looked up on PATH. It is not run through a shell.
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory, relative to the sandbox working directory.
+    pub cwd: Option<String>,
+    /// Extra environment variables; only names on the server's allowlist.
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+    /// Seconds before the command is killed; capped by the server's limit.
+    pub timeout_secs: Option<u64>,
+    /// Bytes kept of stdout and of stderr; capped by the server's limit.
+    pub max_output_bytes: Option<usize>,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct ExecuteCommandOutput {
+    /// None when the process was ended by a signal.
+    pub exit_code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+    pub stdout_truncated: bool,
+    pub stderr_truncated: bool,
+    pub duration_ms: u64,
+}
+
+/// Settings for `execute_command`, which is off unless `MCP_EXEC_ENABLED=1`.
+#[derive(Debug, Clone)]
+pub struct ExecConfig {
+    pub enabled: bool,
+    /// Default and maximum run time (`MCP_EXEC_TIMEOUT_SECS`, default 60).
+    pub timeout: Duration,
+    /// Default and maximum bytes kept per stream (`MCP_EXEC_MAX_OUTPUT_BYTES`, default 1 MiB).
+    pub max_output_bytes: usize,
+    /// Variables passed through from the server's environment and the only
+    /// ones callers may set (`MCP_EXEC_ENV_ALLOWLIST`, comma-separated).
+    pub env_allowlist: Vec<String>,
+}
+
+impl ExecConfig {
+    pub fn from_env() -> Self {
+        let enabled = matches!(env::var("MCP_EXEC_ENABLED").as_deref(), Ok("1") | Ok("true") | Ok("yes"));
+        let timeout = env::var("MCP_EXEC_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .filter(|&secs| secs > 0)
+            .unwrap_or(DEFAULT_EXEC_TIMEOUT_SECS);
+        let max_output_bytes = env::var("MCP_EXEC_MAX_OUTPUT_BYTES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_EXEC_MAX_OUTPUT_BYTES);
+        let env_allowlist = match env::var("MCP_EXEC_ENV_ALLOWLIST") {
+            Ok(list) => list
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(str::to_string)
+                .collect(),
+            Err(_) => DEFAULT_EXEC_ENV.iter().map(|name| name.to_string()).collect(),
+        };
+        Self {
+            enabled,
+            timeout: Duration::from_secs(timeout),
+            max_output_bytes,
+            env_allowlist,
+        }
+    }
+
+    fn allows_env(&self, name: &str) -> bool {
+        // Windows variable names are case-insensitive.
+        self.env_allowlist
+            .iter()
+            .any(|allowed| allowed == name || (cfg!(windows) && allowed.eq_ignore_ascii_case(name)))
+    }
+}
+
 // --- SystemCommand Trait Definition ---
 
 #[async_trait]
@@ -87,129 +249,536 @@ pub trait SystemCommand: Send + Sync + 'static {
 
     // List all open network ports and connections
     async fn list_ports(&self) -> CallToolResult;
+
+    // Run a program with a timeout and capped output; `cancel` kills it early
+    async fn execute_command(&self, input: ExecuteCommandInput, cancel: CancellationToken) -> CallToolResult;
+
+    // Show processes as a parent/child tree with per-subtree totals
+    async fn get_process_tree(&self, input: GetProcessTreeInput) -> CallToolResult;
+
+    // Detail one process: command line, start time, children, open files
+    async fn get_process_info(&self, input: GetProcessInfoInput) -> CallToolResult;
 }
 
 // --- LibSystemCommand Implementation (using sysinfo, netstat2) ---
 pub struct LibSystemCommand;
 
+impl LibSystemCommand {
+    fn memory_usage() -> MemoryUsageOutput {
+        let mut system = sysinfo::System::new();
+        system.refresh_memory();
+        MemoryUsageOutput {
+            total_memory_kb: system.total_memory() / BYTES_PER_KB,
+            used_memory_kb: system.used_memory() / BYTES_PER_KB,
+            free_memory_kb: system.free_memory() / BYTES_PER_KB,
+            available_memory_kb: system.available_memory() / BYTES_PER_KB,
+            swap_total_kb: system.total_swap() / BYTES_PER_KB,
+            swap_used_kb: system.used_swap() / BYTES_PER_KB,
+        }
+    }
+
+    fn disk_usage() -> DiskUsageOutput {
+        let disks = sysinfo::Disks::new_with_refreshed_list();
+        DiskUsageOutput {
+            disks: disks
+                .list()
+                .iter()
+                .map(|disk| DiskUsageInfo {
+                    name: disk.name().to_string_lossy().into_owned(),
+                    total_space_gb: disk.total_space() / BYTES_PER_GB,
+                    available_space_gb: disk.available_space() / BYTES_PER_GB,
+                    file_system: disk.file_system().to_string_lossy().into_owned(),
+                    mount_point: disk.mount_point().display().to_string(),
+                })
+                .collect(),
+        }
+    }
+
+    // CPU usage is measured between two refreshes, so this blocks for
+    // sysinfo's minimum update interval.
+    fn process_snapshot() -> sysinfo::System {
+        use sysinfo::{ProcessRefreshKind, ProcessesToUpdate};
+
+        let mut system = sysinfo::System::new();
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::everything());
+        std::thread::sleep(sysinfo::MINIMUM_CPU_UPDATE_INTERVAL);
+        system.refresh_processes_specifics(ProcessesToUpdate::All, true, ProcessRefreshKind::everything().without_environ());
+        system
+    }
+
+    fn children_by_parent(system: &sysinfo::System) -> HashMap<u32, Vec<u32>> {
+        let mut children: HashMap<u32, Vec<u32>> = HashMap::new();
+        for (pid, process) in system.processes() {
+            // Threads show up as processes on Linux; they aren't children.
+            if process.thread_kind().is_some() {
+                continue;
+            }
+            if let Some(parent) = process.parent() {
+                children.entry(parent.as_u32()).or_default().push(pid.as_u32());
+            }
+        }
+        for pids in children.values_mut() {
+            pids.sort_unstable();
+        }
+        children
+    }
+
+    fn process_tree(input: GetProcessTreeInput) -> Result<ProcessTreeOutput, McpToolError> {
+        let system = Self::process_snapshot();
+        let children = Self::children_by_parent(&system);
+        let roots: Vec<u32> = match input.pid {
+            Some(pid) => {
+                if system.process(sysinfo::Pid::from_u32(pid)).is_none() {
+                    return Err(process_not_found(pid));
+                }
+                vec![pid]
+            }
+            None => {
+                let mut roots: Vec<u32> = system
+                    .processes()
+                    .iter()
+                    .filter(|(_, process)| process.thread_kind().is_none())
+                    .filter(|(_, process)| {
+                        process
+                            .parent()
+                            .is_none_or(|parent| system.process(parent).is_none())
+                    })
+                    .map(|(pid, _)| pid.as_u32())
+                    .collect();
+                roots.sort_unstable();
+                roots
+            }
+        };
+        let max_depth = input.max_depth.unwrap_or(usize::MAX);
+        let roots = roots
+            .into_iter()
+            .filter_map(|pid| Self::tree_node(&system, &children, pid, max_depth, &mut Vec::new()))
+            .collect();
+        Ok(ProcessTreeOutput { roots })
+    }
+
+    // `path` holds the ancestors being visited, guarding against pid reuse cycles.
+    fn tree_node(
+        system: &sysinfo::System,
+        children: &HashMap<u32, Vec<u32>>,
+        pid: u32,
+        depth: usize,
+        path: &mut Vec<u32>,
+    ) -> Option<ProcessTreeNode> {
+        if path.contains(&pid) {
+            return None;
+        }
+        let process = system.process(sysinfo::Pid::from_u32(pid))?;
+        path.push(pid);
+        let subtree: Vec<ProcessTreeNode> = children
+            .get(&pid)
+            .into_iter()
+            .flatten()
+            .filter_map(|&child| Self::tree_node(system, children, child, depth.saturating_sub(1), path))
+            .collect();
+        path.pop();
+
+        let memory_usage_kb = process.memory() / BYTES_PER_KB;
+        let cpu_usage = process.cpu_usage();
+        Some(ProcessTreeNode {
+            pid,
+            name: process.name().to_string_lossy().into_owned(),
+            command_line: process
+                .cmd()
+                .iter()
+                .map(|arg| arg.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" "),
+            cpu_usage,
+            memory_usage_kb,
+            subtree_cpu_usage: cpu_usage + subtree.iter().map(|child| child.subtree_cpu_usage).sum::<f32>(),
+            subtree_memory_usage_kb: memory_usage_kb + subtree.iter().map(|child| child.subtree_memory_usage_kb).sum::<u64>(),
+            subtree_processes: 1 + subtree.iter().map(|child| child.subtree_processes).sum::<usize>(),
+            // Totals above still count levels past `max_depth`.
+            children: if depth == 0 { Vec::new() } else { subtree },
+        })
+    }
+
+    fn process_info(input: GetProcessInfoInput) -> Result<ProcessDetails, McpToolError> {
+        let system = Self::process_snapshot();
+        let process = system
+            .process(sysinfo::Pid::from_u32(input.pid))
+            .ok_or_else(|| process_not_found(input.pid))?;
+        let children = Self::children_by_parent(&system).remove(&input.pid).unwrap_or_default();
+        let environment = input.include_environment.then(|| {
+            // The second refresh skipped the environment, so read it again here.
+            let mut system = sysinfo::System::new();
+            let pid = sysinfo::Pid::from_u32(input.pid);
+            system.refresh_processes_specifics(
+                sysinfo::ProcessesToUpdate::Some(&[pid]),
+                true,
+                sysinfo::ProcessRefreshKind::nothing().with_environ(sysinfo::UpdateKind::Always),
+            );
+            system
+                .process(pid)
+                .map(|process| process.environ().iter().map(|entry| entry.to_string_lossy().into_owned()).collect())
+                .unwrap_or_default()
+        });
+        Ok(ProcessDetails {
+            pid: input.pid,
+            parent_pid: process.parent().map(|parent| parent.as_u32()),
+            name: process.name().to_string_lossy().into_owned(),
+            status: process.status().to_string(),
+            command_line: process.cmd().iter().map(|arg| arg.to_string_lossy().into_owned()).collect(),
+            executable: process.exe().map(|path| path.display().to_string()),
+            cwd: process.cwd().map(|path| path.display().to_string()),
+            start_time: chrono::DateTime::from_timestamp(process.start_time() as i64, 0).map(|time| time.to_rfc3339()),
+            run_time_secs: process.run_time(),
+            cpu_usage: process.cpu_usage(),
+            memory_usage_kb: process.memory() / BYTES_PER_KB,
+            virtual_memory_usage_kb: process.virtual_memory() / BYTES_PER_KB,
+            children,
+            open_file_count: process.open_files().map(|count| count as u64),
+            open_files: open_files(input.pid),
+            environment,
+        })
+    }
+
+    fn open_ports() -> Result<ListPortsOutput, McpToolError> {
+        use netstat2::{AddressFamilyFlags, ProtocolFlags, ProtocolSocketInfo};
+
+        let sockets = netstat2::get_sockets_info(
+            AddressFamilyFlags::IPV4 | AddressFamilyFlags::IPV6,
+            ProtocolFlags::TCP | ProtocolFlags::UDP,
+        )
+        .map_err(|e| McpToolError::new(ErrorCode::Io, format!("Failed to read socket table: {}", e)))?;
+
+        let mut system = sysinfo::System::new();
+        system.refresh_processes(sysinfo::ProcessesToUpdate::All, true);
+        let process_name = |pid: u32| {
+            system
+                .process(sysinfo::Pid::from_u32(pid))
+                .map(|process| process.name().to_string_lossy().into_owned())
+        };
+
+        let connections = sockets
+            .into_iter()
+            .map(|socket| {
+                // A socket shared by several processes is reported under the first.
+                let pid = socket.associated_pids.first().copied();
+                let process_name = pid.and_then(process_name);
+                match socket.protocol_socket_info {
+                    ProtocolSocketInfo::Tcp(tcp) => PortConnection {
+                        protocol: "tcp".to_string(),
+                        local_address: tcp.local_addr.to_string(),
+                        local_port: tcp.local_port,
+                        remote_address: tcp.remote_addr.to_string(),
+                        remote_port: tcp.remote_port,
+                        status: tcp.state.to_string(),
+                        pid,
+                        process_name,
+                    },
+                    ProtocolSocketInfo::Udp(udp) => PortConnection {
+                        protocol: "udp".to_string(),
+                        local_address: udp.local_addr.to_string(),
+                        local_port: udp.local_port,
+                        remote_address: String::new(),
+                        remote_port: 0,
+                        status: String::new(),
+                        pid,
+                        process_name,
+                    },
+                }
+            })
+            .collect();
+        Ok(ListPortsOutput { connections })
+    }
+}
+
+fn process_not_found(pid: u32) -> McpToolError {
+    McpToolError::new(ErrorCode::ProcessNotFound, format!("No process with PID {}", pid))
+}
+
+// Lists what a process's descriptors point at; other platforms don't expose this
+// without extra privileges or tools.
+#[cfg(target_os = "linux")]
+fn open_files(pid: u32) -> Option<Vec<String>> {
+    const MAX_OPEN_FILES: usize = 256;
+    let entries = std::fs::read_dir(format!("/proc/{}/fd", pid)).ok()?;
+    let mut files: Vec<(u32, String)> = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let fd = entry.file_name().to_string_lossy().parse().ok()?;
+            let target = std::fs::read_link(entry.path()).ok()?;
+            Some((fd, target.display().to_string()))
+        })
+        .collect();
+    files.sort_unstable();
+    Some(
+        files
+            .into_iter()
+            .take(MAX_OPEN_FILES)
+            .map(|(fd, target)| format!("{}: {}", fd, target))
+            .collect(),
+    )
+}
+
+#[cfg(not(target_os = "linux"))]
+fn open_files(_pid: u32) -> Option<Vec<String>> {
+    None
+}
+
+fn structured_output(output: impl Serialize) -> CallToolResult {
+    match serde_json::to_value(output) {
+        Ok(value) => CallToolResult::structured(value),
+        Err(e) => McpToolError::new(ErrorCode::Internal, format!("Failed to serialize output: {}", e)).into_call_tool_result(),
+    }
+}
+
 #[async_trait]
 impl SystemCommand for LibSystemCommand {
     async fn kill_process(&self, input: KillProcessInput) -> CallToolResult {
         // Implement using sysinfo::System::kill_process_by_pid or similar
         // For now, return a placeholder
-        CallToolResult::structured_error(
-            serde_json::json!({"error": format!("LibSystemCommand::kill_process for PID {} not yet implemented.", input.pid)})
+        McpToolError::new(
+            ErrorCode::NotImplemented,
+            format!("LibSystemCommand::kill_process for PID {} not yet implemented.", input.pid),
         )
+        .into_call_tool_result()
     }
 
     async fn list_processes(&self) -> CallToolResult {
         // Implement using sysinfo
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "LibSystemCommand::list_processes not yet implemented."})
-        )
+        McpToolError::new(ErrorCode::NotImplemented, "LibSystemCommand::list_processes not yet implemented.")
+            .into_call_tool_result()
     }
 
     async fn get_memory_usage(&self) -> CallToolResult {
-        // Implement using sysinfo
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "LibSystemCommand::get_memory_usage not yet implemented."})
-        )
+        structured_output(Self::memory_usage())
     }
 
     async fn get_disk_usage(&self) -> CallToolResult {
-        // Implement using sysinfo
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "LibSystemCommand::get_disk_usage not yet implemented."})
-        )
+        // Enumerating mounts can stall on unresponsive network filesystems.
+        match tokio::task::spawn_blocking(Self::disk_usage).await {
+            Ok(output) => structured_output(output),
+            Err(e) => McpToolError::new(ErrorCode::Internal, format!("Disk usage task failed: {}", e)).into_call_tool_result(),
+        }
     }
 
     async fn list_ports(&self) -> CallToolResult {
-        // Implement using netstat2
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "LibSystemCommand::list_ports not yet implemented."})
-        )
+        match tokio::task::spawn_blocking(Self::open_ports).await {
+            Ok(Ok(output)) => structured_output(output),
+            Ok(Err(e)) => e.into_call_tool_result(),
+            Err(e) => McpToolError::new(ErrorCode::Internal, format!("Port listing task failed: {}", e)).into_call_tool_result(),
+        }
+    }
+
+    async fn get_process_tree(&self, input: GetProcessTreeInput) -> CallToolResult {
+        match tokio::task::spawn_blocking(move || Self::process_tree(input)).await {
+            Ok(Ok(output)) => structured_output(output),
+            Ok(Err(e)) => e.into_call_tool_result(),
+            Err(e) => McpToolError::new(ErrorCode::Internal, format!("Process tree task failed: {}", e)).into_call_tool_result(),
+        }
+    }
+
+    async fn get_process_info(&self, input: GetProcessInfoInput) -> CallToolResult {
+        match tokio::task::spawn_blocking(move || Self::process_info(input)).await {
+            Ok(Ok(output)) => structured_output(output),
+            Ok(Err(e)) => e.into_call_tool_result(),
+            Err(e) => McpToolError::new(ErrorCode::Internal, format!("Process info task failed: {}", e)).into_call_tool_result(),
+        }
+    }
+
+    async fn execute_command(&self, _input: ExecuteCommandInput, _cancel: CancellationToken) -> CallToolResult {
+        // Commands must run inside the sandbox, which only BinSystemCommand holds.
+        McpToolError::new(ErrorCode::NotImplemented, "LibSystemCommand::execute_command is not supported.")
+            .into_call_tool_result()
     }
 }
 
 // --- BinSystemCommand Implementation (using external binaries) ---
-pub struct BinSystemCommand;
+pub struct BinSystemCommand {
+    sandbox: Arc<Sandbox>,
+    exec: ExecConfig,
+}
 
-// Helper function to run shell commands (similar to previous run_command)
-async fn run_shell_command_bin(command: &str, args: &[&str]) -> Result<std::process::Output, std::io::Error> {
-    tokio::process::Command::new(command)
-        .args(args)
-        .output()
-        .await
+impl BinSystemCommand {
+    pub fn new(sandbox: Arc<Sandbox>, exec: ExecConfig) -> Self {
+        Self { sandbox, exec }
+    }
+
+    // Helper to run external commands; they always run inside the sandbox.
+    async fn run_shell_command_bin(&self, command: &str, args: &[&str]) -> Result<std::process::Output, std::io::Error> {
+        self.sandbox
+            .output(tokio::process::Command::new(command).args(args))
+            .await
+    }
 }
 
 #[async_trait]
 impl SystemCommand for BinSystemCommand {
+    #[tracing::instrument(name = "system.kill_process", skip_all, fields(pid = input.pid))]
     async fn kill_process(&self, input: KillProcessInput) -> CallToolResult {
         let pid = input.pid;
         let os = std::env::consts::OS;
         let command_result = match os {
             "windows" => {
-                run_shell_command_bin("taskkill", &["/PID", &pid.to_string(), "/F"]).await
+                self.run_shell_command_bin("taskkill", &["/PID", &pid.to_string(), "/F"]).await
             },
             "linux" | "macos" => {
-                run_shell_command_bin("kill", &["-9", &pid.to_string()]).await
+                self.run_shell_command_bin("kill", &["-9", &pid.to_string()]).await
             },
             _ => {
-                return CallToolResult::structured_error(
-                    serde_json::json!({"error": format!("Unsupported operating system: {}", os)})
-                );
+                return McpToolError::new(ErrorCode::UnsupportedPlatform, format!("Unsupported operating system: {}", os))
+                    .into_call_tool_result();
             }
         };
 
         match command_result {
             Ok(output) => {
                 if output.status.success() {
-                    CallToolResult::structured(
-                        serde_json::json!({"message": format!("Process {} killed successfully.", pid)})
-                    )
+                    structured_output(KillProcessOutput {
+                        pid,
+                        message: format!("Process {} killed successfully.", pid),
+                    })
                 } else {
-                    CallToolResult::structured_error(
-                        serde_json::json!({
-                            "error": format!("Failed to kill process {}: {}", pid, String::from_utf8_lossy(&output.stderr)),
-                            "stdout": String::from_utf8_lossy(&output.stdout),
-                            "stderr": String::from_utf8_lossy(&output.stderr),
-                        })
+                    McpToolError::new(
+                        ErrorCode::CommandFailed,
+                        format!("Failed to kill process {}: {}", pid, String::from_utf8_lossy(&output.stderr)),
                     )
+                    .with_details(serde_json::json!({
+                        "stdout": String::from_utf8_lossy(&output.stdout),
+                        "stderr": String::from_utf8_lossy(&output.stderr),
+                    }))
+                    .into_call_tool_result()
                 }
             },
             Err(e) => {
-                CallToolResult::structured_error(
-                    serde_json::json!({"error": format!("Failed to execute kill command for PID {}: {}", pid, e)})
+                McpToolError::new(
+                    ErrorCode::CommandFailed,
+                    format!("Failed to execute kill command for PID {}: {}", pid, e),
                 )
+                .into_call_tool_result()
             }
         }
     }
 
+    #[tracing::instrument(name = "system.list_processes", skip_all)]
     async fn list_processes(&self) -> CallToolResult {
         // Implement using platform-specific commands (e.g., 'ps', 'tasklist')
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "BinSystemCommand::list_processes not yet implemented."})
-        )
+        McpToolError::new(ErrorCode::NotImplemented, "BinSystemCommand::list_processes not yet implemented.")
+            .into_call_tool_result()
     }
 
+    // sysinfo reads these on every platform without spawning `free`/`df`/`wmic`.
+    #[tracing::instrument(name = "system.get_memory_usage", skip_all)]
     async fn get_memory_usage(&self) -> CallToolResult {
-        // Implement using platform-specific commands (e.g., 'free', 'wmic OS get FreePhysicalMemory')
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "BinSystemCommand::get_memory_usage not yet implemented."})
-        )
+        LibSystemCommand.get_memory_usage().await
     }
 
+    #[tracing::instrument(name = "system.get_disk_usage", skip_all)]
     async fn get_disk_usage(&self) -> CallToolResult {
-        // Implement using platform-specific commands (e.g., 'df', 'wmic logicaldisk get Caption,Size,Freespace')
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "BinSystemCommand::get_disk_usage not yet implemented."})
-        )
+        LibSystemCommand.get_disk_usage().await
     }
 
+    // netstat2 reads the kernel socket tables directly; output formats of
+    // `netstat`/`ss` differ too much between platforms to parse reliably.
+    #[tracing::instrument(name = "system.list_ports", skip_all)]
     async fn list_ports(&self) -> CallToolResult {
-        // Implement using platform-specific commands (e.g., 'netstat', 'ss')
-        CallToolResult::structured_error(
-            serde_json::json!({"error": "BinSystemCommand::list_ports not yet implemented."})
-        )
+        LibSystemCommand.list_ports().await
+    }
+
+    #[tracing::instrument(name = "system.get_process_tree", skip_all, fields(pid = ?input.pid))]
+    async fn get_process_tree(&self, input: GetProcessTreeInput) -> CallToolResult {
+        LibSystemCommand.get_process_tree(input).await
+    }
+
+    #[tracing::instrument(name = "system.get_process_info", skip_all, fields(pid = input.pid))]
+    async fn get_process_info(&self, input: GetProcessInfoInput) -> CallToolResult {
+        LibSystemCommand.get_process_info(input).await
+    }
+
+    // Only the program is recorded; arguments and environment may hold secrets.
+    #[tracing::instrument(name = "system.execute_command", skip_all, fields(program = %input.command))]
+    async fn execute_command(&self, input: ExecuteCommandInput, cancel: CancellationToken) -> CallToolResult {
+        if !self.exec.enabled {
+            return McpToolError::new(
+                ErrorCode::PermissionDenied,
+                "execute_command is disabled; set MCP_EXEC_ENABLED=1 to allow it",
+            )
+            .into_call_tool_result();
+        }
+        if let Some(name) = input.env.keys().find(|name| !self.exec.allows_env(name)) {
+            return McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("Environment variable '{}' is not on the allowlist", name),
+            )
+            .with_details(serde_json::json!({ "allowlist": self.exec.env_allowlist }))
+            .into_call_tool_result();
+        }
+
+        let mut command = tokio::process::Command::new(&input.command);
+        command.args(&input.args).stdin(std::process::Stdio::null()).env_clear();
+        for name in &self.exec.env_allowlist {
+            if let Some(value) = env::var_os(name) {
+                command.env(name, value);
+            }
+        }
+        command.envs(&input.env);
+        if let Some(cwd) = &input.cwd {
+            match self.sandbox.check_read_path(Path::new(cwd)) {
+                Ok(dir) if dir.is_dir() => {
+                    command.current_dir(dir);
+                }
+                Ok(dir) => {
+                    return McpToolError::new(ErrorCode::InvalidArgument, format!("{} is not a directory", dir.display()))
+                        .into_call_tool_result();
+                }
+                Err(e) => return e.into_call_tool_result(),
+            }
+        }
+
+        let timeout = input
+            .timeout_secs
+            .map(Duration::from_secs)
+            .map_or(self.exec.timeout, |requested| requested.min(self.exec.timeout));
+        let max_output = input
+            .max_output_bytes
+            .map_or(self.exec.max_output_bytes, |requested| requested.min(self.exec.max_output_bytes));
+
+        let started = Instant::now();
+        // On timeout or cancellation the future is dropped, and the child with it (kill_on_drop).
+        let run = tokio::select! {
+            result = tokio::time::timeout(timeout, self.sandbox.output_capped(&mut command, max_output)) => result,
+            _ = cancel.cancelled() => {
+                return McpToolError::new(
+                    ErrorCode::Cancelled,
+                    format!("{} was cancelled by the client and killed", input.command),
+                )
+                .into_call_tool_result();
+            }
+        };
+        let output = match run {
+            Ok(Ok(output)) => output,
+            Ok(Err(e)) => {
+                let code = match e.kind() {
+                    std::io::ErrorKind::NotFound => ErrorCode::FileNotFound,
+                    // Includes strict sandboxes refusing to run anything.
+                    std::io::ErrorKind::PermissionDenied => ErrorCode::PermissionDenied,
+                    _ => ErrorCode::CommandFailed,
+                };
+                return McpToolError::new(code, format!("Failed to run {}: {}", input.command, e)).into_call_tool_result();
+            }
+            Err(_) => {
+                return McpToolError::new(
+                    ErrorCode::Timeout,
+                    format!("{} did not finish within {}s and was killed", input.command, timeout.as_secs()),
+                )
+                .into_call_tool_result();
+            }
+        };
+        structured_output(ExecuteCommandOutput {
+            exit_code: output.status.code(),
+            stdout: String::from_utf8_lossy(&output.stdout).into_owned(),
+            stderr: String::from_utf8_lossy(&output.stderr).into_owned(),
+            stdout_truncated: output.stdout_truncated,
+            stderr_truncated: output.stderr_truncated,
+            duration_ms: started.elapsed().as_millis() as u64,
+        })
     }
 }
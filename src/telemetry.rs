@@ -0,0 +1,175 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// How often the server writes accumulated counts to disk.
+const FLUSH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Feature usage counts. Only names and counts are recorded: no arguments,
+/// results, paths or identifiers, and nothing ever leaves the machine unless
+/// the user exports it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TelemetryData {
+    /// Recording is off unless the user opts in.
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub since: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub tool_calls: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub transports: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub commands: BTreeMap<String, u64>,
+    /// Result cache lookups per tool, for tools with a cache TTL.
+    #[serde(default)]
+    pub cache_hits: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub cache_misses: BTreeMap<String, u64>,
+}
+
+/// Local usage counter shared by the server and CLI.
+pub struct Telemetry {
+    path: PathBuf,
+    data: Mutex<TelemetryData>,
+}
+
+impl Telemetry {
+    /// Default location: <local data dir>/copilot_mcp_tool/telemetry.json
+    pub fn default_path() -> Result<PathBuf> {
+        let dir = dirs::data_local_dir()
+            .context("Failed to get local data directory")?
+            .join("copilot_mcp_tool");
+        Ok(dir.join("telemetry.json"))
+    }
+
+    pub fn load(path: PathBuf) -> Result<Self> {
+        let data = if path.exists() {
+            let content = fs::read_to_string(&path).context("Failed to read telemetry file")?;
+            serde_json::from_str(&content).context("Failed to parse telemetry file")?
+        } else {
+            TelemetryData::default()
+        };
+        Ok(Self {
+            path,
+            data: Mutex::new(data),
+        })
+    }
+
+    /// Loads from the default path, falling back to a disabled in-memory instance.
+    pub fn load_default() -> Self {
+        let path = Self::default_path().unwrap_or_else(|_| std::env::temp_dir().join("copilot_mcp_telemetry.json"));
+        Self::load(path.clone()).unwrap_or_else(|e| {
+            tracing::warn!("Telemetry disabled, could not load {}: {}", path.display(), e);
+            Self {
+                path,
+                data: Mutex::new(TelemetryData::default()),
+            }
+        })
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.data.lock().unwrap().enabled
+    }
+
+    pub fn set_enabled(&self, enabled: bool) -> Result<()> {
+        {
+            let mut data = self.data.lock().unwrap();
+            data.enabled = enabled;
+            if enabled && data.since.is_none() {
+                data.since = Some(Utc::now());
+            }
+        }
+        self.flush()
+    }
+
+    /// Clears all counts but keeps the opt-in choice.
+    pub fn reset(&self) -> Result<()> {
+        {
+            let mut data = self.data.lock().unwrap();
+            *data = TelemetryData {
+                enabled: data.enabled,
+                since: data.enabled.then(Utc::now),
+                ..Default::default()
+            };
+        }
+        self.flush()
+    }
+
+    pub fn record_tool_call(&self, tool: &str) {
+        self.increment(|data| &mut data.tool_calls, tool);
+    }
+
+    pub fn record_transport(&self, transport: &str) {
+        self.increment(|data| &mut data.transports, transport);
+    }
+
+    pub fn record_command(&self, command: &str) {
+        self.increment(|data| &mut data.commands, command);
+    }
+
+    pub fn record_cache_lookup(&self, tool: &str, hit: bool) {
+        if hit {
+            self.increment(|data| &mut data.cache_hits, tool);
+        } else {
+            self.increment(|data| &mut data.cache_misses, tool);
+        }
+    }
+
+    fn increment(&self, counters: impl FnOnce(&mut TelemetryData) -> &mut BTreeMap<String, u64>, key: &str) {
+        let mut data = self.data.lock().unwrap();
+        if !data.enabled {
+            return;
+        }
+        *counters(&mut data).entry(key.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn snapshot(&self) -> TelemetryData {
+        self.data.lock().unwrap().clone()
+    }
+
+    pub fn flush(&self) -> Result<()> {
+        let data = self.snapshot();
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(&self.path, serde_json::to_string_pretty(&data)?).context("Failed to write telemetry file")?;
+        Ok(())
+    }
+
+    // Picks up `telemetry enable/disable` run from the CLI while the server is up.
+    fn reload_enabled(&self) {
+        let on_disk = fs::read_to_string(&self.path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<TelemetryData>(&content).ok());
+        if let Some(on_disk) = on_disk {
+            let mut data = self.data.lock().unwrap();
+            if data.enabled != on_disk.enabled {
+                data.enabled = on_disk.enabled;
+                data.since = on_disk.since;
+            }
+        }
+    }
+
+    /// Periodically persists counts while the server runs (no-op when disabled).
+    pub fn spawn_flusher(self: Arc<Self>) {
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(FLUSH_INTERVAL);
+            loop {
+                interval.tick().await;
+                self.reload_enabled();
+                if self.is_enabled() {
+                    if let Err(e) = self.flush() {
+                        tracing::warn!("Failed to flush telemetry: {}", e);
+                    }
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,72 @@
+//! TLS for the TCP transport, so the server can listen on a non-loopback
+//! address for remote agents.
+//!
+//! The server loads a PEM certificate chain and private key. Clients verify the
+//! server against the system roots, plus any CA or self-signed certificate
+//! they are given explicitly.
+
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use tokio_rustls::rustls::{ClientConfig, RootCertStore, ServerConfig};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let file = File::open(path).with_context(|| format!("Cannot open certificate {}", path.display()))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("Cannot parse certificate {}", path.display()))?;
+    if certs.is_empty() {
+        return Err(anyhow!("No certificates found in {}", path.display()));
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKeyDer<'static>> {
+    let file = File::open(path).with_context(|| format!("Cannot open private key {}", path.display()))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .with_context(|| format!("Cannot parse private key {}", path.display()))?
+        .ok_or_else(|| anyhow!("No private key found in {}", path.display()))
+}
+
+/// Builds the server side from a PEM certificate chain and private key.
+pub fn acceptor(cert: &Path, key: &Path) -> Result<TlsAcceptor> {
+    let config = ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(load_certs(cert)?, load_key(key)?)
+        .context("Certificate and private key don't form a usable pair")?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Builds the client side. The server must present a certificate signed by a
+/// system root or by one of the PEM files in `trusted` (a CA, or the server's
+/// own self-signed certificate).
+pub fn connector(trusted: &[PathBuf]) -> Result<TlsConnector> {
+    let mut roots = RootCertStore::empty();
+    let native = rustls_native_certs::load_native_certs();
+    for error in &native.errors {
+        tracing::debug!("Skipping system certificate: {}", error);
+    }
+    roots.add_parsable_certificates(native.certs);
+    for path in trusted {
+        for cert in load_certs(path)? {
+            roots
+                .add(cert)
+                .with_context(|| format!("Cannot trust certificate {}", path.display()))?;
+        }
+    }
+    let config = ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    Ok(TlsConnector::from(Arc::new(config)))
+}
+
+/// Parses the name the server's certificate must match: a DNS name or an IP
+/// address.
+pub fn server_name(host: &str) -> Result<ServerName<'static>> {
+    ServerName::try_from(host.to_string()).map_err(|_| anyhow!("'{}' is not a valid TLS server name", host))
+}
@@ -1,7 +1,10 @@
 use rmcp::{handler::server::{ServerHandler, tool::ToolRouter}, tool_router};
+use rmcp::model::{CallToolResult, Content};
+use schemars::JsonSchema;
 use serde::Deserialize;
 use std::sync::Arc; // Import Arc
 use crate::level2_tool_module::{TimeTool, TimeInput}; // Import TimeTool and TimeInput
+use crate::registry::ToolRegistry;
 
 #[derive(Clone)]
 pub struct WeatherTool {
@@ -9,7 +12,7 @@ pub struct WeatherTool {
     pub time_tool: Arc<TimeTool>, // Add pub
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, JsonSchema)]
 pub struct WeatherInput {
     pub location: String,
 }
@@ -40,3 +43,10 @@ pub fn new_weather_tool(time_tool: Arc<TimeTool>) -> WeatherTool {
         time_tool,
     }
 }
+
+pub fn register_tools<C: Send + 'static>(registry: &mut ToolRegistry<C>, weather_tool: Arc<WeatherTool>) {
+    registry.register("get_weather", "Reports the weather for a location.", move |_, input: WeatherInput| {
+        let weather_tool = weather_tool.clone();
+        async move { Ok(CallToolResult::success(vec![Content::text(weather_tool.get_weather(input).await)])) }
+    });
+}
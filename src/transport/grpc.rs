@@ -0,0 +1,114 @@
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use anyhow::Result;
+use futures::Stream;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream};
+use tokio::sync::mpsc;
+use tokio_stream::wrappers::ReceiverStream;
+use tokio_stream::StreamExt;
+use tonic::{Request, Response, Status, Streaming};
+
+pub mod proto {
+    tonic::include_proto!("mcp.transport.v1");
+}
+
+use proto::mcp_transport_server::{McpTransport, McpTransportServer};
+use proto::JsonRpcFrame;
+
+/// Size of the in-memory pipe between a gRPC stream and the MCP service.
+const BRIDGE_BUFFER_SIZE: usize = 64 * 1024;
+
+type FrameStream = Pin<Box<dyn Stream<Item = Result<JsonRpcFrame, Status>> + Send>>;
+
+/// gRPC service that runs one MCP session per `Session` stream.
+/// `on_connection` receives the service end of the bridged pipe plus a peer label
+/// and is expected to spawn the MCP service on it, exactly like a TCP connection.
+pub struct GrpcTransport<F> {
+    on_connection: Arc<F>,
+}
+
+impl<F> GrpcTransport<F>
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    pub fn new(on_connection: F) -> Self {
+        Self {
+            on_connection: Arc::new(on_connection),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl<F> McpTransport for GrpcTransport<F>
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    type SessionStream = FrameStream;
+
+    async fn session(
+        &self,
+        request: Request<Streaming<JsonRpcFrame>>,
+    ) -> Result<Response<Self::SessionStream>, Status> {
+        let peer = request.remote_addr();
+        let mut inbound = request.into_inner();
+
+        // The MCP service speaks newline-delimited JSON over an AsyncRead/AsyncWrite
+        // pair, so the gRPC frames are bridged through an in-memory duplex pipe.
+        let (service_io, bridge_io) = tokio::io::duplex(BRIDGE_BUFFER_SIZE);
+        let (bridge_read, mut bridge_write) = tokio::io::split(bridge_io);
+
+        let peer_label = match peer {
+            Some(addr) => format!("grpc://{}", addr),
+            None => "grpc://unknown".to_string(),
+        };
+        (self.on_connection)(service_io, peer_label);
+
+        tokio::spawn(async move {
+            while let Some(frame) = inbound.next().await {
+                let frame = match frame {
+                    Ok(frame) => frame,
+                    Err(status) => {
+                        tracing::warn!("gRPC inbound stream error: {}", status);
+                        break;
+                    }
+                };
+                let mut line = frame.json.replace('\n', " ");
+                line.push('\n');
+                if bridge_write.write_all(line.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            let _ = bridge_write.shutdown().await;
+        });
+
+        let (tx, rx) = mpsc::channel(32);
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(bridge_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if tx.send(Ok(JsonRpcFrame { json: line })).await.is_err() {
+                    break;
+                }
+            }
+        });
+
+        Ok(Response::new(Box::pin(ReceiverStream::new(rx)) as Self::SessionStream))
+    }
+}
+
+/// Serves the MCP gRPC transport on `addr` until the process exits.
+pub async fn serve<F>(addr: SocketAddr, on_connection: F) -> Result<()>
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    tracing::info!("MCP gRPC transport listening on {}", addr);
+    tonic::transport::Server::builder()
+        .add_service(McpTransportServer::new(GrpcTransport::new(on_connection)))
+        .serve(addr)
+        .await?;
+    Ok(())
+}
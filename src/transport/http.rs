@@ -0,0 +1,370 @@
+//! MCP streamable-HTTP transport: clients POST JSON-RPC messages to `/mcp`
+//! and read the replies from a Server-Sent Events stream; an optional GET
+//! stream carries server-initiated notifications and requests.
+//!
+//! Each session is bridged onto an in-memory pipe, exactly like a gRPC
+//! stream, so the tool handler sees an ordinary newline-delimited connection.
+//!
+//! Opening a session needs the server's auth token, either as an
+//! `Authorization: Bearer` header or in `initialize` like on TCP. The session
+//! id that comes back then stands for it on later requests.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::env;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::Router;
+use futures::{Stream, StreamExt};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, DuplexStream, WriteHalf};
+use tokio::sync::mpsc;
+
+use crate::auth::{self, AUTH_CAPABILITY};
+
+pub const SESSION_HEADER: &str = "mcp-session-id";
+
+/// Size of the in-memory pipe between an HTTP session and the MCP service.
+const BRIDGE_BUFFER_SIZE: usize = 64 * 1024;
+
+const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 30 * 60;
+
+type MessageSender = mpsc::UnboundedSender<Value>;
+
+// Where the service's outgoing messages go. Responses follow the request id
+// back to the POST that sent it; everything else goes to the GET stream, or
+// to every open POST stream when there is none.
+#[derive(Default)]
+struct Routes {
+    pending: HashMap<String, MessageSender>,
+    standalone: Option<MessageSender>,
+}
+
+impl Routes {
+    fn route(&mut self, message: Value) {
+        let is_response = message.get("method").is_none();
+        if let Some(id) = message.get("id").filter(|_| is_response) {
+            if let Some(sender) = self.pending.remove(&id.to_string()) {
+                let _ = sender.send(message);
+            }
+            return;
+        }
+        if let Some(standalone) = self.standalone.as_ref().filter(|sender| !sender.is_closed()) {
+            let _ = standalone.send(message);
+            return;
+        }
+        let mut sent: Vec<&MessageSender> = Vec::new();
+        for sender in self.pending.values() {
+            if !sent.iter().any(|other| other.same_channel(sender)) {
+                let _ = sender.send(message.clone());
+                sent.push(sender);
+            }
+        }
+    }
+}
+
+struct HttpSession {
+    writer: tokio::sync::Mutex<WriteHalf<DuplexStream>>,
+    routes: Mutex<Routes>,
+    last_seen: Mutex<Instant>,
+}
+
+impl HttpSession {
+    fn touch(&self) {
+        *self.last_seen.lock().unwrap() = Instant::now();
+    }
+
+    async fn send(&self, message: &Value) -> std::io::Result<()> {
+        let mut line = serde_json::to_vec(message)?;
+        line.push(b'\n');
+        self.writer.lock().await.write_all(&line).await
+    }
+
+    // Closing the pipe ends the MCP service, which in turn ends the router.
+    async fn close(&self) {
+        let _ = self.writer.lock().await.shutdown().await;
+    }
+}
+
+type Sessions = Arc<Mutex<HashMap<String, Arc<HttpSession>>>>;
+
+struct HttpTransport<F> {
+    on_connection: F,
+    sessions: Sessions,
+    auth_token: Arc<str>,
+}
+
+impl<F> HttpTransport<F>
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    fn open_session(&self, peer: &str) -> (String, Arc<HttpSession>) {
+        let id = uuid::Uuid::new_v4().to_string();
+        let (service_io, bridge_io) = tokio::io::duplex(BRIDGE_BUFFER_SIZE);
+        let (bridge_read, bridge_write) = tokio::io::split(bridge_io);
+        let session = Arc::new(HttpSession {
+            writer: tokio::sync::Mutex::new(bridge_write),
+            routes: Mutex::new(Routes::default()),
+            last_seen: Mutex::new(Instant::now()),
+        });
+        self.sessions.lock().unwrap().insert(id.clone(), session.clone());
+        (self.on_connection)(service_io, format!("http://{}#{}", peer, id));
+
+        let sessions = self.sessions.clone();
+        let router = session.clone();
+        let session_id = id.clone();
+        tokio::spawn(async move {
+            let mut lines = BufReader::new(bridge_read).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                match serde_json::from_str::<Value>(&line) {
+                    Ok(message) => router.routes.lock().unwrap().route(message),
+                    Err(e) => tracing::warn!("Dropping invalid message from MCP service: {}", e),
+                }
+            }
+            sessions.lock().unwrap().remove(&session_id);
+            tracing::info!("HTTP session {} closed", session_id);
+        });
+        (id, session)
+    }
+
+    fn session(&self, headers: &HeaderMap) -> Result<(String, Arc<HttpSession>), Response> {
+        let id = headers
+            .get(SESSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .ok_or_else(|| (StatusCode::BAD_REQUEST, "Missing Mcp-Session-Id header").into_response())?;
+        let session = self
+            .sessions
+            .lock()
+            .unwrap()
+            .get(id)
+            .cloned()
+            .ok_or_else(|| (StatusCode::NOT_FOUND, "Unknown or expired session").into_response())?;
+        session.touch();
+        Ok((id.to_string(), session))
+    }
+}
+
+// Browsers send an Origin; only local pages may talk to a local server
+// (guards against DNS rebinding).
+fn origin_allowed(headers: &HeaderMap) -> bool {
+    let Some(origin) = headers.get(header::ORIGIN).and_then(|value| value.to_str().ok()) else {
+        return true;
+    };
+    url::Url::parse(origin)
+        .ok()
+        .and_then(|url| url.host_str().map(str::to_string))
+        .is_some_and(|host| matches!(host.as_str(), "localhost" | "127.0.0.1" | "[::1]"))
+}
+
+// Checks the token of every `initialize` in a new session's first POST. A
+// bearer token is copied into requests that carry none, so the service's own
+// `initialize` check sees it too.
+fn authorize_initialize(messages: &mut [Value], headers: &HeaderMap, expected: &str) -> bool {
+    let bearer = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+    let mut authorized = true;
+    for message in messages.iter_mut().filter(|message| message.get("method") == Some(&Value::from("initialize"))) {
+        let offered = message
+            .pointer("/params/capabilities/experimental/auth/token")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let token = match (offered, bearer) {
+            (Some(offered), _) => offered,
+            (None, Some(bearer)) => {
+                if let Some(params) = message.get_mut("params").and_then(Value::as_object_mut) {
+                    let capabilities = params.entry("capabilities").or_insert_with(|| json!({}));
+                    if let Some(capabilities) = capabilities.as_object_mut() {
+                        let experimental = capabilities.entry("experimental").or_insert_with(|| json!({}));
+                        if let Some(experimental) = experimental.as_object_mut() {
+                            experimental.insert(AUTH_CAPABILITY.to_string(), json!({ "token": bearer }));
+                        }
+                    }
+                }
+                bearer.to_string()
+            }
+            (None, None) => String::new(),
+        };
+        authorized &= auth::token_matches(expected, &token);
+    }
+    authorized
+}
+
+fn event_stream(mut receiver: mpsc::UnboundedReceiver<Value>) -> Sse<impl Stream<Item = Result<Event, Infallible>>> {
+    let stream = futures::stream::poll_fn(move |cx| receiver.poll_recv(cx))
+        .map(|message| Ok(Event::default().event("message").data(message.to_string())));
+    Sse::new(stream).keep_alive(KeepAlive::default())
+}
+
+fn with_session_header(mut response: Response, id: &str) -> Response {
+    if let Ok(value) = HeaderValue::from_str(id) {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+async fn handle_post<F>(
+    State(transport): State<Arc<HttpTransport<F>>>,
+    axum::extract::ConnectInfo(peer): axum::extract::ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    if !origin_allowed(&headers) {
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+    let mut messages = match serde_json::from_slice::<Value>(&body) {
+        Ok(Value::Array(batch)) => batch,
+        Ok(message) => vec![message],
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid JSON: {}", e)).into_response(),
+    };
+
+    let initializing = messages.iter().any(|message| message.get("method") == Some(&Value::from("initialize")));
+    let (id, session) = if initializing && !headers.contains_key(SESSION_HEADER) {
+        // Origin only stops browsers; every client must prove the token.
+        if !authorize_initialize(&mut messages, &headers, &transport.auth_token) {
+            tracing::warn!("Rejected HTTP client {} without a valid auth token", peer);
+            let mut response = (StatusCode::UNAUTHORIZED, "Missing or invalid auth token").into_response();
+            response
+                .headers_mut()
+                .insert(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"));
+            return response;
+        }
+        transport.open_session(&peer.to_string())
+    } else {
+        match transport.session(&headers) {
+            Ok(found) => found,
+            Err(response) => return response,
+        }
+    };
+
+    // Register the requests before sending them so no reply can slip past.
+    let (sender, receiver) = mpsc::unbounded_channel();
+    let mut expects_reply = false;
+    {
+        let mut routes = session.routes.lock().unwrap();
+        for message in &messages {
+            if let (Some(request_id), Some(_)) = (message.get("id"), message.get("method")) {
+                routes.pending.insert(request_id.to_string(), sender.clone());
+                expects_reply = true;
+            }
+        }
+    }
+    drop(sender);
+
+    for message in &messages {
+        if let Err(e) = session.send(message).await {
+            tracing::warn!("HTTP session {} is gone: {}", id, e);
+            return (StatusCode::NOT_FOUND, "Session closed").into_response();
+        }
+    }
+
+    let response = if expects_reply {
+        event_stream(receiver).into_response()
+    } else {
+        StatusCode::ACCEPTED.into_response()
+    };
+    with_session_header(response, &id)
+}
+
+async fn handle_get<F>(State(transport): State<Arc<HttpTransport<F>>>, headers: HeaderMap) -> Response
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    if !origin_allowed(&headers) {
+        return (StatusCode::FORBIDDEN, "Origin not allowed").into_response();
+    }
+    let (id, session) = match transport.session(&headers) {
+        Ok(found) => found,
+        Err(response) => return response,
+    };
+    let (sender, receiver) = mpsc::unbounded_channel();
+    // A new GET stream replaces the previous one.
+    session.routes.lock().unwrap().standalone = Some(sender);
+    with_session_header(event_stream(receiver).into_response(), &id)
+}
+
+async fn handle_delete<F>(State(transport): State<Arc<HttpTransport<F>>>, headers: HeaderMap) -> Response
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    match transport.session(&headers) {
+        Ok((id, session)) => {
+            transport.sessions.lock().unwrap().remove(&id);
+            session.close().await;
+            StatusCode::NO_CONTENT.into_response()
+        }
+        Err(response) => response,
+    }
+}
+
+// Clients that vanish without a DELETE would otherwise keep their session forever.
+fn spawn_idle_reaper(sessions: Sessions, idle_timeout: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            let expired: Vec<(String, Arc<HttpSession>)> = {
+                let mut sessions = sessions.lock().unwrap();
+                let ids: Vec<String> = sessions
+                    .iter()
+                    .filter(|(_, session)| session.last_seen.lock().unwrap().elapsed() > idle_timeout)
+                    .map(|(id, _)| id.clone())
+                    .collect();
+                ids.into_iter()
+                    .filter_map(|id| sessions.remove(&id).map(|session| (id, session)))
+                    .collect()
+            };
+            for (id, session) in expired {
+                tracing::info!("Closing idle HTTP session {}", id);
+                session.close().await;
+            }
+        }
+    });
+}
+
+/// Serves the MCP streamable-HTTP transport at `http://<addr>/mcp` until the
+/// process exits. New sessions must present `auth_token`. Sessions idle for
+/// `MCP_HTTP_IDLE_TIMEOUT_SECS` (default 30 minutes) are closed.
+pub async fn serve<F>(addr: SocketAddr, auth_token: Arc<str>, on_connection: F) -> Result<()>
+where
+    F: Fn(DuplexStream, String) + Send + Sync + 'static,
+{
+    let idle_timeout = Duration::from_secs(
+        env::var("MCP_HTTP_IDLE_TIMEOUT_SECS")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(DEFAULT_IDLE_TIMEOUT_SECS),
+    );
+    let transport = Arc::new(HttpTransport {
+        on_connection,
+        sessions: Arc::new(Mutex::new(HashMap::new())),
+        auth_token,
+    });
+    spawn_idle_reaper(transport.sessions.clone(), idle_timeout);
+
+    let app = Router::new()
+        .route(
+            "/mcp",
+            post(handle_post::<F>).get(handle_get::<F>).delete(handle_delete::<F>),
+        )
+        .with_state(transport);
+
+    tracing::info!("MCP HTTP transport listening on http://{}/mcp", addr);
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await?;
+    Ok(())
+}
@@ -0,0 +1,6 @@
+// Alternative transports for the MCP server. The TCP listener in main.rs stays
+// the default; these adapt other wire protocols onto the same ServerHandler.
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod http;
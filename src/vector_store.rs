@@ -0,0 +1,475 @@
+//! Local vector store behind `index_documents` and `semantic_search`.
+//!
+//! Project files, split into chunks of a few dozen lines, and the solfunmeme
+//! memes are embedded with the LLM driver's embeddings API. Documents and
+//! their vectors are saved to `vector_store.json` in the data dir. An HNSW
+//! graph over them is rebuilt in memory on load and after every change, and
+//! answers queries by cosine similarity.
+//!
+//! All vectors must come from one embedding model; indexing with another
+//! model fails unless the call passes `reset` to start over.
+
+use std::collections::HashSet;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use instant_distance::{Builder, HnswMap, Point, Search};
+use mcp_common::runtime_paths;
+use mcp_errors::{ErrorCode, McpToolError};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::copilot::Copilot;
+use crate::llm_usage::MeteredLlm;
+
+const CHUNK_LINES: usize = 40;
+// Chunks of very long lines are cut here to stay within embedding input limits.
+const MAX_CHUNK_CHARS: usize = 4000;
+const MAX_FILE_BYTES: u64 = 1024 * 1024;
+const MAX_INDEX_FILES: usize = 2000;
+// Inputs per embeddings request.
+const EMBED_BATCH: usize = 64;
+const DEFAULT_SEARCH_RESULTS: usize = 5;
+const MAX_SEARCH_RESULTS: usize = 50;
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentSource {
+    File,
+    Meme,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct IndexDocumentsInput {
+    /// Files or directories under the allowed roots; directories are indexed
+    /// recursively, skipping hidden entries. Relative paths resolve against
+    /// the first root.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    /// Also index the solfunmeme memes.
+    #[serde(default)]
+    pub memes: bool,
+    /// Embedding model (default: the index's model, then `MCP_EMBEDDING_MODEL`,
+    /// then the driver's usual one).
+    pub model: Option<String>,
+    /// Drop everything indexed so far first, e.g. to switch models.
+    #[serde(default)]
+    pub reset: bool,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct IndexDocumentsOutput {
+    pub model: String,
+    /// Files read and embedded.
+    pub files: usize,
+    /// Files left out because they are not UTF-8 text or larger than 1 MiB.
+    pub skipped: usize,
+    /// True when the paths held more than 2000 files and the rest were left out.
+    pub truncated: bool,
+    pub memes: usize,
+    /// Chunks embedded by this call.
+    pub chunks: usize,
+    /// Documents in the store after this call.
+    pub total: usize,
+}
+
+#[derive(Deserialize, Serialize, JsonSchema, Debug, Clone)]
+pub struct SemanticSearchInput {
+    pub query: String,
+    /// Maximum results (default 5, at most 50).
+    pub limit: Option<usize>,
+    /// Only return documents of this kind.
+    pub source: Option<DocumentSource>,
+}
+
+#[derive(Serialize, JsonSchema, Debug, Clone)]
+pub struct SemanticMatch {
+    pub source: DocumentSource,
+    /// The file's path, or the meme's name.
+    pub title: String,
+    /// 1-based first line of a file chunk.
+    pub line: Option<usize>,
+    /// Cosine similarity to the query, from -1 to 1.
+    pub score: f32,
+    pub text: String,
+}
+
+#[derive(Serialize, JsonSchema, Debug)]
+pub struct SemanticSearchOutput {
+    pub model: String,
+    /// Best match first.
+    pub results: Vec<SemanticMatch>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+struct Document {
+    // What indexing again replaces: a file's path, or `meme:<id>`.
+    key: String,
+    source: DocumentSource,
+    title: String,
+    line: Option<usize>,
+    text: String,
+    // Unit length, so cosine similarity is a dot product.
+    vector: Vec<f32>,
+}
+
+impl Document {
+    // File chunks don't say where they come from, so the path goes in too.
+    fn embedding_input(&self) -> String {
+        match self.source {
+            DocumentSource::File => format!("{}\n\n{}", self.title, self.text),
+            DocumentSource::Meme => self.text.clone(),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct StoredIndex {
+    model: Option<String>,
+    documents: Vec<Document>,
+}
+
+#[derive(Clone)]
+struct Embedding(Vec<f32>);
+
+impl Point for Embedding {
+    fn distance(&self, other: &Self) -> f32 {
+        1.0 - dot(&self.0, &other.0)
+    }
+}
+
+struct Index {
+    stored: StoredIndex,
+    // Maps to positions in `stored.documents`; None while the store is empty.
+    graph: Option<HnswMap<Embedding, usize>>,
+}
+
+impl Index {
+    fn build(stored: StoredIndex) -> Self {
+        let graph = (!stored.documents.is_empty()).then(|| {
+            let points = stored.documents.iter().map(|document| Embedding(document.vector.clone())).collect();
+            Builder::default().build(points, (0..stored.documents.len()).collect())
+        });
+        Self { stored, graph }
+    }
+
+    fn model(&self) -> Option<&str> {
+        self.stored.model.as_deref().filter(|_| !self.stored.documents.is_empty())
+    }
+}
+
+pub struct VectorStore {
+    path: PathBuf,
+    index: RwLock<Index>,
+}
+
+impl VectorStore {
+    /// Loads the store from the data dir, starting empty when there is none
+    /// or it can't be read.
+    pub fn load_default() -> Self {
+        let path = runtime_paths::vector_store_path()
+            .unwrap_or_else(|_| std::env::temp_dir().join("copilot_mcp_vector_store.json"));
+        let stored = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content).unwrap_or_else(|e| {
+                tracing::warn!("Ignoring unreadable vector store {}: {}", path.display(), e);
+                StoredIndex::default()
+            }),
+            Err(e) if e.kind() == ErrorKind::NotFound => StoredIndex::default(),
+            Err(e) => {
+                tracing::warn!("Could not read vector store {}: {}", path.display(), e);
+                StoredIndex::default()
+            }
+        };
+        Self {
+            path,
+            index: RwLock::new(Index::build(stored)),
+        }
+    }
+
+    fn model(&self) -> Option<String> {
+        self.index.read().unwrap().model().map(str::to_string)
+    }
+
+    fn check_model(index: &Index, model: &str) -> Result<(), McpToolError> {
+        match index.model() {
+            Some(current) if current != model => Err(McpToolError::new(
+                ErrorCode::InvalidArgument,
+                format!("The index was built with {}; pass reset to re-index everything with {}", current, model),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Embeds the files under `input.paths`, which must lie under `roots`,
+    /// and the memes if asked, replacing what was indexed for them before.
+    pub async fn index(
+        &self,
+        llm: &MeteredLlm,
+        roots: Vec<PathBuf>,
+        input: IndexDocumentsInput,
+    ) -> Result<IndexDocumentsOutput, McpToolError> {
+        if input.paths.is_empty() && !input.memes {
+            return Err(McpToolError::new(ErrorCode::InvalidArgument, "Give paths to index, or set memes"));
+        }
+        let model = input
+            .model
+            .clone()
+            .or_else(|| self.model().filter(|_| !input.reset))
+            .unwrap_or_else(|| llm.embedding_model());
+        if !input.reset {
+            Self::check_model(&self.index.read().unwrap(), &model)?;
+        }
+
+        let paths = input.paths.clone();
+        let files = tokio::task::spawn_blocking(move || file_documents(&roots, &paths))
+            .await
+            .map_err(|e| McpToolError::new(ErrorCode::Internal, e.to_string()))??;
+        let mut documents = files.documents;
+        let mut memes = 0;
+        if input.memes {
+            let meme_documents = meme_documents();
+            memes = meme_documents.len();
+            documents.extend(meme_documents);
+        }
+
+        let inputs = documents.iter().map(Document::embedding_input).collect();
+        let vectors = embed(llm, &model, inputs).await?;
+        for (document, vector) in documents.iter_mut().zip(vectors) {
+            document.vector = vector;
+        }
+        let chunks = documents.len();
+        // Rebuilding the graph is CPU-bound.
+        let total = tokio::task::block_in_place(|| self.insert(&model, documents, input.reset))?;
+        Ok(IndexDocumentsOutput {
+            model,
+            files: files.read,
+            skipped: files.skipped,
+            truncated: files.truncated,
+            memes,
+            chunks,
+            total,
+        })
+    }
+
+    fn insert(&self, model: &str, documents: Vec<Document>, reset: bool) -> Result<usize, McpToolError> {
+        let mut index = self.index.write().unwrap();
+        if documents.is_empty() && !reset {
+            return Ok(index.stored.documents.len());
+        }
+        let mut stored = if reset {
+            StoredIndex::default()
+        } else {
+            Self::check_model(&index, model)?;
+            std::mem::take(&mut index.stored)
+        };
+        let dimension = stored.documents.first().map(|document| document.vector.len());
+        if let Some(dimension) = dimension.filter(|&dimension| documents.iter().any(|document| document.vector.len() != dimension)) {
+            index.stored = stored;
+            return Err(McpToolError::new(
+                ErrorCode::LlmInvalidResponse,
+                format!("{} returned vectors that don't have the index's {} dimensions", model, dimension),
+            ));
+        }
+        let keys: HashSet<String> = documents.iter().map(|document| document.key.clone()).collect();
+        stored.documents.retain(|document| !keys.contains(&document.key));
+        stored.documents.extend(documents);
+        stored.model = Some(model.to_string());
+        *index = Index::build(stored);
+        self.persist(&index.stored);
+        Ok(index.stored.documents.len())
+    }
+
+    fn persist(&self, stored: &StoredIndex) {
+        let result = serde_json::to_vec(stored)
+            .map_err(anyhow::Error::from)
+            .and_then(|json| Ok(runtime_paths::write_private_file(&self.path, &json)?));
+        if let Err(e) = result {
+            tracing::warn!("Failed to save vector store {}: {}", self.path.display(), e);
+        }
+    }
+
+    /// Returns the documents closest in meaning to `input.query`.
+    pub async fn search(&self, llm: &MeteredLlm, input: SemanticSearchInput) -> Result<SemanticSearchOutput, McpToolError> {
+        let model = self.model().ok_or_else(|| {
+            McpToolError::new(ErrorCode::ResourceNotFound, "Nothing is indexed yet; call index_documents first")
+        })?;
+        let limit = input.limit.unwrap_or(DEFAULT_SEARCH_RESULTS).clamp(1, MAX_SEARCH_RESULTS);
+        let query = embed(llm, &model, vec![input.query])
+            .await?
+            .pop()
+            .ok_or_else(|| McpToolError::new(ErrorCode::LlmInvalidResponse, "No embedding came back for the query"))?;
+
+        let index = self.index.read().unwrap();
+        let (Some(graph), Some(first)) = (&index.graph, index.stored.documents.first()) else {
+            return Ok(SemanticSearchOutput { model, results: Vec::new() });
+        };
+        if query.len() != first.vector.len() {
+            return Err(McpToolError::new(
+                ErrorCode::LlmInvalidResponse,
+                format!("{} returned a query vector that doesn't match the index's {} dimensions", model, first.vector.len()),
+            ));
+        }
+        // The graph yields its nearest candidates (about 100) in order; a
+        // `source` filter picks from those.
+        let mut search = Search::default();
+        let results = graph
+            .search(&Embedding(query), &mut search)
+            .map(|item| (item.distance, &index.stored.documents[*item.value]))
+            .filter(|(_, document)| input.source.is_none_or(|source| document.source == source))
+            .take(limit)
+            .map(|(distance, document)| SemanticMatch {
+                source: document.source,
+                title: document.title.clone(),
+                line: document.line,
+                score: 1.0 - distance,
+                text: document.text.clone(),
+            })
+            .collect();
+        Ok(SemanticSearchOutput { model, results })
+    }
+}
+
+async fn embed(llm: &MeteredLlm, model: &str, inputs: Vec<String>) -> Result<Vec<Vec<f32>>, McpToolError> {
+    let mut vectors = Vec::with_capacity(inputs.len());
+    for batch in inputs.chunks(EMBED_BATCH) {
+        let embedded = llm.embeddings(batch.to_vec(), model.to_string()).await?;
+        if embedded.len() != batch.len() {
+            return Err(McpToolError::new(
+                ErrorCode::LlmInvalidResponse,
+                format!("{} returned {} embeddings for {} inputs", model, embedded.len(), batch.len()),
+            ));
+        }
+        vectors.extend(embedded.into_iter().map(normalized));
+    }
+    Ok(vectors)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(mut vector: Vec<f32>) -> Vec<f32> {
+    let norm = dot(&vector, &vector).sqrt();
+    if norm > 0.0 {
+        vector.iter_mut().for_each(|value| *value /= norm);
+    }
+    vector
+}
+
+struct FileDocuments {
+    documents: Vec<Document>,
+    read: usize,
+    skipped: usize,
+    truncated: bool,
+}
+
+// Chunks of the text files under `paths`, which must lie under `roots`.
+fn file_documents(roots: &[PathBuf], paths: &[String]) -> Result<FileDocuments, McpToolError> {
+    let first_root = roots.first().ok_or_else(|| {
+        McpToolError::new(ErrorCode::PermissionDenied, "No file system roots are configured, or none overlap the client's roots")
+    })?;
+    let mut files = Vec::new();
+    let mut truncated = false;
+    for path in paths {
+        // Joining an absolute path replaces the root.
+        let path = first_root.join(path);
+        let resolved = path
+            .canonicalize()
+            .map_err(|_| McpToolError::new(ErrorCode::FileNotFound, format!("{} does not exist", path.display())))?;
+        if !roots.iter().any(|root| resolved.starts_with(root)) {
+            return Err(McpToolError::new(
+                ErrorCode::SandboxViolation,
+                format!("{} is outside the allowed roots", path.display()),
+            )
+            .with_details(serde_json::json!({ "roots": roots })));
+        }
+        // Symlinks aren't followed, so the walk can't leave the root.
+        let entries = walkdir::WalkDir::new(&resolved)
+            .into_iter()
+            .filter_entry(|entry| entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file());
+        for entry in entries {
+            if files.len() == MAX_INDEX_FILES {
+                truncated = true;
+                break;
+            }
+            files.push(entry.into_path());
+        }
+    }
+    files.sort();
+    files.dedup();
+
+    let mut documents = Vec::new();
+    let (mut read, mut skipped) = (0, 0);
+    for file in &files {
+        match read_text(file) {
+            Some(text) => {
+                read += 1;
+                documents.extend(chunks(file, &text));
+            }
+            None => skipped += 1,
+        }
+    }
+    Ok(FileDocuments {
+        documents,
+        read,
+        skipped,
+        truncated,
+    })
+}
+
+fn read_text(path: &Path) -> Option<String> {
+    if fs::metadata(path).ok()?.len() > MAX_FILE_BYTES {
+        return None;
+    }
+    String::from_utf8(fs::read(path).ok()?).ok().filter(|text| !text.contains('\0'))
+}
+
+fn chunks(path: &Path, text: &str) -> Vec<Document> {
+    let title = path.display().to_string();
+    let lines: Vec<&str> = text.lines().collect();
+    lines
+        .chunks(CHUNK_LINES)
+        .enumerate()
+        .filter(|(_, chunk)| chunk.iter().any(|line| !line.trim().is_empty()))
+        .map(|(number, chunk)| {
+            let mut text = chunk.join("\n");
+            if let Some((end, _)) = text.char_indices().nth(MAX_CHUNK_CHARS) {
+                text.truncate(end);
+            }
+            Document {
+                key: title.clone(),
+                source: DocumentSource::File,
+                title: title.clone(),
+                line: Some(number * CHUNK_LINES + 1),
+                text,
+                vector: Vec::new(),
+            }
+        })
+        .collect()
+}
+
+fn meme_documents() -> Vec<Document> {
+    solfunmeme_core::get_memes()
+        .into_iter()
+        .map(|meme| Document {
+            key: format!("meme:{}", meme.id),
+            source: DocumentSource::Meme,
+            text: format!(
+                "{} {}\n{}\n{}\nCategory: {}\nTags: {}",
+                meme.emoji,
+                meme.name,
+                meme.description,
+                meme.content,
+                solfunmeme_core::category_name(&meme.category),
+                meme.tags.join(", "),
+            ),
+            title: meme.name,
+            line: None,
+            vector: Vec::new(),
+        })
+        .collect()
+}
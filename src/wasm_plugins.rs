@@ -0,0 +1,236 @@
+//! WebAssembly tool plugins: components implementing the `tool-plugin` world
+//! in `wit/tool-plugin.wit`, run with wasmtime. Unlike native plugins they
+//! cannot touch the host beyond what their manifest grants.
+//!
+//! The manifest is a JSON file next to the component with the same stem
+//! (`weather.wasm` reads `weather.json`); without one the plugin gets no
+//! files and no network:
+//!
+//! ```json
+//! {
+//!   "dirs": [{ "host": "/home/me/notes", "guest": "/notes", "writable": false }],
+//!   "connect": ["api.weather.example:443"]
+//! }
+//! ```
+//!
+//! Host directories must pass the server's sandbox. `connect` entries are
+//! resolved when the plugin loads, and only those addresses may be reached
+//! over TCP. Each call gets a fresh instance with a fuel and memory budget.
+
+use std::collections::HashSet;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{anyhow, Context, Result};
+use copilot_mcp_tool::sandbox::Sandbox;
+use mcp_errors::{ErrorCode, McpToolError};
+use rmcp::model::CallToolResult;
+use serde::Deserialize;
+use wasmtime::component::{Component, Linker, ResourceTable};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder, Trap};
+use wasmtime_wasi::{DirPerms, FilePerms, WasiCtx, WasiCtxBuilder, WasiView};
+
+use crate::plugins::{self, PluginBackend, ToolDefinition};
+
+wasmtime::component::bindgen!({
+    world: "tool-plugin",
+    path: "wit/tool-plugin.wit",
+});
+
+/// Fuel for one call unless `MCP_WASM_FUEL` says otherwise; roughly one unit
+/// per instruction.
+const DEFAULT_FUEL: u64 = 5_000_000_000;
+const DEFAULT_MAX_MEMORY_MB: usize = 256;
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+struct Manifest {
+    #[serde(default)]
+    dirs: Vec<DirGrant>,
+    /// `host:port` pairs the plugin may connect to.
+    #[serde(default)]
+    connect: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct DirGrant {
+    host: PathBuf,
+    guest: String,
+    #[serde(default)]
+    writable: bool,
+}
+
+// A manifest checked against the sandbox, with addresses resolved.
+struct Capabilities {
+    dirs: Vec<(PathBuf, String, bool)>,
+    connect: Arc<HashSet<SocketAddr>>,
+}
+
+struct PluginState {
+    ctx: WasiCtx,
+    table: ResourceTable,
+    limits: StoreLimits,
+}
+
+impl WasiView for PluginState {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut WasiCtx {
+        &mut self.ctx
+    }
+}
+
+/// The wasmtime engine and WASI linker shared by every WebAssembly plugin.
+pub struct WasmRuntime {
+    engine: Engine,
+    linker: Linker<PluginState>,
+    sandbox: Arc<Sandbox>,
+    fuel: u64,
+    max_memory_bytes: usize,
+}
+
+impl WasmRuntime {
+    /// Reads the per-call budget from `MCP_WASM_FUEL` and
+    /// `MCP_WASM_MAX_MEMORY_MB` (default 256).
+    pub fn from_env(sandbox: Arc<Sandbox>) -> Result<Self> {
+        fn var<T: std::str::FromStr>(name: &str) -> Option<T> {
+            std::env::var(name).ok().and_then(|value| value.parse().ok())
+        }
+        let mut config = Config::new();
+        config.wasm_component_model(true);
+        config.consume_fuel(true);
+        let engine = Engine::new(&config)?;
+        let mut linker = Linker::new(&engine);
+        wasmtime_wasi::add_to_linker_sync(&mut linker)?;
+        Ok(Self {
+            engine,
+            linker,
+            sandbox,
+            fuel: var("MCP_WASM_FUEL").unwrap_or(DEFAULT_FUEL),
+            max_memory_bytes: var::<usize>("MCP_WASM_MAX_MEMORY_MB").unwrap_or(DEFAULT_MAX_MEMORY_MB) * 1024 * 1024,
+        })
+    }
+
+    /// Compiles the component at `path` and asks it for its tools.
+    pub fn load(self: &Arc<Self>, path: &Path) -> Result<(WasmPlugin, Vec<ToolDefinition>)> {
+        let capabilities = self.capabilities(&path.with_extension("json"))?;
+        let component = Component::from_file(&self.engine, path).with_context(|| format!("Failed to compile {}", path.display()))?;
+        let plugin = WasmPlugin {
+            runtime: self.clone(),
+            component,
+            capabilities,
+        };
+        let mut store = plugin.store()?;
+        let instance = ToolPlugin::instantiate(&mut store, &plugin.component, &self.linker)?;
+        let definitions = instance
+            .call_list_tools(&mut store)?
+            .into_iter()
+            .map(|definition| {
+                Ok(ToolDefinition {
+                    input_schema: serde_json::from_str(&definition.input_schema)
+                        .with_context(|| format!("Invalid input schema for tool '{}'", definition.name))?,
+                    output_schema: definition
+                        .output_schema
+                        .as_deref()
+                        .map(serde_json::from_str)
+                        .transpose()
+                        .with_context(|| format!("Invalid output schema for tool '{}'", definition.name))?,
+                    name: definition.name,
+                    description: definition.description,
+                    dangerous: definition.dangerous,
+                })
+            })
+            .collect::<Result<Vec<_>>>()
+            .with_context(|| format!("{}: list-tools", path.display()))?;
+        Ok((plugin, definitions))
+    }
+
+    fn capabilities(&self, manifest_path: &Path) -> Result<Capabilities> {
+        let manifest: Manifest = match std::fs::read_to_string(manifest_path) {
+            Ok(text) => serde_json::from_str(&text).with_context(|| format!("Invalid manifest {}", manifest_path.display()))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", manifest_path.display())),
+        };
+        let mut dirs = Vec::new();
+        for grant in manifest.dirs {
+            let host = if grant.writable {
+                self.sandbox.check_write_path(&grant.host)
+            } else {
+                self.sandbox.check_read_path(&grant.host)
+            }
+            .map_err(|e| anyhow!("{}: {}", manifest_path.display(), e))?;
+            dirs.push((host, grant.guest, grant.writable));
+        }
+        let mut connect = HashSet::new();
+        for address in &manifest.connect {
+            let resolved = address
+                .to_socket_addrs()
+                .with_context(|| format!("{}: cannot resolve '{}'", manifest_path.display(), address))?;
+            connect.extend(resolved);
+        }
+        Ok(Capabilities {
+            dirs,
+            connect: Arc::new(connect),
+        })
+    }
+}
+
+/// A compiled WebAssembly plugin and the capabilities it was granted.
+pub struct WasmPlugin {
+    runtime: Arc<WasmRuntime>,
+    component: Component,
+    capabilities: Capabilities,
+}
+
+impl WasmPlugin {
+    // A store with the plugin's WASI context and a full call budget. The
+    // plugin's stdout is not connected: it carries MCP frames in stdio mode.
+    fn store(&self) -> Result<Store<PluginState>> {
+        let mut builder = WasiCtxBuilder::new();
+        builder.inherit_stderr();
+        for (host, guest, writable) in &self.capabilities.dirs {
+            let (dir_perms, file_perms) = if *writable {
+                (DirPerms::all(), FilePerms::all())
+            } else {
+                (DirPerms::READ, FilePerms::READ)
+            };
+            builder.preopened_dir(host, guest, dir_perms, file_perms)?;
+        }
+        let connect = self.capabilities.connect.clone();
+        builder
+            .allow_udp(false)
+            .allow_ip_name_lookup(!connect.is_empty())
+            .socket_addr_check(move |addr, _| {
+                let allowed = connect.contains(&addr);
+                Box::pin(async move { allowed })
+            });
+        let state = PluginState {
+            ctx: builder.build(),
+            table: ResourceTable::new(),
+            limits: StoreLimitsBuilder::new().memory_size(self.runtime.max_memory_bytes).build(),
+        };
+        let mut store = Store::new(&self.runtime.engine, state);
+        store.limiter(|state| &mut state.limits);
+        store.set_fuel(self.runtime.fuel)?;
+        Ok(store)
+    }
+}
+
+impl PluginBackend for WasmPlugin {
+    fn call(&self, name: &str, arguments: &str) -> Result<CallToolResult, McpToolError> {
+        let trapped = |e: anyhow::Error| match e.downcast_ref::<Trap>() {
+            Some(Trap::OutOfFuel) => McpToolError::new(ErrorCode::Timeout, format!("Plugin tool '{}' ran out of fuel", name)),
+            _ => McpToolError::new(ErrorCode::Internal, format!("Plugin tool '{}' failed: {:#}", name, e)),
+        };
+        let mut store = self.store().map_err(trapped)?;
+        let instance = ToolPlugin::instantiate(&mut store, &self.component, &self.runtime.linker).map_err(trapped)?;
+        match instance.call_call_tool(&mut store, name, arguments).map_err(trapped)? {
+            Ok(output) => plugins::parse_result(&output),
+            Err(message) => Err(McpToolError::new(ErrorCode::CommandFailed, message)),
+        }
+    }
+}
@@ -0,0 +1,115 @@
+// The whole conversation is sent with every turn.
+let history = [];
+const log = document.getElementById("log");
+
+function bubble(className, text) {
+    const div = document.createElement("div");
+    div.className = "message " + className;
+    div.textContent = text || "";
+    log.appendChild(div);
+    div.scrollIntoView({ block: "end" });
+    return div;
+}
+
+function showItems(container, items) {
+    for (const item of items) {
+        if (item.kind === "text") {
+            const pre = document.createElement("div");
+            pre.textContent = item.text;
+            container.appendChild(pre);
+        } else if (item.kind === "image") {
+            const img = document.createElement("img");
+            img.src = "data:" + item.mime_type + ";base64," + item.data;
+            img.alt = "Tool result image";
+            container.appendChild(img);
+        } else {
+            const link = document.createElement("a");
+            link.download = item.name;
+            link.href = "data:" + item.mime_type + ";base64," + item.data;
+            link.textContent = "Download " + item.name + " (" + item.size + " bytes)";
+            container.appendChild(link);
+        }
+    }
+}
+
+// Reads `event:` / `data:` blocks from a fetch response body.
+async function* serverEvents(response) {
+    const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+    let buffer = "";
+    while (true) {
+        const { value, done } = await reader.read();
+        if (done) return;
+        buffer += value;
+        let end;
+        while ((end = buffer.indexOf("\n\n")) >= 0) {
+            const block = buffer.slice(0, end);
+            buffer = buffer.slice(end + 2);
+            let name = "message", data = "";
+            for (const line of block.split("\n")) {
+                if (line.startsWith("event:")) name = line.slice(6).trim();
+                else if (line.startsWith("data:")) data += line.slice(5).trimStart();
+            }
+            if (data) yield { name, data: JSON.parse(data) };
+        }
+    }
+}
+
+async function send(text) {
+    history.push({ role: "user", content: text });
+    bubble("user", text);
+    let reply = null;
+    const response = await fetch("/chat/turn", {
+        method: "POST",
+        headers: {
+            "Content-Type": "application/json",
+            "X-CSRF-Token": document.querySelector("meta[name=csrf-token]").content,
+        },
+        body: JSON.stringify({ messages: history, model: document.getElementById("model").value }),
+    });
+    if (!response.ok) {
+        bubble("error", "The web client refused the message: " + response.status);
+        return;
+    }
+    for await (const event of serverEvents(response)) {
+        if (event.name === "delta") {
+            reply = reply || bubble("assistant", "");
+            reply.textContent += event.data.text;
+        } else if (event.name === "message") {
+            history.push(event.data);
+            if (event.data.role === "assistant") {
+                // The final text replaces the streamed one, which drivers without streaming never sent.
+                (reply || bubble("assistant", "")).textContent = event.data.content;
+            }
+            reply = null;
+        } else if (event.name === "tool_call") {
+            bubble("tool", "Calling " + event.data.name + " " + JSON.stringify(event.data.arguments));
+        } else if (event.name === "tool_result") {
+            const div = bubble("tool", event.data.name + (event.data.is_error ? " failed:" : " returned:"));
+            showItems(div, event.data.items);
+        } else if (event.name === "error") {
+            bubble("error", event.data.message);
+        }
+    }
+}
+
+document.getElementById("chat").addEventListener("submit", async event => {
+    event.preventDefault();
+    const input = document.getElementById("input");
+    const button = document.getElementById("send");
+    const text = input.value.trim();
+    if (!text) return;
+    input.value = "";
+    button.disabled = true;
+    try {
+        await send(text);
+    } catch (e) {
+        bubble("error", String(e));
+    } finally {
+        button.disabled = false;
+    }
+});
+
+document.getElementById("clear").addEventListener("click", () => {
+    history = [];
+    log.replaceChildren();
+});
@@ -0,0 +1,88 @@
+// Points kept per chart: ten minutes at the server's sampling rate is plenty.
+const MAX_POINTS = 300;
+const series = { cpu: [], memory: [], received: [], transmitted: [] };
+
+function push(values, value) {
+    values.push(value);
+    if (values.length > MAX_POINTS) values.shift();
+}
+
+function draw(id, lines, fixedMax) {
+    const canvas = document.getElementById(id);
+    const ctx = canvas.getContext("2d");
+    ctx.clearRect(0, 0, canvas.width, canvas.height);
+    const max = fixedMax || Math.max(1, ...lines.flatMap(line => line.values));
+    for (const line of lines) {
+        ctx.strokeStyle = line.color;
+        ctx.beginPath();
+        line.values.forEach((value, i) => {
+            const x = (i / Math.max(1, MAX_POINTS - 1)) * canvas.width;
+            const y = canvas.height - (value / max) * (canvas.height - 4) - 2;
+            if (i === 0) ctx.moveTo(x, y); else ctx.lineTo(x, y);
+        });
+        ctx.stroke();
+    }
+}
+
+function cell(row, text, className) {
+    const td = row.insertCell();
+    td.textContent = text;
+    if (className) td.className = className;
+}
+
+function setStatus(text, isError) {
+    const status = document.getElementById("status");
+    status.textContent = text;
+    status.className = isError ? "error" : "";
+}
+
+const events = new EventSource("/dashboard/events");
+events.onopen = () => setStatus(`Live, updating every ${document.body.dataset.intervalSecs}s`, false);
+events.onerror = () => setStatus("Disconnected from the web client; retrying…", true);
+
+events.addEventListener("metrics", event => {
+    for (const sample of JSON.parse(event.data)) {
+        push(series.cpu, sample.cpu_usage_percent);
+        push(series.memory, 100 * sample.memory_used_kb / Math.max(1, sample.memory_total_kb));
+        push(series.received, sample.network_received_bytes_per_sec / 1024);
+        push(series.transmitted, sample.network_transmitted_bytes_per_sec / 1024);
+    }
+    draw("cpu", [{ values: series.cpu, color: "#c33" }], 100);
+    draw("memory", [{ values: series.memory, color: "#36c" }], 100);
+    draw("network", [{ values: series.received, color: "#393" }, { values: series.transmitted, color: "#c93" }]);
+    const last = values => values.length ? values[values.length - 1].toFixed(1) : "";
+    document.getElementById("cpu-latest").textContent = last(series.cpu);
+    document.getElementById("memory-latest").textContent = last(series.memory);
+    document.getElementById("network-latest").textContent = last(series.received) + " / " + last(series.transmitted);
+});
+
+events.addEventListener("connections", event => {
+    const connections = JSON.parse(event.data);
+    document.getElementById("connection-count").textContent = connections.length;
+    const body = document.getElementById("connections");
+    body.replaceChildren();
+    for (const connection of connections) {
+        const row = body.insertRow();
+        cell(row, connection.remote_address + ":" + connection.remote_port);
+        cell(row, connection.process_name || "");
+    }
+});
+
+events.addEventListener("calls", event => {
+    const body = document.getElementById("calls");
+    body.replaceChildren();
+    for (const call of JSON.parse(event.data)) {
+        const row = body.insertRow();
+        cell(row, new Date(call.timestamp).toLocaleTimeString());
+        cell(row, call.tool);
+        cell(row, call.caller || "");
+        cell(row, call.duration_ms + " ms");
+        cell(row, call.success ? "ok" : (call.error_code || "failed"), call.success ? "" : "failed");
+    }
+});
+
+events.addEventListener("error", event => {
+    if (!event.data) return;
+    const error = JSON.parse(event.data);
+    setStatus(error.source + ": " + error.message, true);
+});